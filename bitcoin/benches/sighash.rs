@@ -0,0 +1,71 @@
+//! Signing and verifying transactions with a growing number of inputs.
+//! The signature hash midstates are computed once per transaction, so the time per input stays flat.
+
+use bitcoin::amount::BitcoinAmount;
+use bitcoin::format::BitcoinFormat;
+use bitcoin::interpreter::VerificationFlags;
+use bitcoin::network::Mainnet;
+use bitcoin::private_key::BitcoinPrivateKey;
+use bitcoin::transaction::{BitcoinTransaction, BitcoinTransactionBuilder, BitcoinTransactionInputBuilder};
+use chainlib_core::{PrivateKey, Transaction};
+
+use core::str::FromStr;
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+
+type N = Mainnet;
+
+const PRIVATE_KEY: &str = "KyRv5iFPHG7iB5E4CqvMzH3WFJVhbfYK4VY7XAedd9Ys69mEsPLQ";
+const INPUTS: [u32; 3] = [10, 100, 1000];
+
+/// Returns a consolidation transaction spending `inputs` outputs of the given format to one address.
+fn consolidation(private_key: &BitcoinPrivateKey<N>, format: &BitcoinFormat, inputs: u32) -> BitcoinTransaction<N> {
+    let address = private_key.to_address(format).unwrap().to_string();
+    let mut builder = BitcoinTransactionBuilder::<N>::new();
+    for index in 0..inputs {
+        let transaction_id = hex::encode([&index.to_le_bytes()[..], &[0u8; 28]].concat());
+        builder = builder.input(
+            BitcoinTransactionInputBuilder::new(&transaction_id, index)
+                .address(&address)
+                .amount(BitcoinAmount(10_000)),
+        );
+    }
+    builder
+        .output("1Fyxts6r24DpEieygQiNnWxUdb18ANa5p7", BitcoinAmount(inputs as i64 * 9_000))
+        .build()
+        .unwrap()
+}
+
+fn sighash(c: &mut Criterion) {
+    let private_key = BitcoinPrivateKey::<N>::from_str(PRIVATE_KEY).unwrap();
+    for (name, format) in [("p2wpkh", BitcoinFormat::Bech32), ("p2tr", BitcoinFormat::P2TR)] {
+        let mut sign = c.benchmark_group(format!("sign/{}", name));
+        sign.sample_size(10);
+        for inputs in INPUTS {
+            let transaction = consolidation(&private_key, &format, inputs);
+            sign.throughput(Throughput::Elements(inputs as u64));
+            sign.bench_with_input(BenchmarkId::from_parameter(inputs), &transaction, |b, transaction| {
+                b.iter_batched(
+                    || transaction.clone(),
+                    |mut transaction| transaction.sign_with_private_key(&private_key).unwrap(),
+                    BatchSize::LargeInput,
+                )
+            });
+        }
+        sign.finish();
+
+        let mut verify = c.benchmark_group(format!("verify/{}", name));
+        verify.sample_size(10);
+        for inputs in INPUTS {
+            let mut transaction = consolidation(&private_key, &format, inputs);
+            transaction.sign_with_private_key(&private_key).unwrap();
+            verify.throughput(Throughput::Elements(inputs as u64));
+            verify.bench_with_input(BenchmarkId::from_parameter(inputs), &transaction, |b, transaction| {
+                b.iter(|| transaction.verify(VerificationFlags::STANDARD))
+            });
+        }
+        verify.finish();
+    }
+}
+
+criterion_group!(benches, sighash);
+criterion_main!(benches);
@@ -1,12 +1,15 @@
+use crate::cashaddr::CashAddress;
 use crate::format::BitcoinFormat;
 use crate::network::BitcoinNetwork;
 use crate::private_key::BitcoinPrivateKey;
 use crate::public_key::BitcoinPublicKey;
+use crate::script::Script;
+use crate::taproot::{tweak_public_key, x_only_public_key, TapTree};
 use crate::witness_program::WitnessProgram;
 use chainlib_core::no_std::*;
 use chainlib_core::{
     crypto::{checksum, hash160},
-    Address, AddressError, PrivateKey,
+    libsecp256k1, Address, AddressError, ExtendedPublicKey, PrivateKey, WatchOnlyAddress,
 };
 
 use base58::{FromBase58, ToBase58};
@@ -39,31 +42,47 @@ impl<N: BitcoinNetwork> Address for BitcoinAddress<N> {
     fn from_public_key(public_key: &Self::PublicKey, format: &Self::Format) -> Result<Self, AddressError> {
         match format {
             BitcoinFormat::P2PKH => Self::p2pkh(public_key),
-            BitcoinFormat::P2WSH => {
+            BitcoinFormat::P2SH | BitcoinFormat::P2SH_P2WSH | BitcoinFormat::P2WSH => {
                 return Err(AddressError::IncompatibleFormats(
                     String::from("non-script"),
-                    String::from("p2wsh address"),
+                    format!("{} address", format),
                 ))
             }
             BitcoinFormat::P2SH_P2WPKH => Self::p2sh_p2wpkh(public_key),
             BitcoinFormat::Bech32 => Self::bech32(public_key),
+            BitcoinFormat::P2TR => Self::p2tr(public_key),
         }
     }
 }
 
-impl<N: BitcoinNetwork> BitcoinAddress<N> {
-
-    pub fn from_hash160(hash: &[u8]) -> Result<Self, AddressError> {
+impl<N: BitcoinNetwork> WatchOnlyAddress for BitcoinAddress<N> {
+    /// Returns the address corresponding to the given secp256k1 public key (compressed).
+    /// A single key has no canonical script, so the P2SH, P2SH_P2WSH and P2WSH formats,
+    /// and deriving them from an extended public key, fail with `AddressError::ScriptRequired`.
+    fn from_secp256k1_public_key(
+        public_key: &libsecp256k1::PublicKey,
+        format: &Self::Format,
+    ) -> Result<Self, AddressError> {
+        match format {
+            BitcoinFormat::P2SH | BitcoinFormat::P2SH_P2WSH | BitcoinFormat::P2WSH => {
+                Err(AddressError::ScriptRequired(format.to_string()))
+            }
+            _ => Self::from_public_key(&BitcoinPublicKey::<N>::from_secp256k1_public_key(*public_key, true), format),
+        }
+    }
 
-        let mut address = [0u8; 25];
-        address[0] = N::to_address_prefix(&BitcoinFormat::P2PKH)[0];
-        address[1..21].copy_from_slice(hash);
+    /// Checks that the extended public key version corresponds to the network.
+    fn validate_extended_public_key(extended_public_key: &ExtendedPublicKey) -> Result<(), AddressError> {
+        N::from_extended_public_key_version(&extended_public_key.version())?;
+        Ok(())
+    }
+}
 
-        let sum = &checksum(&address[0..21])[0..4];
-        address[21..25].copy_from_slice(sum);
+impl<N: BitcoinNetwork> BitcoinAddress<N> {
 
+    pub fn from_hash160(hash: &[u8]) -> Result<Self, AddressError> {
         Ok(Self {
-            address: address.to_base58(),
+            address: Self::encode_hash(&BitcoinFormat::P2PKH, hash)?,
             format: BitcoinFormat::P2PKH,
             _network: PhantomData,
         })
@@ -76,22 +95,45 @@ impl<N: BitcoinNetwork> BitcoinAddress<N> {
             false => public_key.to_secp256k1_public_key().serialize().to_vec(),
         };
 
+        Ok(Self {
+            address: Self::encode_hash(&BitcoinFormat::P2PKH, &hash160(&public_key))?,
+            format: BitcoinFormat::P2PKH,
+            _network: PhantomData,
+        })
+    }
+
+    /// Returns a P2SH address from a given redeem script.
+    pub fn p2sh(redeem_script: &[u8]) -> Result<Self, AddressError> {
+        Ok(Self {
+            address: Self::encode_hash(&BitcoinFormat::P2SH, &hash160(redeem_script))?,
+            format: BitcoinFormat::P2SH,
+            _network: PhantomData,
+        })
+    }
+
+    /// Returns a P2SH_P2WSH address from a given witness script.
+    pub fn p2sh_p2wsh(witness_script: &[u8]) -> Result<Self, AddressError> {
+        Self::check_segwit(&BitcoinFormat::P2SH_P2WSH)?;
+        let mut redeem_script = vec![0x00, 0x20];
+        redeem_script.extend(Sha256::digest(witness_script));
+
         let mut address = [0u8; 25];
-        address[0] = N::to_address_prefix(&BitcoinFormat::P2PKH)[0];
-        address[1..21].copy_from_slice(&hash160(&public_key));
+        address[0] = N::to_address_prefix(&BitcoinFormat::P2SH_P2WSH)[0];
+        address[1..21].copy_from_slice(&hash160(&redeem_script));
 
         let sum = &checksum(&address[0..21])[0..4];
         address[21..25].copy_from_slice(sum);
 
         Ok(Self {
             address: address.to_base58(),
-            format: BitcoinFormat::P2PKH,
+            format: BitcoinFormat::P2SH_P2WSH,
             _network: PhantomData,
         })
     }
 
     // Returns a P2WSH address in Bech32 format from a given Bitcoin script
     pub fn p2wsh(original_script: &Vec<u8>) -> Result<Self, AddressError> {
+        Self::check_segwit(&BitcoinFormat::P2WSH)?;
         let script = Sha256::digest(&original_script).to_vec();
 
         // Organize as a hash
@@ -114,6 +156,7 @@ impl<N: BitcoinNetwork> BitcoinAddress<N> {
 
     /// Returns a P2SH_P2WPKH address from a given Bitcoin public key.
     pub fn p2sh_p2wpkh(public_key: &<Self as Address>::PublicKey) -> Result<Self, AddressError> {
+        Self::check_segwit(&BitcoinFormat::P2SH_P2WPKH)?;
         let mut address = [0u8; 25];
         address[0] = N::to_address_prefix(&BitcoinFormat::P2SH_P2WPKH)[0];
         address[1..21].copy_from_slice(&hash160(&Self::create_redeem_script(public_key)));
@@ -130,6 +173,7 @@ impl<N: BitcoinNetwork> BitcoinAddress<N> {
 
     /// Returns a Bech32 address from a given Bitcoin public key.
     pub fn bech32(public_key: &<Self as Address>::PublicKey) -> Result<Self, AddressError> {
+        Self::check_segwit(&BitcoinFormat::Bech32)?;
         let redeem_script = Self::create_redeem_script(public_key);
         let version = u5::try_from_u8(redeem_script[0])?;
 
@@ -146,11 +190,159 @@ impl<N: BitcoinNetwork> BitcoinAddress<N> {
         })
     }
 
+    /// Returns a P2TR address in Bech32m format from a given Bitcoin public key,
+    /// tweaked without a script tree as specified in BIP-86.
+    pub fn p2tr(public_key: &<Self as Address>::PublicKey) -> Result<Self, AddressError> {
+        let (internal_key, _) = x_only_public_key(&public_key.to_secp256k1_public_key());
+        Self::p2tr_with_script_tree(&internal_key, None)
+    }
+
+    /// Returns a P2TR address in Bech32m format from a given x-only internal key,
+    /// committing to the script tree if any.
+    pub fn p2tr_with_script_tree(internal_key: &[u8; 32], script_tree: Option<&TapTree>) -> Result<Self, AddressError> {
+        Self::check_segwit(&BitcoinFormat::P2TR)?;
+        let merkle_root = script_tree.map(|script_tree| script_tree.merkle_root());
+        let (output_key, _) = tweak_public_key(internal_key, merkle_root.as_ref())?;
+
+        let mut data = vec![u5::try_from_u8(1)?];
+        data.extend_from_slice(&output_key.to_base32());
+
+        let prefix = String::from_utf8(N::to_address_prefix(&BitcoinFormat::P2TR))?;
+        let bech32m = bech32::encode(&prefix, data, Variant::Bech32m)?;
+        Ok(Self {
+            address: bech32m,
+            format: BitcoinFormat::P2TR,
+            _network: PhantomData,
+        })
+    }
+
+    /// Returns the P2SH, P2SH_P2WSH or P2WSH address committing to the given script.
+    pub fn from_script(script: &[u8], format: &BitcoinFormat) -> Result<Self, AddressError> {
+        match format {
+            BitcoinFormat::P2SH => Self::p2sh(script),
+            BitcoinFormat::P2SH_P2WSH => Self::p2sh_p2wsh(script),
+            BitcoinFormat::P2WSH => Self::p2wsh(&script.to_vec()),
+            _ => Err(AddressError::IncompatibleFormats(String::from("script"), format!("{} address", format))),
+        }
+    }
+
+    /// Returns the address in the format of the script it commits to, if it is the given script.
+    /// Parsed script hash and 32-byte SegWit v0 addresses default to P2SH_P2WPKH and Bech32,
+    /// as the address alone does not reveal the script.
+    pub fn with_script(self, script: &[u8]) -> Result<Self, AddressError> {
+        let formats = match self.format {
+            // A P2SH_P2WPKH redeem script is a version 0 program of a 20-byte public key hash
+            BitcoinFormat::P2SH_P2WPKH if script.len() == 22 && script[..2] == [0x00, 0x14] => vec![],
+            BitcoinFormat::P2SH_P2WPKH => vec![BitcoinFormat::P2SH, BitcoinFormat::P2SH_P2WSH],
+            BitcoinFormat::Bech32 => vec![BitcoinFormat::P2WSH],
+            _ => vec![],
+        };
+        for format in formats {
+            let address = Self::from_script(script, &format)?;
+            if address.address == self.address.to_lowercase() || address.address == self.address {
+                return Ok(address);
+            }
+        }
+        Ok(self)
+    }
+
+    /// Returns the address paid to by the given script public key, reversing `create_script_pub_key`.
+    /// As with parsed addresses, script hash outputs default to P2SH_P2WPKH and SegWit v0 outputs to Bech32,
+    /// until `with_script` resolves them.
+    pub fn from_script_pub_key(script_pub_key: &[u8]) -> Result<Self, AddressError> {
+        let script = Script::from(script_pub_key);
+        let address = if script.is_p2pkh() {
+            Self::from_hash160(&script_pub_key[3..23])?.address
+        } else if script.is_p2sh() {
+            Self::encode_hash(&BitcoinFormat::P2SH, &script_pub_key[2..22])?
+        } else if let Some(program) = script.witness_program() {
+            Self::check_segwit(&BitcoinFormat::Bech32)?;
+            let mut data = vec![u5::try_from_u8(program.version)?];
+            data.extend_from_slice(&program.program.to_base32());
+
+            // Version 0 programs are encoded with Bech32 and later versions with Bech32m (BIP-350)
+            let variant = match program.version {
+                0 => Variant::Bech32,
+                _ => Variant::Bech32m,
+            };
+            let prefix = String::from_utf8(N::to_address_prefix(&BitcoinFormat::Bech32))?;
+            bech32::encode(&prefix, data, variant)?
+        } else {
+            return Err(AddressError::Message(format!("script public key {} has no address", script)));
+        };
+        Self::from_str(&address)
+    }
+
     /// Returns the format of the Bitcoin address.
     pub fn format(&self) -> BitcoinFormat {
         self.format.clone()
     }
 
+    /// Returns the public key hash of a P2PKH address or the script hash of a P2SH address,
+    /// in either the Base58 or CashAddr encoding.
+    pub fn to_hash160(&self) -> Result<Vec<u8>, AddressError> {
+        match self.format {
+            BitcoinFormat::P2PKH | BitcoinFormat::P2SH | BitcoinFormat::P2SH_P2WPKH | BitcoinFormat::P2SH_P2WSH => {}
+            _ => return Err(AddressError::IncompatibleFormats(self.format.to_string(), String::from("hash160"))),
+        }
+        match N::cash_address_prefix() {
+            Some(prefix) if self.address.starts_with(prefix) => Ok(CashAddress::decode(&self.address, prefix)?.hash),
+            _ => Ok(self.address.from_base58()?[1..21].to_vec()),
+        }
+    }
+
+    /// Returns the P2PKH or P2SH address in the Base58 encoding, e.g. the legacy encoding of a CashAddr address.
+    pub fn to_legacy_address(&self) -> Result<Self, AddressError> {
+        let hash = self.to_hash160()?;
+        let mut address = [0u8; 25];
+        address[0] = N::to_address_prefix(&self.format)[0];
+        address[1..21].copy_from_slice(&hash);
+
+        let sum = &checksum(&address[0..21])[0..4];
+        address[21..25].copy_from_slice(sum);
+
+        Ok(Self {
+            address: address.to_base58(),
+            format: self.format.clone(),
+            _network: PhantomData,
+        })
+    }
+
+    /// Returns the P2PKH or P2SH address in the CashAddr encoding of the network, e.g. of a legacy Bitcoin Cash address.
+    pub fn to_cash_address(&self) -> Result<Self, AddressError> {
+        let prefix = N::cash_address_prefix()
+            .ok_or_else(|| AddressError::IncompatibleFormats(String::from("CashAddr"), format!("{} address", N::NAME)))?;
+        Ok(Self {
+            address: CashAddress::new(prefix, self.format.clone(), &self.to_hash160()?)?.to_string(),
+            format: self.format.clone(),
+            _network: PhantomData,
+        })
+    }
+
+    /// Returns the string of a P2PKH address of the public key hash or P2SH address of the script hash,
+    /// in the CashAddr encoding on networks using it and otherwise in Base58.
+    fn encode_hash(format: &BitcoinFormat, hash: &[u8]) -> Result<String, AddressError> {
+        if let Some(prefix) = N::cash_address_prefix() {
+            return Ok(CashAddress::new(prefix, format.clone(), hash)?.to_string());
+        }
+
+        let mut address = [0u8; 25];
+        address[0] = N::to_address_prefix(format)[0];
+        address[1..21].copy_from_slice(hash);
+
+        let sum = &checksum(&address[0..21])[0..4];
+        address[21..25].copy_from_slice(sum);
+        Ok(address.to_base58())
+    }
+
+    /// Returns an error if the network has not activated SegWit, and so has no addresses of the format.
+    fn check_segwit(format: &BitcoinFormat) -> Result<(), AddressError> {
+        match N::is_segwit() {
+            true => Ok(()),
+            false => Err(AddressError::IncompatibleFormats(format.to_string(), format!("{} address", N::NAME))),
+        }
+    }
+
     /// Returns a redeem script for a given Bitcoin public key.
     fn create_redeem_script(public_key: &<Self as Address>::PublicKey) -> [u8; 22] {
         let mut redeem = [0u8; 22];
@@ -176,35 +368,65 @@ impl<N: BitcoinNetwork> FromStr for BitcoinAddress<N> {
             return Err(AddressError::InvalidCharacterLength(address.len()));
         }
 
-        let prefix = &address.to_lowercase()[0..2];
+        let lowercase = address.to_lowercase();
+        let prefix = &lowercase[0..2];
 
-        if let Ok(format) = BitcoinFormat::from_address_prefix(prefix.as_bytes()) {
-            if BitcoinFormat::Bech32 == format {
-                //let bech32 = Bech32::from_str(&address)?;
-                let (hrp, data, variant) = bech32::decode(&address)?;
-                if data.is_empty() {
-                    return Err(AddressError::InvalidAddress(address.to_owned()));
-                }
-
-                // let data = bech32.data();
-                let version = data[0].to_u8();
-                let mut program = Vec::from_base32(&data[1..])?;
-
-                let mut data = vec![version, program.len() as u8];
-                data.append(&mut program);
-
-                // Check that the witness program is valid.
-                let _ = WitnessProgram::new(data.as_slice())?;
+        // CashAddr addresses have a prefix or start with their type, unlike Base58 ones of the same network
+        if let Some(cash_address_prefix) = N::cash_address_prefix() {
+            if lowercase.contains(':') || lowercase.starts_with('q') || lowercase.starts_with('p') {
+                let cash_address = CashAddress::decode(address, cash_address_prefix)?;
                 // Check that the address prefix corresponds to the correct network.
-                let _ = N::from_address_prefix(prefix.as_bytes())?;
+                let _ = N::from_address_prefix(cash_address.prefix.as_bytes())?;
+                if cash_address.hash.len() != 20 {
+                    return Err(AddressError::InvalidByteLength(cash_address.hash.len()));
+                }
 
+                // The address is kept in its canonical form, lowercase and with its prefix
                 return Ok(Self {
-                    address: address.to_owned(),
-                    format: BitcoinFormat::Bech32,
+                    address: cash_address.to_string(),
+                    format: cash_address.format,
                     _network: PhantomData,
                 });
             }
         }
+        // The human-readable part of a Bech32 address precedes its last separator
+        let hrp = &lowercase[..lowercase.rfind('1').unwrap_or(0)];
+
+        let is_bech32 = (N::is_segwit() && hrp.as_bytes() == N::to_address_prefix(&BitcoinFormat::Bech32))
+            || matches!(BitcoinFormat::from_address_prefix(prefix.as_bytes()), Ok(BitcoinFormat::Bech32));
+        if is_bech32 {
+            //let bech32 = Bech32::from_str(&address)?;
+            let (hrp, data, variant) = bech32::decode(&address)?;
+            if data.is_empty() {
+                return Err(AddressError::InvalidAddress(address.to_owned()));
+            }
+
+            // let data = bech32.data();
+            let version = data[0].to_u8();
+            let mut program = Vec::from_base32(&data[1..])?;
+
+            let mut data = vec![version, program.len() as u8];
+            data.append(&mut program);
+
+            // Check that the witness program is valid.
+            let program = WitnessProgram::new(data.as_slice())?;
+            // Check that the address prefix corresponds to the correct network.
+            let _ = N::from_address_prefix(hrp.as_bytes())?;
+
+            // Version 0 programs are encoded with Bech32 and later versions with Bech32m (BIP-350)
+            let format = match (program.version, variant) {
+                (0, Variant::Bech32) => BitcoinFormat::Bech32,
+                (1, Variant::Bech32m) if program.program.len() == 32 => BitcoinFormat::P2TR,
+                (1..=16, Variant::Bech32m) => BitcoinFormat::Bech32,
+                _ => return Err(AddressError::InvalidAddress(address.to_owned())),
+            };
+
+            return Ok(Self {
+                address: address.to_owned(),
+                format,
+                _network: PhantomData,
+            });
+        }
 
         let data = address.from_base58()?;
         if data.len() != 25 {
@@ -213,7 +435,8 @@ impl<N: BitcoinNetwork> FromStr for BitcoinAddress<N> {
 
         // Check that the address prefix corresponds to the correct network.
         let _ = N::from_address_prefix(&data[0..2])?;
-        let format = BitcoinFormat::from_address_prefix(&data[0..2])?;
+        let format = N::to_base58_format(data[0])
+            .ok_or_else(|| AddressError::InvalidPrefix(hex::encode(&data[0..1])))?;
 
         Ok(Self {
             address: address.into(),
@@ -848,6 +1071,115 @@ mod tests {
         }
     }
 
+    mod watch_only_mainnet {
+        use super::*;
+        use chainlib_core::DerivationPath;
+
+        type N = Mainnet;
+
+        // https://github.com/bitcoin/bips/blob/master/bip-0084.mediawiki#test-vectors
+        const ZPUB: &str = "zpub6rFR7y4Q2AijBEqTUquhVz398htDFrtymD9xYYfG1m4wAcvPhXNfE3EfH1r1ADqtfSdVCToUG868RvUUkgDKf31mGDtKsAYz2oz2AGutZYs";
+
+        const ADDRESSES: [(&str, &str); 3] = [
+            ("m/0/0", "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu"),
+            ("m/0/1", "bc1qnjg0jd8228aq7egyzacy8cys3knf9xvrerkf9g"),
+            ("m/1/0", "bc1q8c6fshw2dlwun7ekn9qwf37cu2rn755upcp6el"),
+        ];
+
+        #[test]
+        fn from_extended_public_key() {
+            let extended_public_key = ExtendedPublicKey::from_str(ZPUB).unwrap();
+            let format = BitcoinFormat::from_extended_public_key(&extended_public_key).unwrap();
+            assert_eq!(BitcoinFormat::Bech32, format);
+
+            ADDRESSES.iter().for_each(|(path, expected_address)| {
+                let path = DerivationPath::from_str(path).unwrap();
+                let address = BitcoinAddress::<N>::from_extended_public_key(&extended_public_key, &path, &format).unwrap();
+                test_to_str(expected_address, &address);
+            });
+        }
+
+        #[test]
+        fn derive_addresses() {
+            let extended_public_key = ExtendedPublicKey::from_str(ZPUB).unwrap();
+            let path = DerivationPath::from_str("m/0").unwrap();
+            let addresses =
+                BitcoinAddress::<N>::derive_addresses(&extended_public_key, &path, 0..20, &BitcoinFormat::Bech32).unwrap();
+            assert_eq!(20, addresses.len());
+            test_to_str(ADDRESSES[0].1, &addresses[0]);
+            test_to_str(ADDRESSES[1].1, &addresses[1]);
+
+            // Every single key format is derivable from the same key
+            for format in [BitcoinFormat::P2PKH, BitcoinFormat::P2SH_P2WPKH, BitcoinFormat::P2TR] {
+                let addresses = BitcoinAddress::<N>::derive_addresses(&extended_public_key, &path, 0..3, &format).unwrap();
+                addresses.iter().for_each(|address| assert_eq!(format, address.format()));
+            }
+
+            // Script formats have no canonical script for a single key
+            for format in [BitcoinFormat::P2SH, BitcoinFormat::P2SH_P2WSH, BitcoinFormat::P2WSH] {
+                assert!(matches!(
+                    BitcoinAddress::<N>::derive_addresses(&extended_public_key, &path, 0..3, &format),
+                    Err(AddressError::ScriptRequired(_))
+                ));
+            }
+        }
+
+        #[test]
+        fn invalid_extended_public_key() {
+            let extended_public_key = ExtendedPublicKey::from_str(ZPUB).unwrap();
+
+            // Hardened derivation requires the private key
+            let path = DerivationPath::from_str("m/0'/0").unwrap();
+            assert!(BitcoinAddress::<N>::from_extended_public_key(&extended_public_key, &path, &BitcoinFormat::Bech32).is_err());
+
+            // A mainnet extended public key cannot derive testnet addresses
+            let path = DerivationPath::from_str("m/0/0").unwrap();
+            assert!(BitcoinAddress::<Testnet>::from_extended_public_key(&extended_public_key, &path, &BitcoinFormat::Bech32).is_err());
+        }
+    }
+
+    mod p2sh_mainnet {
+        use super::*;
+
+        type N = Mainnet;
+
+        // A 2-of-3 multisig script of BIP-67 and its P2SH, P2SH_P2WSH and P2WSH addresses
+        const SCRIPT: &str = "522102632b12f4ac5b1d1b72b2a3b508c19172de44f6f46bcee50ba33f3f9291e47ed021027735a29bae7780a9755fae7a1c4374c656ac6a69ea9f3697fda61bb99a4f3e772102e2cc6bd5f45edd43bebe7cb9b675f0ce9ed3efe613b177588290ad188d11b40453ae";
+        const ADDRESSES: [(BitcoinFormat, &str); 3] = [
+            (BitcoinFormat::P2SH, "3CKHTjBKxCARLzwABMu9yD85kvtm7WnMfH"),
+            (BitcoinFormat::P2SH_P2WSH, "31iXMTVFX7qKnPnGVx2ZmJYWuNy3BiCNHS"),
+            (BitcoinFormat::P2WSH, "bc1qud6dmdcc27eg8s5hsy6a075gs49w65l6xtc4cplp6m2d4ggh43wqew2vqs"),
+        ];
+
+        #[test]
+        fn from_script() {
+            let script = hex::decode(SCRIPT).unwrap();
+            ADDRESSES.iter().for_each(|(format, expected_address)| {
+                let address = BitcoinAddress::<N>::from_script(&script, format).unwrap();
+                assert_eq!(*expected_address, address.to_string());
+                assert_eq!(*format, address.format());
+            });
+            assert!(BitcoinAddress::<N>::from_script(&script, &BitcoinFormat::P2PKH).is_err());
+        }
+
+        #[test]
+        fn with_script() {
+            // The script resolves the format of parsed script hash and witness script hash addresses
+            let script = hex::decode(SCRIPT).unwrap();
+            ADDRESSES.iter().for_each(|(format, expected_address)| {
+                let address = BitcoinAddress::<N>::from_str(expected_address).unwrap();
+                assert_ne!(*format, address.format());
+                assert_eq!(*format, address.with_script(&script).unwrap().format());
+            });
+
+            let address = BitcoinAddress::<N>::from_str("3CKHTjBKxCARLzwABMu9yD85kvtm7WnMfH").unwrap();
+            let other_script = [&script[..script.len() - 2], &[0x52, 0xae]].concat();
+            assert_eq!(BitcoinFormat::P2SH_P2WPKH, address.with_script(&other_script).unwrap().format());
+            let address = BitcoinAddress::<N>::from_str(&ADDRESSES[2].1.to_uppercase()).unwrap();
+            assert_eq!(BitcoinFormat::P2WSH, address.with_script(&script).unwrap().format());
+        }
+    }
+
     mod p2wsh_mainnet {
         use super::*;
 
@@ -896,4 +1228,602 @@ mod tests {
             });
         }
     }
+
+    mod p2tr_mainnet {
+        use super::*;
+
+        type N = Mainnet;
+
+        // https://github.com/bitcoin/bips/blob/master/bip-0086.mediawiki#test-vectors
+        const KEYPAIRS: [(&str, &str); 1] = [(
+            "KyRv5iFPHG7iB5E4CqvMzH3WFJVhbfYK4VY7XAedd9Ys69mEsPLQ",
+            "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr",
+        )];
+
+        // https://github.com/bitcoin/bips/blob/master/bip-0341/wallet-test-vectors.json
+        const ADDRESSES: [&str; 3] = [
+            "bc1p2wsldez5mud2yam29q22wgfh9439spgduvct83k3pm50fcxa5dps59h4z5",
+            "bc1pz37fc4cn9ah8anwm4xqqhvxygjf9rjf2resrw8h8w4tmvcs0863sa2e586",
+            "bc1punvppl2stp38f7kwv2u2spltjuvuaayuqsthe34hd2dyy5w4g58qqfuag5",
+        ];
+
+        const INVALID: [&str; 3] = [
+            "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqr9a0ap", // Bech32 checksum for witness version 1
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kemeawh",                     // Bech32m checksum for witness version 0
+            "tb1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqp3mvzv", // invalid hrp
+        ];
+
+        #[test]
+        fn from_private_key() {
+            KEYPAIRS.iter().for_each(|(private_key, address)| {
+                let private_key = BitcoinPrivateKey::<N>::from_str(private_key).unwrap();
+                test_from_private_key(address, &private_key, &BitcoinFormat::P2TR);
+            });
+        }
+
+        #[test]
+        fn from_str() {
+            KEYPAIRS.iter().map(|(_, address)| address).chain(ADDRESSES.iter()).for_each(|address| {
+                test_from_str::<N>(address, &BitcoinFormat::P2TR);
+            });
+        }
+
+        #[test]
+        fn p2tr_with_script_tree() {
+            // https://github.com/bitcoin/bips/blob/master/bip-0341/wallet-test-vectors.json
+            let mut internal_key = [0u8; 32];
+            internal_key.copy_from_slice(
+                &hex::decode("f9f400803e683727b14f463836e1e78e1c64417638aa066919291a225f0e8dd8").unwrap(),
+            );
+            let script_tree = TapTree::branch(
+                TapTree::leaf(hex::decode("2044b178d64c32c4a05cc4f4d1407268f764c940d20ce97abfd44db5c3592b72fdac").unwrap()),
+                TapTree::leaf(hex::decode("07546170726f6f74").unwrap()),
+            );
+            let address = BitcoinAddress::<N>::p2tr_with_script_tree(&internal_key, Some(&script_tree)).unwrap();
+            assert_eq!("bc1pwl3s54fzmk0cjnpl3w9af39je7pv5ldg504x5guk2hpecpg2kgsqaqstjq", address.to_string());
+            assert_eq!(BitcoinFormat::P2TR, address.format());
+
+            internal_key.copy_from_slice(
+                &hex::decode("d6889cb081036e0faefa3a35157ad71086b123b2b144b649798b494c300a961d").unwrap(),
+            );
+            let address = BitcoinAddress::<N>::p2tr_with_script_tree(&internal_key, None).unwrap();
+            assert_eq!(ADDRESSES[0], address.to_string());
+        }
+
+        #[test]
+        fn from_invalid_address() {
+            INVALID.iter().for_each(|invalid_p2tr| {
+                assert!(BitcoinAddress::<N>::from_str(invalid_p2tr).is_err());
+            });
+        }
+    }
+
+    mod p2tr_testnet {
+        use super::*;
+
+        type N = Testnet;
+
+        // The BIP-86 internal key of m/86'/0'/0'/0/0, either parity yields the same output key
+        const PUBLIC_KEYS: [(&str, &str); 2] = [
+            (
+                "02cc8a4bc64d897bddc5fbc2f670f7a8ba0b386779106cf1223c6fc5d7cd6fc115",
+                "tb1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqp3mvzv",
+            ),
+            (
+                "03cc8a4bc64d897bddc5fbc2f670f7a8ba0b386779106cf1223c6fc5d7cd6fc115",
+                "tb1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqp3mvzv",
+            ),
+        ];
+
+        #[test]
+        fn from_public_key() {
+            PUBLIC_KEYS.iter().for_each(|(public_key, address)| {
+                let public_key = BitcoinPublicKey::<N>::from_str(public_key).unwrap();
+                test_from_public_key(address, &public_key, &BitcoinFormat::P2TR);
+            });
+        }
+
+        #[test]
+        fn from_str() {
+            PUBLIC_KEYS.iter().for_each(|(_, address)| {
+                test_from_str::<N>(address, &BitcoinFormat::P2TR);
+            });
+        }
+    }
+
+    mod litecoin_mainnet {
+        use super::*;
+
+        type N = Litecoin;
+
+        // (WIF, P2PKH, P2SH_P2WPKH, Bech32)
+        const KEYPAIRS: [(&str, &str, &str, &str); 3] = [
+            (
+                "T45TTV58LamkyYq34BzWjvgUi2zQxg4MNCB2WuBVFbSg5a7Dqx4L",
+                "LcLittPgg5DXY34hp62SnWa5KtEcvWHUsr",
+                "MNBLQ7xsucsTiEL6g7G5BqzMyNi8KB1PHW",
+                "ltc1qh0q7g23e6pdye3sh2ttfvwmld8gfhvnml4ucqx",
+            ),
+            (
+                "T3TccUZx4EXBZaHnFiP9eTr8igDEZoqSjNvbA56Z8vV74oyAcjTK",
+                "Lf2SXRzFwowWvG4TZ3Wcir3hpp1D6zsqGn",
+                "MKMsH8hfxgia8KFstMfC9mHFbPu7UgrWfo",
+                "ltc1qmy63mjadtw8nhzl69ukdepwzsyvv4yexsu9lwa",
+            ),
+            (
+                "T4UgJBpcJnhyPniVbrGaaHvuinnferrowEHo7GvNATWqCuaibJK6",
+                "LUwdk5bJAk5iVZYfNHweLCFTp75SnfE5r8",
+                "MMSUCM28bRwqhpmHVAookHkokTbCHe9uCT",
+                "ltc1qd2djfgmlfkps4dv7lgalajer5lnjyg5ylaj55v",
+            ),
+        ];
+
+        #[test]
+        fn from_private_key() {
+            KEYPAIRS.iter().for_each(|(private_key, p2pkh, p2sh_p2wpkh, bech32)| {
+                let private_key = BitcoinPrivateKey::<N>::from_str(private_key).unwrap();
+                test_from_private_key(p2pkh, &private_key, &BitcoinFormat::P2PKH);
+                test_from_private_key(p2sh_p2wpkh, &private_key, &BitcoinFormat::P2SH_P2WPKH);
+                test_from_private_key(bech32, &private_key, &BitcoinFormat::Bech32);
+            });
+        }
+
+        #[test]
+        fn from_str() {
+            KEYPAIRS.iter().for_each(|(_, p2pkh, p2sh_p2wpkh, bech32)| {
+                test_from_str::<N>(p2pkh, &BitcoinFormat::P2PKH);
+                test_from_str::<N>(p2sh_p2wpkh, &BitcoinFormat::P2SH_P2WPKH);
+                test_from_str::<N>(bech32, &BitcoinFormat::Bech32);
+            });
+        }
+
+        #[test]
+        fn legacy_script_hash() {
+            // The script hash prefix shared with Bitcoin is still accepted
+            test_from_str::<N>("3FyC6EYuxW22uj4CaEGjNCjxeg7gHyFeVv", &BitcoinFormat::P2SH_P2WPKH);
+        }
+
+        #[test]
+        fn test_invalid() {
+            for address in [
+                "1J7mdg5rbQyUHENYdx39WVWK7fsLpEoXZy",
+                "bc1qh0q7g23e6pdye3sh2ttfvwmld8gfhvnmmfxuck",
+                "tltc1qh0q7g23e6pdye3sh2ttfvwmld8gfhvnmg8l3nv",
+                "DNFsAw2VtpskpEZ9NY2i4FfuzobeBaxqMm",
+            ] {
+                assert!(BitcoinAddress::<N>::from_str(address).is_err());
+            }
+            assert!(BitcoinAddress::<Mainnet>::from_str("ltc1qh0q7g23e6pdye3sh2ttfvwmld8gfhvnml4ucqx").is_err());
+            assert!(BitcoinAddress::<Mainnet>::from_str("MNBLQ7xsucsTiEL6g7G5BqzMyNi8KB1PHW").is_err());
+            assert!(BitcoinPrivateKey::<N>::from_str("L1uyy5qTuGrVXrmrsvHWHgVzW9kKdrp27wBC7Vs6nZDTF2BRUVwy").is_err());
+        }
+    }
+
+    mod litecoin_testnet {
+        use super::*;
+
+        type N = LitecoinTestnet;
+
+        // (WIF, P2PKH, P2SH_P2WPKH, Bech32)
+        const KEYPAIRS: [(&str, &str, &str, &str); 3] = [
+            (
+                "cNcBUemoNGVRN9fRtxrmtteAPQeWZ399d2REmX1TBjvWpRfNMy91",
+                "mxdivjAqQSQj4LrAMX1XLQidyfU3pCWeS7",
+                "QatAGzMBb4aUFhSnsTvd4rAf1Qmftgx4SX",
+                "tltc1qh0q7g23e6pdye3sh2ttfvwmld8gfhvnmg8l3nv",
+            ),
+            (
+                "cMzLdeGd5vEqxB8B6VFQoRopQ3sLAAvEzDAoQgvX54xwofSWj1fx",
+                "n1KSZGmQgB8iSZqv6UVhGkCGUbEdw8Lm3Q",
+                "QY4hA15ye8RafnNa5iKk2mTYdRxfEK3CQX",
+                "tltc1qmy63mjadtw8nhzl69ukdepwzsyvv4yex8wxkah",
+            ),
+            (
+                "cP1QKMXHLURdnPYtSd8qjFtbQASmFDwcC4Y1MtkL6bzfwm7gkog3",
+                "mqEdmvNSu7Gv1sL7uivit6Q2TtJsZtiKEe",
+                "Qa9J5DQSGserFHsygXUMdHw6nVejzRjKGy",
+                "tltc1qd2djfgmlfkps4dv7lgalajer5lnjyg5yg03a8x",
+            ),
+        ];
+
+        #[test]
+        fn from_private_key() {
+            KEYPAIRS.iter().for_each(|(private_key, p2pkh, p2sh_p2wpkh, bech32)| {
+                let private_key = BitcoinPrivateKey::<N>::from_str(private_key).unwrap();
+                test_from_private_key(p2pkh, &private_key, &BitcoinFormat::P2PKH);
+                test_from_private_key(p2sh_p2wpkh, &private_key, &BitcoinFormat::P2SH_P2WPKH);
+                test_from_private_key(bech32, &private_key, &BitcoinFormat::Bech32);
+            });
+        }
+
+        #[test]
+        fn from_str() {
+            KEYPAIRS.iter().for_each(|(_, p2pkh, p2sh_p2wpkh, bech32)| {
+                test_from_str::<N>(p2pkh, &BitcoinFormat::P2PKH);
+                test_from_str::<N>(p2sh_p2wpkh, &BitcoinFormat::P2SH_P2WPKH);
+                test_from_str::<N>(bech32, &BitcoinFormat::Bech32);
+            });
+            test_from_str::<N>("2N7XQ9yUwZxXP7WgkFMtbz9jDs2Kr2njYRy", &BitcoinFormat::P2SH_P2WPKH);
+        }
+    }
+
+    mod dogecoin {
+        use super::*;
+
+        // (WIF, P2PKH)
+        const MAINNET_KEYPAIRS: [(&str, &str); 3] = [
+            ("QPe7AaawBpHHSEbD6ptSQnyiEDNfw9BGSExgSiwnQyss1cbfeDc9", "DNFsAw2VtpskpEZ9NY2i4FfuzobeBaxqMm"),
+            ("QP2GKa5kuU2i2G3xJMH5KL9NErbVYGxMoRiF5trrJJvHzrJ2Ebp7", "DQwaoUd5AZbkCTYu7VWszb9YVjNEFtT2DQ"),
+            ("QQ3L1HLRA2DVrUUfeVAWFAE9EyAvdKyj1H5T36gfKqx28wu74iwb", "DErn28E7PVjwmm36vjwubwMJV2STv44F3x"),
+        ];
+        const TESTNET_KEYPAIRS: [(&str, &str); 3] = [
+            ("cfmScyVbiaKhKBgy17eZVEq8tH3pDsgbwufVtRheAhRwP63J3FCL", "nmJvtwmQpoLUhD8LQMgAJfGDEfyw8darVD"),
+            ("cf9bmxzRSE57uD9iCe3CPmzntvGdq1ThK6R4Xbci42UNNKqVnZZH", "nozeXVMz6Y4U5S869KALEzjqjbkXHJD3nu"),
+            ("cgAfTgF5gnFujRaRYmvdKc5Zu2r4v4V4WwnGUoSX5ZW6WRVwf9YR", "nduqk8y2KUCfejcHxZbMrLwbitpkz9dFTG"),
+        ];
+
+        fn test_keypairs<N: BitcoinNetwork>(keypairs: &[(&str, &str)]) {
+            keypairs.iter().for_each(|(private_key, address)| {
+                let private_key = BitcoinPrivateKey::<N>::from_str(private_key).unwrap();
+                test_from_private_key(address, &private_key, &BitcoinFormat::P2PKH);
+                test_from_str::<N>(address, &BitcoinFormat::P2PKH);
+            });
+        }
+
+        #[test]
+        fn from_private_key() {
+            test_keypairs::<Dogecoin>(&MAINNET_KEYPAIRS);
+            test_keypairs::<DogecoinTestnet>(&TESTNET_KEYPAIRS);
+        }
+
+        #[test]
+        fn script_hash() {
+            let address = BitcoinAddress::<Dogecoin>::p2sh(&[0x51]).unwrap();
+            assert!(address.to_string().starts_with('9') || address.to_string().starts_with('A'));
+            test_from_str::<Dogecoin>(&address.to_string(), &BitcoinFormat::P2SH);
+        }
+
+        #[test]
+        fn test_invalid() {
+            // Dogecoin has no SegWit
+            let private_key = BitcoinPrivateKey::<Dogecoin>::from_str(MAINNET_KEYPAIRS[0].0).unwrap();
+            for format in [BitcoinFormat::P2SH_P2WPKH, BitcoinFormat::Bech32, BitcoinFormat::P2TR, BitcoinFormat::P2WSH] {
+                assert!(BitcoinAddress::from_private_key(&private_key, &format).is_err());
+            }
+            assert!(BitcoinAddress::<Dogecoin>::from_str("bc1qh0q7g23e6pdye3sh2ttfvwmld8gfhvnmmfxuck").is_err());
+            assert!(BitcoinAddress::<Dogecoin>::from_str("LcLittPgg5DXY34hp62SnWa5KtEcvWHUsr").is_err());
+            assert!(BitcoinAddress::<Dogecoin>::from_script_pub_key(&hex::decode("0014751e76e8199196d454941c45d1b3a323f1433bd6").unwrap()).is_err());
+        }
+    }
+
+    mod dash {
+        use super::*;
+
+        // (WIF, P2PKH)
+        const MAINNET_KEYPAIRS: [(&str, &str); 3] = [
+            ("XCK7U1AKEtRcG3CYYK3X2oL7gCcgLPehvZcgBct8zzYbxqgRdBrB", "XsocTvjkZ8C4SAy8VqMNN2C6x1T2qqbjCG"),
+            ("XBhGczf8xYB2r4fHjqS9wLVmgqqVwXRoHkNEpnoCtKb2x5RsXrCP", "XvVL6ULKprv3pPxtEnqYJMfjSwDd3uJmFy"),
+            ("XCiLJhuoD6MpgH615yKasAaYgxQw2aTAVbjSmzd1urcm6B4oRVR3", "XkQXK7wN3o4FPhT643GZuhsVSEHreBrkeg"),
+        ];
+        const TESTNET_KEYPAIRS: [(&str, &str); 3] = [
+            ("cNcBUemoNGVRN9fRtxrmtteAPQeWZ399d2REmX1TBjvWpRfNMy91", "ydSDUspBzfr8mutg4gfmQ3cTEHwQKyyH32"),
+            ("cMzLdeGd5vEqxB8B6VFQoRopQ3sLAAvEzDAoQgvX54xwofSWj1fx", "yg7w7RQmGQa8A8tRoe9wLP65jDhzXLkAW7"),
+            ("cP1QKMXHLURdnPYtSd8qjFtbQASmFDwcC4Y1MtkL6bzfwm7gkog3", "yW38L51oVLiKjSNdctaxwjHqiWnE9e7icT"),
+        ];
+
+        fn test_keypairs<N: BitcoinNetwork>(keypairs: &[(&str, &str)]) {
+            keypairs.iter().for_each(|(private_key, address)| {
+                let private_key = BitcoinPrivateKey::<N>::from_str(private_key).unwrap();
+                test_from_private_key(address, &private_key, &BitcoinFormat::P2PKH);
+                test_from_str::<N>(address, &BitcoinFormat::P2PKH);
+            });
+        }
+
+        #[test]
+        fn from_private_key() {
+            test_keypairs::<Dash>(&MAINNET_KEYPAIRS);
+            test_keypairs::<DashTestnet>(&TESTNET_KEYPAIRS);
+        }
+
+        #[test]
+        fn script_hash() {
+            let address = BitcoinAddress::<Dash>::p2sh(&[0x51]).unwrap();
+            assert!(address.to_string().starts_with('7'));
+            test_from_str::<Dash>(&address.to_string(), &BitcoinFormat::P2SH);
+        }
+
+        #[test]
+        fn test_invalid() {
+            let private_key = BitcoinPrivateKey::<Dash>::from_str(MAINNET_KEYPAIRS[0].0).unwrap();
+            assert!(BitcoinAddress::from_private_key(&private_key, &BitcoinFormat::Bech32).is_err());
+            assert!(BitcoinAddress::<Dash>::from_str("DNFsAw2VtpskpEZ9NY2i4FfuzobeBaxqMm").is_err());
+            assert!(BitcoinAddress::<DashTestnet>::from_str("XsocTvjkZ8C4SAy8VqMNN2C6x1T2qqbjCG").is_err());
+        }
+    }
+
+    mod regtest {
+        use super::*;
+
+        type N = Regtest;
+
+        const PRIVATE_KEY: &str = "cPnuYdFEiKoyLWhKbFjVMbYZsXo7G7e18Xgadb798GCsLtnQbixp";
+        // (format, address)
+        const ADDRESSES: [(BitcoinFormat, &str); 3] = [
+            (BitcoinFormat::P2PKH, "n3PFj7J96N3uTKZfcRYHggxfd1eAKgDLjX"),
+            (BitcoinFormat::P2SH_P2WPKH, "2N1Mdnn2XCeoyxkmVYWpKhCLfamgeXJmrYV"),
+            (BitcoinFormat::Bech32, "bcrt1qalwlmdxd2ggue4290ekzxl9tetg56neewvcrh5"),
+        ];
+
+        #[test]
+        fn from_private_key() {
+            let private_key = BitcoinPrivateKey::<N>::from_str(PRIVATE_KEY).unwrap();
+            ADDRESSES.iter().for_each(|(format, address)| {
+                test_from_private_key(address, &private_key, format);
+            });
+        }
+
+        #[test]
+        fn from_str() {
+            ADDRESSES.iter().for_each(|(format, address)| {
+                test_from_str::<N>(address, format);
+            });
+        }
+
+        #[test]
+        fn test_invalid() {
+            // Regtest and mainnet Bech32 prefixes share their first two characters
+            let address = "bcrt1qalwlmdxd2ggue4290ekzxl9tetg56neewvcrh5";
+            assert!(BitcoinAddress::<Mainnet>::from_str(address).is_err());
+            assert!(BitcoinAddress::<Testnet>::from_str(address).is_err());
+            assert!(BitcoinAddress::<N>::from_str("bc1qalwlmdxd2ggue4290ekzxl9tetg56neexr6amw").is_err());
+            assert!(BitcoinAddress::<N>::from_str("tb1qalwlmdxd2ggue4290ekzxl9tetg56neev9pwqa").is_err());
+        }
+    }
+
+    mod signet {
+        use super::*;
+
+        type N = Signet;
+
+        const PRIVATE_KEY: &str = "cPnuYdFEiKoyLWhKbFjVMbYZsXo7G7e18Xgadb798GCsLtnQbixp";
+        // (format, address)
+        const ADDRESSES: [(BitcoinFormat, &str); 3] = [
+            (BitcoinFormat::P2PKH, "n3PFj7J96N3uTKZfcRYHggxfd1eAKgDLjX"),
+            (BitcoinFormat::P2SH_P2WPKH, "2N1Mdnn2XCeoyxkmVYWpKhCLfamgeXJmrYV"),
+            (BitcoinFormat::Bech32, "tb1qalwlmdxd2ggue4290ekzxl9tetg56neev9pwqa"),
+        ];
+
+        #[test]
+        fn from_private_key() {
+            let private_key = BitcoinPrivateKey::<N>::from_str(PRIVATE_KEY).unwrap();
+            ADDRESSES.iter().for_each(|(format, address)| {
+                test_from_private_key(address, &private_key, format);
+            });
+        }
+
+        #[test]
+        fn from_str() {
+            ADDRESSES.iter().for_each(|(format, address)| {
+                test_from_str::<N>(address, format);
+            });
+            assert!(BitcoinAddress::<N>::from_str("bcrt1qalwlmdxd2ggue4290ekzxl9tetg56neewvcrh5").is_err());
+        }
+    }
+
+    mod custom_network {
+        use super::*;
+
+        type N = CustomNetwork<1>;
+
+        fn register() {
+            N::register(NetworkParameters {
+                name: "private-signet".into(),
+                p2pkh_prefix: 0x7D,
+                p2sh_prefix: 0x57,
+                bech32_hrp: Some("sbt".into()),
+                wif_prefix: 0x9F,
+                extended_public_key_versions: vec![[0x04, 0x35, 0x87, 0xCF]],
+            });
+        }
+
+        const PRIVATE_KEY: &str = "QZQToiRGi2WyP7dsMCA3ffybxmivUdwr7Br9sFPZqyuvpQtEeF3q";
+        // (format, address)
+        const ADDRESSES: [(BitcoinFormat, &str); 3] = [
+            (BitcoinFormat::P2PKH, "sg7hWdUB2tYAuPWsxJCkUSmgS5FNDtBpAS"),
+            (BitcoinFormat::P2SH_P2WPKH, "c9WtTwY7ozJUpWa3snWaqXfuynbpwFhYX4"),
+            (BitcoinFormat::Bech32, "sbt1qalwlmdxd2ggue4290ekzxl9tetg56neeur3gh2"),
+        ];
+
+        #[test]
+        fn from_private_key() {
+            register();
+            let private_key = BitcoinPrivateKey::<N>::from_str(PRIVATE_KEY).unwrap();
+            assert_eq!(PRIVATE_KEY, private_key.to_string());
+            ADDRESSES.iter().for_each(|(format, address)| {
+                test_from_private_key(address, &private_key, format);
+            });
+        }
+
+        #[test]
+        fn from_str() {
+            register();
+            ADDRESSES.iter().for_each(|(format, address)| {
+                test_from_str::<N>(address, format);
+            });
+            assert_eq!(N::from_str("private-signet").unwrap().to_string(), "private-signet");
+        }
+
+        #[test]
+        fn test_invalid() {
+            register();
+            assert!(BitcoinAddress::<N>::from_str("bc1qalwlmdxd2ggue4290ekzxl9tetg56neexr6amw").is_err());
+            assert!(BitcoinAddress::<N>::from_str("n3PFj7J96N3uTKZfcRYHggxfd1eAKgDLjX").is_err());
+            assert!(BitcoinPrivateKey::<N>::from_str("cPnuYdFEiKoyLWhKbFjVMbYZsXo7G7e18Xgadb798GCsLtnQbixp").is_err());
+            assert!(N::from_str("signet").is_err());
+
+            // Networks that are not registered parse nothing
+            assert!(BitcoinAddress::<CustomNetwork<2>>::from_str(ADDRESSES[0].1).is_err());
+            assert!(BitcoinPrivateKey::<CustomNetwork<2>>::from_str(PRIVATE_KEY).is_err());
+            assert!(CustomNetwork::<2>::from_str("private-signet").is_err());
+        }
+    }
+
+    mod bitcoin_cash {
+        use super::*;
+        use crate::transaction::create_script_pub_key;
+
+        // (WIF, CashAddr P2PKH, legacy P2PKH)
+        const MAINNET_KEYPAIRS: [(&str, &str, &str); 3] = [
+            (
+                "KyRv5iFPHG7iB5E4CqvMzH3WFJVhbfYK4VY7XAedd9Ys69mEsPLQ",
+                "bitcoincash:qrhamld5e4fprnx4g4lxcgmu409dzn208yc07hur5p",
+                "1NsJS4DAHLcegD63trZurmkLm23TRAujXd",
+            ),
+            (
+                "L1uyy5qTuGrVXrmrsvHWHgVzW9kKdrp27wBC7Vs6nZDTF2BRUVwy",
+                "bitcoincash:qprcvtlpvhnpyxhcp4wau8ktg78dzuzktvetlc7g9s",
+                "17XBj6iFEsf8kzDMGQk5ghZipxX49VXuaV",
+            ),
+            (
+                "KwtetKxofS1Lhp7idNJzb5B5WninBRfELdwkjvTMZZGME4G72kMz",
+                "bitcoincash:qrv6fk7uaaww8wegp322sgqa9jflgqc3tsxaxsmukf",
+                "1Lqo8DmoGQqMga77j3AUZBUMwSR2HD4zeW",
+            ),
+        ];
+        const TESTNET_KEYPAIRS: [(&str, &str, &str); 2] = [
+            (
+                "cTt14Wpo6gKBSjuf9PjrbYc1Jz9hN4wepeJLm1DANsE6v5szQn4h",
+                "bchtest:qq23jz6l6zare0kqmkj8tv0uweqzgp34hvk5qklhay",
+                "mhSWVCZ7GtrYeDavBbZCUKownLPSAnxMyD",
+            ),
+            (
+                "cRfuiTAEpcEdgjXHLkYK3mFZWARjgMDRYGdka2G5hGpQdGKVATqN",
+                "bchtest:qqw4r8j8cwt4cnmzmvlx2q2dfw7mrg4ajs9tphzv7a",
+                "miByfZ8aBt8xQwUW9DXJw4ockykf2P42MZ",
+            ),
+        ];
+
+        fn test_keypairs<N: BitcoinNetwork>(keypairs: &[(&str, &str, &str)]) {
+            keypairs.iter().for_each(|(private_key, cash_address, legacy_address)| {
+                let private_key = BitcoinPrivateKey::<N>::from_str(private_key).unwrap();
+                test_from_private_key(cash_address, &private_key, &BitcoinFormat::P2PKH);
+                test_from_str::<N>(cash_address, &BitcoinFormat::P2PKH);
+                test_from_str::<N>(legacy_address, &BitcoinFormat::P2PKH);
+            });
+        }
+
+        fn test_conversions<N: BitcoinNetwork>(keypairs: &[(&str, &str, &str)]) {
+            keypairs.iter().for_each(|(_, cash_address, legacy_address)| {
+                let cash = BitcoinAddress::<N>::from_str(cash_address).unwrap();
+                let legacy = BitcoinAddress::<N>::from_str(legacy_address).unwrap();
+                assert_eq!(*legacy_address, cash.to_legacy_address().unwrap().to_string());
+                assert_eq!(*cash_address, legacy.to_cash_address().unwrap().to_string());
+                assert_eq!(cash.to_hash160().unwrap(), legacy.to_hash160().unwrap());
+                assert_eq!(create_script_pub_key(&cash).unwrap(), create_script_pub_key(&legacy).unwrap());
+                assert_eq!(cash, BitcoinAddress::from_script_pub_key(&create_script_pub_key(&legacy).unwrap()).unwrap());
+            });
+        }
+
+        #[test]
+        fn from_private_key() {
+            test_keypairs::<BitcoinCash>(&MAINNET_KEYPAIRS);
+            test_keypairs::<BitcoinCashTestnet>(&TESTNET_KEYPAIRS);
+        }
+
+        #[test]
+        fn conversions() {
+            test_conversions::<BitcoinCash>(&MAINNET_KEYPAIRS);
+            test_conversions::<BitcoinCashTestnet>(&TESTNET_KEYPAIRS);
+        }
+
+        #[test]
+        fn script_hash() {
+            let address = BitcoinAddress::<BitcoinCash>::p2sh(&[0x51]).unwrap();
+            assert_eq!("bitcoincash:prdpw30fk4ym6zl6rftfjuw806arpn26fv8cp7wyl3", address.to_string());
+            assert_eq!("3MaB7QVq3k4pQx3BhsvEADgzQonLSBwMdj", address.to_legacy_address().unwrap().to_string());
+            test_from_str::<BitcoinCash>(&address.to_string(), &BitcoinFormat::P2SH);
+            test_from_str::<BitcoinCash>("3MaB7QVq3k4pQx3BhsvEADgzQonLSBwMdj", &BitcoinFormat::P2SH);
+        }
+
+        #[test]
+        fn canonical_form() {
+            // The prefix may be omitted and the address may be uppercase
+            let expected = MAINNET_KEYPAIRS[0].1;
+            for address in [&expected[12..], &expected.to_uppercase(), &expected[12..].to_uppercase()] {
+                let address = BitcoinAddress::<BitcoinCash>::from_str(address).unwrap();
+                assert_eq!(expected, address.to_string());
+                assert_eq!(BitcoinFormat::P2PKH, address.format());
+            }
+        }
+
+        #[test]
+        fn test_invalid() {
+            let private_key = BitcoinPrivateKey::<BitcoinCash>::from_str(MAINNET_KEYPAIRS[0].0).unwrap();
+            assert!(BitcoinAddress::from_private_key(&private_key, &BitcoinFormat::Bech32).is_err());
+            assert!(BitcoinAddress::from_private_key(&private_key, &BitcoinFormat::P2SH_P2WPKH).is_err());
+            // Addresses of the other network
+            assert!(BitcoinAddress::<BitcoinCash>::from_str(TESTNET_KEYPAIRS[0].1).is_err());
+            assert!(BitcoinAddress::<BitcoinCashTestnet>::from_str(MAINNET_KEYPAIRS[0].1).is_err());
+            assert!(BitcoinAddress::<BitcoinCashTestnet>::from_str(MAINNET_KEYPAIRS[0].2).is_err());
+            // Mixed case and invalid checksum
+            assert!(BitcoinAddress::<BitcoinCash>::from_str("bitcoincash:qrhamld5e4fprnx4g4lxcgmu409dzn208yc07huR5p").is_err());
+            assert!(BitcoinAddress::<BitcoinCash>::from_str("bitcoincash:qrhamld5e4fprnx4g4lxcgmu409dzn208yc07hur5q").is_err());
+            // Only 20-byte hashes are supported
+            assert!(BitcoinAddress::<BitcoinCash>::from_str(
+                "bitcoincash:pvqqzqsrqszsvpcgpy9qkrqdpc83qygjzv2p29shrqv35xcur50p7h2c7ctj5"
+            )
+            .is_err());
+            // Other networks have no CashAddr addresses
+            let address = BitcoinAddress::<Mainnet>::from_str("1NsJS4DAHLcegD63trZurmkLm23TRAujXd").unwrap();
+            assert!(address.to_cash_address().is_err());
+            assert!(BitcoinAddress::<Mainnet>::from_str(MAINNET_KEYPAIRS[0].1).is_err());
+        }
+    }
+
+    mod script_pub_key {
+        use super::*;
+        use crate::transaction::create_script_pub_key;
+
+        const MAINNET_ADDRESSES: [(&str, &str); 6] = [
+            ("1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2", "76a91477bff20c60e522dfaa3350c39b030a5d004e839a88ac"),
+            ("3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy", "a914b472a266d0bd89c13706a4132ccfb16f7c3b9fcb87"),
+            ("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4", "0014751e76e8199196d454941c45d1b3a323f1433bd6"),
+            (
+                "bc1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3qccfmv3",
+                "00201863143c14c5166804bd19203356da136c985678cd4d27a1b8c6329604903262",
+            ),
+            (
+                "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0",
+                "512079be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+            ),
+            ("bc1sw50qgdz25j", "6002751e"),
+        ];
+
+        #[test]
+        fn from_script_pub_key() {
+            MAINNET_ADDRESSES.iter().for_each(|(address, script_pub_key)| {
+                let address = BitcoinAddress::<Mainnet>::from_str(address).unwrap();
+                let script_pub_key = hex::decode(script_pub_key).unwrap();
+                assert_eq!(script_pub_key, create_script_pub_key(&address).unwrap());
+                assert_eq!(address, BitcoinAddress::from_script_pub_key(&script_pub_key).unwrap());
+            });
+
+            let address = BitcoinAddress::<Testnet>::from_str("mhSWVCZ7GtrYeDavBbZCUKownLPSAnxMyD").unwrap();
+            let script_pub_key = create_script_pub_key(&address).unwrap();
+            assert_eq!(address, BitcoinAddress::from_script_pub_key(&script_pub_key).unwrap());
+        }
+
+        #[test]
+        fn invalid() {
+            // OP_RETURN, P2PK and a version 0 program of neither 20 nor 32 bytes
+            for script_pub_key in [
+                "6a0b68656c6c6f20776f726c64",
+                "210279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798ac",
+                "0015751e76e8199196d454941c45d1b3a323f1433bd6ab",
+            ] {
+                let script_pub_key = hex::decode(script_pub_key).unwrap();
+                assert!(BitcoinAddress::<Mainnet>::from_script_pub_key(&script_pub_key).is_err());
+            }
+        }
+    }
 }
@@ -1,3 +1,4 @@
+use crate::network::BitcoinNetwork;
 use chainlib_core::no_std::*;
 use chainlib_core::{Amount, AmountError};
 
@@ -7,7 +8,7 @@ use serde::Serialize;
 // Number of satoshis (base unit) per BTC
 const COIN: i64 = 1_0000_0000;
 
-// Maximum number of satoshis
+// Maximum number of satoshis on Bitcoin, see `BitcoinNetwork::max_money` for other networks
 const MAX_COINS: i64 = 21_000_000 * COIN;
 
 /// Represents the amount of Bitcoin in satoshis
@@ -71,13 +72,19 @@ impl BitcoinAmount {
     pub const ONE_BTC: BitcoinAmount = BitcoinAmount(COIN);
 
     pub fn from_satoshi(satoshis: i64) -> Result<Self, AmountError> {
-        if -MAX_COINS <= satoshis && satoshis <= MAX_COINS {
+        Self::from_satoshi_within(satoshis, MAX_COINS)
+    }
+
+    /// Returns the amount in the base unit of the network, if it is within the money supply of the network.
+    pub fn from_satoshi_for<N: BitcoinNetwork>(satoshis: i64) -> Result<Self, AmountError> {
+        Self::from_satoshi_within(satoshis, N::max_money())
+    }
+
+    fn from_satoshi_within(satoshis: i64, max_money: i64) -> Result<Self, AmountError> {
+        if -max_money <= satoshis && satoshis <= max_money {
             Ok(Self(satoshis))
         } else {
-            return Err(AmountError::AmountOutOfBounds(
-                satoshis.to_string(),
-                MAX_COINS.to_string(),
-            ));
+            Err(AmountError::AmountOutOfBounds(satoshis.to_string(), max_money.to_string()))
         }
     }
 
@@ -298,6 +305,23 @@ mod tests {
         }
     }
 
+    mod network_supply {
+        use super::*;
+        use crate::network::*;
+
+        #[test]
+        fn test_max_money() {
+            let litecoins = 84_000_000 * COIN;
+            let dogecoins = 10_000_000_000 * COIN;
+            assert!(BitcoinAmount::from_satoshi(MAX_COINS + 1).is_err());
+            assert_eq!(BitcoinAmount(MAX_COINS), BitcoinAmount::from_satoshi_for::<Mainnet>(MAX_COINS).unwrap());
+            assert_eq!(BitcoinAmount(litecoins), BitcoinAmount::from_satoshi_for::<Litecoin>(litecoins).unwrap());
+            assert!(BitcoinAmount::from_satoshi_for::<Litecoin>(litecoins + 1).is_err());
+            assert_eq!(BitcoinAmount(dogecoins), BitcoinAmount::from_satoshi_for::<DogecoinTestnet>(dogecoins).unwrap());
+            assert!(BitcoinAmount::from_satoshi_for::<Dash>(MAX_COINS + 1).is_err());
+        }
+    }
+
     mod test_invalid {
         use super::*;
 
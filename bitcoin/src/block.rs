@@ -0,0 +1,956 @@
+//! # Block
+//!
+//! Block headers, full blocks and `merkleblock` proofs as relayed between nodes (BIP-37).
+//! A header commits to its previous block and to the Merkle root of its transaction ids, and proves its
+//! work by hashing below the target encoded in its compact `bits`. A light client validates a chain of
+//! headers and confirms that a transaction is included in one of them with a partial Merkle tree, the
+//! proof returned by the `gettxoutproof` RPC, without trusting the node that served it.
+//! https://github.com/bitcoin/bips/blob/master/bip-0037.mediawiki#partial-merkle-branch-format
+
+use crate::network::{BitcoinNetwork, DifficultyAdjustment};
+use crate::transaction::{
+    read_variable_length_integer, variable_length_integer, BitcoinTransaction, BitcoinTransactionId,
+    BitcoinTransactionParameters, BitcoinVector,
+};
+use chainlib_core::no_std::{io::Read, *};
+use chainlib_core::{Transaction, TransactionError};
+
+use core::{fmt, str::FromStr};
+use sha2::{Digest, Sha256};
+
+/// The timespan of a difficulty adjustment period, two weeks in seconds
+const TARGET_TIMESPAN: i64 = 14 * 24 * 60 * 60;
+/// The time after which a block may have the lowest difficulty where allowed, twice the target block spacing
+const MIN_DIFFICULTY_DELAY: u32 = 2 * 10 * 60;
+/// The number of previous blocks whose median time a block's time must be after
+const MEDIAN_TIME_SPAN: usize = 11;
+/// The largest number of transactions of a block, of 4M weight units and 240 units per transaction at least
+const MAX_BLOCK_TRANSACTIONS: u32 = 4_000_000 / 240;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum BlockError {
+    #[error("{0}: {1}")]
+    Crate(&'static str, String),
+
+    #[error("invalid compact target {0:#010x}")]
+    InvalidBits(u32),
+
+    #[error("invalid block: {0}")]
+    InvalidBlock(&'static str),
+
+    #[error("invalid hash {0}")]
+    InvalidHash(String),
+
+    #[error("invalid Merkle root: {{ expected: {0}, found: {1} }}")]
+    InvalidMerkleRoot(String, String),
+
+    #[error("invalid partial Merkle tree: {0}")]
+    InvalidPartialMerkleTree(&'static str),
+
+    #[error("block at height {0} does not follow the previous block")]
+    InvalidPreviousBlock(u32),
+
+    #[error("block hash {0} is above its target")]
+    InvalidProofOfWork(String),
+
+    #[error("time {1} of block at height {0} is not after the median time of the previous blocks")]
+    InvalidTime(u32, u32),
+
+    #[error("unexpected compact target of block at height {0}: {{ expected: {1:#010x}, found: {2:#010x} }}")]
+    UnexpectedBits(u32, u32, u32),
+
+    #[error("unsupported proof of work of {0}")]
+    UnsupportedProofOfWork(&'static str),
+}
+
+impl From<TransactionError> for BlockError {
+    fn from(error: TransactionError) -> Self {
+        BlockError::Crate("Transaction", format!("{:?}", error))
+    }
+}
+
+impl From<chainlib_core::no_std::io::Error> for BlockError {
+    fn from(error: chainlib_core::no_std::io::Error) -> Self {
+        BlockError::Crate("io", format!("{:?}", error))
+    }
+}
+
+impl From<BlockError> for TransactionError {
+    fn from(error: BlockError) -> Self {
+        TransactionError::Crate("Block", format!("{:?}", error))
+    }
+}
+
+/// Represents a double SHA256 hash, such as a block hash, a transaction id or a Merkle root,
+/// in its internal byte order and displayed reversed as by Bitcoin Core
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Hash256(pub [u8; 32]);
+
+impl Hash256 {
+    /// Returns the double SHA256 hash of the data.
+    pub fn hash(data: &[u8]) -> Self {
+        Self(Sha256::digest(Sha256::digest(data)).into())
+    }
+
+    /// Returns the hash as a 256-bit big-endian number, the hash being a little-endian one.
+    fn to_be_bytes(self) -> [u8; 32] {
+        let mut bytes = self.0;
+        bytes.reverse();
+        bytes
+    }
+}
+
+impl From<&BitcoinTransactionId> for Hash256 {
+    fn from(transaction_id: &BitcoinTransactionId) -> Self {
+        let mut hash = [0u8; 32];
+        hash.copy_from_slice(&transaction_id.txid);
+        hash.reverse();
+        Self(hash)
+    }
+}
+
+impl FromStr for Hash256 {
+    type Err = BlockError;
+
+    fn from_str(hash: &str) -> Result<Self, Self::Err> {
+        match hex::decode(hash).ok().and_then(|bytes| <[u8; 32]>::try_from(bytes).ok()) {
+            Some(mut bytes) => {
+                bytes.reverse();
+                Ok(Self(bytes))
+            }
+            None => Err(BlockError::InvalidHash(hash.into())),
+        }
+    }
+}
+
+impl fmt::Display for Hash256 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", hex::encode(self.to_be_bytes()))
+    }
+}
+
+/// Represents the 80-byte header of a block
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BlockHeader {
+    /// The version, signaling soft forks (BIP-9)
+    pub version: i32,
+    /// The hash of the previous block
+    pub previous_block_hash: Hash256,
+    /// The Merkle root of the transaction ids of the block
+    pub merkle_root: Hash256,
+    /// The Unix timestamp of the block
+    pub time: u32,
+    /// The compact target the block hash must not exceed
+    pub bits: u32,
+    /// The nonce varied to find a block hash below the target
+    pub nonce: u32,
+}
+
+impl BlockHeader {
+    /// The size of a serialized header
+    pub const SIZE: usize = 80;
+
+    /// Read and output a block header
+    pub fn read<R: Read>(mut reader: R) -> Result<Self, BlockError> {
+        let mut header = [0u8; Self::SIZE];
+        reader.read_exact(&mut header)?;
+        Self::from_bytes(&header)
+    }
+
+    /// Returns the block header of the 80 bytes.
+    pub fn from_bytes(header: &[u8]) -> Result<Self, BlockError> {
+        if header.len() != Self::SIZE {
+            return Err(BlockError::InvalidBlock("header is not 80 bytes"));
+        }
+        let word = |offset: usize| u32::from_le_bytes([header[offset], header[offset + 1], header[offset + 2], header[offset + 3]]);
+        let hash = |offset: usize| {
+            let mut hash = [0u8; 32];
+            hash.copy_from_slice(&header[offset..offset + 32]);
+            Hash256(hash)
+        };
+        Ok(Self {
+            version: word(0) as i32,
+            previous_block_hash: hash(4),
+            merkle_root: hash(36),
+            time: word(68),
+            bits: word(72),
+            nonce: word(76),
+        })
+    }
+
+    /// Returns the serialized header.
+    pub fn to_bytes(&self) -> [u8; 80] {
+        let mut header = [0u8; Self::SIZE];
+        header[0..4].copy_from_slice(&self.version.to_le_bytes());
+        header[4..36].copy_from_slice(&self.previous_block_hash.0);
+        header[36..68].copy_from_slice(&self.merkle_root.0);
+        header[68..72].copy_from_slice(&self.time.to_le_bytes());
+        header[72..76].copy_from_slice(&self.bits.to_le_bytes());
+        header[76..80].copy_from_slice(&self.nonce.to_le_bytes());
+        header
+    }
+
+    /// Returns the hash of the header, which identifies the block.
+    pub fn block_hash(&self) -> Hash256 {
+        Hash256::hash(&self.to_bytes())
+    }
+
+    /// Returns the target of the compact bits, a 256-bit big-endian number.
+    pub fn target(&self) -> Result<[u8; 32], BlockError> {
+        compact_to_target(self.bits)
+    }
+
+    /// Returns the difficulty of the block, the ratio of the target of difficulty 1 (0x1d00ffff) to its target.
+    pub fn difficulty(&self) -> f64 {
+        let mut shift = (self.bits >> 24) & 0xff;
+        let mut difficulty = 0x0000ffff as f64 / (self.bits & 0x00ffffff) as f64;
+        while shift < 29 {
+            difficulty *= 256.0;
+            shift += 1;
+        }
+        while shift > 29 {
+            difficulty /= 256.0;
+            shift -= 1;
+        }
+        difficulty
+    }
+
+    /// Checks that the block hash is below the target of the header,
+    /// which must not be above the target of the lowest difficulty of the network.
+    pub fn check_proof_of_work<N: BitcoinNetwork>(&self) -> Result<(), BlockError> {
+        let pow_limit = compact_to_target(N::pow_limit_bits().ok_or(BlockError::UnsupportedProofOfWork(N::NAME))?)?;
+        let target = self.target()?;
+        if target == [0u8; 32] || target > pow_limit {
+            return Err(BlockError::InvalidBits(self.bits));
+        }
+        let block_hash = self.block_hash();
+        match block_hash.to_be_bytes() <= target {
+            true => Ok(()),
+            false => Err(BlockError::InvalidProofOfWork(block_hash.to_string())),
+        }
+    }
+}
+
+/// Checks that the headers form a chain from the given height of the first one, each following the previous
+/// one with enough work and the target required by the network's difficulty adjustment. The targets of periods
+/// starting before the first header, and of testnet blocks following a run of lowest difficulty blocks going back
+/// to it, are only checked against the lowest difficulty.
+pub fn validate_header_chain<N: BitcoinNetwork>(headers: &[BlockHeader], height: u32) -> Result<(), BlockError> {
+    for (index, header) in headers.iter().enumerate() {
+        header.check_proof_of_work::<N>()?;
+        let previous = match index.checked_sub(1) {
+            Some(previous) => &headers[..=previous],
+            None => continue,
+        };
+        let header_height = height + index as u32;
+        if header.previous_block_hash != previous[previous.len() - 1].block_hash() {
+            return Err(BlockError::InvalidPreviousBlock(header_height));
+        }
+
+        let mut times = previous.iter().rev().take(MEDIAN_TIME_SPAN).map(|header| header.time).collect::<Vec<_>>();
+        times.sort_unstable();
+        if header.time <= times[times.len() / 2] {
+            return Err(BlockError::InvalidTime(header_height, header.time));
+        }
+
+        if let Some(expected) = next_bits::<N>(previous, height, header.time)? {
+            if header.bits != expected {
+                return Err(BlockError::UnexpectedBits(header_height, expected, header.bits));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns the compact target of the block following the last of the previous headers at the given time,
+/// or `None` if it depends on headers before the first one.
+fn next_bits<N: BitcoinNetwork>(previous: &[BlockHeader], height: u32, time: u32) -> Result<Option<u32>, BlockError> {
+    let pow_limit = N::pow_limit_bits().ok_or(BlockError::UnsupportedProofOfWork(N::NAME))?;
+    let last = &previous[previous.len() - 1];
+    let next_height = height + previous.len() as u32;
+    let interval = match N::difficulty_adjustment() {
+        DifficultyAdjustment::Periodic(interval) => interval,
+        DifficultyAdjustment::Fixed => return Ok(Some(last.bits)),
+        DifficultyAdjustment::Unchecked => return Ok(None),
+    };
+
+    if !next_height.is_multiple_of(interval) {
+        if !N::allows_min_difficulty_blocks() {
+            return Ok(Some(last.bits));
+        }
+        if time > last.time.saturating_add(MIN_DIFFICULTY_DELAY) {
+            return Ok(Some(pow_limit));
+        }
+        // Otherwise the target of the last block not mined at the lowest difficulty
+        let normal = previous
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|(index, header)| (height + *index as u32).is_multiple_of(interval) || header.bits != pow_limit);
+        return Ok(normal.map(|(_, header)| header.bits));
+    }
+
+    let first = match (next_height - interval).checked_sub(height) {
+        Some(first) => &previous[first as usize],
+        None => return Ok(None),
+    };
+    calculate_next_bits(first.time, last.time, last.bits, pow_limit).map(Some)
+}
+
+/// Returns the compact target of the period following the one from the first to the last block, adjusted by the
+/// ratio of its timespan, limited to a factor of 4, to two weeks, and not above the target of the lowest difficulty.
+pub fn calculate_next_bits(first_time: u32, last_time: u32, last_bits: u32, pow_limit_bits: u32) -> Result<u32, BlockError> {
+    let timespan = (last_time as i64 - first_time as i64).clamp(TARGET_TIMESPAN / 4, TARGET_TIMESPAN * 4);
+    let pow_limit = compact_to_target(pow_limit_bits)?;
+    let target = multiply_divide(compact_to_target(last_bits)?, timespan as u64, TARGET_TIMESPAN as u64);
+    Ok(target_to_compact(&target.min(pow_limit)))
+}
+
+/// Returns the 256-bit big-endian target of the compact bits, a 3-byte mantissa and a 1-byte size in bytes.
+fn compact_to_target(bits: u32) -> Result<[u8; 32], BlockError> {
+    let size = (bits >> 24) as usize;
+    let word = bits & 0x007fffff;
+    let is_negative = word != 0 && bits & 0x00800000 != 0;
+    let is_overflow = word != 0 && (size > 34 || (word > 0xff && size > 33) || (word > 0xffff && size > 32));
+    if is_negative || is_overflow {
+        return Err(BlockError::InvalidBits(bits));
+    }
+
+    let mut target = [0u8; 32];
+    match size {
+        0..=3 => target[29..].copy_from_slice(&(word >> (8 * (3 - size))).to_be_bytes()[1..]),
+        _ => {
+            for (index, byte) in word.to_be_bytes()[1..].iter().enumerate() {
+                // The exponent of the byte in base 256
+                let exponent = size - 1 - index;
+                if exponent < 32 {
+                    target[31 - exponent] = *byte;
+                }
+            }
+        }
+    }
+    Ok(target)
+}
+
+/// Returns the compact bits of the 256-bit big-endian target, rounded down to its 3 most significant bytes.
+fn target_to_compact(target: &[u8; 32]) -> u32 {
+    let mut size = 32 - target.iter().take_while(|byte| **byte == 0).count();
+    let mut compact = match size {
+        0..=3 => u32::from_be_bytes([0, target[29], target[30], target[31]]) << (8 * (3 - size)),
+        _ => u32::from_be_bytes([0, target[32 - size], target[33 - size], target[34 - size]]),
+    };
+    // The mantissa is signed, so a set top bit moves to the next byte
+    if compact & 0x00800000 != 0 {
+        compact >>= 8;
+        size += 1;
+    }
+    compact | (size as u32) << 24
+}
+
+/// Returns the 256-bit big-endian number multiplied, modulo 2^256 as Bitcoin Core does, then divided.
+fn multiply_divide(mut number: [u8; 32], multiplier: u64, divisor: u64) -> [u8; 32] {
+    let mut carry = 0u64;
+    for byte in number.iter_mut().rev() {
+        let product = *byte as u64 * multiplier + carry;
+        *byte = product as u8;
+        carry = product >> 8;
+    }
+    let mut remainder = 0u64;
+    for byte in number.iter_mut() {
+        let dividend = (remainder << 8) | *byte as u64;
+        *byte = (dividend / divisor) as u8;
+        remainder = dividend % divisor;
+    }
+    number
+}
+
+/// Returns the Merkle root of the hashes, each level duplicating its last hash if odd,
+/// or `None` if there are no hashes.
+pub fn merkle_root(hashes: &[Hash256]) -> Option<Hash256> {
+    compute_merkle_root(hashes).map(|(root, _)| root)
+}
+
+/// Returns the Merkle root of the hashes and true if a level has two identical hashes in a pair,
+/// as in a block whose transactions were duplicated to have the root of another one (CVE-2012-2459).
+fn compute_merkle_root(hashes: &[Hash256]) -> Option<(Hash256, bool)> {
+    let mut level = hashes.to_vec();
+    let mut is_mutated = false;
+    while level.len() > 1 {
+        is_mutated |= level.chunks(2).any(|pair| pair.len() == 2 && pair[0] == pair[1]);
+        if level.len() % 2 == 1 {
+            level.push(level[level.len() - 1]);
+        }
+        level = level.chunks(2).map(|pair| hash_pair(&pair[0], &pair[1])).collect();
+    }
+    level.first().map(|root| (*root, is_mutated))
+}
+
+/// Returns the hash of a node of a Merkle tree with the given children.
+fn hash_pair(left: &Hash256, right: &Hash256) -> Hash256 {
+    Hash256::hash(&[left.0, right.0].concat())
+}
+
+/// Represents a Bitcoin block, its header and transactions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BitcoinBlock<N: BitcoinNetwork> {
+    /// The block header
+    pub header: BlockHeader,
+    /// The transactions, starting with the coinbase transaction
+    pub transactions: Vec<BitcoinTransaction<N>>,
+}
+
+impl<N: BitcoinNetwork> BitcoinBlock<N> {
+    /// Read and output a block
+    pub fn read<R: Read>(mut reader: R) -> Result<Self, BlockError> {
+        let header = BlockHeader::read(&mut reader)?;
+        let transactions = BitcoinVector::read(&mut reader, |reader| {
+            BitcoinTransaction::<N>::new(&BitcoinTransactionParameters::<N>::read(reader)?)
+        })?;
+        Ok(Self { header, transactions })
+    }
+
+    /// Returns the block of the bytes, which must all be read.
+    pub fn from_bytes(block: &[u8]) -> Result<Self, BlockError> {
+        let mut reader = block;
+        let block = Self::read(&mut reader)?;
+        match reader.is_empty() {
+            true => Ok(block),
+            false => Err(BlockError::InvalidBlock("trailing data")),
+        }
+    }
+
+    /// Returns the serialized block.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, BlockError> {
+        let mut block = self.header.to_bytes().to_vec();
+        block.extend(variable_length_integer(self.transactions.len() as u64)?);
+        for transaction in &self.transactions {
+            block.extend(transaction.to_bytes()?);
+        }
+        Ok(block)
+    }
+
+    /// Returns the hash of the block header.
+    pub fn block_hash(&self) -> Hash256 {
+        self.header.block_hash()
+    }
+
+    /// Returns the transaction ids of the transactions of the block.
+    pub fn transaction_ids(&self) -> Result<Vec<Hash256>, BlockError> {
+        self.transactions
+            .iter()
+            .map(|transaction| Ok(Hash256::from(&transaction.to_transaction_id()?)))
+            .collect()
+    }
+
+    /// Returns the Merkle root of the transaction ids of the block.
+    pub fn merkle_root(&self) -> Result<Hash256, BlockError> {
+        merkle_root(&self.transaction_ids()?).ok_or(BlockError::InvalidBlock("no transactions"))
+    }
+
+    /// Checks the proof of work of the block, that its only coinbase transaction comes first,
+    /// and that its header commits to its transactions without duplicates.
+    pub fn validate(&self) -> Result<(), BlockError> {
+        self.header.check_proof_of_work::<N>()?;
+        match self.transactions.split_first() {
+            Some((coinbase, transactions)) if coinbase.is_coinbase() => {
+                if transactions.iter().any(BitcoinTransaction::is_coinbase) {
+                    return Err(BlockError::InvalidBlock("more than one coinbase transaction"));
+                }
+            }
+            Some(_) => return Err(BlockError::InvalidBlock("first transaction is not a coinbase transaction")),
+            None => return Err(BlockError::InvalidBlock("no transactions")),
+        }
+        let (merkle_root, is_mutated) = compute_merkle_root(&self.transaction_ids()?).ok_or(BlockError::InvalidBlock("no transactions"))?;
+        if is_mutated {
+            return Err(BlockError::InvalidBlock("duplicate transactions"));
+        }
+        match merkle_root == self.header.merkle_root {
+            true => Ok(()),
+            false => Err(BlockError::InvalidMerkleRoot(self.header.merkle_root.to_string(), merkle_root.to_string())),
+        }
+    }
+}
+
+/// Represents a partial Merkle tree, the hashes and flags of a depth-first traversal of the Merkle tree of
+/// a block's transactions that descends into the branches of the matched transactions only (BIP-37)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PartialMerkleTree {
+    /// The number of transactions of the block
+    pub transactions: u32,
+    /// The hashes of the nodes the traversal does not descend into, and of the matched transactions
+    pub hashes: Vec<Hash256>,
+    /// For each node of the traversal, true if a matched transaction descends from it
+    pub flags: Vec<bool>,
+}
+
+impl PartialMerkleTree {
+    /// Returns the partial Merkle tree of the transaction ids matching the transactions flagged in `matches`.
+    pub fn new(transaction_ids: &[Hash256], matches: &[bool]) -> Self {
+        let mut tree = Self { transactions: transaction_ids.len() as u32, hashes: vec![], flags: vec![] };
+        if !transaction_ids.is_empty() {
+            tree.build(tree.height(), 0, transaction_ids, matches);
+        }
+        tree
+    }
+
+    /// Read and output a partial Merkle tree
+    pub fn read<R: Read>(mut reader: R) -> Result<Self, BlockError> {
+        let mut transactions = [0u8; 4];
+        reader.read_exact(&mut transactions)?;
+        let hashes = BitcoinVector::read(&mut reader, |reader| {
+            let mut hash = [0u8; 32];
+            reader.read_exact(&mut hash)?;
+            Ok(Hash256(hash))
+        })?;
+        let flag_bytes = read_variable_length_integer(&mut reader)?;
+        let mut flags = Vec::with_capacity(flag_bytes.min(MAX_BLOCK_TRANSACTIONS as usize) * 8);
+        for _ in 0..flag_bytes {
+            let mut byte = [0u8; 1];
+            reader.read_exact(&mut byte)?;
+            flags.extend((0..8).map(|bit| byte[0] >> bit & 1 == 1));
+        }
+        Ok(Self { transactions: u32::from_le_bytes(transactions), hashes, flags })
+    }
+
+    /// Returns the serialized partial Merkle tree, with its flags packed from the least significant bit.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, BlockError> {
+        let mut tree = self.transactions.to_le_bytes().to_vec();
+        tree.extend(variable_length_integer(self.hashes.len() as u64)?);
+        for hash in &self.hashes {
+            tree.extend(hash.0);
+        }
+        let flags = self
+            .flags
+            .chunks(8)
+            .map(|bits| bits.iter().enumerate().fold(0u8, |byte, (bit, flag)| byte | (*flag as u8) << bit))
+            .collect::<Vec<_>>();
+        tree.extend(variable_length_integer(flags.len() as u64)?);
+        tree.extend(flags);
+        Ok(tree)
+    }
+
+    /// Returns the Merkle root of the tree and its matched transaction ids with their index in the block,
+    /// or an error if the tree is malformed or does not use all of its hashes and flags.
+    pub fn extract_matches(&self) -> Result<(Hash256, Vec<(usize, Hash256)>), BlockError> {
+        let invalid = BlockError::InvalidPartialMerkleTree;
+        if self.transactions == 0 {
+            return Err(invalid("no transactions"));
+        }
+        if self.transactions > MAX_BLOCK_TRANSACTIONS {
+            return Err(invalid("too many transactions"));
+        }
+        if self.hashes.len() > self.transactions as usize {
+            return Err(invalid("more hashes than transactions"));
+        }
+        if self.flags.len() < self.hashes.len() {
+            return Err(invalid("fewer flags than hashes"));
+        }
+
+        let mut traversal = Traversal { flags_used: 0, hashes_used: 0, matches: vec![] };
+        let merkle_root = self.traverse(self.height(), 0, &mut traversal)?;
+        // Only the padding of the last flag byte may be left unused
+        if traversal.flags_used.div_ceil(8) != self.flags.len().div_ceil(8) {
+            return Err(invalid("unused flags"));
+        }
+        if traversal.hashes_used != self.hashes.len() {
+            return Err(invalid("unused hashes"));
+        }
+        Ok((merkle_root, traversal.matches))
+    }
+
+    /// Returns the height of the tree, from its root to its transactions.
+    fn height(&self) -> u32 {
+        let mut height = 0;
+        while self.width(height) > 1 {
+            height += 1;
+        }
+        height
+    }
+
+    /// Returns the number of nodes of the tree at the height above its transactions.
+    fn width(&self, height: u32) -> u32 {
+        ((self.transactions as u64 + (1 << height) - 1) >> height) as u32
+    }
+
+    /// Returns the hash of the node of the full Merkle tree at the height and position.
+    fn calculate_hash(&self, height: u32, position: u32, transaction_ids: &[Hash256]) -> Hash256 {
+        if height == 0 {
+            return transaction_ids[position as usize];
+        }
+        let left = self.calculate_hash(height - 1, position * 2, transaction_ids);
+        let right = match position * 2 + 1 < self.width(height - 1) {
+            true => self.calculate_hash(height - 1, position * 2 + 1, transaction_ids),
+            false => left,
+        };
+        hash_pair(&left, &right)
+    }
+
+    /// Appends the flags and hashes of the traversal of the node at the height and position.
+    fn build(&mut self, height: u32, position: u32, transaction_ids: &[Hash256], matches: &[bool]) {
+        let start = (position as usize) << height;
+        let end = (((position as usize) + 1) << height).min(transaction_ids.len());
+        let is_parent_of_match = (start..end).any(|index| matches.get(index).copied().unwrap_or(false));
+        self.flags.push(is_parent_of_match);
+        if height == 0 || !is_parent_of_match {
+            self.hashes.push(self.calculate_hash(height, position, transaction_ids));
+        } else {
+            self.build(height - 1, position * 2, transaction_ids, matches);
+            if position * 2 + 1 < self.width(height - 1) {
+                self.build(height - 1, position * 2 + 1, transaction_ids, matches);
+            }
+        }
+    }
+
+    /// Returns the hash of the node at the height and position from the hashes and flags of the traversal.
+    fn traverse(&self, height: u32, position: u32, traversal: &mut Traversal) -> Result<Hash256, BlockError> {
+        let invalid = BlockError::InvalidPartialMerkleTree;
+        let is_parent_of_match = *self.flags.get(traversal.flags_used).ok_or(invalid("missing flags"))?;
+        traversal.flags_used += 1;
+        if height == 0 || !is_parent_of_match {
+            let hash = *self.hashes.get(traversal.hashes_used).ok_or(invalid("missing hashes"))?;
+            traversal.hashes_used += 1;
+            if height == 0 && is_parent_of_match {
+                traversal.matches.push((position as usize, hash));
+            }
+            return Ok(hash);
+        }
+        let left = self.traverse(height - 1, position * 2, traversal)?;
+        let right = match position * 2 + 1 < self.width(height - 1) {
+            true => self.traverse(height - 1, position * 2 + 1, traversal)?,
+            false => left,
+        };
+        // Identical children only occur by duplicating the last node of a level (CVE-2012-2459)
+        if position * 2 + 1 < self.width(height - 1) && left == right {
+            return Err(invalid("identical children"));
+        }
+        Ok(hash_pair(&left, &right))
+    }
+}
+
+/// The progress of the traversal of a partial Merkle tree
+struct Traversal {
+    flags_used: usize,
+    hashes_used: usize,
+    matches: Vec<(usize, Hash256)>,
+}
+
+/// Represents a `merkleblock` message, a block header with a partial Merkle tree proving
+/// the inclusion of some of its transactions
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MerkleBlock {
+    /// The block header
+    pub header: BlockHeader,
+    /// The partial Merkle tree of the matched transactions
+    pub tree: PartialMerkleTree,
+}
+
+impl MerkleBlock {
+    /// Returns the merkle block of the block proving the inclusion of the given transactions.
+    pub fn from_block<N: BitcoinNetwork>(block: &BitcoinBlock<N>, transaction_ids: &[Hash256]) -> Result<Self, BlockError> {
+        let block_transaction_ids = block.transaction_ids()?;
+        let matches = block_transaction_ids.iter().map(|id| transaction_ids.contains(id)).collect::<Vec<_>>();
+        Ok(Self { header: block.header, tree: PartialMerkleTree::new(&block_transaction_ids, &matches) })
+    }
+
+    /// Read and output a merkle block
+    pub fn read<R: Read>(mut reader: R) -> Result<Self, BlockError> {
+        let header = BlockHeader::read(&mut reader)?;
+        let tree = PartialMerkleTree::read(&mut reader)?;
+        Ok(Self { header, tree })
+    }
+
+    /// Returns the merkle block of the bytes, such as those returned by the `gettxoutproof` RPC.
+    pub fn from_bytes(merkle_block: &[u8]) -> Result<Self, BlockError> {
+        let mut reader = merkle_block;
+        let merkle_block = Self::read(&mut reader)?;
+        match reader.is_empty() {
+            true => Ok(merkle_block),
+            false => Err(BlockError::InvalidBlock("trailing data")),
+        }
+    }
+
+    /// Returns the serialized merkle block.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, BlockError> {
+        Ok([self.header.to_bytes().to_vec(), self.tree.to_bytes()?].concat())
+    }
+
+    /// Returns the matched transaction ids with their index in the block, once the partial Merkle tree
+    /// is checked to be well formed and to have the Merkle root of the header.
+    pub fn extract_matches(&self) -> Result<Vec<(usize, Hash256)>, BlockError> {
+        let (merkle_root, matches) = self.tree.extract_matches()?;
+        match merkle_root == self.header.merkle_root {
+            true => Ok(matches),
+            false => Err(BlockError::InvalidMerkleRoot(self.header.merkle_root.to_string(), merkle_root.to_string())),
+        }
+    }
+
+    /// Returns true if the merkle block proves that the transaction is included in its block.
+    pub fn contains(&self, transaction_id: &Hash256) -> Result<bool, BlockError> {
+        Ok(self.extract_matches()?.iter().any(|(_, id)| id == transaction_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::*;
+
+    const GENESIS_HEADER: &str = "0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a29ab5f49ffff001d1dac2b7c";
+    const GENESIS_COINBASE: &str = "01000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000";
+    const HEADERS: [(&str, &str); 2] = [
+        (
+            "010000006fe28c0ab6f1b372c1a6a246ae63f74f931e8365e15a089c68d6190000000000982051fd1e4ba744bbbe680e1fee14677ba1a3c3540bf7b1cdb606e857233e0e61bc6649ffff001d01e36299",
+            "00000000839a8e6886ab5951d76f411475428afc90947ee320161bbf18eb6048",
+        ),
+        (
+            "010000004860eb18bf1b1620e37e9490fc8a427514416fd75159ab86688e9a8300000000d5fdcc541e25de1c7a5addedf24858b8bb665c9f36ef744ee42c316022c90f9bb0bc6649ffff001d08d2bd61",
+            "000000006a625f06636b8bb6ac7b960a8d03705d1ace08b1a19da3fdcc99ddbd",
+        ),
+    ];
+    // A merkle block of 7 transactions matching the third and sixth ones
+    const MERKLE_BLOCK: &str = "0000002000000000000000000000000000000000000000000000000000000000000000007de65c7d57cdc72971c9beab94af6ad4e99f233fb6ccebd2b4b19f13697ca54d00f15365ffff7f200000000007000000064bbe83bc38ebe2bcc7520d234139df1c0eb9ffa51f83eab1c5129b5b906b76551cc3adea40ebfd94433ac004777d68150cce9db4c771bc7de1b297a7b795bbbac942a06c127c2c18022677e888020afb174208d299354f3ecfedb124a1f3fa45214e63bf41490e67d34476778f6707aa6c8d2c8dccdf78ae11e40ee9f91e89a788e443a340e2356812f72e04258672e5b287a177b66636e961cbc8d66b1e9b97ae4b0cbad80bc9de53a409bb530683b2e15f10f111c383fea8bcc8004c7f62c302db02";
+
+    fn hash(hash: &str) -> Hash256 {
+        Hash256::from_str(hash).unwrap()
+    }
+
+    /// Returns the header with the first nonce giving it enough work on regtest.
+    fn mine(mut header: BlockHeader) -> BlockHeader {
+        while header.check_proof_of_work::<Regtest>().is_err() {
+            header.nonce += 1;
+        }
+        header
+    }
+
+    /// Returns the genesis coinbase transaction spending the outpoint of the index instead.
+    fn spending<N: BitcoinNetwork>(index: u32) -> BitcoinTransaction<N> {
+        let mut transaction = hex::decode(GENESIS_COINBASE).unwrap();
+        transaction[37..41].copy_from_slice(&index.to_le_bytes());
+        BitcoinTransaction::<N>::from_bytes(&transaction).unwrap()
+    }
+
+    #[test]
+    fn genesis_block() {
+        let block_bytes = hex::decode(format!("{}01{}", GENESIS_HEADER, GENESIS_COINBASE)).unwrap();
+        let block = BitcoinBlock::<Mainnet>::from_bytes(&block_bytes).unwrap();
+        assert_eq!(block_bytes, block.to_bytes().unwrap());
+        assert_eq!(
+            "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f",
+            block.block_hash().to_string()
+        );
+        assert_eq!(
+            vec![hash("4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b")],
+            block.transaction_ids().unwrap()
+        );
+        assert_eq!(block.header.merkle_root, block.merkle_root().unwrap());
+        assert!(block.transactions[0].is_coinbase());
+        assert_eq!(1.0, block.header.difficulty());
+        assert_eq!(Ok(()), block.validate());
+
+        assert_eq!(
+            Err(BlockError::InvalidBlock("trailing data")),
+            BitcoinBlock::<Mainnet>::from_bytes(&[block_bytes.clone(), vec![0]].concat())
+        );
+        assert!(BitcoinBlock::<Mainnet>::from_bytes(&block_bytes[..block_bytes.len() - 1]).is_err());
+        assert_eq!(
+            Err(BlockError::UnsupportedProofOfWork(<Litecoin as chainlib_core::Network>::NAME)),
+            block.header.check_proof_of_work::<Litecoin>()
+        );
+    }
+
+    #[test]
+    fn validate_block() {
+        let coinbase = BitcoinTransaction::<Regtest>::from_bytes(&hex::decode(GENESIS_COINBASE).unwrap()).unwrap();
+        let transactions = vec![coinbase.clone(), spending(0), spending(1)];
+        let mut header = BlockHeader::from_bytes(&hex::decode(GENESIS_HEADER).unwrap()).unwrap();
+        header.bits = Regtest::pow_limit_bits().unwrap();
+        header.merkle_root = merkle_root(&BitcoinBlock { header, transactions: transactions.clone() }.transaction_ids().unwrap()).unwrap();
+        let header = mine(header);
+
+        let block = BitcoinBlock::<Regtest> { header, transactions: transactions.clone() };
+        assert_eq!(Ok(()), block.validate());
+        assert_eq!(block, BitcoinBlock::from_bytes(&block.to_bytes().unwrap()).unwrap());
+
+        // Duplicating the last transaction keeps the Merkle root
+        let mutated = BitcoinBlock::<Regtest> { header, transactions: [transactions.clone(), vec![spending(1)]].concat() };
+        assert_eq!(block.merkle_root(), mutated.merkle_root());
+        assert_eq!(Err(BlockError::InvalidBlock("duplicate transactions")), mutated.validate());
+
+        let block = BitcoinBlock::<Regtest> { header, transactions: vec![coinbase.clone(), spending(0), coinbase] };
+        assert_eq!(Err(BlockError::InvalidBlock("more than one coinbase transaction")), block.validate());
+        let block = BitcoinBlock::<Regtest> { header, transactions: vec![spending(0)] };
+        assert_eq!(Err(BlockError::InvalidBlock("first transaction is not a coinbase transaction")), block.validate());
+        let block = BitcoinBlock::<Regtest> { header, transactions: vec![] };
+        assert_eq!(Err(BlockError::InvalidBlock("no transactions")), block.validate());
+        let block = BitcoinBlock::<Regtest> { header, transactions: transactions[..2].to_vec() };
+        assert!(matches!(block.validate(), Err(BlockError::InvalidMerkleRoot(_, _))));
+    }
+
+    #[test]
+    fn header_chain() {
+        let mut headers = vec![BlockHeader::from_bytes(&hex::decode(GENESIS_HEADER).unwrap()).unwrap()];
+        for (header, block_hash) in HEADERS {
+            let header = BlockHeader::read(hex::decode(header).unwrap().as_slice()).unwrap();
+            assert_eq!(block_hash, header.block_hash().to_string());
+            assert_eq!(hex::decode(HEADERS.iter().find(|(_, h)| *h == block_hash).unwrap().0).unwrap(), header.to_bytes());
+            headers.push(header);
+        }
+        assert_eq!(Ok(()), validate_header_chain::<Mainnet>(&headers, 0));
+        assert_eq!(Ok(()), validate_header_chain::<Mainnet>(&headers[1..], 1));
+
+        let swapped = vec![headers[0], headers[2], headers[1]];
+        assert_eq!(Err(BlockError::InvalidPreviousBlock(1)), validate_header_chain::<Mainnet>(&swapped, 0));
+
+        let mut tampered = headers.clone();
+        tampered[2].nonce += 1;
+        assert!(matches!(
+            validate_header_chain::<Mainnet>(&tampered, 0),
+            Err(BlockError::InvalidProofOfWork(_))
+        ));
+        // Regtest blocks do not have enough work on mainnet
+        let mut regtest = headers[0];
+        regtest.bits = Regtest::pow_limit_bits().unwrap();
+        assert_eq!(Err(BlockError::InvalidBits(0x207fffff)), mine(regtest).check_proof_of_work::<Mainnet>());
+        assert!(BlockHeader::from_bytes(&[0u8; 79]).is_err());
+    }
+
+    #[test]
+    fn regtest_header_chain() {
+        let bits = Regtest::pow_limit_bits().unwrap();
+        let mut headers = vec![mine(BlockHeader {
+            version: 1,
+            previous_block_hash: Hash256::default(),
+            merkle_root: Hash256::hash(b"merkle root"),
+            time: 1_700_000_000,
+            bits,
+            nonce: 0,
+        })];
+        for index in 1..14 {
+            let previous = headers[index - 1];
+            headers.push(mine(BlockHeader { previous_block_hash: previous.block_hash(), time: previous.time + 600, nonce: 0, ..previous }));
+        }
+        assert_eq!(Ok(()), validate_header_chain::<Regtest>(&headers, 0));
+
+        let mut early = headers[..13].to_vec();
+        early.push(mine(BlockHeader { time: headers[7].time, ..headers[13] }));
+        assert_eq!(Err(BlockError::InvalidTime(13, headers[7].time)), validate_header_chain::<Regtest>(&early, 0));
+
+        let mut harder = headers[..13].to_vec();
+        harder.push(mine(BlockHeader { bits: 0x2070ffff, ..headers[13] }));
+        assert_eq!(Err(BlockError::UnexpectedBits(13, bits, 0x2070ffff)), validate_header_chain::<Regtest>(&harder, 0));
+    }
+
+    #[test]
+    fn min_difficulty_blocks() {
+        let pow_limit = Testnet::pow_limit_bits().unwrap();
+        let header = |time: u32, bits: u32| BlockHeader { version: 1, previous_block_hash: Hash256::default(), merkle_root: Hash256::default(), time, bits, nonce: 0 };
+        let previous = [header(0, 0x1c00ffff), header(600, pow_limit), header(1200, pow_limit)];
+
+        // After 20 minutes a testnet block may have the lowest difficulty, otherwise that of the last normal block
+        assert_eq!(Ok(Some(pow_limit)), next_bits::<Testnet>(&previous, 1, 2401));
+        assert_eq!(Ok(Some(0x1c00ffff)), next_bits::<Testnet>(&previous, 1, 2400));
+        assert_eq!(Ok(None), next_bits::<Testnet>(&previous[1..], 2, 2400));
+        assert_eq!(Ok(Some(pow_limit)), next_bits::<Testnet>(&previous[1..], 2016, 2400));
+        assert_eq!(Ok(Some(pow_limit)), next_bits::<Mainnet>(&previous, 1, 2401));
+        assert_eq!(Ok(None), next_bits::<Mainnet>(&previous, 2013, 2401));
+        assert_eq!(Ok(None), next_bits::<BitcoinCash>(&previous, 1, 2401));
+    }
+
+    #[test]
+    fn compact_targets() {
+        let target = |bits: u32| compact_to_target(bits).map(|target| hex::encode(target).trim_start_matches('0').to_string());
+        assert_eq!(Ok("12".to_string()), target(0x01123456));
+        assert_eq!(Ok("80".to_string()), target(0x02008000));
+        assert_eq!(Ok("92340000".to_string()), target(0x05009234));
+        assert_eq!(Ok("ffff0000000000000000000000000000000000000000000000000000".to_string()), target(0x1d00ffff));
+        assert_eq!(Ok("".to_string()), target(0x00123456));
+        assert_eq!(Err(BlockError::InvalidBits(0x04923456)), target(0x04923456));
+        assert_eq!(Err(BlockError::InvalidBits(0xff123456)), target(0xff123456));
+
+        for bits in [0x01120000, 0x02008000, 0x05009234, 0x1d00ffff, 0x1c05a3f4, 0x207fffff] {
+            assert_eq!(bits, target_to_compact(&compact_to_target(bits).unwrap()));
+        }
+    }
+
+    #[test]
+    fn retarget() {
+        let pow_limit = Mainnet::pow_limit_bits().unwrap();
+        assert_eq!(Ok(0x1d00d86a), calculate_next_bits(1261130161, 1262152739, 0x1d00ffff, pow_limit));
+        assert_eq!(Ok(0x1d00ffff), calculate_next_bits(1231006505, 1233061996, 0x1d00ffff, pow_limit));
+        assert_eq!(Ok(0x1c0168fd), calculate_next_bits(1279008237, 1279297671, 0x1c05a3f4, pow_limit));
+        assert_eq!(Ok(0x1d00e1fd), calculate_next_bits(1263163443, 1269211443, 0x1c387f6f, pow_limit));
+    }
+
+    #[test]
+    fn merkle_roots() {
+        let transaction_ids = [
+            hash("b1fea52486ce0c62bb442b530a3f0132b826c74e473d1f2c220bfa78111c5082"),
+            hash("f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16"),
+        ];
+        assert_eq!(
+            Some(hash("7dac2c5666815c17a3b36427de37bb9d2e2c5ccec3f8633eb91a4205cb4c10ff")),
+            merkle_root(&transaction_ids)
+        );
+        assert_eq!(Some(transaction_ids[0]), merkle_root(&transaction_ids[..1]));
+        assert_eq!(None, merkle_root(&[]));
+        assert!(Hash256::from_str("00").is_err());
+    }
+
+    #[test]
+    fn merkle_block() {
+        let bytes = hex::decode(MERKLE_BLOCK).unwrap();
+        let merkle_block = MerkleBlock::from_bytes(&bytes).unwrap();
+        assert_eq!(bytes, merkle_block.to_bytes().unwrap());
+        assert_eq!((7, 6, 16), (merkle_block.tree.transactions, merkle_block.tree.hashes.len(), merkle_block.tree.flags.len()));
+
+        let matched = [
+            hash("babb95b7a797b2e17dbc71c7b49dce0c15687d7704c03a4394fdeb40eaadc31c"),
+            hash("979b1e6bd6c8cb61e93666b677a187b2e5728625042ef7126835e240a343e488"),
+        ];
+        assert_eq!(Ok(vec![(2, matched[0]), (5, matched[1])]), merkle_block.extract_matches());
+        assert_eq!(Ok(true), merkle_block.contains(&matched[1]));
+        assert_eq!(Ok(false), merkle_block.contains(&Hash256::hash(&[0])));
+
+        // The proof is built back from all the transaction ids of the block
+        let transaction_ids = (0..7u8).map(|index| Hash256::hash(&[index])).collect::<Vec<_>>();
+        assert_eq!(Some(merkle_block.header.merkle_root), merkle_root(&transaction_ids));
+        let matches = (0..7).map(|index| index == 2 || index == 5).collect::<Vec<_>>();
+        let tree = PartialMerkleTree::new(&transaction_ids, &matches);
+        assert_eq!(bytes[80..], tree.to_bytes().unwrap()[..]);
+        for index in 0..7 {
+            let matches = (0..7).map(|i| i == index).collect::<Vec<_>>();
+            let (root, extracted) = PartialMerkleTree::new(&transaction_ids, &matches).extract_matches().unwrap();
+            assert_eq!((merkle_block.header.merkle_root, vec![(index, transaction_ids[index])]), (root, extracted));
+        }
+    }
+
+    #[test]
+    fn invalid_merkle_block() {
+        let merkle_block = MerkleBlock::from_bytes(&hex::decode(MERKLE_BLOCK).unwrap()).unwrap();
+
+        let mut tampered = merkle_block.clone();
+        tampered.tree.hashes[0].0[0] ^= 1;
+        assert!(matches!(tampered.extract_matches(), Err(BlockError::InvalidMerkleRoot(_, _))));
+        let mut tampered = merkle_block.clone();
+        tampered.header.merkle_root.0[0] ^= 1;
+        assert!(matches!(tampered.extract_matches(), Err(BlockError::InvalidMerkleRoot(_, _))));
+
+        let invalid = |tree: PartialMerkleTree| MerkleBlock { tree, ..merkle_block.clone() }.extract_matches();
+        let tree = merkle_block.tree.clone();
+        assert_eq!(Err(BlockError::InvalidPartialMerkleTree("no transactions")), invalid(PartialMerkleTree { transactions: 0, ..tree.clone() }));
+        assert_eq!(Err(BlockError::InvalidPartialMerkleTree("too many transactions")), invalid(PartialMerkleTree { transactions: u32::MAX, ..tree.clone() }));
+        assert_eq!(Err(BlockError::InvalidPartialMerkleTree("more hashes than transactions")), invalid(PartialMerkleTree { transactions: 5, ..tree.clone() }));
+        assert_eq!(Err(BlockError::InvalidPartialMerkleTree("unused hashes")), invalid(PartialMerkleTree { hashes: [tree.hashes.clone(), vec![Hash256::default()]].concat(), ..tree.clone() }));
+        assert_eq!(Err(BlockError::InvalidPartialMerkleTree("missing hashes")), invalid(PartialMerkleTree { hashes: tree.hashes[..5].to_vec(), ..tree.clone() }));
+        assert_eq!(Err(BlockError::InvalidPartialMerkleTree("unused flags")), invalid(PartialMerkleTree { flags: [tree.flags.clone(), vec![false; 8]].concat(), ..tree.clone() }));
+        assert_eq!(Err(BlockError::InvalidPartialMerkleTree("missing flags")), invalid(PartialMerkleTree { flags: tree.flags[..7].to_vec(), ..tree.clone() }));
+
+        // A proof of the duplicated last transaction of an odd level is rejected
+        let transaction_ids = (0..3u8).map(|index| Hash256::hash(&[index])).collect::<Vec<_>>();
+        let duplicated = [transaction_ids.clone(), vec![transaction_ids[2]]].concat();
+        let tree = PartialMerkleTree::new(&duplicated, &[false, false, false, true]);
+        assert_eq!(merkle_root(&transaction_ids), merkle_root(&duplicated));
+        assert_eq!(Err(BlockError::InvalidPartialMerkleTree("identical children")), tree.extract_matches());
+        assert!(MerkleBlock::from_bytes(&hex::decode(&MERKLE_BLOCK[..MERKLE_BLOCK.len() - 2]).unwrap()).is_err());
+    }
+}
@@ -0,0 +1,518 @@
+//! # Block Filter
+//!
+//! Compact block filters (BIP-158), Golomb-coded sets of the scripts a block pays to and spends from.
+//! A light client downloads the filter of each block and only fetches the blocks whose filter matches one of
+//! its scripts, without revealing them to the node serving the filters. Each filter commits to the previous
+//! one with a filter header, so filters served by different nodes can be checked against each other (BIP-157).
+//! https://github.com/bitcoin/bips/blob/master/bip-0158.mediawiki
+
+use crate::block::{BitcoinBlock, Hash256};
+use crate::network::BitcoinNetwork;
+use crate::transaction::{read_variable_length_integer, variable_length_integer};
+use chainlib_core::no_std::*;
+use chainlib_core::TransactionError;
+
+/// The Golomb-Rice parameter of basic filters, the number of bits of each remainder
+pub const BASIC_FILTER_P: u8 = 19;
+/// The inverse false positive rate of basic filters
+pub const BASIC_FILTER_M: u64 = 784931;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum BlockFilterError {
+    #[error("{0}: {1}")]
+    Crate(&'static str, String),
+
+    #[error("invalid filter: {0}")]
+    InvalidFilter(&'static str),
+
+    #[error("invalid number of previous output scripts: {{ expected: {0}, found: {1} }}")]
+    InvalidPrevouts(usize, usize),
+}
+
+impl From<TransactionError> for BlockFilterError {
+    fn from(error: TransactionError) -> Self {
+        BlockFilterError::Crate("Transaction", format!("{:?}", error))
+    }
+}
+
+impl From<BlockFilterError> for TransactionError {
+    fn from(error: BlockFilterError) -> Self {
+        TransactionError::Crate("BlockFilter", format!("{:?}", error))
+    }
+}
+
+/// Represents a Golomb-coded set, the sorted hashes of its elements mapped to the range [0, N * M),
+/// encoded as the Golomb-Rice codes of their differences
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GolombCodedSet {
+    /// The SipHash key of the elements
+    key: [u8; 16],
+    /// The number of bits of the remainder of each difference
+    p: u8,
+    /// The inverse false positive rate
+    m: u64,
+    /// The number of elements
+    n: u64,
+    /// The Golomb-Rice codes of the differences
+    data: Vec<u8>,
+}
+
+impl GolombCodedSet {
+    /// Returns the set of the distinct elements, hashed with the key.
+    pub fn new(key: [u8; 16], p: u8, m: u64, elements: &[Vec<u8>]) -> Result<Self, BlockFilterError> {
+        let mut elements = elements.iter().map(Vec::as_slice).collect::<Vec<_>>();
+        elements.sort_unstable();
+        elements.dedup();
+
+        let mut set = Self { key, p, m, n: elements.len() as u64, data: vec![] };
+        set.check_parameters()?;
+        let mut hashes = elements.iter().map(|element| set.hash_to_range(element)).collect::<Vec<_>>();
+        hashes.sort_unstable();
+
+        let mut writer = BitWriter::default();
+        let mut last = 0;
+        for hash in hashes {
+            let delta = hash - last;
+            for _ in 0..delta >> p {
+                writer.write(1, 1);
+            }
+            writer.write(0, 1);
+            writer.write(delta, p);
+            last = hash;
+        }
+        set.data = writer.bytes;
+        Ok(set)
+    }
+
+    /// Returns the set of the encoded bytes, which must hold exactly its number of elements.
+    pub fn from_bytes(key: [u8; 16], p: u8, m: u64, set: &[u8]) -> Result<Self, BlockFilterError> {
+        if set.is_empty() {
+            return Err(BlockFilterError::InvalidFilter("missing number of elements"));
+        }
+        let mut reader = set;
+        let n = read_variable_length_integer(&mut reader)? as u64;
+        let set = Self { key, p, m, n, data: reader.to_vec() };
+        set.check_parameters()?;
+
+        let mut reader = BitReader::new(&set.data);
+        for _ in 0..n {
+            reader.read_golomb_rice(p).ok_or(BlockFilterError::InvalidFilter("missing elements"))?;
+        }
+        // Only the padding of the last byte may be left unread
+        match reader.position.div_ceil(8) == set.data.len() {
+            true => Ok(set),
+            false => Err(BlockFilterError::InvalidFilter("trailing data")),
+        }
+    }
+
+    /// Returns the encoded set, its number of elements followed by their codes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, BlockFilterError> {
+        Ok([variable_length_integer(self.n)?, self.data.clone()].concat())
+    }
+
+    /// Returns the number of elements of the set.
+    pub fn len(&self) -> usize {
+        self.n as usize
+    }
+
+    /// Returns true if the set has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    /// Returns true if the element is probably in the set, with a false positive rate of 1/M.
+    pub fn contains(&self, element: &[u8]) -> bool {
+        self.match_hashes(&[self.hash_to_range(element)])
+    }
+
+    /// Returns true if any of the elements is probably in the set, decoding the set once for all of them.
+    pub fn contains_any(&self, elements: &[Vec<u8>]) -> bool {
+        let mut hashes = elements.iter().map(|element| self.hash_to_range(element)).collect::<Vec<_>>();
+        hashes.sort_unstable();
+        self.match_hashes(&hashes)
+    }
+
+    /// Checks that the number of elements and the range of their hashes fit in 64 bits, as in Bitcoin Core.
+    fn check_parameters(&self) -> Result<(), BlockFilterError> {
+        if self.p > 32 {
+            return Err(BlockFilterError::InvalidFilter("Golomb-Rice parameter above 32"));
+        }
+        if self.n > u32::MAX as u64 || self.m > u32::MAX as u64 {
+            return Err(BlockFilterError::InvalidFilter("too many elements"));
+        }
+        Ok(())
+    }
+
+    /// Returns the SipHash of the element mapped to the range [0, N * M).
+    fn hash_to_range(&self, element: &[u8]) -> u64 {
+        let hash = siphash_2_4(&self.key, element) as u128;
+        ((hash * (self.n * self.m) as u128) >> 64) as u64
+    }
+
+    /// Returns true if any of the sorted hashes is in the set, walking both in order.
+    fn match_hashes(&self, hashes: &[u64]) -> bool {
+        let mut reader = BitReader::new(&self.data);
+        let mut hashes = hashes.iter().peekable();
+        let mut value = 0u64;
+        for _ in 0..self.n {
+            value = match reader.read_golomb_rice(self.p) {
+                Some(delta) => value + delta,
+                None => return false,
+            };
+            while hashes.next_if(|hash| **hash < value).is_some() {}
+            match hashes.peek() {
+                Some(hash) if **hash == value => return true,
+                Some(_) => continue,
+                None => return false,
+            }
+        }
+        false
+    }
+}
+
+/// Writes bits from the most significant one of each byte
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    position: usize,
+}
+
+impl BitWriter {
+    /// Writes the given number of least significant bits of the value, the most significant first.
+    fn write(&mut self, value: u64, bits: u8) {
+        for bit in (0..bits).rev() {
+            if self.position.is_multiple_of(8) {
+                self.bytes.push(0);
+            }
+            let byte = self.bytes.len() - 1;
+            self.bytes[byte] |= ((value >> bit & 1) as u8) << (7 - self.position % 8);
+            self.position += 1;
+        }
+    }
+}
+
+/// Reads bits from the most significant one of each byte
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, position: 0 }
+    }
+
+    /// Returns the value of the given number of bits, or `None` past the end of the bytes.
+    fn read(&mut self, bits: u8) -> Option<u64> {
+        (0..bits).try_fold(0u64, |value, _| {
+            let byte = self.bytes.get(self.position / 8)?;
+            let bit = byte >> (7 - self.position % 8) & 1;
+            self.position += 1;
+            Some(value << 1 | bit as u64)
+        })
+    }
+
+    /// Returns the value of a Golomb-Rice code, a unary quotient and a remainder of `p` bits.
+    fn read_golomb_rice(&mut self, p: u8) -> Option<u64> {
+        let mut quotient = 0u64;
+        while self.read(1)? == 1 {
+            quotient += 1;
+        }
+        Some(quotient << p | self.read(p)?)
+    }
+}
+
+/// Returns the SipHash-2-4 of the data with the 128-bit key.
+fn siphash_2_4(key: &[u8; 16], data: &[u8]) -> u64 {
+    let k0 = u64::from_le_bytes(key[..8].try_into().unwrap());
+    let k1 = u64::from_le_bytes(key[8..].try_into().unwrap());
+    let mut v = [
+        k0 ^ 0x736f6d6570736575,
+        k1 ^ 0x646f72616e646f6d,
+        k0 ^ 0x6c7967656e657261,
+        k1 ^ 0x7465646279746573,
+    ];
+    let round = |v: &mut [u64; 4]| {
+        v[0] = v[0].wrapping_add(v[1]);
+        v[1] = v[1].rotate_left(13) ^ v[0];
+        v[0] = v[0].rotate_left(32);
+        v[2] = v[2].wrapping_add(v[3]);
+        v[3] = v[3].rotate_left(16) ^ v[2];
+        v[0] = v[0].wrapping_add(v[3]);
+        v[3] = v[3].rotate_left(21) ^ v[0];
+        v[2] = v[2].wrapping_add(v[1]);
+        v[1] = v[1].rotate_left(17) ^ v[2];
+        v[2] = v[2].rotate_left(32);
+    };
+    let compress = |v: &mut [u64; 4], word: u64| {
+        v[3] ^= word;
+        round(v);
+        round(v);
+        v[0] ^= word;
+    };
+
+    let chunks = data.chunks_exact(8);
+    // The last word holds the remaining bytes and the length of the data in its most significant byte
+    let mut last = [0u8; 8];
+    last[..chunks.remainder().len()].copy_from_slice(chunks.remainder());
+    last[7] = data.len() as u8;
+    for chunk in chunks {
+        compress(&mut v, u64::from_le_bytes(chunk.try_into().unwrap()));
+    }
+    compress(&mut v, u64::from_le_bytes(last));
+
+    v[2] ^= 0xff;
+    for _ in 0..4 {
+        round(&mut v);
+    }
+    v[0] ^ v[1] ^ v[2] ^ v[3]
+}
+
+/// Represents the basic filter of a block, keyed by its hash
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BlockFilter {
+    /// The hash of the block
+    pub block_hash: Hash256,
+    /// The set of the scripts of the block
+    pub filter: GolombCodedSet,
+}
+
+impl BlockFilter {
+    /// Returns the basic filter of the block, of the scripts of its outputs other than empty and
+    /// `OP_RETURN` scripts, and of the scripts of the outputs spent by its non-coinbase inputs, in any order.
+    pub fn basic<N: BitcoinNetwork>(block: &BitcoinBlock<N>, prevout_scripts: &[Vec<u8>]) -> Result<Self, BlockFilterError> {
+        let inputs = block
+            .transactions
+            .iter()
+            .filter(|transaction| !transaction.is_coinbase())
+            .map(|transaction| transaction.parameters.inputs.len())
+            .sum();
+        if prevout_scripts.len() != inputs {
+            return Err(BlockFilterError::InvalidPrevouts(inputs, prevout_scripts.len()));
+        }
+
+        let outputs = block.transactions.iter().flat_map(|transaction| &transaction.parameters.outputs);
+        let scripts = outputs
+            .map(|output| &output.script_pub_key)
+            .filter(|script| script.first().is_some_and(|opcode| *opcode != 0x6a))
+            .chain(prevout_scripts.iter().filter(|script| !script.is_empty()))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let block_hash = block.block_hash();
+        let filter = GolombCodedSet::new(Self::key(&block_hash), BASIC_FILTER_P, BASIC_FILTER_M, &scripts)?;
+        Ok(Self { block_hash, filter })
+    }
+
+    /// Returns the basic filter of the block of the given hash from its encoded bytes, as served in `cfilter` messages.
+    pub fn from_bytes(block_hash: Hash256, filter: &[u8]) -> Result<Self, BlockFilterError> {
+        let filter = GolombCodedSet::from_bytes(Self::key(&block_hash), BASIC_FILTER_P, BASIC_FILTER_M, filter)?;
+        Ok(Self { block_hash, filter })
+    }
+
+    /// Returns the encoded filter.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, BlockFilterError> {
+        self.filter.to_bytes()
+    }
+
+    /// Returns the hash of the encoded filter.
+    pub fn filter_hash(&self) -> Result<Hash256, BlockFilterError> {
+        Ok(Hash256::hash(&self.to_bytes()?))
+    }
+
+    /// Returns the filter header of the filter following the given previous filter header.
+    pub fn filter_header(&self, previous_filter_header: &Hash256) -> Result<Hash256, BlockFilterError> {
+        Ok(filter_header(&self.filter_hash()?, previous_filter_header))
+    }
+
+    /// Returns true if the block probably pays to or spends from any of the script public keys,
+    /// e.g. those of `create_script_pub_key` for the addresses of a wallet.
+    pub fn match_any(&self, script_pub_keys: &[Vec<u8>]) -> bool {
+        self.filter.contains_any(script_pub_keys)
+    }
+
+    /// Returns the SipHash key of a filter, the first 16 bytes of the block hash.
+    fn key(block_hash: &Hash256) -> [u8; 16] {
+        let mut key = [0u8; 16];
+        key.copy_from_slice(&block_hash.0[..16]);
+        key
+    }
+}
+
+/// Returns the filter header of a filter of the given hash, committing to the previous filter header.
+pub fn filter_header(filter_hash: &Hash256, previous_filter_header: &Hash256) -> Hash256 {
+    Hash256::hash(&[filter_hash.0, previous_filter_header.0].concat())
+}
+
+/// Returns the filter headers of consecutive filters of the given hashes, as in a `cfheaders` message,
+/// following the given previous filter header, zero before the genesis block.
+pub fn filter_headers(filter_hashes: &[Hash256], previous_filter_header: &Hash256) -> Vec<Hash256> {
+    filter_hashes
+        .iter()
+        .scan(*previous_filter_header, |header, filter_hash| {
+            *header = filter_header(filter_hash, header);
+            Some(*header)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::address::BitcoinAddress;
+    use crate::amount::BitcoinAmount;
+    use crate::network::*;
+    use crate::transaction::{create_script_pub_key, BitcoinTransaction, BitcoinTransactionOutput};
+    use chainlib_core::Transaction;
+    use core::str::FromStr;
+
+    const GENESIS_COINBASE: &str = "01000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000";
+    const GENESIS_SCRIPT: &str = "4104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac";
+
+    // https://github.com/bitcoin/bips/blob/master/bip-0158/testnet-19.json
+    const TESTNET_BLOCK_2: &str = "0100000006128e87be8b1b4dea47a7247d5528d2702c96826c7a648497e773b800000000e241352e3bec0a95a6217e10c3abb54adfa05abb12c126695595580fb92e222032e7494dffff001d00d235340101000000010000000000000000000000000000000000000000000000000000000000000000ffffffff0e0432e7494d010e062f503253482fffffffff0100f2052a010000002321038a7f6ef1c8ca0c588aa53fa860128077c9e6c11e6830f4d7ee4e763a56b7718fac00000000";
+    const TESTNET_BLOCK_1_FILTER_HEADER: &str = "d7bdac13a59d745b1add0d2ce852f1a0442e8945fc1bf3848d3cbffd88c24fe1";
+
+    fn genesis_block<N: BitcoinNetwork>(time_bits_nonce: &str) -> BitcoinBlock<N> {
+        let header = format!(
+            "0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a{}",
+            time_bits_nonce
+        );
+        BitcoinBlock::from_bytes(&hex::decode(format!("{}01{}", header, GENESIS_COINBASE)).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn siphash() {
+        let key: [u8; 16] = core::array::from_fn(|i| i as u8);
+        assert_eq!(0x726fdb47dd0e0e31, siphash_2_4(&key, &[]));
+        assert_eq!(0xa129ca6149be45e5, siphash_2_4(&key, &(0..15).collect::<Vec<u8>>()));
+    }
+
+    #[test]
+    fn golomb_coded_set() {
+        let key: [u8; 16] = core::array::from_fn(|i| i as u8);
+        let elements = (0..40u8).map(|i| vec![i; i as usize % 7 + 1]).collect::<Vec<_>>();
+
+        // Duplicate elements are encoded once
+        let set = GolombCodedSet::new(key, BASIC_FILTER_P, BASIC_FILTER_M, &[elements.clone(), elements[..3].to_vec()].concat()).unwrap();
+        assert_eq!(set, GolombCodedSet::from_bytes(key, BASIC_FILTER_P, BASIC_FILTER_M, &set.to_bytes().unwrap()).unwrap());
+        assert_eq!(40, set.len());
+        assert!(elements.iter().all(|element| set.contains(element)));
+        assert!(!set.contains(b"absent"));
+        assert!(set.contains_any(&[b"absent".to_vec(), elements[39].clone()]));
+        assert!(!set.contains_any(&[b"absent".to_vec()]));
+        assert!(!set.contains_any(&[]));
+
+        let empty = GolombCodedSet::new(key, BASIC_FILTER_P, BASIC_FILTER_M, &[]).unwrap();
+        assert!(empty.is_empty());
+        assert_eq!(vec![0], empty.to_bytes().unwrap());
+        assert!(!empty.contains(b""));
+
+        // The basic filter of the BIP-158 testnet block 2
+        let encoded = hex::decode("0174a170").unwrap();
+        let set = GolombCodedSet::from_bytes(key, BASIC_FILTER_P, BASIC_FILTER_M, &encoded).unwrap();
+        assert_eq!(1, set.len());
+        assert_eq!(encoded, set.to_bytes().unwrap());
+
+        let invalid = |set: &[u8]| GolombCodedSet::from_bytes(key, BASIC_FILTER_P, BASIC_FILTER_M, set);
+        assert_eq!(Err(BlockFilterError::InvalidFilter("missing number of elements")), invalid(&[]));
+        assert_eq!(Err(BlockFilterError::InvalidFilter("missing elements")), invalid(&encoded[..encoded.len() - 1]));
+        assert_eq!(Err(BlockFilterError::InvalidFilter("trailing data")), invalid(&[encoded.clone(), vec![0]].concat()));
+        assert_eq!(Err(BlockFilterError::InvalidFilter("missing elements")), invalid(&[0xfe, 0xff, 0xff, 0xff, 0xff, 0xff]));
+    }
+
+    #[test]
+    fn testnet_genesis_filter() {
+        // The first test vector of BIP-158
+        let block = genesis_block::<Testnet>("dae5494dffff001d1aa4ae18");
+        assert_eq!("000000000933ea01ad0ee984209779baaec3ced90fa3f408719526f8d77f4943", block.block_hash().to_string());
+
+        let filter = BlockFilter::basic(&block, &[]).unwrap();
+        assert_eq!("019dfca8", hex::encode(filter.to_bytes().unwrap()));
+        assert_eq!(filter, BlockFilter::from_bytes(block.block_hash(), &hex::decode("019dfca8").unwrap()).unwrap());
+        assert_eq!(
+            "21584579b7eb08997773e5aeff3a7f932700042d0ed2a6129012b7d7ae81b750",
+            filter.filter_header(&Hash256::default()).unwrap().to_string()
+        );
+        assert!(filter.match_any(&[hex::decode(GENESIS_SCRIPT).unwrap()]));
+        assert_eq!(Err(BlockFilterError::InvalidPrevouts(0, 1)), BlockFilter::basic(&block, &[vec![]]));
+    }
+
+    #[test]
+    fn testnet_block_filter() {
+        // The test vector of BIP-158 for the testnet block 2, paying its coinbase to a compressed key
+        let block = BitcoinBlock::<Testnet>::from_bytes(&hex::decode(TESTNET_BLOCK_2).unwrap()).unwrap();
+        assert_eq!("000000006c02c8ea6e4ff69651f7fcde348fb9d557a06e6957b65552002a7820", block.block_hash().to_string());
+
+        let filter = BlockFilter::basic(&block, &[]).unwrap();
+        assert_eq!("0174a170", hex::encode(filter.to_bytes().unwrap()));
+        assert_eq!(
+            "186afd11ef2b5e7e3504f2e8cbf8df28a1fd251fe53d60dff8b1467d1b386cf0",
+            filter.filter_header(&Hash256::from_str(TESTNET_BLOCK_1_FILTER_HEADER).unwrap()).unwrap().to_string()
+        );
+        assert!(filter.match_any(&[block.transactions[0].parameters.outputs[0].script_pub_key.clone()]));
+        assert!(!filter.match_any(&[hex::decode(GENESIS_SCRIPT).unwrap()]));
+    }
+
+    // The genesis block followed by a transaction spending a P2WPKH output into the genesis script,
+    // an empty script and an OP_RETURN output
+    fn spending_block() -> (BitcoinBlock<Mainnet>, Vec<Vec<u8>>) {
+        type N = Mainnet;
+        let mut block = genesis_block::<N>("29ab5f49ffff001d1dac2b7c");
+        let mut spending = block.transactions[0].to_bytes().unwrap();
+        spending[37..41].copy_from_slice(&0u32.to_le_bytes());
+        let mut spending = BitcoinTransaction::<N>::from_bytes(&spending).unwrap();
+        for script_pub_key in [vec![], vec![0x6a, 0x01, 0x00]] {
+            spending.parameters.outputs.push(BitcoinTransactionOutput { amount: BitcoinAmount(0), script_pub_key });
+        }
+        block.transactions.push(spending);
+
+        let witness_address = BitcoinAddress::<N>::from_str("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4").unwrap();
+        (block, vec![create_script_pub_key(&witness_address).unwrap()])
+    }
+
+    #[test]
+    fn basic_filter() {
+        type N = Mainnet;
+        let (block, prevout_scripts) = spending_block();
+        let filter = BlockFilter::basic(&block, &prevout_scripts).unwrap();
+
+        // The filter holds the distinct output and prevout scripts, without empty and OP_RETURN scripts
+        assert_eq!(2, filter.filter.len());
+        assert!(filter.match_any(&[hex::decode(GENESIS_SCRIPT).unwrap()]));
+        assert_eq!(filter, BlockFilter::from_bytes(block.block_hash(), &filter.to_bytes().unwrap()).unwrap());
+
+        let address = BitcoinAddress::<N>::from_str("1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2").unwrap();
+        let script_pub_key = create_script_pub_key(&address).unwrap();
+        assert!(filter.match_any(&[script_pub_key.clone(), prevout_scripts[0].clone()]));
+        assert!(!filter.match_any(&[script_pub_key]));
+        assert!(!filter.match_any(&[vec![0x6a, 0x01, 0x00], vec![]]));
+        assert_eq!(Err(BlockFilterError::InvalidPrevouts(1, 0)), BlockFilter::basic(&block, &[]));
+    }
+
+    #[test]
+    fn filter_header_chain() {
+        // The block hashes and basic filters of the BIP-158 testnet blocks 2 and 3
+        let filters = [
+            ("000000006c02c8ea6e4ff69651f7fcde348fb9d557a06e6957b65552002a7820", "0174a170"),
+            ("000000008b896e272758da5297bcd98fdc6d97c9b765ecec401e286dc1fdbe10", "016cf7a0"),
+        ];
+        let filter_hashes = filters
+            .iter()
+            .map(|(block_hash, filter)| {
+                let filter = BlockFilter::from_bytes(Hash256::from_str(block_hash).unwrap(), &hex::decode(filter).unwrap()).unwrap();
+                filter.filter_hash().unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        let headers = filter_headers(&filter_hashes, &Hash256::from_str(TESTNET_BLOCK_1_FILTER_HEADER).unwrap());
+        assert_eq!(
+            vec![
+                Hash256::from_str("186afd11ef2b5e7e3504f2e8cbf8df28a1fd251fe53d60dff8b1467d1b386cf0").unwrap(),
+                Hash256::from_str("8d63aadf5ab7257cb6d2316a57b16f517bff1c6388f124ec4c04af1212729d2a").unwrap(),
+            ],
+            headers
+        );
+        assert!(filter_headers(&[], &headers[1]).is_empty());
+    }
+}
@@ -0,0 +1,287 @@
+//!
+//! CashAddr
+//!
+//! This module contains the CashAddr encoding of Bitcoin Cash addresses, e.g.
+//! `bitcoincash:qpm2qsznhks23z7629mms6s4cwef74vcwvy22gdx6a`.
+//!
+//! A CashAddr address is a network prefix followed by a separator and a Base32 payload:
+//! - The payload is a version byte followed by the hash, padded to a multiple of 5 bits.
+//! - The version byte is a reserved bit, 4 type bits (0 for P2PKH, 1 for P2SH) and 3 size bits of the hash.
+//! - The payload ends with a 40-bit BCH checksum of the prefix and payload.
+//! - The prefix may be omitted when it is implied by the network, and the address must not be mixed case.
+//!
+//! https://github.com/bitcoincashorg/bitcoincash.org/blob/master/spec/cashaddr.md
+//!
+
+use crate::format::BitcoinFormat;
+use chainlib_core::no_std::*;
+use chainlib_core::AddressError;
+
+use bech32::{u5, FromBase32, ToBase32};
+use core::fmt;
+
+/// The Base32 alphabet of CashAddr, shared with Bech32
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+/// The generators of the BCH code of the checksum
+const GENERATORS: [u64; 5] = [0x98f2bc8e61, 0x79b76d99e2, 0xf33e5fb3c4, 0xae2eabe2a8, 0x1e4f43e470];
+
+/// The number of 5-bit groups of the checksum
+const CHECKSUM_LENGTH: usize = 8;
+
+/// The sizes of the hash by the size bits of the version byte
+const HASH_SIZES: [usize; 8] = [20, 24, 28, 32, 40, 48, 56, 64];
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum CashAddrError {
+    #[error("invalid character {0:?}")]
+    InvalidCharacter(char),
+
+    #[error("invalid checksum: {{ expected: {0}, found: {1} }}")]
+    InvalidChecksum(String, String),
+
+    #[error("invalid hash length {0}")]
+    InvalidHashLength(usize),
+
+    #[error("invalid payload length {0}")]
+    InvalidPayloadLength(usize),
+
+    #[error("invalid padding of the payload")]
+    InvalidPadding,
+
+    #[error("invalid version byte {0:#04x}")]
+    InvalidVersion(u8),
+
+    #[error("mixed case address")]
+    MixedCase,
+
+    #[error("{0} has no CashAddr address")]
+    UnsupportedFormat(BitcoinFormat),
+}
+
+impl From<CashAddrError> for AddressError {
+    fn from(error: CashAddrError) -> Self {
+        AddressError::Crate("CashAddr", format!("{:?}", error))
+    }
+}
+
+/// Represents a CashAddr address
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CashAddress {
+    /// The network prefix, in lowercase
+    pub prefix: String,
+    /// The format of the address, P2PKH or P2SH
+    pub format: BitcoinFormat,
+    /// The public key hash or script hash
+    pub hash: Vec<u8>,
+}
+
+impl CashAddress {
+    /// Returns a new CashAddr address of the given public key hash (P2PKH) or script hash (P2SH).
+    pub fn new(prefix: &str, format: BitcoinFormat, hash: &[u8]) -> Result<Self, CashAddrError> {
+        let _ = Self::version(&format, hash.len())?;
+        Ok(Self {
+            prefix: prefix.to_lowercase(),
+            format,
+            hash: hash.to_vec(),
+        })
+    }
+
+    /// Returns the CashAddr address decoded from the given string, which may omit the default prefix.
+    pub fn decode(address: &str, default_prefix: &str) -> Result<Self, CashAddrError> {
+        if address.chars().any(|c| c.is_ascii_lowercase()) && address.chars().any(|c| c.is_ascii_uppercase()) {
+            return Err(CashAddrError::MixedCase);
+        }
+        let address = address.to_lowercase();
+        let (prefix, payload) = match address.rfind(':') {
+            Some(separator) => (&address[..separator], &address[separator + 1..]),
+            None => (default_prefix, &address[..]),
+        };
+
+        let data = payload
+            .chars()
+            .map(|c| match CHARSET.iter().position(|&d| d as char == c) {
+                Some(value) => Ok(u5::try_from_u8(value as u8).unwrap()),
+                None => Err(CashAddrError::InvalidCharacter(c)),
+            })
+            .collect::<Result<Vec<u5>, CashAddrError>>()?;
+        if data.len() <= CHECKSUM_LENGTH {
+            return Err(CashAddrError::InvalidPayloadLength(data.len()));
+        }
+
+        let (data, checksum) = data.split_at(data.len() - CHECKSUM_LENGTH);
+        let expected = create_checksum(prefix, data);
+        if checksum != expected.as_slice() {
+            return Err(CashAddrError::InvalidChecksum(to_string(&expected), to_string(checksum)));
+        }
+
+        let payload = Vec::<u8>::from_base32(data).map_err(|_| CashAddrError::InvalidPadding)?;
+        let (version, hash) = match payload.split_first() {
+            Some((version, hash)) => (*version, hash),
+            None => return Err(CashAddrError::InvalidPayloadLength(0)),
+        };
+        let format = match version >> 3 {
+            0 => BitcoinFormat::P2PKH,
+            1 => BitcoinFormat::P2SH,
+            _ => return Err(CashAddrError::InvalidVersion(version)),
+        };
+        if HASH_SIZES[(version & 0x07) as usize] != hash.len() {
+            return Err(CashAddrError::InvalidHashLength(hash.len()));
+        }
+
+        Self::new(prefix, format, hash)
+    }
+
+    /// Returns the version byte of the type and hash size of an address.
+    fn version(format: &BitcoinFormat, hash_length: usize) -> Result<u8, CashAddrError> {
+        let address_type = match format {
+            BitcoinFormat::P2PKH => 0,
+            BitcoinFormat::P2SH => 1,
+            format => return Err(CashAddrError::UnsupportedFormat(format.clone())),
+        };
+        match HASH_SIZES.iter().position(|&size| size == hash_length) {
+            Some(size) => Ok(address_type << 3 | size as u8),
+            None => Err(CashAddrError::InvalidHashLength(hash_length)),
+        }
+    }
+}
+
+impl fmt::Display for CashAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let version = Self::version(&self.format, self.hash.len()).map_err(|_| fmt::Error)?;
+        let data = [vec![version], self.hash.clone()].concat().to_base32();
+        let checksum = create_checksum(&self.prefix, &data);
+        write!(f, "{}:{}{}", self.prefix, to_string(&data), to_string(&checksum))
+    }
+}
+
+/// Returns the checksum of the payload under the prefix, of which only the lower 5 bits of each character are used.
+fn create_checksum(prefix: &str, data: &[u5]) -> Vec<u5> {
+    let mut values: Vec<u8> = prefix.bytes().map(|c| c & 0x1f).collect();
+    values.push(0);
+    values.extend(data.iter().map(|value| value.to_u8()));
+    values.extend([0u8; CHECKSUM_LENGTH]);
+
+    let polymod = polymod(&values);
+    (0..CHECKSUM_LENGTH)
+        .map(|i| u5::try_from_u8(((polymod >> (5 * (CHECKSUM_LENGTH - 1 - i))) & 0x1f) as u8).unwrap())
+        .collect()
+}
+
+/// Returns the remainder of the values as a polynomial over GF(32) modulo the generator of the code.
+fn polymod(values: &[u8]) -> u64 {
+    let mut checksum = 1u64;
+    for value in values {
+        let top = checksum >> 35;
+        checksum = ((checksum & 0x07_ffff_ffff) << 5) ^ *value as u64;
+        for (i, generator) in GENERATORS.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                checksum ^= generator;
+            }
+        }
+    }
+    checksum ^ 1
+}
+
+/// Returns the characters of the 5-bit values.
+fn to_string(data: &[u5]) -> String {
+    data.iter().map(|value| CHARSET[value.to_u8() as usize] as char).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_round_trip(address: &str, format: BitcoinFormat, hash: &str) {
+        let cash_address = CashAddress::decode(address, "bitcoincash").unwrap();
+        assert_eq!(format, cash_address.format);
+        assert_eq!(hash, hex::encode(&cash_address.hash));
+        assert_eq!(address, cash_address.to_string());
+
+        let prefix = &address[..address.find(':').unwrap()];
+        let cash_address = CashAddress::new(prefix, format, &hex::decode(hash).unwrap()).unwrap();
+        assert_eq!(address, cash_address.to_string());
+    }
+
+    #[test]
+    fn valid() {
+        const ADDRESSES: [(&str, BitcoinFormat, &str); 6] = [
+            (
+                "bitcoincash:qpm2qsznhks23z7629mms6s4cwef74vcwvy22gdx6a",
+                BitcoinFormat::P2PKH,
+                "76a04053bda0a88bda5177b86a15c3b29f559873",
+            ),
+            (
+                "bitcoincash:qr95sy3j9xwd2ap32xkykttr4cvcu7as4y0qverfuy",
+                BitcoinFormat::P2PKH,
+                "cb481232299cd5743151ac4b2d63ae198e7bb0a9",
+            ),
+            (
+                "bitcoincash:ppm2qsznhks23z7629mms6s4cwef74vcwvn0h829pq",
+                BitcoinFormat::P2SH,
+                "76a04053bda0a88bda5177b86a15c3b29f559873",
+            ),
+            (
+                "bchtest:pr6m7j9njldwwzlg9v7v53unlr4jkmx6eyvwc0uz5t",
+                BitcoinFormat::P2SH,
+                "f5bf48b397dae70be82b3cca4793f8eb2b6cdac9",
+            ),
+            (
+                "bitcoincash:pvqqzqsrqszsvpcgpy9qkrqdpc83qygjzv2p29shrqv35xcur50p7h2c7ctj5",
+                BitcoinFormat::P2SH,
+                "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+            ),
+            (
+                "bchtest:quqqzqsrqszsvpcgpy9qkrqdpc83qygjzv2p29shrqv35xcur50p7gppyg3jgffxyu5zj23t9skjutesxyerxdp4xcmnswf68v7r603lln37l7xe",
+                BitcoinFormat::P2PKH,
+                "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f\
+                 202122232425262728292a2b2c2d2e2f303132333435363738393a3b3c3d3e3f",
+            ),
+        ];
+        ADDRESSES
+            .iter()
+            .for_each(|(address, format, hash)| test_round_trip(address, format.clone(), hash));
+    }
+
+    #[test]
+    fn default_prefix() {
+        let address = "bitcoincash:qr6m7j9njldwwzlg9v7v53unlr4jkmx6eylep8ekg2";
+        let cash_address = CashAddress::decode("qr6m7j9njldwwzlg9v7v53unlr4jkmx6eylep8ekg2", "bitcoincash").unwrap();
+        assert_eq!(address, cash_address.to_string());
+
+        let cash_address = CashAddress::decode(&address.to_uppercase(), "bchtest").unwrap();
+        assert_eq!(address, cash_address.to_string());
+
+        // The checksum commits to the prefix
+        let error = CashAddress::decode("qr6m7j9njldwwzlg9v7v53unlr4jkmx6eylep8ekg2", "bchtest").unwrap_err();
+        assert!(matches!(error, CashAddrError::InvalidChecksum(_, _)));
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(
+            CashAddrError::MixedCase,
+            CashAddress::decode("bitcoincash:qpm2qsznhks23z7629mms6s4cwef74vcwvY22gdx6a", "bitcoincash").unwrap_err()
+        );
+        assert_eq!(
+            CashAddrError::InvalidCharacter('b'),
+            CashAddress::decode("bitcoincash:qpm2qsznhks23z7629mms6s4cwef74vcwvb22gdx6a", "bitcoincash").unwrap_err()
+        );
+        assert!(matches!(
+            CashAddress::decode("bitcoincash:qpm2qsznhks23z7629mms6s4cwef74vcwvy22gdx6q", "bitcoincash"),
+            Err(CashAddrError::InvalidChecksum(_, _))
+        ));
+        assert_eq!(
+            CashAddrError::InvalidPayloadLength(8),
+            CashAddress::decode("bitcoincash:y22gdx6a", "bitcoincash").unwrap_err()
+        );
+        assert_eq!(
+            CashAddrError::UnsupportedFormat(BitcoinFormat::Bech32),
+            CashAddress::new("bitcoincash", BitcoinFormat::Bech32, &[0u8; 20]).unwrap_err()
+        );
+        assert_eq!(
+            CashAddrError::InvalidHashLength(21),
+            CashAddress::new("bitcoincash", BitcoinFormat::P2PKH, &[0u8; 21]).unwrap_err()
+        );
+    }
+}
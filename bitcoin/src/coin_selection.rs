@@ -0,0 +1,746 @@
+//! # Coin Selection
+//!
+//! Chooses the unspent outputs that fund a set of outputs at a fee rate and computes the change.
+//! Every candidate is valued at its effective value, its amount less the fee of spending it, so
+//! that an input never costs more than it contributes. The strategies follow Bitcoin Core:
+//! Branch-and-Bound searches for a changeless selection of the least waste, the knapsack solver
+//! approximates the smallest selection above the target, largest-first spends the largest outputs
+//! and single-random-draw spends outputs at random until the target and a minimum change are met.
+//! The waste of a selection is the fee paid by its inputs above the long-term fee rate, plus the
+//! cost of creating and spending the change, or the excess given up to the fee without change.
+
+use crate::address::BitcoinAddress;
+use crate::amount::BitcoinAmount;
+use crate::format::BitcoinFormat;
+use crate::network::BitcoinNetwork;
+use crate::script::Script;
+use crate::transaction::{
+    variable_length_integer, BitcoinTransactionInput, BitcoinTransactionOutput, BitcoinTransactionParameters,
+};
+use chainlib_core::no_std::*;
+use chainlib_core::TransactionError;
+
+use core::cmp::Reverse;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+/// The default fee rate of relaying an output on Bitcoin, in satoshi per virtual byte, below which it is dust,
+/// see `BitcoinNetwork::dust_relay_fee_rate` for other networks
+pub const DEFAULT_DUST_RELAY_FEE_RATE: u64 = 3;
+/// The default fee rate expected to spend outputs in the long term, in satoshi per virtual byte
+pub const DEFAULT_LONG_TERM_FEE_RATE: u64 = 10;
+/// The number of selections Branch-and-Bound explores before giving up
+const BNB_TOTAL_TRIES: usize = 100_000;
+/// The number of random subsets the knapsack solver approximates the target with
+const KNAPSACK_ITERATIONS: usize = 1_000;
+/// The change the knapsack solver aims for when no subset matches the target
+const MIN_CHANGE: i64 = 1_000_000;
+/// The change single-random-draw aims for in addition to the target
+const CHANGE_LOWER: i64 = 50_000;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum CoinSelectionError {
+    #[error("insufficient funds: {0} available for a target of {1}")]
+    InsufficientFunds(i64, i64),
+
+    #[error("no changeless selection within the cost of change")]
+    NoChangelessSolution,
+
+    #[error("unsupported change address format {0}")]
+    UnsupportedChangeAddress(String),
+}
+
+impl From<CoinSelectionError> for TransactionError {
+    fn from(error: CoinSelectionError) -> Self {
+        TransactionError::Crate("CoinSelection", format!("{:?}", error))
+    }
+}
+
+/// Represents a coin selection algorithm
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CoinSelectionStrategy {
+    /// Searches for the changeless selection of the least waste
+    BranchAndBound,
+    /// Approximates the smallest selection above the target with random subsets
+    Knapsack,
+    /// Spends the outputs of the largest effective value first
+    LargestFirst,
+    /// Spends outputs in random order until the target and a minimum change are met
+    SingleRandomDraw,
+}
+
+/// Represents an input with the value it contributes and the fees of spending it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Candidate {
+    /// The index of the input
+    index: usize,
+    /// The amount of the outpoint in satoshi
+    value: i64,
+    /// The fee of spending the input at the fee rate
+    fee: i64,
+    /// The fee of spending the input at the long-term fee rate
+    long_term_fee: i64,
+}
+
+impl Candidate {
+    /// Returns the amount less the fee of spending the input.
+    fn effective_value(&self) -> i64 {
+        self.value - self.fee
+    }
+
+    /// Returns the fee paid above the long-term fee rate.
+    fn waste(&self) -> i64 {
+        self.fee - self.long_term_fee
+    }
+}
+
+/// Represents the inputs chosen to fund the outputs and the change they leave
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoinSelection<N: BitcoinNetwork> {
+    /// The inputs to spend, starting with the inputs that must be spent
+    pub inputs: Vec<BitcoinTransactionInput<N>>,
+    /// The change output, unless the excess is below the dust threshold and given up to the fee
+    pub change: Option<BitcoinTransactionOutput>,
+    /// The fee paid by the transaction
+    pub fee: BitcoinAmount,
+    /// The waste of the selection in satoshi
+    pub waste: i64,
+}
+
+impl<N: BitcoinNetwork> CoinSelection<N> {
+    /// Returns the parameters of a transaction spending the inputs to the outputs followed by the change.
+    pub fn transaction_parameters(
+        &self,
+        outputs: Vec<BitcoinTransactionOutput>,
+    ) -> Result<BitcoinTransactionParameters<N>, TransactionError> {
+        let outputs = outputs.into_iter().chain(self.change.clone()).collect();
+        BitcoinTransactionParameters::<N>::new(self.inputs.clone(), outputs)
+    }
+}
+
+/// Represents the unspent outputs to select from and the fee rates to select them at
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoinSelector<N: BitcoinNetwork> {
+    candidates: Vec<BitcoinTransactionInput<N>>,
+    must_spend: Vec<BitcoinTransactionInput<N>>,
+    change_address: BitcoinAddress<N>,
+    fee_rate: u64,
+    long_term_fee_rate: u64,
+    dust_relay_fee_rate: u64,
+}
+
+impl<N: BitcoinNetwork> CoinSelector<N> {
+    /// Returns a selector without candidates at a fee rate in satoshi per virtual byte,
+    /// paying the change to the given address.
+    pub fn new(change_address: BitcoinAddress<N>, fee_rate: u64) -> Self {
+        Self {
+            candidates: vec![],
+            must_spend: vec![],
+            change_address,
+            fee_rate,
+            long_term_fee_rate: DEFAULT_LONG_TERM_FEE_RATE,
+            dust_relay_fee_rate: N::dust_relay_fee_rate(),
+        }
+    }
+
+    /// Adds an input that may be spent. Its outpoint needs an amount and an address.
+    pub fn candidate(mut self, input: BitcoinTransactionInput<N>) -> Self {
+        self.candidates.push(input);
+        self
+    }
+
+    /// Adds the inputs that may be spent.
+    pub fn candidates(mut self, inputs: Vec<BitcoinTransactionInput<N>>) -> Self {
+        self.candidates.extend(inputs);
+        self
+    }
+
+    /// Adds an input that is spent by every selection.
+    pub fn must_spend(mut self, input: BitcoinTransactionInput<N>) -> Self {
+        self.must_spend.push(input);
+        self
+    }
+
+    /// Sets the fee rate expected to spend outputs in the long term, in satoshi per virtual byte.
+    pub fn long_term_fee_rate(mut self, long_term_fee_rate: u64) -> Self {
+        self.long_term_fee_rate = long_term_fee_rate;
+        self
+    }
+
+    /// Sets the fee rate of relaying an output, in satoshi per virtual byte, below which the change is dust.
+    pub fn dust_relay_fee_rate(mut self, dust_relay_fee_rate: u64) -> Self {
+        self.dust_relay_fee_rate = dust_relay_fee_rate;
+        self
+    }
+
+    /// Returns the inputs funding the outputs at the fee rate with the given strategy,
+    /// and the change output if the excess is above the dust threshold.
+    pub fn select<R: Rng>(
+        &self,
+        outputs: &[BitcoinTransactionOutput],
+        strategy: CoinSelectionStrategy,
+        rng: &mut R,
+    ) -> Result<CoinSelection<N>, TransactionError> {
+        let change = BitcoinTransactionOutput::new(&self.change_address, BitcoinAmount::ZERO)?;
+        let change_fee = fee(change.serialize()?.len() as u64 * 4, self.fee_rate);
+        let change_spend_fee = fee(self.change_spend_weight()?, self.long_term_fee_rate);
+        let cost_of_change = change_fee + change_spend_fee;
+        let dust = dust_threshold(&change, self.dust_relay_fee_rate).0.max(N::dust_limit());
+
+        // Every input pays for an empty witness once any of them has a witness
+        let mut sizes = vec![];
+        for input in self.must_spend.iter().chain(&self.candidates) {
+            let value = input.outpoint.amount.ok_or(TransactionError::MissingOutpointAmount)?.0;
+            let (script_sig, witness) = input.estimate_size()?;
+            let base = input.outpoint.reverse_transaction_id.len()
+                + 4
+                + input.sequence.len()
+                + variable_length_integer(script_sig as u64)?.len()
+                + script_sig;
+            sizes.push((value, base as u64 * 4, witness as u64));
+        }
+        let segwit = sizes.iter().any(|(_, _, witness)| *witness > 0);
+        let mut candidates = sizes.iter().enumerate().map(|(index, (value, base, witness))| {
+            let weight = base + if segwit { (*witness).max(1) } else { 0 };
+            Candidate {
+                index,
+                value: *value,
+                fee: fee(weight, self.fee_rate),
+                long_term_fee: fee(weight, self.long_term_fee_rate),
+            }
+        });
+        let preset: Vec<Candidate> = candidates.by_ref().take(self.must_spend.len()).collect();
+        let pool: Vec<Candidate> = candidates.filter(|candidate| candidate.effective_value() > 0).collect();
+
+        // The version, the lock time, the input and output counts, the outputs and the segwit marker and flag
+        let mut base = 4 + 4 + 1 + variable_length_integer(outputs.len() as u64 + 1)?.len() as u64;
+        for output in outputs {
+            base += output.serialize()?.len() as u64;
+        }
+        let not_input_weight = base * 4 + if segwit { 2 } else { 0 };
+        let total_output = outputs.iter().map(|output| output.amount.0).sum::<i64>();
+        let target = total_output + fee(not_input_weight, self.fee_rate);
+
+        let preset_value = preset.iter().map(Candidate::effective_value).sum::<i64>();
+        let remaining = target - preset_value;
+        let (selected, changeless) = match remaining > 0 {
+            true => {
+                let available = pool.iter().map(Candidate::effective_value).sum::<i64>();
+                if available < remaining {
+                    return Err(CoinSelectionError::InsufficientFunds(preset_value + available, target).into());
+                }
+                match strategy {
+                    CoinSelectionStrategy::BranchAndBound => match branch_and_bound(&pool, remaining, cost_of_change) {
+                        Some(selected) => (selected, true),
+                        None => return Err(CoinSelectionError::NoChangelessSolution.into()),
+                    },
+                    CoinSelectionStrategy::Knapsack => (knapsack(&pool, remaining + change_fee, rng), false),
+                    CoinSelectionStrategy::LargestFirst => (largest_first(&pool, remaining), false),
+                    CoinSelectionStrategy::SingleRandomDraw => {
+                        match single_random_draw(&pool, remaining + change_fee + CHANGE_LOWER, rng) {
+                            Some(selected) => (selected, false),
+                            None => {
+                                let required = target + change_fee + CHANGE_LOWER;
+                                return Err(CoinSelectionError::InsufficientFunds(preset_value + available, required).into());
+                            }
+                        }
+                    }
+                }
+            }
+            false => (vec![], false),
+        };
+
+        let selection: Vec<&Candidate> = preset.iter().chain(selected.iter().map(|index| &pool[*index])).collect();
+        let excess = selection.iter().map(|candidate| candidate.effective_value()).sum::<i64>() - target;
+        let change_value = excess - change_fee;
+        let change = match !changeless && change_value >= dust {
+            true => Some(BitcoinTransactionOutput {
+                amount: BitcoinAmount::from_satoshi_for::<N>(change_value)?,
+                script_pub_key: change.script_pub_key,
+            }),
+            false => None,
+        };
+
+        let inputs_waste = selection.iter().map(|candidate| candidate.waste()).sum::<i64>();
+        let waste = inputs_waste + if change.is_some() { cost_of_change } else { excess };
+        let total_input = selection.iter().map(|candidate| candidate.value).sum::<i64>();
+        let total_change = change.as_ref().map_or(0, |change| change.amount.0);
+        let inputs = selection
+            .iter()
+            .map(|candidate| match candidate.index < self.must_spend.len() {
+                true => self.must_spend[candidate.index].clone(),
+                false => self.candidates[candidate.index - self.must_spend.len()].clone(),
+            })
+            .collect();
+
+        Ok(CoinSelection {
+            inputs,
+            change,
+            fee: BitcoinAmount::from_satoshi_for::<N>(total_input - total_output - total_change)?,
+            waste,
+        })
+    }
+
+    /// Returns the weight of an input spending the change address with placeholder signatures.
+    fn change_spend_weight(&self) -> Result<u64, CoinSelectionError> {
+        let signature = BitcoinTransactionInput::<N>::PLACEHOLDER_SIGNATURE_SIZE as u64;
+        let public_key = BitcoinTransactionInput::<N>::PLACEHOLDER_PUBLIC_KEY_SIZE as u64;
+        let schnorr_signature = BitcoinTransactionInput::<N>::PLACEHOLDER_SCHNORR_SIGNATURE_SIZE as u64;
+        // The outpoint, the sequence and the length of the script sig
+        let outpoint = 32 + 4 + 4 + 1;
+
+        match self.change_address.format() {
+            BitcoinFormat::P2PKH => Ok((outpoint + signature + public_key) * 4),
+            BitcoinFormat::P2SH_P2WPKH => Ok((outpoint + 23) * 4 + 1 + signature + public_key),
+            BitcoinFormat::Bech32 => Ok(outpoint * 4 + 1 + signature + public_key),
+            BitcoinFormat::P2TR => Ok(outpoint * 4 + 1 + schnorr_signature),
+            format => Err(CoinSelectionError::UnsupportedChangeAddress(format!("{:?}", format))),
+        }
+    }
+}
+
+/// Returns the amount below which an output costs more to spend than it is worth at the
+/// dust relay fee rate in satoshi per virtual byte. Unspendable outputs are never dust.
+pub fn dust_threshold(output: &BitcoinTransactionOutput, dust_relay_fee_rate: u64) -> BitcoinAmount {
+    let script_pub_key = Script::from(&output.script_pub_key[..]);
+    if script_pub_key.is_op_return() {
+        return BitcoinAmount::ZERO;
+    }
+    let size = output.serialize().map_or(0, |output| output.len() as u64);
+    // The outpoint, the script sig length, the sequence and a signature with a public key,
+    // which is discounted as witness data when spending a witness program
+    let spend_size = match script_pub_key.witness_program() {
+        Some(_) => 32 + 4 + 1 + 107 / 4 + 4,
+        None => 32 + 4 + 1 + 107 + 4,
+    };
+    BitcoinAmount((size + spend_size) as i64 * dust_relay_fee_rate as i64)
+}
+
+/// Returns the dust threshold of the output at the default dust relay fee rate of the network,
+/// raised to the dust limit of the network unless the output is unspendable.
+pub fn default_dust_threshold<N: BitcoinNetwork>(output: &BitcoinTransactionOutput) -> BitcoinAmount {
+    match Script::from(&output.script_pub_key[..]).is_op_return() {
+        true => BitcoinAmount::ZERO,
+        false => BitcoinAmount(dust_threshold(output, N::dust_relay_fee_rate()).0.max(N::dust_limit())),
+    }
+}
+
+/// Returns the fee of the weight at the fee rate, charged per started virtual byte.
+fn fee(weight: u64, fee_rate: u64) -> i64 {
+    (weight.div_ceil(4) * fee_rate) as i64
+}
+
+/// Returns the selection of the least waste with an effective value of at least the target
+/// and at most the target and the cost of change, by a depth-first search of inclusion and exclusion.
+/// https://github.com/bitcoin/bitcoin/blob/master/src/wallet/coinselection.cpp
+fn branch_and_bound(pool: &[Candidate], target: i64, cost_of_change: i64) -> Option<Vec<usize>> {
+    let mut pool: Vec<(usize, &Candidate)> = pool.iter().enumerate().collect();
+    pool.sort_by_key(|(_, candidate)| Reverse(candidate.effective_value()));
+
+    let mut current_value = 0;
+    let mut current_waste = 0;
+    let mut current_available = pool.iter().map(|(_, candidate)| candidate.effective_value()).sum::<i64>();
+    let mut current_selection: Vec<usize> = vec![];
+    let mut best_selection: Option<Vec<usize>> = None;
+    let mut best_waste = i64::MAX;
+    // Spending more inputs only adds waste when the fee rate is above the long-term fee rate
+    let is_fee_rate_high = pool.first().is_some_and(|(_, candidate)| candidate.fee > candidate.long_term_fee);
+
+    let mut index = 0;
+    for _ in 0..BNB_TOTAL_TRIES {
+        let backtrack = if current_value + current_available < target
+            || current_value > target + cost_of_change
+            || (current_waste > best_waste && is_fee_rate_high)
+        {
+            true
+        } else if current_value >= target {
+            // The excess is given up to the fee and added to the waste
+            let waste = current_waste + current_value - target;
+            if waste <= best_waste {
+                best_selection = Some(current_selection.clone());
+                best_waste = waste;
+            }
+            true
+        } else {
+            false
+        };
+
+        match backtrack {
+            true => {
+                let last = match current_selection.last() {
+                    Some(last) => *last,
+                    None => break,
+                };
+                // Restore the candidates omitted after the last one included, then exclude it
+                index -= 1;
+                while index > last {
+                    current_available += pool[index].1.effective_value();
+                    index -= 1;
+                }
+                let (_, candidate) = pool[index];
+                current_value -= candidate.effective_value();
+                current_waste -= candidate.waste();
+                current_selection.pop();
+            }
+            false => {
+                let (_, candidate) = pool[index];
+                current_available -= candidate.effective_value();
+                // Omitting a candidate equivalent to the previous, omitted one explores the same selections
+                let equivalent = index > 0
+                    && current_selection.last() != Some(&(index - 1))
+                    && candidate.effective_value() == pool[index - 1].1.effective_value()
+                    && candidate.fee == pool[index - 1].1.fee;
+                if !equivalent {
+                    current_selection.push(index);
+                    current_value += candidate.effective_value();
+                    current_waste += candidate.waste();
+                }
+            }
+        }
+        index += 1;
+    }
+
+    best_selection.map(|selection| selection.iter().map(|index| pool[*index].0).collect())
+}
+
+/// Returns a candidate matching the target, all the candidates below the target and a minimum
+/// change if they add up to it, or else the smallest of the random subsets and the smallest
+/// candidate above the target. The pool must cover the target.
+fn knapsack<R: Rng>(pool: &[Candidate], target: i64, rng: &mut R) -> Vec<usize> {
+    let mut shuffled: Vec<usize> = (0..pool.len()).collect();
+    shuffled.shuffle(rng);
+
+    let mut applicable = vec![];
+    let mut total_lower = 0;
+    let mut lowest_larger: Option<usize> = None;
+    for index in shuffled {
+        let value = pool[index].effective_value();
+        if value == target {
+            return vec![index];
+        } else if value < target + MIN_CHANGE {
+            applicable.push(index);
+            total_lower += value;
+        } else if lowest_larger.is_none_or(|lowest| value < pool[lowest].effective_value()) {
+            lowest_larger = Some(index);
+        }
+    }
+
+    if total_lower == target {
+        return applicable;
+    }
+    if total_lower < target {
+        return lowest_larger.into_iter().collect();
+    }
+
+    applicable.sort_by_key(|index| Reverse(pool[*index].effective_value()));
+    let values: Vec<i64> = applicable.iter().map(|index| pool[*index].effective_value()).collect();
+    let (mut best, mut best_value) = approximate_best_subset(&values, total_lower, target, rng);
+    if best_value != target && total_lower >= target + MIN_CHANGE {
+        (best, best_value) = approximate_best_subset(&values, total_lower, target + MIN_CHANGE, rng);
+    }
+
+    // The smallest candidate above the target is preferred to a subset leaving less than the minimum change
+    match lowest_larger {
+        Some(lowest)
+            if (best_value != target && best_value < target + MIN_CHANGE)
+                || pool[lowest].effective_value() <= best_value =>
+        {
+            vec![lowest]
+        }
+        _ => applicable.iter().zip(best).filter(|(_, included)| *included).map(|(index, _)| *index).collect(),
+    }
+}
+
+/// Returns the inclusions and the value of the smallest random subset of at least the target,
+/// starting from all the values.
+fn approximate_best_subset<R: Rng>(values: &[i64], total_lower: i64, target: i64, rng: &mut R) -> (Vec<bool>, i64) {
+    let mut best = vec![true; values.len()];
+    let mut best_value = total_lower;
+
+    for _ in 0..KNAPSACK_ITERATIONS {
+        if best_value == target {
+            break;
+        }
+        let mut included = vec![false; values.len()];
+        let mut total = 0;
+        let mut reached_target = false;
+        // Include values at random, then the remaining values in order
+        for pass in 0..2 {
+            if reached_target {
+                break;
+            }
+            for (index, value) in values.iter().enumerate() {
+                let include = match pass {
+                    0 => rng.gen_bool(0.5),
+                    _ => !included[index],
+                };
+                if include {
+                    total += value;
+                    included[index] = true;
+                    if total >= target {
+                        reached_target = true;
+                        if total < best_value {
+                            best_value = total;
+                            best = included.clone();
+                        }
+                        total -= value;
+                        included[index] = false;
+                    }
+                }
+            }
+        }
+    }
+
+    (best, best_value)
+}
+
+/// Returns the candidates of the largest effective value until they add up to the target.
+fn largest_first(pool: &[Candidate], target: i64) -> Vec<usize> {
+    let mut sorted: Vec<usize> = (0..pool.len()).collect();
+    sorted.sort_by_key(|index| Reverse(pool[*index].effective_value()));
+
+    let mut selected = vec![];
+    let mut total = 0;
+    for index in sorted {
+        if total >= target {
+            break;
+        }
+        total += pool[index].effective_value();
+        selected.push(index);
+    }
+    selected
+}
+
+/// Returns candidates drawn at random until they add up to the target, if they can.
+fn single_random_draw<R: Rng>(pool: &[Candidate], target: i64, rng: &mut R) -> Option<Vec<usize>> {
+    let mut shuffled: Vec<usize> = (0..pool.len()).collect();
+    shuffled.shuffle(rng);
+
+    let mut selected = vec![];
+    let mut total = 0;
+    for index in shuffled {
+        total += pool[index].effective_value();
+        selected.push(index);
+        if total >= target {
+            return Some(selected);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::*;
+    use crate::private_key::BitcoinPrivateKey;
+    use crate::transaction::{create_script_pub_key, BitcoinTransaction, SignatureHash};
+    use chainlib_core::{PrivateKey, Transaction};
+    use core::str::FromStr;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    type N = Mainnet;
+
+    const PRIVATE_KEY: &str = "KyRv5iFPHG7iB5E4CqvMzH3WFJVhbfYK4VY7XAedd9Ys69mEsPLQ";
+
+    const STRATEGIES: [CoinSelectionStrategy; 4] = [
+        CoinSelectionStrategy::BranchAndBound,
+        CoinSelectionStrategy::Knapsack,
+        CoinSelectionStrategy::LargestFirst,
+        CoinSelectionStrategy::SingleRandomDraw,
+    ];
+
+    fn address(format: &BitcoinFormat) -> BitcoinAddress<N> {
+        BitcoinPrivateKey::<N>::from_str(PRIVATE_KEY).unwrap().to_address(format).unwrap()
+    }
+
+    /// Returns inputs of the amounts spending outputs of the given format.
+    fn inputs(format: &BitcoinFormat, amounts: &[i64]) -> Vec<BitcoinTransactionInput<N>> {
+        amounts
+            .iter()
+            .enumerate()
+            .map(|(index, amount)| {
+                let address = address(format);
+                BitcoinTransactionInput::<N>::new(
+                    vec![index as u8 + 1; 32],
+                    index as u32,
+                    Some(address),
+                    Some(BitcoinAmount(*amount)),
+                    None,
+                    None,
+                    None,
+                    SignatureHash::SIGHASH_ALL,
+                )
+                .unwrap()
+            })
+            .collect()
+    }
+
+    fn output(amount: i64) -> BitcoinTransactionOutput {
+        let address = BitcoinAddress::<N>::from_str("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4").unwrap();
+        BitcoinTransactionOutput::new(&address, BitcoinAmount(amount)).unwrap()
+    }
+
+    fn amounts(selection: &CoinSelection<N>) -> Vec<i64> {
+        selection.inputs.iter().map(|input| input.outpoint.amount.unwrap().0).collect()
+    }
+
+    #[test]
+    fn branch_and_bound_without_fees() {
+        let selector = CoinSelector::new(address(&BitcoinFormat::Bech32), 0)
+            .candidates(inputs(&BitcoinFormat::Bech32, &[100_000, 200_000, 300_000, 400_000, 500_000]));
+        let selection = selector
+            .select(&[output(700_000)], CoinSelectionStrategy::BranchAndBound, &mut StdRng::seed_from_u64(0))
+            .unwrap();
+
+        // Without fees, consolidating the most inputs wastes the least
+        assert_eq!(vec![400_000, 200_000, 100_000], amounts(&selection));
+        assert_eq!(None, selection.change);
+        assert_eq!(BitcoinAmount(0), selection.fee);
+        assert_eq!(-3 * fee(272, DEFAULT_LONG_TERM_FEE_RATE), selection.waste);
+    }
+
+    #[test]
+    fn branch_and_bound_without_solution() {
+        let selector = CoinSelector::new(address(&BitcoinFormat::Bech32), 0)
+            .candidates(inputs(&BitcoinFormat::Bech32, &[100_000, 300_000]));
+        let result = selector.select(&[output(150_000)], CoinSelectionStrategy::BranchAndBound, &mut StdRng::seed_from_u64(0));
+        let expected: TransactionError = CoinSelectionError::NoChangelessSolution.into();
+        assert_eq!(expected.to_string(), result.unwrap_err().to_string());
+    }
+
+    #[test]
+    fn fee_covers_the_fee_rate() {
+        let fee_rate = 7;
+        let values = [30_000, 120_000, 2_500_000, 75_000, 800_000, 1_300_000, 50_000];
+        for format in [BitcoinFormat::P2PKH, BitcoinFormat::Bech32, BitcoinFormat::P2TR] {
+            let selector = CoinSelector::new(address(&format), fee_rate).candidates(inputs(&format, &values));
+            let outputs = vec![output(900_000), output(400_000)];
+            for strategy in STRATEGIES {
+                let selection = match selector.select(&outputs, strategy, &mut StdRng::seed_from_u64(1)) {
+                    Ok(selection) => selection,
+                    Err(_) if strategy == CoinSelectionStrategy::BranchAndBound => continue,
+                    Err(error) => panic!("{:?} {:?}: {}", format, strategy, error),
+                };
+
+                let parameters = selection.transaction_parameters(outputs.clone()).unwrap();
+                let transaction = BitcoinTransaction::<N>::new(&parameters).unwrap();
+                let vsize = transaction.vsize().unwrap() as i64;
+                let total_input = amounts(&selection).iter().sum::<i64>();
+                let total_output = parameters.outputs.iter().map(|output| output.amount.0).sum::<i64>();
+
+                assert_eq!(selection.fee.0, total_input - total_output);
+                assert!(selection.fee.0 >= vsize * fee_rate as i64, "{:?} {:?}", format, strategy);
+                if let Some(change) = &selection.change {
+                    assert!(change.amount.0 >= dust_threshold(change, DEFAULT_DUST_RELAY_FEE_RATE).0);
+                    // Only the rounding of the virtual bytes of each input is paid above the fee rate
+                    assert!(selection.fee.0 < (vsize + parameters.inputs.len() as i64 + 1) * fee_rate as i64);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn largest_first() {
+        let selector = CoinSelector::new(address(&BitcoinFormat::Bech32), 2)
+            .candidates(inputs(&BitcoinFormat::Bech32, &[10_000, 600_000, 20_000, 500_000]));
+        let selection = selector
+            .select(&[output(700_000)], CoinSelectionStrategy::LargestFirst, &mut StdRng::seed_from_u64(0))
+            .unwrap();
+
+        assert_eq!(vec![600_000, 500_000], amounts(&selection));
+        let change = selection.change.unwrap();
+        assert_eq!(1_100_000 - 700_000 - selection.fee.0, change.amount.0);
+        assert_eq!(create_script_pub_key(&address(&BitcoinFormat::Bech32)).unwrap(), change.script_pub_key);
+    }
+
+    #[test]
+    fn must_spend() {
+        let candidates = inputs(&BitcoinFormat::Bech32, &[100_000, 200_000, 300_000]);
+        let must_spend = inputs(&BitcoinFormat::P2PKH, &[5_000]).remove(0);
+        for strategy in STRATEGIES {
+            let selector = CoinSelector::new(address(&BitcoinFormat::Bech32), 1)
+                .candidates(candidates.clone())
+                .must_spend(must_spend.clone());
+            let selection = match selector.select(&[output(250_000)], strategy, &mut StdRng::seed_from_u64(2)) {
+                Ok(selection) => selection,
+                Err(_) if strategy == CoinSelectionStrategy::BranchAndBound => continue,
+                Err(error) => panic!("{:?}: {}", strategy, error),
+            };
+            assert_eq!(must_spend, selection.inputs[0]);
+        }
+
+        // Inputs that must be spent and cover the target are spent alone
+        let selector = CoinSelector::new(address(&BitcoinFormat::Bech32), 1)
+            .candidates(candidates)
+            .must_spend(inputs(&BitcoinFormat::Bech32, &[1_000_000]).remove(0));
+        let selection = selector
+            .select(&[output(250_000)], CoinSelectionStrategy::Knapsack, &mut StdRng::seed_from_u64(2))
+            .unwrap();
+        assert_eq!(vec![1_000_000], amounts(&selection));
+        assert!(selection.change.is_some());
+    }
+
+    #[test]
+    fn dust_change_is_given_up_to_the_fee() {
+        let selector = CoinSelector::new(address(&BitcoinFormat::Bech32), 1)
+            .candidates(inputs(&BitcoinFormat::Bech32, &[100_000]));
+        let selection = selector
+            .select(&[output(99_600)], CoinSelectionStrategy::LargestFirst, &mut StdRng::seed_from_u64(0))
+            .unwrap();
+
+        assert_eq!(None, selection.change);
+        assert_eq!(BitcoinAmount(400), selection.fee);
+    }
+
+    #[test]
+    fn insufficient_funds() {
+        let selector = CoinSelector::new(address(&BitcoinFormat::Bech32), 10)
+            .candidates(inputs(&BitcoinFormat::Bech32, &[100_000, 200_000, 500]));
+        for strategy in STRATEGIES {
+            let result = selector.select(&[output(300_000)], strategy, &mut StdRng::seed_from_u64(0));
+            // The 500 satoshi output costs more to spend than it is worth
+            let available = 100_000 - fee(272, 10) + 200_000 - fee(272, 10);
+            let target = 300_000 + fee((4 + 4 + 1 + 1 + 31) * 4 + 2, 10);
+            let expected: TransactionError = CoinSelectionError::InsufficientFunds(available, target).into();
+            assert_eq!(expected.to_string(), result.unwrap_err().to_string());
+        }
+    }
+
+    #[test]
+    fn unsupported_change_address() {
+        let change = BitcoinAddress::<N>::p2wsh(&vec![0x51]).unwrap();
+        let selector = CoinSelector::new(change, 1).candidates(inputs(&BitcoinFormat::Bech32, &[100_000]));
+        let result = selector.select(&[output(50_000)], CoinSelectionStrategy::LargestFirst, &mut StdRng::seed_from_u64(0));
+        let expected: TransactionError = CoinSelectionError::UnsupportedChangeAddress("P2WSH".into()).into();
+        assert_eq!(expected.to_string(), result.unwrap_err().to_string());
+    }
+
+    #[test]
+    fn dust_thresholds() {
+        let threshold = |format: &BitcoinFormat| {
+            let output = BitcoinTransactionOutput::new(&address(format), BitcoinAmount::ZERO).unwrap();
+            dust_threshold(&output, DEFAULT_DUST_RELAY_FEE_RATE)
+        };
+        assert_eq!(BitcoinAmount(546), threshold(&BitcoinFormat::P2PKH));
+        assert_eq!(BitcoinAmount(294), threshold(&BitcoinFormat::Bech32));
+        assert_eq!(BitcoinAmount(330), threshold(&BitcoinFormat::P2TR));
+    }
+
+    #[test]
+    fn network_dust_thresholds() {
+        fn threshold<M: BitcoinNetwork>(address: &str) -> BitcoinAmount {
+            let address = BitcoinAddress::<M>::from_str(address).unwrap();
+            default_dust_threshold::<M>(&BitcoinTransactionOutput::new(&address, BitcoinAmount::ZERO).unwrap())
+        }
+        assert_eq!(BitcoinAmount(546), threshold::<Mainnet>("1J7mdg5rbQyUHENYdx39WVWK7fsLpEoXZy"));
+        assert_eq!(BitcoinAmount(5_460), threshold::<Litecoin>("LcLittPgg5DXY34hp62SnWa5KtEcvWHUsr"));
+        assert_eq!(BitcoinAmount(2_940), threshold::<Litecoin>("ltc1qh0q7g23e6pdye3sh2ttfvwmld8gfhvnml4ucqx"));
+        assert_eq!(BitcoinAmount(1_000_000), threshold::<Dogecoin>("DNFsAw2VtpskpEZ9NY2i4FfuzobeBaxqMm"));
+        assert_eq!(BitcoinAmount(546), threshold::<Dash>("XsocTvjkZ8C4SAy8VqMNN2C6x1T2qqbjCG"));
+        let data = BitcoinTransactionOutput {
+            amount: BitcoinAmount::ZERO,
+            script_pub_key: Script::new_op_return(b"data").into_bytes(),
+        };
+        assert_eq!(BitcoinAmount::ZERO, default_dust_threshold::<Dogecoin>(&data));
+    }
+}
@@ -0,0 +1,868 @@
+//! # Descriptors
+//!
+//! Output script descriptors (BIP-380 to BIP-386), the strings wallets store to describe the outputs
+//! they watch, e.g. `wpkh([d34db33f/84'/0'/0']xpub.../0/*)` or `sh(wsh(sortedmulti(2,...)))`.
+//! A key may carry its origin, the fingerprint of its master key and its derivation path, and an
+//! extended public key ending with `/*` ranges the descriptor over its non-hardened children.
+//! The checksum following `#` is verified when present and always appended when displayed.
+//! Only public keys are supported, so hardened steps below an extended public key are rejected.
+//! https://github.com/bitcoin/bips/blob/master/bip-0380.mediawiki
+
+use crate::address::BitcoinAddress;
+use crate::multisig::{MultisigError, MultisigScript};
+use crate::network::BitcoinNetwork;
+use crate::psbt::KeySource;
+use crate::public_key::BitcoinPublicKey;
+use crate::script::{Opcode, Script};
+use crate::taproot::{lift_x, tweak_public_key, x_only_public_key, TapTree, TaprootError};
+use crate::transaction::{create_script_pub_key, push_data, BitcoinTransactionInputBuilder};
+use chainlib_core::crypto::hash160;
+use chainlib_core::no_std::*;
+use chainlib_core::{
+    libsecp256k1, AddressError, ChildIndex, DerivationPath, ExtendedKeyError, ExtendedPublicKey, TransactionError,
+};
+
+use core::{fmt, ops::Range, str::FromStr};
+use sha2::{Digest, Sha256};
+
+/// The characters of a descriptor, in the order of their checksum symbols
+const INPUT_CHARSET: &str =
+    "0123456789()[],'/*abcdefgh@:$%{}IJKLMNOPQRSTUVWXYZ&+-.;<=>?!^_|~ijklmnopqrstuvwxyzABCDEFGH`#\"\\ ";
+/// The characters of a checksum
+const CHECKSUM_CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+/// The generator of the checksum code
+const CHECKSUM_GENERATOR: [u64; 5] = [0xf5dee51989, 0xa9fdca3312, 0x1bab10e32d, 0x3706b1677a, 0x644d626ffd];
+/// The number of characters of a checksum
+const CHECKSUM_LENGTH: usize = 8;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum DescriptorError {
+    #[error("{0}: {1}")]
+    Crate(&'static str, String),
+
+    #[error("invalid character {0:?}")]
+    InvalidCharacter(char),
+
+    #[error("invalid descriptor checksum: {{ expected: {0}, found: {1} }}")]
+    InvalidChecksum(String, String),
+
+    #[error("{0} is not allowed {1}")]
+    InvalidContext(String, &'static str),
+
+    #[error("invalid descriptor expression {0}")]
+    InvalidExpression(String),
+
+    #[error("invalid descriptor key {0}")]
+    InvalidKey(String),
+
+    #[error("invalid threshold of {0} for {1} public keys")]
+    InvalidThreshold(usize, usize),
+
+    #[error("descriptor {0} has no address")]
+    MissingAddress(String),
+
+    #[error("uncompressed public key {0} {1}")]
+    UncompressedPublicKey(String, &'static str),
+}
+
+impl From<AddressError> for DescriptorError {
+    fn from(error: AddressError) -> Self {
+        DescriptorError::Crate("Address", format!("{:?}", error))
+    }
+}
+
+impl From<ExtendedKeyError> for DescriptorError {
+    fn from(error: ExtendedKeyError) -> Self {
+        DescriptorError::Crate("ExtendedPublicKey", format!("{:?}", error))
+    }
+}
+
+impl From<MultisigError> for DescriptorError {
+    fn from(error: MultisigError) -> Self {
+        DescriptorError::Crate("Multisig", format!("{:?}", error))
+    }
+}
+
+impl From<TaprootError> for DescriptorError {
+    fn from(error: TaprootError) -> Self {
+        DescriptorError::Crate("Taproot", format!("{:?}", error))
+    }
+}
+
+impl From<TransactionError> for DescriptorError {
+    fn from(error: TransactionError) -> Self {
+        DescriptorError::Crate("Transaction", format!("{:?}", error))
+    }
+}
+
+impl From<DescriptorError> for AddressError {
+    fn from(error: DescriptorError) -> Self {
+        AddressError::Crate("Descriptor", format!("{:?}", error))
+    }
+}
+
+impl From<DescriptorError> for TransactionError {
+    fn from(error: DescriptorError) -> Self {
+        TransactionError::Crate("Descriptor", format!("{:?}", error))
+    }
+}
+
+/// Returns the checksum of the given descriptor without its `#` suffix.
+pub fn descriptor_checksum(descriptor: &str) -> Result<String, DescriptorError> {
+    let mut checksum = 1u64;
+    let mut step = |symbol: u64| {
+        let top = checksum >> 35;
+        checksum = (checksum & 0x7_ffff_ffff) << 5 ^ symbol;
+        for (i, generator) in CHECKSUM_GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                checksum ^= generator;
+            }
+        }
+    };
+
+    // Each character contributes its position modulo 32, and every three characters their positions divided by 32
+    let mut groups = vec![];
+    for character in descriptor.chars() {
+        let position = INPUT_CHARSET.find(character).ok_or(DescriptorError::InvalidCharacter(character))? as u64;
+        step(position & 31);
+        groups.push(position >> 5);
+        if groups.len() == 3 {
+            step(groups[0] * 9 + groups[1] * 3 + groups[2]);
+            groups.clear();
+        }
+    }
+    match groups[..] {
+        [first] => step(first),
+        [first, second] => step(first * 3 + second),
+        _ => {}
+    }
+    (0..CHECKSUM_LENGTH).for_each(|_| step(0));
+
+    checksum ^= 1;
+    Ok((0..CHECKSUM_LENGTH)
+        .map(|i| CHECKSUM_CHARSET[(checksum >> (5 * (CHECKSUM_LENGTH - 1 - i)) & 31) as usize] as char)
+        .collect())
+}
+
+/// Represents the expression a script or key appears in, which restricts what it may contain
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Context {
+    /// The outermost expression
+    Top,
+    /// The redeem script of sh()
+    Sh,
+    /// The witness script of wsh()
+    Wsh,
+    /// A leaf script of tr()
+    Tap,
+}
+
+impl Context {
+    /// Returns the description of the context used in errors.
+    fn description(&self) -> &'static str {
+        match self {
+            Context::Top => "at the top level",
+            Context::Sh => "inside sh()",
+            Context::Wsh => "inside wsh()",
+            Context::Tap => "inside tr()",
+        }
+    }
+}
+
+/// Represents the public key of a descriptor, either given or derived from an extended public key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DescriptorKey {
+    /// A compressed, uncompressed or x-only serialized public key
+    Single(Vec<u8>),
+    /// An extended public key and the non-hardened path below it,
+    /// followed by the child index of the descriptor if it is ranged
+    Extended {
+        extended_public_key: ExtendedPublicKey,
+        path: DerivationPath,
+        ranged: bool,
+    },
+}
+
+/// Represents a key expression, i.e. a public key with its origin if known
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DescriptorPublicKey {
+    /// The fingerprint of the master key and the path to the key, if known
+    pub origin: Option<KeySource>,
+    /// The public key
+    pub key: DescriptorKey,
+}
+
+impl DescriptorPublicKey {
+    /// Returns true if the key is an extended public key ending with a wildcard.
+    pub fn is_ranged(&self) -> bool {
+        matches!(self.key, DescriptorKey::Extended { ranged: true, .. })
+    }
+
+    /// Returns the public key at the given child index, ignored unless the key is ranged.
+    /// X-only keys are lifted to their even y coordinate.
+    pub fn derive<N: BitcoinNetwork>(&self, index: u32) -> Result<BitcoinPublicKey<N>, DescriptorError> {
+        match &self.key {
+            DescriptorKey::Single(key) if key.len() == 32 => {
+                let mut x = [0u8; 32];
+                x.copy_from_slice(key);
+                Ok(BitcoinPublicKey::from_secp256k1_public_key(lift_x(&x)?, true))
+            }
+            DescriptorKey::Single(key) => {
+                let public_key = libsecp256k1::PublicKey::parse_slice(key, None)
+                    .map_err(|_| DescriptorError::InvalidKey(hex::encode(key)))?;
+                Ok(BitcoinPublicKey::from_secp256k1_public_key(public_key, key.len() == 33))
+            }
+            DescriptorKey::Extended { extended_public_key, path, ranged } => {
+                let mut child = extended_public_key.derive_path(path)?;
+                if *ranged {
+                    child = child.derive_child(ChildIndex::Normal(index))?;
+                }
+                Ok(BitcoinPublicKey::from_secp256k1_public_key(child.to_secp256k1_public_key(), true))
+            }
+        }
+    }
+
+    /// Returns the key of the given key expression, checking that it fits the context.
+    fn parse<N: BitcoinNetwork>(expression: &str, context: Context) -> Result<Self, DescriptorError> {
+        let invalid = || DescriptorError::InvalidKey(expression.into());
+        let (origin, key) = match expression.strip_prefix('[') {
+            Some(origin) => {
+                let (origin, key) = origin.split_once(']').ok_or_else(invalid)?;
+                let (fingerprint, path) = origin.split_once('/').unwrap_or((origin, ""));
+                let fingerprint = match hex::decode(fingerprint) {
+                    Ok(fingerprint) if fingerprint.len() == 4 => [fingerprint[0], fingerprint[1], fingerprint[2], fingerprint[3]],
+                    _ => return Err(invalid()),
+                };
+                (Some(KeySource::new(fingerprint, parse_path(path)?)), key)
+            }
+            None => (None, expression),
+        };
+
+        if let Ok(public_key) = hex::decode(key) {
+            match (public_key.len(), public_key.first(), context) {
+                (32, _, Context::Tap) | (33, Some(0x02 | 0x03), _) => {}
+                (65, Some(0x04), Context::Top | Context::Sh) => {}
+                (65, Some(0x04), _) => {
+                    return Err(DescriptorError::UncompressedPublicKey(key.into(), context.description()))
+                }
+                _ => return Err(invalid()),
+            }
+            let key = Self { origin, key: DescriptorKey::Single(public_key) };
+            // Parse the point now rather than at derivation
+            key.derive::<N>(0)?;
+            return Ok(key);
+        }
+
+        let mut steps = key.split('/');
+        let extended_public_key = ExtendedPublicKey::from_str(steps.next().unwrap_or_default()).map_err(|_| invalid())?;
+        N::from_extended_public_key_version(&extended_public_key.version()).map_err(|_| invalid())?;
+        let mut steps: Vec<&str> = steps.collect();
+        let ranged = steps.last() == Some(&"*");
+        if ranged {
+            steps.pop();
+        }
+        // Hardened children, including those of a hardened wildcard, need the extended private key
+        let hardened = || DescriptorError::from(ExtendedKeyError::HardenedDerivation(key.into()));
+        match steps.iter().position(|step| step.starts_with('*')) {
+            Some(position) if position + 1 == steps.len() && matches!(steps[position], "*'" | "*h" | "*H") => {
+                return Err(hardened())
+            }
+            Some(_) => return Err(invalid()),
+            None => {}
+        }
+        let path = parse_path(&steps.join("/"))?;
+        if path.is_hardened() {
+            return Err(hardened());
+        }
+
+        Ok(Self {
+            origin,
+            key: DescriptorKey::Extended { extended_public_key, path, ranged },
+        })
+    }
+}
+
+impl fmt::Display for DescriptorPublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(origin) = &self.origin {
+            write!(f, "[{}{}]", hex::encode(origin.fingerprint), format_path(&origin.path))?;
+        }
+        match &self.key {
+            DescriptorKey::Single(key) => write!(f, "{}", hex::encode(key)),
+            DescriptorKey::Extended { extended_public_key, path, ranged } => {
+                write!(f, "{}{}", extended_public_key, format_path(path))?;
+                match ranged {
+                    true => write!(f, "/*"),
+                    false => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+/// Represents the script tree of a tr() descriptor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TapTreeDescriptor<N: BitcoinNetwork> {
+    /// A leaf script expression
+    Leaf(Box<Descriptor<N>>),
+    /// A `{left,right}` pair of script trees
+    Branch(Box<TapTreeDescriptor<N>>, Box<TapTreeDescriptor<N>>),
+}
+
+impl<N: BitcoinNetwork> TapTreeDescriptor<N> {
+    /// Returns the script tree at the given child index.
+    pub fn derive(&self, index: u32) -> Result<TapTree, DescriptorError> {
+        match self {
+            TapTreeDescriptor::Leaf(script) => Ok(TapTree::leaf(script.script(index, Context::Tap)?)),
+            TapTreeDescriptor::Branch(left, right) => Ok(TapTree::branch(left.derive(index)?, right.derive(index)?)),
+        }
+    }
+
+    /// Returns true if a leaf script has a ranged key.
+    fn is_ranged(&self) -> bool {
+        match self {
+            TapTreeDescriptor::Leaf(script) => script.is_ranged(),
+            TapTreeDescriptor::Branch(left, right) => left.is_ranged() || right.is_ranged(),
+        }
+    }
+
+    /// Returns the script tree of the given `{left,right}` or script expression.
+    fn parse(expression: &str) -> Result<Self, DescriptorError> {
+        match expression.strip_prefix('{').and_then(|tree| tree.strip_suffix('}')) {
+            Some(branch) => match split_arguments(branch)?[..] {
+                [left, right] => Ok(TapTreeDescriptor::Branch(Box::new(Self::parse(left)?), Box::new(Self::parse(right)?))),
+                _ => Err(DescriptorError::InvalidExpression(expression.into())),
+            },
+            None => Ok(TapTreeDescriptor::Leaf(Box::new(Descriptor::parse(expression, Context::Tap)?))),
+        }
+    }
+}
+
+impl<N: BitcoinNetwork> fmt::Display for TapTreeDescriptor<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TapTreeDescriptor::Leaf(script) => write!(f, "{}", script.expression()),
+            TapTreeDescriptor::Branch(left, right) => write!(f, "{{{},{}}}", left, right),
+        }
+    }
+}
+
+/// Represents an output script descriptor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Descriptor<N: BitcoinNetwork> {
+    /// pk(KEY): a <public key> OP_CHECKSIG script
+    Pk(DescriptorPublicKey),
+    /// pkh(KEY): a P2PKH script (BIP-381)
+    Pkh(DescriptorPublicKey),
+    /// wpkh(KEY): a P2WPKH script (BIP-382)
+    Wpkh(DescriptorPublicKey),
+    /// sh(SCRIPT): a P2SH script of the redeem script (BIP-381)
+    Sh(Box<Descriptor<N>>),
+    /// wsh(SCRIPT): a P2WSH script of the witness script (BIP-382)
+    Wsh(Box<Descriptor<N>>),
+    /// multi(k,KEY,...) or sortedmulti(k,KEY,...): a k-of-n OP_CHECKMULTISIG script (BIP-383),
+    /// with the keys in the given order or sorted as in BIP-67
+    Multi {
+        threshold: usize,
+        keys: Vec<DescriptorPublicKey>,
+        sorted: bool,
+    },
+    /// tr(KEY) or tr(KEY,TREE): a P2TR script of the internal key and the script tree (BIP-386)
+    Tr {
+        internal_key: DescriptorPublicKey,
+        tree: Option<TapTreeDescriptor<N>>,
+    },
+    /// addr(ADDR): the script of the address (BIP-385)
+    Addr(BitcoinAddress<N>),
+    /// raw(HEX): the given script (BIP-385)
+    Raw(Vec<u8>),
+}
+
+impl<N: BitcoinNetwork> Descriptor<N> {
+    /// Returns true if the descriptor has a key ending with a wildcard, and so describes one output per index.
+    pub fn is_ranged(&self) -> bool {
+        match self {
+            Descriptor::Pk(key) | Descriptor::Pkh(key) | Descriptor::Wpkh(key) => key.is_ranged(),
+            Descriptor::Sh(script) | Descriptor::Wsh(script) => script.is_ranged(),
+            Descriptor::Multi { keys, .. } => keys.iter().any(|key| key.is_ranged()),
+            Descriptor::Tr { internal_key, tree } => {
+                internal_key.is_ranged() || tree.as_ref().is_some_and(|tree| tree.is_ranged())
+            }
+            Descriptor::Addr(_) | Descriptor::Raw(_) => false,
+        }
+    }
+
+    /// Returns the script public key at the given child index, ignored unless the descriptor is ranged.
+    pub fn script_pub_key(&self, index: u32) -> Result<Vec<u8>, DescriptorError> {
+        self.script(index, Context::Top)
+    }
+
+    /// Returns the script public keys at the given range of child indexes.
+    pub fn script_pub_keys(&self, range: Range<u32>) -> Result<Vec<Vec<u8>>, DescriptorError> {
+        range.map(|index| self.script_pub_key(index)).collect()
+    }
+
+    /// Returns the address at the given child index, ignored unless the descriptor is ranged.
+    /// Bare pk(), multi() and raw scripts other than standard outputs have no address.
+    pub fn address(&self, index: u32) -> Result<BitcoinAddress<N>, DescriptorError> {
+        match self {
+            Descriptor::Pkh(key) => Ok(BitcoinAddress::p2pkh(&key.derive(index)?)?),
+            Descriptor::Wpkh(key) => Ok(BitcoinAddress::bech32(&key.derive(index)?)?),
+            Descriptor::Sh(script) => match script.as_ref() {
+                Descriptor::Wpkh(key) => Ok(BitcoinAddress::p2sh_p2wpkh(&key.derive(index)?)?),
+                Descriptor::Wsh(script) => Ok(BitcoinAddress::p2sh_p2wsh(&script.script(index, Context::Wsh)?)?),
+                script => Ok(BitcoinAddress::p2sh(&script.script(index, Context::Sh)?)?),
+            },
+            Descriptor::Wsh(script) => Ok(BitcoinAddress::p2wsh(&script.script(index, Context::Wsh)?)?),
+            Descriptor::Tr { internal_key, tree } => {
+                let (internal_key, _) = x_only_public_key(&internal_key.derive::<N>(index)?.to_secp256k1_public_key());
+                let tree = tree.as_ref().map(|tree| tree.derive(index)).transpose()?;
+                Ok(BitcoinAddress::p2tr_with_script_tree(&internal_key, tree.as_ref())?)
+            }
+            Descriptor::Addr(address) => Ok(address.clone()),
+            Descriptor::Raw(script) => BitcoinAddress::from_script_pub_key(script)
+                .map_err(|_| DescriptorError::MissingAddress(self.to_string())),
+            Descriptor::Pk(_) | Descriptor::Multi { .. } => Err(DescriptorError::MissingAddress(self.to_string())),
+        }
+    }
+
+    /// Returns the addresses at the given range of child indexes.
+    pub fn addresses(&self, range: Range<u32>) -> Result<Vec<BitcoinAddress<N>>, DescriptorError> {
+        range.map(|index| self.address(index)).collect()
+    }
+
+    /// Returns the script an `Outpoint` of the descriptor needs for signing at the given child index,
+    /// i.e. the redeem script of sh() and sh(wpkh()) or the witness script of wsh() and sh(wsh()).
+    pub fn redeem_script(&self, index: u32) -> Result<Option<Vec<u8>>, DescriptorError> {
+        match self {
+            Descriptor::Sh(script) => match script.as_ref() {
+                Descriptor::Wsh(script) => Ok(Some(script.script(index, Context::Wsh)?)),
+                script => Ok(Some(script.script(index, Context::Sh)?)),
+            },
+            Descriptor::Wsh(script) => Ok(Some(script.script(index, Context::Wsh)?)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Returns a builder for the input spending the output of the descriptor at the given child index,
+    /// with its address and redeem or witness script.
+    pub fn input(
+        &self,
+        index: u32,
+        transaction_id: &str,
+        vout: u32,
+    ) -> Result<BitcoinTransactionInputBuilder<N>, DescriptorError> {
+        let input = BitcoinTransactionInputBuilder::new(transaction_id, vout).address(&self.address(index)?.to_string());
+        Ok(match self.redeem_script(index)? {
+            Some(redeem_script) => input.redeem_script(redeem_script),
+            None => input,
+        })
+    }
+
+    /// Returns the script of the descriptor in the given context, e.g. the leaf script of pk() in tr().
+    fn script(&self, index: u32, context: Context) -> Result<Vec<u8>, DescriptorError> {
+        match self {
+            Descriptor::Pk(key) => {
+                let public_key = key.derive::<N>(index)?;
+                let public_key = match (context, public_key.is_compressed()) {
+                    (Context::Tap, _) => x_only_public_key(&public_key.to_secp256k1_public_key()).0.to_vec(),
+                    (_, true) => public_key.to_secp256k1_public_key().serialize_compressed().to_vec(),
+                    (_, false) => public_key.to_secp256k1_public_key().serialize().to_vec(),
+                };
+                Ok([push_data(&public_key), vec![Opcode::OP_CHECKSIG as u8]].concat())
+            }
+            Descriptor::Pkh(key) => Ok(create_script_pub_key(&BitcoinAddress::p2pkh(&key.derive::<N>(index)?)?)?),
+            Descriptor::Wpkh(key) => {
+                let public_key = key.derive::<N>(index)?.to_secp256k1_public_key().serialize_compressed();
+                let mut public_key_hash = [0u8; 20];
+                public_key_hash.copy_from_slice(&hash160(&public_key));
+                Ok(Script::new_p2wpkh(&public_key_hash).into_bytes())
+            }
+            Descriptor::Sh(script) => {
+                let mut script_hash = [0u8; 20];
+                script_hash.copy_from_slice(&hash160(&script.script(index, Context::Sh)?));
+                Ok(Script::new_p2sh(&script_hash).into_bytes())
+            }
+            Descriptor::Wsh(script) => {
+                let mut script_hash = [0u8; 32];
+                script_hash.copy_from_slice(&Sha256::digest(script.script(index, Context::Wsh)?));
+                Ok(Script::new_p2wsh(&script_hash).into_bytes())
+            }
+            Descriptor::Multi { threshold, keys, sorted } => {
+                let public_keys = keys
+                    .iter()
+                    .map(|key| key.derive::<N>(index))
+                    .collect::<Result<Vec<BitcoinPublicKey<N>>, DescriptorError>>()?;
+                let script = match sorted {
+                    true => MultisigScript::sorted(*threshold, &public_keys)?,
+                    false => MultisigScript::new(*threshold, &public_keys)?,
+                };
+                Ok(script.to_script())
+            }
+            Descriptor::Tr { internal_key, tree } => {
+                let (internal_key, _) = x_only_public_key(&internal_key.derive::<N>(index)?.to_secp256k1_public_key());
+                let merkle_root = tree.as_ref().map(|tree| tree.derive(index)).transpose()?.map(|tree| tree.merkle_root());
+                let (output_key, _) = tweak_public_key(&internal_key, merkle_root.as_ref())?;
+                Ok(Script::new_p2tr(&output_key).into_bytes())
+            }
+            Descriptor::Addr(address) => Ok(create_script_pub_key(address)?),
+            Descriptor::Raw(script) => Ok(script.clone()),
+        }
+    }
+
+    /// Returns the descriptor of the given expression, checking that it fits the context.
+    fn parse(expression: &str, context: Context) -> Result<Self, DescriptorError> {
+        let invalid = || DescriptorError::InvalidExpression(expression.into());
+        let (name, arguments) = expression
+            .strip_suffix(')')
+            .and_then(|expression| expression.split_once('('))
+            .ok_or_else(invalid)?;
+
+        let allowed = match name {
+            "pk" => true,
+            "pkh" | "multi" | "sortedmulti" => context != Context::Tap,
+            "wpkh" => matches!(context, Context::Top | Context::Sh),
+            "wsh" => matches!(context, Context::Top | Context::Sh),
+            "sh" | "tr" | "addr" | "raw" => context == Context::Top,
+            _ => return Err(invalid()),
+        };
+        if !allowed {
+            return Err(DescriptorError::InvalidContext(format!("{}()", name), context.description()));
+        }
+
+        let arguments = split_arguments(arguments)?;
+        let descriptor = match (name, &arguments[..]) {
+            ("pk", [key]) => Descriptor::Pk(DescriptorPublicKey::parse::<N>(key, context)?),
+            ("pkh", [key]) => Descriptor::Pkh(DescriptorPublicKey::parse::<N>(key, context)?),
+            ("wpkh", [key]) => match DescriptorPublicKey::parse::<N>(key, context)? {
+                DescriptorPublicKey { key: DescriptorKey::Single(public_key), .. } if public_key.len() == 65 => {
+                    return Err(DescriptorError::UncompressedPublicKey(hex::encode(public_key), "inside wpkh()"))
+                }
+                key => Descriptor::Wpkh(key),
+            },
+            ("sh", [script]) => Descriptor::Sh(Box::new(Self::parse(script, Context::Sh)?)),
+            ("wsh", [script]) => Descriptor::Wsh(Box::new(Self::parse(script, Context::Wsh)?)),
+            ("multi" | "sortedmulti", [threshold, keys @ ..]) if !keys.is_empty() => {
+                let threshold = threshold.parse::<usize>().map_err(|_| invalid())?;
+                if threshold == 0 || threshold > keys.len() || keys.len() > 16 {
+                    return Err(DescriptorError::InvalidThreshold(threshold, keys.len()));
+                }
+                Descriptor::Multi {
+                    threshold,
+                    keys: keys
+                        .iter()
+                        .map(|key| DescriptorPublicKey::parse::<N>(key, context))
+                        .collect::<Result<Vec<DescriptorPublicKey>, DescriptorError>>()?,
+                    sorted: name == "sortedmulti",
+                }
+            }
+            ("tr", [internal_key, tree @ ..]) if tree.len() <= 1 => Descriptor::Tr {
+                internal_key: DescriptorPublicKey::parse::<N>(internal_key, Context::Tap)?,
+                tree: tree.first().map(|tree| TapTreeDescriptor::parse(tree)).transpose()?,
+            },
+            ("addr", [address]) => Descriptor::Addr(BitcoinAddress::from_str(address)?),
+            ("raw", [script]) => Descriptor::Raw(hex::decode(script).map_err(|_| invalid())?),
+            _ => return Err(invalid()),
+        };
+        Ok(descriptor)
+    }
+
+    /// Returns the descriptor without its checksum.
+    fn expression(&self) -> String {
+        match self {
+            Descriptor::Pk(key) => format!("pk({})", key),
+            Descriptor::Pkh(key) => format!("pkh({})", key),
+            Descriptor::Wpkh(key) => format!("wpkh({})", key),
+            Descriptor::Sh(script) => format!("sh({})", script.expression()),
+            Descriptor::Wsh(script) => format!("wsh({})", script.expression()),
+            Descriptor::Multi { threshold, keys, sorted } => {
+                let name = match sorted {
+                    true => "sortedmulti",
+                    false => "multi",
+                };
+                let keys: Vec<String> = keys.iter().map(|key| key.to_string()).collect();
+                format!("{}({},{})", name, threshold, keys.join(","))
+            }
+            Descriptor::Tr { internal_key, tree: None } => format!("tr({})", internal_key),
+            Descriptor::Tr { internal_key, tree: Some(tree) } => format!("tr({},{})", internal_key, tree),
+            Descriptor::Addr(address) => format!("addr({})", address),
+            Descriptor::Raw(script) => format!("raw({})", hex::encode(script)),
+        }
+    }
+}
+
+impl<N: BitcoinNetwork> FromStr for Descriptor<N> {
+    type Err = DescriptorError;
+
+    /// Returns the descriptor of the given string, verifying its checksum if any.
+    fn from_str(descriptor: &str) -> Result<Self, Self::Err> {
+        let expression = match descriptor.split_once('#') {
+            Some((expression, checksum)) => {
+                let expected = descriptor_checksum(expression)?;
+                if checksum != expected {
+                    return Err(DescriptorError::InvalidChecksum(expected, checksum.into()));
+                }
+                expression
+            }
+            None => descriptor,
+        };
+        Self::parse(expression, Context::Top)
+    }
+}
+
+impl<N: BitcoinNetwork> fmt::Display for Descriptor<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let expression = self.expression();
+        let checksum = descriptor_checksum(&expression).map_err(|_| fmt::Error)?;
+        write!(f, "{}#{}", expression, checksum)
+    }
+}
+
+/// Returns the arguments of an expression, split at the commas outside of nested expressions.
+pub(crate) fn split_arguments(arguments: &str) -> Result<Vec<&str>, DescriptorError> {
+    let mut depth = 0usize;
+    let mut start = 0;
+    let mut split = vec![];
+    for (position, character) in arguments.char_indices() {
+        match character {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => {
+                depth = depth
+                    .checked_sub(1)
+                    .ok_or_else(|| DescriptorError::InvalidExpression(arguments.into()))?
+            }
+            ',' if depth == 0 => {
+                split.push(&arguments[start..position]);
+                start = position + 1;
+            }
+            _ => {}
+        }
+    }
+    match depth {
+        0 => {
+            split.push(&arguments[start..]);
+            Ok(split)
+        }
+        _ => Err(DescriptorError::InvalidExpression(arguments.into())),
+    }
+}
+
+/// Returns the derivation path of the given steps, without the leading "m".
+fn parse_path(path: &str) -> Result<DerivationPath, DescriptorError> {
+    match path.is_empty() {
+        true => Ok(DerivationPath::default()),
+        false => Ok(DerivationPath::from_str(&format!("m/{}", path))?),
+    }
+}
+
+/// Returns the steps of the derivation path, each preceded by a slash.
+fn format_path(path: &DerivationPath) -> String {
+    path.indexes().iter().map(|index| format!("/{}", index)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::BitcoinFormat;
+    use crate::network::*;
+
+    type N = Mainnet;
+
+    const XPUB: &str = "xpub68Gmy5EdvgibQVfPdqkBBCHxA5htiqg55crXYuXoQRKfDBFA1WEjWgP6LHhwBZeNK1VTsfTFUHCdrfp1bgwQ9xv5ski8PX9rL2dZXvgGDnw";
+    const OTHER_XPUB: &str = "xpub6D4BDPcP2GT577Vvch3R8wDkScZWzQzMMUm3PWbmWvVJrZwQY4VUNgqFJPMM3No2dFDFGTsxxpG5uJh7n7epu4trkrX7x7DogT5Uv6fcLW5";
+    // The public keys of the private keys 1, 2 and 3
+    const PUBLIC_KEYS: [&str; 3] = [
+        "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+        "02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5",
+        "02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9",
+    ];
+    const UNCOMPRESSED_PUBLIC_KEY: &str = "0479be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8";
+
+    fn descriptor(descriptor: &str) -> Descriptor<N> {
+        Descriptor::<N>::from_str(descriptor).unwrap()
+    }
+
+    fn addresses(descriptor: &Descriptor<N>, range: Range<u32>) -> Vec<String> {
+        descriptor.addresses(range).unwrap().iter().map(|address| address.to_string()).collect()
+    }
+
+    #[test]
+    fn checksum() {
+        // https://github.com/bitcoin/bips/blob/master/bip-0380.mediawiki#test-vectors
+        assert_eq!("89f8spxm", descriptor_checksum("raw(deadbeef)").unwrap());
+        assert!(Descriptor::<N>::from_str("raw(deadbeef)#89f8spxm").is_ok());
+        assert!(Descriptor::<N>::from_str("raw(deadbeef)").is_ok());
+        assert_eq!(
+            Err(DescriptorError::InvalidChecksum("89f8spxm".into(), "89f8spxn".into())),
+            Descriptor::<N>::from_str("raw(deadbeef)#89f8spxn")
+        );
+        assert!(Descriptor::<N>::from_str("raw(deadbeef)#").is_err());
+        assert!(Descriptor::<N>::from_str("raw(deadbeef)#89f8spxmx").is_err());
+        assert_eq!(Err(DescriptorError::InvalidCharacter('é')), descriptor_checksum("raw(é)"));
+        assert_eq!("raw(deadbeef)#89f8spxm", descriptor("raw(deadbeef)").to_string());
+    }
+
+    #[test]
+    fn wpkh() {
+        // https://github.com/bitcoin/bips/blob/master/bip-0382.mediawiki#test-vectors
+        let wpkh = descriptor("wpkh([ffffffff/13']xpub69H7F5d8KSRgmmdJg2KhpAK8SR3DjMwAdkxj3ZuxV27CprR9LgpeyGmXUbC6wb7ERfvrnKZjXoUmmDznezpbZb7ap6r1D3tgFxHmwMkQTPH/1/2/*)");
+        assert!(wpkh.is_ranged());
+        assert_eq!(
+            vec![
+                "0014326b2249e3a25d5dc60935f044ee835d090ba859",
+                "0014af0bd98abc2f2cae66e36896a39ffe2d32984fb7",
+                "00141fa798efd1cbf95cebf912c031b8a4a6e9fb9f27",
+            ],
+            wpkh.script_pub_keys(0..3).unwrap().iter().map(hex::encode).collect::<Vec<String>>()
+        );
+        assert_eq!(
+            vec![
+                "bc1qxf4jyj0r5fw4m3sfxhcyfm5rt5ysh2zej5q0n2",
+                "bc1q4u9anz4u9uk2uehrdzt288l795efsnahdcv7nh",
+                "bc1qr7ne3m73e0u4e6leztqrrw9y5m5lh8e8y2j7hv",
+            ],
+            addresses(&wpkh, 0..3)
+        );
+        assert_eq!(
+            "wpkh([ffffffff/13']xpub69H7F5d8KSRgmmdJg2KhpAK8SR3DjMwAdkxj3ZuxV27CprR9LgpeyGmXUbC6wb7ERfvrnKZjXoUmmDznezpbZb7ap6r1D3tgFxHmwMkQTPH/1/2/*)#66s997t5",
+            wpkh.to_string()
+        );
+        assert_eq!(None, wpkh.redeem_script(0).unwrap());
+    }
+
+    #[test]
+    fn pkh() {
+        let pkh = descriptor(&format!("pkh([d34db33f/44'/0'/0']{}/1/*)#tr2yw70z", XPUB));
+        assert_eq!(vec!["1J5rebbkQaunJTUoNVREDbeB49DqMNFFXk", "15Gwr548Jmcbr4RTrwzxMSo9heuwHqMmBz"], addresses(&pkh, 0..2));
+        assert_eq!(format!("pkh([d34db33f/44'/0'/0']{}/1/*)#tr2yw70z", XPUB), pkh.to_string());
+        match &pkh {
+            Descriptor::Pkh(DescriptorPublicKey { origin: Some(origin), .. }) => {
+                assert_eq!([0xd3, 0x4d, 0xb3, 0x3f], origin.fingerprint);
+                assert_eq!("m/44'/0'/0'", origin.path.to_string());
+            }
+            _ => panic!("expected pkh()"),
+        }
+
+        let pkh = descriptor(&format!("pkh({})", UNCOMPRESSED_PUBLIC_KEY));
+        assert!(!pkh.is_ranged());
+        assert_eq!("1EHNa6Q4Jz2uvNExL497mE43ikXhwF6kZm", pkh.address(7).unwrap().to_string());
+    }
+
+    #[test]
+    fn sh() {
+        let sh_wpkh = descriptor(&format!("sh(wpkh({}))", PUBLIC_KEYS[0]));
+        let address = sh_wpkh.address(0).unwrap();
+        assert_eq!("3JvL6Ymt8MVWiCNHC7oWU6nLeHNJKLZGLN", address.to_string());
+        assert_eq!(BitcoinFormat::P2SH_P2WPKH, address.format());
+        assert_eq!(
+            Some("0014751e76e8199196d454941c45d1b3a323f1433bd6".into()),
+            sh_wpkh.redeem_script(0).unwrap().map(hex::encode)
+        );
+
+        let sh_multi = descriptor(&format!("sh(multi(2,{},{}))", PUBLIC_KEYS[0], PUBLIC_KEYS[1]));
+        assert_eq!("33RQmypKhD6f4tMquiR5a3C6dRT7eBpaiG", sh_multi.address(0).unwrap().to_string());
+        assert_eq!(
+            Some(format!("52 21{} 21{} 52ae", PUBLIC_KEYS[0], PUBLIC_KEYS[1]).replace(' ', "")),
+            sh_multi.redeem_script(0).unwrap().map(hex::encode)
+        );
+    }
+
+    #[test]
+    fn sh_wsh_sortedmulti() {
+        let expression = format!("sh(wsh(sortedmulti(2,{}/0/*,{}/0/*)))", XPUB, OTHER_XPUB);
+        let sh_wsh = descriptor(&expression);
+        assert_eq!(format!("{}#2sadl5s6", expression), sh_wsh.to_string());
+        assert_eq!(vec!["3FYnjuaJRxWffA5JkfM5v2NquQMMp1Nbq6", "369yQmBL4XV8dwsAU5JRbTvJnm86Pamr3M"], addresses(&sh_wsh, 0..2));
+        assert_eq!(BitcoinFormat::P2SH_P2WSH, sh_wsh.address(0).unwrap().format());
+        assert_eq!(
+            "5221024ac2b23769fb433a321cb602853dd3597c95d0ede67976045353372054a40e1321027b6a7dd645507d775215a9035be06700e1ed8c541da9351b4bd14bd50ab6142852ae",
+            hex::encode(sh_wsh.redeem_script(0).unwrap().unwrap())
+        );
+
+        // The keys of sortedmulti() are sorted, while those of multi() keep their order
+        let sorted = descriptor(&format!("wsh(sortedmulti(1,{},{},{}))", PUBLIC_KEYS[2], PUBLIC_KEYS[0], PUBLIC_KEYS[1]));
+        let unsorted = descriptor(&format!("wsh(multi(1,{},{},{}))", PUBLIC_KEYS[2], PUBLIC_KEYS[0], PUBLIC_KEYS[1]));
+        assert_eq!("bc1q2fac9qghryxsed58nzluxny2634ydlmk5jj4my8277g6wx4g9erq59um37", unsorted.address(0).unwrap().to_string());
+        assert_ne!(sorted.address(0).unwrap(), unsorted.address(0).unwrap());
+    }
+
+    #[test]
+    fn tr() {
+        // https://github.com/bitcoin/bips/blob/master/bip-0086.mediawiki#test-vectors
+        let tr = descriptor("tr(xpub6BgBgsespWvERF3LHQu6CnqdvfEvtMcQjYrcRzx53QJjSxarj2afYWcLteoGVky7D3UKDP9QyrLprQ3VCECoY49yfdDEHGCtMMj92pReUsQ/0/*)");
+        assert_eq!(
+            vec!["bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr", "bc1p4qhjn9zdvkux4e44uhx8tc55attvtyu358kutcqkudyccelu0was9fqzwh"],
+            addresses(&tr, 0..2)
+        );
+
+        // Leaf keys may be x-only
+        let x_only = |public_key: &str| public_key[2..].to_string();
+        let expression = format!("tr({},{{pk({}),pk({})}})", PUBLIC_KEYS[0], x_only(PUBLIC_KEYS[1]), PUBLIC_KEYS[2]);
+        let tr = descriptor(&expression);
+        assert_eq!("bc1pprj5dr4rgrw835zyx8ncp9qe54n3ljcgcft0tw9mlj657udee6nq008pgf", tr.address(0).unwrap().to_string());
+        assert_eq!(tr, descriptor(&tr.to_string()));
+        assert!(tr.to_string().starts_with(&expression));
+    }
+
+    #[test]
+    fn addr_raw() {
+        let addr = descriptor("addr(bc1pprj5dr4rgrw835zyx8ncp9qe54n3ljcgcft0tw9mlj657udee6nq008pgf)");
+        let raw = descriptor(&format!("raw({})", hex::encode(addr.script_pub_key(0).unwrap())));
+        assert_eq!(addr.address(0).unwrap(), raw.address(0).unwrap());
+        assert_eq!(DescriptorError::MissingAddress("raw(deadbeef)#89f8spxm".into()), descriptor("raw(deadbeef)").address(0).unwrap_err());
+        assert!(Descriptor::<Testnet>::from_str("addr(bc1pprj5dr4rgrw835zyx8ncp9qe54n3ljcgcft0tw9mlj657udee6nq008pgf)").is_err());
+        assert!(descriptor(&format!("multi(1,{})", PUBLIC_KEYS[0])).address(0).is_err());
+    }
+
+    #[test]
+    fn input() {
+        let transaction_id = "61d520ccb74288c96bc1a2b20ea1c0d5a704776dd0164a396efec3ea7040349d";
+        let sh_wsh = descriptor(&format!("sh(wsh(sortedmulti(2,{}/0/*,{}/0/*)))", XPUB, OTHER_XPUB));
+        let input = sh_wsh.input(1, transaction_id, 0).unwrap().amount(crate::amount::BitcoinAmount(10_000)).build().unwrap();
+        assert_eq!(BitcoinFormat::P2SH_P2WSH, input.outpoint.address.as_ref().unwrap().format());
+        assert_eq!(sh_wsh.redeem_script(1).unwrap(), input.outpoint.redeem_script);
+        assert_eq!(Some(sh_wsh.script_pub_key(1).unwrap()), input.outpoint.script_pub_key);
+    }
+
+    #[test]
+    fn invalid() {
+        let invalid = [
+            // Scripts outside of their contexts
+            format!("wsh(wpkh({}))", PUBLIC_KEYS[0]),
+            format!("sh(sh(pk({})))", PUBLIC_KEYS[0]),
+            format!("wsh(wsh(pk({})))", PUBLIC_KEYS[0]),
+            format!("sh(tr({}))", PUBLIC_KEYS[0]),
+            format!("tr({},multi(1,{}))", PUBLIC_KEYS[0], PUBLIC_KEYS[1]),
+            // Uncompressed and x-only keys outside of their contexts
+            format!("wpkh({})", UNCOMPRESSED_PUBLIC_KEY),
+            format!("wsh(pk({}))", UNCOMPRESSED_PUBLIC_KEY),
+            format!("pkh({})", &PUBLIC_KEYS[0][2..]),
+            // Hardened derivation below an extended public key, invalid origins and paths
+            format!("wpkh({}/0'/*)", XPUB),
+            format!("wpkh({}/0/*')", XPUB),
+            format!("wpkh({}/*/0)", XPUB),
+            format!("wpkh([d34db33f/44'/0'/0'{}/0/*)", XPUB),
+            format!("wpkh([d34db3/44']{}/0/*)", XPUB),
+            format!("wpkh({}//0)", XPUB),
+            // Invalid thresholds, arguments and expressions
+            format!("multi(0,{})", PUBLIC_KEYS[0]),
+            format!("multi(2,{})", PUBLIC_KEYS[0]),
+            format!("pk({},{})", PUBLIC_KEYS[0], PUBLIC_KEYS[1]),
+            format!("tr({},{{pk({})}})", PUBLIC_KEYS[0], PUBLIC_KEYS[1]),
+            format!("wpkh({}", PUBLIC_KEYS[0]),
+            format!("wpkh({}))", PUBLIC_KEYS[0]),
+            format!("combo({})", PUBLIC_KEYS[0]),
+            "raw(deadbeefx)".into(),
+        ];
+        for descriptor in invalid.iter() {
+            assert!(Descriptor::<N>::from_str(descriptor).is_err(), "{}", descriptor);
+        }
+
+        assert_eq!(
+            Err(DescriptorError::InvalidContext("wpkh()".into(), "inside wsh()")),
+            Descriptor::<N>::from_str(&format!("wsh(wpkh({}))", PUBLIC_KEYS[0]))
+        );
+        // A mainnet extended public key does not describe testnet outputs
+        assert!(Descriptor::<Testnet>::from_str(&format!("wpkh({}/0/*)", XPUB)).is_err());
+    }
+}
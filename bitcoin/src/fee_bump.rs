@@ -0,0 +1,435 @@
+//! # Fee Bumping
+//!
+//! Replaces a stuck transaction with one paying a higher fee (BIP-125), or spends one of its outputs
+//! with a child paying the fee of both (CPFP).
+//! A replacement spends the inputs of the original and pays its outputs, funding the higher fee from
+//! the change output and, once the change runs out, from additional inputs selected as in
+//! [`crate::coin_selection`], which must be confirmed (rule 2). It pays at least the fee of the original
+//! and the incremental relay fee for its own size (rules 3 and 4), at a higher fee rate (rule 6),
+//! and replaces only the original (rule 5).
+//! https://github.com/bitcoin/bips/blob/master/bip-0125.mediawiki
+
+use crate::address::BitcoinAddress;
+use crate::amount::BitcoinAmount;
+use crate::coin_selection::{default_dust_threshold, CoinSelectionStrategy, CoinSelector};
+use crate::network::BitcoinNetwork;
+use crate::transaction::{
+    default_sighash, BitcoinTransaction, BitcoinTransactionInput, BitcoinTransactionOutput,
+    BitcoinTransactionParameters, Outpoint, SEQUENCE_ENABLE_RBF,
+};
+use chainlib_core::no_std::*;
+use chainlib_core::{Transaction, TransactionError};
+
+use rand::Rng;
+
+/// The default fee rate a replacement pays on Bitcoin for its own size in addition to the fee of the original,
+/// in satoshi per virtual byte, see `BitcoinNetwork::min_relay_fee_rate` for other networks
+pub const DEFAULT_INCREMENTAL_RELAY_FEE_RATE: u64 = 1;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum FeeBumpError {
+    #[error("fee rate of {0} sat/vB is below the minimum of {1} sat/vB")]
+    FeeRateTooLow(u64, u64),
+
+    #[error("output of {0} cannot pay the child fee of {1}")]
+    InsufficientFunds(i64, i64),
+
+    #[error("invalid change output {0}")]
+    InvalidChangeOutput(usize),
+
+    #[error("missing change output or change address")]
+    MissingChange,
+
+    #[error("missing output {0}")]
+    MissingOutput(u32),
+
+    #[error("missing prevout of input {0}")]
+    MissingPrevout(usize),
+
+    #[error("transaction does not signal replaceability")]
+    NotReplaceable,
+}
+
+impl From<FeeBumpError> for TransactionError {
+    fn from(error: FeeBumpError) -> Self {
+        TransactionError::Crate("FeeBump", format!("{:?}", error))
+    }
+}
+
+/// Represents the replacement of a transaction at a higher fee rate
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeeBump<N: BitcoinNetwork> {
+    transaction: BitcoinTransaction<N>,
+    prevouts: Vec<Outpoint<N>>,
+    change_output: Option<usize>,
+    change_address: Option<BitcoinAddress<N>>,
+    candidates: Vec<BitcoinTransactionInput<N>>,
+    incremental_relay_fee_rate: u64,
+}
+
+impl<N: BitcoinNetwork> FeeBump<N> {
+    /// Returns the replacement of the transaction given the outpoints it spends,
+    /// which need an amount and an address.
+    pub fn new(transaction: BitcoinTransaction<N>, prevouts: Vec<Outpoint<N>>) -> Self {
+        Self {
+            transaction,
+            prevouts,
+            change_output: None,
+            change_address: None,
+            candidates: vec![],
+            incremental_relay_fee_rate: N::min_relay_fee_rate(),
+        }
+    }
+
+    /// Sets the index of the change output the higher fee is taken from.
+    pub fn change_output(mut self, index: usize) -> Self {
+        self.change_output = Some(index);
+        self
+    }
+
+    /// Sets the address of the change, if it differs from the change output or there is none.
+    pub fn change_address(mut self, change_address: BitcoinAddress<N>) -> Self {
+        self.change_address = Some(change_address);
+        self
+    }
+
+    /// Adds a confirmed input that may be spent once the change runs out.
+    pub fn candidate(mut self, input: BitcoinTransactionInput<N>) -> Self {
+        self.candidates.push(input);
+        self
+    }
+
+    /// Sets the fee rate paid for the size of the replacement in addition to the fee of the original.
+    pub fn incremental_relay_fee_rate(mut self, incremental_relay_fee_rate: u64) -> Self {
+        self.incremental_relay_fee_rate = incremental_relay_fee_rate;
+        self
+    }
+
+    /// Returns the lowest fee rate of a replacement in satoshi per virtual byte, the fee rate
+    /// of the original over the smallest replacement plus the incremental relay fee rate.
+    pub fn minimum_fee_rate(&self) -> Result<u64, TransactionError> {
+        let (_, original_fee, _) = self.original()?;
+        let outputs = self.outputs()?;
+        let smallest = BitcoinTransaction::<N>::new(&BitcoinTransactionParameters::<N>::new(self.inputs()?, outputs)?)?;
+        Ok((original_fee as u64).div_ceil(smallest.vsize()?) + self.incremental_relay_fee_rate)
+    }
+
+    /// Returns the unsigned replacement at the fee rate in satoshi per virtual byte.
+    /// The change output keeps its position and is dropped if it falls below the dust threshold.
+    pub fn replace<R: Rng>(&self, fee_rate: u64, rng: &mut R) -> Result<BitcoinTransaction<N>, TransactionError> {
+        let (original, original_fee, original_vsize) = self.original()?;
+        if !original.signals_rbf() {
+            return Err(FeeBumpError::NotReplaceable.into());
+        }
+        let minimum_fee_rate = self.minimum_fee_rate()?;
+        if fee_rate < minimum_fee_rate {
+            return Err(FeeBumpError::FeeRateTooLow(fee_rate, minimum_fee_rate).into());
+        }
+
+        let change_address = match (&self.change_address, self.change_output) {
+            (Some(change_address), _) => change_address.clone(),
+            (None, Some(index)) => {
+                let change = &original.parameters.outputs[index];
+                BitcoinAddress::<N>::from_script_pub_key(&change.script_pub_key)?
+            }
+            (None, None) => return Err(FeeBumpError::MissingChange.into()),
+        };
+
+        let outputs = self.outputs()?;
+        let mut selector = CoinSelector::new(change_address, fee_rate).candidates(self.candidates.clone());
+        for input in self.inputs()? {
+            selector = selector.must_spend(input);
+        }
+        let selection = selector
+            .select(&outputs, CoinSelectionStrategy::BranchAndBound, rng)
+            .or_else(|_| selector.select(&outputs, CoinSelectionStrategy::Knapsack, rng))?;
+
+        let mut outputs = outputs;
+        if let Some(change) = selection.change {
+            let index = self.change_output.unwrap_or(outputs.len()).min(outputs.len());
+            outputs.insert(index, change);
+        }
+        let replacement = BitcoinTransaction::<N>::new(&BitcoinTransactionParameters {
+            version: original.parameters.version,
+            inputs: selection.inputs,
+            outputs,
+            lock_time: original.parameters.lock_time,
+            segwit_flag: false,
+        })?;
+
+        let fee = selection.fee.0;
+        let vsize = replacement.vsize()? as i64;
+        if fee < original_fee + vsize * self.incremental_relay_fee_rate as i64
+            || fee * original_vsize as i64 <= original_fee * vsize
+        {
+            return Err(TransactionError::InvalidFee(format!(
+                "{} for {} vbytes does not replace {} for {} vbytes",
+                fee, vsize, original_fee, original_vsize
+            )));
+        }
+        Ok(replacement)
+    }
+
+    /// Returns the original with the prevouts of its inputs, its fee and its virtual size.
+    fn original(&self) -> Result<(BitcoinTransaction<N>, i64, u64), TransactionError> {
+        let mut original = self.transaction.clone();
+        for (vin, input) in original.parameters.inputs.iter_mut().enumerate() {
+            input.outpoint = prevout(&self.prevouts, input, vin)?;
+        }
+        if let Some(index) = self.change_output {
+            if index >= original.parameters.outputs.len() {
+                return Err(FeeBumpError::InvalidChangeOutput(index).into());
+            }
+        }
+
+        let total_input = original
+            .parameters
+            .inputs
+            .iter()
+            .map(|input| input.outpoint.amount.map(|amount| amount.0))
+            .sum::<Option<i64>>()
+            .ok_or(TransactionError::MissingOutpointAmount)?;
+        let total_output = original.parameters.outputs.iter().map(|output| output.amount.0).sum::<i64>();
+        let vsize = original.vsize()?;
+        Ok((original, total_input - total_output, vsize))
+    }
+
+    /// Returns the inputs of the original with their prevouts and without signatures.
+    fn inputs(&self) -> Result<Vec<BitcoinTransactionInput<N>>, TransactionError> {
+        let mut inputs = vec![];
+        for (vin, input) in self.transaction.parameters.inputs.iter().enumerate() {
+            let mut input = input.clone();
+            input.outpoint = prevout(&self.prevouts, &input, vin)?;
+            input.script_sig = vec![];
+            input.witnesses = vec![];
+            input.partial_signatures = BTreeMap::new();
+            input.is_signed = false;
+            inputs.push(input);
+        }
+        Ok(inputs)
+    }
+
+    /// Returns the outputs of the original without the change output.
+    fn outputs(&self) -> Result<Vec<BitcoinTransactionOutput>, TransactionError> {
+        let mut outputs = self.transaction.parameters.outputs.clone();
+        if let Some(index) = self.change_output {
+            if index >= outputs.len() {
+                return Err(FeeBumpError::InvalidChangeOutput(index).into());
+            }
+            outputs.remove(index);
+        }
+        Ok(outputs)
+    }
+}
+
+/// Returns the prevout spent by the input.
+fn prevout<N: BitcoinNetwork>(
+    prevouts: &[Outpoint<N>],
+    input: &BitcoinTransactionInput<N>,
+    vin: usize,
+) -> Result<Outpoint<N>, FeeBumpError> {
+    prevouts
+        .iter()
+        .find(|prevout| {
+            prevout.reverse_transaction_id == input.outpoint.reverse_transaction_id
+                && prevout.index == input.outpoint.index
+        })
+        .cloned()
+        .ok_or(FeeBumpError::MissingPrevout(vin))
+}
+
+/// Returns an unsigned child spending the output of the parent to the address, paying the fee that brings
+/// the parent and the child to the package fee rate in satoshi per virtual byte.
+/// The prevouts of the parent need an amount, the redeem script is that of the spent output, if any.
+pub fn child_pays_for_parent<N: BitcoinNetwork>(
+    parent: &BitcoinTransaction<N>,
+    parent_prevouts: &[Outpoint<N>],
+    vout: u32,
+    redeem_script: Option<Vec<u8>>,
+    address: &BitcoinAddress<N>,
+    package_fee_rate: u64,
+) -> Result<BitcoinTransaction<N>, TransactionError> {
+    let (_, parent_fee, parent_vsize) = FeeBump::new(parent.clone(), parent_prevouts.to_vec()).original()?;
+    let output = parent
+        .parameters
+        .outputs
+        .get(vout as usize)
+        .ok_or(FeeBumpError::MissingOutput(vout))?;
+
+    let spent_address = match &redeem_script {
+        Some(redeem_script) => BitcoinAddress::<N>::from_script_pub_key(&output.script_pub_key)?.with_script(redeem_script)?,
+        None => BitcoinAddress::<N>::from_script_pub_key(&output.script_pub_key)?,
+    };
+    let input = BitcoinTransactionInput::<N>::new(
+        parent.to_transaction_id()?.txid,
+        vout,
+        Some(spent_address),
+        Some(output.amount),
+        redeem_script,
+        Some(output.script_pub_key.clone()),
+        Some(SEQUENCE_ENABLE_RBF.to_le_bytes().to_vec()),
+        default_sighash::<N>(),
+    )?;
+    let mut child = BitcoinTransaction::<N>::new(&BitcoinTransactionParameters::<N>::new(
+        vec![input],
+        vec![BitcoinTransactionOutput::new(address, output.amount)?],
+    )?)?;
+
+    // The child pays at least the package fee rate for its own size
+    let child_vsize = child.vsize()? as i64;
+    let package_fee = (parent_vsize as i64 + child_vsize) * package_fee_rate as i64 - parent_fee;
+    let fee = package_fee.max(child_vsize * package_fee_rate as i64);
+    let amount = output.amount.0 - fee;
+    let child_output = &mut child.parameters.outputs[0];
+    if amount < default_dust_threshold::<N>(child_output).0 {
+        return Err(FeeBumpError::InsufficientFunds(output.amount.0, fee).into());
+    }
+    child_output.amount = BitcoinAmount::from_satoshi_for::<N>(amount)?;
+    Ok(child)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::BitcoinFormat;
+    use crate::interpreter::VerificationFlags;
+    use crate::network::*;
+    use crate::private_key::BitcoinPrivateKey;
+    use crate::transaction::SignatureHash;
+    use chainlib_core::PrivateKey;
+    use core::str::FromStr;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    type N = Mainnet;
+
+    const PRIVATE_KEY: &str = "KyRv5iFPHG7iB5E4CqvMzH3WFJVhbfYK4VY7XAedd9Ys69mEsPLQ";
+    const RECIPIENT: &str = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4";
+
+    fn private_key() -> BitcoinPrivateKey<N> {
+        BitcoinPrivateKey::<N>::from_str(PRIVATE_KEY).unwrap()
+    }
+
+    fn address(format: &BitcoinFormat) -> BitcoinAddress<N> {
+        private_key().to_address(format).unwrap()
+    }
+
+    fn input(index: u32, amount: i64, sequence: u32) -> BitcoinTransactionInput<N> {
+        BitcoinTransactionInput::<N>::new(
+            vec![index as u8 + 1; 32],
+            index,
+            Some(address(&BitcoinFormat::Bech32)),
+            Some(BitcoinAmount(amount)),
+            None,
+            None,
+            Some(sequence.to_le_bytes().to_vec()),
+            SignatureHash::SIGHASH_ALL,
+        )
+        .unwrap()
+    }
+
+    /// Returns the signed original paying 500,000 satoshi with a change output, parsed without prevouts.
+    fn stuck_transaction(sequence: u32, change: i64) -> (BitcoinTransaction<N>, Vec<Outpoint<N>>) {
+        let inputs = vec![input(0, 300_000, sequence), input(1, 400_000, sequence)];
+        let prevouts = inputs.iter().map(|input| input.outpoint.clone()).collect();
+        let recipient = BitcoinAddress::<N>::from_str(RECIPIENT).unwrap();
+        let outputs = vec![
+            BitcoinTransactionOutput::new(&recipient, BitcoinAmount(500_000)).unwrap(),
+            BitcoinTransactionOutput::new(&address(&BitcoinFormat::Bech32), BitcoinAmount(change)).unwrap(),
+        ];
+        let mut transaction =
+            BitcoinTransaction::<N>::new(&BitcoinTransactionParameters::<N>::new(inputs, outputs).unwrap()).unwrap();
+        let signed = transaction.sign_with_private_key(&private_key()).unwrap();
+        (BitcoinTransaction::<N>::from_bytes(&signed).unwrap(), prevouts)
+    }
+
+    fn fee(transaction: &BitcoinTransaction<N>) -> i64 {
+        let total_input = transaction.parameters.inputs.iter().map(|input| input.outpoint.amount.unwrap().0).sum::<i64>();
+        total_input - transaction.parameters.outputs.iter().map(|output| output.amount.0).sum::<i64>()
+    }
+
+    fn sign_and_verify(transaction: &BitcoinTransaction<N>) {
+        let mut signed = transaction.clone();
+        signed.sign_with_private_key(&private_key()).unwrap();
+        assert!(signed.verify(VerificationFlags::STANDARD).iter().all(|result| result.is_ok()));
+    }
+
+    #[test]
+    fn replace_reducing_change() {
+        let (original, prevouts) = stuck_transaction(SEQUENCE_ENABLE_RBF, 199_000);
+        let bump = FeeBump::new(original.clone(), prevouts).change_output(1);
+        let minimum_fee_rate = bump.minimum_fee_rate().unwrap();
+        let replacement = bump.replace(20, &mut StdRng::seed_from_u64(0)).unwrap();
+
+        let vsize = replacement.vsize().unwrap() as i64;
+        assert!(minimum_fee_rate < 20);
+        assert_eq!(original.parameters.inputs.len(), replacement.parameters.inputs.len());
+        assert_eq!(original.parameters.outputs[0], replacement.parameters.outputs[0]);
+        assert!(replacement.parameters.outputs[1].amount.0 < 199_000);
+        assert!(fee(&replacement) >= 20 * vsize);
+        assert!(fee(&replacement) >= 1_000 + vsize);
+        sign_and_verify(&replacement);
+    }
+
+    #[test]
+    fn replace_adding_inputs() {
+        let (original, prevouts) = stuck_transaction(SEQUENCE_ENABLE_RBF, 199_000);
+        let bump = FeeBump::new(original, prevouts)
+            .change_output(1)
+            .candidate(input(2, 1_000_000, SEQUENCE_ENABLE_RBF));
+        let replacement = bump.replace(1_500, &mut StdRng::seed_from_u64(0)).unwrap();
+
+        let vsize = replacement.vsize().unwrap() as i64;
+        assert_eq!(3, replacement.parameters.inputs.len());
+        assert!(fee(&replacement) >= 1_500 * vsize);
+        sign_and_verify(&replacement);
+
+        // Without candidates the change cannot fund the fee
+        let (original, prevouts) = stuck_transaction(SEQUENCE_ENABLE_RBF, 199_000);
+        assert!(FeeBump::new(original, prevouts).change_output(1).replace(1_500, &mut StdRng::seed_from_u64(0)).is_err());
+    }
+
+    #[test]
+    fn invalid_replacements() {
+        let (original, prevouts) = stuck_transaction(0xffff_fffe, 199_000);
+        let result = FeeBump::new(original, prevouts).change_output(1).replace(20, &mut StdRng::seed_from_u64(0));
+        assert_eq!(TransactionError::from(FeeBumpError::NotReplaceable).to_string(), result.unwrap_err().to_string());
+
+        // The original pays 1,000 satoshi
+        let (original, prevouts) = stuck_transaction(SEQUENCE_ENABLE_RBF, 199_000);
+        let bump = FeeBump::new(original.clone(), prevouts.clone()).change_output(1);
+        let minimum_fee_rate = bump.minimum_fee_rate().unwrap();
+        let result = bump.replace(minimum_fee_rate - 1, &mut StdRng::seed_from_u64(0));
+        let expected = FeeBumpError::FeeRateTooLow(minimum_fee_rate - 1, minimum_fee_rate);
+        assert_eq!(TransactionError::from(expected).to_string(), result.unwrap_err().to_string());
+
+        let result = FeeBump::new(original.clone(), prevouts.clone()).replace(20, &mut StdRng::seed_from_u64(0));
+        assert_eq!(TransactionError::from(FeeBumpError::MissingChange).to_string(), result.unwrap_err().to_string());
+
+        let result = FeeBump::new(original, prevouts[..1].to_vec()).change_output(1).replace(20, &mut StdRng::seed_from_u64(0));
+        assert_eq!(TransactionError::from(FeeBumpError::MissingPrevout(1)).to_string(), result.unwrap_err().to_string());
+    }
+
+    #[test]
+    fn cpfp() {
+        let (parent, prevouts) = stuck_transaction(SEQUENCE_ENABLE_RBF, 199_000);
+        let parent_fee = 1_000;
+        let parent_vsize = FeeBump::new(parent.clone(), prevouts.clone()).original().unwrap().2 as i64;
+        let address = address(&BitcoinFormat::P2TR);
+        let child = child_pays_for_parent(&parent, &prevouts, 1, None, &address, 30).unwrap();
+
+        let child_vsize = child.vsize().unwrap() as i64;
+        let child_fee = 199_000 - child.parameters.outputs[0].amount.0;
+        assert!(parent_fee + child_fee >= 30 * (parent_vsize + child_vsize));
+        assert!(parent_fee + child_fee < 30 * (parent_vsize + child_vsize + 1));
+        assert_eq!(parent.to_transaction_id().unwrap().txid, {
+            let mut txid = child.parameters.inputs[0].outpoint.reverse_transaction_id.clone();
+            txid.reverse();
+            txid
+        });
+        sign_and_verify(&child);
+
+        let result = child_pays_for_parent(&parent, &prevouts, 2, None, &address, 30);
+        assert_eq!(TransactionError::from(FeeBumpError::MissingOutput(2)).to_string(), result.unwrap_err().to_string());
+        assert!(child_pays_for_parent(&parent, &prevouts, 1, None, &address, 2_000).is_err());
+    }
+}
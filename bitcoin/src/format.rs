@@ -1,6 +1,6 @@
 use crate::network::BitcoinNetwork;
 use chainlib_core::no_std::*;
-use chainlib_core::{AddressError, Format};
+use chainlib_core::{AddressError, ExtendedPublicKey, Format, FormatError};
 
 use core::fmt;
 use serde::Serialize;
@@ -12,13 +12,17 @@ pub enum BitcoinFormat {
     /// Pay-to-Pubkey Hash, e.g. 1NoZQSmjYHUZMbqLerwmT4xfe8A6mAo8TT
     P2PKH,
     /// Pay-to-Script Hash, e.g. 34AgLJhwXrvmkZS1o5TrcdeevMt22Nar53
-    //P2SH,
+    P2SH,
     /// Pay-to-Witness-Script Hash, e.g. bc1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3qccfmv3
     P2WSH,
     /// SegWit Pay-to-Witness-Public-Key Hash, e.g. 34AgLJhwXrvmkZS1o5TrcdeevMt22Nar53
     P2SH_P2WPKH,
-    /// Bech32, e.g. bc1pw508d6qejxtdg4y5r3zarvary0c5xw7kw508d6qejxtdg4y5r3zarvary0c5xw7k7grplx
+    /// SegWit Pay-to-Witness-Script Hash nested in P2SH, e.g. 31iXMTVFX7qKnPnGVx2ZmJYWuNy3BiCNHS
+    P2SH_P2WSH,
+    /// Bech32, e.g. bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4
     Bech32,
+    /// Pay-to-Taproot (BIP-86 key path), e.g. bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr
+    P2TR,
 }
 
 impl Format for BitcoinFormat {}
@@ -30,6 +34,7 @@ impl BitcoinFormat {
     }
 
     /// Returns the format of the given address prefix.
+    /// Script hash addresses are reported as P2SH_P2WPKH, as the prefix does not reveal the script.
     pub fn from_address_prefix(prefix: &[u8]) -> Result<Self, AddressError> {
         if prefix.len() < 2 {
             return Err(AddressError::InvalidPrefix(String::from_utf8(prefix.to_vec())?));
@@ -41,15 +46,29 @@ impl BitcoinFormat {
             _ => return Err(AddressError::InvalidPrefix(String::from_utf8(prefix.to_vec())?)),
         }
     }
+
+    /// Returns the format implied by the version bytes of the given extended public key,
+    /// i.e. xpub/tpub for P2PKH, ypub/upub for P2SH_P2WPKH and zpub/vpub for Bech32.
+    pub fn from_extended_public_key(extended_public_key: &ExtendedPublicKey) -> Result<Self, FormatError> {
+        match extended_public_key.version() {
+            [0x04, 0x88, 0xB2, 0x1E] | [0x04, 0x35, 0x87, 0xCF] => Ok(BitcoinFormat::P2PKH),
+            [0x04, 0x9D, 0x7C, 0xB2] | [0x04, 0x4A, 0x52, 0x62] => Ok(BitcoinFormat::P2SH_P2WPKH),
+            [0x04, 0xB2, 0x47, 0x46] | [0x04, 0x5F, 0x1C, 0xF6] => Ok(BitcoinFormat::Bech32),
+            version => Err(FormatError::InvalidVersionBytes(version.to_vec())),
+        }
+    }
 }
 
 impl fmt::Display for BitcoinFormat {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             BitcoinFormat::P2PKH => write!(f, "p2pkh"),
+            BitcoinFormat::P2SH => write!(f, "p2sh"),
             BitcoinFormat::P2WSH => write!(f, "p2wsh"),
             BitcoinFormat::P2SH_P2WPKH => write!(f, "p2sh_p2wpkh"),
+            BitcoinFormat::P2SH_P2WSH => write!(f, "p2sh_p2wsh"),
             BitcoinFormat::Bech32 => write!(f, "bech32"),
+            BitcoinFormat::P2TR => write!(f, "p2tr"),
         }
     }
 }
@@ -0,0 +1,2137 @@
+//! # Interpreter
+//!
+//! Verification of transaction inputs, executing their script sig and witness against the script
+//! public key of the spent output as Bitcoin Core does.
+//!
+//! - Legacy outputs run the script sig, then the script public key and, for P2SH outputs, the redeem
+//!   script on the stack left by the script sig (BIP-16).
+//! - SegWit v0 programs run the witness script of P2WSH or the implied P2PKH script of P2WPKH,
+//!   signed with the BIP-143 signature hash.
+//! - Taproot programs verify a Schnorr signature for the output key, or run the tapscript leaf proven
+//!   by the control block (BIP-341, BIP-342).
+//!
+//! The rules soft forked in over time and the stricter rules of standardness (relay policy) are selected
+//! with [`VerificationFlags`], using the flags and error names of Bitcoin Core. An invalid input reports
+//! the error with the opcode being executed when the script failed, if any.
+
+use crate::amount::BitcoinAmount;
+use crate::network::BitcoinNetwork;
+use crate::script::{script_number, Instruction, Instructions, Opcode, Script};
+use crate::sighash_cache::SignatureHashCache;
+use crate::taproot::{schnorr_verify, tagged_hash, ControlBlock, TapLeaf, TAPROOT_LEAF_TAPSCRIPT};
+use crate::transaction::{read_variable_length_integer, variable_length_integer, BitcoinTransaction, SIGHASH_FORKID};
+use chainlib_core::crypto::hash160;
+use chainlib_core::libsecp256k1::{self, Message, PublicKey, Signature};
+use chainlib_core::no_std::*;
+use chainlib_core::TransactionError;
+
+use core::{
+    fmt,
+    ops::{BitOr, BitOrAssign},
+    str::FromStr,
+};
+use ripemd::Ripemd160;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+/// The maximum size of a legacy or SegWit v0 script
+const MAX_SCRIPT_SIZE: usize = 10_000;
+/// The maximum size of a stack element
+const MAX_SCRIPT_ELEMENT_SIZE: usize = 520;
+/// The maximum number of non-push opcodes of a legacy or SegWit v0 script
+const MAX_OPS_PER_SCRIPT: usize = 201;
+/// The maximum number of elements on the stack and altstack together
+const MAX_STACK_SIZE: usize = 1_000;
+/// The maximum number of public keys of OP_CHECKMULTISIG
+const MAX_PUBKEYS_PER_MULTISIG: i64 = 20;
+/// Lock times below the threshold are block heights, above it timestamps
+const LOCKTIME_THRESHOLD: i64 = 500_000_000;
+/// The sequence of an input that disables the lock time of its transaction
+const SEQUENCE_FINAL: u32 = 0xffff_ffff;
+/// The sequence bit disabling the relative lock time of an input (BIP-68)
+const SEQUENCE_LOCKTIME_DISABLE_FLAG: i64 = 1 << 31;
+/// The sequence bit of relative lock times in units of 512 seconds rather than blocks (BIP-68)
+const SEQUENCE_LOCKTIME_TYPE_FLAG: i64 = 1 << 22;
+/// The sequence bits holding the relative lock time (BIP-68)
+const SEQUENCE_LOCKTIME_MASK: i64 = 0x0000_ffff;
+/// The first byte of the annex, the last witness element of a Taproot spend
+const ANNEX_TAG: u8 = 0x50;
+/// The validation weight consumed by each signature checked in tapscript
+const VALIDATION_WEIGHT_PER_SIGOP_PASSED: i64 = 50;
+/// The validation weight budget of a tapscript in addition to the size of its witness
+const VALIDATION_WEIGHT_OFFSET: i64 = 50;
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum InterpreterError {
+    #[error("script evaluated without error but finished with a false/empty top stack element")]
+    EvalFalse,
+
+    #[error("OP_RETURN was encountered")]
+    OpReturn,
+
+    #[error("script is too big")]
+    ScriptSize,
+
+    #[error("push value size limit exceeded")]
+    PushSize,
+
+    #[error("operation limit exceeded")]
+    OpCount,
+
+    #[error("stack size limit exceeded")]
+    StackSize,
+
+    #[error("signature count negative or greater than pubkey count")]
+    SigCount,
+
+    #[error("pubkey count negative or limit exceeded")]
+    PubkeyCount,
+
+    #[error("script failed an OP_VERIFY operation")]
+    Verify,
+
+    #[error("script failed an OP_EQUALVERIFY operation")]
+    EqualVerify,
+
+    #[error("script failed an OP_CHECKMULTISIGVERIFY operation")]
+    CheckMultisigVerify,
+
+    #[error("script failed an OP_CHECKSIGVERIFY operation")]
+    CheckSigVerify,
+
+    #[error("script failed an OP_NUMEQUALVERIFY operation")]
+    NumEqualVerify,
+
+    #[error("opcode missing or not understood")]
+    BadOpcode,
+
+    #[error("attempted to use a disabled opcode")]
+    DisabledOpcode,
+
+    #[error("operation not valid with the current stack size")]
+    InvalidStackOperation,
+
+    #[error("operation not valid with the current altstack size")]
+    InvalidAltstackOperation,
+
+    #[error("invalid OP_IF construction")]
+    UnbalancedConditional,
+
+    #[error("negative locktime")]
+    NegativeLocktime,
+
+    #[error("locktime requirement not satisfied")]
+    UnsatisfiedLocktime,
+
+    #[error("signature hash type missing or not understood")]
+    SigHashType,
+
+    #[error("signature must use SIGHASH_FORKID")]
+    MustUseForkId,
+
+    #[error("non-canonical DER signature")]
+    SigDer,
+
+    #[error("data push larger than necessary")]
+    MinimalData,
+
+    #[error("only push operators allowed in signatures")]
+    SigPushOnly,
+
+    #[error("non-canonical signature: S value is unnecessarily high")]
+    SigHighS,
+
+    #[error("dummy CHECKMULTISIG argument must be zero")]
+    SigNullDummy,
+
+    #[error("public key is neither compressed or uncompressed")]
+    PubkeyType,
+
+    #[error("stack size must be exactly one after execution")]
+    CleanStack,
+
+    #[error("OP_IF/NOTIF argument must be minimal")]
+    MinimalIf,
+
+    #[error("signature must be zero for failed CHECK(MULTI)SIG operation")]
+    NullFail,
+
+    #[error("NOPx reserved for soft-fork upgrades")]
+    DiscourageUpgradableNops,
+
+    #[error("witness version reserved for soft-fork upgrades")]
+    DiscourageUpgradableWitnessProgram,
+
+    #[error("taproot version reserved for soft-fork upgrades")]
+    DiscourageUpgradableTaprootVersion,
+
+    #[error("OP_SUCCESSx reserved for soft-fork upgrades")]
+    DiscourageOpSuccess,
+
+    #[error("public key version reserved for soft-fork upgrades")]
+    DiscourageUpgradablePubkeyType,
+
+    #[error("witness program has incorrect length")]
+    WitnessProgramWrongLength,
+
+    #[error("witness program was passed an empty witness")]
+    WitnessProgramWitnessEmpty,
+
+    #[error("witness program hash mismatch")]
+    WitnessProgramMismatch,
+
+    #[error("witness requires empty scriptSig")]
+    WitnessMalleated,
+
+    #[error("witness requires only-redeemscript scriptSig")]
+    WitnessMalleatedP2sh,
+
+    #[error("witness provided for non-witness script")]
+    WitnessUnexpected,
+
+    #[error("using non-compressed keys in segwit")]
+    WitnessPubkeyType,
+
+    #[error("invalid Schnorr signature size")]
+    SchnorrSigSize,
+
+    #[error("invalid Schnorr signature hash type")]
+    SchnorrSigHashType,
+
+    #[error("invalid Schnorr signature")]
+    SchnorrSig,
+
+    #[error("invalid Taproot control block size")]
+    TaprootWrongControlSize,
+
+    #[error("too much signature validation relative to witness weight")]
+    TapscriptValidationWeight,
+
+    #[error("OP_CHECKMULTISIG(VERIFY) is not available in tapscript")]
+    TapscriptCheckMultisig,
+
+    #[error("OP_IF/NOTIF argument must be minimal in tapscript")]
+    TapscriptMinimalIf,
+
+    #[error("using OP_CODESEPARATOR in non-witness script")]
+    OpCodeSeparator,
+
+    #[error("signature is found in scriptCode")]
+    SigFindAndDelete,
+
+    #[error("script number overflow or non-minimally encoded script number")]
+    UnknownError,
+
+    #[error("invalid verification flag {0}")]
+    InvalidFlag(String),
+
+    #[error("input {0} does not exist")]
+    InvalidInputIndex(usize),
+
+    #[error("invalid witness of input {0}")]
+    InvalidWitness(usize),
+
+    #[error("missing outpoint amount")]
+    MissingOutpointAmount,
+
+    #[error("missing outpoint script public key")]
+    MissingOutpointScriptPublicKey,
+}
+
+impl From<InterpreterError> for TransactionError {
+    fn from(error: InterpreterError) -> Self {
+        TransactionError::Crate("Interpreter", format!("{:?}", error))
+    }
+}
+
+/// Represents the failure of an input, with the opcode being executed when its script failed
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+#[error("{error}{}", at_opcode(.opcode))]
+pub struct ScriptFailure {
+    /// The rule the input broke
+    pub error: InterpreterError,
+    /// The byte of the failing opcode, or none if the input failed outside of script execution
+    /// (e.g. a false result, a witness program mismatch or an invalid Taproot key path signature)
+    pub opcode: Option<u8>,
+}
+
+impl From<InterpreterError> for ScriptFailure {
+    fn from(error: InterpreterError) -> Self {
+        Self { error, opcode: None }
+    }
+}
+
+impl From<ScriptFailure> for TransactionError {
+    fn from(failure: ScriptFailure) -> Self {
+        TransactionError::Crate("Interpreter", format!("{:?}", failure))
+    }
+}
+
+/// Returns the description of the failing opcode of a script failure.
+fn at_opcode(opcode: &Option<u8>) -> String {
+    match opcode {
+        Some(byte @ 0x01..=0x4b) => format!(" at push of {} bytes", byte),
+        Some(byte) => match Opcode::from_byte(*byte) {
+            Some(opcode) => format!(" at {}", opcode),
+            None => format!(" at 0x{:02x}", byte),
+        },
+        None => String::new(),
+    }
+}
+
+/// Represents the set of rules a script is verified with, using the flag bits of Bitcoin Core
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct VerificationFlags(u32);
+
+macro_rules! verification_flags {
+    ($($(#[doc = $doc:expr])* $flag:ident = $bit:expr,)*) => {
+        impl VerificationFlags {
+            $($(#[doc = $doc])* pub const $flag: Self = Self(1 << $bit);)*
+
+            /// Every flag, in order of their bits
+            const FLAGS: &'static [(&'static str, Self)] = &[$((stringify!($flag), Self::$flag),)*];
+        }
+    };
+}
+
+verification_flags! {
+    /// Evaluate P2SH redeem scripts (BIP-16)
+    P2SH = 0,
+    /// Require strictly encoded signatures with a defined hash type and compressed or uncompressed public keys
+    STRICTENC = 1,
+    /// Require strict DER signatures (BIP-66)
+    DERSIG = 2,
+    /// Require signatures with a low S value (BIP-146)
+    LOW_S = 3,
+    /// Require the extra argument of OP_CHECKMULTISIG to be empty (BIP-147)
+    NULLDUMMY = 4,
+    /// Require the script sig to only push data
+    SIGPUSHONLY = 5,
+    /// Require data and numbers to be pushed minimally
+    MINIMALDATA = 6,
+    /// Reject the upgradable opcodes OP_NOP1 and OP_NOP4 to OP_NOP10
+    DISCOURAGE_UPGRADABLE_NOPS = 7,
+    /// Require a single element on the stack after execution
+    CLEANSTACK = 8,
+    /// Enforce OP_CHECKLOCKTIMEVERIFY (BIP-65)
+    CHECKLOCKTIMEVERIFY = 9,
+    /// Enforce OP_CHECKSEQUENCEVERIFY (BIP-112)
+    CHECKSEQUENCEVERIFY = 10,
+    /// Evaluate SegWit programs (BIP-141)
+    WITNESS = 11,
+    /// Reject spends of witness programs of unknown versions
+    DISCOURAGE_UPGRADABLE_WITNESS_PROGRAM = 12,
+    /// Require the argument of OP_IF and OP_NOTIF to be empty or 0x01 in SegWit v0 scripts
+    MINIMALIF = 13,
+    /// Require failed signatures to be empty (BIP-146)
+    NULLFAIL = 14,
+    /// Require compressed public keys in SegWit v0 scripts
+    WITNESS_PUBKEYTYPE = 15,
+    /// Reject OP_CODESEPARATOR and signatures found in the script code of legacy scripts
+    CONST_SCRIPTCODE = 16,
+    /// Evaluate Taproot programs and tapscript (BIP-341, BIP-342)
+    TAPROOT = 17,
+    /// Reject Taproot script path spends of unknown leaf versions
+    DISCOURAGE_UPGRADABLE_TAPROOT_VERSION = 18,
+    /// Reject tapscripts with OP_SUCCESSx opcodes
+    DISCOURAGE_OP_SUCCESS = 19,
+    /// Reject tapscript public keys of unknown types
+    DISCOURAGE_UPGRADABLE_PUBKEYTYPE = 20,
+}
+
+impl VerificationFlags {
+    /// No rules beyond those of the original protocol
+    pub const NONE: Self = Self(0);
+
+    /// The rules enforced by consensus for every block since the Taproot soft fork
+    pub const CONSENSUS: Self = Self(
+        Self::P2SH.0
+            | Self::DERSIG.0
+            | Self::NULLDUMMY.0
+            | Self::CHECKLOCKTIMEVERIFY.0
+            | Self::CHECKSEQUENCEVERIFY.0
+            | Self::WITNESS.0
+            | Self::TAPROOT.0,
+    );
+
+    /// The rules enforced by nodes before relaying a transaction (standardness)
+    pub const STANDARD: Self = Self(
+        Self::CONSENSUS.0
+            | Self::STRICTENC.0
+            | Self::MINIMALDATA.0
+            | Self::DISCOURAGE_UPGRADABLE_NOPS.0
+            | Self::CLEANSTACK.0
+            | Self::MINIMALIF.0
+            | Self::NULLFAIL.0
+            | Self::LOW_S.0
+            | Self::DISCOURAGE_UPGRADABLE_WITNESS_PROGRAM.0
+            | Self::WITNESS_PUBKEYTYPE.0
+            | Self::CONST_SCRIPTCODE.0
+            | Self::DISCOURAGE_UPGRADABLE_TAPROOT_VERSION.0
+            | Self::DISCOURAGE_OP_SUCCESS.0
+            | Self::DISCOURAGE_UPGRADABLE_PUBKEYTYPE.0,
+    );
+
+    /// Every rule, including SIGPUSHONLY which standardness checks separately
+    pub const ALL: Self = Self((1 << 21) - 1);
+
+    /// Returns the flag bits.
+    pub fn bits(&self) -> u32 {
+        self.0
+    }
+
+    /// Returns true if every given flag is set.
+    pub fn contains(&self, flags: Self) -> bool {
+        self.0 & flags.0 == flags.0
+    }
+
+    /// Returns true if any given flag is set.
+    pub fn intersects(&self, flags: Self) -> bool {
+        self.0 & flags.0 != 0
+    }
+
+    /// Sets the given flags.
+    pub fn insert(&mut self, flags: Self) {
+        self.0 |= flags.0;
+    }
+
+    /// Clears the given flags.
+    pub fn remove(&mut self, flags: Self) {
+        self.0 &= !flags.0;
+    }
+}
+
+impl BitOr for VerificationFlags {
+    type Output = Self;
+
+    fn bitor(self, flags: Self) -> Self {
+        Self(self.0 | flags.0)
+    }
+}
+
+impl BitOrAssign for VerificationFlags {
+    fn bitor_assign(&mut self, flags: Self) {
+        self.insert(flags);
+    }
+}
+
+impl FromStr for VerificationFlags {
+    type Err = InterpreterError;
+
+    /// Returns the flags of a comma separated list of Bitcoin Core flag names, e.g. `P2SH,WITNESS`.
+    /// An empty list or `NONE` has no flags.
+    fn from_str(names: &str) -> Result<Self, Self::Err> {
+        names
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty() && *name != "NONE")
+            .try_fold(Self::NONE, |flags, name| {
+                match Self::FLAGS.iter().find(|(flag_name, _)| *flag_name == name) {
+                    Some((_, flag)) => Ok(flags | *flag),
+                    None => Err(InterpreterError::InvalidFlag(name.into())),
+                }
+            })
+    }
+}
+
+impl fmt::Display for VerificationFlags {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names: Vec<&str> = Self::FLAGS
+            .iter()
+            .filter(|(_, flag)| self.contains(*flag))
+            .map(|(name, _)| *name)
+            .collect();
+        match names.is_empty() {
+            true => write!(f, "NONE"),
+            false => write!(f, "{}", names.join(",")),
+        }
+    }
+}
+
+/// The rules a script is executed under
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum SigVersion {
+    /// Script sigs, script public keys and P2SH redeem scripts
+    Base,
+    /// P2WPKH and P2WSH scripts
+    WitnessV0,
+    /// Taproot script path spends of leaf version 0xc0
+    Tapscript,
+}
+
+/// The state of a witness program spend shared by its signature checks
+#[derive(Debug, Default)]
+struct ExecutionData {
+    /// The opcode position of the last executed OP_CODESEPARATOR
+    code_separator_position: u32,
+    /// The annex of a Taproot spend, with its 0x50 tag
+    annex: Option<Vec<u8>>,
+    /// The leaf hash of a Taproot script path spend
+    tapleaf_hash: Option<[u8; 32]>,
+    /// The validation weight left for signature checks in tapscript
+    validation_weight_left: i64,
+}
+
+/// Verifies the script sig and witness of the input against the script public key of its outpoint,
+/// given the flags of the rules to enforce. SegWit inputs require the amount of their outpoint, and
+/// Taproot inputs the amount and script public key of the outpoints of every input (BIP-341).
+pub fn verify_input<N: BitcoinNetwork>(
+    transaction: &BitcoinTransaction<N>,
+    vin: usize,
+    flags: VerificationFlags,
+) -> Result<(), ScriptFailure> {
+    let cache = SignatureHashCache::new(transaction).map_err(|_| InterpreterError::UnknownError)?;
+    verify_input_with_cache(transaction, &cache, vin, flags)
+}
+
+/// Verifies the input as `verify_input`, given the signature hash midstates of the transaction.
+pub(crate) fn verify_input_with_cache<N: BitcoinNetwork>(
+    transaction: &BitcoinTransaction<N>,
+    cache: &SignatureHashCache,
+    vin: usize,
+    flags: VerificationFlags,
+) -> Result<(), ScriptFailure> {
+    let input = match transaction.parameters.inputs.get(vin) {
+        Some(input) => input,
+        None => return Err(InterpreterError::InvalidInputIndex(vin).into()),
+    };
+    let script_pub_key = match &input.outpoint.script_pub_key {
+        Some(script_pub_key) => script_pub_key,
+        None => return Err(InterpreterError::MissingOutpointScriptPublicKey.into()),
+    };
+
+    // The witness elements are stored with their compact size prefix
+    let witness = input
+        .witnesses
+        .iter()
+        .map(|element| {
+            let length = read_variable_length_integer(&element[..]).ok()?;
+            let prefix = variable_length_integer(length as u64).ok()?.len();
+            match element.len() == prefix + length {
+                true => Some(element[prefix..].to_vec()),
+                false => None,
+            }
+        })
+        .collect::<Option<Vec<Vec<u8>>>>()
+        .ok_or(InterpreterError::InvalidWitness(vin))?;
+    let witness_size = variable_length_integer(witness.len() as u64)
+        .map_err(|_| InterpreterError::InvalidWitness(vin))?
+        .len()
+        + input.witnesses.iter().map(Vec::len).sum::<usize>();
+
+    let interpreter = Interpreter {
+        transaction,
+        cache,
+        vin,
+        amount: input.outpoint.amount,
+        flags,
+    };
+    interpreter.verify_script(&input.script_sig, script_pub_key, &witness, witness_size)
+}
+
+/// Executes the scripts of an input of a transaction
+struct Interpreter<'a, N: BitcoinNetwork> {
+    transaction: &'a BitcoinTransaction<N>,
+    cache: &'a SignatureHashCache,
+    vin: usize,
+    amount: Option<BitcoinAmount>,
+    flags: VerificationFlags,
+}
+
+impl<'a, N: BitcoinNetwork> Interpreter<'a, N> {
+    /// Verifies the script sig and witness against the script public key.
+    fn verify_script(
+        &self,
+        script_sig: &[u8],
+        script_pub_key: &[u8],
+        witness: &[Vec<u8>],
+        witness_size: usize,
+    ) -> Result<(), ScriptFailure> {
+        let flags = self.flags;
+        if flags.contains(VerificationFlags::SIGPUSHONLY) && !is_push_only(script_sig) {
+            return Err(InterpreterError::SigPushOnly.into());
+        }
+
+        // The script sig and script public key are evaluated in turn on the same stack (CVE-2010-5141)
+        let mut stack = vec![];
+        self.eval_script(&mut stack, script_sig, SigVersion::Base, &mut ExecutionData::default())?;
+        let p2sh_stack = stack.clone();
+        self.eval_script(&mut stack, script_pub_key, SigVersion::Base, &mut ExecutionData::default())?;
+        if !stack.last().is_some_and(|top| cast_to_bool(top)) {
+            return Err(InterpreterError::EvalFalse.into());
+        }
+
+        let mut had_witness = false;
+        if flags.contains(VerificationFlags::WITNESS) {
+            if let Some((version, program)) = witness_program(script_pub_key) {
+                had_witness = true;
+                // The script sig must be empty, or the transaction id would be malleable
+                if !script_sig.is_empty() {
+                    return Err(InterpreterError::WitnessMalleated.into());
+                }
+                self.verify_witness_program(witness, witness_size, version, program, false)?;
+                // The witness program leaves a single element, bypassing the clean stack check
+                stack.truncate(1);
+            }
+        }
+
+        if flags.contains(VerificationFlags::P2SH) && Script::from(script_pub_key).is_p2sh() {
+            if !is_push_only(script_sig) {
+                return Err(InterpreterError::SigPushOnly.into());
+            }
+
+            // The redeem script runs on the stack left by the script sig, which cannot be empty
+            // as the script public key succeeded
+            stack = p2sh_stack;
+            let redeem_script = stack.pop().unwrap_or_default();
+            self.eval_script(&mut stack, &redeem_script, SigVersion::Base, &mut ExecutionData::default())?;
+            if !stack.last().is_some_and(|top| cast_to_bool(top)) {
+                return Err(InterpreterError::EvalFalse.into());
+            }
+
+            if flags.contains(VerificationFlags::WITNESS) {
+                if let Some((version, program)) = witness_program(&redeem_script) {
+                    had_witness = true;
+                    // The script sig must be exactly the push of the redeem script
+                    if script_sig != serialized_push(&redeem_script) {
+                        return Err(InterpreterError::WitnessMalleatedP2sh.into());
+                    }
+                    self.verify_witness_program(witness, witness_size, version, program, true)?;
+                    stack.truncate(1);
+                }
+            }
+        }
+
+        // Only checked after P2SH and witness evaluation, whose arguments stay on the legacy stack
+        if flags.contains(VerificationFlags::CLEANSTACK) && stack.len() != 1 {
+            return Err(InterpreterError::CleanStack.into());
+        }
+
+        if flags.contains(VerificationFlags::WITNESS) && !had_witness && !witness.is_empty() {
+            return Err(InterpreterError::WitnessUnexpected.into());
+        }
+
+        Ok(())
+    }
+
+    /// Verifies the witness against the witness program of the given version (BIP-141, BIP-341).
+    fn verify_witness_program(
+        &self,
+        witness: &[Vec<u8>],
+        witness_size: usize,
+        version: u8,
+        program: &[u8],
+        is_p2sh: bool,
+    ) -> Result<(), ScriptFailure> {
+        let mut stack = witness.to_vec();
+        let mut execution = ExecutionData::default();
+
+        match (version, program.len()) {
+            // P2WSH commits to the SHA-256 of the witness script, the last witness element
+            (0, 32) => {
+                let witness_script = stack.pop().ok_or(InterpreterError::WitnessProgramWitnessEmpty)?;
+                if Sha256::digest(&witness_script)[..] != program[..] {
+                    return Err(InterpreterError::WitnessProgramMismatch.into());
+                }
+                self.execute_witness_script(stack, &witness_script, SigVersion::WitnessV0, &mut execution)
+            }
+            // P2WPKH commits to the HASH160 of the public key, spent with a signature and the public key
+            (0, 20) => {
+                if stack.len() != 2 {
+                    return Err(InterpreterError::WitnessProgramMismatch.into());
+                }
+                let script = Script::new()
+                    .push_opcode(Opcode::OP_DUP)
+                    .push_opcode(Opcode::OP_HASH160)
+                    .push_slice(program)
+                    .push_opcode(Opcode::OP_EQUALVERIFY)
+                    .push_opcode(Opcode::OP_CHECKSIG);
+                self.execute_witness_script(stack, script.as_bytes(), SigVersion::WitnessV0, &mut execution)
+            }
+            (0, _) => Err(InterpreterError::WitnessProgramWrongLength.into()),
+            // P2TR commits to the x-only output key, which cannot be nested in P2SH
+            (1, 32) if !is_p2sh => {
+                if !self.flags.contains(VerificationFlags::TAPROOT) {
+                    return Ok(());
+                }
+                if stack.is_empty() {
+                    return Err(InterpreterError::WitnessProgramWitnessEmpty.into());
+                }
+                if stack.len() >= 2 && stack.last().and_then(|element| element.first()) == Some(&ANNEX_TAG) {
+                    execution.annex = stack.pop();
+                }
+
+                if stack.len() == 1 {
+                    return Ok(self.check_schnorr_signature(&stack[0], program, &execution)?);
+                }
+
+                let control = stack.pop().unwrap_or_default();
+                let script = stack.pop().unwrap_or_default();
+                let control_block =
+                    ControlBlock::deserialize(&control).map_err(|_| InterpreterError::TaprootWrongControlSize)?;
+                let mut output_key = [0u8; 32];
+                output_key.copy_from_slice(program);
+                if !control_block.verify(&output_key, &script) {
+                    return Err(InterpreterError::WitnessProgramMismatch.into());
+                }
+                let leaf = TapLeaf {
+                    version: control_block.leaf_version,
+                    script,
+                };
+                execution.tapleaf_hash = Some(leaf.hash());
+
+                if leaf.version == TAPROOT_LEAF_TAPSCRIPT {
+                    execution.validation_weight_left = witness_size as i64 + VALIDATION_WEIGHT_OFFSET;
+                    return self.execute_witness_script(stack, &leaf.script, SigVersion::Tapscript, &mut execution);
+                }
+                match self.flags.contains(VerificationFlags::DISCOURAGE_UPGRADABLE_TAPROOT_VERSION) {
+                    true => Err(InterpreterError::DiscourageUpgradableTaprootVersion.into()),
+                    false => Ok(()),
+                }
+            }
+            // Pay to anchor (P2A) outputs are spent without a witness
+            (1, 2) if !is_p2sh && program == [0x4e, 0x73] => Ok(()),
+            // Other programs are left for future soft forks
+            _ => match self.flags.contains(VerificationFlags::DISCOURAGE_UPGRADABLE_WITNESS_PROGRAM) {
+                true => Err(InterpreterError::DiscourageUpgradableWitnessProgram.into()),
+                false => Ok(()),
+            },
+        }
+    }
+
+    /// Executes the witness script of a SegWit v0 or tapscript spend on the remaining witness elements,
+    /// which must leave a single true element.
+    fn execute_witness_script(
+        &self,
+        mut stack: Vec<Vec<u8>>,
+        script: &[u8],
+        sig_version: SigVersion,
+        execution: &mut ExecutionData,
+    ) -> Result<(), ScriptFailure> {
+        if sig_version == SigVersion::Tapscript {
+            // Any OP_SUCCESSx makes the script succeed before anything else is checked (BIP-342)
+            let mut instructions = Instructions::new(script);
+            loop {
+                let position = instructions.position();
+                let failure = |error| ScriptFailure {
+                    error,
+                    opcode: Some(script[position]),
+                };
+                match instructions.next() {
+                    Some(Ok(_)) if is_op_success(script[position]) => {
+                        return match self.flags.contains(VerificationFlags::DISCOURAGE_OP_SUCCESS) {
+                            true => Err(failure(InterpreterError::DiscourageOpSuccess)),
+                            false => Ok(()),
+                        };
+                    }
+                    Some(Ok(_)) => {}
+                    Some(Err(_)) => return Err(failure(InterpreterError::BadOpcode)),
+                    None => break,
+                }
+            }
+            if stack.len() > MAX_STACK_SIZE {
+                return Err(InterpreterError::StackSize.into());
+            }
+        }
+
+        if stack.iter().any(|element| element.len() > MAX_SCRIPT_ELEMENT_SIZE) {
+            return Err(InterpreterError::PushSize.into());
+        }
+
+        self.eval_script(&mut stack, script, sig_version, execution)?;
+
+        // Witness scripts implicitly require a clean stack
+        if stack.len() != 1 {
+            return Err(InterpreterError::CleanStack.into());
+        }
+        if !cast_to_bool(&stack[0]) {
+            return Err(InterpreterError::EvalFalse.into());
+        }
+        Ok(())
+    }
+
+    /// Executes the script on the stack, failing with the opcode being executed.
+    fn eval_script(
+        &self,
+        stack: &mut Vec<Vec<u8>>,
+        script: &[u8],
+        sig_version: SigVersion,
+        execution: &mut ExecutionData,
+    ) -> Result<(), ScriptFailure> {
+        let mut opcode = None;
+        self.execute(stack, script, sig_version, execution, &mut opcode)
+            .map_err(|error| ScriptFailure { error, opcode })
+    }
+
+    /// Executes the script on the stack, keeping the byte of the opcode being executed.
+    fn execute(
+        &self,
+        stack: &mut Vec<Vec<u8>>,
+        script: &[u8],
+        sig_version: SigVersion,
+        execution: &mut ExecutionData,
+        opcode: &mut Option<u8>,
+    ) -> Result<(), InterpreterError> {
+        use InterpreterError::*;
+
+        let flags = self.flags;
+        let is_legacy = sig_version == SigVersion::Base || sig_version == SigVersion::WitnessV0;
+        if is_legacy && script.len() > MAX_SCRIPT_SIZE {
+            return Err(ScriptSize);
+        }
+        let require_minimal = flags.contains(VerificationFlags::MINIMALDATA);
+
+        let mut conditions: Vec<bool> = vec![];
+        let mut altstack: Vec<Vec<u8>> = vec![];
+        let mut op_count = 0;
+        // The script code signed by signatures starts after the last executed OP_CODESEPARATOR
+        let mut code_start = 0;
+        execution.code_separator_position = u32::MAX;
+
+        let mut instructions = Instructions::new(script);
+        let mut opcode_position = 0u32;
+        loop {
+            let start = instructions.position();
+            let instruction = match instructions.next() {
+                Some(instruction) => instruction,
+                None => break,
+            };
+            let byte = script[start];
+            *opcode = Some(byte);
+            let position = opcode_position;
+            opcode_position += 1;
+
+            let executing = conditions.iter().all(|condition| *condition);
+            let instruction = instruction.map_err(|_| BadOpcode)?;
+            if let Instruction::PushBytes(data) = instruction {
+                if data.len() > MAX_SCRIPT_ELEMENT_SIZE {
+                    return Err(PushSize);
+                }
+            }
+
+            // OP_RESERVED does not count towards the limit
+            if is_legacy && byte > Opcode::OP_16 as u8 {
+                op_count += 1;
+                if op_count > MAX_OPS_PER_SCRIPT {
+                    return Err(OpCount);
+                }
+            }
+
+            // Disabled opcodes fail even in unexecuted branches (CVE-2010-5137)
+            if is_disabled(byte) {
+                return Err(DisabledOpcode);
+            }
+            if byte == Opcode::OP_CODESEPARATOR as u8
+                && sig_version == SigVersion::Base
+                && flags.contains(VerificationFlags::CONST_SCRIPTCODE)
+            {
+                return Err(OpCodeSeparator);
+            }
+
+            let opcode = match instruction {
+                Instruction::PushBytes(data) => {
+                    if executing {
+                        if require_minimal && !is_minimal_push(byte, data) {
+                            return Err(MinimalData);
+                        }
+                        stack.push(data.to_vec());
+                    }
+                    None
+                }
+                Instruction::Op(opcode) => Some(opcode),
+                Instruction::Unknown(_) if executing => return Err(BadOpcode),
+                Instruction::Unknown(_) => None,
+            };
+
+            // Flow control runs in unexecuted branches, so OP_VERIF and OP_VERNOTIF always fail
+            let is_conditional = (Opcode::OP_IF as u8..=Opcode::OP_ENDIF as u8).contains(&byte);
+            let opcode = match opcode {
+                Some(opcode) if executing || is_conditional => opcode,
+                _ => {
+                    if stack.len() + altstack.len() > MAX_STACK_SIZE {
+                        return Err(StackSize);
+                    }
+                    continue;
+                }
+            };
+
+            match opcode {
+                Opcode::OP_1NEGATE
+                | Opcode::OP_1
+                | Opcode::OP_2
+                | Opcode::OP_3
+                | Opcode::OP_4
+                | Opcode::OP_5
+                | Opcode::OP_6
+                | Opcode::OP_7
+                | Opcode::OP_8
+                | Opcode::OP_9
+                | Opcode::OP_10
+                | Opcode::OP_11
+                | Opcode::OP_12
+                | Opcode::OP_13
+                | Opcode::OP_14
+                | Opcode::OP_15
+                | Opcode::OP_16 => stack.push(script_number(byte as i64 - Opcode::OP_RESERVED as i64)),
+
+                // Control
+                Opcode::OP_NOP => {}
+
+                Opcode::OP_CHECKLOCKTIMEVERIFY => {
+                    if flags.contains(VerificationFlags::CHECKLOCKTIMEVERIFY) {
+                        // Lock times are 5-byte numbers so that timestamps last beyond 2038
+                        let lock_time = script_num(top(stack, 1)?, require_minimal, 5)?;
+                        if lock_time < 0 {
+                            return Err(NegativeLocktime);
+                        }
+                        if !self.check_lock_time(lock_time) {
+                            return Err(UnsatisfiedLocktime);
+                        }
+                    }
+                }
+
+                Opcode::OP_CHECKSEQUENCEVERIFY => {
+                    if flags.contains(VerificationFlags::CHECKSEQUENCEVERIFY) {
+                        let sequence = script_num(top(stack, 1)?, require_minimal, 5)?;
+                        if sequence < 0 {
+                            return Err(NegativeLocktime);
+                        }
+                        // Operands with the disable flag are left for future soft forks
+                        if sequence & SEQUENCE_LOCKTIME_DISABLE_FLAG == 0 && !self.check_sequence(sequence) {
+                            return Err(UnsatisfiedLocktime);
+                        }
+                    }
+                }
+
+                Opcode::OP_NOP1
+                | Opcode::OP_NOP4
+                | Opcode::OP_NOP5
+                | Opcode::OP_NOP6
+                | Opcode::OP_NOP7
+                | Opcode::OP_NOP8
+                | Opcode::OP_NOP9
+                | Opcode::OP_NOP10 => {
+                    if flags.contains(VerificationFlags::DISCOURAGE_UPGRADABLE_NOPS) {
+                        return Err(DiscourageUpgradableNops);
+                    }
+                }
+
+                Opcode::OP_IF | Opcode::OP_NOTIF => {
+                    let mut value = false;
+                    if executing {
+                        let condition = stack.pop().ok_or(UnbalancedConditional)?;
+                        let is_minimal = condition.is_empty() || condition == [0x01];
+                        if sig_version == SigVersion::Tapscript && !is_minimal {
+                            return Err(TapscriptMinimalIf);
+                        }
+                        if sig_version == SigVersion::WitnessV0
+                            && flags.contains(VerificationFlags::MINIMALIF)
+                            && !is_minimal
+                        {
+                            return Err(MinimalIf);
+                        }
+                        value = cast_to_bool(&condition) == (opcode == Opcode::OP_IF);
+                    }
+                    conditions.push(value);
+                }
+
+                Opcode::OP_ELSE => match conditions.last_mut() {
+                    Some(condition) => *condition = !*condition,
+                    None => return Err(UnbalancedConditional),
+                },
+
+                Opcode::OP_ENDIF => {
+                    conditions.pop().ok_or(UnbalancedConditional)?;
+                }
+
+                Opcode::OP_VERIFY => match cast_to_bool(top(stack, 1)?) {
+                    true => {
+                        stack.pop();
+                    }
+                    false => return Err(Verify),
+                },
+
+                Opcode::OP_RETURN => return Err(OpReturn),
+
+                // Stack
+                Opcode::OP_TOALTSTACK => {
+                    let element = stack.pop().ok_or(InvalidStackOperation)?;
+                    altstack.push(element);
+                }
+
+                Opcode::OP_FROMALTSTACK => {
+                    let element = altstack.pop().ok_or(InvalidAltstackOperation)?;
+                    stack.push(element);
+                }
+
+                Opcode::OP_2DROP => {
+                    require(stack, 2)?;
+                    stack.truncate(stack.len() - 2);
+                }
+
+                Opcode::OP_2DUP => {
+                    require(stack, 2)?;
+                    stack.extend_from_within(stack.len() - 2..);
+                }
+
+                Opcode::OP_3DUP => {
+                    require(stack, 3)?;
+                    stack.extend_from_within(stack.len() - 3..);
+                }
+
+                Opcode::OP_2OVER => {
+                    require(stack, 4)?;
+                    stack.extend_from_within(stack.len() - 4..stack.len() - 2);
+                }
+
+                Opcode::OP_2ROT => {
+                    require(stack, 6)?;
+                    let length = stack.len();
+                    let elements: Vec<Vec<u8>> = stack.drain(length - 6..length - 4).collect();
+                    stack.extend(elements);
+                }
+
+                Opcode::OP_2SWAP => {
+                    require(stack, 4)?;
+                    let length = stack.len();
+                    stack.swap(length - 4, length - 2);
+                    stack.swap(length - 3, length - 1);
+                }
+
+                Opcode::OP_IFDUP => {
+                    let element = top(stack, 1)?;
+                    if cast_to_bool(element) {
+                        stack.push(element.clone());
+                    }
+                }
+
+                Opcode::OP_DEPTH => stack.push(script_number(stack.len() as i64)),
+
+                Opcode::OP_DROP => {
+                    stack.pop().ok_or(InvalidStackOperation)?;
+                }
+
+                Opcode::OP_DUP => stack.push(top(stack, 1)?.clone()),
+
+                Opcode::OP_NIP => {
+                    require(stack, 2)?;
+                    stack.remove(stack.len() - 2);
+                }
+
+                Opcode::OP_OVER => stack.push(top(stack, 2)?.clone()),
+
+                Opcode::OP_PICK | Opcode::OP_ROLL => {
+                    require(stack, 2)?;
+                    let n = script_num(&stack.pop().unwrap_or_default(), require_minimal, 4)?;
+                    if n < 0 || n as usize >= stack.len() {
+                        return Err(InvalidStackOperation);
+                    }
+                    let index = stack.len() - 1 - n as usize;
+                    let element = match opcode == Opcode::OP_ROLL {
+                        true => stack.remove(index),
+                        false => stack[index].clone(),
+                    };
+                    stack.push(element);
+                }
+
+                Opcode::OP_ROT => {
+                    require(stack, 3)?;
+                    let length = stack.len();
+                    stack[length - 3..].rotate_left(1);
+                }
+
+                Opcode::OP_SWAP => {
+                    require(stack, 2)?;
+                    let length = stack.len();
+                    stack.swap(length - 2, length - 1);
+                }
+
+                Opcode::OP_TUCK => {
+                    let element = top(stack, 1)?.clone();
+                    require(stack, 2)?;
+                    stack.insert(stack.len() - 2, element);
+                }
+
+                Opcode::OP_SIZE => stack.push(script_number(top(stack, 1)?.len() as i64)),
+
+                // Bitwise logic
+                Opcode::OP_EQUAL | Opcode::OP_EQUALVERIFY => {
+                    require(stack, 2)?;
+                    let equal = stack.pop() == stack.pop();
+                    stack.push(boolean(equal));
+                    if opcode == Opcode::OP_EQUALVERIFY {
+                        match equal {
+                            true => drop(stack.pop()),
+                            false => return Err(EqualVerify),
+                        }
+                    }
+                }
+
+                // Arithmetic on numbers of up to 4 bytes, whose results may take 5 bytes
+                Opcode::OP_1ADD
+                | Opcode::OP_1SUB
+                | Opcode::OP_NEGATE
+                | Opcode::OP_ABS
+                | Opcode::OP_NOT
+                | Opcode::OP_0NOTEQUAL => {
+                    let n = script_num(top(stack, 1)?, require_minimal, 4)?;
+                    let result = match opcode {
+                        Opcode::OP_1ADD => n + 1,
+                        Opcode::OP_1SUB => n - 1,
+                        Opcode::OP_NEGATE => -n,
+                        Opcode::OP_ABS => n.abs(),
+                        Opcode::OP_NOT => (n == 0) as i64,
+                        _ => (n != 0) as i64,
+                    };
+                    stack.pop();
+                    stack.push(script_number(result));
+                }
+
+                Opcode::OP_ADD
+                | Opcode::OP_SUB
+                | Opcode::OP_BOOLAND
+                | Opcode::OP_BOOLOR
+                | Opcode::OP_NUMEQUAL
+                | Opcode::OP_NUMEQUALVERIFY
+                | Opcode::OP_NUMNOTEQUAL
+                | Opcode::OP_LESSTHAN
+                | Opcode::OP_GREATERTHAN
+                | Opcode::OP_LESSTHANOREQUAL
+                | Opcode::OP_GREATERTHANOREQUAL
+                | Opcode::OP_MIN
+                | Opcode::OP_MAX => {
+                    let a = script_num(top(stack, 2)?, require_minimal, 4)?;
+                    let b = script_num(top(stack, 1)?, require_minimal, 4)?;
+                    let result = match opcode {
+                        Opcode::OP_ADD => a + b,
+                        Opcode::OP_SUB => a - b,
+                        Opcode::OP_BOOLAND => (a != 0 && b != 0) as i64,
+                        Opcode::OP_BOOLOR => (a != 0 || b != 0) as i64,
+                        Opcode::OP_NUMEQUAL | Opcode::OP_NUMEQUALVERIFY => (a == b) as i64,
+                        Opcode::OP_NUMNOTEQUAL => (a != b) as i64,
+                        Opcode::OP_LESSTHAN => (a < b) as i64,
+                        Opcode::OP_GREATERTHAN => (a > b) as i64,
+                        Opcode::OP_LESSTHANOREQUAL => (a <= b) as i64,
+                        Opcode::OP_GREATERTHANOREQUAL => (a >= b) as i64,
+                        Opcode::OP_MIN => a.min(b),
+                        _ => a.max(b),
+                    };
+                    stack.truncate(stack.len() - 2);
+                    stack.push(script_number(result));
+                    if opcode == Opcode::OP_NUMEQUALVERIFY {
+                        match result != 0 {
+                            true => drop(stack.pop()),
+                            false => return Err(NumEqualVerify),
+                        }
+                    }
+                }
+
+                Opcode::OP_WITHIN => {
+                    let n = script_num(top(stack, 3)?, require_minimal, 4)?;
+                    let min = script_num(top(stack, 2)?, require_minimal, 4)?;
+                    let max = script_num(top(stack, 1)?, require_minimal, 4)?;
+                    stack.truncate(stack.len() - 3);
+                    stack.push(boolean(min <= n && n < max));
+                }
+
+                // Crypto
+                Opcode::OP_RIPEMD160
+                | Opcode::OP_SHA1
+                | Opcode::OP_SHA256
+                | Opcode::OP_HASH160
+                | Opcode::OP_HASH256 => {
+                    let element = stack.pop().ok_or(InvalidStackOperation)?;
+                    stack.push(match opcode {
+                        Opcode::OP_RIPEMD160 => Ripemd160::digest(&element).to_vec(),
+                        Opcode::OP_SHA1 => Sha1::digest(&element).to_vec(),
+                        Opcode::OP_SHA256 => Sha256::digest(&element).to_vec(),
+                        Opcode::OP_HASH160 => hash160(&element),
+                        _ => Sha256::digest(Sha256::digest(&element)).to_vec(),
+                    });
+                }
+
+                Opcode::OP_CODESEPARATOR => {
+                    code_start = instructions.position();
+                    execution.code_separator_position = position;
+                }
+
+                Opcode::OP_CHECKSIG | Opcode::OP_CHECKSIGVERIFY => {
+                    require(stack, 2)?;
+                    let public_key = stack.pop().unwrap_or_default();
+                    let signature = stack.pop().unwrap_or_default();
+                    let success =
+                        self.eval_checksig(&signature, &public_key, &script[code_start..], sig_version, execution)?;
+                    stack.push(boolean(success));
+                    if opcode == Opcode::OP_CHECKSIGVERIFY {
+                        match success {
+                            true => drop(stack.pop()),
+                            false => return Err(CheckSigVerify),
+                        }
+                    }
+                }
+
+                Opcode::OP_CHECKSIGADD => {
+                    if sig_version != SigVersion::Tapscript {
+                        return Err(BadOpcode);
+                    }
+                    require(stack, 3)?;
+                    let n = script_num(top(stack, 2)?, require_minimal, 4)?;
+                    let public_key = stack.pop().unwrap_or_default();
+                    stack.pop();
+                    let signature = stack.pop().unwrap_or_default();
+                    let success =
+                        self.eval_checksig(&signature, &public_key, &script[code_start..], sig_version, execution)?;
+                    stack.push(script_number(n + success as i64));
+                }
+
+                Opcode::OP_CHECKMULTISIG | Opcode::OP_CHECKMULTISIGVERIFY => {
+                    if sig_version == SigVersion::Tapscript {
+                        return Err(TapscriptCheckMultisig);
+                    }
+
+                    // [dummy] [signatures ...] <m> [public keys ...] <n>
+                    let mut i = 1;
+                    let mut key_count = script_num(top(stack, i)?, require_minimal, 4)?;
+                    if !(0..=MAX_PUBKEYS_PER_MULTISIG).contains(&key_count) {
+                        return Err(PubkeyCount);
+                    }
+                    op_count += key_count as usize;
+                    if op_count > MAX_OPS_PER_SCRIPT {
+                        return Err(OpCount);
+                    }
+                    i += 1;
+                    let mut key_index = i;
+                    // The depth of the last public key, below which the failed signatures must be empty
+                    let mut key_depth = key_count as usize + 2;
+                    i += key_count as usize;
+                    let mut signature_count = script_num(top(stack, i)?, require_minimal, 4)?;
+                    if signature_count < 0 || signature_count > key_count {
+                        return Err(SigCount);
+                    }
+                    i += 1;
+                    let mut signature_index = i;
+                    i += signature_count as usize;
+                    require(stack, i)?;
+
+                    // Legacy scripts sign the script code without the signatures
+                    let mut script_code = script[code_start..].to_vec();
+                    for k in 0..signature_count as usize {
+                        if sig_version == SigVersion::Base && !self.uses_fork_id(top(stack, signature_index + k)?) {
+                            let (code, found) =
+                                find_and_delete(&script_code, &serialized_push(top(stack, signature_index + k)?));
+                            if found > 0 && flags.contains(VerificationFlags::CONST_SCRIPTCODE) {
+                                return Err(SigFindAndDelete);
+                            }
+                            script_code = code;
+                        }
+                    }
+
+                    // Signatures are matched to the public keys in order, failing once too few keys are left
+                    let mut success = true;
+                    while success && signature_count > 0 {
+                        let signature = top(stack, signature_index)?;
+                        let public_key = top(stack, key_index)?;
+                        self.check_signature_encoding(signature)?;
+                        self.check_public_key_encoding(public_key, sig_version)?;
+                        if self.check_ecdsa_signature(signature, public_key, &script_code, sig_version)? {
+                            signature_index += 1;
+                            signature_count -= 1;
+                        }
+                        key_index += 1;
+                        key_count -= 1;
+                        if signature_count > key_count {
+                            success = false;
+                        }
+                    }
+
+                    // Pop the arguments, requiring the signatures of a failed check to be empty
+                    for _ in 1..i {
+                        if !success
+                            && flags.contains(VerificationFlags::NULLFAIL)
+                            && key_depth == 0
+                            && !top(stack, 1)?.is_empty()
+                        {
+                            return Err(NullFail);
+                        }
+                        key_depth = key_depth.saturating_sub(1);
+                        stack.pop();
+                    }
+
+                    // An extra argument is popped by a historical bug, which must be empty (BIP-147)
+                    let dummy = stack.pop().ok_or(InvalidStackOperation)?;
+                    if flags.contains(VerificationFlags::NULLDUMMY) && !dummy.is_empty() {
+                        return Err(SigNullDummy);
+                    }
+
+                    stack.push(boolean(success));
+                    if opcode == Opcode::OP_CHECKMULTISIGVERIFY {
+                        match success {
+                            true => drop(stack.pop()),
+                            false => return Err(CheckMultisigVerify),
+                        }
+                    }
+                }
+
+                _ => return Err(BadOpcode),
+            }
+
+            if stack.len() + altstack.len() > MAX_STACK_SIZE {
+                return Err(StackSize);
+            }
+        }
+
+        *opcode = None;
+        match conditions.is_empty() {
+            true => Ok(()),
+            false => Err(UnbalancedConditional),
+        }
+    }
+
+    /// Returns the result of the signature check of OP_CHECKSIG, OP_CHECKSIGVERIFY or OP_CHECKSIGADD,
+    /// failing the script on signatures or public keys that are invalid rather than not matching.
+    fn eval_checksig(
+        &self,
+        signature: &[u8],
+        public_key: &[u8],
+        script_code: &[u8],
+        sig_version: SigVersion,
+        execution: &mut ExecutionData,
+    ) -> Result<bool, InterpreterError> {
+        if sig_version == SigVersion::Tapscript {
+            // Empty signatures fail without aborting, other signatures consume validation weight
+            let success = !signature.is_empty();
+            if success {
+                execution.validation_weight_left -= VALIDATION_WEIGHT_PER_SIGOP_PASSED;
+                if execution.validation_weight_left < 0 {
+                    return Err(InterpreterError::TapscriptValidationWeight);
+                }
+            }
+            return match public_key.len() {
+                0 => Err(InterpreterError::PubkeyType),
+                32 => {
+                    if success {
+                        self.check_schnorr_signature(signature, public_key, execution)?;
+                    }
+                    Ok(success)
+                }
+                // Public keys of other sizes are left for future soft forks
+                _ => match self.flags.contains(VerificationFlags::DISCOURAGE_UPGRADABLE_PUBKEYTYPE) {
+                    true => Err(InterpreterError::DiscourageUpgradablePubkeyType),
+                    false => Ok(success),
+                },
+            };
+        }
+
+        // Legacy scripts sign the script code without the signature
+        let mut script_code = script_code.to_vec();
+        if sig_version == SigVersion::Base && !self.uses_fork_id(signature) {
+            let (code, found) = find_and_delete(&script_code, &serialized_push(signature));
+            if found > 0 && self.flags.contains(VerificationFlags::CONST_SCRIPTCODE) {
+                return Err(InterpreterError::SigFindAndDelete);
+            }
+            script_code = code;
+        }
+
+        self.check_signature_encoding(signature)?;
+        self.check_public_key_encoding(public_key, sig_version)?;
+        let success = self.check_ecdsa_signature(signature, public_key, &script_code, sig_version)?;
+        if !success && self.flags.contains(VerificationFlags::NULLFAIL) && !signature.is_empty() {
+            return Err(InterpreterError::NullFail);
+        }
+        Ok(success)
+    }
+
+    /// Checks the encoding of an ECDSA signature followed by its hash type.
+    fn check_signature_encoding(&self, signature: &[u8]) -> Result<(), InterpreterError> {
+        // An empty signature is a compact way to fail a signature check
+        if signature.is_empty() {
+            return Ok(());
+        }
+        let strict = VerificationFlags::DERSIG | VerificationFlags::LOW_S | VerificationFlags::STRICTENC;
+        if self.flags.intersects(strict) && !is_strict_der_signature(signature) {
+            return Err(InterpreterError::SigDer);
+        }
+        if self.flags.contains(VerificationFlags::LOW_S) {
+            match Signature::parse_der_lax(&signature[..signature.len() - 1]) {
+                Ok(signature) if !signature.s.is_high() => {}
+                _ => return Err(InterpreterError::SigHighS),
+            }
+        }
+        if self.flags.contains(VerificationFlags::STRICTENC) {
+            let mut base = signature[signature.len() - 1] & !0x80;
+            if N::fork_id().is_some() {
+                // Networks with a fork id only accept replay protected signatures
+                if base & SIGHASH_FORKID == 0 {
+                    return Err(InterpreterError::MustUseForkId);
+                }
+                base &= !SIGHASH_FORKID;
+            }
+            if !(0x01..=0x03).contains(&base) {
+                return Err(InterpreterError::SigHashType);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns true if the signature commits to the fork id of the network, and so signs a BIP-143 message
+    /// of the script code without removing the signatures.
+    fn uses_fork_id(&self, signature: &[u8]) -> bool {
+        N::fork_id().is_some() && matches!(signature.last(), Some(hash_type) if hash_type & SIGHASH_FORKID != 0)
+    }
+
+    /// Checks the encoding of an ECDSA public key.
+    fn check_public_key_encoding(&self, public_key: &[u8], sig_version: SigVersion) -> Result<(), InterpreterError> {
+        let is_compressed = matches!(public_key, [0x02 | 0x03, ..] if public_key.len() == 33);
+        let is_uncompressed = matches!(public_key, [0x04, ..] if public_key.len() == 65);
+        if self.flags.contains(VerificationFlags::STRICTENC) && !is_compressed && !is_uncompressed {
+            return Err(InterpreterError::PubkeyType);
+        }
+        if self.flags.contains(VerificationFlags::WITNESS_PUBKEYTYPE)
+            && sig_version == SigVersion::WitnessV0
+            && !is_compressed
+        {
+            return Err(InterpreterError::WitnessPubkeyType);
+        }
+        Ok(())
+    }
+
+    /// Returns true if the ECDSA signature followed by its hash type signs the input for the public key.
+    fn check_ecdsa_signature(
+        &self,
+        signature: &[u8],
+        public_key: &[u8],
+        script_code: &[u8],
+        sig_version: SigVersion,
+    ) -> Result<bool, InterpreterError> {
+        // The length of a public key must match its prefix, including the hybrid prefixes 0x06 and 0x07
+        let is_valid_length = match public_key.first() {
+            Some(0x02 | 0x03) => public_key.len() == 33,
+            Some(0x04 | 0x06 | 0x07) => public_key.len() == 65,
+            _ => false,
+        };
+        let (hash_type, signature) = match signature.split_last() {
+            Some((hash_type, signature)) if is_valid_length => (*hash_type as u32, signature),
+            _ => return Ok(false),
+        };
+
+        let message = match (sig_version, N::fork_id()) {
+            (SigVersion::Base, Some(fork_id)) if hash_type & SIGHASH_FORKID as u32 != 0 => {
+                let amount = self.amount.ok_or(InterpreterError::MissingOutpointAmount)?;
+                let preimage = self
+                    .transaction
+                    .witness_v0_signature_message(self.cache, self.vin, script_code, amount, fork_id << 8 | hash_type)
+                    .map_err(|_| InterpreterError::UnknownError)?;
+                double_sha256(&preimage)
+            }
+            (SigVersion::Base, _) => self
+                .transaction
+                .legacy_signature_digest(self.vin, script_code, hash_type)
+                .map_err(|_| InterpreterError::UnknownError)?,
+            _ => {
+                let amount = self.amount.ok_or(InterpreterError::MissingOutpointAmount)?;
+                let preimage = self
+                    .transaction
+                    .witness_v0_signature_message(self.cache, self.vin, script_code, amount, hash_type)
+                    .map_err(|_| InterpreterError::UnknownError)?;
+                double_sha256(&preimage)
+            }
+        };
+
+        let public_key = match PublicKey::parse_slice(public_key, None) {
+            Ok(public_key) => public_key,
+            Err(_) => return Ok(false),
+        };
+        let mut signature = match Signature::parse_der_lax(signature) {
+            Ok(signature) => signature,
+            Err(_) => return Ok(false),
+        };
+        signature.normalize_s();
+        Ok(libsecp256k1::verify(&Message::parse(&message), &signature, &public_key))
+    }
+
+    /// Checks the BIP-340 signature, followed by its hash type unless it is SIGHASH_DEFAULT,
+    /// of a Taproot key path spend or of a tapscript.
+    fn check_schnorr_signature(
+        &self,
+        signature: &[u8],
+        public_key: &[u8],
+        execution: &ExecutionData,
+    ) -> Result<(), InterpreterError> {
+        let (signature, hash_type) = match signature.len() {
+            64 => (signature, 0x00),
+            65 if signature[64] != 0x00 => (&signature[..64], signature[64]),
+            65 => return Err(InterpreterError::SchnorrSigHashType),
+            _ => return Err(InterpreterError::SchnorrSigSize),
+        };
+        let has_output = self.vin < self.transaction.parameters.outputs.len();
+        if !matches!(hash_type, 0x00..=0x03 | 0x81..=0x83) || (hash_type & 0x03 == 0x03 && !has_output) {
+            return Err(InterpreterError::SchnorrSigHashType);
+        }
+
+        let leaf = execution
+            .tapleaf_hash
+            .map(|tapleaf_hash| (tapleaf_hash, execution.code_separator_position));
+        let message = self
+            .transaction
+            .taproot_signature_message(self.cache, self.vin, hash_type, execution.annex.as_deref(), leaf)
+            .map_err(|error| match error {
+                TransactionError::MissingOutpointAmount => InterpreterError::MissingOutpointAmount,
+                TransactionError::MissingOutpointScriptPublicKey => InterpreterError::MissingOutpointScriptPublicKey,
+                _ => InterpreterError::SchnorrSigHashType,
+            })?;
+
+        let mut x_only_public_key = [0u8; 32];
+        x_only_public_key.copy_from_slice(public_key);
+        let mut schnorr_signature = [0u8; 64];
+        schnorr_signature.copy_from_slice(signature);
+        match schnorr_verify(&tagged_hash("TapSighash", &message), &x_only_public_key, &schnorr_signature) {
+            true => Ok(()),
+            false => Err(InterpreterError::SchnorrSig),
+        }
+    }
+
+    /// Returns true if the lock time of OP_CHECKLOCKTIMEVERIFY has passed for the transaction (BIP-65).
+    fn check_lock_time(&self, lock_time: i64) -> bool {
+        let transaction_lock_time = self.transaction.parameters.lock_time as i64;
+        // Block heights and timestamps are not comparable
+        if (transaction_lock_time < LOCKTIME_THRESHOLD) != (lock_time < LOCKTIME_THRESHOLD) {
+            return false;
+        }
+        // The lock time of the transaction is disabled if the input is final
+        lock_time <= transaction_lock_time && self.sequence() != SEQUENCE_FINAL
+    }
+
+    /// Returns true if the relative lock time of OP_CHECKSEQUENCEVERIFY has passed for the input (BIP-112).
+    fn check_sequence(&self, sequence: i64) -> bool {
+        let input_sequence = self.sequence() as i64;
+        if self.transaction.parameters.version < 2 || input_sequence & SEQUENCE_LOCKTIME_DISABLE_FLAG != 0 {
+            return false;
+        }
+
+        // Block counts and times are not comparable
+        let mask = SEQUENCE_LOCKTIME_TYPE_FLAG | SEQUENCE_LOCKTIME_MASK;
+        let (input_sequence, sequence) = (input_sequence & mask, sequence & mask);
+        if (input_sequence < SEQUENCE_LOCKTIME_TYPE_FLAG) != (sequence < SEQUENCE_LOCKTIME_TYPE_FLAG) {
+            return false;
+        }
+        sequence <= input_sequence
+    }
+
+    /// Returns the sequence of the input.
+    fn sequence(&self) -> u32 {
+        let sequence = &self.transaction.parameters.inputs[self.vin].sequence;
+        sequence.iter().rev().fold(0, |value, &byte| (value << 8) | byte as u32)
+    }
+}
+
+/// Returns true if the element is a number other than zero or negative zero.
+fn cast_to_bool(element: &[u8]) -> bool {
+    match element.split_last() {
+        Some((&last, rest)) => rest.iter().any(|&byte| byte != 0) || (last != 0 && last != 0x80),
+        None => false,
+    }
+}
+
+/// Returns the element of a boolean result.
+fn boolean(value: bool) -> Vec<u8> {
+    match value {
+        true => vec![0x01],
+        false => vec![],
+    }
+}
+
+/// Returns the number of a stack element of at most `max_size` bytes, which must be minimally
+/// encoded if required.
+pub(crate) fn script_num(element: &[u8], require_minimal: bool, max_size: usize) -> Result<i64, InterpreterError> {
+    if element.len() > max_size {
+        return Err(InterpreterError::UnknownError);
+    }
+    let (last, rest) = match element.split_last() {
+        Some(split) => split,
+        None => return Ok(0),
+    };
+    // The last byte holds the sign, so it may only be zero to keep the top bit of the previous byte positive
+    if require_minimal && last & 0x7f == 0 && rest.last().is_none_or(|byte| byte & 0x80 == 0) {
+        return Err(InterpreterError::UnknownError);
+    }
+    let magnitude = element
+        .iter()
+        .rev()
+        .fold(0i64, |value, &byte| (value << 8) | byte as i64)
+        & !(0x80 << (8 * rest.len()));
+    match last & 0x80 != 0 {
+        true => Ok(-magnitude),
+        false => Ok(magnitude),
+    }
+}
+
+/// Returns the element `depth` elements from the top of the stack, the top being 1.
+fn top(stack: &[Vec<u8>], depth: usize) -> Result<&Vec<u8>, InterpreterError> {
+    require(stack, depth)?;
+    Ok(&stack[stack.len() - depth])
+}
+
+/// Checks that the stack has at least `depth` elements.
+fn require(stack: &[Vec<u8>], depth: usize) -> Result<(), InterpreterError> {
+    match stack.len() >= depth {
+        true => Ok(()),
+        false => Err(InterpreterError::InvalidStackOperation),
+    }
+}
+
+/// Returns true for the opcodes disabled in 2010 (CVE-2010-5137).
+fn is_disabled(opcode: u8) -> bool {
+    matches!(opcode, 0x7e..=0x81 | 0x83..=0x86 | 0x8d | 0x8e | 0x95..=0x99)
+}
+
+/// Returns true for the OP_SUCCESSx opcodes of tapscript (BIP-342).
+fn is_op_success(opcode: u8) -> bool {
+    matches!(opcode, 0x50 | 0x62 | 0x7e..=0x81 | 0x83..=0x86 | 0x89 | 0x8a | 0x8d | 0x8e | 0x95..=0x99 | 0xbb..=0xfe)
+}
+
+/// Returns true if the data is pushed with the smallest possible opcode (BIP-62).
+fn is_minimal_push(opcode: u8, data: &[u8]) -> bool {
+    match data {
+        [] => opcode == Opcode::OP_0 as u8,
+        [1..=16] | [0x81] => false,
+        _ if data.len() <= 0x4b => opcode as usize == data.len(),
+        _ if data.len() <= 0xff => opcode == Opcode::OP_PUSHDATA1 as u8,
+        _ if data.len() <= 0xffff => opcode == Opcode::OP_PUSHDATA2 as u8,
+        _ => true,
+    }
+}
+
+/// Returns true if the script only pushes data, counting OP_RESERVED as a push.
+fn is_push_only(script: &[u8]) -> bool {
+    Instructions::new(script).all(|instruction| match instruction {
+        Ok(Instruction::PushBytes(_)) => true,
+        Ok(Instruction::Op(opcode)) => opcode as u8 <= Opcode::OP_16 as u8,
+        _ => false,
+    })
+}
+
+/// Returns the version and program of a witness program script `OP_n <2 to 40 bytes>`,
+/// including programs that are not valid for their version.
+fn witness_program(script: &[u8]) -> Option<(u8, &[u8])> {
+    if !(4..=42).contains(&script.len()) || script[1] as usize + 2 != script.len() {
+        return None;
+    }
+    match script[0] {
+        0x00 => Some((0, &script[2..])),
+        opcode @ 0x51..=0x60 => Some((opcode - 0x50, &script[2..])),
+        _ => None,
+    }
+}
+
+/// Returns the push of the data as serialized by Bitcoin Core, which unlike a minimal push
+/// never uses OP_1NEGATE or OP_1 to OP_16.
+fn serialized_push(data: &[u8]) -> Vec<u8> {
+    let mut push = match data.len() {
+        length @ 0x00..=0x4b => vec![length as u8],
+        length @ 0x4c..=0xff => vec![Opcode::OP_PUSHDATA1 as u8, length as u8],
+        length @ 0x100..=0xffff => [&[Opcode::OP_PUSHDATA2 as u8][..], &(length as u16).to_le_bytes()].concat(),
+        length => [&[Opcode::OP_PUSHDATA4 as u8][..], &(length as u32).to_le_bytes()].concat(),
+    };
+    push.extend(data);
+    push
+}
+
+/// Returns the script without the occurrences of the pattern starting at an instruction boundary,
+/// and the number of occurrences removed.
+fn find_and_delete(script: &[u8], pattern: &[u8]) -> (Vec<u8>, usize) {
+    let mut result = vec![];
+    let mut found = 0;
+    let (mut position, mut kept) = (0, 0);
+    loop {
+        result.extend(&script[kept..position]);
+        while script[position..].starts_with(pattern) {
+            position += pattern.len();
+            found += 1;
+        }
+        kept = position;
+        let mut instructions = Instructions::new(&script[position..]);
+        match instructions.next() {
+            Some(Ok(_)) => position += instructions.position(),
+            _ => break,
+        }
+    }
+    match found {
+        0 => (script.to_vec(), 0),
+        _ => {
+            result.extend(&script[kept..]);
+            (result, found)
+        }
+    }
+}
+
+/// Returns SHA256(SHA256(data)).
+fn double_sha256(data: &[u8]) -> [u8; 32] {
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(&Sha256::digest(Sha256::digest(data)));
+    hash
+}
+
+/// Returns true if the signature followed by its hash type is strictly DER encoded (BIP-66):
+/// `0x30 <length> 0x02 <length of R> <R> 0x02 <length of S> <S> <hash type>`, with R and S
+/// positive and without unnecessary leading zeros.
+fn is_strict_der_signature(signature: &[u8]) -> bool {
+    let length = signature.len();
+    if !(9..=73).contains(&length) || signature[0] != 0x30 || signature[1] as usize != length - 3 {
+        return false;
+    }
+    let r_length = signature[3] as usize;
+    if 5 + r_length >= length {
+        return false;
+    }
+    let s_length = signature[5 + r_length] as usize;
+    if r_length + s_length + 7 != length {
+        return false;
+    }
+
+    let (r, s) = (&signature[4..4 + r_length], &signature[6 + r_length..6 + r_length + s_length]);
+    let is_positive_integer = |integer: &[u8]| match integer {
+        [] => false,
+        [first, ..] if first & 0x80 != 0 => false,
+        [0x00, second, ..] => second & 0x80 != 0,
+        _ => true,
+    };
+    signature[2] == 0x02 && signature[4 + r_length] == 0x02 && is_positive_integer(r) && is_positive_integer(s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::BitcoinFormat;
+    use crate::private_key::BitcoinPrivateKey;
+    use crate::taproot::{tweak_public_key, TapTree};
+    use crate::transaction::{
+        BitcoinTransactionBuilder, BitcoinTransactionInput, BitcoinTransactionInputBuilder, BitcoinTransactionOutput,
+        BitcoinTransactionParameters, Outpoint, SignatureHash,
+    };
+    use crate::Mainnet;
+    use chainlib_core::{PrivateKey, Transaction};
+    use serde_json::Value;
+
+    type N = Mainnet;
+
+    // Vectors in the format of the Bitcoin Core unit tests, https://github.com/bitcoin/bitcoin/tree/master/src/test/data
+    const SCRIPT_TESTS: &str = include_str!("../tests/data/script_tests.json");
+    const TX_VALID: &str = include_str!("../tests/data/tx_valid.json");
+    const TX_INVALID: &str = include_str!("../tests/data/tx_invalid.json");
+
+    /// The flags of the transaction tests which are skipped, with the reason
+    const SKIPPED_FLAGS: [(&str, &str); 1] = [(
+        "BADTX",
+        "marks transactions failing CheckTransaction, e.g. without outputs or with duplicate inputs, which nodes check \
+         before and independently of script verification",
+    )];
+
+    /// The internal key of the `#TAPROOTOUTPUT#` placeholder, the point H of BIP-341 with no known secret key
+    const NUMS_H: &str = "50929b74c1a04954b78b4b6035e97a5e078a5a0f28ec96d547bfee9ace803ac0";
+
+    /// Returns the script of the ASM dialect of the Bitcoin Core tests: numbers, raw bytes prefixed
+    /// with 0x, pushes of quoted strings and opcodes with or without their OP_ prefix.
+    fn parse_script(asm: &str) -> Vec<u8> {
+        asm.split_whitespace().fold(vec![], |mut script, token| {
+            if let Ok(number) = token.parse::<i64>() {
+                script.extend(Script::new().push_int(number).as_bytes());
+            } else if let Some(bytes) = token.strip_prefix("0x") {
+                script.extend(hex::decode(bytes).unwrap());
+            } else if token.len() >= 2 && token.starts_with('\'') && token.ends_with('\'') {
+                script.extend(serialized_push(&token.as_bytes()[1..token.len() - 1]));
+            } else {
+                let name = match token.starts_with("OP_") {
+                    true => token.to_string(),
+                    false => format!("OP_{}", token),
+                };
+                script.push(Opcode::from_str(&name).unwrap() as u8);
+            }
+            script
+        })
+    }
+
+    /// Returns the output key and control block of the tree of a single tapscript with the internal key H,
+    /// which replace the `#TAPROOTOUTPUT#` and `#CONTROLBLOCK#` placeholders of the Bitcoin Core script tests.
+    fn taproot_spend(tapscript: &[u8]) -> ([u8; 32], Vec<u8>) {
+        let mut internal_key = [0u8; 32];
+        internal_key.copy_from_slice(&hex::decode(NUMS_H).unwrap());
+        let tree = TapTree::leaf(tapscript.to_vec());
+        let (output_key, _) = tweak_public_key(&internal_key, Some(&tree.merkle_root())).unwrap();
+        let control_block = tree.control_block(&internal_key, &TapLeaf::new(tapscript.to_vec())).unwrap();
+        (output_key, control_block.serialize())
+    }
+
+    /// Returns the witness of a script test and its tapscript, given as `#SCRIPT#` followed by its ASM.
+    fn parse_witness(elements: &[Value]) -> (Vec<Vec<u8>>, Option<Vec<u8>>) {
+        let mut tapscript: Option<Vec<u8>> = None;
+        let witness = elements
+            .iter()
+            .map(|element| match element.as_str().unwrap() {
+                "#CONTROLBLOCK#" => taproot_spend(tapscript.as_ref().unwrap()).1,
+                element => match element.strip_prefix("#SCRIPT#") {
+                    Some(asm) => {
+                        let script = parse_script(asm);
+                        tapscript = Some(script.clone());
+                        script
+                    }
+                    None => hex::decode(element).unwrap(),
+                },
+            })
+            .collect();
+        (witness, tapscript)
+    }
+
+    /// Returns the Bitcoin Core name of the error, e.g. `SIG_HASHTYPE` for `SigHashType`, without underscores.
+    fn error_name(error: &InterpreterError) -> String {
+        format!("{:?}", error).to_uppercase()
+    }
+
+    fn input(reverse_transaction_id: Vec<u8>, index: u32, script_sig: Vec<u8>, witness: &[Vec<u8>]) -> BitcoinTransactionInput<N> {
+        BitcoinTransactionInput {
+            outpoint: Outpoint {
+                reverse_transaction_id,
+                index,
+                amount: None,
+                script_pub_key: None,
+                redeem_script: None,
+                address: None,
+            },
+            script_sig,
+            sequence: vec![0xff; 4],
+            sighash_code: SignatureHash::SIGHASH_ALL,
+            witnesses: witness
+                .iter()
+                .map(|element| [variable_length_integer(element.len() as u64).unwrap(), element.clone()].concat())
+                .collect(),
+            is_signed: true,
+            partial_signatures: BTreeMap::new(),
+            witness_script_data: None,
+        }
+    }
+
+    /// Returns the transaction spending the output of a coinbase-like transaction crediting the script
+    /// public key, as built by the Bitcoin Core script tests.
+    fn spending_transaction(
+        script_sig: Vec<u8>,
+        script_pub_key: Vec<u8>,
+        witness: &[Vec<u8>],
+        amount: BitcoinAmount,
+    ) -> BitcoinTransaction<N> {
+        let credit = BitcoinTransaction::<N> {
+            parameters: BitcoinTransactionParameters {
+                version: 1,
+                inputs: vec![input(vec![0u8; 32], u32::MAX, vec![0x00, 0x00], &[])],
+                outputs: vec![BitcoinTransactionOutput {
+                    amount,
+                    script_pub_key: script_pub_key.clone(),
+                }],
+                lock_time: 0,
+                segwit_flag: false,
+            },
+        };
+        let credit_id = Sha256::digest(Sha256::digest(credit.to_bytes().unwrap())).to_vec();
+
+        let mut spend = input(credit_id, 0, script_sig, witness);
+        spend.outpoint.amount = Some(amount);
+        spend.outpoint.script_pub_key = Some(script_pub_key);
+        BitcoinTransaction {
+            parameters: BitcoinTransactionParameters {
+                version: 1,
+                inputs: vec![spend],
+                outputs: vec![BitcoinTransactionOutput {
+                    amount,
+                    script_pub_key: vec![],
+                }],
+                lock_time: 0,
+                segwit_flag: !witness.is_empty(),
+            },
+        }
+    }
+
+    /// Returns the transaction of a Bitcoin Core transaction test, with the script public key and
+    /// amount of each outpoint, which defaults to zero.
+    fn test_transaction(test: &[Value]) -> BitcoinTransaction<N> {
+        let mut transaction = BitcoinTransaction::<N>::from_str(test[1].as_str().unwrap()).unwrap();
+        let prevouts = test[0].as_array().unwrap();
+        for input in transaction.parameters.inputs.iter_mut() {
+            let prevout = prevouts
+                .iter()
+                .find(|prevout| {
+                    let mut transaction_id = hex::decode(prevout[0].as_str().unwrap()).unwrap();
+                    transaction_id.reverse();
+                    transaction_id == input.outpoint.reverse_transaction_id
+                        && prevout[1].as_i64().unwrap() as u32 == input.outpoint.index
+                })
+                .unwrap();
+            input.outpoint.script_pub_key = Some(parse_script(prevout[2].as_str().unwrap()));
+            input.outpoint.amount = Some(BitcoinAmount(prevout.get(3).map_or(0, |amount| amount.as_i64().unwrap())));
+        }
+        transaction
+    }
+
+    /// Removes the flags which require a removed flag, as every flag set is tested in Bitcoin Core.
+    fn trim_flags(mut flags: VerificationFlags) -> VerificationFlags {
+        if !flags.contains(VerificationFlags::P2SH) {
+            flags.remove(VerificationFlags::WITNESS);
+        }
+        if !flags.contains(VerificationFlags::WITNESS) {
+            flags.remove(VerificationFlags::CLEANSTACK);
+        }
+        flags
+    }
+
+    /// Adds the flags required by the flags.
+    fn fill_flags(mut flags: VerificationFlags) -> VerificationFlags {
+        if flags.contains(VerificationFlags::CLEANSTACK) {
+            flags.insert(VerificationFlags::WITNESS);
+        }
+        if flags.contains(VerificationFlags::WITNESS) {
+            flags.insert(VerificationFlags::P2SH);
+        }
+        flags
+    }
+
+    fn is_valid(transaction: &BitcoinTransaction<N>, flags: VerificationFlags) -> bool {
+        transaction.verify(flags).iter().all(Result::is_ok)
+    }
+
+    fn tests(json: &str) -> Vec<Vec<Value>> {
+        let tests: Vec<Vec<Value>> = serde_json::from_str(json).unwrap();
+        // Single strings are comments
+        tests.into_iter().filter(|test| test.len() > 1).collect()
+    }
+
+    /// Returns the skipped flag of a transaction test.
+    fn skipped_flag(test: &[Value]) -> Option<&'static str> {
+        let flags: Vec<&str> = test[2].as_str().unwrap().split(',').collect();
+        SKIPPED_FLAGS.iter().map(|(flag, _)| *flag).find(|flag| flags.contains(flag))
+    }
+
+    #[test]
+    fn script_tests() {
+        let failures: Vec<String> = tests(SCRIPT_TESTS)
+            .iter()
+            .filter_map(|test| {
+                let (witness, tapscript, amount, test) = match test[0].as_array() {
+                    Some(witness) => {
+                        let (amount, witness) = witness.split_last().unwrap();
+                        let amount = (amount.as_f64().unwrap() * 100_000_000.0).round() as i64;
+                        let (witness, tapscript) = parse_witness(witness);
+                        (witness, tapscript, amount, &test[1..])
+                    }
+                    None => (vec![], None, 0, &test[..]),
+                };
+                let script_sig = parse_script(test[0].as_str().unwrap());
+                let script_pub_key = match &tapscript {
+                    Some(tapscript) => {
+                        let output_key = format!("0x{}", hex::encode(taproot_spend(tapscript).0));
+                        parse_script(&test[1].as_str().unwrap().replace("#TAPROOTOUTPUT#", &output_key))
+                    }
+                    None => parse_script(test[1].as_str().unwrap()),
+                };
+                let flags = VerificationFlags::from_str(test[2].as_str().unwrap()).unwrap();
+                let expected = test[3].as_str().unwrap().replace('_', "");
+
+                let transaction = spending_transaction(script_sig, script_pub_key, &witness, BitcoinAmount(amount));
+                let result = match transaction.verify_input(0, flags) {
+                    Ok(()) => "OK".to_string(),
+                    Err(failure) => error_name(&failure.error),
+                };
+                match result == expected {
+                    true => None,
+                    false => Some(format!("{:?}: expected {}, found {}", test, expected, result)),
+                }
+            })
+            .collect();
+        assert!(failures.is_empty(), "{:#?}", failures);
+    }
+
+    #[test]
+    fn tx_valid() {
+        tests(TX_VALID).iter().filter(|test| skipped_flag(test).is_none()).for_each(|test| {
+            let transaction = test_transaction(test);
+            let excluded = VerificationFlags::from_str(test[2].as_str().unwrap()).unwrap();
+            let mut flags = VerificationFlags::ALL;
+            flags.remove(excluded);
+            assert_eq!(flags, fill_flags(flags), "{:?}", test);
+            assert!(is_valid(&transaction, flags), "{:?}: {:?}", test, transaction.verify(flags));
+
+            // Removing flags never invalidates a transaction, and the excluded flags are all needed
+            VerificationFlags::FLAGS.iter().for_each(|(name, flag)| {
+                let mut fewer = flags;
+                fewer.remove(*flag);
+                assert!(is_valid(&transaction, trim_flags(fewer)), "{:?} without {}", test, name);
+                if excluded.contains(*flag) {
+                    assert!(!is_valid(&transaction, fill_flags(flags | *flag)), "{:?} with {}", test, name);
+                }
+            });
+        });
+    }
+
+    #[test]
+    fn tx_invalid() {
+        let (skipped, tests): (Vec<Vec<Value>>, Vec<Vec<Value>>) =
+            tests(TX_INVALID).into_iter().partition(|test| skipped_flag(test).is_some());
+        for (flag, reason) in SKIPPED_FLAGS.iter() {
+            let is_used = skipped.iter().any(|test| skipped_flag(test) == Some(*flag));
+            assert!(is_used, "no test is skipped for {}, which {}", flag, reason);
+        }
+
+        tests.iter().for_each(|test| {
+            let transaction = test_transaction(test);
+            let flags = VerificationFlags::from_str(test[2].as_str().unwrap()).unwrap();
+            assert!(!is_valid(&transaction, flags), "{:?}", test);
+
+            // Adding flags never validates a transaction, and the flags are all needed
+            VerificationFlags::FLAGS.iter().for_each(|(name, flag)| {
+                assert!(!is_valid(&transaction, fill_flags(flags | *flag)), "{:?} with {}", test, name);
+                if flags.contains(*flag) {
+                    let mut fewer = flags;
+                    fewer.remove(*flag);
+                    assert!(is_valid(&transaction, trim_flags(fewer)), "{:?} without {}", test, name);
+                }
+            });
+        });
+    }
+
+    mod signed_transactions {
+        use super::*;
+
+        const PRIVATE_KEY: &str = "KyRv5iFPHG7iB5E4CqvMzH3WFJVhbfYK4VY7XAedd9Ys69mEsPLQ";
+        const TRANSACTION_ID: &str = "9cb01977970f1322931532c9467e85eee9daf620b1886eb70d7c9b0e6c993bc4";
+
+        /// Returns a transaction spending a P2PKH, P2SH-P2WPKH, P2WPKH and P2TR output of the key.
+        fn signed_transaction() -> BitcoinTransaction<N> {
+            let private_key = BitcoinPrivateKey::<N>::from_str(PRIVATE_KEY).unwrap();
+            let public_key = private_key.to_public_key().to_secp256k1_public_key().serialize_compressed();
+            let input = |index, format| {
+                let address = private_key.to_address(&format).unwrap().to_string();
+                BitcoinTransactionInputBuilder::new(TRANSACTION_ID, index)
+                    .address(&address)
+                    .amount(BitcoinAmount(10000 * (index as i64 + 1)))
+            };
+            let mut transaction = BitcoinTransactionBuilder::<N>::new()
+                .version(2)
+                .input(input(0, BitcoinFormat::P2PKH))
+                .input(input(1, BitcoinFormat::P2SH_P2WPKH).redeem_script([vec![0x00, 0x14], hash160(&public_key)].concat()))
+                .input(input(2, BitcoinFormat::Bech32))
+                .input(input(3, BitcoinFormat::P2TR))
+                .output("1Fyxts6r24DpEieygQiNnWxUdb18ANa5p7", BitcoinAmount(90000))
+                .build()
+                .unwrap();
+            transaction.sign_with_private_key(&private_key).unwrap();
+            transaction
+        }
+
+        #[test]
+        fn verify() {
+            let transaction = signed_transaction();
+            assert_eq!(vec![Ok(()); 4], transaction.verify(VerificationFlags::STANDARD));
+            assert_eq!(vec![Ok(()); 4], transaction.verify(VerificationFlags::ALL));
+
+            // The signed transaction verifies once deserialized with the outpoints of its inputs
+            let mut deserialized = BitcoinTransaction::<N>::from_bytes(&transaction.to_bytes().unwrap()).unwrap();
+            let outpoints: Vec<Outpoint<N>> =
+                transaction.parameters.inputs.iter().map(|input| input.outpoint.clone()).collect();
+            assert_eq!(
+                Err(InterpreterError::MissingOutpointScriptPublicKey.into()),
+                deserialized.verify_input(0, VerificationFlags::STANDARD)
+            );
+            outpoints.into_iter().for_each(|outpoint| deserialized = deserialized.update_outpoint(outpoint));
+            assert_eq!(vec![Ok(()); 4], deserialized.verify(VerificationFlags::STANDARD));
+        }
+
+        #[test]
+        fn invalid_signatures() {
+            // Signatures no longer sign the transaction once its output is changed
+            let mut transaction = signed_transaction();
+            transaction.parameters.outputs[0].amount = BitcoinAmount(80000);
+
+            let null_fail = ScriptFailure {
+                error: InterpreterError::NullFail,
+                opcode: Some(Opcode::OP_CHECKSIG as u8),
+            };
+            let schnorr_signature = ScriptFailure::from(InterpreterError::SchnorrSig);
+            assert_eq!(
+                vec![Err(null_fail.clone()), Err(null_fail.clone()), Err(null_fail), Err(schnorr_signature.clone())],
+                transaction.verify(VerificationFlags::STANDARD)
+            );
+
+            // Without NULLFAIL, a failed signature check leaves false on the stack
+            let eval_false = ScriptFailure::from(InterpreterError::EvalFalse);
+            assert_eq!(
+                vec![Err(eval_false.clone()), Err(eval_false.clone()), Err(eval_false), Err(schnorr_signature)],
+                transaction.verify(VerificationFlags::CONSENSUS)
+            );
+
+            // Only Taproot signatures commit to the amounts of every input
+            let mut transaction = signed_transaction();
+            transaction.parameters.inputs[0].outpoint.amount = Some(BitcoinAmount(1));
+            let results = transaction.verify(VerificationFlags::STANDARD);
+            assert!(results[..3].iter().all(Result::is_ok));
+            assert_eq!(Err(InterpreterError::SchnorrSig.into()), results[3]);
+
+            // SegWit signatures commit to the amount of their input
+            transaction.parameters.inputs[2].outpoint.amount = None;
+            assert_eq!(
+                Err(ScriptFailure {
+                    error: InterpreterError::MissingOutpointAmount,
+                    opcode: Some(Opcode::OP_CHECKSIG as u8)
+                }),
+                transaction.verify_input(2, VerificationFlags::STANDARD)
+            );
+            assert_eq!(
+                Err(InterpreterError::InvalidInputIndex(4).into()),
+                transaction.verify_input(4, VerificationFlags::STANDARD)
+            );
+        }
+    }
+
+    #[test]
+    fn failing_opcode() {
+        let verify = |script_sig: &str, script_pub_key: &str, flags: VerificationFlags| {
+            spending_transaction(parse_script(script_sig), parse_script(script_pub_key), &[], BitcoinAmount(0))
+                .verify_input(0, flags)
+        };
+        let failure = verify("1 2", "ADD 4 EQUALVERIFY 1", VerificationFlags::STANDARD).unwrap_err();
+        assert_eq!(
+            ScriptFailure {
+                error: InterpreterError::EqualVerify,
+                opcode: Some(Opcode::OP_EQUALVERIFY as u8)
+            },
+            failure
+        );
+        assert_eq!("script failed an OP_EQUALVERIFY operation at OP_EQUALVERIFY", failure.to_string());
+
+        let failure = verify("0x4c01 0x07", "7 EQUAL", VerificationFlags::STANDARD).unwrap_err();
+        assert_eq!("data push larger than necessary at OP_PUSHDATA1", failure.to_string());
+        let failure = verify("0x01 0x07", "7 EQUAL", VerificationFlags::STANDARD).unwrap_err();
+        assert_eq!("data push larger than necessary at push of 1 bytes", failure.to_string());
+        let failure = verify("1", "0xbb", VerificationFlags::STANDARD).unwrap_err();
+        assert_eq!("opcode missing or not understood at 0xbb", failure.to_string());
+        let failure = verify("1", "1", VerificationFlags::STANDARD).unwrap_err();
+        assert_eq!(ScriptFailure::from(InterpreterError::CleanStack), failure);
+        assert_eq!("stack size must be exactly one after execution", failure.to_string());
+        assert_eq!(Ok(()), verify("1", "1", VerificationFlags::CONSENSUS));
+    }
+
+    #[test]
+    fn flags() {
+        let flags = VerificationFlags::from_str("P2SH, WITNESS,TAPROOT").unwrap();
+        assert_eq!(VerificationFlags::P2SH | VerificationFlags::WITNESS | VerificationFlags::TAPROOT, flags);
+        assert_eq!("P2SH,WITNESS,TAPROOT", flags.to_string());
+        assert_eq!(flags, VerificationFlags::from_str(&flags.to_string()).unwrap());
+        assert_eq!(VerificationFlags::NONE, VerificationFlags::from_str("NONE").unwrap());
+        assert_eq!(VerificationFlags::NONE, VerificationFlags::from_str("").unwrap());
+        assert_eq!("NONE", VerificationFlags::NONE.to_string());
+        assert_eq!(
+            Err(InterpreterError::InvalidFlag("SEGWIT".into())),
+            VerificationFlags::from_str("P2SH,SEGWIT")
+        );
+
+        assert!(VerificationFlags::STANDARD.contains(VerificationFlags::CONSENSUS));
+        assert!(!VerificationFlags::STANDARD.contains(VerificationFlags::SIGPUSHONLY));
+        assert!(VerificationFlags::ALL.contains(VerificationFlags::STANDARD | VerificationFlags::SIGPUSHONLY));
+        assert_eq!(VerificationFlags::ALL, VerificationFlags::FLAGS.iter().fold(VerificationFlags::NONE, |all, (_, flag)| all | *flag));
+    }
+}
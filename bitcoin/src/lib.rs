@@ -21,10 +21,40 @@ pub mod public_key;
 
 pub mod witness_program;
 
+pub mod cashaddr;
+
+pub mod script;
+
+pub mod multisig;
+
+pub mod taproot;
+
+pub mod timelock;
+
+pub mod interpreter;
+
 pub mod transaction;
 
+pub mod block;
+
+pub mod block_filter;
+
+pub mod psbt;
+
+pub mod descriptor;
+
+pub mod miniscript;
+
 pub mod amount;
 
+pub mod coin_selection;
+
+pub mod fee_bump;
+
+pub mod omni;
+
+mod sighash_cache;
+
 mod testnet_daemon{
     use core::str::FromStr;
 
@@ -0,0 +1,1493 @@
+//! # Miniscript
+//!
+//! Miniscript, a subset of Bitcoin Script built from fragments that compose by type, so that a script
+//! written with it can be checked for correctness, its maximum witness size computed and its witness
+//! built from whichever signatures, preimages and lock times are available.
+//! A spending policy such as `or(thresh(2,pk(A),pk(B),pk(C)),and(pk(D),older(52560)))` compiles to
+//! `or_d(multi(2,A,B,C),and_v(v:pk(D),older(52560)))`, whose script is spent through P2WSH with
+//! compressed public keys. The `pk_h` fragment and the `hash256`, `ripemd160` and `hash160` hash locks
+//! are not supported.
+//! https://bitcoin.sipa.be/miniscript/
+
+use crate::address::BitcoinAddress;
+use crate::descriptor::split_arguments;
+use crate::interpreter::script_num;
+use crate::network::BitcoinNetwork;
+use crate::script::{Instruction, Instructions, Opcode, Script};
+use crate::timelock::{LockTime, RelativeLockTime};
+use crate::transaction::variable_length_integer;
+use chainlib_core::no_std::*;
+use chainlib_core::{libsecp256k1, AddressError, TransactionError};
+
+use core::{fmt, str::FromStr};
+
+/// The maximum number of public keys of a `multi` fragment in P2WSH
+const MAX_MULTI_KEYS: usize = 20;
+/// The maximum size of a low-S DER signature with its sighash byte and length prefix
+const SIGNATURE_SIZE: usize = 73;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum MiniscriptError {
+    #[error("invalid miniscript expression {0}")]
+    InvalidExpression(String),
+
+    #[error("invalid miniscript public key {0}")]
+    InvalidKey(String),
+
+    #[error("invalid miniscript script {0}")]
+    InvalidScript(String),
+
+    #[error("invalid threshold of {0} for {1} subexpressions")]
+    InvalidThreshold(usize, usize),
+
+    #[error("invalid timelock {0}")]
+    InvalidTimelock(String),
+
+    #[error("{0} does not type check")]
+    InvalidType(String),
+}
+
+impl From<MiniscriptError> for AddressError {
+    fn from(error: MiniscriptError) -> Self {
+        AddressError::Crate("Miniscript", format!("{:?}", error))
+    }
+}
+
+impl From<MiniscriptError> for TransactionError {
+    fn from(error: MiniscriptError) -> Self {
+        TransactionError::Crate("Miniscript", format!("{:?}", error))
+    }
+}
+
+/// Represents the basic type of a miniscript expression
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BaseType {
+    /// Pushes a nonzero value when satisfied and an exact zero when dissatisfied
+    B,
+    /// Continues when satisfied and aborts the script otherwise, without pushing anything
+    V,
+    /// Pushes the public key whose signature satisfies it
+    K,
+    /// Like B, but operates below the top stack element
+    W,
+}
+
+/// Represents the type of a miniscript expression, its basic type and properties
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Type {
+    /// The basic type
+    pub base: BaseType,
+    /// z: always consumes exactly zero stack elements
+    pub zero_arg: bool,
+    /// o: always consumes exactly one stack element
+    pub one_arg: bool,
+    /// n: never needs a zero top stack element to be satisfied
+    pub non_zero: bool,
+    /// d: has a dissatisfaction without signatures
+    pub dissatisfiable: bool,
+    /// u: pushes exactly 1 when satisfied
+    pub unit: bool,
+}
+
+impl Type {
+    /// Returns the type of the basic type without properties.
+    fn base(base: BaseType) -> Self {
+        Self { base, zero_arg: false, one_arg: false, non_zero: false, dissatisfiable: false, unit: false }
+    }
+
+    /// Returns true if the type is B and dissatisfiable with a unit satisfaction (Bdu).
+    fn is_bdu(&self) -> bool {
+        self.base == BaseType::B && self.dissatisfiable && self.unit
+    }
+}
+
+/// Represents a miniscript expression, a fragment or a wrapper of other expressions
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Miniscript {
+    /// 0, never satisfied
+    False,
+    /// 1, always satisfied
+    True,
+    /// pk_k(K), a compressed public key for a signature check
+    PkK(Vec<u8>),
+    /// older(n), a relative lock time
+    Older(RelativeLockTime),
+    /// after(n), an absolute lock time
+    After(LockTime),
+    /// sha256(h), the preimage of a SHA256 hash
+    Sha256([u8; 32]),
+    /// a:X, X on the alt stack
+    Alt(Box<Miniscript>),
+    /// s:X, X below the top stack element
+    Swap(Box<Miniscript>),
+    /// c:X, a signature check of the public key of X
+    Check(Box<Miniscript>),
+    /// d:X, X if the top stack element is 1 and 0 otherwise
+    DupIf(Box<Miniscript>),
+    /// v:X, X or abort
+    Verify(Box<Miniscript>),
+    /// j:X, X if the top stack element is nonzero and 0 otherwise
+    NonZero(Box<Miniscript>),
+    /// n:X, X with its nonzero result converted to 1
+    ZeroNotEqual(Box<Miniscript>),
+    /// and_v(X,Y), X and Y
+    AndV(Box<Miniscript>, Box<Miniscript>),
+    /// and_b(X,Y), X and Y
+    AndB(Box<Miniscript>, Box<Miniscript>),
+    /// andor(X,Y,Z), X and Y, or Z
+    AndOr(Box<Miniscript>, Box<Miniscript>, Box<Miniscript>),
+    /// or_b(X,Z), X or Z, both evaluated
+    OrB(Box<Miniscript>, Box<Miniscript>),
+    /// or_c(X,Z), X or Z, continuing the script
+    OrC(Box<Miniscript>, Box<Miniscript>),
+    /// or_d(X,Z), X or Z
+    OrD(Box<Miniscript>, Box<Miniscript>),
+    /// or_i(X,Z), X or Z as chosen by the witness
+    OrI(Box<Miniscript>, Box<Miniscript>),
+    /// thresh(k,X1,...,Xn), k of the expressions
+    Thresh(usize, Vec<Miniscript>),
+    /// multi(k,K1,...,Kn), k signatures of the public keys with `OP_CHECKMULTISIG`
+    Multi(usize, Vec<Vec<u8>>),
+}
+
+/// The maximum witness size, in bytes with length prefixes, and number of elements of a satisfaction
+type Size = Option<(usize, usize)>;
+/// The witness stack of a satisfaction, bottom first
+type Witness = Option<Vec<Vec<u8>>>;
+
+impl Miniscript {
+    /// Returns the miniscript of a P2WSH witness script, which must be its canonical encoding.
+    pub fn from_script(script: &[u8]) -> Result<Self, MiniscriptError> {
+        let invalid = || MiniscriptError::InvalidScript(hex::encode(script));
+        let tokens = Instructions::new(script)
+            .collect::<Result<Vec<Instruction>, _>>()
+            .map_err(|_| invalid())?;
+        let mut decoder = Decoder { end: tokens.len(), tokens };
+        let miniscript = decoder.parse_expression().ok_or_else(invalid)?;
+        match decoder.end == 0 && miniscript.to_script() == script {
+            true => miniscript.checked(),
+            false => Err(invalid()),
+        }
+    }
+
+    /// Returns the serialized script.
+    pub fn to_script(&self) -> Vec<u8> {
+        self.encode(Script::new(), false).into_bytes()
+    }
+
+    /// Returns the P2WSH address of the script.
+    pub fn p2wsh_address<N: BitcoinNetwork>(&self) -> Result<BitcoinAddress<N>, AddressError> {
+        BitcoinAddress::<N>::p2wsh(&self.to_script())
+    }
+
+    /// Returns the public keys of the miniscript, in script order.
+    pub fn public_keys(&self) -> Vec<&[u8]> {
+        match self {
+            Miniscript::PkK(key) => vec![key.as_slice()],
+            Miniscript::Multi(_, keys) => keys.iter().map(Vec::as_slice).collect(),
+            _ => self.children().into_iter().flat_map(Miniscript::public_keys).collect(),
+        }
+    }
+
+    /// Returns the type of the miniscript, or an error if it does not type check.
+    pub fn type_check(&self) -> Result<Type, MiniscriptError> {
+        use BaseType::*;
+        let invalid = || MiniscriptError::InvalidType(self.to_string());
+        let ty = match self {
+            Miniscript::False => Type { zero_arg: true, dissatisfiable: true, unit: true, ..Type::base(B) },
+            Miniscript::True => Type { zero_arg: true, unit: true, ..Type::base(B) },
+            Miniscript::PkK(_) => Type { one_arg: true, non_zero: true, dissatisfiable: true, unit: true, ..Type::base(K) },
+            Miniscript::Older(_) | Miniscript::After(_) => Type { zero_arg: true, ..Type::base(B) },
+            Miniscript::Sha256(_) => Type { one_arg: true, non_zero: true, dissatisfiable: true, unit: true, ..Type::base(B) },
+            Miniscript::Alt(x) => match x.type_check()? {
+                x if x.base == B => Type { dissatisfiable: x.dissatisfiable, unit: x.unit, ..Type::base(W) },
+                _ => return Err(invalid()),
+            },
+            Miniscript::Swap(x) => match x.type_check()? {
+                x if x.base == B && x.one_arg => Type { dissatisfiable: x.dissatisfiable, unit: x.unit, ..Type::base(W) },
+                _ => return Err(invalid()),
+            },
+            Miniscript::Check(x) => match x.type_check()? {
+                x if x.base == K => Type { base: B, unit: true, ..x },
+                _ => return Err(invalid()),
+            },
+            // `OP_IF` only requires an exact 1 with MINIMALIF, which is not consensus in P2WSH, so d:X is not u
+            Miniscript::DupIf(x) => match x.type_check()? {
+                x if x.base == V && x.zero_arg => {
+                    Type { one_arg: true, non_zero: true, dissatisfiable: true, ..Type::base(B) }
+                }
+                _ => return Err(invalid()),
+            },
+            Miniscript::Verify(x) => match x.type_check()? {
+                x if x.base == B => Type { zero_arg: x.zero_arg, one_arg: x.one_arg, non_zero: x.non_zero, ..Type::base(V) },
+                _ => return Err(invalid()),
+            },
+            Miniscript::NonZero(x) => match x.type_check()? {
+                x if x.base == B && x.non_zero => {
+                    Type { one_arg: x.one_arg, non_zero: true, dissatisfiable: true, unit: x.unit, ..Type::base(B) }
+                }
+                _ => return Err(invalid()),
+            },
+            Miniscript::ZeroNotEqual(x) => match x.type_check()? {
+                x if x.base == B => Type { unit: true, ..x },
+                _ => return Err(invalid()),
+            },
+            Miniscript::AndV(x, y) => match (x.type_check()?, y.type_check()?) {
+                (x, y) if x.base == V && y.base != W => Type {
+                    base: y.base,
+                    zero_arg: x.zero_arg && y.zero_arg,
+                    one_arg: (x.zero_arg && y.one_arg) || (x.one_arg && y.zero_arg),
+                    non_zero: x.non_zero || (x.zero_arg && y.non_zero),
+                    dissatisfiable: false,
+                    unit: y.unit,
+                },
+                _ => return Err(invalid()),
+            },
+            Miniscript::AndB(x, y) => match (x.type_check()?, y.type_check()?) {
+                (x, y) if x.base == B && y.base == W => Type {
+                    base: B,
+                    zero_arg: x.zero_arg && y.zero_arg,
+                    one_arg: (x.zero_arg && y.one_arg) || (x.one_arg && y.zero_arg),
+                    non_zero: x.non_zero || (x.zero_arg && y.non_zero),
+                    dissatisfiable: x.dissatisfiable && y.dissatisfiable,
+                    unit: true,
+                },
+                _ => return Err(invalid()),
+            },
+            Miniscript::OrB(x, z) => match (x.type_check()?, z.type_check()?) {
+                (x, z) if x.base == B && x.dissatisfiable && z.base == W && z.dissatisfiable => Type {
+                    zero_arg: x.zero_arg && z.zero_arg,
+                    one_arg: (x.zero_arg && z.one_arg) || (x.one_arg && z.zero_arg),
+                    dissatisfiable: true,
+                    unit: true,
+                    ..Type::base(B)
+                },
+                _ => return Err(invalid()),
+            },
+            Miniscript::OrC(x, z) => match (x.type_check()?, z.type_check()?) {
+                (x, z) if x.is_bdu() && z.base == V => Type {
+                    zero_arg: x.zero_arg && z.zero_arg,
+                    one_arg: x.one_arg && z.zero_arg,
+                    ..Type::base(V)
+                },
+                _ => return Err(invalid()),
+            },
+            Miniscript::OrD(x, z) => match (x.type_check()?, z.type_check()?) {
+                (x, z) if x.is_bdu() && z.base == B => Type {
+                    zero_arg: x.zero_arg && z.zero_arg,
+                    one_arg: x.one_arg && z.zero_arg,
+                    dissatisfiable: z.dissatisfiable,
+                    unit: z.unit,
+                    ..Type::base(B)
+                },
+                _ => return Err(invalid()),
+            },
+            Miniscript::OrI(x, z) => match (x.type_check()?, z.type_check()?) {
+                (x, z) if x.base == z.base && x.base != W => Type {
+                    one_arg: x.zero_arg && z.zero_arg,
+                    dissatisfiable: x.dissatisfiable || z.dissatisfiable,
+                    unit: x.unit && z.unit,
+                    ..Type::base(x.base)
+                },
+                _ => return Err(invalid()),
+            },
+            Miniscript::AndOr(x, y, z) => match (x.type_check()?, y.type_check()?, z.type_check()?) {
+                (x, y, z) if x.is_bdu() && y.base == z.base && y.base != W => Type {
+                    zero_arg: x.zero_arg && y.zero_arg && z.zero_arg,
+                    one_arg: (x.zero_arg && y.one_arg && z.one_arg) || (x.one_arg && y.zero_arg && z.zero_arg),
+                    dissatisfiable: z.dissatisfiable,
+                    unit: y.unit && z.unit,
+                    ..Type::base(y.base)
+                },
+                _ => return Err(invalid()),
+            },
+            Miniscript::Thresh(threshold, subs) => {
+                if *threshold == 0 || *threshold > subs.len() {
+                    return Err(MiniscriptError::InvalidThreshold(*threshold, subs.len()));
+                }
+                let types = subs.iter().map(Miniscript::type_check).collect::<Result<Vec<Type>, _>>()?;
+                let is_valid = types.iter().enumerate().all(|(i, ty)| {
+                    ty.dissatisfiable && ty.unit && ty.base == if i == 0 { B } else { W }
+                });
+                if !is_valid {
+                    return Err(invalid());
+                }
+                let zero_args = types.iter().filter(|ty| ty.zero_arg).count();
+                let one_args = types.iter().filter(|ty| ty.one_arg).count();
+                Type {
+                    zero_arg: zero_args == types.len(),
+                    one_arg: zero_args + 1 == types.len() && one_args == 1,
+                    dissatisfiable: true,
+                    unit: true,
+                    ..Type::base(B)
+                }
+            }
+            Miniscript::Multi(threshold, keys) => {
+                if *threshold == 0 || *threshold > keys.len() || keys.len() > MAX_MULTI_KEYS {
+                    return Err(MiniscriptError::InvalidThreshold(*threshold, keys.len()));
+                }
+                Type { non_zero: true, dissatisfiable: true, unit: true, ..Type::base(B) }
+            }
+        };
+        Ok(ty)
+    }
+
+    /// Returns the number of elements and size of the largest witness stack satisfying the miniscript,
+    /// with the length prefixes of its elements and without the witness script.
+    pub fn max_satisfaction_size(&self) -> Option<(usize, usize)> {
+        self.max_sizes().0.map(|(size, elements)| (elements, size))
+    }
+
+    /// Returns the maximum size of a P2WSH witness satisfying the miniscript,
+    /// with its element count, length prefixes and the witness script.
+    pub fn max_witness_size(&self) -> Option<usize> {
+        let (elements, size) = self.max_satisfaction_size()?;
+        let script = self.to_script();
+        Some(
+            variable_length_integer(elements as u64 + 1).ok()?.len()
+                + size
+                + variable_length_integer(script.len() as u64).ok()?.len()
+                + script.len(),
+        )
+    }
+
+    /// Returns the smallest witness stack satisfying the miniscript with what the satisfier has available,
+    /// bottom first and without the witness script, or `None` if it cannot be satisfied yet.
+    pub fn satisfy(&self, satisfier: &Satisfier) -> Option<Vec<Vec<u8>>> {
+        self.satisfactions(satisfier).0
+    }
+
+    /// Returns the miniscript if it type checks as B, as required at the top level.
+    fn checked(self) -> Result<Self, MiniscriptError> {
+        match self.type_check()?.base {
+            BaseType::B => Ok(self),
+            _ => Err(MiniscriptError::InvalidType(self.to_string())),
+        }
+    }
+
+    /// Returns the subexpressions of a wrapper or combinator.
+    fn children(&self) -> Vec<&Miniscript> {
+        match self {
+            Miniscript::Alt(x)
+            | Miniscript::Swap(x)
+            | Miniscript::Check(x)
+            | Miniscript::DupIf(x)
+            | Miniscript::Verify(x)
+            | Miniscript::NonZero(x)
+            | Miniscript::ZeroNotEqual(x) => vec![x],
+            Miniscript::AndV(x, y)
+            | Miniscript::AndB(x, y)
+            | Miniscript::OrB(x, y)
+            | Miniscript::OrC(x, y)
+            | Miniscript::OrD(x, y)
+            | Miniscript::OrI(x, y) => vec![x, y],
+            Miniscript::AndOr(x, y, z) => vec![x, y, z],
+            Miniscript::Thresh(_, subs) => subs.iter().collect(),
+            _ => vec![],
+        }
+    }
+
+    /// Returns true if the script of the miniscript ends with an opcode having a VERIFY variant,
+    /// which v: replaces rather than appending `OP_VERIFY`.
+    fn has_verify_opcode(&self) -> bool {
+        match self {
+            Miniscript::Check(_) | Miniscript::Sha256(_) | Miniscript::Thresh(..) | Miniscript::Multi(..) => true,
+            Miniscript::AndV(_, y) => y.has_verify_opcode(),
+            _ => false,
+        }
+    }
+
+    /// Appends the script of the miniscript, ending with the VERIFY variant of its last opcode if `verify` is set.
+    fn encode(&self, script: Script, verify: bool) -> Script {
+        let last = |opcode: Opcode, verify_opcode: Opcode| if verify { verify_opcode } else { opcode };
+        match self {
+            Miniscript::False => script.push_opcode(Opcode::OP_0),
+            Miniscript::True => script.push_opcode(Opcode::OP_1),
+            Miniscript::PkK(key) => script.push_slice(key),
+            Miniscript::Older(lock_time) => script
+                .push_int(lock_time.to_sequence() as i64)
+                .push_opcode(Opcode::OP_CHECKSEQUENCEVERIFY),
+            Miniscript::After(lock_time) => script
+                .push_int(lock_time.to_consensus_u32() as i64)
+                .push_opcode(Opcode::OP_CHECKLOCKTIMEVERIFY),
+            Miniscript::Sha256(hash) => script
+                .push_opcode(Opcode::OP_SIZE)
+                .push_int(32)
+                .push_opcode(Opcode::OP_EQUALVERIFY)
+                .push_opcode(Opcode::OP_SHA256)
+                .push_slice(hash)
+                .push_opcode(last(Opcode::OP_EQUAL, Opcode::OP_EQUALVERIFY)),
+            Miniscript::Alt(x) => x
+                .encode(script.push_opcode(Opcode::OP_TOALTSTACK), false)
+                .push_opcode(Opcode::OP_FROMALTSTACK),
+            Miniscript::Swap(x) => x.encode(script.push_opcode(Opcode::OP_SWAP), false),
+            Miniscript::Check(x) => x
+                .encode(script, false)
+                .push_opcode(last(Opcode::OP_CHECKSIG, Opcode::OP_CHECKSIGVERIFY)),
+            Miniscript::DupIf(x) => x
+                .encode(script.push_opcode(Opcode::OP_DUP).push_opcode(Opcode::OP_IF), false)
+                .push_opcode(Opcode::OP_ENDIF),
+            Miniscript::Verify(x) => match x.has_verify_opcode() {
+                true => x.encode(script, true),
+                false => x.encode(script, false).push_opcode(Opcode::OP_VERIFY),
+            },
+            Miniscript::NonZero(x) => {
+                let script = script
+                    .push_opcode(Opcode::OP_SIZE)
+                    .push_opcode(Opcode::OP_0NOTEQUAL)
+                    .push_opcode(Opcode::OP_IF);
+                x.encode(script, false).push_opcode(Opcode::OP_ENDIF)
+            }
+            Miniscript::ZeroNotEqual(x) => x.encode(script, false).push_opcode(Opcode::OP_0NOTEQUAL),
+            Miniscript::AndV(x, y) => y.encode(x.encode(script, false), verify),
+            Miniscript::AndB(x, y) => y.encode(x.encode(script, false), false).push_opcode(Opcode::OP_BOOLAND),
+            Miniscript::OrB(x, z) => z.encode(x.encode(script, false), false).push_opcode(Opcode::OP_BOOLOR),
+            Miniscript::OrC(x, z) => z
+                .encode(x.encode(script, false).push_opcode(Opcode::OP_NOTIF), false)
+                .push_opcode(Opcode::OP_ENDIF),
+            Miniscript::OrD(x, z) => {
+                let script = x
+                    .encode(script, false)
+                    .push_opcode(Opcode::OP_IFDUP)
+                    .push_opcode(Opcode::OP_NOTIF);
+                z.encode(script, false).push_opcode(Opcode::OP_ENDIF)
+            }
+            Miniscript::OrI(x, z) => {
+                let script = x.encode(script.push_opcode(Opcode::OP_IF), false).push_opcode(Opcode::OP_ELSE);
+                z.encode(script, false).push_opcode(Opcode::OP_ENDIF)
+            }
+            Miniscript::AndOr(x, y, z) => {
+                let script = z
+                    .encode(x.encode(script, false).push_opcode(Opcode::OP_NOTIF), false)
+                    .push_opcode(Opcode::OP_ELSE);
+                y.encode(script, false).push_opcode(Opcode::OP_ENDIF)
+            }
+            Miniscript::Thresh(threshold, subs) => {
+                let script = subs.iter().enumerate().fold(script, |script, (i, sub)| match i {
+                    0 => sub.encode(script, false),
+                    _ => sub.encode(script, false).push_opcode(Opcode::OP_ADD),
+                });
+                script
+                    .push_int(*threshold as i64)
+                    .push_opcode(last(Opcode::OP_EQUAL, Opcode::OP_EQUALVERIFY))
+            }
+            Miniscript::Multi(threshold, keys) => keys
+                .iter()
+                .fold(script.push_int(*threshold as i64), |script, key| script.push_slice(key))
+                .push_int(keys.len() as i64)
+                .push_opcode(last(Opcode::OP_CHECKMULTISIG, Opcode::OP_CHECKMULTISIGVERIFY)),
+        }
+    }
+
+    /// Returns the maximum sizes of the satisfaction and of the dissatisfaction of the miniscript.
+    fn max_sizes(&self) -> (Size, Size) {
+        let add = |a: Size, b: Size| a.zip(b).map(|(a, b)| (a.0 + b.0, a.1 + b.1));
+        let max = |a: Size, b: Size| a.max(b);
+        let one = Some((2, 1));
+        let empty = Some((1, 1));
+        match self {
+            Miniscript::False => (None, Some((0, 0))),
+            Miniscript::True | Miniscript::Older(_) | Miniscript::After(_) => (Some((0, 0)), None),
+            Miniscript::PkK(_) => (Some((SIGNATURE_SIZE, 1)), empty),
+            Miniscript::Sha256(_) => (Some((33, 1)), Some((33, 1))),
+            Miniscript::Alt(x) | Miniscript::Swap(x) | Miniscript::Check(x) | Miniscript::ZeroNotEqual(x) => x.max_sizes(),
+            Miniscript::DupIf(x) => (add(x.max_sizes().0, one), empty),
+            Miniscript::Verify(x) => (x.max_sizes().0, None),
+            Miniscript::NonZero(x) => (x.max_sizes().0, empty),
+            Miniscript::AndV(x, y) => (add(x.max_sizes().0, y.max_sizes().0), None),
+            Miniscript::AndB(x, y) => {
+                let ((sat_x, dsat_x), (sat_y, dsat_y)) = (x.max_sizes(), y.max_sizes());
+                (add(sat_x, sat_y), add(dsat_x, dsat_y))
+            }
+            Miniscript::OrB(x, z) => {
+                let ((sat_x, dsat_x), (sat_z, dsat_z)) = (x.max_sizes(), z.max_sizes());
+                (max(add(sat_x, dsat_z), add(dsat_x, sat_z)), add(dsat_x, dsat_z))
+            }
+            Miniscript::OrC(x, z) => {
+                let ((sat_x, dsat_x), (sat_z, _)) = (x.max_sizes(), z.max_sizes());
+                (max(sat_x, add(dsat_x, sat_z)), None)
+            }
+            Miniscript::OrD(x, z) => {
+                let ((sat_x, dsat_x), (sat_z, dsat_z)) = (x.max_sizes(), z.max_sizes());
+                (max(sat_x, add(dsat_x, sat_z)), add(dsat_x, dsat_z))
+            }
+            Miniscript::OrI(x, z) => {
+                let ((sat_x, dsat_x), (sat_z, dsat_z)) = (x.max_sizes(), z.max_sizes());
+                (max(add(sat_x, one), add(sat_z, empty)), max(add(dsat_x, one), add(dsat_z, empty)))
+            }
+            Miniscript::AndOr(x, y, z) => {
+                let ((sat_x, dsat_x), (sat_y, _), (sat_z, dsat_z)) = (x.max_sizes(), y.max_sizes(), z.max_sizes());
+                (max(add(sat_x, sat_y), add(dsat_x, sat_z)), add(dsat_x, dsat_z))
+            }
+            Miniscript::Thresh(threshold, subs) => {
+                // The largest satisfaction dissatisfies all but the k subexpressions growing the witness the most
+                let sizes = subs.iter().map(Miniscript::max_sizes).collect::<Vec<_>>();
+                let dsat = sizes.iter().try_fold((0, 0), |total, (_, dsat)| add(Some(total), *dsat));
+                let mut growths = sizes
+                    .iter()
+                    .filter_map(|(sat, dsat)| sat.zip(*dsat))
+                    .map(|(sat, dsat)| (sat.0 as isize - dsat.0 as isize, sat.1 as isize - dsat.1 as isize))
+                    .collect::<Vec<_>>();
+                growths.sort_unstable_by(|a, b| b.cmp(a));
+                let sat = match growths.len() >= *threshold {
+                    true => dsat.map(|(size, elements)| {
+                        growths[..*threshold].iter().fold((size as isize, elements as isize), |total, growth| {
+                            (total.0 + growth.0, total.1 + growth.1)
+                        })
+                    }),
+                    false => None,
+                };
+                (sat.map(|(size, elements)| (size as usize, elements as usize)), dsat)
+            }
+            Miniscript::Multi(threshold, _) => {
+                (Some((1 + threshold * SIGNATURE_SIZE, threshold + 1)), Some((threshold + 1, threshold + 1)))
+            }
+        }
+    }
+
+    /// Returns the smallest satisfaction and the dissatisfaction of the miniscript with what the satisfier has available.
+    fn satisfactions(&self, satisfier: &Satisfier) -> (Witness, Witness) {
+        // Witness stacks are listed bottom first, so the elements consumed first come last
+        let join = |bottom: Witness, top: Witness| bottom.zip(top).map(|(bottom, top)| [bottom, top].concat());
+        let push = |witness: Witness, element: &[u8]| witness.map(|witness| [witness, vec![element.to_vec()]].concat());
+        let smallest = |a: Witness, b: Witness| match (a, b) {
+            (Some(a), Some(b)) => Some(if witness_size(&b) < witness_size(&a) { b } else { a }),
+            (a, b) => a.or(b),
+        };
+        let nothing = Some(vec![]);
+        match self {
+            Miniscript::False => (None, nothing),
+            Miniscript::True => (nothing, None),
+            Miniscript::PkK(key) => (satisfier.signatures.get(key).map(|signature| vec![signature.clone()]), Some(vec![vec![]])),
+            Miniscript::Older(lock_time) => match satisfier.version >= 2 && lock_time.is_satisfied_by_sequence(satisfier.sequence) {
+                true => (nothing, None),
+                false => (None, None),
+            },
+            // `OP_CHECKLOCKTIMEVERIFY` fails for final inputs, which disable the lock time of the transaction
+            Miniscript::After(lock_time) => {
+                match lock_time.is_satisfied_by(&satisfier.lock_time) && satisfier.sequence != u32::MAX {
+                    true => (nothing, None),
+                    false => (None, None),
+                }
+            }
+            Miniscript::Sha256(hash) => {
+                let preimage = satisfier.preimages.get(hash).filter(|preimage| preimage.len() == 32);
+                (preimage.map(|preimage| vec![preimage.clone()]), Some(vec![vec![0u8; 32]]))
+            }
+            Miniscript::Alt(x) | Miniscript::Swap(x) | Miniscript::Check(x) | Miniscript::ZeroNotEqual(x) => {
+                x.satisfactions(satisfier)
+            }
+            Miniscript::DupIf(x) => (push(x.satisfactions(satisfier).0, &[1]), Some(vec![vec![]])),
+            Miniscript::Verify(x) => (x.satisfactions(satisfier).0, None),
+            Miniscript::NonZero(x) => (x.satisfactions(satisfier).0, Some(vec![vec![]])),
+            Miniscript::AndV(x, y) => (join(y.satisfactions(satisfier).0, x.satisfactions(satisfier).0), None),
+            Miniscript::AndB(x, y) => {
+                let ((sat_x, dsat_x), (sat_y, dsat_y)) = (x.satisfactions(satisfier), y.satisfactions(satisfier));
+                (join(sat_y, sat_x), join(dsat_y, dsat_x))
+            }
+            Miniscript::OrB(x, z) => {
+                let ((sat_x, dsat_x), (sat_z, dsat_z)) = (x.satisfactions(satisfier), z.satisfactions(satisfier));
+                let sat = smallest(join(dsat_z.clone(), sat_x), join(sat_z, dsat_x.clone()));
+                (sat, join(dsat_z, dsat_x))
+            }
+            Miniscript::OrC(x, z) => {
+                let ((sat_x, dsat_x), (sat_z, _)) = (x.satisfactions(satisfier), z.satisfactions(satisfier));
+                (smallest(sat_x, join(sat_z, dsat_x)), None)
+            }
+            Miniscript::OrD(x, z) => {
+                let ((sat_x, dsat_x), (sat_z, dsat_z)) = (x.satisfactions(satisfier), z.satisfactions(satisfier));
+                (smallest(sat_x, join(sat_z, dsat_x.clone())), join(dsat_z, dsat_x))
+            }
+            Miniscript::OrI(x, z) => {
+                let ((sat_x, dsat_x), (sat_z, dsat_z)) = (x.satisfactions(satisfier), z.satisfactions(satisfier));
+                let sat = smallest(push(sat_x, &[1]), push(sat_z, &[]));
+                (sat, smallest(push(dsat_x, &[1]), push(dsat_z, &[])))
+            }
+            Miniscript::AndOr(x, y, z) => {
+                let (sat_x, dsat_x) = x.satisfactions(satisfier);
+                let ((sat_y, _), (sat_z, dsat_z)) = (y.satisfactions(satisfier), z.satisfactions(satisfier));
+                (smallest(join(sat_y, sat_x), join(sat_z, dsat_x.clone())), join(dsat_z, dsat_x))
+            }
+            Miniscript::Thresh(threshold, subs) => {
+                let satisfactions = subs.iter().map(|sub| sub.satisfactions(satisfier)).collect::<Vec<_>>();
+                let dsats = satisfactions.iter().map(|(_, dsat)| dsat.clone()).collect::<Option<Vec<_>>>();
+                let dsats = match dsats {
+                    Some(dsats) => dsats,
+                    None => return (None, None),
+                };
+                // Satisfy the k subexpressions whose satisfactions grow the witness the least
+                let mut growths = satisfactions
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(i, (sat, dsat))| Some((witness_size(sat.as_ref()?) as isize - witness_size(dsat.as_ref()?) as isize, i)))
+                    .collect::<Vec<_>>();
+                growths.sort_unstable();
+                let sat = match growths.len() >= *threshold {
+                    true => {
+                        let satisfied = growths[..*threshold].iter().map(|(_, i)| *i).collect::<Vec<_>>();
+                        let sat = satisfactions.iter().enumerate().rev().fold(vec![], |witness, (i, (sat, dsat))| {
+                            let sub = if satisfied.contains(&i) { sat } else { dsat };
+                            [witness, sub.clone().unwrap_or_default()].concat()
+                        });
+                        Some(sat)
+                    }
+                    false => None,
+                };
+                (sat, Some(dsats.into_iter().rev().flatten().collect()))
+            }
+            Miniscript::Multi(threshold, keys) => {
+                // The dummy element consumed by `OP_CHECKMULTISIG`, then signatures in the order of their keys
+                let signatures = keys
+                    .iter()
+                    .filter_map(|key| satisfier.signatures.get(key).cloned())
+                    .take(*threshold)
+                    .collect::<Vec<_>>();
+                let sat = match signatures.len() == *threshold {
+                    true => Some([vec![vec![]], signatures].concat()),
+                    false => None,
+                };
+                (sat, Some(vec![vec![]; threshold + 1]))
+            }
+        }
+    }
+
+    /// Returns the miniscript of the expression, which may be missing its top-level type check.
+    fn parse(expression: &str) -> Result<Self, MiniscriptError> {
+        let invalid = || MiniscriptError::InvalidExpression(expression.into());
+        let (wrappers, fragment) = match (expression.find(':'), expression.find('(')) {
+            (Some(colon), None) => (&expression[..colon], &expression[colon + 1..]),
+            (Some(colon), Some(parenthesis)) if colon < parenthesis => (&expression[..colon], &expression[colon + 1..]),
+            _ => ("", expression),
+        };
+        let mut miniscript = match fragment {
+            "0" => Miniscript::False,
+            "1" => Miniscript::True,
+            _ => {
+                let (name, arguments) = fragment
+                    .strip_suffix(')')
+                    .and_then(|fragment| fragment.split_once('('))
+                    .ok_or_else(invalid)?;
+                let arguments = split_arguments(arguments).map_err(|_| invalid())?;
+                let parse = |argument: &str| Miniscript::parse(argument).map(Box::new);
+                match (name, &arguments[..]) {
+                    ("pk_k", [key]) => Miniscript::PkK(parse_key(key)?),
+                    ("pk", [key]) => Miniscript::Check(Box::new(Miniscript::PkK(parse_key(key)?))),
+                    ("older", [value]) => Miniscript::Older(parse_older(value)?),
+                    ("after", [value]) => Miniscript::After(parse_after(value)?),
+                    ("sha256", [hash]) => Miniscript::Sha256(parse_hash(hash)?),
+                    ("and_v", [x, y]) => Miniscript::AndV(parse(x)?, parse(y)?),
+                    ("and_b", [x, y]) => Miniscript::AndB(parse(x)?, parse(y)?),
+                    ("and_n", [x, y]) => Miniscript::AndOr(parse(x)?, parse(y)?, Box::new(Miniscript::False)),
+                    ("andor", [x, y, z]) => Miniscript::AndOr(parse(x)?, parse(y)?, parse(z)?),
+                    ("or_b", [x, z]) => Miniscript::OrB(parse(x)?, parse(z)?),
+                    ("or_c", [x, z]) => Miniscript::OrC(parse(x)?, parse(z)?),
+                    ("or_d", [x, z]) => Miniscript::OrD(parse(x)?, parse(z)?),
+                    ("or_i", [x, z]) => Miniscript::OrI(parse(x)?, parse(z)?),
+                    ("thresh", [threshold, subs @ ..]) => {
+                        let subs = subs.iter().map(|sub| Miniscript::parse(sub)).collect::<Result<Vec<_>, _>>()?;
+                        Miniscript::Thresh(threshold.parse().map_err(|_| invalid())?, subs)
+                    }
+                    ("multi", [threshold, keys @ ..]) => {
+                        let keys = keys.iter().map(|key| parse_key(key)).collect::<Result<Vec<_>, _>>()?;
+                        Miniscript::Multi(threshold.parse().map_err(|_| invalid())?, keys)
+                    }
+                    _ => return Err(invalid()),
+                }
+            }
+        };
+        // The innermost wrapper comes last
+        for wrapper in wrappers.chars().rev() {
+            let x = Box::new(miniscript);
+            miniscript = match wrapper {
+                'a' => Miniscript::Alt(x),
+                's' => Miniscript::Swap(x),
+                'c' => Miniscript::Check(x),
+                'd' => Miniscript::DupIf(x),
+                'v' => Miniscript::Verify(x),
+                'j' => Miniscript::NonZero(x),
+                'n' => Miniscript::ZeroNotEqual(x),
+                't' => Miniscript::AndV(x, Box::new(Miniscript::True)),
+                'l' => Miniscript::OrI(Box::new(Miniscript::False), x),
+                'u' => Miniscript::OrI(x, Box::new(Miniscript::False)),
+                _ => return Err(invalid()),
+            };
+        }
+        Ok(miniscript)
+    }
+}
+
+impl FromStr for Miniscript {
+    type Err = MiniscriptError;
+
+    fn from_str(miniscript: &str) -> Result<Self, Self::Err> {
+        Self::parse(miniscript)?.checked()
+    }
+}
+
+impl fmt::Display for Miniscript {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Wrappers, including the t:, l: and u: shorthands, are written as a prefix of the fragment
+        let mut wrappers = String::new();
+        let mut node = self;
+        loop {
+            let (wrapper, x) = match node {
+                Miniscript::Alt(x) => ('a', x),
+                Miniscript::Swap(x) => ('s', x),
+                Miniscript::Check(x) if !matches!(**x, Miniscript::PkK(_)) => ('c', x),
+                Miniscript::DupIf(x) => ('d', x),
+                Miniscript::Verify(x) => ('v', x),
+                Miniscript::NonZero(x) => ('j', x),
+                Miniscript::ZeroNotEqual(x) => ('n', x),
+                Miniscript::AndV(x, y) if **y == Miniscript::True => ('t', x),
+                Miniscript::OrI(x, z) if **x == Miniscript::False => ('l', z),
+                Miniscript::OrI(x, z) if **z == Miniscript::False => ('u', x),
+                _ => break,
+            };
+            wrappers.push(wrapper);
+            node = x;
+        }
+        if !wrappers.is_empty() {
+            write!(f, "{}:", wrappers)?;
+        }
+
+        let join = |subs: &[&Miniscript]| subs.iter().map(|sub| sub.to_string()).collect::<Vec<_>>().join(",");
+        match node {
+            Miniscript::False => write!(f, "0"),
+            Miniscript::True => write!(f, "1"),
+            Miniscript::PkK(key) => write!(f, "pk_k({})", hex::encode(key)),
+            Miniscript::Check(x) => match &**x {
+                Miniscript::PkK(key) => write!(f, "pk({})", hex::encode(key)),
+                _ => unreachable!(),
+            },
+            Miniscript::Older(lock_time) => write!(f, "older({})", lock_time.to_sequence()),
+            Miniscript::After(lock_time) => write!(f, "after({})", lock_time.to_consensus_u32()),
+            Miniscript::Sha256(hash) => write!(f, "sha256({})", hex::encode(hash)),
+            Miniscript::AndV(x, y) => write!(f, "and_v({})", join(&[x, y])),
+            Miniscript::AndB(x, y) => write!(f, "and_b({})", join(&[x, y])),
+            Miniscript::AndOr(x, y, z) if **z == Miniscript::False => write!(f, "and_n({})", join(&[x, y])),
+            Miniscript::AndOr(x, y, z) => write!(f, "andor({})", join(&[x, y, z])),
+            Miniscript::OrB(x, z) => write!(f, "or_b({})", join(&[x, z])),
+            Miniscript::OrC(x, z) => write!(f, "or_c({})", join(&[x, z])),
+            Miniscript::OrD(x, z) => write!(f, "or_d({})", join(&[x, z])),
+            Miniscript::OrI(x, z) => write!(f, "or_i({})", join(&[x, z])),
+            Miniscript::Thresh(threshold, subs) => {
+                write!(f, "thresh({},{})", threshold, join(&subs.iter().collect::<Vec<_>>()))
+            }
+            Miniscript::Multi(threshold, keys) => {
+                let keys = keys.iter().map(hex::encode).collect::<Vec<_>>();
+                write!(f, "multi({},{})", threshold, keys.join(","))
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Represents the signatures, preimages and lock times available to satisfy a miniscript
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Satisfier {
+    /// The signatures with their sighash byte, by compressed public key
+    pub signatures: BTreeMap<Vec<u8>, Vec<u8>>,
+    /// The preimages, by SHA256 hash
+    pub preimages: BTreeMap<[u8; 32], Vec<u8>>,
+    /// The version of the spending transaction, from 2 on which relative lock times apply (BIP-68)
+    pub version: u32,
+    /// The lock time of the spending transaction
+    pub lock_time: LockTime,
+    /// The sequence number of the spending input
+    pub sequence: u32,
+}
+
+impl Satisfier {
+    /// Returns a satisfier without signatures or preimages for the spending transaction and input.
+    pub fn new(version: u32, lock_time: LockTime, sequence: u32) -> Self {
+        Self { signatures: BTreeMap::new(), preimages: BTreeMap::new(), version, lock_time, sequence }
+    }
+}
+
+/// Represents a spending policy, the conditions of a script without its structure
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Policy {
+    /// pk(K), a signature of the compressed public key
+    Key(Vec<u8>),
+    /// after(n), an absolute lock time
+    After(LockTime),
+    /// older(n), a relative lock time
+    Older(RelativeLockTime),
+    /// sha256(h), the preimage of a SHA256 hash
+    Sha256([u8; 32]),
+    /// and(X,Y), all of the policies
+    And(Vec<Policy>),
+    /// or(X,Y), any of the policies, each weighted by how likely it is to be used
+    Or(Vec<(usize, Policy)>),
+    /// thresh(k,X1,...,Xn), k of the policies
+    Threshold(usize, Vec<Policy>),
+}
+
+impl Policy {
+    /// Returns the miniscript of the policy, choosing the combinators that minimize the expected witness size.
+    pub fn compile(&self) -> Result<Miniscript, MiniscriptError> {
+        self.compile_expression()?.checked()
+    }
+
+    /// Returns a miniscript of type B for the policy.
+    fn compile_expression(&self) -> Result<Miniscript, MiniscriptError> {
+        let compiled = match self {
+            Policy::Key(key) => Miniscript::Check(Box::new(Miniscript::PkK(key.clone()))),
+            Policy::After(lock_time) => Miniscript::After(*lock_time),
+            Policy::Older(lock_time) => Miniscript::Older(*lock_time),
+            Policy::Sha256(hash) => Miniscript::Sha256(*hash),
+            Policy::And(subs) => {
+                // Timelocks take no witness elements, so they are left last to end the script
+                let mut subs = subs.iter().map(Policy::compile_expression).collect::<Result<Vec<_>, _>>()?;
+                subs.sort_by_key(|sub| sub.type_check().map(|ty| ty.zero_arg).unwrap_or(false));
+                let last = subs.pop().ok_or(MiniscriptError::InvalidThreshold(0, 0))?;
+                subs.into_iter().rev().fold(last, |y, x| and_v(verify(x), y))
+            }
+            Policy::Or(subs) => {
+                let mut subs = subs
+                    .iter()
+                    .map(|(weight, sub)| Ok((*weight, sub.compile_expression()?)))
+                    .collect::<Result<Vec<_>, MiniscriptError>>()?;
+                subs.sort_by(|(a, _), (b, _)| b.cmp(a));
+                let (weight, last) = subs.pop().ok_or(MiniscriptError::InvalidThreshold(0, 0))?;
+                subs.into_iter()
+                    .rev()
+                    .fold((weight, last), |(weight_z, z), (weight_x, x)| {
+                        (weight_x + weight_z, or((weight_x, x), (weight_z, z)))
+                    })
+                    .1
+            }
+            Policy::Threshold(threshold, subs) => {
+                if *threshold == 0 || *threshold > subs.len() {
+                    return Err(MiniscriptError::InvalidThreshold(*threshold, subs.len()));
+                }
+                let keys = subs
+                    .iter()
+                    .map(|sub| match sub {
+                        Policy::Key(key) => Some(key.clone()),
+                        _ => None,
+                    })
+                    .collect::<Option<Vec<_>>>();
+                match keys {
+                    _ if subs.len() == 1 => subs[0].compile_expression()?,
+                    _ if *threshold == subs.len() => Policy::And(subs.clone()).compile_expression()?,
+                    Some(keys) if keys.len() <= MAX_MULTI_KEYS => Miniscript::Multi(*threshold, keys),
+                    _ if *threshold == 1 => Policy::Or(subs.iter().map(|sub| (1, sub.clone())).collect()).compile_expression()?,
+                    _ => {
+                        let subs = subs
+                            .iter()
+                            .enumerate()
+                            .map(|(i, sub)| {
+                                let x = make_du(sub.compile_expression()?);
+                                match (i, x.type_check()?.one_arg) {
+                                    (0, _) => Ok(x),
+                                    (_, true) => Ok(Miniscript::Swap(Box::new(x))),
+                                    (_, false) => Ok(Miniscript::Alt(Box::new(x))),
+                                }
+                            })
+                            .collect::<Result<Vec<_>, MiniscriptError>>()?;
+                        Miniscript::Thresh(*threshold, subs)
+                    }
+                }
+            }
+        };
+        Ok(compiled)
+    }
+}
+
+impl FromStr for Policy {
+    type Err = MiniscriptError;
+
+    fn from_str(policy: &str) -> Result<Self, Self::Err> {
+        let invalid = || MiniscriptError::InvalidExpression(policy.into());
+        let (name, arguments) = policy
+            .strip_suffix(')')
+            .and_then(|policy| policy.split_once('('))
+            .ok_or_else(invalid)?;
+        let arguments = split_arguments(arguments).map_err(|_| invalid())?;
+        let policy = match (name, &arguments[..]) {
+            ("pk", [key]) => Policy::Key(parse_key(key)?),
+            ("after", [value]) => Policy::After(parse_after(value)?),
+            ("older", [value]) => Policy::Older(parse_older(value)?),
+            ("sha256", [hash]) => Policy::Sha256(parse_hash(hash)?),
+            ("and", [_, _, ..]) => Policy::And(arguments.iter().map(|sub| sub.parse()).collect::<Result<_, _>>()?),
+            ("or", [_, _, ..]) => {
+                let subs = arguments
+                    .iter()
+                    .map(|sub| match sub.split_once('@') {
+                        Some((weight, sub)) if !weight.contains('(') => {
+                            let weight = weight.parse::<usize>().ok().filter(|weight| *weight > 0).ok_or_else(invalid)?;
+                            Ok((weight, sub.parse()?))
+                        }
+                        _ => Ok((1, sub.parse()?)),
+                    })
+                    .collect::<Result<_, MiniscriptError>>()?;
+                Policy::Or(subs)
+            }
+            ("thresh", [threshold, subs @ ..]) => {
+                let subs = subs.iter().map(|sub| sub.parse()).collect::<Result<Vec<Policy>, _>>()?;
+                let threshold = threshold.parse::<usize>().map_err(|_| invalid())?;
+                if threshold == 0 || threshold > subs.len() {
+                    return Err(MiniscriptError::InvalidThreshold(threshold, subs.len()));
+                }
+                Policy::Threshold(threshold, subs)
+            }
+            _ => return Err(invalid()),
+        };
+        Ok(policy)
+    }
+}
+
+impl fmt::Display for Policy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let join = |subs: Vec<String>| subs.join(",");
+        match self {
+            Policy::Key(key) => write!(f, "pk({})", hex::encode(key)),
+            Policy::After(lock_time) => write!(f, "after({})", lock_time.to_consensus_u32()),
+            Policy::Older(lock_time) => write!(f, "older({})", lock_time.to_sequence()),
+            Policy::Sha256(hash) => write!(f, "sha256({})", hex::encode(hash)),
+            Policy::And(subs) => write!(f, "and({})", join(subs.iter().map(Policy::to_string).collect())),
+            Policy::Or(subs) => {
+                let subs = subs
+                    .iter()
+                    .map(|(weight, sub)| match weight {
+                        1 => sub.to_string(),
+                        _ => format!("{}@{}", weight, sub),
+                    })
+                    .collect();
+                write!(f, "or({})", join(subs))
+            }
+            Policy::Threshold(threshold, subs) => {
+                write!(f, "thresh({},{})", threshold, join(subs.iter().map(Policy::to_string).collect()))
+            }
+        }
+    }
+}
+
+/// Decodes a script into a miniscript, reading its instructions backwards from the end.
+struct Decoder<'a> {
+    tokens: Vec<Instruction<'a>>,
+    /// The number of instructions left to read
+    end: usize,
+}
+
+impl<'a> Decoder<'a> {
+    /// Returns the next instruction to read without reading it.
+    fn peek(&self) -> Option<Instruction<'a>> {
+        self.end.checked_sub(1).map(|end| self.tokens[end])
+    }
+
+    /// Reads the next instruction.
+    fn next(&mut self) -> Option<Instruction<'a>> {
+        let token = self.peek()?;
+        self.end -= 1;
+        Some(token)
+    }
+
+    /// Reads the opcode if it is next.
+    fn accept(&mut self, opcode: Opcode) -> bool {
+        let is_next = self.peek() == Some(Instruction::Op(opcode));
+        if is_next {
+            self.end -= 1;
+        }
+        is_next
+    }
+
+    /// Reads the number pushed next.
+    fn number(&mut self) -> Option<i64> {
+        let number = match self.peek()? {
+            Instruction::PushBytes(data) => script_num(data, true, 5).ok(),
+            Instruction::Op(small) if (Opcode::OP_1 as u8..=Opcode::OP_16 as u8).contains(&(small as u8)) => {
+                Some((small as u8 - Opcode::OP_1 as u8 + 1) as i64)
+            }
+            _ => None,
+        }?;
+        self.end -= 1;
+        Some(number)
+    }
+
+    /// Reads a sequence of expressions up to the start of the script or of the enclosing fragment,
+    /// joined by and_v.
+    fn parse_expression(&mut self) -> Option<Miniscript> {
+        let mut y = self.parse_single()?;
+        loop {
+            match self.peek() {
+                None
+                | Some(Instruction::Op(Opcode::OP_IF))
+                | Some(Instruction::Op(Opcode::OP_NOTIF))
+                | Some(Instruction::Op(Opcode::OP_ELSE))
+                | Some(Instruction::Op(Opcode::OP_TOALTSTACK))
+                | Some(Instruction::Op(Opcode::OP_SWAP)) => return Some(y),
+                _ => y = Miniscript::AndV(Box::new(self.parse_single()?), Box::new(y)),
+            }
+        }
+    }
+
+    /// Reads a W expression, a: or s: of an expression.
+    fn parse_w(&mut self) -> Option<Miniscript> {
+        match self.accept(Opcode::OP_FROMALTSTACK) {
+            true => {
+                let x = self.parse_expression()?;
+                self.accept(Opcode::OP_TOALTSTACK).then(|| Miniscript::Alt(Box::new(x)))
+            }
+            false => {
+                let x = self.parse_expression()?;
+                self.accept(Opcode::OP_SWAP).then(|| Miniscript::Swap(Box::new(x)))
+            }
+        }
+    }
+
+    /// Reads a single fragment or wrapper, which and_v does not join.
+    fn parse_single(&mut self) -> Option<Miniscript> {
+        let single = match self.next()? {
+            Instruction::PushBytes([]) => Miniscript::False,
+            Instruction::PushBytes(key) if key.len() == 33 && is_public_key(key) => Miniscript::PkK(key.to_vec()),
+            Instruction::Op(Opcode::OP_1) => Miniscript::True,
+            Instruction::Op(Opcode::OP_CHECKSIG) => Miniscript::Check(Box::new(self.parse_single()?)),
+            Instruction::Op(Opcode::OP_CHECKSIGVERIFY) => {
+                Miniscript::Verify(Box::new(Miniscript::Check(Box::new(self.parse_single()?))))
+            }
+            Instruction::Op(opcode @ (Opcode::OP_CHECKMULTISIG | Opcode::OP_CHECKMULTISIGVERIFY)) => {
+                let count = usize::try_from(self.number()?).ok()?;
+                let mut keys = (0..count)
+                    .map(|_| match self.next()? {
+                        Instruction::PushBytes(key) if key.len() == 33 && is_public_key(key) => Some(key.to_vec()),
+                        _ => None,
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                keys.reverse();
+                let multi = Miniscript::Multi(usize::try_from(self.number()?).ok()?, keys);
+                match opcode {
+                    Opcode::OP_CHECKMULTISIG => multi,
+                    _ => Miniscript::Verify(Box::new(multi)),
+                }
+            }
+            Instruction::Op(Opcode::OP_CHECKSEQUENCEVERIFY) => {
+                let value = u32::try_from(self.number()?).ok().filter(|value| *value != 0)?;
+                Miniscript::Older(RelativeLockTime::from_sequence(value).filter(|lock_time| lock_time.to_sequence() == value)?)
+            }
+            Instruction::Op(Opcode::OP_CHECKLOCKTIMEVERIFY) => {
+                let value = u32::try_from(self.number()?).ok().filter(|value| *value != 0)?;
+                Miniscript::After(LockTime::from_consensus(value))
+            }
+            Instruction::Op(opcode @ (Opcode::OP_EQUAL | Opcode::OP_EQUALVERIFY)) => {
+                let equal = match self.peek()? {
+                    Instruction::PushBytes(hash) if hash.len() == 32 => {
+                        self.end -= 1;
+                        let hash = <[u8; 32]>::try_from(hash).ok()?;
+                        let size = [Opcode::OP_SHA256, Opcode::OP_EQUALVERIFY].into_iter().all(|opcode| self.accept(opcode))
+                            && self.number()? == 32
+                            && self.accept(Opcode::OP_SIZE);
+                        size.then_some(Miniscript::Sha256(hash))?
+                    }
+                    _ => {
+                        let threshold = usize::try_from(self.number()?).ok()?;
+                        let mut subs = vec![];
+                        while self.accept(Opcode::OP_ADD) {
+                            subs.push(self.parse_w()?);
+                        }
+                        subs.push(self.parse_single()?);
+                        subs.reverse();
+                        Miniscript::Thresh(threshold, subs)
+                    }
+                };
+                match opcode {
+                    Opcode::OP_EQUAL => equal,
+                    _ => Miniscript::Verify(Box::new(equal)),
+                }
+            }
+            Instruction::Op(Opcode::OP_VERIFY) => Miniscript::Verify(Box::new(self.parse_single()?)),
+            Instruction::Op(Opcode::OP_0NOTEQUAL) => Miniscript::ZeroNotEqual(Box::new(self.parse_single()?)),
+            Instruction::Op(Opcode::OP_BOOLAND) => {
+                let y = self.parse_w()?;
+                Miniscript::AndB(Box::new(self.parse_single()?), Box::new(y))
+            }
+            Instruction::Op(Opcode::OP_BOOLOR) => {
+                let z = self.parse_w()?;
+                Miniscript::OrB(Box::new(self.parse_single()?), Box::new(z))
+            }
+            Instruction::Op(Opcode::OP_ENDIF) => {
+                let last = self.parse_expression()?;
+                if self.accept(Opcode::OP_ELSE) {
+                    let first = self.parse_expression()?;
+                    return match self.next()? {
+                        Instruction::Op(Opcode::OP_IF) => Some(Miniscript::OrI(Box::new(first), Box::new(last))),
+                        Instruction::Op(Opcode::OP_NOTIF) => {
+                            Some(Miniscript::AndOr(Box::new(self.parse_single()?), Box::new(last), Box::new(first)))
+                        }
+                        _ => None,
+                    };
+                }
+                match self.next()? {
+                    Instruction::Op(Opcode::OP_IF) if self.accept(Opcode::OP_DUP) => Miniscript::DupIf(Box::new(last)),
+                    Instruction::Op(Opcode::OP_IF) if self.accept(Opcode::OP_0NOTEQUAL) && self.accept(Opcode::OP_SIZE) => {
+                        Miniscript::NonZero(Box::new(last))
+                    }
+                    Instruction::Op(Opcode::OP_NOTIF) if self.accept(Opcode::OP_IFDUP) => {
+                        Miniscript::OrD(Box::new(self.parse_single()?), Box::new(last))
+                    }
+                    Instruction::Op(Opcode::OP_NOTIF) => Miniscript::OrC(Box::new(self.parse_single()?), Box::new(last)),
+                    _ => return None,
+                }
+            }
+            _ => return None,
+        };
+        Some(single)
+    }
+}
+
+/// Returns v:X, with v: applied to the last expression of an and_v.
+fn verify(x: Miniscript) -> Miniscript {
+    match x {
+        Miniscript::AndV(x, y) => Miniscript::AndV(x, Box::new(verify(*y))),
+        x => Miniscript::Verify(Box::new(x)),
+    }
+}
+
+/// Returns and_v(X,Y), nested to the right as scripts are decoded.
+fn and_v(x: Miniscript, y: Miniscript) -> Miniscript {
+    match x {
+        Miniscript::AndV(a, b) => and_v(*a, and_v(*b, y)),
+        x => Miniscript::AndV(Box::new(x), Box::new(y)),
+    }
+}
+
+/// Returns the alternative of the weighted expressions of type B with the smallest expected satisfaction.
+fn or((weight_x, x): (usize, Miniscript), (weight_z, z): (usize, Miniscript)) -> Miniscript {
+    let bdu = |m: &Miniscript| m.type_check().map(|ty| ty.is_bdu()).unwrap_or(false);
+    let size = |size: Size| size.map(|(size, _)| size).unwrap_or(0);
+    let ((sat_x, dsat_x), (sat_z, dsat_z)) = (x.max_sizes(), z.max_sizes());
+
+    // Expected satisfaction sizes of or_d(X,Z), or_d(Z,X), or_i(X,Z) and or_i(Z,X), whose scripts are as large
+    let mut candidates = vec![];
+    if bdu(&x) {
+        candidates.push((weight_x * size(sat_x) + weight_z * (size(dsat_x) + size(sat_z)), 0));
+    }
+    if bdu(&z) {
+        candidates.push((weight_z * size(sat_z) + weight_x * (size(dsat_z) + size(sat_x)), 1));
+    }
+    candidates.push((weight_x * (size(sat_x) + 2) + weight_z * (size(sat_z) + 1), 2));
+    candidates.push((weight_z * (size(sat_z) + 2) + weight_x * (size(sat_x) + 1), 3));
+
+    let (x, z) = (Box::new(x), Box::new(z));
+    match candidates.into_iter().min().map(|(_, candidate)| candidate) {
+        Some(0) => Miniscript::OrD(x, z),
+        Some(1) => Miniscript::OrD(z, x),
+        Some(2) => Miniscript::OrI(x, z),
+        _ => Miniscript::OrI(z, x),
+    }
+}
+
+/// Returns an expression of type Bdu equivalent to the expression of type B, for thresh.
+fn make_du(x: Miniscript) -> Miniscript {
+    let ty = match x.type_check() {
+        Ok(ty) => ty,
+        Err(_) => return x,
+    };
+    let x = match (ty.dissatisfiable, ty.zero_arg) {
+        (true, _) => x,
+        (false, true) => Miniscript::DupIf(Box::new(verify(x))),
+        (false, false) => Miniscript::OrI(Box::new(Miniscript::False), Box::new(x)),
+    };
+    match x.type_check().map(|ty| ty.unit) {
+        Ok(true) => x,
+        _ => Miniscript::ZeroNotEqual(Box::new(x)),
+    }
+}
+
+/// Returns the size of the witness stack with the length prefixes of its elements.
+fn witness_size(witness: &[Vec<u8>]) -> usize {
+    witness.iter().map(|element| 1 + element.len()).sum()
+}
+
+/// Returns true if the bytes are a valid compressed public key.
+fn is_public_key(key: &[u8]) -> bool {
+    libsecp256k1::PublicKey::parse_slice(key, Some(libsecp256k1::PublicKeyFormat::Compressed)).is_ok()
+}
+
+/// Returns the compressed public key of the hex string.
+fn parse_key(key: &str) -> Result<Vec<u8>, MiniscriptError> {
+    match hex::decode(key) {
+        Ok(bytes) if bytes.len() == 33 && is_public_key(&bytes) => Ok(bytes),
+        _ => Err(MiniscriptError::InvalidKey(key.into())),
+    }
+}
+
+/// Returns the SHA256 hash of the hex string.
+fn parse_hash(hash: &str) -> Result<[u8; 32], MiniscriptError> {
+    hex::decode(hash)
+        .ok()
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .ok_or_else(|| MiniscriptError::InvalidExpression(hash.into()))
+}
+
+/// Returns the absolute lock time of after(n), with 1 <= n < 2^31.
+fn parse_after(value: &str) -> Result<LockTime, MiniscriptError> {
+    match value.parse::<u32>() {
+        Ok(lock_time) if lock_time != 0 && lock_time < 1 << 31 => Ok(LockTime::from_consensus(lock_time)),
+        _ => Err(MiniscriptError::InvalidTimelock(value.into())),
+    }
+}
+
+/// Returns the relative lock time of older(n), a nonzero sequence number of a relative lock time.
+fn parse_older(value: &str) -> Result<RelativeLockTime, MiniscriptError> {
+    value
+        .parse::<u32>()
+        .ok()
+        .filter(|sequence| *sequence != 0)
+        .and_then(|sequence| RelativeLockTime::from_sequence(sequence).filter(|lock_time| lock_time.to_sequence() == sequence))
+        .ok_or_else(|| MiniscriptError::InvalidTimelock(value.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amount::BitcoinAmount;
+    use crate::interpreter::VerificationFlags;
+    use crate::network::*;
+    use crate::private_key::BitcoinPrivateKey;
+    use crate::transaction::{BitcoinTransaction, BitcoinTransactionBuilder, BitcoinTransactionInputBuilder};
+    use chainlib_core::{PrivateKey, Transaction};
+    use sha2::{Digest, Sha256};
+
+    type N = Mainnet;
+
+    const KEYS: [&str; 4] = [
+        "KwN7qiBnU4GNhboBhuPaPaFingTDKU4r27pGggwQYz865TvBT74V",
+        "KwXH1Mu4FBtGN9nRn2VkBpienaVGZKvCAkZAdE96kK71dHR1oDRs",
+        "KwcN2pT3wnRAurhy7qMczzbkpY5nXMW2ubh696UBc1bcwctTx26z",
+        "KwtetKxofS1Lhp7idNJzb5B5WninBRfELdwkjvTMZZGME4G72kMz",
+    ];
+    const TRANSACTION_ID: &str = "9cb01977970f1322931532c9467e85eee9daf620b1886eb70d7c9b0e6c993bc4";
+
+    fn private_key(index: usize) -> BitcoinPrivateKey<N> {
+        BitcoinPrivateKey::<N>::from_str(KEYS[index]).unwrap()
+    }
+
+    fn public_key(index: usize) -> String {
+        hex::encode(private_key(index).to_public_key().to_secp256k1_public_key().serialize_compressed())
+    }
+
+    /// Returns the policy "2 of keys 0, 1 and 2, or key 3 after a year".
+    fn inheritance() -> Policy {
+        let (a, b, c, d) = (public_key(0), public_key(1), public_key(2), public_key(3));
+        Policy::from_str(&format!("or(thresh(2,pk({}),pk({}),pk({})),and(pk({}),older(52560)))", a, b, c, d)).unwrap()
+    }
+
+    /// Returns a transaction spending the P2WSH output of the miniscript with the given sequence number.
+    fn spend(miniscript: &Miniscript, sequence: u32) -> BitcoinTransactionBuilder<N> {
+        let input = BitcoinTransactionInputBuilder::new(TRANSACTION_ID, 0)
+            .address(&miniscript.p2wsh_address::<N>().unwrap().to_string())
+            .amount(BitcoinAmount(100_000))
+            .redeem_script(miniscript.to_script())
+            .sequence(sequence);
+        BitcoinTransactionBuilder::<N>::new()
+            .input(input)
+            .output("1Fyxts6r24DpEieygQiNnWxUdb18ANa5p7", BitcoinAmount(99_000))
+    }
+
+    fn sign(transaction: &mut BitcoinTransaction<N>, index: usize) {
+        transaction.sign_with_private_key(&private_key(index)).unwrap();
+    }
+
+    #[test]
+    fn compile() {
+        let policy = inheritance();
+        assert_eq!(policy, Policy::from_str(&policy.to_string()).unwrap());
+
+        let (a, b, c, d) = (public_key(0), public_key(1), public_key(2), public_key(3));
+        let miniscript = policy.compile().unwrap();
+        assert_eq!(format!("or_d(multi(2,{},{},{}),and_v(v:pk({}),older(52560)))", a, b, c, d), miniscript.to_string());
+        assert_eq!(
+            format!(
+                "OP_2 {} {} {} OP_3 OP_CHECKMULTISIG OP_IFDUP OP_NOTIF {} OP_CHECKSIGVERIFY 50cd00 OP_CHECKSEQUENCEVERIFY OP_ENDIF",
+                a, b, c, d
+            ),
+            Script::from(&miniscript.to_script()[..]).to_asm()
+        );
+
+        // A likely branch is not preceded by an expensive dissatisfaction, and is selected by an empty element
+        let policy = Policy::from_str(&format!("or(thresh(2,pk({}),pk({}),pk({})),9@and(pk({}),older(52560)))", a, b, c, d));
+        assert_eq!(
+            format!("or_i(multi(2,{},{},{}),and_v(v:pk({}),older(52560)))", a, b, c, d),
+            policy.unwrap().compile().unwrap().to_string()
+        );
+        let policy = Policy::from_str(&format!("or(pk({}),9@and(pk({}),after(800000)))", a, b)).unwrap();
+        assert_eq!(format!("or_d(pk({}),and_v(v:pk({}),after(800000)))", a, b), policy.compile().unwrap().to_string());
+
+        // Other thresholds are made of dissatisfiable units
+        let hash = hex::encode(Sha256::digest(b"preimage"));
+        let policy = Policy::from_str(&format!("thresh(2,pk({}),sha256({}),after(800000))", a, hash)).unwrap();
+        assert_eq!(format!("thresh(2,pk({}),s:sha256({}),sndv:after(800000))", a, hash), policy.compile().unwrap().to_string());
+
+        assert_eq!(Err(MiniscriptError::InvalidThreshold(3, 2)), Policy::from_str(&format!("thresh(3,pk({}),pk({}))", a, b)));
+        assert_eq!(Err(MiniscriptError::InvalidTimelock("0".into())), Policy::from_str("after(0)"));
+        assert_eq!(Err(MiniscriptError::InvalidKey("02".into())), Policy::from_str("pk(02)"));
+        assert!(Policy::from_str(&format!("or(0@pk({}),pk({}))", a, b)).is_err());
+        assert!(Policy::from_str(&format!("and(pk({}))", a)).is_err());
+    }
+
+    #[test]
+    fn type_check() {
+        let (a, b) = (public_key(0), public_key(1));
+        let miniscript = Miniscript::from_str(&format!("and_v(v:pk({}),older(144))", a)).unwrap();
+        let ty = miniscript.type_check().unwrap();
+        assert_eq!(BaseType::B, ty.base);
+        assert!(ty.one_arg && ty.non_zero && !ty.dissatisfiable);
+        assert_eq!(format!("{} OP_CHECKSIGVERIFY 9000 OP_CHECKSEQUENCEVERIFY", a), Script::from(&miniscript.to_script()[..]).to_asm());
+
+        let miniscript = Miniscript::from_str(&format!("or_b(pk({}),s:pk({}))", a, b)).unwrap();
+        assert!(miniscript.type_check().unwrap().is_bdu());
+        let miniscript = Miniscript::from_str(&format!("andor(pk({}),after(800000),l:pk({}))", a, b)).unwrap();
+        assert!(miniscript.type_check().unwrap().dissatisfiable);
+        assert_eq!(format!("andor(pk({}),after(800000),l:pk({}))", a, b), miniscript.to_string());
+
+        // The top level must be B, and each combinator requires the types of its subexpressions
+        for invalid in [
+            format!("v:pk({})", a),
+            format!("pk_k({})", a),
+            format!("and_v(pk({}),pk({}))", a, b),
+            format!("or_d(older(144),pk({}))", a),
+            format!("thresh(2,pk({}),pk({}))", a, b),
+            format!("d:pk({})", a),
+        ] {
+            assert_eq!(Err(MiniscriptError::InvalidType(invalid.clone())), Miniscript::from_str(&invalid).map(|_| ()));
+        }
+        assert_eq!(
+            Err(MiniscriptError::InvalidThreshold(0, 1)),
+            Miniscript::from_str(&format!("multi(0,{})", a)).map(|_| ())
+        );
+        assert!(Miniscript::from_str(&format!("x:pk({})", a)).is_err());
+        assert!(Miniscript::from_str(&format!("pk({},{})", a, b)).is_err());
+    }
+
+    #[test]
+    fn scripts() {
+        let (a, b, c) = (public_key(0), public_key(1), public_key(2));
+        let hash = hex::encode(Sha256::digest(b"preimage"));
+        for miniscript in [
+            format!("pk({})", a),
+            format!("and_v(v:pk({}),older(144))", a),
+            format!("or_d(multi(2,{},{},{}),and_v(v:pk({}),older(52560)))", a, b, c, a),
+            format!("or_i(and_v(v:pk({}),after(1700000000)),pk({}))", a, b),
+            format!("andor(pk({}),after(800000),l:pk({}))", a, b),
+            format!("and_b(pk({}),a:pk({}))", a, b),
+            format!("or_b(pk({}),s:pk({}))", a, b),
+            format!("t:or_c(pk({}),v:sha256({}))", a, hash),
+            format!("and_v(v:multi(1,{},{}),j:pk({}))", a, b, c),
+            format!("thresh(2,pk({}),s:sha256({}),sndv:after(800000))", a, hash),
+            format!("and_v(v:thresh(1,pk({}),a:pk({})),n:pk({}))", a, b, c),
+            format!("and_n(pk({}),older(1))", a),
+        ] {
+            let parsed = Miniscript::from_str(&miniscript).unwrap();
+            assert_eq!(miniscript, parsed.to_string());
+            assert_eq!(parsed, Miniscript::from_script(&parsed.to_script()).unwrap());
+        }
+
+        // Scripts outside of miniscript are rejected
+        let timelock = Script::new()
+            .push_int(144)
+            .push_opcode(Opcode::OP_CHECKSEQUENCEVERIFY)
+            .push_opcode(Opcode::OP_DROP)
+            .push_slice(&hex::decode(&a).unwrap())
+            .push_opcode(Opcode::OP_CHECKSIG)
+            .into_bytes();
+        assert!(Miniscript::from_script(&timelock).is_err());
+        let non_minimal = [vec![0x02, 0x06, 0x00, 0xb2]].concat();
+        assert!(Miniscript::from_script(&non_minimal).is_err());
+        let verify = Miniscript::from_str(&format!("pk({})", a)).unwrap().to_script();
+        assert!(Miniscript::from_script(&[verify, vec![0x69]].concat()).is_err());
+    }
+
+    #[test]
+    fn witness_size() {
+        let miniscript = inheritance().compile().unwrap();
+        assert_eq!(148, miniscript.to_script().len());
+        assert_eq!(Some((3, 1 + 2 * SIGNATURE_SIZE)), miniscript.max_satisfaction_size());
+        assert_eq!(Some(1 + 147 + 1 + 148), miniscript.max_witness_size());
+
+        let a = public_key(0);
+        let miniscript = Miniscript::from_str(&format!("or_i(and_v(v:pk({}),after(1700000000)),pk({}))", a, a)).unwrap();
+        assert_eq!(Some((2, SIGNATURE_SIZE + 2)), miniscript.max_satisfaction_size());
+        let hash = hex::encode(Sha256::digest(b"preimage"));
+        let miniscript = Miniscript::from_str(&format!("thresh(2,pk({}),s:sha256({}),sndv:after(800000))", a, hash)).unwrap();
+        assert_eq!(Some((3, SIGNATURE_SIZE + 33 + 2)), miniscript.max_satisfaction_size());
+        assert_eq!(None, Miniscript::from_str("and_v(v:0,1)").unwrap().max_satisfaction_size());
+    }
+
+    #[test]
+    fn satisfy() {
+        let (a, b) = (public_key(0), public_key(1));
+        let preimage = b"an example of a 32-byte preimage".to_vec();
+        let hash: [u8; 32] = Sha256::digest(&preimage).into();
+        let miniscript =
+            Miniscript::from_str(&format!("thresh(2,pk({}),s:sha256({}),sndv:after(800000))", a, hex::encode(hash))).unwrap();
+
+        // Below the lock time and without signature, only the preimage is available
+        let mut satisfier = Satisfier::new(2, LockTime::Blocks(799_999), 0xffff_fffe);
+        satisfier.preimages.insert(hash, preimage.clone());
+        assert_eq!(None, miniscript.satisfy(&satisfier));
+
+        satisfier.signatures.insert(hex::decode(&a).unwrap(), vec![0x30; 72]);
+        assert_eq!(Some(vec![vec![], preimage.clone(), vec![0x30; 72]]), miniscript.satisfy(&satisfier));
+
+        // The smallest satisfaction, without the signature, is chosen once the lock time has passed
+        satisfier.lock_time = LockTime::Blocks(800_000);
+        assert_eq!(Some(vec![vec![1], preimage.clone(), vec![]]), miniscript.satisfy(&satisfier));
+
+        // The lock time of a final input is not enforced
+        satisfier.sequence = u32::MAX;
+        assert_eq!(Some(vec![vec![], preimage, vec![0x30; 72]]), miniscript.satisfy(&satisfier));
+
+        let miniscript = Miniscript::from_str(&format!("or_i(pk({}),and_v(v:pk({}),older(144)))", a, b)).unwrap();
+        let mut satisfier = Satisfier::new(1, LockTime::ZERO, 144);
+        satisfier.signatures.insert(hex::decode(&b).unwrap(), vec![0x30; 72]);
+        assert_eq!(None, miniscript.satisfy(&satisfier));
+        satisfier.version = 2;
+        assert_eq!(Some(vec![vec![0x30; 72], vec![]]), miniscript.satisfy(&satisfier));
+    }
+
+    #[test]
+    fn spend_threshold() {
+        let miniscript = inheritance().compile().unwrap();
+        let mut transaction = spend(&miniscript, 0xffff_fffd).build().unwrap();
+        let (_, witness) = transaction.parameters.inputs[0].estimate_size().unwrap();
+        assert_eq!(miniscript.max_witness_size(), Some(witness));
+
+        // The threshold is complete once two of its cosigners have signed
+        sign(&mut transaction, 0);
+        assert!(!transaction.parameters.inputs[0].is_signed);
+        sign(&mut transaction, 2);
+        assert!(transaction.parameters.inputs[0].is_signed);
+        assert_eq!(Ok(()), transaction.verify_input(0, VerificationFlags::STANDARD));
+        assert!(transaction.parameters.inputs[0].estimate_size().unwrap().1 <= witness);
+    }
+
+    #[test]
+    fn spend_timelock() {
+        let miniscript = inheritance().compile().unwrap();
+
+        // The heir key alone spends once the relative lock time has passed
+        let mut transaction = spend(&miniscript, 52560).build().unwrap();
+        sign(&mut transaction, 3);
+        assert!(transaction.parameters.inputs[0].is_signed);
+        assert_eq!(Ok(()), transaction.verify_input(0, VerificationFlags::STANDARD));
+
+        let mut transaction = spend(&miniscript, 52559).build().unwrap();
+        sign(&mut transaction, 3);
+        assert!(!transaction.parameters.inputs[0].is_signed);
+        let mut transaction = spend(&miniscript, 52560).version(1).build().unwrap();
+        sign(&mut transaction, 3);
+        assert!(!transaction.parameters.inputs[0].is_signed);
+
+        // Keys outside of the miniscript do not sign it
+        let a = public_key(0);
+        let miniscript = Miniscript::from_str(&format!("thresh(2,pk({}),s:sha256({}),sndv:after(800000))", a, "00".repeat(32))).unwrap();
+        let mut transaction = spend(&miniscript, 0xffff_fffe).lock_time(800_000).build().unwrap();
+        sign(&mut transaction, 1);
+        assert!(!transaction.parameters.inputs[0].is_signed);
+        sign(&mut transaction, 0);
+        assert!(transaction.parameters.inputs[0].is_signed);
+        assert_eq!(Ok(()), transaction.verify_input(0, VerificationFlags::STANDARD));
+    }
+}
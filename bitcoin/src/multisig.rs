@@ -0,0 +1,336 @@
+//! # Multisig
+//!
+//! m-of-n `OP_CHECKMULTISIG` scripts and their P2SH, P2SH-P2WSH and P2WSH addresses.
+//! The public keys are kept in the order of the script, either as given or sorted as specified
+//! in BIP-67 so that every cosigner derives the same address from the same set of keys.
+//! A spend collects the signatures of the cosigners by public key and is final once the threshold
+//! of them is reached, ordered as the public keys of the script.
+
+use crate::address::BitcoinAddress;
+use crate::format::BitcoinFormat;
+use crate::network::BitcoinNetwork;
+use crate::public_key::BitcoinPublicKey;
+use crate::script::Opcode;
+use chainlib_core::no_std::*;
+use chainlib_core::{libsecp256k1, AddressError, TransactionError};
+
+/// The maximum number of public keys pushed with the small integer opcodes
+const MAX_PUBLIC_KEY_COUNT: usize = 16;
+/// The maximum size of a P2SH redeem script (BIP-16)
+const MAX_REDEEM_SCRIPT_SIZE: usize = 520;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum MultisigError {
+    #[error("invalid multisig script {0}")]
+    InvalidScript(String),
+
+    #[error("invalid threshold of {0} for {1} public keys")]
+    InvalidThreshold(usize, usize),
+
+    #[error("redeem script of {0} bytes exceeds 520 bytes")]
+    ScriptTooLarge(usize),
+
+    #[error("uncompressed public key {0}")]
+    UncompressedPublicKey(String),
+}
+
+impl From<MultisigError> for AddressError {
+    fn from(error: MultisigError) -> Self {
+        AddressError::Crate("Multisig", format!("{:?}", error))
+    }
+}
+
+impl From<MultisigError> for TransactionError {
+    fn from(error: MultisigError) -> Self {
+        TransactionError::Crate("Multisig", format!("{:?}", error))
+    }
+}
+
+/// Represents an `OP_m <public keys> OP_n OP_CHECKMULTISIG` script
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MultisigScript {
+    /// The number of signatures required to spend
+    threshold: usize,
+    /// The serialized public keys in the order of the script
+    public_keys: Vec<Vec<u8>>,
+}
+
+impl MultisigScript {
+    /// Returns the m-of-n script of the public keys in the given order.
+    pub fn new<N: BitcoinNetwork>(threshold: usize, public_keys: &[BitcoinPublicKey<N>]) -> Result<Self, MultisigError> {
+        let public_keys = public_keys
+            .iter()
+            .map(|public_key| match public_key.is_compressed() {
+                true => public_key.to_secp256k1_public_key().serialize_compressed().to_vec(),
+                false => public_key.to_secp256k1_public_key().serialize().to_vec(),
+            })
+            .collect();
+        Self::from_public_keys(threshold, public_keys)
+    }
+
+    /// Returns the m-of-n script of the compressed public keys sorted lexicographically (BIP-67).
+    /// https://github.com/bitcoin/bips/blob/master/bip-0067.mediawiki
+    pub fn sorted<N: BitcoinNetwork>(threshold: usize, public_keys: &[BitcoinPublicKey<N>]) -> Result<Self, MultisigError> {
+        let mut public_keys = public_keys
+            .iter()
+            .map(|public_key| {
+                let serialized = public_key.to_secp256k1_public_key().serialize_compressed().to_vec();
+                match public_key.is_compressed() {
+                    true => Ok(serialized),
+                    false => Err(MultisigError::UncompressedPublicKey(hex::encode(serialized))),
+                }
+            })
+            .collect::<Result<Vec<Vec<u8>>, MultisigError>>()?;
+        public_keys.sort();
+        Self::from_public_keys(threshold, public_keys)
+    }
+
+    /// Returns the multisig script of the given bytes.
+    pub fn from_script(script: &[u8]) -> Result<Self, MultisigError> {
+        let invalid = || MultisigError::InvalidScript(hex::encode(script));
+        let threshold = match script.first() {
+            Some(&opcode @ 0x51..=0x60) => (opcode - 0x50) as usize,
+            _ => return Err(invalid()),
+        };
+
+        let mut public_keys = vec![];
+        let mut position = 1;
+        while let Some(&len @ (33 | 65)) = script.get(position) {
+            public_keys.push(script.get(position + 1..position + 1 + len as usize).ok_or_else(invalid)?.to_vec());
+            position += 1 + len as usize;
+        }
+
+        match script.len() == position + 2
+            && script[position] == 0x50 + public_keys.len() as u8
+            && script[position + 1] == Opcode::OP_CHECKMULTISIG as u8
+        {
+            true => Self::from_public_keys(threshold, public_keys),
+            false => Err(invalid()),
+        }
+    }
+
+    /// Returns the number of signatures required to spend.
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    /// Returns the serialized public keys in the order of the script.
+    pub fn public_keys(&self) -> &[Vec<u8>] {
+        &self.public_keys
+    }
+
+    /// Returns the serialization of the public key used by the script, if any.
+    pub fn find_public_key(&self, public_key: &libsecp256k1::PublicKey) -> Option<&Vec<u8>> {
+        let compressed = public_key.serialize_compressed();
+        let uncompressed = public_key.serialize();
+        self.public_keys
+            .iter()
+            .find(|key| key[..] == compressed[..] || key[..] == uncompressed[..])
+    }
+
+    /// Returns the script in bytes.
+    pub fn to_script(&self) -> Vec<u8> {
+        let mut script = vec![0x50 + self.threshold as u8];
+        for public_key in &self.public_keys {
+            script.push(public_key.len() as u8);
+            script.extend(public_key);
+        }
+        script.push(0x50 + self.public_keys.len() as u8);
+        script.push(Opcode::OP_CHECKMULTISIG as u8);
+        script
+    }
+
+    /// Returns the P2SH, P2SH_P2WSH or P2WSH address of the script.
+    /// SegWit scripts only relay with compressed public keys (BIP-143).
+    pub fn to_address<N: BitcoinNetwork>(&self, format: &BitcoinFormat) -> Result<BitcoinAddress<N>, AddressError> {
+        let script = self.to_script();
+        match format {
+            BitcoinFormat::P2SH if script.len() > MAX_REDEEM_SCRIPT_SIZE => {
+                Err(MultisigError::ScriptTooLarge(script.len()).into())
+            }
+            BitcoinFormat::P2SH_P2WSH | BitcoinFormat::P2WSH => match self.public_keys.iter().find(|key| key.len() != 33) {
+                Some(public_key) => Err(MultisigError::UncompressedPublicKey(hex::encode(public_key)).into()),
+                None => BitcoinAddress::from_script(&script, format),
+            },
+            _ => BitcoinAddress::from_script(&script, format),
+        }
+    }
+
+    /// Returns the stack satisfying the script with the signatures of the given public keys,
+    /// or `None` until the threshold of signatures is reached.
+    /// The stack starts with the empty element popped by OP_CHECKMULTISIG, followed by
+    /// the signatures in the order of the public keys of the script.
+    pub fn satisfy(&self, signatures: &BTreeMap<Vec<u8>, Vec<u8>>) -> Option<Vec<Vec<u8>>> {
+        let signatures: Vec<Vec<u8>> = self
+            .public_keys
+            .iter()
+            .filter_map(|public_key| signatures.get(public_key).cloned())
+            .take(self.threshold)
+            .collect();
+        match signatures.len() == self.threshold {
+            true => Some([vec![vec![]], signatures].concat()),
+            false => None,
+        }
+    }
+
+    /// Returns the script of the given threshold and serialized public keys, checking their count.
+    fn from_public_keys(threshold: usize, public_keys: Vec<Vec<u8>>) -> Result<Self, MultisigError> {
+        if threshold == 0 || threshold > public_keys.len() || public_keys.len() > MAX_PUBLIC_KEY_COUNT {
+            return Err(MultisigError::InvalidThreshold(threshold, public_keys.len()));
+        }
+        Ok(Self { threshold, public_keys })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::*;
+    use core::str::FromStr;
+
+    type N = Mainnet;
+
+    fn public_keys(public_keys: &[&str]) -> Vec<BitcoinPublicKey<N>> {
+        public_keys
+            .iter()
+            .map(|public_key| BitcoinPublicKey::<N>::from_str(public_key).unwrap())
+            .collect()
+    }
+
+    // https://github.com/bitcoin/bips/blob/master/bip-0067.mediawiki#test-vectors
+    const VECTORS: [(&[&str], &str, &str); 3] = [
+        (
+            &[
+                "02ff12471208c14bd580709cb2358d98975247d8765f92bc25eab3b2763ed605f8",
+                "02fe6f0a5a297eb38c391581c4413e084773ea23954d93f7753db7dc0adc188b2f",
+            ],
+            "522102fe6f0a5a297eb38c391581c4413e084773ea23954d93f7753db7dc0adc188b2f2102ff12471208c14bd580709cb2358d98975247d8765f92bc25eab3b2763ed605f852ae",
+            "39bgKC7RFbpoCRbtD5KEdkYKtNyhpsNa3Z",
+        ),
+        (
+            &[
+                "02632b12f4ac5b1d1b72b2a3b508c19172de44f6f46bcee50ba33f3f9291e47ed0",
+                "027735a29bae7780a9755fae7a1c4374c656ac6a69ea9f3697fda61bb99a4f3e77",
+                "02e2cc6bd5f45edd43bebe7cb9b675f0ce9ed3efe613b177588290ad188d11b404",
+            ],
+            "522102632b12f4ac5b1d1b72b2a3b508c19172de44f6f46bcee50ba33f3f9291e47ed021027735a29bae7780a9755fae7a1c4374c656ac6a69ea9f3697fda61bb99a4f3e772102e2cc6bd5f45edd43bebe7cb9b675f0ce9ed3efe613b177588290ad188d11b40453ae",
+            "3CKHTjBKxCARLzwABMu9yD85kvtm7WnMfH",
+        ),
+        (
+            &[
+                "022df8750480ad5b26950b25c7ba79d3e37d75f640f8e5d9bcd5b150a0f85014da",
+                "03e3818b65bcc73a7d64064106a859cc1a5a728c4345ff0b641209fba0d90de6e9",
+                "021f2f6e1e50cb6a953935c3601284925decd3fd21bc445712576873fb8c6ebc18",
+            ],
+            "5221021f2f6e1e50cb6a953935c3601284925decd3fd21bc445712576873fb8c6ebc1821022df8750480ad5b26950b25c7ba79d3e37d75f640f8e5d9bcd5b150a0f85014da2103e3818b65bcc73a7d64064106a859cc1a5a728c4345ff0b641209fba0d90de6e953ae",
+            "3Q4sF6tv9wsdqu2NtARzNCpQgwifm2rAba",
+        ),
+    ];
+
+    mod bip67 {
+        use super::*;
+
+        #[test]
+        fn sorted() {
+            VECTORS.iter().for_each(|(keys, script, address)| {
+                let multisig = MultisigScript::sorted(2, &public_keys(keys)).unwrap();
+                assert_eq!(*script, hex::encode(multisig.to_script()));
+                assert_eq!(*address, multisig.to_address::<N>(&BitcoinFormat::P2SH).unwrap().to_string());
+            });
+        }
+
+        #[test]
+        fn from_script() {
+            VECTORS.iter().for_each(|(keys, script, _)| {
+                let multisig = MultisigScript::from_script(&hex::decode(script).unwrap()).unwrap();
+                assert_eq!(2, multisig.threshold());
+                assert_eq!(keys.len(), multisig.public_keys().len());
+                assert_eq!(*script, hex::encode(multisig.to_script()));
+            });
+
+            // The keys of the script are not required to be valid points
+            let script = "522102000000000000000000000000000000000000414141414141414141414141414021020000000000000000000000000000000000004141414141414141414141414141210300000000000000000000000000000000000041414141414141414141414141402103000000000000000000000000000000000000414141414141414141414141414154ae";
+            let multisig = MultisigScript::from_script(&hex::decode(script).unwrap()).unwrap();
+            let address = multisig.to_address::<N>(&BitcoinFormat::P2SH).unwrap();
+            assert_eq!("32V85igBri9zcfBRVupVvwK18NFtS37FuD", address.to_string());
+        }
+    }
+
+    #[test]
+    fn new() {
+        let (keys, _, _) = VECTORS[1];
+        let mut keys = keys.to_vec();
+        keys.reverse();
+        let multisig = MultisigScript::new(1, &public_keys(&keys)).unwrap();
+        let expected: Vec<Vec<u8>> = keys.iter().map(|key| hex::decode(key).unwrap()).collect();
+        assert_eq!(expected, multisig.public_keys());
+        assert_eq!(vec![0x51, 0x21], multisig.to_script()[..2].to_vec());
+    }
+
+    #[test]
+    fn to_address() {
+        let (keys, _, _) = VECTORS[1];
+        let multisig = MultisigScript::sorted(2, &public_keys(keys)).unwrap();
+        assert_eq!(
+            "bc1qud6dmdcc27eg8s5hsy6a075gs49w65l6xtc4cplp6m2d4ggh43wqew2vqs",
+            multisig.to_address::<N>(&BitcoinFormat::P2WSH).unwrap().to_string()
+        );
+        assert_eq!(
+            "31iXMTVFX7qKnPnGVx2ZmJYWuNy3BiCNHS",
+            multisig.to_address::<N>(&BitcoinFormat::P2SH_P2WSH).unwrap().to_string()
+        );
+        assert_eq!(
+            "tb1qud6dmdcc27eg8s5hsy6a075gs49w65l6xtc4cplp6m2d4ggh43wqwxur6l",
+            multisig.to_address::<Testnet>(&BitcoinFormat::P2WSH).unwrap().to_string()
+        );
+        assert_eq!(
+            "2MsGjRCRH8aLfzBQpB5eSPFXn7jBD1TfEzL",
+            multisig.to_address::<Testnet>(&BitcoinFormat::P2SH_P2WSH).unwrap().to_string()
+        );
+        assert!(multisig.to_address::<N>(&BitcoinFormat::Bech32).is_err());
+    }
+
+    #[test]
+    fn satisfy() {
+        let (keys, _, _) = VECTORS[1];
+        let multisig = MultisigScript::sorted(2, &public_keys(keys)).unwrap();
+        let mut signatures = BTreeMap::new();
+        signatures.insert(multisig.public_keys()[2].clone(), vec![0x03]);
+        assert_eq!(None, multisig.satisfy(&signatures));
+
+        // The signatures follow the order of the public keys, up to the threshold
+        signatures.insert(multisig.public_keys()[0].clone(), vec![0x01]);
+        assert_eq!(Some(vec![vec![], vec![0x01], vec![0x03]]), multisig.satisfy(&signatures));
+        signatures.insert(multisig.public_keys()[1].clone(), vec![0x02]);
+        assert_eq!(Some(vec![vec![], vec![0x01], vec![0x02]]), multisig.satisfy(&signatures));
+    }
+
+    #[test]
+    fn invalid() {
+        let (keys, script, _) = VECTORS[1];
+        let keys = public_keys(keys);
+        assert_eq!(Err(MultisigError::InvalidThreshold(0, 3)), MultisigScript::sorted(0, &keys));
+        assert_eq!(Err(MultisigError::InvalidThreshold(4, 3)), MultisigScript::sorted(4, &keys));
+        let many_keys: Vec<BitcoinPublicKey<N>> = keys.iter().cycle().take(17).cloned().collect();
+        assert_eq!(Err(MultisigError::InvalidThreshold(1, 17)), MultisigScript::new(1, &many_keys));
+
+        // A P2SH redeem script is at most 520 bytes, i.e. 15 compressed public keys
+        let multisig = MultisigScript::new(1, &many_keys[..16]).unwrap();
+        assert!(multisig.to_address::<N>(&BitcoinFormat::P2SH).is_err());
+        assert!(multisig.to_address::<N>(&BitcoinFormat::P2WSH).is_ok());
+        assert!(MultisigScript::new(1, &many_keys[..15]).unwrap().to_address::<N>(&BitcoinFormat::P2SH).is_ok());
+
+        // Uncompressed public keys are neither sorted nor spent with SegWit
+        let uncompressed = BitcoinPublicKey::<N>::from_secp256k1_public_key(keys[0].to_secp256k1_public_key(), false);
+        let keys = vec![uncompressed, keys[1].clone()];
+        assert!(matches!(MultisigScript::sorted(1, &keys), Err(MultisigError::UncompressedPublicKey(_))));
+        let multisig = MultisigScript::new(1, &keys).unwrap();
+        assert!(multisig.to_address::<N>(&BitcoinFormat::P2SH).is_ok());
+        assert!(multisig.to_address::<N>(&BitcoinFormat::P2SH_P2WSH).is_err());
+
+        let script = hex::decode(script).unwrap();
+        assert!(MultisigScript::from_script(&script[..script.len() - 1]).is_err());
+        assert!(MultisigScript::from_script(&[&[0x54], &script[1..]].concat()).is_err());
+        assert!(MultisigScript::from_script(&[]).is_err());
+    }
+}
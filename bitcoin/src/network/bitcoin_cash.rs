@@ -0,0 +1,183 @@
+use crate::format::BitcoinFormat;
+use crate::network::{BitcoinNetwork, DifficultyAdjustment};
+use chainlib_core::no_std::*;
+use chainlib_core::{
+    AddressError, Network, NetworkError, PrivateKeyError,
+};
+
+use core::{fmt, str::FromStr};
+use serde::Serialize;
+
+/// Represents the Bitcoin Cash main network, with CashAddr prefix `bitcoincash` and replay protected signatures
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub struct BitcoinCash;
+
+impl Network for BitcoinCash {
+    const NAME: &'static str = "bitcoin-cash";
+}
+
+impl BitcoinNetwork for BitcoinCash {
+
+    /// Returns the address prefix of the given network.
+    fn to_address_prefix(format: &BitcoinFormat) -> Vec<u8> {
+        match format {
+            BitcoinFormat::P2PKH => vec![0x00],
+            BitcoinFormat::P2SH | BitcoinFormat::P2SH_P2WPKH | BitcoinFormat::P2SH_P2WSH => vec![0x05],
+            // Without SegWit there are no witness programs to encode
+            BitcoinFormat::P2WSH | BitcoinFormat::Bech32 | BitcoinFormat::P2TR => vec![],
+        }
+    }
+
+    /// Returns the network of the given address prefix, of a legacy or CashAddr address.
+    fn from_address_prefix(prefix: &[u8]) -> Result<Self, AddressError> {
+        match prefix {
+            [0x00, ..] | [0x05, ..] | b"bitcoincash" => Ok(Self),
+            _ => Err(AddressError::InvalidPrefix(String::from_utf8(prefix.to_owned())?)),
+        }
+    }
+
+    /// Returns the wif prefix of the given network.
+    fn to_private_key_prefix() -> u8 {
+        0x80
+    }
+
+    /// Returns the network of the given wif prefix.
+    fn from_private_key_prefix(prefix: u8) -> Result<Self, PrivateKeyError> {
+        match prefix {
+            0x80 => Ok(Self),
+            _ => Err(PrivateKeyError::InvalidPrefix(vec![prefix])),
+        }
+    }
+
+    /// Returns the network of the given extended public key version bytes (xpub).
+    fn from_extended_public_key_version(version: &[u8; 4]) -> Result<Self, NetworkError> {
+        match version {
+            [0x04, 0x88, 0xB2, 0x1E] => Ok(Self),
+            _ => Err(NetworkError::InvalidExtendedPublicKeyPrefix(hex::encode(version))),
+        }
+    }
+
+    /// Returns false, as Bitcoin Cash has not activated SegWit.
+    fn is_segwit() -> bool {
+        false
+    }
+
+    /// Returns the CashAddr prefix `bitcoincash`.
+    fn cash_address_prefix() -> Option<&'static str> {
+        Some("bitcoincash")
+    }
+
+    /// Returns the fork id 0 of Bitcoin Cash.
+    fn fork_id() -> Option<u32> {
+        Some(0)
+    }
+
+    /// Returns that the per-block adjustment of the difficulty (ASERT) is not checked.
+    fn difficulty_adjustment() -> DifficultyAdjustment {
+        DifficultyAdjustment::Unchecked
+    }
+}
+
+impl FromStr for BitcoinCash {
+    type Err = NetworkError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            Self::NAME => Ok(Self),
+            _ => Err(NetworkError::InvalidNetwork(s.into())),
+        }
+    }
+}
+
+impl fmt::Display for BitcoinCash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", Self::NAME)
+    }
+}
+
+/// Represents the Bitcoin Cash test network, with CashAddr prefix `bchtest` and replay protected signatures
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub struct BitcoinCashTestnet;
+
+impl Network for BitcoinCashTestnet {
+    const NAME: &'static str = "bitcoin-cash-testnet";
+}
+
+impl BitcoinNetwork for BitcoinCashTestnet {
+
+    /// Returns the address prefix of the given network.
+    fn to_address_prefix(format: &BitcoinFormat) -> Vec<u8> {
+        match format {
+            BitcoinFormat::P2PKH => vec![0x6F],
+            BitcoinFormat::P2SH | BitcoinFormat::P2SH_P2WPKH | BitcoinFormat::P2SH_P2WSH => vec![0xC4],
+            // Without SegWit there are no witness programs to encode
+            BitcoinFormat::P2WSH | BitcoinFormat::Bech32 | BitcoinFormat::P2TR => vec![],
+        }
+    }
+
+    /// Returns the network of the given address prefix, of a legacy or CashAddr address.
+    fn from_address_prefix(prefix: &[u8]) -> Result<Self, AddressError> {
+        match prefix {
+            [0x6F, ..] | [0xC4, ..] | b"bchtest" => Ok(Self),
+            _ => Err(AddressError::InvalidPrefix(String::from_utf8(prefix.to_owned())?)),
+        }
+    }
+
+    /// Returns the wif prefix of the given network.
+    fn to_private_key_prefix() -> u8 {
+        0xEF
+    }
+
+    /// Returns the network of the given wif prefix.
+    fn from_private_key_prefix(prefix: u8) -> Result<Self, PrivateKeyError> {
+        match prefix {
+            0xEF => Ok(Self),
+            _ => Err(PrivateKeyError::InvalidPrefix(vec![prefix])),
+        }
+    }
+
+    /// Returns the network of the given extended public key version bytes (tpub).
+    fn from_extended_public_key_version(version: &[u8; 4]) -> Result<Self, NetworkError> {
+        match version {
+            [0x04, 0x35, 0x87, 0xCF] => Ok(Self),
+            _ => Err(NetworkError::InvalidExtendedPublicKeyPrefix(hex::encode(version))),
+        }
+    }
+
+    /// Returns false, as Bitcoin Cash has not activated SegWit.
+    fn is_segwit() -> bool {
+        false
+    }
+
+    /// Returns the CashAddr prefix `bchtest`.
+    fn cash_address_prefix() -> Option<&'static str> {
+        Some("bchtest")
+    }
+
+    /// Returns the fork id 0 of Bitcoin Cash.
+    fn fork_id() -> Option<u32> {
+        Some(0)
+    }
+
+    /// Returns that the per-block adjustment of the difficulty (ASERT) is not checked.
+    fn difficulty_adjustment() -> DifficultyAdjustment {
+        DifficultyAdjustment::Unchecked
+    }
+}
+
+impl FromStr for BitcoinCashTestnet {
+    type Err = NetworkError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            Self::NAME => Ok(Self),
+            _ => Err(NetworkError::InvalidNetwork(s.into())),
+        }
+    }
+}
+
+impl fmt::Display for BitcoinCashTestnet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", Self::NAME)
+    }
+}
@@ -0,0 +1,131 @@
+use crate::format::BitcoinFormat;
+use crate::network::BitcoinNetwork;
+use chainlib_core::no_std::*;
+use chainlib_core::{
+    AddressError, Network, NetworkError, PrivateKeyError,
+};
+
+use core::{fmt, str::FromStr};
+use serde::Serialize;
+use std::sync::RwLock;
+
+/// The parameters of a network defined at runtime, registered with `CustomNetwork::register`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NetworkParameters {
+    /// The name of the network, used to display and parse it
+    pub name: String,
+    /// The version byte of P2PKH addresses
+    pub p2pkh_prefix: u8,
+    /// The version byte of P2SH addresses
+    pub p2sh_prefix: u8,
+    /// The human-readable part of Bech32 addresses, or None if the network has not activated SegWit
+    pub bech32_hrp: Option<String>,
+    /// The version byte of WIF private keys
+    pub wif_prefix: u8,
+    /// The version bytes of the extended public keys of the network
+    pub extended_public_key_versions: Vec<[u8; 4]>,
+}
+
+/// The parameters of the registered custom networks, by id
+static PARAMETERS: RwLock<BTreeMap<usize, NetworkParameters>> = RwLock::new(BTreeMap::new());
+
+/// Represents a network defined at runtime, such as a private signet.
+/// Distinct networks are told apart by their `ID`, and are registered once with their parameters before use.
+/// Deriving addresses or keys on a network that is not registered panics, while parsing them fails.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub struct CustomNetwork<const ID: usize = 0>;
+
+impl<const ID: usize> CustomNetwork<ID> {
+    /// Registers the parameters of the network, replacing any previously registered.
+    pub fn register(parameters: NetworkParameters) {
+        PARAMETERS.write().unwrap_or_else(|e| e.into_inner()).insert(ID, parameters);
+    }
+
+    /// Returns the parameters of the network, if registered.
+    pub fn parameters() -> Option<NetworkParameters> {
+        PARAMETERS.read().unwrap_or_else(|e| e.into_inner()).get(&ID).cloned()
+    }
+
+    fn registered() -> NetworkParameters {
+        Self::parameters().unwrap_or_else(|| panic!("custom network {} is not registered", ID))
+    }
+}
+
+impl<const ID: usize> Network for CustomNetwork<ID> {
+    const NAME: &'static str = "custom";
+}
+
+impl<const ID: usize> BitcoinNetwork for CustomNetwork<ID> {
+
+    /// Returns the address prefix of the given network.
+    fn to_address_prefix(format: &BitcoinFormat) -> Vec<u8> {
+        let parameters = Self::registered();
+        match (format, parameters.bech32_hrp) {
+            (BitcoinFormat::P2PKH, _) => vec![parameters.p2pkh_prefix],
+            (BitcoinFormat::P2SH | BitcoinFormat::P2SH_P2WPKH | BitcoinFormat::P2SH_P2WSH, _) => {
+                vec![parameters.p2sh_prefix]
+            }
+            (BitcoinFormat::P2WSH, Some(_)) => vec![0x00],
+            (BitcoinFormat::Bech32 | BitcoinFormat::P2TR, Some(hrp)) => hrp.into_bytes(),
+            // Without SegWit there are no witness programs to encode
+            (BitcoinFormat::P2WSH | BitcoinFormat::Bech32 | BitcoinFormat::P2TR, None) => vec![],
+        }
+    }
+
+    /// Returns the network of the given address prefix.
+    fn from_address_prefix(prefix: &[u8]) -> Result<Self, AddressError> {
+        let invalid = || AddressError::InvalidPrefix(String::from_utf8_lossy(prefix).into_owned());
+        let parameters = Self::parameters().ok_or_else(invalid)?;
+        match prefix {
+            [version, ..] if *version == parameters.p2pkh_prefix || *version == parameters.p2sh_prefix => Ok(Self),
+            _ if parameters.bech32_hrp.as_deref().map(str::as_bytes) == Some(prefix) => Ok(Self),
+            _ => Err(invalid()),
+        }
+    }
+
+    /// Returns the wif prefix of the given network.
+    fn to_private_key_prefix() -> u8 {
+        Self::registered().wif_prefix
+    }
+
+    /// Returns the network of the given wif prefix.
+    fn from_private_key_prefix(prefix: u8) -> Result<Self, PrivateKeyError> {
+        match Self::parameters() {
+            Some(parameters) if parameters.wif_prefix == prefix => Ok(Self),
+            _ => Err(PrivateKeyError::InvalidPrefix(vec![prefix])),
+        }
+    }
+
+    /// Returns the network of the given extended public key version bytes.
+    fn from_extended_public_key_version(version: &[u8; 4]) -> Result<Self, NetworkError> {
+        match Self::parameters() {
+            Some(parameters) if parameters.extended_public_key_versions.contains(version) => Ok(Self),
+            _ => Err(NetworkError::InvalidExtendedPublicKeyPrefix(hex::encode(version))),
+        }
+    }
+
+    /// Returns true if the network is registered with a Bech32 prefix.
+    fn is_segwit() -> bool {
+        Self::parameters().is_some_and(|parameters| parameters.bech32_hrp.is_some())
+    }
+}
+
+impl<const ID: usize> FromStr for CustomNetwork<ID> {
+    type Err = NetworkError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match Self::parameters() {
+            Some(parameters) if parameters.name == s => Ok(Self),
+            _ => Err(NetworkError::InvalidNetwork(s.into())),
+        }
+    }
+}
+
+impl<const ID: usize> fmt::Display for CustomNetwork<ID> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match Self::parameters() {
+            Some(parameters) => write!(f, "{}", parameters.name),
+            None => write!(f, "{}", Self::NAME),
+        }
+    }
+}
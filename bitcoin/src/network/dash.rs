@@ -0,0 +1,163 @@
+use crate::format::BitcoinFormat;
+use crate::network::BitcoinNetwork;
+use chainlib_core::no_std::*;
+use chainlib_core::{
+    AddressError, Network, NetworkError, PrivateKeyError,
+};
+
+use core::{fmt, str::FromStr};
+use serde::Serialize;
+
+/// Represents the Dash main network, which has no SegWit
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub struct Dash;
+
+impl Network for Dash {
+    const NAME: &'static str = "dash";
+}
+
+impl BitcoinNetwork for Dash {
+
+    /// Returns the address prefix of the given network.
+    fn to_address_prefix(format: &BitcoinFormat) -> Vec<u8> {
+        match format {
+            BitcoinFormat::P2PKH => vec![0x4C],
+            BitcoinFormat::P2SH | BitcoinFormat::P2SH_P2WPKH | BitcoinFormat::P2SH_P2WSH => vec![0x10],
+            // Without SegWit there are no witness programs to encode
+            BitcoinFormat::P2WSH | BitcoinFormat::Bech32 | BitcoinFormat::P2TR => vec![],
+        }
+    }
+
+    /// Returns the network of the given address prefix.
+    fn from_address_prefix(prefix: &[u8]) -> Result<Self, AddressError> {
+        match prefix {
+            [0x4C, ..] | [0x10, ..] => Ok(Self),
+            _ => Err(AddressError::InvalidPrefix(String::from_utf8(prefix.to_owned())?)),
+        }
+    }
+
+    /// Returns the wif prefix of the given network.
+    fn to_private_key_prefix() -> u8 {
+        0xCC
+    }
+
+    /// Returns the network of the given wif prefix.
+    fn from_private_key_prefix(prefix: u8) -> Result<Self, PrivateKeyError> {
+        match prefix {
+            0xCC => Ok(Self),
+            _ => Err(PrivateKeyError::InvalidPrefix(vec![prefix])),
+        }
+    }
+
+    /// Returns the network of the given extended public key version bytes (xpub).
+    fn from_extended_public_key_version(version: &[u8; 4]) -> Result<Self, NetworkError> {
+        match version {
+            [0x04, 0x88, 0xB2, 0x1E] => Ok(Self),
+            _ => Err(NetworkError::InvalidExtendedPublicKeyPrefix(hex::encode(version))),
+        }
+    }
+
+    /// Returns false, as Dash has not activated SegWit.
+    fn is_segwit() -> bool {
+        false
+    }
+
+    /// Returns `None`, as the proof of work uses X11 rather than double SHA256.
+    fn pow_limit_bits() -> Option<u32> {
+        None
+    }
+}
+
+impl FromStr for Dash {
+    type Err = NetworkError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            Self::NAME => Ok(Self),
+            _ => Err(NetworkError::InvalidNetwork(s.into())),
+        }
+    }
+}
+
+impl fmt::Display for Dash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", Self::NAME)
+    }
+}
+
+/// Represents the Dash test network, which has no SegWit
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub struct DashTestnet;
+
+impl Network for DashTestnet {
+    const NAME: &'static str = "dash-testnet";
+}
+
+impl BitcoinNetwork for DashTestnet {
+
+    /// Returns the address prefix of the given network.
+    fn to_address_prefix(format: &BitcoinFormat) -> Vec<u8> {
+        match format {
+            BitcoinFormat::P2PKH => vec![0x8C],
+            BitcoinFormat::P2SH | BitcoinFormat::P2SH_P2WPKH | BitcoinFormat::P2SH_P2WSH => vec![0x13],
+            // Without SegWit there are no witness programs to encode
+            BitcoinFormat::P2WSH | BitcoinFormat::Bech32 | BitcoinFormat::P2TR => vec![],
+        }
+    }
+
+    /// Returns the network of the given address prefix.
+    fn from_address_prefix(prefix: &[u8]) -> Result<Self, AddressError> {
+        match prefix {
+            [0x8C, ..] | [0x13, ..] => Ok(Self),
+            _ => Err(AddressError::InvalidPrefix(String::from_utf8(prefix.to_owned())?)),
+        }
+    }
+
+    /// Returns the wif prefix of the given network.
+    fn to_private_key_prefix() -> u8 {
+        0xEF
+    }
+
+    /// Returns the network of the given wif prefix.
+    fn from_private_key_prefix(prefix: u8) -> Result<Self, PrivateKeyError> {
+        match prefix {
+            0xEF => Ok(Self),
+            _ => Err(PrivateKeyError::InvalidPrefix(vec![prefix])),
+        }
+    }
+
+    /// Returns the network of the given extended public key version bytes (tpub).
+    fn from_extended_public_key_version(version: &[u8; 4]) -> Result<Self, NetworkError> {
+        match version {
+            [0x04, 0x35, 0x87, 0xCF] => Ok(Self),
+            _ => Err(NetworkError::InvalidExtendedPublicKeyPrefix(hex::encode(version))),
+        }
+    }
+
+    /// Returns false, as Dash has not activated SegWit.
+    fn is_segwit() -> bool {
+        false
+    }
+
+    /// Returns `None`, as the proof of work uses X11 rather than double SHA256.
+    fn pow_limit_bits() -> Option<u32> {
+        None
+    }
+}
+
+impl FromStr for DashTestnet {
+    type Err = NetworkError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            Self::NAME => Ok(Self),
+            _ => Err(NetworkError::InvalidNetwork(s.into())),
+        }
+    }
+}
+
+impl fmt::Display for DashTestnet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", Self::NAME)
+    }
+}
@@ -0,0 +1,203 @@
+use crate::format::BitcoinFormat;
+use crate::network::BitcoinNetwork;
+use chainlib_core::no_std::*;
+use chainlib_core::{
+    AddressError, Network, NetworkError, PrivateKeyError,
+};
+
+use core::{fmt, str::FromStr};
+use serde::Serialize;
+
+/// Represents the Dogecoin main network, which has no SegWit
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub struct Dogecoin;
+
+impl Network for Dogecoin {
+    const NAME: &'static str = "dogecoin";
+}
+
+impl BitcoinNetwork for Dogecoin {
+
+    /// Returns the address prefix of the given network.
+    fn to_address_prefix(format: &BitcoinFormat) -> Vec<u8> {
+        match format {
+            BitcoinFormat::P2PKH => vec![0x1E],
+            BitcoinFormat::P2SH | BitcoinFormat::P2SH_P2WPKH | BitcoinFormat::P2SH_P2WSH => vec![0x16],
+            // Without SegWit there are no witness programs to encode
+            BitcoinFormat::P2WSH | BitcoinFormat::Bech32 | BitcoinFormat::P2TR => vec![],
+        }
+    }
+
+    /// Returns the network of the given address prefix.
+    fn from_address_prefix(prefix: &[u8]) -> Result<Self, AddressError> {
+        match prefix {
+            [0x1E, ..] | [0x16, ..] => Ok(Self),
+            _ => Err(AddressError::InvalidPrefix(String::from_utf8(prefix.to_owned())?)),
+        }
+    }
+
+    /// Returns the wif prefix of the given network.
+    fn to_private_key_prefix() -> u8 {
+        0x9E
+    }
+
+    /// Returns the network of the given wif prefix.
+    fn from_private_key_prefix(prefix: u8) -> Result<Self, PrivateKeyError> {
+        match prefix {
+            0x9E => Ok(Self),
+            _ => Err(PrivateKeyError::InvalidPrefix(vec![prefix])),
+        }
+    }
+
+    /// Returns the network of the given extended public key version bytes (dgub, xpub).
+    fn from_extended_public_key_version(version: &[u8; 4]) -> Result<Self, NetworkError> {
+        match version {
+            [0x02, 0xFA, 0xCA, 0xFD] | [0x04, 0x88, 0xB2, 0x1E] => Ok(Self),
+            _ => Err(NetworkError::InvalidExtendedPublicKeyPrefix(hex::encode(version))),
+        }
+    }
+
+    /// Returns false, as Dogecoin has not activated SegWit.
+    fn is_segwit() -> bool {
+        false
+    }
+
+    /// Returns the largest amount of the network, 10 billion dogecoins, as there is no cap on the supply.
+    fn max_money() -> i64 {
+        10_000_000_000 * 100_000_000
+    }
+
+    /// Returns no dust relay fee rate, as Dogecoin Core only has a dust limit.
+    fn dust_relay_fee_rate() -> u64 {
+        0
+    }
+
+    /// Returns the default dust limit of Dogecoin Core, 0.01 DOGE.
+    fn dust_limit() -> i64 {
+        1_000_000
+    }
+
+    /// Returns the default minimum relay fee rate of Dogecoin Core, 0.001 DOGE per kilobyte.
+    fn min_relay_fee_rate() -> u64 {
+        100
+    }
+
+    /// Returns `None`, as the proof of work uses scrypt rather than double SHA256.
+    fn pow_limit_bits() -> Option<u32> {
+        None
+    }
+}
+
+impl FromStr for Dogecoin {
+    type Err = NetworkError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            Self::NAME => Ok(Self),
+            _ => Err(NetworkError::InvalidNetwork(s.into())),
+        }
+    }
+}
+
+impl fmt::Display for Dogecoin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", Self::NAME)
+    }
+}
+
+/// Represents the Dogecoin test network, which has no SegWit
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub struct DogecoinTestnet;
+
+impl Network for DogecoinTestnet {
+    const NAME: &'static str = "dogecoin-testnet";
+}
+
+impl BitcoinNetwork for DogecoinTestnet {
+
+    /// Returns the address prefix of the given network.
+    fn to_address_prefix(format: &BitcoinFormat) -> Vec<u8> {
+        match format {
+            BitcoinFormat::P2PKH => vec![0x71],
+            BitcoinFormat::P2SH | BitcoinFormat::P2SH_P2WPKH | BitcoinFormat::P2SH_P2WSH => vec![0xC4],
+            // Without SegWit there are no witness programs to encode
+            BitcoinFormat::P2WSH | BitcoinFormat::Bech32 | BitcoinFormat::P2TR => vec![],
+        }
+    }
+
+    /// Returns the network of the given address prefix.
+    fn from_address_prefix(prefix: &[u8]) -> Result<Self, AddressError> {
+        match prefix {
+            [0x71, ..] | [0xC4, ..] => Ok(Self),
+            _ => Err(AddressError::InvalidPrefix(String::from_utf8(prefix.to_owned())?)),
+        }
+    }
+
+    /// Returns the wif prefix of the given network.
+    fn to_private_key_prefix() -> u8 {
+        0xF1
+    }
+
+    /// Returns the network of the given wif prefix.
+    fn from_private_key_prefix(prefix: u8) -> Result<Self, PrivateKeyError> {
+        match prefix {
+            0xF1 => Ok(Self),
+            _ => Err(PrivateKeyError::InvalidPrefix(vec![prefix])),
+        }
+    }
+
+    /// Returns the network of the given extended public key version bytes (tgub, tpub).
+    fn from_extended_public_key_version(version: &[u8; 4]) -> Result<Self, NetworkError> {
+        match version {
+            [0x04, 0x32, 0xA9, 0xA8] | [0x04, 0x35, 0x87, 0xCF] => Ok(Self),
+            _ => Err(NetworkError::InvalidExtendedPublicKeyPrefix(hex::encode(version))),
+        }
+    }
+
+    /// Returns false, as Dogecoin has not activated SegWit.
+    fn is_segwit() -> bool {
+        false
+    }
+
+    /// Returns the largest amount of the network, 10 billion dogecoins, as there is no cap on the supply.
+    fn max_money() -> i64 {
+        10_000_000_000 * 100_000_000
+    }
+
+    /// Returns no dust relay fee rate, as Dogecoin Core only has a dust limit.
+    fn dust_relay_fee_rate() -> u64 {
+        0
+    }
+
+    /// Returns the default dust limit of Dogecoin Core, 0.01 DOGE.
+    fn dust_limit() -> i64 {
+        1_000_000
+    }
+
+    /// Returns the default minimum relay fee rate of Dogecoin Core, 0.001 DOGE per kilobyte.
+    fn min_relay_fee_rate() -> u64 {
+        100
+    }
+
+    /// Returns `None`, as the proof of work uses scrypt rather than double SHA256.
+    fn pow_limit_bits() -> Option<u32> {
+        None
+    }
+}
+
+impl FromStr for DogecoinTestnet {
+    type Err = NetworkError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            Self::NAME => Ok(Self),
+            _ => Err(NetworkError::InvalidNetwork(s.into())),
+        }
+    }
+}
+
+impl fmt::Display for DogecoinTestnet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", Self::NAME)
+    }
+}
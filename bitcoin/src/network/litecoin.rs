@@ -0,0 +1,209 @@
+use crate::format::BitcoinFormat;
+use crate::network::BitcoinNetwork;
+use chainlib_core::no_std::*;
+use chainlib_core::{
+    AddressError, Network, NetworkError, PrivateKeyError,
+};
+
+use core::{fmt, str::FromStr};
+use serde::Serialize;
+
+/// Represents the Litecoin main network, with Bech32 prefix `ltc` and script hash addresses starting with M
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub struct Litecoin;
+
+impl Network for Litecoin {
+    const NAME: &'static str = "litecoin";
+}
+
+impl BitcoinNetwork for Litecoin {
+
+    /// Returns the address prefix of the given network.
+    fn to_address_prefix(format: &BitcoinFormat) -> Vec<u8> {
+        match format {
+            BitcoinFormat::P2PKH => vec![0x30],
+            BitcoinFormat::P2SH => vec![0x32],
+            BitcoinFormat::P2WSH => vec![0x00],
+            BitcoinFormat::P2SH_P2WPKH => vec![0x32],
+            BitcoinFormat::P2SH_P2WSH => vec![0x32],
+            BitcoinFormat::Bech32 => vec![0x6c, 0x74, 0x63],
+            BitcoinFormat::P2TR => vec![0x6c, 0x74, 0x63],
+        }
+    }
+
+    /// Returns the network of the given address prefix.
+    fn from_address_prefix(prefix: &[u8]) -> Result<Self, AddressError> {
+        match prefix {
+            [0x30, ..] | [0x32, ..] | [0x05, ..] | b"ltc" => Ok(Self),
+            _ => Err(AddressError::InvalidPrefix(String::from_utf8(prefix.to_owned())?)),
+        }
+    }
+
+    /// Returns the wif prefix of the given network.
+    fn to_private_key_prefix() -> u8 {
+        0xB0
+    }
+
+    /// Returns the network of the given wif prefix.
+    fn from_private_key_prefix(prefix: u8) -> Result<Self, PrivateKeyError> {
+        match prefix {
+            0xB0 => Ok(Self),
+            _ => Err(PrivateKeyError::InvalidPrefix(vec![prefix])),
+        }
+    }
+
+    /// Returns the network of the given extended public key version bytes (xpub, Ltub, Mtub).
+    fn from_extended_public_key_version(version: &[u8; 4]) -> Result<Self, NetworkError> {
+        match version {
+            [0x04, 0x88, 0xB2, 0x1E] | [0x01, 0x9D, 0xA4, 0x62] | [0x01, 0xB2, 0x6E, 0xF6] => Ok(Self),
+            _ => Err(NetworkError::InvalidExtendedPublicKeyPrefix(hex::encode(version))),
+        }
+    }
+
+    /// Returns the format of a Base58 address of the given version byte, if any,
+    /// accepting the script hash prefix Litecoin shared with Bitcoin before its own.
+    fn to_base58_format(version: u8) -> Option<BitcoinFormat> {
+        match version {
+            0x30 => Some(BitcoinFormat::P2PKH),
+            0x32 | 0x05 => Some(BitcoinFormat::P2SH_P2WPKH),
+            _ => None,
+        }
+    }
+
+    /// Returns the largest amount of the network, 84 million litecoins.
+    fn max_money() -> i64 {
+        84_000_000 * 100_000_000
+    }
+
+    /// Returns the default dust relay fee rate of Litecoin Core, 0.0003 LTC per kilobyte.
+    fn dust_relay_fee_rate() -> u64 {
+        30
+    }
+
+    /// Returns the default minimum relay fee rate of Litecoin Core, 0.0001 LTC per kilobyte.
+    fn min_relay_fee_rate() -> u64 {
+        10
+    }
+
+    /// Returns `None`, as the proof of work uses scrypt rather than double SHA256.
+    fn pow_limit_bits() -> Option<u32> {
+        None
+    }
+}
+
+impl FromStr for Litecoin {
+    type Err = NetworkError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            Self::NAME => Ok(Self),
+            _ => Err(NetworkError::InvalidNetwork(s.into())),
+        }
+    }
+}
+
+impl fmt::Display for Litecoin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", Self::NAME)
+    }
+}
+
+/// Represents the Litecoin test network, with Bech32 prefix `tltc` and script hash addresses starting with Q
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub struct LitecoinTestnet;
+
+impl Network for LitecoinTestnet {
+    const NAME: &'static str = "litecoin-testnet";
+}
+
+impl BitcoinNetwork for LitecoinTestnet {
+
+    /// Returns the address prefix of the given network.
+    fn to_address_prefix(format: &BitcoinFormat) -> Vec<u8> {
+        match format {
+            BitcoinFormat::P2PKH => vec![0x6F],
+            BitcoinFormat::P2SH => vec![0x3A],
+            BitcoinFormat::P2WSH => vec![0x00],
+            BitcoinFormat::P2SH_P2WPKH => vec![0x3A],
+            BitcoinFormat::P2SH_P2WSH => vec![0x3A],
+            BitcoinFormat::Bech32 => vec![0x74, 0x6c, 0x74, 0x63],
+            BitcoinFormat::P2TR => vec![0x74, 0x6c, 0x74, 0x63],
+        }
+    }
+
+    /// Returns the network of the given address prefix.
+    fn from_address_prefix(prefix: &[u8]) -> Result<Self, AddressError> {
+        match prefix {
+            [0x6F, ..] | [0x3A, ..] | [0xC4, ..] | b"tltc" => Ok(Self),
+            _ => Err(AddressError::InvalidPrefix(String::from_utf8(prefix.to_owned())?)),
+        }
+    }
+
+    /// Returns the wif prefix of the given network.
+    fn to_private_key_prefix() -> u8 {
+        0xEF
+    }
+
+    /// Returns the network of the given wif prefix.
+    fn from_private_key_prefix(prefix: u8) -> Result<Self, PrivateKeyError> {
+        match prefix {
+            0xEF => Ok(Self),
+            _ => Err(PrivateKeyError::InvalidPrefix(vec![prefix])),
+        }
+    }
+
+    /// Returns the network of the given extended public key version bytes (tpub, ttub).
+    fn from_extended_public_key_version(version: &[u8; 4]) -> Result<Self, NetworkError> {
+        match version {
+            [0x04, 0x35, 0x87, 0xCF] | [0x04, 0x36, 0xF6, 0xE1] => Ok(Self),
+            _ => Err(NetworkError::InvalidExtendedPublicKeyPrefix(hex::encode(version))),
+        }
+    }
+
+    /// Returns the format of a Base58 address of the given version byte, if any,
+    /// accepting the script hash prefix Litecoin shared with Bitcoin before its own.
+    fn to_base58_format(version: u8) -> Option<BitcoinFormat> {
+        match version {
+            0x6F => Some(BitcoinFormat::P2PKH),
+            0x3A | 0xC4 => Some(BitcoinFormat::P2SH_P2WPKH),
+            _ => None,
+        }
+    }
+
+    /// Returns the largest amount of the network, 84 million litecoins.
+    fn max_money() -> i64 {
+        84_000_000 * 100_000_000
+    }
+
+    /// Returns the default dust relay fee rate of Litecoin Core, 0.0003 LTC per kilobyte.
+    fn dust_relay_fee_rate() -> u64 {
+        30
+    }
+
+    /// Returns the default minimum relay fee rate of Litecoin Core, 0.0001 LTC per kilobyte.
+    fn min_relay_fee_rate() -> u64 {
+        10
+    }
+
+    /// Returns `None`, as the proof of work uses scrypt rather than double SHA256.
+    fn pow_limit_bits() -> Option<u32> {
+        None
+    }
+}
+
+impl FromStr for LitecoinTestnet {
+    type Err = NetworkError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            Self::NAME => Ok(Self),
+            _ => Err(NetworkError::InvalidNetwork(s.into())),
+        }
+    }
+}
+
+impl fmt::Display for LitecoinTestnet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", Self::NAME)
+    }
+}
@@ -21,16 +21,19 @@ impl BitcoinNetwork for Mainnet {
     fn to_address_prefix(format: &BitcoinFormat) -> Vec<u8> {
         match format {
             BitcoinFormat::P2PKH => vec![0x00],
+            BitcoinFormat::P2SH => vec![0x05],
             BitcoinFormat::P2WSH => vec![0x00],
             BitcoinFormat::P2SH_P2WPKH => vec![0x05],
+            BitcoinFormat::P2SH_P2WSH => vec![0x05],
             BitcoinFormat::Bech32 => vec![0x62, 0x63],
+            BitcoinFormat::P2TR => vec![0x62, 0x63],
         }
     }
 
     /// Returns the network of the given address prefix.
     fn from_address_prefix(prefix: &[u8]) -> Result<Self, AddressError> {
-        match (prefix[0], prefix[1]) {
-            (0x00, _) | (0x05, _) | (0x62, 0x63) => Ok(Self),
+        match prefix {
+            [0x00, ..] | [0x05, ..] | b"bc" => Ok(Self),
             _ => Err(AddressError::InvalidPrefix(String::from_utf8(prefix.to_owned())?)),
         }
     }
@@ -47,6 +50,14 @@ impl BitcoinNetwork for Mainnet {
             _ => Err(PrivateKeyError::InvalidPrefix(vec![prefix])),
         }
     }
+
+    /// Returns the network of the given extended public key version bytes (xpub, ypub, zpub).
+    fn from_extended_public_key_version(version: &[u8; 4]) -> Result<Self, NetworkError> {
+        match version {
+            [0x04, 0x88, 0xB2, 0x1E] | [0x04, 0x9D, 0x7C, 0xB2] | [0x04, 0xB2, 0x47, 0x46] => Ok(Self),
+            _ => Err(NetworkError::InvalidExtendedPublicKeyPrefix(hex::encode(version))),
+        }
+    }
 }
 
 impl FromStr for Mainnet {
@@ -1,7 +1,7 @@
 use crate::format::BitcoinFormat;
 use chainlib_core::no_std::*;
 use chainlib_core::{
-    AddressError, Network, PrivateKeyError,
+    AddressError, Network, NetworkError, PrivateKeyError,
 };
 
 pub mod mainnet;
@@ -10,6 +10,40 @@ pub use self::mainnet::*;
 pub mod testnet;
 pub use self::testnet::*;
 
+pub mod regtest;
+pub use self::regtest::*;
+
+pub mod signet;
+pub use self::signet::*;
+
+pub mod litecoin;
+pub use self::litecoin::*;
+
+pub mod dogecoin;
+pub use self::dogecoin::*;
+
+pub mod dash;
+pub use self::dash::*;
+
+pub mod bitcoin_cash;
+pub use self::bitcoin_cash::*;
+
+#[cfg(feature = "std")]
+pub mod custom;
+#[cfg(feature = "std")]
+pub use self::custom::*;
+
+/// Represents how a network adjusts the difficulty of its proof of work
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DifficultyAdjustment {
+    /// At the end of every period of the given number of blocks, towards two weeks per period, as Bitcoin does
+    Periodic(u32),
+    /// Never, as on regtest
+    Fixed,
+    /// With an algorithm whose targets are not checked, such as the per-block adjustment of Bitcoin Cash
+    Unchecked,
+}
+
 /// The interface for a Bitcoin network.
 pub trait BitcoinNetwork: Network {
 
@@ -24,4 +58,73 @@ pub trait BitcoinNetwork: Network {
 
     /// Returns the network of the given wif prefix.
     fn from_private_key_prefix(prefix: u8) -> Result<Self, PrivateKeyError>;
+
+    /// Returns the network of the given extended public key version bytes.
+    fn from_extended_public_key_version(version: &[u8; 4]) -> Result<Self, NetworkError>;
+
+    /// Returns true if the network has activated SegWit (BIP-141), and so has SegWit and Taproot addresses.
+    fn is_segwit() -> bool {
+        true
+    }
+
+    /// Returns the format of a Base58 address of the given version byte, if any.
+    /// Script hash addresses are reported as P2SH_P2WPKH on SegWit networks, as the prefix does not reveal the script.
+    fn to_base58_format(version: u8) -> Option<BitcoinFormat> {
+        if version == Self::to_address_prefix(&BitcoinFormat::P2PKH)[0] {
+            Some(BitcoinFormat::P2PKH)
+        } else if version == Self::to_address_prefix(&BitcoinFormat::P2SH)[0] {
+            match Self::is_segwit() {
+                true => Some(BitcoinFormat::P2SH_P2WPKH),
+                false => Some(BitcoinFormat::P2SH),
+            }
+        } else {
+            None
+        }
+    }
+
+    /// Returns the largest amount of the network in its base unit, by default 21 million coins.
+    fn max_money() -> i64 {
+        21_000_000 * 100_000_000
+    }
+
+    /// Returns the default fee rate of relaying an output, in base units per virtual byte, below which it is dust.
+    fn dust_relay_fee_rate() -> u64 {
+        3
+    }
+
+    /// Returns the amount below which an output is dust regardless of its size, by default none.
+    fn dust_limit() -> i64 {
+        0
+    }
+
+    /// Returns the default minimum fee rate of relaying a transaction, in base units per virtual byte.
+    fn min_relay_fee_rate() -> u64 {
+        1
+    }
+
+    /// Returns the prefix of the CashAddr encoding of P2PKH and P2SH addresses, if the network uses it.
+    fn cash_address_prefix() -> Option<&'static str> {
+        None
+    }
+
+    /// Returns the fork id that replay protected signatures (SIGHASH_FORKID) commit to, if the network requires them.
+    fn fork_id() -> Option<u32> {
+        None
+    }
+
+    /// Returns the compact target of the lowest difficulty of the network's double SHA256 proof of work,
+    /// or `None` if its proof of work uses another hash function.
+    fn pow_limit_bits() -> Option<u32> {
+        Some(0x1d00ffff)
+    }
+
+    /// Returns how the network adjusts the difficulty of its proof of work, by default every 2016 blocks.
+    fn difficulty_adjustment() -> DifficultyAdjustment {
+        DifficultyAdjustment::Periodic(2016)
+    }
+
+    /// Returns true if a block may have the lowest difficulty once 20 minutes have passed since the previous one.
+    fn allows_min_difficulty_blocks() -> bool {
+        false
+    }
 }
@@ -0,0 +1,89 @@
+use crate::format::BitcoinFormat;
+use crate::network::{BitcoinNetwork, DifficultyAdjustment};
+use chainlib_core::no_std::*;
+use chainlib_core::{
+    AddressError, Network, NetworkError, PrivateKeyError
+};
+
+use core::{fmt, str::FromStr};
+use serde::Serialize;
+
+/// Represents the Bitcoin regression test network of a local node, with Bech32 prefix `bcrt`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub struct Regtest;
+
+impl Network for Regtest {
+    const NAME: &'static str = "regtest";
+}
+
+impl BitcoinNetwork for Regtest {
+
+    /// Returns the address prefix of the given network.
+    fn to_address_prefix(format: &BitcoinFormat) -> Vec<u8> {
+        match format {
+            BitcoinFormat::P2PKH => vec![0x6F],
+            BitcoinFormat::P2SH => vec![0xC4],
+            BitcoinFormat::P2WSH => vec![0x00],
+            BitcoinFormat::P2SH_P2WPKH => vec![0xC4],
+            BitcoinFormat::P2SH_P2WSH => vec![0xC4],
+            BitcoinFormat::Bech32 => vec![0x62, 0x63, 0x72, 0x74],
+            BitcoinFormat::P2TR => vec![0x62, 0x63, 0x72, 0x74],
+        }
+    }
+
+    /// Returns the network of the given address prefix.
+    fn from_address_prefix(prefix: &[u8]) -> Result<Self, AddressError> {
+        match prefix {
+            [0x6F, ..] | [0xC4, ..] | b"bcrt" => Ok(Self),
+            _ => Err(AddressError::InvalidPrefix(String::from_utf8(prefix.to_owned())?)),
+        }
+    }
+
+    /// Returns the wif prefix of the given network.
+    fn to_private_key_prefix() -> u8 {
+        0xEF
+    }
+
+    /// Returns the network of the given wif prefix.
+    fn from_private_key_prefix(prefix: u8) -> Result<Self, PrivateKeyError> {
+        match prefix {
+            0xEF => Ok(Self),
+            _ => Err(PrivateKeyError::InvalidPrefix(vec![prefix])),
+        }
+    }
+
+    /// Returns the network of the given extended public key version bytes (tpub, upub, vpub).
+    fn from_extended_public_key_version(version: &[u8; 4]) -> Result<Self, NetworkError> {
+        match version {
+            [0x04, 0x35, 0x87, 0xCF] | [0x04, 0x4A, 0x52, 0x62] | [0x04, 0x5F, 0x1C, 0xF6] => Ok(Self),
+            _ => Err(NetworkError::InvalidExtendedPublicKeyPrefix(hex::encode(version))),
+        }
+    }
+
+    /// Returns the compact target of the lowest difficulty, which regtest blocks always have.
+    fn pow_limit_bits() -> Option<u32> {
+        Some(0x207fffff)
+    }
+
+    /// Returns that regtest never adjusts its difficulty.
+    fn difficulty_adjustment() -> DifficultyAdjustment {
+        DifficultyAdjustment::Fixed
+    }
+}
+
+impl FromStr for Regtest {
+    type Err = NetworkError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            Self::NAME => Ok(Self),
+            _ => Err(NetworkError::InvalidNetwork(s.into())),
+        }
+    }
+}
+
+impl fmt::Display for Regtest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", Self::NAME)
+    }
+}
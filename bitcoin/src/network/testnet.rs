@@ -21,16 +21,19 @@ impl BitcoinNetwork for Testnet {
     fn to_address_prefix(format: &BitcoinFormat) -> Vec<u8> {
         match format {
             BitcoinFormat::P2PKH => vec![0x6F],
+            BitcoinFormat::P2SH => vec![0xC4],
             BitcoinFormat::P2WSH => vec![0x00],
             BitcoinFormat::P2SH_P2WPKH => vec![0xC4],
+            BitcoinFormat::P2SH_P2WSH => vec![0xC4],
             BitcoinFormat::Bech32 => vec![0x74, 0x62],
+            BitcoinFormat::P2TR => vec![0x74, 0x62],
         }
     }
 
     /// Returns the network of the given address prefix.
     fn from_address_prefix(prefix: &[u8]) -> Result<Self, AddressError> {
-        match (prefix[0], prefix[1]) {
-            (0x6F, _) | (0x00, _) | (0xC4, _) | (0x74, 0x62) => Ok(Self),
+        match prefix {
+            [0x6F, ..] | [0x00, ..] | [0xC4, ..] | b"tb" => Ok(Self),
             _ => Err(AddressError::InvalidPrefix(String::from_utf8(prefix.to_owned())?)),
         }
     }
@@ -47,6 +50,19 @@ impl BitcoinNetwork for Testnet {
             _ => Err(PrivateKeyError::InvalidPrefix(vec![prefix])),
         }
     }
+
+    /// Returns the network of the given extended public key version bytes (tpub, upub, vpub).
+    fn from_extended_public_key_version(version: &[u8; 4]) -> Result<Self, NetworkError> {
+        match version {
+            [0x04, 0x35, 0x87, 0xCF] | [0x04, 0x4A, 0x52, 0x62] | [0x04, 0x5F, 0x1C, 0xF6] => Ok(Self),
+            _ => Err(NetworkError::InvalidExtendedPublicKeyPrefix(hex::encode(version))),
+        }
+    }
+
+    /// Returns true, as a testnet block may have the lowest difficulty 20 minutes after the previous one.
+    fn allows_min_difficulty_blocks() -> bool {
+        true
+    }
 }
 
 impl FromStr for Testnet {
@@ -0,0 +1,762 @@
+//! # Omni Layer
+//!
+//! Encodes and decodes Omni Layer transactions, which are carried in the OP_RETURN output of a
+//! Bitcoin transaction (class C): the marker `omni` followed by the payload, a big endian version,
+//! type and fields. The sender is the address contributing the most to the inputs and the
+//! reference, the recipient of sends and the seller of DEx accepts, is the last output not paying
+//! the sender, so a full transaction pays the change to the sender first, then the payload and then
+//! the reference, as Omni Core does.
+//! https://github.com/OmniLayer/spec
+
+use crate::address::BitcoinAddress;
+use crate::amount::BitcoinAmount;
+use crate::coin_selection::default_dust_threshold;
+use crate::network::BitcoinNetwork;
+use crate::script::{Instruction, Instructions, Opcode, Script};
+use crate::transaction::{
+    BitcoinTransaction, BitcoinTransactionBuilder, BitcoinTransactionInputBuilder, BitcoinTransactionOutput,
+};
+use chainlib_core::no_std::*;
+use chainlib_core::TransactionError;
+
+use core::str::FromStr;
+
+/// The marker prefixed to the payload of a class C transaction
+pub const OMNI_MARKER: [u8; 4] = *b"omni";
+/// The property id of OMNI
+pub const OMNI_PROPERTY_ID: u32 = 1;
+/// The property id of Test OMNI
+pub const TEST_OMNI_PROPERTY_ID: u32 = 2;
+/// The property id of Tether USD
+pub const USDT_PROPERTY_ID: u32 = 31;
+
+/// The maximum length of a memo, excluding its null terminator
+const MAX_MEMO_LENGTH: usize = 255;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum OmniError {
+    #[error("invalid amount {0}")]
+    InvalidAmount(i64),
+
+    #[error("invalid memo of {0} bytes")]
+    InvalidMemo(usize),
+
+    #[error("invalid payload: {0}")]
+    InvalidPayload(String),
+
+    #[error("invalid script: {0}")]
+    InvalidScript(String),
+
+    #[error("missing change address")]
+    MissingChange,
+
+    #[error("missing reference address")]
+    MissingReference,
+
+    #[error("unsupported transaction type {0} version {1}")]
+    UnsupportedTransactionType(u16, u16),
+}
+
+impl From<OmniError> for TransactionError {
+    fn from(error: OmniError) -> Self {
+        TransactionError::Crate("Omni", format!("{:?}", error))
+    }
+}
+
+/// Represents the ecosystem of the properties moved by a send-all
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Ecosystem {
+    Main = 1,
+    Test = 2,
+}
+
+/// Represents the action of a DEx sell offer
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DexAction {
+    New = 1,
+    Update = 2,
+    Cancel = 3,
+}
+
+/// Represents the payload of an Omni Layer transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OmniTransaction {
+    /// Sends the amount of the property to the reference (type 0)
+    SimpleSend { property_id: u32, amount: i64 },
+    /// Distributes the amount of the property to the holders of the distribution property,
+    /// by default the property itself (type 3)
+    SendToOwners {
+        property_id: u32,
+        amount: i64,
+        distribution_property_id: Option<u32>,
+    },
+    /// Sends the whole balance of every property of the ecosystem to the reference (type 4)
+    SendAll { ecosystem: Ecosystem },
+    /// Offers the amount of the property for the amount of bitcoin on the traditional DEx, to be
+    /// paid within the payment window in blocks by a buyer paying at least the fee (type 20)
+    DexSellOffer {
+        property_id: u32,
+        amount: i64,
+        amount_desired: i64,
+        payment_window: u8,
+        min_fee: i64,
+        action: DexAction,
+    },
+    /// Accepts the amount of the sell offer of the property by the reference (type 22)
+    DexAccept { property_id: u32, amount: i64 },
+    /// Offers the amount of the property for the amount of the desired property on the
+    /// distributed exchange (type 25)
+    MetaDexTrade {
+        property_id: u32,
+        amount: i64,
+        desired_property_id: u32,
+        desired_amount: i64,
+    },
+    /// Grants new tokens of the managed property to the reference, or to the issuer without one (type 55)
+    GrantTokens { property_id: u32, amount: i64, memo: String },
+    /// Revokes tokens of the managed property held by the issuer (type 56)
+    RevokeTokens { property_id: u32, amount: i64, memo: String },
+}
+
+impl OmniTransaction {
+    /// Returns the type and version of the transaction.
+    pub fn transaction_type(&self) -> (u16, u16) {
+        match self {
+            Self::SimpleSend { .. } => (0, 0),
+            Self::SendToOwners {
+                distribution_property_id: None,
+                ..
+            } => (3, 0),
+            Self::SendToOwners { .. } => (3, 1),
+            Self::SendAll { .. } => (4, 0),
+            Self::DexSellOffer { .. } => (20, 1),
+            Self::DexAccept { .. } => (22, 0),
+            Self::MetaDexTrade { .. } => (25, 0),
+            Self::GrantTokens { .. } => (55, 0),
+            Self::RevokeTokens { .. } => (56, 0),
+        }
+    }
+
+    /// Returns true if the transaction acts on the reference, which must then be an output.
+    pub fn requires_reference(&self) -> bool {
+        matches!(self, Self::SimpleSend { .. } | Self::SendAll { .. } | Self::DexAccept { .. })
+    }
+
+    /// Returns the payload of the transaction, without the marker.
+    pub fn to_payload(&self) -> Result<Vec<u8>, OmniError> {
+        let (transaction_type, version) = self.transaction_type();
+        let mut payload = [version.to_be_bytes(), transaction_type.to_be_bytes()].concat();
+        match self {
+            Self::SimpleSend { property_id, amount } | Self::DexAccept { property_id, amount } => {
+                payload.extend(property_id.to_be_bytes());
+                payload.extend(positive(*amount)?.to_be_bytes());
+            }
+            Self::SendToOwners {
+                property_id,
+                amount,
+                distribution_property_id,
+            } => {
+                payload.extend(property_id.to_be_bytes());
+                payload.extend(positive(*amount)?.to_be_bytes());
+                if let Some(distribution_property_id) = distribution_property_id {
+                    payload.extend(distribution_property_id.to_be_bytes());
+                }
+            }
+            Self::SendAll { ecosystem } => payload.push(*ecosystem as u8),
+            Self::DexSellOffer {
+                property_id,
+                amount,
+                amount_desired,
+                payment_window,
+                min_fee,
+                action,
+            } => {
+                // A cancellation does not need the amounts
+                if *action != DexAction::Cancel {
+                    positive(*amount)?;
+                    positive(*amount_desired)?;
+                }
+                if *min_fee < 0 {
+                    return Err(OmniError::InvalidAmount(*min_fee));
+                }
+                payload.extend(property_id.to_be_bytes());
+                payload.extend(amount.to_be_bytes());
+                payload.extend(amount_desired.to_be_bytes());
+                payload.push(*payment_window);
+                payload.extend(min_fee.to_be_bytes());
+                payload.push(*action as u8);
+            }
+            Self::MetaDexTrade {
+                property_id,
+                amount,
+                desired_property_id,
+                desired_amount,
+            } => {
+                payload.extend(property_id.to_be_bytes());
+                payload.extend(positive(*amount)?.to_be_bytes());
+                payload.extend(desired_property_id.to_be_bytes());
+                payload.extend(positive(*desired_amount)?.to_be_bytes());
+            }
+            Self::GrantTokens {
+                property_id,
+                amount,
+                memo,
+            }
+            | Self::RevokeTokens {
+                property_id,
+                amount,
+                memo,
+            } => {
+                if memo.len() > MAX_MEMO_LENGTH || memo.contains('\0') {
+                    return Err(OmniError::InvalidMemo(memo.len()));
+                }
+                payload.extend(property_id.to_be_bytes());
+                payload.extend(positive(*amount)?.to_be_bytes());
+                payload.extend(memo.as_bytes());
+                payload.push(0);
+            }
+        }
+        Ok(payload)
+    }
+
+    /// Returns the transaction of the payload, without the marker. As in Omni Core, any bytes
+    /// following the fields of the transaction are ignored.
+    pub fn from_payload(payload: &[u8]) -> Result<Self, OmniError> {
+        let mut reader = PayloadReader(payload);
+        let version = reader.u16()?;
+        let transaction_type = reader.u16()?;
+        let transaction = match (transaction_type, version) {
+            (0, 0) => Self::SimpleSend {
+                property_id: reader.u32()?,
+                amount: reader.i64()?,
+            },
+            (3, 0) | (3, 1) => Self::SendToOwners {
+                property_id: reader.u32()?,
+                amount: reader.i64()?,
+                distribution_property_id: match version {
+                    0 => None,
+                    _ => Some(reader.u32()?),
+                },
+            },
+            (4, 0) => Self::SendAll {
+                ecosystem: match reader.u8()? {
+                    1 => Ecosystem::Main,
+                    2 => Ecosystem::Test,
+                    ecosystem => return Err(OmniError::InvalidPayload(format!("unknown ecosystem {}", ecosystem))),
+                },
+            },
+            (20, 1) => Self::DexSellOffer {
+                property_id: reader.u32()?,
+                amount: reader.i64()?,
+                amount_desired: reader.i64()?,
+                payment_window: reader.u8()?,
+                min_fee: reader.i64()?,
+                action: match reader.u8()? {
+                    1 => DexAction::New,
+                    2 => DexAction::Update,
+                    3 => DexAction::Cancel,
+                    action => return Err(OmniError::InvalidPayload(format!("unknown DEx action {}", action))),
+                },
+            },
+            (22, 0) => Self::DexAccept {
+                property_id: reader.u32()?,
+                amount: reader.i64()?,
+            },
+            (25, 0) => Self::MetaDexTrade {
+                property_id: reader.u32()?,
+                amount: reader.i64()?,
+                desired_property_id: reader.u32()?,
+                desired_amount: reader.i64()?,
+            },
+            (55, 0) => Self::GrantTokens {
+                property_id: reader.u32()?,
+                amount: reader.i64()?,
+                memo: reader.string()?,
+            },
+            (56, 0) => Self::RevokeTokens {
+                property_id: reader.u32()?,
+                amount: reader.i64()?,
+                memo: reader.string()?,
+            },
+            _ => return Err(OmniError::UnsupportedTransactionType(transaction_type, version)),
+        };
+        Ok(transaction)
+    }
+
+    /// Returns the script `OP_RETURN <omni || payload>` of the data output.
+    pub fn to_script(&self) -> Result<Vec<u8>, OmniError> {
+        let data = [&OMNI_MARKER[..], &self.to_payload()?].concat();
+        Ok(Script::new_op_return(&data).into_bytes())
+    }
+
+    /// Returns the transaction of the data output script, whose pushes following OP_RETURN
+    /// are concatenated and start with the marker.
+    pub fn from_script(script: &[u8]) -> Result<Self, OmniError> {
+        let mut instructions = Instructions::new(script);
+        if instructions.next() != Some(Ok(Instruction::Op(Opcode::OP_RETURN))) {
+            return Err(OmniError::InvalidScript("missing OP_RETURN".into()));
+        }
+        let mut data = vec![];
+        for instruction in instructions {
+            match instruction {
+                Ok(Instruction::PushBytes(bytes)) => data.extend_from_slice(bytes),
+                Ok(_) => return Err(OmniError::InvalidScript("non-push opcode".into())),
+                Err(error) => return Err(OmniError::InvalidScript(error.to_string())),
+            }
+        }
+        match data.strip_prefix(&OMNI_MARKER[..]) {
+            Some(payload) => Self::from_payload(payload),
+            None => Err(OmniError::InvalidScript("missing marker".into())),
+        }
+    }
+
+    /// Returns the data output of the transaction.
+    pub fn to_output(&self) -> Result<BitcoinTransactionOutput, OmniError> {
+        Ok(BitcoinTransactionOutput {
+            amount: BitcoinAmount::ZERO,
+            script_pub_key: self.to_script()?,
+        })
+    }
+
+    /// Returns the Omni transaction carried by the first output with the marker, if any.
+    pub fn from_transaction<N: BitcoinNetwork>(transaction: &BitcoinTransaction<N>) -> Result<Option<Self>, OmniError> {
+        transaction
+            .parameters
+            .outputs
+            .iter()
+            .find(|output| is_omni_script(&output.script_pub_key))
+            .map(|output| Self::from_script(&output.script_pub_key))
+            .transpose()
+    }
+}
+
+/// Returns the amount if it is positive, as Omni Core requires of every amount sent.
+fn positive(amount: i64) -> Result<i64, OmniError> {
+    match amount > 0 {
+        true => Ok(amount),
+        false => Err(OmniError::InvalidAmount(amount)),
+    }
+}
+
+/// Returns true if the script is an OP_RETURN whose data starts with the marker.
+fn is_omni_script(script: &[u8]) -> bool {
+    let mut instructions = Instructions::new(script);
+    instructions.next() == Some(Ok(Instruction::Op(Opcode::OP_RETURN)))
+        && matches!(instructions.next(), Some(Ok(Instruction::PushBytes(data))) if data.starts_with(&OMNI_MARKER))
+}
+
+/// Reads the big endian fields of a payload.
+struct PayloadReader<'a>(&'a [u8]);
+
+impl<'a> PayloadReader<'a> {
+    fn take<const SIZE: usize>(&mut self) -> Result<[u8; SIZE], OmniError> {
+        if self.0.len() < SIZE {
+            return Err(OmniError::InvalidPayload(format!("expected {} more bytes", SIZE - self.0.len())));
+        }
+        let (bytes, rest) = self.0.split_at(SIZE);
+        self.0 = rest;
+        Ok(bytes.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> Result<u8, OmniError> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, OmniError> {
+        Ok(u16::from_be_bytes(self.take()?))
+    }
+
+    fn u32(&mut self) -> Result<u32, OmniError> {
+        Ok(u32::from_be_bytes(self.take()?))
+    }
+
+    fn i64(&mut self) -> Result<i64, OmniError> {
+        Ok(i64::from_be_bytes(self.take()?))
+    }
+
+    /// Reads a null terminated string, or the remaining bytes without a terminator.
+    fn string(&mut self) -> Result<String, OmniError> {
+        let len = self.0.iter().position(|&byte| byte == 0).unwrap_or(self.0.len());
+        let string = String::from_utf8(self.0[..len].to_vec())
+            .map_err(|_| OmniError::InvalidPayload("memo is not UTF-8".into()))?;
+        self.0 = &self.0[(len + 1).min(self.0.len())..];
+        Ok(string)
+    }
+}
+
+/// Returns the sender of the transaction, the address of the inputs contributing the most,
+/// or of the first input if the amounts are unknown.
+pub fn sender<N: BitcoinNetwork>(transaction: &BitcoinTransaction<N>) -> Option<BitcoinAddress<N>> {
+    let mut contributions: Vec<(&BitcoinAddress<N>, i64)> = vec![];
+    for outpoint in transaction.parameters.inputs.iter().map(|input| &input.outpoint) {
+        if let Some(address) = &outpoint.address {
+            let amount = outpoint.amount.map_or(0, |amount| amount.0);
+            match contributions.iter_mut().find(|(other, _)| *other == address) {
+                Some((_, total)) => *total += amount,
+                None => contributions.push((address, amount)),
+            }
+        }
+    }
+    // The first of the largest contributions
+    contributions
+        .iter()
+        .rev()
+        .max_by_key(|(_, total)| *total)
+        .map(|(address, _)| (*address).clone())
+}
+
+/// Returns the reference of the transaction, the address of the last output not paying the sender,
+/// or of the last output if all pay the sender.
+pub fn reference_address<N: BitcoinNetwork>(transaction: &BitcoinTransaction<N>) -> Option<BitcoinAddress<N>> {
+    let sender = sender(transaction);
+    let addresses: Vec<BitcoinAddress<N>> = transaction
+        .parameters
+        .outputs
+        .iter()
+        .filter(|output| !Script::from(&output.script_pub_key[..]).is_op_return())
+        .filter_map(|output| BitcoinAddress::from_script_pub_key(&output.script_pub_key).ok())
+        .collect();
+    addresses
+        .iter()
+        .rev()
+        .find(|address| Some(*address) != sender.as_ref())
+        .or(addresses.last())
+        .cloned()
+}
+
+/// Represents a builder for a Bitcoin transaction carrying an Omni Layer transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OmniTransactionBuilder<N: BitcoinNetwork> {
+    transaction: OmniTransaction,
+    inputs: Vec<BitcoinTransactionInputBuilder<N>>,
+    reference: Option<String>,
+    change_address: Option<String>,
+    fee: BitcoinAmount,
+}
+
+impl<N: BitcoinNetwork> OmniTransactionBuilder<N> {
+    /// Returns a builder of the Omni transaction without inputs, reference or fee.
+    pub fn new(transaction: OmniTransaction) -> Self {
+        Self {
+            transaction,
+            inputs: vec![],
+            reference: None,
+            change_address: None,
+            fee: BitcoinAmount::ZERO,
+        }
+    }
+
+    /// Adds an input, which needs an amount. The first input should be of the sender.
+    pub fn input(mut self, input: BitcoinTransactionInputBuilder<N>) -> Self {
+        self.inputs.push(input);
+        self
+    }
+
+    /// Sets the reference, the recipient of a send or the seller of a DEx accept,
+    /// which is paid the dust threshold.
+    pub fn reference(mut self, address: &str) -> Self {
+        self.reference = Some(address.to_string());
+        self
+    }
+
+    /// Sets the change address, by default the address of the first input.
+    pub fn change_address(mut self, address: &str) -> Self {
+        self.change_address = Some(address.to_string());
+        self
+    }
+
+    /// Sets the fee of the transaction.
+    pub fn fee(mut self, fee: BitcoinAmount) -> Self {
+        self.fee = fee;
+        self
+    }
+
+    /// Returns the unsigned transaction paying the change, the payload and the reference in that
+    /// order. Change below the dust threshold is added to the fee.
+    pub fn build(&self) -> Result<BitcoinTransaction<N>, TransactionError> {
+        if self.transaction.requires_reference() && self.reference.is_none() {
+            return Err(OmniError::MissingReference.into());
+        }
+
+        let mut total_input = 0;
+        let mut sender = None;
+        for builder in &self.inputs {
+            let input = builder.build()?;
+            total_input += input.outpoint.amount.ok_or(TransactionError::MissingField("amount"))?.0;
+            if sender.is_none() {
+                sender = input.outpoint.address;
+            }
+        }
+
+        let reference = match &self.reference {
+            Some(address) => {
+                let output = BitcoinTransactionOutput::new(&BitcoinAddress::<N>::from_str(address)?, BitcoinAmount::ZERO)?;
+                Some((address, default_dust_threshold::<N>(&output)))
+            }
+            None => None,
+        };
+
+        let change = total_input - reference.map_or(0, |(_, amount)| amount.0) - self.fee.0;
+        if change < 0 {
+            return Err(TransactionError::InsufficientFunds(
+                total_input.to_string(),
+                (total_input - change).to_string(),
+            ));
+        }
+
+        let mut builder = BitcoinTransactionBuilder::<N>::new();
+        for input in &self.inputs {
+            builder = builder.input(input.clone());
+        }
+        let change_address = match &self.change_address {
+            Some(address) => Some(BitcoinAddress::<N>::from_str(address)?),
+            None => sender,
+        };
+        match change_address {
+            Some(address) => {
+                let output = BitcoinTransactionOutput::new(&address, BitcoinAmount(change))?;
+                if change >= default_dust_threshold::<N>(&output).0 {
+                    builder = builder.output(&address.to_string(), BitcoinAmount(change));
+                }
+            }
+            None if change > 0 => return Err(OmniError::MissingChange.into()),
+            None => {}
+        }
+        builder = builder.omni_output(self.transaction.clone());
+        if let Some((address, amount)) = reference {
+            builder = builder.output(address, amount);
+        }
+        builder.build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::*;
+
+    type N = Mainnet;
+
+    const TRANSACTION_ID: &str = "9cb01977970f1322931532c9467e85eee9daf620b1886eb70d7c9b0e6c993bc4";
+    const SENDER: &str = "1Fyxts6r24DpEieygQiNnWxUdb18ANa5p7";
+    const RECIPIENT: &str = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4";
+
+    fn input(index: u32, address: &str, amount: i64) -> BitcoinTransactionInputBuilder<N> {
+        BitcoinTransactionInputBuilder::new(TRANSACTION_ID, index)
+            .address(address)
+            .amount(BitcoinAmount(amount))
+    }
+
+    #[test]
+    fn payloads() {
+        let transactions = [
+            (
+                OmniTransaction::SimpleSend {
+                    property_id: USDT_PROPERTY_ID,
+                    amount: 800_000_000,
+                },
+                "000000000000001f000000002faf0800",
+            ),
+            (
+                OmniTransaction::SendToOwners {
+                    property_id: 3,
+                    amount: 1_000,
+                    distribution_property_id: None,
+                },
+                "000000030000000300000000000003e8",
+            ),
+            (
+                OmniTransaction::SendToOwners {
+                    property_id: 3,
+                    amount: 1_000,
+                    distribution_property_id: Some(USDT_PROPERTY_ID),
+                },
+                "000100030000000300000000000003e80000001f",
+            ),
+            (OmniTransaction::SendAll { ecosystem: Ecosystem::Main }, "0000000401"),
+            (
+                OmniTransaction::DexSellOffer {
+                    property_id: OMNI_PROPERTY_ID,
+                    amount: 150_000_000,
+                    amount_desired: 100_000,
+                    payment_window: 10,
+                    min_fee: 1_000,
+                    action: DexAction::New,
+                },
+                "00010014000000010000000008f0d18000000000000186a00a00000000000003e801",
+            ),
+            (
+                OmniTransaction::DexAccept {
+                    property_id: OMNI_PROPERTY_ID,
+                    amount: 50_000_000,
+                },
+                "00000016000000010000000002faf080",
+            ),
+            (
+                OmniTransaction::MetaDexTrade {
+                    property_id: TEST_OMNI_PROPERTY_ID,
+                    amount: 1,
+                    desired_property_id: 2_147_483_651,
+                    desired_amount: 25,
+                },
+                "00000019000000020000000000000001800000030000000000000019",
+            ),
+            (
+                OmniTransaction::GrantTokens {
+                    property_id: 2_147_483_651,
+                    amount: 1_000,
+                    memo: "issue".into(),
+                },
+                "000000378000000300000000000003e8697373756500",
+            ),
+            (
+                OmniTransaction::RevokeTokens {
+                    property_id: 2_147_483_651,
+                    amount: 1_000,
+                    memo: String::new(),
+                },
+                "000000388000000300000000000003e800",
+            ),
+        ];
+        for (transaction, payload) in transactions {
+            let payload = hex::decode(payload).unwrap();
+            assert_eq!(payload, transaction.to_payload().unwrap());
+            assert_eq!(transaction, OmniTransaction::from_payload(&payload).unwrap());
+        }
+    }
+
+    #[test]
+    fn invalid_payloads() {
+        for payload in [
+            "0000000000000001",
+            "0000000403",
+            "0001001400000001000000000000000100000000000000010a000000000000000004",
+            "000200000000001f000000002faf0800",
+            "00000032",
+        ] {
+            assert!(OmniTransaction::from_payload(&hex::decode(payload).unwrap()).is_err());
+        }
+        let send = |amount| OmniTransaction::SimpleSend {
+            property_id: USDT_PROPERTY_ID,
+            amount,
+        };
+        assert_eq!(Err(OmniError::InvalidAmount(0)), send(0).to_payload());
+        assert_eq!(Err(OmniError::InvalidAmount(-1)), send(-1).to_payload());
+        let grant = OmniTransaction::GrantTokens {
+            property_id: 3,
+            amount: 1,
+            memo: "m".repeat(MAX_MEMO_LENGTH + 1),
+        };
+        assert_eq!(Err(OmniError::InvalidMemo(MAX_MEMO_LENGTH + 1)), grant.to_payload());
+        // A cancellation needs no amounts
+        let cancel = OmniTransaction::DexSellOffer {
+            property_id: OMNI_PROPERTY_ID,
+            amount: 0,
+            amount_desired: 0,
+            payment_window: 0,
+            min_fee: 0,
+            action: DexAction::Cancel,
+        };
+        assert_eq!(cancel, OmniTransaction::from_payload(&cancel.to_payload().unwrap()).unwrap());
+    }
+
+    #[test]
+    fn scripts() {
+        let send = OmniTransaction::SimpleSend {
+            property_id: USDT_PROPERTY_ID,
+            amount: 800_000_000,
+        };
+        let script = hex::decode("6a146f6d6e69000000000000001f000000002faf0800").unwrap();
+        assert_eq!(script, send.to_script().unwrap());
+        assert_eq!(send, OmniTransaction::from_script(&script).unwrap());
+
+        // The data may be split across pushes
+        let split = hex::decode("6a046f6d6e6910000000000000001f000000002faf0800").unwrap();
+        assert_eq!(send, OmniTransaction::from_script(&split).unwrap());
+
+        for script in [
+            "6a0b68656c6c6f20776f726c64",
+            "76a914000000000000000000000000000000000000000088ac",
+            "6a146f6d6e69000000000000001f000000002faf08",
+            "6a6f6d6e69000000000000001f000000002faf0800",
+        ] {
+            assert!(OmniTransaction::from_script(&hex::decode(script).unwrap()).is_err());
+        }
+    }
+
+    #[test]
+    fn build() {
+        let send = OmniTransaction::SimpleSend {
+            property_id: USDT_PROPERTY_ID,
+            amount: 800_000_000,
+        };
+        let transaction = OmniTransactionBuilder::<N>::new(send.clone())
+            .input(input(0, SENDER, 100_000))
+            .reference(RECIPIENT)
+            .fee(BitcoinAmount(1_000))
+            .build()
+            .unwrap();
+
+        // The change to the sender, the payload and the reference
+        let outputs = &transaction.parameters.outputs;
+        assert_eq!(3, outputs.len());
+        assert_eq!(BitcoinAmount(100_000 - 294 - 1_000), outputs[0].amount);
+        assert_eq!(send.to_output().unwrap(), outputs[1]);
+        assert_eq!(BitcoinAmount(294), outputs[2].amount);
+
+        assert_eq!(Some(send), OmniTransaction::from_transaction(&transaction).unwrap());
+        assert_eq!(Some(BitcoinAddress::from_str(SENDER).unwrap()), sender(&transaction));
+        assert_eq!(Some(BitcoinAddress::from_str(RECIPIENT).unwrap()), reference_address(&transaction));
+    }
+
+    #[test]
+    fn build_without_reference() {
+        let offer = OmniTransaction::DexSellOffer {
+            property_id: OMNI_PROPERTY_ID,
+            amount: 150_000_000,
+            amount_desired: 100_000,
+            payment_window: 10,
+            min_fee: 1_000,
+            action: DexAction::New,
+        };
+        let builder = OmniTransactionBuilder::<N>::new(offer.clone())
+            .input(input(0, SENDER, 10_000))
+            .input(input(1, RECIPIENT, 20_000))
+            .fee(BitcoinAmount(1_000));
+        let transaction = builder.clone().build().unwrap();
+        let outputs = &transaction.parameters.outputs;
+        assert_eq!(2, outputs.len());
+        assert_eq!(BitcoinAmount(29_000), outputs[0].amount);
+        assert_eq!(offer.to_output().unwrap(), outputs[1]);
+
+        // The sender contributes the most, and is the reference of a transaction only paying the sender
+        assert_eq!(Some(BitcoinAddress::from_str(RECIPIENT).unwrap()), sender(&transaction));
+        assert_eq!(Some(BitcoinAddress::from_str(SENDER).unwrap()), reference_address(&transaction));
+
+        // Change below the dust threshold is added to the fee
+        let transaction = builder.clone().fee(BitcoinAmount(29_500)).build().unwrap();
+        assert_eq!(vec![offer.to_output().unwrap()], transaction.parameters.outputs);
+
+        assert!(builder.fee(BitcoinAmount(30_001)).build().is_err());
+    }
+
+    #[test]
+    fn missing_reference() {
+        let send_all = OmniTransaction::SendAll { ecosystem: Ecosystem::Main };
+        let builder = OmniTransactionBuilder::<N>::new(send_all).input(input(0, SENDER, 100_000));
+        assert_eq!(
+            TransactionError::from(OmniError::MissingReference).to_string(),
+            builder.build().unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn transaction_without_omni() {
+        let transaction = BitcoinTransactionBuilder::<N>::new()
+            .input(input(0, SENDER, 100_000))
+            .output(RECIPIENT, BitcoinAmount(99_000))
+            .build()
+            .unwrap();
+        assert_eq!(None, OmniTransaction::from_transaction(&transaction).unwrap());
+    }
+}
@@ -0,0 +1,1847 @@
+//! # PSBT
+//!
+//! Partially signed Bitcoin transactions (BIP-174), exchanged between the creator, updaters,
+//! signers, combiner, finalizer and extractor of a transaction, e.g. hardware wallets and Bitcoin Core.
+//! Version 2 (BIP-370) stores the transaction fields in the maps, so that constructors can
+//! add inputs and outputs after creation, e.g. for coinjoins and payjoins.
+
+use crate::amount::BitcoinAmount;
+use crate::format::BitcoinFormat;
+use crate::multisig::MultisigScript;
+use crate::network::BitcoinNetwork;
+use crate::private_key::BitcoinPrivateKey;
+use crate::script::{Opcode, Script};
+use crate::sighash_cache::SignatureHashCache;
+use crate::transaction::{
+    create_p2sh_p2wsh_redeem_script, push_data, read_variable_length_integer, variable_length_integer,
+    BitcoinTransaction, BitcoinTransactionInput, BitcoinTransactionOutput, BitcoinTransactionParameters,
+    SignatureHash,
+};
+use chainlib_core::crypto::{hash160, sha256};
+use chainlib_core::no_std::*;
+use chainlib_core::{libsecp256k1, ChildIndex, DerivationPath, PrivateKey, Transaction, TransactionError};
+
+use core::{fmt, str::FromStr};
+use sha2::{Digest, Sha256};
+
+/// The magic bytes of a PSBT ("psbt" followed by a separator)
+const PSBT_MAGIC: [u8; 5] = [0x70, 0x73, 0x62, 0x74, 0xff];
+
+const PSBT_GLOBAL_UNSIGNED_TX: u8 = 0x00;
+const PSBT_GLOBAL_XPUB: u8 = 0x01;
+const PSBT_GLOBAL_TX_VERSION: u8 = 0x02;
+const PSBT_GLOBAL_FALLBACK_LOCKTIME: u8 = 0x03;
+const PSBT_GLOBAL_INPUT_COUNT: u8 = 0x04;
+const PSBT_GLOBAL_OUTPUT_COUNT: u8 = 0x05;
+const PSBT_GLOBAL_TX_MODIFIABLE: u8 = 0x06;
+const PSBT_GLOBAL_VERSION: u8 = 0xfb;
+
+const PSBT_IN_NON_WITNESS_UTXO: u8 = 0x00;
+const PSBT_IN_WITNESS_UTXO: u8 = 0x01;
+const PSBT_IN_PARTIAL_SIG: u8 = 0x02;
+const PSBT_IN_SIGHASH_TYPE: u8 = 0x03;
+const PSBT_IN_REDEEM_SCRIPT: u8 = 0x04;
+const PSBT_IN_WITNESS_SCRIPT: u8 = 0x05;
+const PSBT_IN_BIP32_DERIVATION: u8 = 0x06;
+const PSBT_IN_FINAL_SCRIPTSIG: u8 = 0x07;
+const PSBT_IN_FINAL_SCRIPTWITNESS: u8 = 0x08;
+const PSBT_IN_PREVIOUS_TXID: u8 = 0x0e;
+const PSBT_IN_OUTPUT_INDEX: u8 = 0x0f;
+const PSBT_IN_SEQUENCE: u8 = 0x10;
+const PSBT_IN_REQUIRED_TIME_LOCKTIME: u8 = 0x11;
+const PSBT_IN_REQUIRED_HEIGHT_LOCKTIME: u8 = 0x12;
+
+const PSBT_OUT_REDEEM_SCRIPT: u8 = 0x00;
+const PSBT_OUT_WITNESS_SCRIPT: u8 = 0x01;
+const PSBT_OUT_BIP32_DERIVATION: u8 = 0x02;
+const PSBT_OUT_AMOUNT: u8 = 0x03;
+const PSBT_OUT_SCRIPT: u8 = 0x04;
+
+/// The flag of a version 2 PSBT allowing inputs to be added
+pub const PSBT_TX_MODIFIABLE_INPUTS: u8 = 0x01;
+/// The flag of a version 2 PSBT allowing outputs to be added
+pub const PSBT_TX_MODIFIABLE_OUTPUTS: u8 = 0x02;
+/// The flag of a version 2 PSBT signed with SIGHASH_SINGLE, which pairs inputs and outputs by index
+pub const PSBT_TX_HAS_SIGHASH_SINGLE: u8 = 0x04;
+
+/// The lock times below are block heights, the others are Unix timestamps
+const LOCK_TIME_THRESHOLD: u32 = 500_000_000;
+
+/// The length of a serialized BIP-32 extended public key
+const EXTENDED_PUBLIC_KEY_LEN: usize = 78;
+
+/// A serialized key and its value
+type KeyValuePair = (Vec<u8>, Vec<u8>);
+
+/// The final script sig and witness stack of an input
+type FinalScripts = (Option<Vec<u8>>, Option<Vec<Vec<u8>>>);
+
+/// The transaction fields of the input and output maps of a version 2 PSBT
+#[derive(Default)]
+struct TransactionFields {
+    previous_txid: Option<Vec<u8>>,
+    output_index: Option<u32>,
+    sequence: Option<u32>,
+    amount: Option<i64>,
+    script: Option<Vec<u8>>,
+}
+
+/// Represents the origin of a public key, i.e. the fingerprint of its master key and its derivation path
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct KeySource {
+    /// The fingerprint of the master key (4 bytes)
+    pub fingerprint: [u8; 4],
+    /// The derivation path from the master key
+    pub path: DerivationPath,
+}
+
+impl KeySource {
+    /// Returns a key source given the fingerprint of the master key and the derivation path.
+    pub fn new(fingerprint: [u8; 4], path: DerivationPath) -> Self {
+        Self { fingerprint, path }
+    }
+
+    fn serialize(&self) -> Vec<u8> {
+        let mut source = self.fingerprint.to_vec();
+        for index in self.path.indexes() {
+            source.extend(&index.to_child_number().to_le_bytes());
+        }
+        source
+    }
+
+    fn deserialize(source: &[u8]) -> Result<Self, TransactionError> {
+        let indexes = source.get(4..).unwrap_or_default().chunks_exact(4);
+        if source.len() < 4 || !indexes.remainder().is_empty() {
+            return Err(TransactionError::InvalidPsbt(format!("invalid key source length: {}", source.len())));
+        }
+
+        let mut fingerprint = [0u8; 4];
+        fingerprint.copy_from_slice(&source[..4]);
+        let path = indexes
+            .map(|index| ChildIndex::from_child_number(u32::from_le_bytes([index[0], index[1], index[2], index[3]])))
+            .collect();
+
+        Ok(Self {
+            fingerprint,
+            path: DerivationPath::new(path),
+        })
+    }
+}
+
+/// Represents the input map of a PSBT
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PsbtInput {
+    /// The serialized transaction spent by the input, required for non-SegWit inputs
+    pub non_witness_utxo: Option<Vec<u8>>,
+    /// The output spent by a SegWit input
+    pub witness_utxo: Option<BitcoinTransactionOutput>,
+    /// The signatures (DER with the sighash byte) by public key
+    pub partial_sigs: BTreeMap<Vec<u8>, Vec<u8>>,
+    /// The signature hash type signers must use
+    pub sighash_type: Option<u32>,
+    /// The redeem script of a P2SH input
+    pub redeem_script: Option<Vec<u8>>,
+    /// The witness script of a P2WSH input
+    pub witness_script: Option<Vec<u8>>,
+    /// The key sources by public key
+    pub bip32_derivation: BTreeMap<Vec<u8>, KeySource>,
+    /// The finalized script sig
+    pub final_script_sig: Option<Vec<u8>>,
+    /// The finalized witness stack
+    pub final_script_witness: Option<Vec<Vec<u8>>>,
+    /// The minimum Unix timestamp lock time the input requires (version 2)
+    pub required_time_lock_time: Option<u32>,
+    /// The minimum block height lock time the input requires (version 2)
+    pub required_height_lock_time: Option<u32>,
+    /// The unknown and proprietary key-value pairs, kept as is
+    pub unknown: BTreeMap<Vec<u8>, Vec<u8>>,
+}
+
+impl PsbtInput {
+    /// Returns `true` if the input has a final script sig or witness.
+    pub fn is_finalized(&self) -> bool {
+        self.final_script_sig.is_some() || self.final_script_witness.is_some()
+    }
+
+    fn read(reader: &mut &[u8], version: u32) -> Result<(Self, TransactionFields), TransactionError> {
+        let mut input = Self::default();
+        let mut fields = TransactionFields::default();
+        for (key, value) in read_map(reader)? {
+            match (key[0], key.len()) {
+                (PSBT_IN_NON_WITNESS_UTXO, 1) => input.non_witness_utxo = Some(value),
+                (PSBT_IN_WITNESS_UTXO, 1) => input.witness_utxo = Some(BitcoinTransactionOutput::read(&mut &value[..])?),
+                (PSBT_IN_PARTIAL_SIG, _) => {
+                    check_public_key(&key[1..])?;
+                    input.partial_sigs.insert(key[1..].to_vec(), value);
+                }
+                (PSBT_IN_SIGHASH_TYPE, 1) => input.sighash_type = Some(read_u32(&value)?),
+                (PSBT_IN_REDEEM_SCRIPT, 1) => input.redeem_script = Some(value),
+                (PSBT_IN_WITNESS_SCRIPT, 1) => input.witness_script = Some(value),
+                (PSBT_IN_BIP32_DERIVATION, _) => {
+                    check_public_key(&key[1..])?;
+                    input.bip32_derivation.insert(key[1..].to_vec(), KeySource::deserialize(&value)?);
+                }
+                (PSBT_IN_FINAL_SCRIPTSIG, 1) => input.final_script_sig = Some(value),
+                (PSBT_IN_FINAL_SCRIPTWITNESS, 1) => input.final_script_witness = Some(read_witness(&value)?),
+                (PSBT_IN_PREVIOUS_TXID, 1) if version == 2 && value.len() == 32 => fields.previous_txid = Some(value),
+                (PSBT_IN_OUTPUT_INDEX, 1) if version == 2 => fields.output_index = Some(read_u32(&value)?),
+                (PSBT_IN_SEQUENCE, 1) if version == 2 => fields.sequence = Some(read_u32(&value)?),
+                (PSBT_IN_REQUIRED_TIME_LOCKTIME, 1) if version == 2 => match read_u32(&value)? {
+                    lock_time if lock_time >= LOCK_TIME_THRESHOLD => input.required_time_lock_time = Some(lock_time),
+                    lock_time => return Err(TransactionError::InvalidPsbt(format!("invalid time lock time: {}", lock_time))),
+                },
+                (PSBT_IN_REQUIRED_HEIGHT_LOCKTIME, 1) if version == 2 => match read_u32(&value)? {
+                    lock_time if lock_time > 0 && lock_time < LOCK_TIME_THRESHOLD => {
+                        input.required_height_lock_time = Some(lock_time)
+                    }
+                    lock_time => return Err(TransactionError::InvalidPsbt(format!("invalid height lock time: {}", lock_time))),
+                },
+                (PSBT_IN_NON_WITNESS_UTXO..=PSBT_IN_FINAL_SCRIPTWITNESS, _)
+                | (PSBT_IN_PREVIOUS_TXID..=PSBT_IN_REQUIRED_HEIGHT_LOCKTIME, _) => {
+                    return Err(TransactionError::InvalidPsbt(format!("invalid input key: {}", hex::encode(&key))))
+                }
+                _ => {
+                    input.unknown.insert(key, value);
+                }
+            }
+        }
+        Ok((input, fields))
+    }
+
+    /// Writes the input map, with the fields of the transaction input for version 2.
+    fn write<N: BitcoinNetwork>(
+        &self,
+        psbt: &mut Vec<u8>,
+        input: Option<&BitcoinTransactionInput<N>>,
+    ) -> Result<(), TransactionError> {
+        if let Some(transaction) = &self.non_witness_utxo {
+            write_pair(psbt, PSBT_IN_NON_WITNESS_UTXO, &[], transaction)?;
+        }
+        if let Some(output) = &self.witness_utxo {
+            write_pair(psbt, PSBT_IN_WITNESS_UTXO, &[], &output.serialize()?)?;
+        }
+        for (public_key, signature) in &self.partial_sigs {
+            write_pair(psbt, PSBT_IN_PARTIAL_SIG, public_key, signature)?;
+        }
+        if let Some(sighash_type) = self.sighash_type {
+            write_pair(psbt, PSBT_IN_SIGHASH_TYPE, &[], &sighash_type.to_le_bytes())?;
+        }
+        if let Some(redeem_script) = &self.redeem_script {
+            write_pair(psbt, PSBT_IN_REDEEM_SCRIPT, &[], redeem_script)?;
+        }
+        if let Some(witness_script) = &self.witness_script {
+            write_pair(psbt, PSBT_IN_WITNESS_SCRIPT, &[], witness_script)?;
+        }
+        for (public_key, source) in &self.bip32_derivation {
+            write_pair(psbt, PSBT_IN_BIP32_DERIVATION, public_key, &source.serialize())?;
+        }
+        if let Some(script_sig) = &self.final_script_sig {
+            write_pair(psbt, PSBT_IN_FINAL_SCRIPTSIG, &[], script_sig)?;
+        }
+        if let Some(witness) = &self.final_script_witness {
+            write_pair(psbt, PSBT_IN_FINAL_SCRIPTWITNESS, &[], &write_witness(witness)?)?;
+        }
+        match input {
+            Some(input) => {
+                write_pair(psbt, PSBT_IN_PREVIOUS_TXID, &[], &input.outpoint.reverse_transaction_id)?;
+                write_pair(psbt, PSBT_IN_OUTPUT_INDEX, &[], &input.outpoint.index.to_le_bytes())?;
+                // An absent sequence is final
+                if input.sequence != BitcoinTransactionInput::<N>::DEFAULT_SEQUENCE {
+                    write_pair(psbt, PSBT_IN_SEQUENCE, &[], &input.sequence)?;
+                }
+                if let Some(lock_time) = self.required_time_lock_time {
+                    write_pair(psbt, PSBT_IN_REQUIRED_TIME_LOCKTIME, &[], &lock_time.to_le_bytes())?;
+                }
+                if let Some(lock_time) = self.required_height_lock_time {
+                    write_pair(psbt, PSBT_IN_REQUIRED_HEIGHT_LOCKTIME, &[], &lock_time.to_le_bytes())?;
+                }
+            }
+            None if self.has_required_lock_time() => {
+                return Err(TransactionError::InvalidPsbt("required lock times need version 2".into()))
+            }
+            None => {}
+        }
+        for (key, value) in &self.unknown {
+            write_raw_pair(psbt, key, value)?;
+        }
+        psbt.push(0x00);
+        Ok(())
+    }
+
+    fn combine(&mut self, other: &Self) {
+        combine_option(&mut self.non_witness_utxo, &other.non_witness_utxo);
+        combine_option(&mut self.witness_utxo, &other.witness_utxo);
+        combine_map(&mut self.partial_sigs, &other.partial_sigs);
+        combine_option(&mut self.sighash_type, &other.sighash_type);
+        combine_option(&mut self.redeem_script, &other.redeem_script);
+        combine_option(&mut self.witness_script, &other.witness_script);
+        combine_map(&mut self.bip32_derivation, &other.bip32_derivation);
+        combine_option(&mut self.final_script_sig, &other.final_script_sig);
+        combine_option(&mut self.final_script_witness, &other.final_script_witness);
+        combine_option(&mut self.required_time_lock_time, &other.required_time_lock_time);
+        combine_option(&mut self.required_height_lock_time, &other.required_height_lock_time);
+        combine_map(&mut self.unknown, &other.unknown);
+
+        if self.is_finalized() {
+            self.clear_signing_data();
+        }
+    }
+
+    fn has_required_lock_time(&self) -> bool {
+        self.required_time_lock_time.is_some() || self.required_height_lock_time.is_some()
+    }
+
+    /// Removes the data only needed until the input is finalized.
+    fn clear_signing_data(&mut self) {
+        self.partial_sigs.clear();
+        self.sighash_type = None;
+        self.redeem_script = None;
+        self.witness_script = None;
+        self.bip32_derivation.clear();
+    }
+}
+
+/// Represents the output map of a PSBT
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PsbtOutput {
+    /// The redeem script of a P2SH output
+    pub redeem_script: Option<Vec<u8>>,
+    /// The witness script of a P2WSH output
+    pub witness_script: Option<Vec<u8>>,
+    /// The key sources by public key, e.g. to verify a change output
+    pub bip32_derivation: BTreeMap<Vec<u8>, KeySource>,
+    /// The unknown and proprietary key-value pairs, kept as is
+    pub unknown: BTreeMap<Vec<u8>, Vec<u8>>,
+}
+
+impl PsbtOutput {
+    fn read(reader: &mut &[u8], version: u32) -> Result<(Self, TransactionFields), TransactionError> {
+        let mut output = Self::default();
+        let mut fields = TransactionFields::default();
+        for (key, value) in read_map(reader)? {
+            match (key[0], key.len()) {
+                (PSBT_OUT_REDEEM_SCRIPT, 1) => output.redeem_script = Some(value),
+                (PSBT_OUT_WITNESS_SCRIPT, 1) => output.witness_script = Some(value),
+                (PSBT_OUT_BIP32_DERIVATION, _) => {
+                    check_public_key(&key[1..])?;
+                    output.bip32_derivation.insert(key[1..].to_vec(), KeySource::deserialize(&value)?);
+                }
+                (PSBT_OUT_AMOUNT, 1) if version == 2 && value.len() == 8 => {
+                    let mut amount = [0u8; 8];
+                    amount.copy_from_slice(&value);
+                    fields.amount = Some(i64::from_le_bytes(amount));
+                }
+                (PSBT_OUT_SCRIPT, 1) if version == 2 => fields.script = Some(value),
+                (PSBT_OUT_REDEEM_SCRIPT..=PSBT_OUT_WITNESS_SCRIPT, _) | (PSBT_OUT_AMOUNT..=PSBT_OUT_SCRIPT, _) => {
+                    return Err(TransactionError::InvalidPsbt(format!("invalid output key: {}", hex::encode(&key))))
+                }
+                _ => {
+                    output.unknown.insert(key, value);
+                }
+            }
+        }
+        Ok((output, fields))
+    }
+
+    /// Writes the output map, with the fields of the transaction output for version 2.
+    fn write(&self, psbt: &mut Vec<u8>, output: Option<&BitcoinTransactionOutput>) -> Result<(), TransactionError> {
+        if let Some(redeem_script) = &self.redeem_script {
+            write_pair(psbt, PSBT_OUT_REDEEM_SCRIPT, &[], redeem_script)?;
+        }
+        if let Some(witness_script) = &self.witness_script {
+            write_pair(psbt, PSBT_OUT_WITNESS_SCRIPT, &[], witness_script)?;
+        }
+        for (public_key, source) in &self.bip32_derivation {
+            write_pair(psbt, PSBT_OUT_BIP32_DERIVATION, public_key, &source.serialize())?;
+        }
+        if let Some(output) = output {
+            write_pair(psbt, PSBT_OUT_AMOUNT, &[], &output.amount.0.to_le_bytes())?;
+            write_pair(psbt, PSBT_OUT_SCRIPT, &[], &output.script_pub_key)?;
+        }
+        for (key, value) in &self.unknown {
+            write_raw_pair(psbt, key, value)?;
+        }
+        psbt.push(0x00);
+        Ok(())
+    }
+
+    fn combine(&mut self, other: &Self) {
+        combine_option(&mut self.redeem_script, &other.redeem_script);
+        combine_option(&mut self.witness_script, &other.witness_script);
+        combine_map(&mut self.bip32_derivation, &other.bip32_derivation);
+        combine_map(&mut self.unknown, &other.unknown);
+    }
+}
+
+/// Represents a partially signed Bitcoin transaction (BIP-174 version 0 or BIP-370 version 2)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BitcoinPsbt<N: BitcoinNetwork> {
+    /// The unsigned transaction, without script sigs or witnesses.
+    /// In version 2, its lock time follows from the fallback and required lock times.
+    pub unsigned_transaction: BitcoinTransactionParameters<N>,
+    /// The key sources by serialized extended public key (78 bytes)
+    pub xpubs: BTreeMap<Vec<u8>, KeySource>,
+    /// The PSBT version, 0 or 2
+    pub version: u32,
+    /// The lock time used when no input requires one (version 2)
+    pub fallback_lock_time: Option<u32>,
+    /// The `PSBT_TX_MODIFIABLE_*` flags (version 2)
+    pub tx_modifiable: u8,
+    /// The unknown and proprietary global key-value pairs, kept as is
+    pub unknown: BTreeMap<Vec<u8>, Vec<u8>>,
+    /// The input maps, one per transaction input
+    pub inputs: Vec<PsbtInput>,
+    /// The output maps, one per transaction output
+    pub outputs: Vec<PsbtOutput>,
+}
+
+impl<N: BitcoinNetwork> BitcoinPsbt<N> {
+    /// Returns a version 0 PSBT of the given transaction parameters (creator). Script sigs and witnesses are removed,
+    /// and the witness UTXOs, redeem scripts and witness scripts known by the outpoints are added.
+    /// Non-SegWit inputs still need their `non_witness_utxo`.
+    pub fn from_parameters(parameters: &BitcoinTransactionParameters<N>) -> Result<Self, TransactionError> {
+        let mut unsigned_transaction = parameters.clone();
+        unsigned_transaction.segwit_flag = false;
+
+        let inputs = unsigned_transaction.inputs.iter_mut().map(Self::unsigned_input).collect();
+
+        Ok(Self {
+            outputs: vec![PsbtOutput::default(); unsigned_transaction.outputs.len()],
+            unsigned_transaction,
+            xpubs: BTreeMap::new(),
+            version: 0,
+            fallback_lock_time: None,
+            tx_modifiable: 0,
+            unknown: BTreeMap::new(),
+            inputs,
+        })
+    }
+
+    /// Returns a PSBT of the given transaction (creator).
+    pub fn from_transaction(transaction: &BitcoinTransaction<N>) -> Result<Self, TransactionError> {
+        Self::from_parameters(&transaction.parameters)
+    }
+
+    /// Converts the PSBT to version 0 or 2. The lock time of a version 2 PSBT becomes its fallback lock time,
+    /// and the required lock times of a version 0 PSBT are replaced by its lock time.
+    pub fn set_version(&mut self, version: u32) -> Result<(), TransactionError> {
+        match (self.version, version) {
+            (0, 0) | (2, 2) => {}
+            (0, 2) => {
+                self.fallback_lock_time = Some(self.unsigned_transaction.lock_time).filter(|lock_time| *lock_time != 0);
+            }
+            (2, 0) => {
+                self.unsigned_transaction.lock_time = self.lock_time()?;
+                self.fallback_lock_time = None;
+                self.tx_modifiable = 0;
+                for input in &mut self.inputs {
+                    input.required_time_lock_time = None;
+                    input.required_height_lock_time = None;
+                }
+            }
+            (_, version) => return Err(TransactionError::InvalidPsbt(format!("unsupported version: {}", version))),
+        }
+        self.version = version;
+        Ok(())
+    }
+
+    /// Returns the lock time of the transaction. In version 2, it is the maximum required height lock time,
+    /// or time lock time if an input only supports those, and the fallback lock time if no input requires one.
+    pub fn lock_time(&self) -> Result<u32, TransactionError> {
+        if self.version == 0 {
+            return Ok(self.unsigned_transaction.lock_time);
+        }
+
+        let requirements: Vec<&PsbtInput> = self.inputs.iter().filter(|input| input.has_required_lock_time()).collect();
+        if requirements.is_empty() {
+            return Ok(self.fallback_lock_time.unwrap_or(0));
+        }
+        if let Some(heights) = requirements.iter().map(|input| input.required_height_lock_time).collect::<Option<Vec<u32>>>() {
+            return Ok(heights.into_iter().max().unwrap_or(0));
+        }
+        match requirements.iter().map(|input| input.required_time_lock_time).collect::<Option<Vec<u32>>>() {
+            Some(times) => Ok(times.into_iter().max().unwrap_or(0)),
+            None => Err(TransactionError::InvalidPsbt("the inputs require incompatible lock times".into())),
+        }
+    }
+
+    /// Adds an input to a version 2 PSBT with modifiable inputs (constructor).
+    /// The input cannot change the lock time once another input is signed.
+    pub fn add_input(&mut self, mut input: BitcoinTransactionInput<N>, mut psbt_input: PsbtInput) -> Result<(), TransactionError> {
+        if self.version != 2 || self.tx_modifiable & PSBT_TX_MODIFIABLE_INPUTS == 0 {
+            return Err(TransactionError::InvalidPsbt("the inputs are not modifiable".into()));
+        }
+
+        let lock_time = self.lock_time()?;
+        let defaults = Self::unsigned_input(&mut input);
+        combine_option(&mut psbt_input.witness_utxo, &defaults.witness_utxo);
+        combine_option(&mut psbt_input.redeem_script, &defaults.redeem_script);
+        combine_option(&mut psbt_input.witness_script, &defaults.witness_script);
+        self.unsigned_transaction.inputs.push(input);
+        self.inputs.push(psbt_input);
+
+        let is_signed = self.inputs.iter().any(|input| !input.partial_sigs.is_empty() || input.is_finalized());
+        match self.lock_time() {
+            Ok(new_lock_time) if new_lock_time == lock_time || !is_signed => {
+                self.unsigned_transaction.lock_time = new_lock_time;
+                Ok(())
+            }
+            result => {
+                self.unsigned_transaction.inputs.pop();
+                self.inputs.pop();
+                result?;
+                Err(TransactionError::InvalidPsbt("the input changes the lock time of signed inputs".into()))
+            }
+        }
+    }
+
+    /// Adds an output to a version 2 PSBT with modifiable outputs (constructor).
+    pub fn add_output(&mut self, output: BitcoinTransactionOutput, psbt_output: PsbtOutput) -> Result<(), TransactionError> {
+        if self.version != 2 || self.tx_modifiable & PSBT_TX_MODIFIABLE_OUTPUTS == 0 {
+            return Err(TransactionError::InvalidPsbt("the outputs are not modifiable".into()));
+        }
+        self.unsigned_transaction.outputs.push(output);
+        self.outputs.push(psbt_output);
+        Ok(())
+    }
+
+    /// Returns a PSBT given its binary serialization.
+    pub fn from_bytes(psbt: &[u8]) -> Result<Self, TransactionError> {
+        if !psbt.starts_with(&PSBT_MAGIC) {
+            return Err(TransactionError::InvalidPsbt("missing magic bytes".into()));
+        }
+        let mut reader = &psbt[PSBT_MAGIC.len()..];
+
+        let mut unsigned_transaction = None;
+        let mut xpubs = BTreeMap::new();
+        let mut version = 0;
+        let mut transaction_version = None;
+        let mut fallback_lock_time = None;
+        let mut input_count = None;
+        let mut output_count = None;
+        let mut tx_modifiable = None;
+        let mut unknown = BTreeMap::new();
+        for (key, value) in read_map(&mut reader)? {
+            match (key[0], key.len()) {
+                (PSBT_GLOBAL_UNSIGNED_TX, 1) => unsigned_transaction = Some(read_unsigned_transaction::<N>(&value)?),
+                (PSBT_GLOBAL_XPUB, len) if len == EXTENDED_PUBLIC_KEY_LEN + 1 => {
+                    xpubs.insert(key[1..].to_vec(), KeySource::deserialize(&value)?);
+                }
+                (PSBT_GLOBAL_TX_VERSION, 1) => transaction_version = Some(read_u32(&value)?),
+                (PSBT_GLOBAL_FALLBACK_LOCKTIME, 1) => fallback_lock_time = Some(read_u32(&value)?),
+                (PSBT_GLOBAL_INPUT_COUNT, 1) => input_count = Some(read_count(&value)?),
+                (PSBT_GLOBAL_OUTPUT_COUNT, 1) => output_count = Some(read_count(&value)?),
+                (PSBT_GLOBAL_TX_MODIFIABLE, 1) if value.len() == 1 => tx_modifiable = Some(value[0]),
+                (PSBT_GLOBAL_VERSION, 1) => version = read_u32(&value)?,
+                (PSBT_GLOBAL_UNSIGNED_TX..=PSBT_GLOBAL_TX_MODIFIABLE, _) | (PSBT_GLOBAL_VERSION, _) => {
+                    return Err(TransactionError::InvalidPsbt(format!("invalid global key: {}", hex::encode(&key))))
+                }
+                _ => {
+                    unknown.insert(key, value);
+                }
+            }
+        }
+
+        let has_version_2_fields = transaction_version.is_some()
+            || fallback_lock_time.is_some()
+            || input_count.is_some()
+            || output_count.is_some()
+            || tx_modifiable.is_some();
+        let (mut unsigned_transaction, input_count, output_count) = match (version, unsigned_transaction) {
+            (0, Some(_)) if has_version_2_fields => {
+                return Err(TransactionError::InvalidPsbt("version 0 has version 2 global fields".into()))
+            }
+            (0, Some(transaction)) => {
+                let (input_count, output_count) = (transaction.inputs.len(), transaction.outputs.len());
+                (transaction, input_count, output_count)
+            }
+            (0, None) => return Err(TransactionError::InvalidPsbt("missing unsigned transaction".into())),
+            (2, Some(_)) => return Err(TransactionError::InvalidPsbt("version 2 has an unsigned transaction".into())),
+            (2, None) => match (transaction_version, input_count, output_count) {
+                (Some(transaction_version), Some(input_count), Some(output_count)) => {
+                    let transaction = BitcoinTransactionParameters {
+                        version: transaction_version,
+                        inputs: vec![],
+                        outputs: vec![],
+                        lock_time: 0,
+                        segwit_flag: false,
+                    };
+                    (transaction, input_count, output_count)
+                }
+                _ => return Err(TransactionError::InvalidPsbt("missing version 2 global fields".into())),
+            },
+            (version, _) => return Err(TransactionError::InvalidPsbt(format!("unsupported version: {}", version))),
+        };
+
+        let mut inputs = vec![];
+        for vin in 0..input_count {
+            let (input, fields) = PsbtInput::read(&mut reader, version)?;
+            if version == 2 {
+                let (mut transaction_id, index) = match (fields.previous_txid, fields.output_index) {
+                    (Some(transaction_id), Some(index)) => (transaction_id, index),
+                    _ => return Err(TransactionError::InvalidPsbt(format!("input {} is missing its outpoint", vin))),
+                };
+                // Returned to the byte order of the transaction by the input
+                transaction_id.reverse();
+                let sequence = fields.sequence.map(|sequence| sequence.to_le_bytes().to_vec());
+                unsigned_transaction.inputs.push(BitcoinTransactionInput::new(
+                    transaction_id,
+                    index,
+                    None,
+                    None,
+                    None,
+                    None,
+                    sequence,
+                    SignatureHash::SIGHASH_ALL,
+                )?);
+            }
+            inputs.push(input);
+        }
+        let mut outputs = vec![];
+        for vout in 0..output_count {
+            let (output, fields) = PsbtOutput::read(&mut reader, version)?;
+            if version == 2 {
+                match (fields.amount, fields.script) {
+                    (Some(amount), Some(script_pub_key)) => unsigned_transaction.outputs.push(BitcoinTransactionOutput {
+                        amount: BitcoinAmount::from_satoshi_for::<N>(amount)?,
+                        script_pub_key,
+                    }),
+                    _ => return Err(TransactionError::InvalidPsbt(format!("output {} is missing its amount or script", vout))),
+                }
+            }
+            outputs.push(output);
+        }
+        if !reader.is_empty() {
+            return Err(TransactionError::InvalidPsbt(format!("{} trailing bytes", reader.len())));
+        }
+
+        let mut psbt = Self {
+            unsigned_transaction,
+            xpubs,
+            version,
+            fallback_lock_time,
+            tx_modifiable: tx_modifiable.unwrap_or(0),
+            unknown,
+            inputs,
+            outputs,
+        };
+        psbt.unsigned_transaction.lock_time = psbt.lock_time()?;
+        Ok(psbt)
+    }
+
+    /// Returns the binary serialization of the PSBT.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, TransactionError> {
+        if self.inputs.len() != self.unsigned_transaction.inputs.len()
+            || self.outputs.len() != self.unsigned_transaction.outputs.len()
+        {
+            return Err(TransactionError::InvalidPsbt("the maps do not match the transaction".into()));
+        }
+
+        let mut psbt = PSBT_MAGIC.to_vec();
+        match self.version {
+            0 => write_pair(&mut psbt, PSBT_GLOBAL_UNSIGNED_TX, &[], &self.unsigned_transaction_bytes()?)?,
+            2 => {}
+            version => return Err(TransactionError::InvalidPsbt(format!("unsupported version: {}", version))),
+        }
+        for (xpub, source) in &self.xpubs {
+            write_pair(&mut psbt, PSBT_GLOBAL_XPUB, xpub, &source.serialize())?;
+        }
+        if self.version == 2 {
+            let transaction = &self.unsigned_transaction;
+            write_pair(&mut psbt, PSBT_GLOBAL_TX_VERSION, &[], &transaction.version.to_le_bytes())?;
+            if let Some(lock_time) = self.fallback_lock_time {
+                write_pair(&mut psbt, PSBT_GLOBAL_FALLBACK_LOCKTIME, &[], &lock_time.to_le_bytes())?;
+            }
+            write_pair(&mut psbt, PSBT_GLOBAL_INPUT_COUNT, &[], &variable_length_integer(transaction.inputs.len() as u64)?)?;
+            write_pair(&mut psbt, PSBT_GLOBAL_OUTPUT_COUNT, &[], &variable_length_integer(transaction.outputs.len() as u64)?)?;
+            if self.tx_modifiable != 0 {
+                write_pair(&mut psbt, PSBT_GLOBAL_TX_MODIFIABLE, &[], &[self.tx_modifiable])?;
+            }
+            write_pair(&mut psbt, PSBT_GLOBAL_VERSION, &[], &self.version.to_le_bytes())?;
+        }
+        for (key, value) in &self.unknown {
+            write_raw_pair(&mut psbt, key, value)?;
+        }
+        psbt.push(0x00);
+
+        let is_version_2 = self.version == 2;
+        for (input, transaction_input) in self.inputs.iter().zip(&self.unsigned_transaction.inputs) {
+            input.write(&mut psbt, Some(transaction_input).filter(|_| is_version_2))?;
+        }
+        for (output, transaction_output) in self.outputs.iter().zip(&self.unsigned_transaction.outputs) {
+            output.write(&mut psbt, Some(transaction_output).filter(|_| is_version_2))?;
+        }
+        Ok(psbt)
+    }
+
+    /// Returns a PSBT given its base64 encoding.
+    pub fn from_base64(psbt: &str) -> Result<Self, TransactionError> {
+        Self::from_bytes(&base64::decode(psbt.trim())?)
+    }
+
+    /// Returns the base64 encoding of the PSBT.
+    pub fn to_base64(&self) -> Result<String, TransactionError> {
+        Ok(base64::encode(self.to_bytes()?))
+    }
+
+    /// Signs every input spendable by the given private key (signer) and returns the number of signed inputs.
+    /// The public key may appear in either encoding, but SegWit inputs must commit to the compressed one.
+    /// Inputs without a UTXO or already finalized are skipped.
+    /// In version 2, the inputs and outputs the signatures commit to stop being modifiable.
+    pub fn sign(&mut self, private_key: &BitcoinPrivateKey<N>) -> Result<usize, TransactionError> {
+        let public_key = private_key.to_public_key().to_secp256k1_public_key();
+        let public_keys = [public_key.serialize_compressed().to_vec(), public_key.serialize().to_vec()];
+        let transaction = BitcoinTransaction::new(&self.transaction_parameters()?)?;
+        let cache = SignatureHashCache::new(&transaction)?;
+
+        let mut signed = 0;
+        for vin in 0..self.inputs.len() {
+            let input = &self.inputs[vin];
+            if input.is_finalized() || (input.non_witness_utxo.is_none() && input.witness_utxo.is_none()) {
+                continue;
+            }
+
+            let spent_output = self.spent_output(vin)?;
+            let (script_code, is_segwit) = self.script_code(vin, &spent_output.script_pub_key)?;
+            if !is_segwit && input.non_witness_utxo.is_none() {
+                return Err(TransactionError::InvalidPsbt(format!("input {} is missing its non-witness UTXO", vin)));
+            }
+            // SegWit only commits to compressed public keys
+            let public_key = match public_keys
+                .iter()
+                .take(if is_segwit { 1 } else { 2 })
+                .find(|public_key| contains(&script_code, public_key) || contains(&script_code, &hash160(public_key)))
+            {
+                Some(public_key) => public_key,
+                None => continue,
+            };
+
+            let sighash = match input.sighash_type {
+                Some(sighash_type) => read_sighash_type(sighash_type)?,
+                None => SignatureHash::SIGHASH_ALL,
+            };
+            let preimage = match is_segwit {
+                true => transaction.witness_v0_signature_message(&cache, vin, &script_code, spent_output.amount, sighash as u32)?,
+                false => transaction.legacy_hash_preimage(vin, &script_code, sighash)?,
+            };
+            let hash = Sha256::digest(Sha256::digest(&preimage));
+
+            let (signature, _) = libsecp256k1::sign(
+                &libsecp256k1::Message::parse_slice(&hash)?,
+                &private_key.to_secp256k1_secret_key(),
+            );
+            let mut signature = signature.serialize_der().as_ref().to_vec();
+            signature.push(sighash as u8);
+
+            self.inputs[vin].partial_sigs.insert(public_key.clone(), signature);
+            if self.version == 2 {
+                self.tx_modifiable = modifiable_after_signing(self.tx_modifiable, sighash);
+            }
+            signed += 1;
+        }
+        Ok(signed)
+    }
+
+    /// Merges the data of another PSBT of the same transaction into this one (combiner).
+    pub fn combine(&mut self, other: &Self) -> Result<(), TransactionError> {
+        if self.unsigned_transaction_bytes()? != other.unsigned_transaction_bytes()? {
+            return Err(TransactionError::InvalidPsbt("cannot combine PSBTs of different transactions".into()));
+        }
+        if self.version != other.version {
+            return Err(TransactionError::InvalidPsbt(format!(
+                "cannot combine PSBT versions {} and {}",
+                self.version, other.version
+            )));
+        }
+
+        // A flag is only kept if both signers left it, but any of them may have signed with SIGHASH_SINGLE
+        let modifiable = PSBT_TX_MODIFIABLE_INPUTS | PSBT_TX_MODIFIABLE_OUTPUTS;
+        self.tx_modifiable = (self.tx_modifiable & other.tx_modifiable & modifiable)
+            | ((self.tx_modifiable | other.tx_modifiable) & PSBT_TX_HAS_SIGHASH_SINGLE);
+        combine_option(&mut self.fallback_lock_time, &other.fallback_lock_time);
+        combine_map(&mut self.xpubs, &other.xpubs);
+        combine_map(&mut self.unknown, &other.unknown);
+        for (input, other) in self.inputs.iter_mut().zip(&other.inputs) {
+            input.combine(other);
+        }
+        for (output, other) in self.outputs.iter_mut().zip(&other.outputs) {
+            output.combine(other);
+        }
+        Ok(())
+    }
+
+    /// Builds the final script sig and witness of every input from its partial signatures (finalizer).
+    /// Supports P2PKH, P2WPKH, P2SH-P2WPKH and P2PK or multisig scripts, bare or wrapped in P2SH, P2WSH or P2SH-P2WSH.
+    /// No input is modified unless every input can be finalized.
+    pub fn finalize(&mut self) -> Result<(), TransactionError> {
+        let mut finals = vec![];
+        for vin in 0..self.inputs.len() {
+            finals.push(match self.inputs[vin].is_finalized() {
+                true => None,
+                false => Some(self.finalize_input(vin)?),
+            });
+        }
+
+        for (input, finalized) in self.inputs.iter_mut().zip(finals) {
+            if let Some((script_sig, witness)) = finalized {
+                input.final_script_sig = script_sig;
+                input.final_script_witness = witness;
+                input.clear_signing_data();
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the signed transaction of a finalized PSBT (extractor).
+    pub fn extract(&self) -> Result<BitcoinTransaction<N>, TransactionError> {
+        let mut parameters = self.transaction_parameters()?;
+        for (vin, (input, psbt_input)) in parameters.inputs.iter_mut().zip(&self.inputs).enumerate() {
+            if !psbt_input.is_finalized() {
+                return Err(TransactionError::InvalidPsbt(format!("input {} is not finalized", vin)));
+            }
+
+            input.script_sig = psbt_input.final_script_sig.clone().unwrap_or_default();
+            input.witnesses = vec![];
+            for item in psbt_input.final_script_witness.iter().flatten() {
+                input.witnesses.push([variable_length_integer(item.len() as u64)?, item.clone()].concat());
+            }
+            input.is_signed = true;
+        }
+        parameters.segwit_flag = parameters.inputs.iter().any(|input| !input.witnesses.is_empty());
+
+        BitcoinTransaction::new(&parameters)
+    }
+
+    /// Removes the script sig and witnesses of the input and returns its map,
+    /// with the witness UTXO, redeem script and witness script known by its outpoint.
+    fn unsigned_input(input: &mut BitcoinTransactionInput<N>) -> PsbtInput {
+        input.script_sig = vec![];
+        input.witnesses = vec![];
+        input.is_signed = false;
+
+        let mut psbt_input = PsbtInput::default();
+        let outpoint = &input.outpoint;
+        if let Some(address) = &outpoint.address {
+            let is_segwit = address.format() != BitcoinFormat::P2PKH && address.format() != BitcoinFormat::P2SH;
+            if let (true, Some(amount), Some(script_pub_key)) = (is_segwit, outpoint.amount, &outpoint.script_pub_key) {
+                psbt_input.witness_utxo = Some(BitcoinTransactionOutput {
+                    amount,
+                    script_pub_key: script_pub_key.clone(),
+                });
+            }
+            match address.format() {
+                BitcoinFormat::P2SH | BitcoinFormat::P2SH_P2WPKH => psbt_input.redeem_script = outpoint.redeem_script.clone(),
+                BitcoinFormat::P2SH_P2WSH => {
+                    psbt_input.redeem_script = outpoint.redeem_script.as_deref().map(create_p2sh_p2wsh_redeem_script);
+                    psbt_input.witness_script = outpoint.redeem_script.clone();
+                }
+                BitcoinFormat::P2WSH => psbt_input.witness_script = outpoint.redeem_script.clone(),
+                _ => {}
+            }
+        }
+        psbt_input
+    }
+
+    /// Returns the transaction parameters with the lock time of the PSBT.
+    fn transaction_parameters(&self) -> Result<BitcoinTransactionParameters<N>, TransactionError> {
+        let mut parameters = self.unsigned_transaction.clone();
+        parameters.lock_time = self.lock_time()?;
+        Ok(parameters)
+    }
+
+    /// Returns the serialized unsigned transaction.
+    fn unsigned_transaction_bytes(&self) -> Result<Vec<u8>, TransactionError> {
+        BitcoinTransaction::new(&self.transaction_parameters()?)?.to_bytes()
+    }
+
+    /// Returns the output spent by the input, checking the non-witness UTXO against the outpoint.
+    fn spent_output(&self, vin: usize) -> Result<BitcoinTransactionOutput, TransactionError> {
+        let outpoint = &self.unsigned_transaction.inputs[vin].outpoint;
+        match (&self.inputs[vin].non_witness_utxo, &self.inputs[vin].witness_utxo) {
+            (Some(transaction), _) => {
+                let transaction = BitcoinTransaction::<N>::from_bytes(transaction)?;
+                let mut transaction_id = transaction.to_transaction_id()?.txid;
+                transaction_id.reverse();
+                if transaction_id != outpoint.reverse_transaction_id {
+                    return Err(TransactionError::InvalidPsbt(format!(
+                        "the non-witness UTXO of input {} does not match its outpoint",
+                        vin
+                    )));
+                }
+                match transaction.parameters.outputs.get(outpoint.index as usize) {
+                    Some(output) => Ok(output.clone()),
+                    None => Err(TransactionError::InvalidPsbt(format!(
+                        "the non-witness UTXO of input {} has no output {}",
+                        vin, outpoint.index
+                    ))),
+                }
+            }
+            (None, Some(output)) => Ok(output.clone()),
+            (None, None) => Err(TransactionError::InvalidPsbt(format!("input {} is missing its UTXO", vin))),
+        }
+    }
+
+    /// Returns the script code of the input and `true` if it is spent with SegWit v0,
+    /// checking the redeem and witness scripts against the spent script public key.
+    fn script_code(&self, vin: usize, script_pub_key: &[u8]) -> Result<(Vec<u8>, bool), TransactionError> {
+        let input = &self.inputs[vin];
+        let mut script = script_pub_key.to_vec();
+        if is_p2sh(&script) {
+            let redeem_script = match &input.redeem_script {
+                Some(redeem_script) => redeem_script,
+                None => return Err(TransactionError::InvalidPsbt(format!("input {} is missing its redeem script", vin))),
+            };
+            if hash160(redeem_script) != script[2..22] {
+                return Err(TransactionError::InvalidPsbt(format!("the redeem script of input {} does not match", vin)));
+            }
+            script = redeem_script.clone();
+        }
+
+        if is_p2wpkh(&script) {
+            return Ok((p2pkh_script(&script[2..22]), true));
+        }
+        if is_p2wsh(&script) {
+            let witness_script = match &input.witness_script {
+                Some(witness_script) => witness_script,
+                None => return Err(TransactionError::InvalidPsbt(format!("input {} is missing its witness script", vin))),
+            };
+            if sha256(witness_script)[..] != script[2..34] {
+                return Err(TransactionError::InvalidPsbt(format!("the witness script of input {} does not match", vin)));
+            }
+            return Ok((witness_script.clone(), true));
+        }
+        Ok((script, false))
+    }
+
+    /// Returns the final script sig and witness of the input.
+    fn finalize_input(&self, vin: usize) -> Result<FinalScripts, TransactionError> {
+        let input = &self.inputs[vin];
+        let script_pub_key = self.spent_output(vin)?.script_pub_key;
+        let (script, redeem_script) = match is_p2sh(&script_pub_key) {
+            true => match &input.redeem_script {
+                Some(redeem_script) => (redeem_script.clone(), Some(push_data(redeem_script))),
+                None => return Err(TransactionError::InvalidPsbt(format!("input {} is missing its redeem script", vin))),
+            },
+            false => (script_pub_key, None),
+        };
+
+        if is_p2wpkh(&script) {
+            let (public_key, signature) = self.public_key_hash_signature(vin, &script[2..22])?;
+            return Ok((redeem_script, Some(vec![signature, public_key])));
+        }
+        if is_p2wsh(&script) {
+            let witness_script = match &input.witness_script {
+                Some(witness_script) => witness_script,
+                None => return Err(TransactionError::InvalidPsbt(format!("input {} is missing its witness script", vin))),
+            };
+            let mut witness = self.satisfy(vin, witness_script)?;
+            witness.push(witness_script.clone());
+            return Ok((redeem_script, Some(witness)));
+        }
+
+        if is_p2pkh(&script) {
+            let (public_key, signature) = self.public_key_hash_signature(vin, &script[3..23])?;
+            return Ok((Some([push_data(&signature), push_data(&public_key)].concat()), None));
+        }
+        let mut script_sig: Vec<u8> = self.satisfy(vin, &script)?.iter().flat_map(|item| push_data(item)).collect();
+        script_sig.extend(redeem_script.unwrap_or_default());
+        Ok((Some(script_sig), None))
+    }
+
+    /// Returns the public key and signature of the input matching the public key hash.
+    fn public_key_hash_signature(&self, vin: usize, public_key_hash: &[u8]) -> Result<(Vec<u8>, Vec<u8>), TransactionError> {
+        match self.inputs[vin]
+            .partial_sigs
+            .iter()
+            .find(|(public_key, _)| hash160(public_key) == public_key_hash)
+        {
+            Some((public_key, signature)) => Ok((public_key.clone(), signature.clone())),
+            None => Err(TransactionError::InvalidPsbt(format!("input {} is missing its signature", vin))),
+        }
+    }
+
+    /// Returns the stack satisfying a P2PK or multisig script from the partial signatures of the input.
+    fn satisfy(&self, vin: usize, script: &[u8]) -> Result<Vec<Vec<u8>>, TransactionError> {
+        let partial_sigs = &self.inputs[vin].partial_sigs;
+        let (threshold, public_keys) = match parse_multisig(script) {
+            Some(multisig) => multisig,
+            None => match script.len() {
+                35 | 67 if script[0] as usize == script.len() - 2 && script[script.len() - 1] == Opcode::OP_CHECKSIG as u8 => {
+                    (1, vec![script[1..script.len() - 1].to_vec()])
+                }
+                _ => return Err(TransactionError::InvalidPsbt(format!("input {} has an unsupported script", vin))),
+            },
+        };
+
+        let signatures: Vec<Vec<u8>> = public_keys
+            .iter()
+            .filter_map(|public_key| partial_sigs.get(public_key).cloned())
+            .take(threshold)
+            .collect();
+        if signatures.len() < threshold {
+            return Err(TransactionError::InvalidPsbt(format!(
+                "input {} has {} of {} signatures",
+                vin,
+                signatures.len(),
+                threshold
+            )));
+        }
+
+        match parse_multisig(script).is_some() {
+            // OP_CHECKMULTISIG pops an extra element
+            true => Ok([vec![vec![]], signatures].concat()),
+            false => Ok(signatures),
+        }
+    }
+}
+
+impl<N: BitcoinNetwork> FromStr for BitcoinPsbt<N> {
+    type Err = TransactionError;
+
+    fn from_str(psbt: &str) -> Result<Self, Self::Err> {
+        Self::from_base64(psbt)
+    }
+}
+
+impl<N: BitcoinNetwork> fmt::Display for BitcoinPsbt<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_base64().map_err(|_| fmt::Error)?)
+    }
+}
+
+/// Reads the key-value pairs of a map up to its separator, rejecting duplicate keys.
+fn read_map(reader: &mut &[u8]) -> Result<Vec<KeyValuePair>, TransactionError> {
+    let mut pairs: Vec<KeyValuePair> = vec![];
+    loop {
+        let key = read_bytes(reader)?;
+        if key.is_empty() {
+            return Ok(pairs);
+        }
+        let value = read_bytes(reader)?;
+        if pairs.iter().any(|(existing, _)| existing == &key) {
+            return Err(TransactionError::InvalidPsbt(format!("duplicate key: {}", hex::encode(&key))));
+        }
+        pairs.push((key, value));
+    }
+}
+
+/// Reads a byte vector prefixed by its variable length integer length.
+fn read_bytes(reader: &mut &[u8]) -> Result<Vec<u8>, TransactionError> {
+    if reader.is_empty() {
+        return Err(TransactionError::InvalidPsbt("unexpected end of data".into()));
+    }
+    let len = read_variable_length_integer(&mut *reader)?;
+    if len > reader.len() {
+        return Err(TransactionError::InvalidPsbt("unexpected end of data".into()));
+    }
+    let (bytes, rest) = reader.split_at(len);
+    *reader = rest;
+    Ok(bytes.to_vec())
+}
+
+/// Returns the modifiable flags of a version 2 PSBT after signing an input with the given sighash.
+fn modifiable_after_signing(tx_modifiable: u8, sighash: SignatureHash) -> u8 {
+    let mut tx_modifiable = tx_modifiable;
+    match sighash {
+        SignatureHash::SIGHASH_ALL_SIGHASH_ANYONECANPAY
+        | SignatureHash::SIGHASH_NONE_SIGHASH_ANYONECANPAY
+        | SignatureHash::SIGHASH_SINGLE_SIGHASH_ANYONECANPAY => {}
+        _ => tx_modifiable &= !PSBT_TX_MODIFIABLE_INPUTS,
+    }
+    match sighash {
+        SignatureHash::SIGHASH_NONE | SignatureHash::SIGHASH_NONE_SIGHASH_ANYONECANPAY => {}
+        SignatureHash::SIGHASH_SINGLE | SignatureHash::SIGHASH_SINGLE_SIGHASH_ANYONECANPAY => {
+            tx_modifiable |= PSBT_TX_HAS_SIGHASH_SINGLE
+        }
+        _ => tx_modifiable &= !PSBT_TX_MODIFIABLE_OUTPUTS,
+    }
+    tx_modifiable
+}
+
+fn read_count(value: &[u8]) -> Result<usize, TransactionError> {
+    let mut reader = value;
+    let count = read_variable_length_integer(&mut reader)?;
+    match value.is_empty() || !reader.is_empty() {
+        true => Err(TransactionError::InvalidPsbt("invalid count".into())),
+        false => Ok(count),
+    }
+}
+
+fn read_u32(value: &[u8]) -> Result<u32, TransactionError> {
+    match value.len() {
+        4 => Ok(u32::from_le_bytes([value[0], value[1], value[2], value[3]])),
+        len => Err(TransactionError::InvalidPsbt(format!("invalid 32-bit value length: {}", len))),
+    }
+}
+
+fn read_sighash_type(sighash_type: u32) -> Result<SignatureHash, TransactionError> {
+    match sighash_type {
+        0x01 | 0x02 | 0x03 | 0x81 | 0x82 | 0x83 => SignatureHash::from_byte(sighash_type as u8),
+        _ => Err(TransactionError::InvalidPsbt(format!("unsupported sighash type: {:#x}", sighash_type))),
+    }
+}
+
+fn read_unsigned_transaction<N: BitcoinNetwork>(transaction: &[u8]) -> Result<BitcoinTransactionParameters<N>, TransactionError> {
+    let mut reader = transaction;
+    let transaction = BitcoinTransactionParameters::<N>::read(&mut reader)?;
+    if !reader.is_empty() {
+        return Err(TransactionError::InvalidPsbt("the unsigned transaction has trailing bytes".into()));
+    }
+    if transaction.segwit_flag || transaction.inputs.iter().any(|input| !input.script_sig.is_empty()) {
+        return Err(TransactionError::InvalidPsbt("the unsigned transaction has script sigs or witnesses".into()));
+    }
+    Ok(transaction)
+}
+
+fn read_witness(witness: &[u8]) -> Result<Vec<Vec<u8>>, TransactionError> {
+    let mut reader = witness;
+    let count = read_variable_length_integer(&mut reader)?;
+    let mut items = vec![];
+    for _ in 0..count {
+        items.push(read_bytes(&mut reader)?);
+    }
+    match reader.is_empty() {
+        true => Ok(items),
+        false => Err(TransactionError::InvalidPsbt("invalid final script witness".into())),
+    }
+}
+
+fn write_witness(witness: &[Vec<u8>]) -> Result<Vec<u8>, TransactionError> {
+    let mut serialized = variable_length_integer(witness.len() as u64)?;
+    for item in witness {
+        serialized.extend(variable_length_integer(item.len() as u64)?);
+        serialized.extend(item);
+    }
+    Ok(serialized)
+}
+
+fn write_pair(psbt: &mut Vec<u8>, key_type: u8, key_data: &[u8], value: &[u8]) -> Result<(), TransactionError> {
+    write_raw_pair(psbt, &[vec![key_type], key_data.to_vec()].concat(), value)
+}
+
+fn write_raw_pair(psbt: &mut Vec<u8>, key: &[u8], value: &[u8]) -> Result<(), TransactionError> {
+    psbt.extend(variable_length_integer(key.len() as u64)?);
+    psbt.extend(key);
+    psbt.extend(variable_length_integer(value.len() as u64)?);
+    psbt.extend(value);
+    Ok(())
+}
+
+fn check_public_key(public_key: &[u8]) -> Result<(), TransactionError> {
+    match libsecp256k1::PublicKey::parse_slice(public_key, None) {
+        Ok(_) => Ok(()),
+        Err(_) => Err(TransactionError::InvalidPsbt(format!("invalid public key: {}", hex::encode(public_key)))),
+    }
+}
+
+fn combine_option<T: Clone>(value: &mut Option<T>, other: &Option<T>) {
+    if value.is_none() {
+        *value = other.clone();
+    }
+}
+
+fn combine_map<K: Clone + Ord, V: Clone>(map: &mut BTreeMap<K, V>, other: &BTreeMap<K, V>) {
+    for (key, value) in other {
+        map.entry(key.clone()).or_insert_with(|| value.clone());
+    }
+}
+
+fn contains(script: &[u8], data: &[u8]) -> bool {
+    script.windows(data.len()).any(|window| window == data)
+}
+
+fn p2pkh_script(public_key_hash: &[u8]) -> Vec<u8> {
+    let mut hash = [0u8; 20];
+    hash.copy_from_slice(public_key_hash);
+    Script::new_p2pkh(&hash).into_bytes()
+}
+
+fn is_p2pkh(script: &[u8]) -> bool {
+    Script::from(script).is_p2pkh()
+}
+
+fn is_p2sh(script: &[u8]) -> bool {
+    Script::from(script).is_p2sh()
+}
+
+fn is_p2wpkh(script: &[u8]) -> bool {
+    Script::from(script).is_p2wpkh()
+}
+
+fn is_p2wsh(script: &[u8]) -> bool {
+    Script::from(script).is_p2wsh()
+}
+
+/// Returns the threshold and public keys of an `OP_m <public keys> OP_n OP_CHECKMULTISIG` script.
+fn parse_multisig(script: &[u8]) -> Option<(usize, Vec<Vec<u8>>)> {
+    let multisig = MultisigScript::from_script(script).ok()?;
+    Some((multisig.threshold(), multisig.public_keys().to_vec()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amount::BitcoinAmount;
+    use crate::transaction::{BitcoinTransactionBuilder, BitcoinTransactionInput, BitcoinTransactionInputBuilder};
+    use crate::{BitcoinAddress, Mainnet};
+
+    type N = Mainnet;
+
+    // p2sh_p2wpkh to p2pkh - based on https://github.com/bitcoin/bips/blob/master/bip-0143.mediawiki#p2sh-p2wpkh
+    const PRIVATE_KEY: &str = "5Kbxro1cmUF9mTJ8fDrTfNB6URTBsFMUG52jzzumP2p9C94uKCh";
+    const TRANSACTION_ID: &str = "77541aeb3c4dac9260b68f74f44c973081a9d4cb2ebe8038b2d70faa201b6bdb";
+    const EXPECTED_SIGNED_TRANSACTION: &str = "01000000000101db6b1b20aa0fd7b23880be2ecbd4a98130974cf4748fb66092ac4d3ceb1a5477010000001716001479091972186c449eb1ded22b78e40d009bdf0089feffffff02b8b4eb0b000000001976a914a457b684d7f0d539a46a45bbc043f35b59d0d96388ac0008af2f000000001976a914fd270b1ee6abcaea97fea7ad0402e8bd8ad6d77c88ac02473044022047ac8e878352d3ebbde1c94ce3a10d057c24175747116f8288e5d794d12d482f0220217f36a485cae903c713331d877c1f64677e3622ad4010726870540656fe9dcb012103ad1d8e89212f0b92c74d23bb710c00662ad1470198ac48c43f7d6f93a2a2687392040000";
+
+    const MULTISIG_PRIVATE_KEYS: [&str; 2] = [
+        "L5hax5dZaByC3kJ4aLrZgnMXGSQReqRDYNqM1VAeXpqDRkRjX42H",
+        "L4uNhZS86VLiKKGZZGNxwP7s67EfYfQ7S9bNnVfVbU9GBVVo2xoD",
+    ];
+
+    fn private_key(private_key: &str) -> BitcoinPrivateKey<N> {
+        BitcoinPrivateKey::<N>::from_str(private_key).unwrap()
+    }
+
+    fn compressed_public_key(private_key: &BitcoinPrivateKey<N>) -> Vec<u8> {
+        private_key.to_public_key().to_secp256k1_public_key().serialize_compressed().to_vec()
+    }
+
+    fn p2sh_p2wpkh_psbt() -> BitcoinPsbt<N> {
+        let private_key = private_key(PRIVATE_KEY);
+        let redeem_script = [vec![0x00, 0x14], hash160(&compressed_public_key(&private_key))].concat();
+        let address = private_key.to_address(&BitcoinFormat::P2SH_P2WPKH).unwrap();
+
+        let transaction = BitcoinTransactionBuilder::<N>::new()
+            .version(1)
+            .lock_time(1170)
+            .input(
+                BitcoinTransactionInputBuilder::new(TRANSACTION_ID, 1)
+                    .address(&address.to_string())
+                    .amount(BitcoinAmount(1000000000))
+                    .redeem_script(redeem_script)
+                    .sequence(0xfffffffe),
+            )
+            .output("1Fyxts6r24DpEieygQiNnWxUdb18ANa5p7", BitcoinAmount(199996600))
+            .output("1Q5YjKVj5yQWHBBsyEBamkfph3cA6G9KK8", BitcoinAmount(800000000))
+            .build()
+            .unwrap();
+        BitcoinPsbt::from_transaction(&transaction).unwrap()
+    }
+
+    fn multisig_script() -> Vec<u8> {
+        let mut script = vec![0x52];
+        for private_key in MULTISIG_PRIVATE_KEYS.iter().map(|key| private_key(key)) {
+            script.extend(push_data(&compressed_public_key(&private_key)));
+        }
+        script.extend(vec![0x52, Opcode::OP_CHECKMULTISIG as u8]);
+        script
+    }
+
+    #[test]
+    fn sign_finalize_extract() {
+        let mut psbt = p2sh_p2wpkh_psbt();
+        assert!(psbt.inputs[0].witness_utxo.is_some());
+        assert!(psbt.inputs[0].redeem_script.is_some());
+        assert!(matches!(psbt.extract(), Err(TransactionError::InvalidPsbt(_))));
+
+        assert_eq!(1, psbt.sign(&private_key(PRIVATE_KEY)).unwrap());
+        assert_eq!(0, psbt.sign(&private_key(MULTISIG_PRIVATE_KEYS[0])).unwrap());
+        assert_eq!(1, psbt.inputs[0].partial_sigs.len());
+
+        psbt.finalize().unwrap();
+        assert!(psbt.inputs[0].is_finalized());
+        assert!(psbt.inputs[0].partial_sigs.is_empty());
+        assert!(psbt.inputs[0].redeem_script.is_none());
+
+        let transaction = psbt.extract().unwrap();
+        assert_eq!(EXPECTED_SIGNED_TRANSACTION, hex::encode(transaction.to_bytes().unwrap()));
+    }
+
+    #[test]
+    fn base64() {
+        let mut psbt = p2sh_p2wpkh_psbt();
+        psbt.inputs[0].bip32_derivation.insert(
+            compressed_public_key(&private_key(PRIVATE_KEY)),
+            KeySource::new([0xd9, 0x0c, 0x6a, 0x4f], DerivationPath::from_str("m/49'/0'/0'/0/1").unwrap()),
+        );
+        psbt.inputs[0].unknown.insert(vec![0xfc, 0x01], vec![0x02]);
+
+        let encoded = psbt.to_base64().unwrap();
+        assert!(encoded.starts_with("cHNidP8B"));
+        assert_eq!(encoded, psbt.to_string());
+        let decoded = BitcoinPsbt::<N>::from_base64(&encoded).unwrap();
+        assert_eq!(psbt.inputs, decoded.inputs);
+        assert_eq!(encoded, decoded.to_base64().unwrap());
+
+        psbt.sign(&private_key(PRIVATE_KEY)).unwrap();
+        let decoded = BitcoinPsbt::<N>::from_str(&psbt.to_string()).unwrap();
+        assert_eq!(psbt.to_bytes().unwrap(), decoded.to_bytes().unwrap());
+        assert_eq!(psbt.inputs, decoded.inputs);
+
+        let mut decoded = decoded;
+        decoded.finalize().unwrap();
+        assert_eq!(EXPECTED_SIGNED_TRANSACTION, hex::encode(decoded.extract().unwrap().to_bytes().unwrap()));
+    }
+
+    #[test]
+    fn p2pkh() {
+        let private_key = private_key(PRIVATE_KEY);
+        let address = private_key.to_address(&BitcoinFormat::P2PKH).unwrap().to_string();
+
+        let funding = BitcoinTransactionBuilder::<N>::new()
+            .input(BitcoinTransactionInputBuilder::new(TRANSACTION_ID, 0).address(&address))
+            .output(&address, BitcoinAmount(50000))
+            .build()
+            .unwrap();
+        // The spent transaction is identified by its serialization, which omits the unsigned script sig
+        let funding = BitcoinTransaction::<N>::from_bytes(&funding.to_bytes().unwrap()).unwrap();
+        let funding_id = funding.to_transaction_id().unwrap().to_string();
+
+        let mut transaction = BitcoinTransactionBuilder::<N>::new()
+            .input(BitcoinTransactionInputBuilder::new(&funding_id, 0).address(&address))
+            .output("1Fyxts6r24DpEieygQiNnWxUdb18ANa5p7", BitcoinAmount(40000))
+            .build()
+            .unwrap();
+        let mut psbt = BitcoinPsbt::from_transaction(&transaction).unwrap();
+
+        // Legacy inputs need the spent transaction
+        assert_eq!(0, psbt.sign(&private_key).unwrap());
+        assert!(matches!(psbt.finalize(), Err(TransactionError::InvalidPsbt(_))));
+
+        let mut mismatch = psbt.clone();
+        mismatch.inputs[0].non_witness_utxo = Some(transaction.to_bytes().unwrap());
+        assert!(matches!(mismatch.sign(&private_key), Err(TransactionError::InvalidPsbt(_))));
+
+        psbt.inputs[0].non_witness_utxo = Some(funding.to_bytes().unwrap());
+        assert_eq!(1, psbt.sign(&private_key).unwrap());
+        psbt.finalize().unwrap();
+
+        let expected = transaction.sign_with_private_key(&private_key).unwrap();
+        assert_eq!(expected, psbt.extract().unwrap().to_bytes().unwrap());
+    }
+
+    #[test]
+    fn p2wsh_multisig() {
+        let script = multisig_script();
+        let address = BitcoinAddress::<N>::p2wsh(&script).unwrap();
+        let input = BitcoinTransactionInput::<N>::new(
+            hex::decode(TRANSACTION_ID).unwrap(),
+            0,
+            Some(address),
+            Some(BitcoinAmount(100000)),
+            Some(script.clone()),
+            None,
+            None,
+            SignatureHash::SIGHASH_ALL,
+        )
+        .unwrap();
+        let output = BitcoinTransactionOutput::new(
+            &BitcoinAddress::<N>::from_str("1Fyxts6r24DpEieygQiNnWxUdb18ANa5p7").unwrap(),
+            BitcoinAmount(90000),
+        )
+        .unwrap();
+        let parameters = BitcoinTransactionParameters::new(vec![input], vec![output]).unwrap();
+
+        let psbt = BitcoinPsbt::from_parameters(&parameters).unwrap();
+        assert_eq!(Some(script.clone()), psbt.inputs[0].witness_script);
+
+        // Each co-signer signs their own copy
+        let mut first = BitcoinPsbt::<N>::from_base64(&psbt.to_base64().unwrap()).unwrap();
+        let mut second = BitcoinPsbt::<N>::from_base64(&psbt.to_base64().unwrap()).unwrap();
+        assert_eq!(1, second.sign(&private_key(MULTISIG_PRIVATE_KEYS[1])).unwrap());
+        assert!(matches!(second.clone().finalize(), Err(TransactionError::InvalidPsbt(_))));
+        assert_eq!(1, first.sign(&private_key(MULTISIG_PRIVATE_KEYS[0])).unwrap());
+
+        first.combine(&second).unwrap();
+        assert_eq!(2, first.inputs[0].partial_sigs.len());
+        first.finalize().unwrap();
+
+        let witness = first.inputs[0].final_script_witness.clone().unwrap();
+        assert_eq!(4, witness.len());
+        assert!(witness[0].is_empty());
+        assert_eq!(script, witness[3]);
+
+        // The signatures follow the order of the public keys and commit to the witness script
+        let unsigned = BitcoinTransaction::new(&first.unsigned_transaction).unwrap();
+        let preimage = unsigned
+            .witness_v0_hash_preimage(0, &script, BitcoinAmount(100000), SignatureHash::SIGHASH_ALL)
+            .unwrap();
+        let message = libsecp256k1::Message::parse_slice(&Sha256::digest(Sha256::digest(&preimage))).unwrap();
+        for (signature, key) in witness[1..3].iter().zip(MULTISIG_PRIVATE_KEYS.iter()) {
+            let signature = libsecp256k1::Signature::parse_der(&signature[..signature.len() - 1]).unwrap();
+            let public_key = private_key(key).to_public_key().to_secp256k1_public_key();
+            assert!(libsecp256k1::verify(&message, &signature, &public_key));
+        }
+
+        let extracted = first.extract().unwrap();
+        assert!(extracted.parameters.segwit_flag);
+        assert_eq!(4, extracted.parameters.inputs[0].witnesses.len());
+
+        // A PSBT of another transaction cannot be combined
+        assert!(matches!(first.combine(&p2sh_p2wpkh_psbt()), Err(TransactionError::InvalidPsbt(_))));
+    }
+
+    #[test]
+    fn invalid_psbt() {
+        let psbt = p2sh_p2wpkh_psbt().to_bytes().unwrap();
+
+        let mut magic = psbt.clone();
+        magic[4] = 0x00;
+        assert!(matches!(BitcoinPsbt::<N>::from_bytes(&magic), Err(TransactionError::InvalidPsbt(_))));
+
+        assert!(matches!(
+            BitcoinPsbt::<N>::from_bytes(&psbt[..psbt.len() - 1]),
+            Err(TransactionError::InvalidPsbt(_))
+        ));
+        assert!(matches!(
+            BitcoinPsbt::<N>::from_bytes(&[psbt.clone(), vec![0x00]].concat()),
+            Err(TransactionError::InvalidPsbt(_))
+        ));
+        assert!(BitcoinPsbt::<N>::from_base64("not base64!").is_err());
+
+        // The unsigned transaction key appears twice in the global map
+        let transaction = BitcoinTransaction::new(&p2sh_p2wpkh_psbt().unsigned_transaction).unwrap().to_bytes().unwrap();
+        let duplicate = [PSBT_MAGIC.to_vec(), pair_of(&transaction), pair_of(&transaction), vec![0x00]].concat();
+        assert!(matches!(BitcoinPsbt::<N>::from_bytes(&duplicate), Err(TransactionError::InvalidPsbt(_))));
+
+        // The unsigned transaction must not have script sigs
+        let signed = hex::decode(EXPECTED_SIGNED_TRANSACTION).unwrap();
+        let signed = [PSBT_MAGIC.to_vec(), pair_of(&signed), vec![0x00, 0x00, 0x00, 0x00]].concat();
+        assert!(matches!(BitcoinPsbt::<N>::from_bytes(&signed), Err(TransactionError::InvalidPsbt(_))));
+
+        // Version 2 replaces the unsigned transaction by its fields, and there is no version 1
+        for version in [1u32, 2] {
+            let mut pair = vec![];
+            write_pair(&mut pair, PSBT_GLOBAL_VERSION, &[], &version.to_le_bytes()).unwrap();
+            let versioned = [PSBT_MAGIC.to_vec(), pair_of(&transaction), pair, vec![0x00, 0x00, 0x00, 0x00]].concat();
+            assert!(matches!(BitcoinPsbt::<N>::from_bytes(&versioned), Err(TransactionError::InvalidPsbt(_))));
+        }
+
+        // Version 0 cannot have the global fields of version 2
+        let mut count = vec![];
+        write_pair(&mut count, PSBT_GLOBAL_INPUT_COUNT, &[], &[0x01]).unwrap();
+        let counted = [PSBT_MAGIC.to_vec(), pair_of(&transaction), count, vec![0x00, 0x00, 0x00, 0x00]].concat();
+        assert!(matches!(BitcoinPsbt::<N>::from_bytes(&counted), Err(TransactionError::InvalidPsbt(_))));
+
+        // Version 2 needs the outpoint of every input
+        let mut psbt = p2sh_p2wpkh_psbt();
+        psbt.set_version(2).unwrap();
+        let encoded = psbt.to_bytes().unwrap();
+        let mut outpoint = vec![];
+        write_pair(&mut outpoint, PSBT_IN_OUTPUT_INDEX, &[], &1u32.to_le_bytes()).unwrap();
+        let position = encoded.windows(outpoint.len()).position(|window| window == &outpoint[..]).unwrap();
+        let truncated = [&encoded[..position], &encoded[position + outpoint.len()..]].concat();
+        assert!(matches!(BitcoinPsbt::<N>::from_bytes(&truncated), Err(TransactionError::InvalidPsbt(_))));
+    }
+
+    #[test]
+    fn version_2() {
+        let mut psbt = p2sh_p2wpkh_psbt();
+        psbt.set_version(2).unwrap();
+        assert_eq!(Some(1170), psbt.fallback_lock_time);
+
+        let encoded = psbt.to_base64().unwrap();
+        let mut decoded = BitcoinPsbt::<N>::from_base64(&encoded).unwrap();
+        assert_eq!(2, decoded.version);
+        assert_eq!(1, decoded.unsigned_transaction.version);
+        assert_eq!(1170, decoded.lock_time().unwrap());
+        assert_eq!(psbt.unsigned_transaction_bytes().unwrap(), decoded.unsigned_transaction_bytes().unwrap());
+        assert_eq!(encoded, decoded.to_base64().unwrap());
+
+        assert_eq!(1, decoded.sign(&private_key(PRIVATE_KEY)).unwrap());
+        decoded.finalize().unwrap();
+        let transaction = BitcoinTransaction::from_psbt(&decoded).unwrap();
+        assert_eq!(EXPECTED_SIGNED_TRANSACTION, hex::encode(transaction.to_bytes().unwrap()));
+
+        decoded.set_version(0).unwrap();
+        assert_eq!(1170, decoded.unsigned_transaction.lock_time);
+        let decoded = BitcoinPsbt::<N>::from_base64(&decoded.to_base64().unwrap()).unwrap();
+        assert_eq!(0, decoded.version);
+        assert_eq!(EXPECTED_SIGNED_TRANSACTION, hex::encode(decoded.extract().unwrap().to_bytes().unwrap()));
+    }
+
+    #[test]
+    fn version_2_constructor() {
+        let input = BitcoinTransactionInput::<N>::new(
+            hex::decode(TRANSACTION_ID).unwrap(),
+            2,
+            None,
+            None,
+            None,
+            None,
+            None,
+            SignatureHash::SIGHASH_ALL,
+        )
+        .unwrap();
+        let output = BitcoinTransactionOutput::new(
+            &BitcoinAddress::<N>::from_str("1Q5YjKVj5yQWHBBsyEBamkfph3cA6G9KK8").unwrap(),
+            BitcoinAmount(5000),
+        )
+        .unwrap();
+
+        let mut psbt = p2sh_p2wpkh_psbt();
+        assert!(matches!(psbt.add_output(output.clone(), PsbtOutput::default()), Err(TransactionError::InvalidPsbt(_))));
+        psbt.set_version(2).unwrap();
+        assert!(matches!(psbt.add_output(output.clone(), PsbtOutput::default()), Err(TransactionError::InvalidPsbt(_))));
+        psbt.tx_modifiable = PSBT_TX_MODIFIABLE_INPUTS | PSBT_TX_MODIFIABLE_OUTPUTS;
+
+        // A payjoin receiver adds an input requiring a later block and an output
+        let height = PsbtInput {
+            required_height_lock_time: Some(1200),
+            ..Default::default()
+        };
+        psbt.add_input(input.clone(), height).unwrap();
+        psbt.add_output(output.clone(), PsbtOutput::default()).unwrap();
+        assert_eq!(1200, psbt.lock_time().unwrap());
+
+        // An input only supporting timestamps cannot join inputs requiring a block height
+        let time = PsbtInput {
+            required_time_lock_time: Some(1_700_000_000),
+            ..Default::default()
+        };
+        assert!(matches!(psbt.add_input(input.clone(), time), Err(TransactionError::InvalidPsbt(_))));
+        assert_eq!(2, psbt.inputs.len());
+        assert_eq!(2, psbt.unsigned_transaction.inputs.len());
+
+        let decoded = BitcoinPsbt::<N>::from_base64(&psbt.to_base64().unwrap()).unwrap();
+        assert_eq!(psbt.inputs, decoded.inputs);
+        assert_eq!(psbt.unsigned_transaction.outputs, decoded.unsigned_transaction.outputs);
+        assert_eq!(1200, decoded.unsigned_transaction.lock_time);
+        assert_eq!(psbt.tx_modifiable, decoded.tx_modifiable);
+
+        // SIGHASH_ALL commits to every input and output
+        assert_eq!(1, psbt.sign(&private_key(PRIVATE_KEY)).unwrap());
+        assert_eq!(0, psbt.tx_modifiable);
+        assert!(matches!(psbt.add_input(input, PsbtInput::default()), Err(TransactionError::InvalidPsbt(_))));
+        assert!(matches!(psbt.add_output(output, PsbtOutput::default()), Err(TransactionError::InvalidPsbt(_))));
+
+        let modifiable = PSBT_TX_MODIFIABLE_INPUTS | PSBT_TX_MODIFIABLE_OUTPUTS;
+        assert_eq!(
+            modifiable | PSBT_TX_HAS_SIGHASH_SINGLE,
+            modifiable_after_signing(modifiable, SignatureHash::SIGHASH_SINGLE_SIGHASH_ANYONECANPAY)
+        );
+        assert_eq!(PSBT_TX_MODIFIABLE_OUTPUTS, modifiable_after_signing(modifiable, SignatureHash::SIGHASH_NONE));
+        assert_eq!(
+            PSBT_TX_MODIFIABLE_INPUTS,
+            modifiable_after_signing(modifiable, SignatureHash::SIGHASH_ALL_SIGHASH_ANYONECANPAY)
+        );
+    }
+
+    // https://github.com/bitcoin/bips/blob/master/bip-0174.mediawiki#test-vectors
+    // A P2SH multisig and a P2SH-P2WSH multisig input of one testnet wallet, signed by two signers
+    mod bip174 {
+        use super::*;
+        use crate::Testnet;
+
+        type T = Testnet;
+
+        const CREATOR: &str = "cHNidP8BAJoCAAAAAljoeiG1ba8MI76OcHBFbDNvfLqlyHV5JPVFiHuyq911AAAAAAD/////g40EJ9DsZQpoqka7CwmK6kQiwHGyyng1Kgd5WdB86h0BAAAAAP////8CcKrwCAAAAAAWABTYXCtx0AYLCcmIauuBXlCZHdoSTQDh9QUAAAAAFgAUAK6pouXw+HaliN9VRuh0LR2HAI8AAAAAAAAAAAA=";
+        const UPDATER: &str = "cHNidP8BAJoCAAAAAljoeiG1ba8MI76OcHBFbDNvfLqlyHV5JPVFiHuyq911AAAAAAD/////g40EJ9DsZQpoqka7CwmK6kQiwHGyyng1Kgd5WdB86h0BAAAAAP////8CcKrwCAAAAAAWABTYXCtx0AYLCcmIauuBXlCZHdoSTQDh9QUAAAAAFgAUAK6pouXw+HaliN9VRuh0LR2HAI8AAAAAAAEAuwIAAAABqtc5MQGL0l+ErkALaISL4J23BurCrBgpi6vucatlb4sAAAAASEcwRAIgWPb8fGoz4bMVSNSByCbAFb0wE1qtQs1neQ2rZtKtJDsCIEoc7SYExnNbY5PltBaR3XiwDwxZQvufdRhW+qk4FX26Af7///8CgPD6AgAAAAAXqRQPuUY0IWlrgsgzryQceMF9295JNIfQ8gonAQAAABepFCnKdPigj4GZlCgYXJe12FLkBj9hh2UAAAABBEdSIQKVg785rgpgl0etGZrd1jT6YQhVnWxc05tMIYPxq5bgfyEC2rYf9JoU22p9ArDNH7t4/EsYMStbTlTa5Nui+/71NtdSriIGApWDvzmuCmCXR60Zmt3WNPphCFWdbFzTm0whg/GrluB/ENkMak8AAACAAAAAgAAAAIAiBgLath/0mhTban0CsM0fu3j8SxgxK1tOVNrk26L7/vU21xDZDGpPAAAAgAAAAIABAACAAAEBIADC6wsAAAAAF6kUt/X69A49QKWkWbHbNTXyty+pIeiHAQQiACCMI1MXN0O1ld+0oHtyuo5C43l9p06H/n2ddJfjsgKJAwEFR1IhAwidwQx6xttU+RMpr2FzM9s4jOrQwjH3IzedG5kDCwLcIQI63ZBPPW3PWd25BrDe4jUpt/+57VDl6GFRkmhgIh8Oc1KuIgYCOt2QTz1tz1nduQaw3uI1Kbf/ue1Q5ehhUZJoYCIfDnMQ2QxqTwAAAIAAAACAAwAAgCIGAwidwQx6xttU+RMpr2FzM9s4jOrQwjH3IzedG5kDCwLcENkMak8AAACAAAAAgAIAAIAAIgIDqaTDf1mW06ol26xrVwrwZQOUSSlCRgs1R1Ptnuylh3EQ2QxqTwAAAIAAAACABAAAgAAiAgJ/Y5l1fS7/VaE2rQLGhLGDi2VW5fG2s0KCqUtrUAUQlhDZDGpPAAAAgAAAAIAFAACAAA==";
+        const SIGHASH_UPDATER: &str = "cHNidP8BAJoCAAAAAljoeiG1ba8MI76OcHBFbDNvfLqlyHV5JPVFiHuyq911AAAAAAD/////g40EJ9DsZQpoqka7CwmK6kQiwHGyyng1Kgd5WdB86h0BAAAAAP////8CcKrwCAAAAAAWABTYXCtx0AYLCcmIauuBXlCZHdoSTQDh9QUAAAAAFgAUAK6pouXw+HaliN9VRuh0LR2HAI8AAAAAAAEAuwIAAAABqtc5MQGL0l+ErkALaISL4J23BurCrBgpi6vucatlb4sAAAAASEcwRAIgWPb8fGoz4bMVSNSByCbAFb0wE1qtQs1neQ2rZtKtJDsCIEoc7SYExnNbY5PltBaR3XiwDwxZQvufdRhW+qk4FX26Af7///8CgPD6AgAAAAAXqRQPuUY0IWlrgsgzryQceMF9295JNIfQ8gonAQAAABepFCnKdPigj4GZlCgYXJe12FLkBj9hh2UAAAABAwQBAAAAAQRHUiEClYO/Oa4KYJdHrRma3dY0+mEIVZ1sXNObTCGD8auW4H8hAtq2H/SaFNtqfQKwzR+7ePxLGDErW05U2uTbovv+9TbXUq4iBgKVg785rgpgl0etGZrd1jT6YQhVnWxc05tMIYPxq5bgfxDZDGpPAAAAgAAAAIAAAACAIgYC2rYf9JoU22p9ArDNH7t4/EsYMStbTlTa5Nui+/71NtcQ2QxqTwAAAIAAAACAAQAAgAABASAAwusLAAAAABepFLf1+vQOPUClpFmx2zU18rcvqSHohwEDBAEAAAABBCIAIIwjUxc3Q7WV37Sge3K6jkLjeX2nTof+fZ10l+OyAokDAQVHUiEDCJ3BDHrG21T5EymvYXMz2ziM6tDCMfcjN50bmQMLAtwhAjrdkE89bc9Z3bkGsN7iNSm3/7ntUOXoYVGSaGAiHw5zUq4iBgI63ZBPPW3PWd25BrDe4jUpt/+57VDl6GFRkmhgIh8OcxDZDGpPAAAAgAAAAIADAACAIgYDCJ3BDHrG21T5EymvYXMz2ziM6tDCMfcjN50bmQMLAtwQ2QxqTwAAAIAAAACAAgAAgAAiAgOppMN/WZbTqiXbrGtXCvBlA5RJKUJGCzVHU+2e7KWHcRDZDGpPAAAAgAAAAIAEAACAACICAn9jmXV9Lv9VoTatAsaEsYOLZVbl8bazQoKpS2tQBRCWENkMak8AAACAAAAAgAUAAIAA";
+        const SIGNED: [&str; 2] = [
+            "cHNidP8BAJoCAAAAAljoeiG1ba8MI76OcHBFbDNvfLqlyHV5JPVFiHuyq911AAAAAAD/////g40EJ9DsZQpoqka7CwmK6kQiwHGyyng1Kgd5WdB86h0BAAAAAP////8CcKrwCAAAAAAWABTYXCtx0AYLCcmIauuBXlCZHdoSTQDh9QUAAAAAFgAUAK6pouXw+HaliN9VRuh0LR2HAI8AAAAAAAEAuwIAAAABqtc5MQGL0l+ErkALaISL4J23BurCrBgpi6vucatlb4sAAAAASEcwRAIgWPb8fGoz4bMVSNSByCbAFb0wE1qtQs1neQ2rZtKtJDsCIEoc7SYExnNbY5PltBaR3XiwDwxZQvufdRhW+qk4FX26Af7///8CgPD6AgAAAAAXqRQPuUY0IWlrgsgzryQceMF9295JNIfQ8gonAQAAABepFCnKdPigj4GZlCgYXJe12FLkBj9hh2UAAAAiAgKVg785rgpgl0etGZrd1jT6YQhVnWxc05tMIYPxq5bgf0cwRAIgdAGK1BgAl7hzMjwAFXILNoTMgSOJEEjn282bVa1nnJkCIHPTabdA4+tT3O+jOCPIBwUUylWn3ZVE8VfBZ5EyYRGMAQEDBAEAAAABBEdSIQKVg785rgpgl0etGZrd1jT6YQhVnWxc05tMIYPxq5bgfyEC2rYf9JoU22p9ArDNH7t4/EsYMStbTlTa5Nui+/71NtdSriIGApWDvzmuCmCXR60Zmt3WNPphCFWdbFzTm0whg/GrluB/ENkMak8AAACAAAAAgAAAAIAiBgLath/0mhTban0CsM0fu3j8SxgxK1tOVNrk26L7/vU21xDZDGpPAAAAgAAAAIABAACAAAEBIADC6wsAAAAAF6kUt/X69A49QKWkWbHbNTXyty+pIeiHIgIDCJ3BDHrG21T5EymvYXMz2ziM6tDCMfcjN50bmQMLAtxHMEQCIGLrelVhB6fHP0WsSrWh3d9vcHX7EnWWmn84Pv/3hLyyAiAMBdu3Rw2/LwhVfdNWxzJcHtMJE+mWzThAlF2xIijaXwEBAwQBAAAAAQQiACCMI1MXN0O1ld+0oHtyuo5C43l9p06H/n2ddJfjsgKJAwEFR1IhAwidwQx6xttU+RMpr2FzM9s4jOrQwjH3IzedG5kDCwLcIQI63ZBPPW3PWd25BrDe4jUpt/+57VDl6GFRkmhgIh8Oc1KuIgYCOt2QTz1tz1nduQaw3uI1Kbf/ue1Q5ehhUZJoYCIfDnMQ2QxqTwAAAIAAAACAAwAAgCIGAwidwQx6xttU+RMpr2FzM9s4jOrQwjH3IzedG5kDCwLcENkMak8AAACAAAAAgAIAAIAAIgIDqaTDf1mW06ol26xrVwrwZQOUSSlCRgs1R1Ptnuylh3EQ2QxqTwAAAIAAAACABAAAgAAiAgJ/Y5l1fS7/VaE2rQLGhLGDi2VW5fG2s0KCqUtrUAUQlhDZDGpPAAAAgAAAAIAFAACAAA==",
+            "cHNidP8BAJoCAAAAAljoeiG1ba8MI76OcHBFbDNvfLqlyHV5JPVFiHuyq911AAAAAAD/////g40EJ9DsZQpoqka7CwmK6kQiwHGyyng1Kgd5WdB86h0BAAAAAP////8CcKrwCAAAAAAWABTYXCtx0AYLCcmIauuBXlCZHdoSTQDh9QUAAAAAFgAUAK6pouXw+HaliN9VRuh0LR2HAI8AAAAAAAEAuwIAAAABqtc5MQGL0l+ErkALaISL4J23BurCrBgpi6vucatlb4sAAAAASEcwRAIgWPb8fGoz4bMVSNSByCbAFb0wE1qtQs1neQ2rZtKtJDsCIEoc7SYExnNbY5PltBaR3XiwDwxZQvufdRhW+qk4FX26Af7///8CgPD6AgAAAAAXqRQPuUY0IWlrgsgzryQceMF9295JNIfQ8gonAQAAABepFCnKdPigj4GZlCgYXJe12FLkBj9hh2UAAAAiAgLath/0mhTban0CsM0fu3j8SxgxK1tOVNrk26L7/vU210gwRQIhAPYQOLMI3B2oZaNIUnRvAVdyk0IIxtJEVDk82ZvfIhd3AiAFbmdaZ1ptCgK4WxTl4pB02KJam1dgvqKBb2YZEKAG6gEBAwQBAAAAAQRHUiEClYO/Oa4KYJdHrRma3dY0+mEIVZ1sXNObTCGD8auW4H8hAtq2H/SaFNtqfQKwzR+7ePxLGDErW05U2uTbovv+9TbXUq4iBgKVg785rgpgl0etGZrd1jT6YQhVnWxc05tMIYPxq5bgfxDZDGpPAAAAgAAAAIAAAACAIgYC2rYf9JoU22p9ArDNH7t4/EsYMStbTlTa5Nui+/71NtcQ2QxqTwAAAIAAAACAAQAAgAABASAAwusLAAAAABepFLf1+vQOPUClpFmx2zU18rcvqSHohyICAjrdkE89bc9Z3bkGsN7iNSm3/7ntUOXoYVGSaGAiHw5zRzBEAiBl9FulmYtZon/+GnvtAWrx8fkNVLOqj3RQql9WolEDvQIgf3JHA60e25ZoCyhLVtT/y4j3+3Weq74IqjDym4UTg9IBAQMEAQAAAAEEIgAgjCNTFzdDtZXftKB7crqOQuN5fadOh/59nXSX47ICiQMBBUdSIQMIncEMesbbVPkTKa9hczPbOIzq0MIx9yM3nRuZAwsC3CECOt2QTz1tz1nduQaw3uI1Kbf/ue1Q5ehhUZJoYCIfDnNSriIGAjrdkE89bc9Z3bkGsN7iNSm3/7ntUOXoYVGSaGAiHw5zENkMak8AAACAAAAAgAMAAIAiBgMIncEMesbbVPkTKa9hczPbOIzq0MIx9yM3nRuZAwsC3BDZDGpPAAAAgAAAAIACAACAACICA6mkw39ZltOqJdusa1cK8GUDlEkpQkYLNUdT7Z7spYdxENkMak8AAACAAAAAgAQAAIAAIgICf2OZdX0u/1WhNq0CxoSxg4tlVuXxtrNCgqlLa1AFEJYQ2QxqTwAAAIAAAACABQAAgAA=",
+        ];
+        const COMBINED: &str = "cHNidP8BAJoCAAAAAljoeiG1ba8MI76OcHBFbDNvfLqlyHV5JPVFiHuyq911AAAAAAD/////g40EJ9DsZQpoqka7CwmK6kQiwHGyyng1Kgd5WdB86h0BAAAAAP////8CcKrwCAAAAAAWABTYXCtx0AYLCcmIauuBXlCZHdoSTQDh9QUAAAAAFgAUAK6pouXw+HaliN9VRuh0LR2HAI8AAAAAAAEAuwIAAAABqtc5MQGL0l+ErkALaISL4J23BurCrBgpi6vucatlb4sAAAAASEcwRAIgWPb8fGoz4bMVSNSByCbAFb0wE1qtQs1neQ2rZtKtJDsCIEoc7SYExnNbY5PltBaR3XiwDwxZQvufdRhW+qk4FX26Af7///8CgPD6AgAAAAAXqRQPuUY0IWlrgsgzryQceMF9295JNIfQ8gonAQAAABepFCnKdPigj4GZlCgYXJe12FLkBj9hh2UAAAAiAgKVg785rgpgl0etGZrd1jT6YQhVnWxc05tMIYPxq5bgf0cwRAIgdAGK1BgAl7hzMjwAFXILNoTMgSOJEEjn282bVa1nnJkCIHPTabdA4+tT3O+jOCPIBwUUylWn3ZVE8VfBZ5EyYRGMASICAtq2H/SaFNtqfQKwzR+7ePxLGDErW05U2uTbovv+9TbXSDBFAiEA9hA4swjcHahlo0hSdG8BV3KTQgjG0kRUOTzZm98iF3cCIAVuZ1pnWm0KArhbFOXikHTYolqbV2C+ooFvZhkQoAbqAQEDBAEAAAABBEdSIQKVg785rgpgl0etGZrd1jT6YQhVnWxc05tMIYPxq5bgfyEC2rYf9JoU22p9ArDNH7t4/EsYMStbTlTa5Nui+/71NtdSriIGApWDvzmuCmCXR60Zmt3WNPphCFWdbFzTm0whg/GrluB/ENkMak8AAACAAAAAgAAAAIAiBgLath/0mhTban0CsM0fu3j8SxgxK1tOVNrk26L7/vU21xDZDGpPAAAAgAAAAIABAACAAAEBIADC6wsAAAAAF6kUt/X69A49QKWkWbHbNTXyty+pIeiHIgICOt2QTz1tz1nduQaw3uI1Kbf/ue1Q5ehhUZJoYCIfDnNHMEQCIGX0W6WZi1mif/4ae+0BavHx+Q1Us6qPdFCqX1aiUQO9AiB/ckcDrR7blmgLKEtW1P/LiPf7dZ6rvgiqMPKbhROD0gEiAgMIncEMesbbVPkTKa9hczPbOIzq0MIx9yM3nRuZAwsC3EcwRAIgYut6VWEHp8c/RaxKtaHd329wdfsSdZaafzg+//eEvLICIAwF27dHDb8vCFV901bHMlwe0wkT6ZbNOECUXbEiKNpfAQEDBAEAAAABBCIAIIwjUxc3Q7WV37Sge3K6jkLjeX2nTof+fZ10l+OyAokDAQVHUiEDCJ3BDHrG21T5EymvYXMz2ziM6tDCMfcjN50bmQMLAtwhAjrdkE89bc9Z3bkGsN7iNSm3/7ntUOXoYVGSaGAiHw5zUq4iBgI63ZBPPW3PWd25BrDe4jUpt/+57VDl6GFRkmhgIh8OcxDZDGpPAAAAgAAAAIADAACAIgYDCJ3BDHrG21T5EymvYXMz2ziM6tDCMfcjN50bmQMLAtwQ2QxqTwAAAIAAAACAAgAAgAAiAgOppMN/WZbTqiXbrGtXCvBlA5RJKUJGCzVHU+2e7KWHcRDZDGpPAAAAgAAAAIAEAACAACICAn9jmXV9Lv9VoTatAsaEsYOLZVbl8bazQoKpS2tQBRCWENkMak8AAACAAAAAgAUAAIAA";
+        const FINALIZED: &str = "cHNidP8BAJoCAAAAAljoeiG1ba8MI76OcHBFbDNvfLqlyHV5JPVFiHuyq911AAAAAAD/////g40EJ9DsZQpoqka7CwmK6kQiwHGyyng1Kgd5WdB86h0BAAAAAP////8CcKrwCAAAAAAWABTYXCtx0AYLCcmIauuBXlCZHdoSTQDh9QUAAAAAFgAUAK6pouXw+HaliN9VRuh0LR2HAI8AAAAAAAEAuwIAAAABqtc5MQGL0l+ErkALaISL4J23BurCrBgpi6vucatlb4sAAAAASEcwRAIgWPb8fGoz4bMVSNSByCbAFb0wE1qtQs1neQ2rZtKtJDsCIEoc7SYExnNbY5PltBaR3XiwDwxZQvufdRhW+qk4FX26Af7///8CgPD6AgAAAAAXqRQPuUY0IWlrgsgzryQceMF9295JNIfQ8gonAQAAABepFCnKdPigj4GZlCgYXJe12FLkBj9hh2UAAAABB9oARzBEAiB0AYrUGACXuHMyPAAVcgs2hMyBI4kQSOfbzZtVrWecmQIgc9Npt0Dj61Pc76M4I8gHBRTKVafdlUTxV8FnkTJhEYwBSDBFAiEA9hA4swjcHahlo0hSdG8BV3KTQgjG0kRUOTzZm98iF3cCIAVuZ1pnWm0KArhbFOXikHTYolqbV2C+ooFvZhkQoAbqAUdSIQKVg785rgpgl0etGZrd1jT6YQhVnWxc05tMIYPxq5bgfyEC2rYf9JoU22p9ArDNH7t4/EsYMStbTlTa5Nui+/71NtdSrgABASAAwusLAAAAABepFLf1+vQOPUClpFmx2zU18rcvqSHohwEHIyIAIIwjUxc3Q7WV37Sge3K6jkLjeX2nTof+fZ10l+OyAokDAQjaBABHMEQCIGLrelVhB6fHP0WsSrWh3d9vcHX7EnWWmn84Pv/3hLyyAiAMBdu3Rw2/LwhVfdNWxzJcHtMJE+mWzThAlF2xIijaXwFHMEQCIGX0W6WZi1mif/4ae+0BavHx+Q1Us6qPdFCqX1aiUQO9AiB/ckcDrR7blmgLKEtW1P/LiPf7dZ6rvgiqMPKbhROD0gFHUiEDCJ3BDHrG21T5EymvYXMz2ziM6tDCMfcjN50bmQMLAtwhAjrdkE89bc9Z3bkGsN7iNSm3/7ntUOXoYVGSaGAiHw5zUq4AIgIDqaTDf1mW06ol26xrVwrwZQOUSSlCRgs1R1Ptnuylh3EQ2QxqTwAAAIAAAACABAAAgAAiAgJ/Y5l1fS7/VaE2rQLGhLGDi2VW5fG2s0KCqUtrUAUQlhDZDGpPAAAAgAAAAIAFAACAAA==";
+        const EXTRACTED: &str = "0200000000010258e87a21b56daf0c23be8e7070456c336f7cbaa5c8757924f545887bb2abdd7500000000da00473044022074018ad4180097b873323c0015720b3684cc8123891048e7dbcd9b55ad679c99022073d369b740e3eb53dcefa33823c8070514ca55a7dd9544f157c167913261118c01483045022100f61038b308dc1da865a34852746f015772934208c6d24454393cd99bdf2217770220056e675a675a6d0a02b85b14e5e29074d8a25a9b5760bea2816f661910a006ea01475221029583bf39ae0a609747ad199addd634fa6108559d6c5cd39b4c2183f1ab96e07f2102dab61ff49a14db6a7d02b0cd1fbb78fc4b18312b5b4e54dae4dba2fbfef536d752aeffffffff838d0427d0ec650a68aa46bb0b098aea4422c071b2ca78352a077959d07cea1d01000000232200208c2353173743b595dfb4a07b72ba8e42e3797da74e87fe7d9d7497e3b2028903ffffffff0270aaf00800000000160014d85c2b71d0060b09c9886aeb815e50991dda124d00e1f5050000000016001400aea9a2e5f0f876a588df5546e8742d1d87008f000400473044022062eb7a556107a7c73f45ac4ab5a1dddf6f7075fb1275969a7f383efff784bcb202200c05dbb7470dbf2f08557dd356c7325c1ed30913e996cd3840945db12228da5f01473044022065f45ba5998b59a27ffe1a7bed016af1f1f90d54b3aa8f7450aa5f56a25103bd02207f724703ad1edb96680b284b56d4ffcb88f7fb759eabbe08aa30f29b851383d20147522103089dc10c7ac6db54f91329af617333db388cead0c231f723379d1b99030b02dc21023add904f3d6dcf59ddb906b0dee23529b7ffb9ed50e5e86151926860221f0e7352ae00000000";
+
+        const SIGNERS: [[&str; 2]; 2] = [
+            ["cP53pDbR5WtAD8dYAW9hhTjuvvTVaEiQBdrz9XPrgLBeRFiyCbQr", "cR6SXDoyfQrcp4piaiHE97Rsgta9mNhGTen9XeonVgwsh4iSgw6d"],
+            ["cT7J9YpCwY3AVRFSjN6ukeEeWY6mhpbJPxRaDaP5QTdygQRxP9Au", "cNBc3SWUip9PPm1GjRoLEJT6T41iNzCYtD7qro84FMnM5zEqeJsE"],
+        ];
+
+        // Invalid: a network transaction
+        const NETWORK_TRANSACTION: &str = "AgAAAAEmgXE3Ht/yhek3re6ks3t4AAwFZsuzrWRkFxPKQhcb9gAAAABqRzBEAiBwsiRRI+a/R01gxbUMBD1MaRpdJDXwmjSnZiqdwlF5CgIgATKcqdrPKAvfMHQOwDkEIkIsgctFg5RXrrdvwS7dlbMBIQJlfRGNM1e44PTCzUbbezn22cONmnCry5st5dyNv+TOMf7///8C09/1BQAAAAAZdqkU0MWZA8W6woaHYOkP1SGkZlqnZSCIrADh9QUAAAAAF6kUNUXm4zuDLEcFDyTT7rk8nAOUi8eHsy4TAA==";
+        // A PSBT of one input and two empty output maps, and the same input without its unsigned transaction
+        const ONE_INPUT: &str = "cHNidP8BAHUCAAAAASaBcTce3/KF6Tet7qSze3gADAVmy7OtZGQXE8pCFxv2AAAAAAD+////AtPf9QUAAAAAGXapFNDFmQPFusKGh2DpD9UhpGZap2UgiKwA4fUFAAAAABepFDVF5uM7gyxHBQ8k0+65PJwDlIvHh7MuEwAAAQD9pQEBAAAAAAECiaPHHqtNIOA3G7ukzGmPopXJRjr6Ljl/hTPMti+VZ+UBAAAAFxYAFL4Y0VKpsBIDna89p95PUzSe7LmF/////4b4qkOnHf8USIk6UwpyN+9rRgi7st0tAXHmOuxqSJC0AQAAABcWABT+Pp7xp0XpdNkCxDVZQ6vLNL1TU/////8CAMLrCwAAAAAZdqkUhc/xCX/Z4Ai7NK9wnGIZeziXikiIrHL++E4sAAAAF6kUM5cluiHv1irHU6m80GfWx6ajnQWHAkcwRAIgJxK+IuAnDzlPVoMR3HyppolwuAJf3TskAinwf4pfOiQCIAGLONfc0xTnNMkna9b7QPZzMlvEuqFEyADS8vAtsnZcASED0uFWdJQbrUqZY3LLh+GFbTZSYG2YVi/jnF6efkE/IQUCSDBFAiEA0SuFLYXc2WHS9fSrZgZU327tzHlMDDPOXMMJ/7X85Y0CIGczio4OFyXBl/saiK9Z9R5E5CVbIBZ8hoQDHAXR8lkqASECI7cr7vCWXRC+B3jv7NYfysb3mk6haTkzgHNEZPhPKrMAAAAAAAAA";
+        const NO_UNSIGNED_TRANSACTION: &str = "cHNidP8AAQD9pQEBAAAAAAECiaPHHqtNIOA3G7ukzGmPopXJRjr6Ljl/hTPMti+VZ+UBAAAAFxYAFL4Y0VKpsBIDna89p95PUzSe7LmF/////4b4qkOnHf8USIk6UwpyN+9rRgi7st0tAXHmOuxqSJC0AQAAABcWABT+Pp7xp0XpdNkCxDVZQ6vLNL1TU/////8CAMLrCwAAAAAZdqkUhc/xCX/Z4Ai7NK9wnGIZeziXikiIrHL++E4sAAAAF6kUM5cluiHv1irHU6m80GfWx6ajnQWHAkcwRAIgJxK+IuAnDzlPVoMR3HyppolwuAJf3TskAinwf4pfOiQCIAGLONfc0xTnNMkna9b7QPZzMlvEuqFEyADS8vAtsnZcASED0uFWdJQbrUqZY3LLh+GFbTZSYG2YVi/jnF6efkE/IQUCSDBFAiEA0SuFLYXc2WHS9fSrZgZU327tzHlMDDPOXMMJ/7X85Y0CIGczio4OFyXBl/saiK9Z9R5E5CVbIBZ8hoQDHAXR8lkqASECI7cr7vCWXRC+B3jv7NYfysb3mk6haTkzgHNEZPhPKrMAAAAAAA==";
+        // Invalid: a duplicate key in the input map
+        const DUPLICATE_INPUT_KEY: &str = "cHNidP8BAHUCAAAAASaBcTce3/KF6Tet7qSze3gADAVmy7OtZGQXE8pCFxv2AAAAAAD+////AtPf9QUAAAAAGXapFNDFmQPFusKGh2DpD9UhpGZap2UgiKwA4fUFAAAAABepFDVF5uM7gyxHBQ8k0+65PJwDlIvHh7MuEwAAAQD9pQEBAAAAAAECiaPHHqtNIOA3G7ukzGmPopXJRjr6Ljl/hTPMti+VZ+UBAAAAFxYAFL4Y0VKpsBIDna89p95PUzSe7LmF/////4b4qkOnHf8USIk6UwpyN+9rRgi7st0tAXHmOuxqSJC0AQAAABcWABT+Pp7xp0XpdNkCxDVZQ6vLNL1TU/////8CAMLrCwAAAAAZdqkUhc/xCX/Z4Ai7NK9wnGIZeziXikiIrHL++E4sAAAAF6kUM5cluiHv1irHU6m80GfWx6ajnQWHAkcwRAIgJxK+IuAnDzlPVoMR3HyppolwuAJf3TskAinwf4pfOiQCIAGLONfc0xTnNMkna9b7QPZzMlvEuqFEyADS8vAtsnZcASED0uFWdJQbrUqZY3LLh+GFbTZSYG2YVi/jnF6efkE/IQUCSDBFAiEA0SuFLYXc2WHS9fSrZgZU327tzHlMDDPOXMMJ/7X85Y0CIGczio4OFyXBl/saiK9Z9R5E5CVbIBZ8hoQDHAXR8lkqASECI7cr7vCWXRC+B3jv7NYfysb3mk6haTkzgHNEZPhPKrMAAAAAAQA/AgAAAAH//////////////////////////////////////////wAAAAAA/////wEAAAAAAAAAAANqAQAAAAAAAAAA";
+
+        fn key_source(index: u32) -> KeySource {
+            let path = DerivationPath::from_str(&format!("m/0'/0'/{}'", index)).unwrap();
+            KeySource::new([0xd9, 0x0c, 0x6a, 0x4f], path)
+        }
+
+        fn insert_key_source(map: &mut BTreeMap<Vec<u8>, KeySource>, public_key: &str, index: u32) {
+            map.insert(hex::decode(public_key).unwrap(), key_source(index));
+        }
+
+        #[test]
+        fn creator_updater() {
+            let mut psbt = BitcoinPsbt::<T>::from_base64(CREATOR).unwrap();
+            assert_eq!(CREATOR, psbt.to_base64().unwrap());
+
+            let input = &mut psbt.inputs[0];
+            input.non_witness_utxo = Some(hex::decode("0200000001aad73931018bd25f84ae400b68848be09db706eac2ac18298babee71ab656f8b0000000048473044022058f6fc7c6a33e1b31548d481c826c015bd30135aad42cd67790dab66d2ad243b02204a1ced2604c6735b6393e5b41691dd78b00f0c5942fb9f751856faa938157dba01feffffff0280f0fa020000000017a9140fb9463421696b82c833af241c78c17ddbde493487d0f20a270100000017a91429ca74f8a08f81999428185c97b5d852e4063f618765000000").unwrap());
+            input.redeem_script = Some(hex::decode("5221029583bf39ae0a609747ad199addd634fa6108559d6c5cd39b4c2183f1ab96e07f2102dab61ff49a14db6a7d02b0cd1fbb78fc4b18312b5b4e54dae4dba2fbfef536d752ae").unwrap());
+            insert_key_source(&mut input.bip32_derivation, "029583bf39ae0a609747ad199addd634fa6108559d6c5cd39b4c2183f1ab96e07f", 0);
+            insert_key_source(&mut input.bip32_derivation, "02dab61ff49a14db6a7d02b0cd1fbb78fc4b18312b5b4e54dae4dba2fbfef536d7", 1);
+
+            let input = &mut psbt.inputs[1];
+            input.witness_utxo = Some(BitcoinTransactionOutput {
+                amount: BitcoinAmount(200000000),
+                script_pub_key: hex::decode("a914b7f5faf40e3d40a5a459b1db3535f2b72fa921e887").unwrap(),
+            });
+            input.redeem_script = Some(hex::decode("00208c2353173743b595dfb4a07b72ba8e42e3797da74e87fe7d9d7497e3b2028903").unwrap());
+            input.witness_script = Some(hex::decode("522103089dc10c7ac6db54f91329af617333db388cead0c231f723379d1b99030b02dc21023add904f3d6dcf59ddb906b0dee23529b7ffb9ed50e5e86151926860221f0e7352ae").unwrap());
+            insert_key_source(&mut input.bip32_derivation, "03089dc10c7ac6db54f91329af617333db388cead0c231f723379d1b99030b02dc", 2);
+            insert_key_source(&mut input.bip32_derivation, "023add904f3d6dcf59ddb906b0dee23529b7ffb9ed50e5e86151926860221f0e73", 3);
+
+            insert_key_source(&mut psbt.outputs[0].bip32_derivation, "03a9a4c37f5996d3aa25dbac6b570af0650394492942460b354753ed9eeca58771", 4);
+            insert_key_source(&mut psbt.outputs[1].bip32_derivation, "027f6399757d2eff55a136ad02c684b1838b6556e5f1b6b34282a94b6b50051096", 5);
+            assert_eq!(UPDATER, psbt.to_base64().unwrap());
+
+            for input in psbt.inputs.iter_mut() {
+                input.sighash_type = Some(SignatureHash::SIGHASH_ALL as u32);
+            }
+            assert_eq!(SIGHASH_UPDATER, psbt.to_base64().unwrap());
+        }
+
+        #[test]
+        fn signer() {
+            for (keys, expected) in SIGNERS.iter().zip(SIGNED.iter()) {
+                let mut psbt = BitcoinPsbt::<T>::from_base64(SIGHASH_UPDATER).unwrap();
+                for key in keys {
+                    assert_eq!(1, psbt.sign(&BitcoinPrivateKey::from_str(key).unwrap()).unwrap());
+                }
+                assert_eq!(*expected, psbt.to_base64().unwrap());
+            }
+        }
+
+        #[test]
+        fn combiner_finalizer_extractor() {
+            let mut psbt = BitcoinPsbt::<T>::from_base64(SIGNED[0]).unwrap();
+            psbt.combine(&BitcoinPsbt::from_base64(SIGNED[1]).unwrap()).unwrap();
+            assert_eq!(COMBINED, psbt.to_base64().unwrap());
+
+            psbt.finalize().unwrap();
+            assert_eq!(FINALIZED, psbt.to_base64().unwrap());
+
+            let psbt = BitcoinPsbt::<T>::from_base64(FINALIZED).unwrap();
+            assert_eq!(EXTRACTED, hex::encode(psbt.extract().unwrap().to_bytes().unwrap()));
+        }
+
+        #[test]
+        fn invalid() {
+            assert!(BitcoinTransaction::<T>::from_bytes(&base64::decode(NETWORK_TRANSACTION).unwrap()).is_ok());
+            assert!(matches!(BitcoinPsbt::<T>::from_base64(NETWORK_TRANSACTION), Err(TransactionError::InvalidPsbt(_))));
+
+            let one_input = base64::decode(ONE_INPUT).unwrap();
+            assert_eq!(2, BitcoinPsbt::<T>::from_bytes(&one_input).unwrap().outputs.len());
+            let missing_outputs = &one_input[..one_input.len() - 2];
+            assert!(matches!(BitcoinPsbt::<T>::from_bytes(missing_outputs), Err(TransactionError::InvalidPsbt(_))));
+            assert!(matches!(
+                BitcoinPsbt::<T>::from_base64(NO_UNSIGNED_TRANSACTION),
+                Err(TransactionError::InvalidPsbt(_))
+            ));
+            assert!(matches!(BitcoinPsbt::<T>::from_base64(DUPLICATE_INPUT_KEY), Err(TransactionError::InvalidPsbt(_))));
+
+            // The unsigned transaction in the witness serialization format
+            let mut creator = maps(&base64::decode(CREATOR).unwrap());
+            let transaction = creator[0][0].1.clone();
+            let (lock_time, rest) = (&transaction[transaction.len() - 4..], &transaction[4..transaction.len() - 4]);
+            creator[0][0].1 = [&transaction[..4], &[0x00, 0x01], rest, &[0x00, 0x00], lock_time].concat();
+            assert!(matches!(BitcoinPsbt::<T>::from_bytes(&serialize(&creator)), Err(TransactionError::InvalidPsbt(_))));
+
+            // Every key of a known type followed by an extra byte, e.g. a public key of 34 bytes
+            for psbt in [COMBINED, FINALIZED] {
+                let maps = maps(&base64::decode(psbt).unwrap());
+                assert_eq!(base64::decode(psbt).unwrap(), serialize(&maps));
+                for (index, map) in maps.iter().enumerate() {
+                    for pair in 0..map.len() {
+                        let mut invalid = maps.clone();
+                        invalid[index][pair].0.push(0x00);
+                        assert!(matches!(
+                            BitcoinPsbt::<T>::from_bytes(&serialize(&invalid)),
+                            Err(TransactionError::InvalidPsbt(_))
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    // https://github.com/bitcoin/bips/blob/master/bip-0370.mediawiki#test-vectors
+    // The other valid and invalid PSBTs of the BIP differ from this one by the given fields
+    mod bip370 {
+        use super::*;
+        use crate::Testnet;
+
+        type T = Testnet;
+
+        const PSBT: &str = "cHNidP8BAgQCAAAAAQQBAQEFAQIB+wQCAAAAAAEAUgIAAAABwaolbiFLlqGCL5PeQr/ztfP/jQUZMG41FddRWl6AWxIAAAAAAP////8BGMaaOwAAAAAWABSwo68UQghBJpPKfRZoUrUtsK7wbgAAAAABAR8Yxpo7AAAAABYAFLCjrxRCCEEmk8p9FmhStS2wrvBuAQ4gCwrZIUGcHIcZc11y3HOfnqngY40f5MHu8PmUQISBX8gBDwQAAAAAAAEDCAAIry8AAAAAAQQWABTEMPZMR1baMQ29GghVcu8pmSYnLAAiAgLjb7/1PdU0Bwz4/TlmFGgPNXqbhdtzQL8c+nRdKtezQBjx6ugBVAAAgAEAAIAAAACAAQAAAAAAAAABAwiLvesLAAAAAAEEFgAUTdGTrJZKVqwbnhzKhFT+L0dPhRMA";
+
+        const GLOBAL: usize = 0;
+        const INPUT: usize = 1;
+        const OUTPUT: usize = 2;
+
+        /// A key of one byte and its value, by map index
+        type Pair<'a> = (usize, u8, &'a [u8]);
+
+        /// Returns the PSBT without the removed keys and with the given pairs, by map index.
+        fn with(psbt: &[u8], removed: &[(usize, u8)], pairs: &[Pair]) -> Vec<u8> {
+            let mut maps = maps(psbt);
+            for (map, key) in removed {
+                maps[*map].retain(|(existing, _)| existing[..] != [*key]);
+            }
+            for (map, key, value) in pairs {
+                maps[*map].retain(|(existing, _)| existing[..] != [*key]);
+                maps[*map].push((vec![*key], value.to_vec()));
+                maps[*map].sort();
+            }
+            serialize(&maps)
+        }
+
+        fn is_invalid(psbt: &[u8]) -> bool {
+            matches!(BitcoinPsbt::<T>::from_bytes(psbt), Err(TransactionError::InvalidPsbt(_)))
+        }
+
+        /// Returns the lock time of the PSBT with a second input, given the required lock times of both inputs.
+        fn lock_time(required: [&[(u8, u32)]; 2]) -> Result<u32, TransactionError> {
+            let mut maps = maps(&with(&base64::decode(PSBT).unwrap(), &[], &[(GLOBAL, PSBT_GLOBAL_INPUT_COUNT, &[2])]));
+            let second = maps[INPUT].clone();
+            maps.insert(INPUT + 1, second);
+            let mut psbt = serialize(&maps);
+            for (input, lock_times) in required.iter().enumerate() {
+                for (key, lock_time) in lock_times.iter() {
+                    psbt = with(&psbt, &[], &[(INPUT + input, *key, &lock_time.to_le_bytes())]);
+                }
+            }
+            BitcoinPsbt::<T>::from_bytes(&psbt)?.lock_time()
+        }
+
+        #[test]
+        fn valid() {
+            let psbt = BitcoinPsbt::<T>::from_base64(PSBT).unwrap();
+            assert_eq!((2, 2), (psbt.version, psbt.unsigned_transaction.version));
+            assert_eq!((1, 2), (psbt.inputs.len(), psbt.outputs.len()));
+            assert_eq!(0, psbt.lock_time().unwrap());
+            assert_eq!(PSBT, psbt.to_base64().unwrap());
+
+            let psbt = base64::decode(PSBT).unwrap();
+            let (time, height) = (500_000_000u32.to_le_bytes(), 10_000u32.to_le_bytes());
+            let cases: [(&[Pair], u32); 6] = [
+                (&[(INPUT, PSBT_IN_SEQUENCE, &0xfffffffeu32.to_le_bytes())], 0),
+                (&[(GLOBAL, PSBT_GLOBAL_FALLBACK_LOCKTIME, &2u32.to_le_bytes())], 2),
+                (&[(INPUT, PSBT_IN_REQUIRED_TIME_LOCKTIME, &time)], 500_000_000),
+                (&[(INPUT, PSBT_IN_REQUIRED_HEIGHT_LOCKTIME, &height)], 10_000),
+                (&[(INPUT, PSBT_IN_REQUIRED_TIME_LOCKTIME, &time), (INPUT, PSBT_IN_REQUIRED_HEIGHT_LOCKTIME, &height)], 10_000),
+                (&[(GLOBAL, PSBT_GLOBAL_FALLBACK_LOCKTIME, &2u32.to_le_bytes()), (INPUT, PSBT_IN_REQUIRED_TIME_LOCKTIME, &time)], 500_000_000),
+            ];
+            for (pairs, lock_time) in cases.iter() {
+                let valid = with(&psbt, &[], pairs);
+                let decoded = BitcoinPsbt::<T>::from_bytes(&valid).unwrap();
+                assert_eq!(*lock_time, decoded.lock_time().unwrap());
+                assert_eq!(valid, decoded.to_bytes().unwrap());
+            }
+
+            // Inputs modifiable, outputs modifiable, has SIGHASH_SINGLE, all of them, and an undefined flag
+            for flags in [0x01, 0x02, 0x04, 0x07, 0x08] {
+                let valid = with(&psbt, &[], &[(GLOBAL, PSBT_GLOBAL_TX_MODIFIABLE, &[flags])]);
+                let decoded = BitcoinPsbt::<T>::from_bytes(&valid).unwrap();
+                assert_eq!(flags, decoded.tx_modifiable);
+                assert_eq!(valid, decoded.to_bytes().unwrap());
+            }
+        }
+
+        #[test]
+        fn invalid() {
+            let psbt = base64::decode(PSBT).unwrap();
+            let required = [
+                (GLOBAL, PSBT_GLOBAL_TX_VERSION),
+                (GLOBAL, PSBT_GLOBAL_INPUT_COUNT),
+                (GLOBAL, PSBT_GLOBAL_OUTPUT_COUNT),
+                (INPUT, PSBT_IN_PREVIOUS_TXID),
+                (INPUT, PSBT_IN_OUTPUT_INDEX),
+                (OUTPUT, PSBT_OUT_AMOUNT),
+                (OUTPUT, PSBT_OUT_SCRIPT),
+            ];
+            for removed in required.iter() {
+                assert!(is_invalid(&with(&psbt, &[*removed], &[])));
+            }
+
+            let mut version_0 = BitcoinPsbt::<T>::from_bytes(&psbt).unwrap();
+            version_0.set_version(0).unwrap();
+            let version_0 = version_0.to_bytes().unwrap();
+            assert!(BitcoinPsbt::<T>::from_bytes(&version_0).is_ok());
+            let transaction = maps(&version_0)[GLOBAL][0].1.clone();
+            assert!(is_invalid(&with(&psbt, &[], &[(GLOBAL, PSBT_GLOBAL_UNSIGNED_TX, &transaction)])));
+
+            // Required lock times of the wrong kind
+            for (key, lock_time) in [
+                (PSBT_IN_REQUIRED_TIME_LOCKTIME, 499_999_999u32),
+                (PSBT_IN_REQUIRED_HEIGHT_LOCKTIME, 500_000_000),
+                (PSBT_IN_REQUIRED_HEIGHT_LOCKTIME, 0),
+            ] {
+                assert!(is_invalid(&with(&psbt, &[], &[(INPUT, key, &lock_time.to_le_bytes())])));
+            }
+
+            // The fields of version 2 in a version 0 PSBT
+            let fields: [Pair; 13] = [
+                (GLOBAL, PSBT_GLOBAL_VERSION, &2u32.to_le_bytes()),
+                (GLOBAL, PSBT_GLOBAL_TX_VERSION, &2u32.to_le_bytes()),
+                (GLOBAL, PSBT_GLOBAL_FALLBACK_LOCKTIME, &0u32.to_le_bytes()),
+                (GLOBAL, PSBT_GLOBAL_INPUT_COUNT, &[1]),
+                (GLOBAL, PSBT_GLOBAL_OUTPUT_COUNT, &[2]),
+                (GLOBAL, PSBT_GLOBAL_TX_MODIFIABLE, &[0x03]),
+                (INPUT, PSBT_IN_PREVIOUS_TXID, &[0x0b; 32]),
+                (INPUT, PSBT_IN_OUTPUT_INDEX, &0u32.to_le_bytes()),
+                (INPUT, PSBT_IN_SEQUENCE, &0xfffffffeu32.to_le_bytes()),
+                (INPUT, PSBT_IN_REQUIRED_TIME_LOCKTIME, &500_000_000u32.to_le_bytes()),
+                (INPUT, PSBT_IN_REQUIRED_HEIGHT_LOCKTIME, &10_000u32.to_le_bytes()),
+                (OUTPUT, PSBT_OUT_AMOUNT, &800_000_000i64.to_le_bytes()),
+                (OUTPUT, PSBT_OUT_SCRIPT, &[0x00, 0x14]),
+            ];
+            for pair in fields.iter() {
+                assert!(is_invalid(&with(&version_0, &[], &[*pair])));
+            }
+        }
+
+        #[test]
+        fn lock_time_determination() {
+            let (time, height) = (PSBT_IN_REQUIRED_TIME_LOCKTIME, PSBT_IN_REQUIRED_HEIGHT_LOCKTIME);
+            assert_eq!(0, lock_time([&[], &[]]).unwrap());
+            assert_eq!(10_000, lock_time([&[(height, 10_000)], &[]]).unwrap());
+            assert_eq!(600_000_000, lock_time([&[(time, 500_000_000)], &[(time, 600_000_000)]]).unwrap());
+            // Inputs supporting both kinds follow the others, and heights are preferred
+            assert_eq!(20_000, lock_time([&[(height, 10_000), (time, 500_000_000)], &[(height, 20_000)]]).unwrap());
+            assert_eq!(600_000_000, lock_time([&[(height, 10_000), (time, 500_000_000)], &[(time, 600_000_000)]]).unwrap());
+            assert_eq!(
+                20_000,
+                lock_time([&[(height, 10_000), (time, 600_000_000)], &[(height, 20_000), (time, 500_000_000)]]).unwrap()
+            );
+            assert!(matches!(
+                lock_time([&[(height, 10_000)], &[(time, 500_000_000)]]),
+                Err(TransactionError::InvalidPsbt(_))
+            ));
+        }
+    }
+
+    /// Returns the global, input and output maps of the PSBT.
+    fn maps(psbt: &[u8]) -> Vec<Vec<KeyValuePair>> {
+        let mut reader = &psbt[PSBT_MAGIC.len()..];
+        let mut maps = vec![];
+        while !reader.is_empty() {
+            maps.push(read_map(&mut reader).unwrap());
+        }
+        maps
+    }
+
+    fn serialize(maps: &[Vec<KeyValuePair>]) -> Vec<u8> {
+        let mut psbt = PSBT_MAGIC.to_vec();
+        for map in maps {
+            for (key, value) in map {
+                write_raw_pair(&mut psbt, key, value).unwrap();
+            }
+            psbt.push(0x00);
+        }
+        psbt
+    }
+
+    fn pair_of(transaction: &[u8]) -> Vec<u8> {
+        let mut pair = vec![];
+        write_pair(&mut pair, PSBT_GLOBAL_UNSIGNED_TX, &[], transaction).unwrap();
+        pair
+    }
+}
@@ -0,0 +1,827 @@
+//! # Script
+//!
+//! Bitcoin scripts as a sequence of opcodes and data pushes.
+//! A script is assembled with minimal pushes, read back as instructions, formatted to and parsed
+//! from ASM, and recognised as one of the standard output templates (P2PKH, P2SH, SegWit programs,
+//! multisig and OP_RETURN data carriers).
+//!
+//! ASM writes data pushes in hex and opcodes by name, e.g. `OP_DUP OP_HASH160 <hash> OP_EQUALVERIFY OP_CHECKSIG`.
+//! Bytes not assigned to any opcode are written as `0x`-prefixed raw bytes, as accepted by Bitcoin Core.
+//! Parsing re-encodes the pushes minimally, so only scripts with minimal pushes round-trip byte for byte.
+
+use crate::multisig::MultisigScript;
+use crate::witness_program::WitnessProgram;
+use chainlib_core::no_std::*;
+use chainlib_core::{AddressError, TransactionError};
+
+use core::{fmt, str::FromStr};
+use serde::Serialize;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ScriptError {
+    #[error("invalid ASM token {0}")]
+    InvalidAsm(String),
+
+    #[error("push of {0} bytes exceeds the {1} remaining bytes of the script")]
+    UnexpectedEndOfScript(usize, usize),
+}
+
+impl From<ScriptError> for AddressError {
+    fn from(error: ScriptError) -> Self {
+        AddressError::Crate("Script", format!("{:?}", error))
+    }
+}
+
+impl From<ScriptError> for TransactionError {
+    fn from(error: ScriptError) -> Self {
+        TransactionError::Crate("Script", format!("{:?}", error))
+    }
+}
+
+macro_rules! opcodes {
+    ($($opcode:ident = $byte:expr,)*) => {
+        /// Represents the script opcodes
+        /// https://en.bitcoin.it/wiki/Script
+        #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+        #[allow(non_camel_case_types)]
+        pub enum Opcode {
+            $($opcode = $byte,)*
+        }
+
+        impl Opcode {
+            /// Returns the opcode of the given byte, if it is assigned.
+            /// The bytes 0x01 to 0x4b push that many bytes and are not opcodes of their own.
+            pub fn from_byte(byte: u8) -> Option<Self> {
+                match byte {
+                    $($byte => Some(Opcode::$opcode),)*
+                    _ => None,
+                }
+            }
+
+            fn name(&self) -> &'static str {
+                match self {
+                    $(Opcode::$opcode => stringify!($opcode),)*
+                }
+            }
+
+            fn from_name(name: &str) -> Option<Self> {
+                match name {
+                    $(stringify!($opcode) => Some(Opcode::$opcode),)*
+                    "OP_FALSE" => Some(Opcode::OP_0),
+                    "OP_TRUE" => Some(Opcode::OP_1),
+                    "OP_NOP2" => Some(Opcode::OP_CHECKLOCKTIMEVERIFY),
+                    "OP_NOP3" => Some(Opcode::OP_CHECKSEQUENCEVERIFY),
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+opcodes! {
+    // Constants
+    OP_0 = 0x00,
+    OP_PUSHDATA1 = 0x4c,
+    OP_PUSHDATA2 = 0x4d,
+    OP_PUSHDATA4 = 0x4e,
+    OP_1NEGATE = 0x4f,
+    OP_RESERVED = 0x50,
+    OP_1 = 0x51,
+    OP_2 = 0x52,
+    OP_3 = 0x53,
+    OP_4 = 0x54,
+    OP_5 = 0x55,
+    OP_6 = 0x56,
+    OP_7 = 0x57,
+    OP_8 = 0x58,
+    OP_9 = 0x59,
+    OP_10 = 0x5a,
+    OP_11 = 0x5b,
+    OP_12 = 0x5c,
+    OP_13 = 0x5d,
+    OP_14 = 0x5e,
+    OP_15 = 0x5f,
+    OP_16 = 0x60,
+
+    // Flow control
+    OP_NOP = 0x61,
+    OP_VER = 0x62,
+    OP_IF = 0x63,
+    OP_NOTIF = 0x64,
+    OP_VERIF = 0x65,
+    OP_VERNOTIF = 0x66,
+    OP_ELSE = 0x67,
+    OP_ENDIF = 0x68,
+    OP_VERIFY = 0x69,
+    OP_RETURN = 0x6a,
+
+    // Stack
+    OP_TOALTSTACK = 0x6b,
+    OP_FROMALTSTACK = 0x6c,
+    OP_2DROP = 0x6d,
+    OP_2DUP = 0x6e,
+    OP_3DUP = 0x6f,
+    OP_2OVER = 0x70,
+    OP_2ROT = 0x71,
+    OP_2SWAP = 0x72,
+    OP_IFDUP = 0x73,
+    OP_DEPTH = 0x74,
+    OP_DROP = 0x75,
+    OP_DUP = 0x76,
+    OP_NIP = 0x77,
+    OP_OVER = 0x78,
+    OP_PICK = 0x79,
+    OP_ROLL = 0x7a,
+    OP_ROT = 0x7b,
+    OP_SWAP = 0x7c,
+    OP_TUCK = 0x7d,
+
+    // Splice
+    OP_CAT = 0x7e,
+    OP_SUBSTR = 0x7f,
+    OP_LEFT = 0x80,
+    OP_RIGHT = 0x81,
+    OP_SIZE = 0x82,
+
+    // Bitwise logic
+    OP_INVERT = 0x83,
+    OP_AND = 0x84,
+    OP_OR = 0x85,
+    OP_XOR = 0x86,
+    OP_EQUAL = 0x87,
+    OP_EQUALVERIFY = 0x88,
+    OP_RESERVED1 = 0x89,
+    OP_RESERVED2 = 0x8a,
+
+    // Arithmetic
+    OP_1ADD = 0x8b,
+    OP_1SUB = 0x8c,
+    OP_2MUL = 0x8d,
+    OP_2DIV = 0x8e,
+    OP_NEGATE = 0x8f,
+    OP_ABS = 0x90,
+    OP_NOT = 0x91,
+    OP_0NOTEQUAL = 0x92,
+    OP_ADD = 0x93,
+    OP_SUB = 0x94,
+    OP_MUL = 0x95,
+    OP_DIV = 0x96,
+    OP_MOD = 0x97,
+    OP_LSHIFT = 0x98,
+    OP_RSHIFT = 0x99,
+    OP_BOOLAND = 0x9a,
+    OP_BOOLOR = 0x9b,
+    OP_NUMEQUAL = 0x9c,
+    OP_NUMEQUALVERIFY = 0x9d,
+    OP_NUMNOTEQUAL = 0x9e,
+    OP_LESSTHAN = 0x9f,
+    OP_GREATERTHAN = 0xa0,
+    OP_LESSTHANOREQUAL = 0xa1,
+    OP_GREATERTHANOREQUAL = 0xa2,
+    OP_MIN = 0xa3,
+    OP_MAX = 0xa4,
+    OP_WITHIN = 0xa5,
+
+    // Crypto
+    OP_RIPEMD160 = 0xa6,
+    OP_SHA1 = 0xa7,
+    OP_SHA256 = 0xa8,
+    OP_HASH160 = 0xa9,
+    OP_HASH256 = 0xaa,
+    OP_CODESEPARATOR = 0xab,
+    OP_CHECKSIG = 0xac,
+    OP_CHECKSIGVERIFY = 0xad,
+    OP_CHECKMULTISIG = 0xae,
+    OP_CHECKMULTISIGVERIFY = 0xaf,
+
+    // Expansion
+    OP_NOP1 = 0xb0,
+    OP_CHECKLOCKTIMEVERIFY = 0xb1,
+    OP_CHECKSEQUENCEVERIFY = 0xb2,
+    OP_NOP4 = 0xb3,
+    OP_NOP5 = 0xb4,
+    OP_NOP6 = 0xb5,
+    OP_NOP7 = 0xb6,
+    OP_NOP8 = 0xb7,
+    OP_NOP9 = 0xb8,
+    OP_NOP10 = 0xb9,
+    OP_CHECKSIGADD = 0xba,
+
+    OP_INVALIDOPCODE = 0xff,
+}
+
+impl fmt::Display for Opcode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Opcode {
+    type Err = ScriptError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Self::from_name(name).ok_or_else(|| ScriptError::InvalidAsm(name.into()))
+    }
+}
+
+/// Represents a single step of a script
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Instruction<'a> {
+    /// Pushes the data onto the stack, which is empty for OP_0
+    PushBytes(&'a [u8]),
+    /// Any other opcode, including OP_1NEGATE and OP_1 to OP_16
+    Op(Opcode),
+    /// A byte not assigned to any opcode (OP_SUCCESSx in Tapscript)
+    Unknown(u8),
+}
+
+/// Iterates over the instructions of a script, stopping after the first malformed push
+#[derive(Debug, Clone)]
+pub struct Instructions<'a> {
+    script: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Instructions<'a> {
+    /// Returns an iterator over the instructions of the given script bytes.
+    pub(crate) fn new(script: &'a [u8]) -> Self {
+        Self { script, position: 0 }
+    }
+
+    /// Returns the offset of the next instruction from the start of the script.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Splits the first `len` bytes off the remaining script.
+    fn take(&mut self, len: usize) -> Result<&'a [u8], ScriptError> {
+        if len > self.script.len() {
+            let remaining = self.script.len();
+            self.script = &[];
+            return Err(ScriptError::UnexpectedEndOfScript(len, remaining));
+        }
+        let (bytes, rest) = self.script.split_at(len);
+        self.script = rest;
+        self.position += len;
+        Ok(bytes)
+    }
+
+    /// Reads the little endian push length of `size` bytes following OP_PUSHDATA1/2/4.
+    fn take_length(&mut self, size: usize) -> Result<usize, ScriptError> {
+        let bytes = self.take(size)?;
+        Ok(bytes.iter().rev().fold(0, |len, &byte| (len << 8) | byte as usize))
+    }
+}
+
+impl<'a> Iterator for Instructions<'a> {
+    type Item = Result<Instruction<'a>, ScriptError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (&byte, rest) = self.script.split_first()?;
+        self.script = rest;
+        self.position += 1;
+
+        let len = match byte {
+            0x00..=0x4b => Ok(byte as usize),
+            0x4c => self.take_length(1),
+            0x4d => self.take_length(2),
+            0x4e => self.take_length(4),
+            _ => {
+                return Some(Ok(match Opcode::from_byte(byte) {
+                    Some(opcode) => Instruction::Op(opcode),
+                    None => Instruction::Unknown(byte),
+                }))
+            }
+        };
+        Some(len.and_then(|len| self.take(len)).map(Instruction::PushBytes))
+    }
+}
+
+/// Represents a Bitcoin script
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Script(Vec<u8>);
+
+impl Script {
+    /// Returns an empty script.
+    pub fn new() -> Self {
+        Self(vec![])
+    }
+
+    /// Returns the P2PKH script `OP_DUP OP_HASH160 <public key hash> OP_EQUALVERIFY OP_CHECKSIG`.
+    pub fn new_p2pkh(public_key_hash: &[u8; 20]) -> Self {
+        Self::new()
+            .push_opcode(Opcode::OP_DUP)
+            .push_opcode(Opcode::OP_HASH160)
+            .push_slice(public_key_hash)
+            .push_opcode(Opcode::OP_EQUALVERIFY)
+            .push_opcode(Opcode::OP_CHECKSIG)
+    }
+
+    /// Returns the P2SH script `OP_HASH160 <script hash> OP_EQUAL`.
+    pub fn new_p2sh(script_hash: &[u8; 20]) -> Self {
+        Self::new()
+            .push_opcode(Opcode::OP_HASH160)
+            .push_slice(script_hash)
+            .push_opcode(Opcode::OP_EQUAL)
+    }
+
+    /// Returns the P2WPKH script `OP_0 <public key hash>`.
+    pub fn new_p2wpkh(public_key_hash: &[u8; 20]) -> Self {
+        Self::new().push_int(0).push_slice(public_key_hash)
+    }
+
+    /// Returns the P2WSH script `OP_0 <sha256(witness script)>`.
+    pub fn new_p2wsh(script_hash: &[u8; 32]) -> Self {
+        Self::new().push_int(0).push_slice(script_hash)
+    }
+
+    /// Returns the P2TR script `OP_1 <x-only output key>`.
+    pub fn new_p2tr(output_key: &[u8; 32]) -> Self {
+        Self::new().push_int(1).push_slice(output_key)
+    }
+
+    /// Returns the script `OP_n <program>` of a witness program of any version.
+    pub fn new_witness_program(program: &WitnessProgram) -> Self {
+        Self(program.to_scriptpubkey())
+    }
+
+    /// Returns the provably unspendable script `OP_RETURN <data>`.
+    pub fn new_op_return(data: &[u8]) -> Self {
+        Self::new().push_opcode(Opcode::OP_RETURN).push_slice(data)
+    }
+
+    /// Appends the opcode to the script.
+    pub fn push_opcode(mut self, opcode: Opcode) -> Self {
+        self.0.push(opcode as u8);
+        self
+    }
+
+    /// Appends the minimal push of the data to the script (BIP-62), using OP_0, OP_1NEGATE
+    /// and OP_1 to OP_16 where possible, then direct pushes and OP_PUSHDATA1/2/4.
+    pub fn push_slice(mut self, data: &[u8]) -> Self {
+        match data {
+            [] => self.0.push(Opcode::OP_0 as u8),
+            [value @ 1..=16] => self.0.push(Opcode::OP_1 as u8 + value - 1),
+            [0x81] => self.0.push(Opcode::OP_1NEGATE as u8),
+            _ => {
+                match data.len() {
+                    len @ 0x01..=0x4b => self.0.push(len as u8),
+                    len @ 0x4c..=0xff => self.0.extend([Opcode::OP_PUSHDATA1 as u8, len as u8]),
+                    len @ 0x100..=0xffff => {
+                        self.0.push(Opcode::OP_PUSHDATA2 as u8);
+                        self.0.extend((len as u16).to_le_bytes());
+                    }
+                    len => {
+                        self.0.push(Opcode::OP_PUSHDATA4 as u8);
+                        self.0.extend((len as u32).to_le_bytes());
+                    }
+                }
+                self.0.extend(data);
+            }
+        }
+        self
+    }
+
+    /// Appends the minimal push of the number to the script.
+    pub fn push_int(self, value: i64) -> Self {
+        self.push_slice(&script_number(value))
+    }
+
+    /// Returns the script in bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Returns the script as a byte vector.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.0
+    }
+
+    /// Returns the size of the script in bytes.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns true if the script has no bytes.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns an iterator over the instructions of the script.
+    pub fn instructions(&self) -> Instructions<'_> {
+        Instructions::new(&self.0)
+    }
+
+    /// Returns the ASM of the script, ending with `[error]` at a malformed push.
+    pub fn to_asm(&self) -> String {
+        let mut asm = vec![];
+        for instruction in self.instructions() {
+            asm.push(match instruction {
+                Ok(Instruction::PushBytes([])) => Opcode::OP_0.to_string(),
+                Ok(Instruction::PushBytes(data)) => hex::encode(data),
+                Ok(Instruction::Op(opcode)) => opcode.to_string(),
+                Ok(Instruction::Unknown(byte)) => format!("0x{:02x}", byte),
+                Err(_) => String::from("[error]"),
+            });
+        }
+        asm.join(" ")
+    }
+
+    /// Returns the script of the given ASM. Hex data is pushed minimally and `0x`-prefixed hex is inserted as is.
+    pub fn from_asm(asm: &str) -> Result<Self, ScriptError> {
+        asm.split_whitespace().try_fold(Self::new(), |mut script, token| {
+            if let Some(opcode) = Opcode::from_name(token) {
+                return Ok(script.push_opcode(opcode));
+            }
+            match token.strip_prefix("0x").map(hex::decode) {
+                Some(Ok(bytes)) => {
+                    script.0.extend(bytes);
+                    Ok(script)
+                }
+                Some(Err(_)) => Err(ScriptError::InvalidAsm(token.into())),
+                None => match hex::decode(token) {
+                    Ok(data) => Ok(script.push_slice(&data)),
+                    Err(_) => Err(ScriptError::InvalidAsm(token.into())),
+                },
+            }
+        })
+    }
+
+    /// Returns true if the script is `OP_DUP OP_HASH160 <20 bytes> OP_EQUALVERIFY OP_CHECKSIG`.
+    pub fn is_p2pkh(&self) -> bool {
+        matches!(self.0[..], [0x76, 0xa9, 0x14, .., 0x88, 0xac] if self.0.len() == 25)
+    }
+
+    /// Returns true if the script is `OP_HASH160 <20 bytes> OP_EQUAL`.
+    pub fn is_p2sh(&self) -> bool {
+        matches!(self.0[..], [0xa9, 0x14, .., 0x87] if self.0.len() == 23)
+    }
+
+    /// Returns true if the script is `OP_0 <20 bytes>`.
+    pub fn is_p2wpkh(&self) -> bool {
+        matches!(self.0[..], [0x00, 0x14, ..] if self.0.len() == 22)
+    }
+
+    /// Returns true if the script is `OP_0 <32 bytes>`.
+    pub fn is_p2wsh(&self) -> bool {
+        matches!(self.0[..], [0x00, 0x20, ..] if self.0.len() == 34)
+    }
+
+    /// Returns true if the script is `OP_1 <32 bytes>`.
+    pub fn is_p2tr(&self) -> bool {
+        matches!(self.0[..], [0x51, 0x20, ..] if self.0.len() == 34)
+    }
+
+    /// Returns true if the script is `OP_m <public keys> OP_n OP_CHECKMULTISIG`.
+    pub fn is_multisig(&self) -> bool {
+        MultisigScript::from_script(&self.0).is_ok()
+    }
+
+    /// Returns true if the script starts with OP_RETURN, i.e. the output can never be spent.
+    pub fn is_op_return(&self) -> bool {
+        self.0.first() == Some(&(Opcode::OP_RETURN as u8))
+    }
+
+    /// Returns the witness program of the script `OP_n <2 to 40 bytes>`, if it is one (BIP-141).
+    pub fn witness_program(&self) -> Option<WitnessProgram> {
+        let version = match self.0.first()? {
+            0x00 => 0,
+            &opcode @ 0x51..=0x60 => opcode - 0x50,
+            _ => return None,
+        };
+        match self.0.get(1) {
+            Some(&len) if len as usize == self.0.len() - 2 => WitnessProgram::new(&[&[version][..], &self.0[1..]].concat()).ok(),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the minimal encoding of a number on the stack, little endian with the sign in the top bit.
+pub(crate) fn script_number(value: i64) -> Vec<u8> {
+    let mut bytes = vec![];
+    let mut magnitude = value.unsigned_abs();
+    while magnitude > 0 {
+        bytes.push(magnitude as u8);
+        magnitude >>= 8;
+    }
+    match bytes.last_mut() {
+        // The top bit is part of the magnitude, so the sign takes another byte
+        Some(last) if *last & 0x80 != 0 => bytes.push(if value < 0 { 0x80 } else { 0x00 }),
+        Some(last) if value < 0 => *last |= 0x80,
+        _ => {}
+    }
+    bytes
+}
+
+/// Returns the script code without its OP_CODESEPARATOR opcodes, as signed by legacy signatures.
+pub(crate) fn remove_code_separators(script: &[u8]) -> Vec<u8> {
+    let mut code = Vec::with_capacity(script.len());
+    let mut instructions = Instructions::new(script);
+    let mut start = 0;
+    while let Some(Ok(instruction)) = instructions.next() {
+        if instruction == Instruction::Op(Opcode::OP_CODESEPARATOR) {
+            code.extend(&script[start..instructions.position() - 1]);
+            start = instructions.position();
+        }
+    }
+    code.extend(&script[start..]);
+    code
+}
+
+impl From<Vec<u8>> for Script {
+    fn from(script: Vec<u8>) -> Self {
+        Self(script)
+    }
+}
+
+impl From<&[u8]> for Script {
+    fn from(script: &[u8]) -> Self {
+        Self(script.to_vec())
+    }
+}
+
+impl From<Script> for Vec<u8> {
+    fn from(script: Script) -> Self {
+        script.0
+    }
+}
+
+impl AsRef<[u8]> for Script {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl FromStr for Script {
+    type Err = ScriptError;
+
+    /// Returns a script given its ASM representation.
+    fn from_str(asm: &str) -> Result<Self, Self::Err> {
+        Self::from_asm(asm)
+    }
+}
+
+impl fmt::Display for Script {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_asm())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUBLIC_KEY_HASH: &str = "89abcdefabbaabbaabbaabbaabbaabbaabbaabba";
+    const SCRIPT_HASH: &str = "1863143c14c5166804bd19203356da136c985678cd4d27a1b8c6329604903262";
+
+    fn script(hex: &str) -> Script {
+        Script::from(hex::decode(hex).unwrap())
+    }
+
+    mod opcode {
+        use super::*;
+
+        #[test]
+        fn from_byte() {
+            let assigned: Vec<u8> = (0..=255u8).filter(|&byte| Opcode::from_byte(byte).is_some()).collect();
+            assert_eq!(0x00, assigned[0]);
+            assert_eq!((0x4c..=0xba).chain([0xff]).collect::<Vec<u8>>(), assigned[1..]);
+
+            for byte in assigned {
+                let opcode = Opcode::from_byte(byte).unwrap();
+                assert_eq!(byte, opcode as u8);
+                assert_eq!(opcode, Opcode::from_str(&opcode.to_string()).unwrap());
+            }
+        }
+
+        #[test]
+        fn aliases() {
+            assert_eq!(Opcode::OP_0, Opcode::from_str("OP_FALSE").unwrap());
+            assert_eq!(Opcode::OP_1, Opcode::from_str("OP_TRUE").unwrap());
+            assert_eq!(Opcode::OP_CHECKLOCKTIMEVERIFY, Opcode::from_str("OP_NOP2").unwrap());
+            assert_eq!(Opcode::OP_CHECKSEQUENCEVERIFY, Opcode::from_str("OP_NOP3").unwrap());
+            assert!(Opcode::from_str("OP_NOTANOPCODE").is_err());
+        }
+    }
+
+    mod push {
+        use super::*;
+
+        #[test]
+        fn push_slice() {
+            let push = |data: &[u8]| Script::new().push_slice(data).into_bytes();
+
+            assert_eq!(vec![0x00], push(&[]));
+            assert_eq!(vec![0x51], push(&[0x01]));
+            assert_eq!(vec![0x60], push(&[0x10]));
+            assert_eq!(vec![0x4f], push(&[0x81]));
+            assert_eq!(vec![0x01, 0x00], push(&[0x00]));
+            assert_eq!(vec![0x01, 0x11], push(&[0x11]));
+            assert_eq!([vec![0x4b], vec![0xab; 75]].concat(), push(&[0xab; 75]));
+            assert_eq!([vec![0x4c, 0x4c], vec![0xab; 76]].concat(), push(&[0xab; 76]));
+            assert_eq!([vec![0x4c, 0xff], vec![0xab; 255]].concat(), push(&[0xab; 255]));
+            assert_eq!([vec![0x4d, 0x00, 0x01], vec![0xab; 256]].concat(), push(&[0xab; 256]));
+            assert_eq!([vec![0x4d, 0xff, 0xff], vec![0xab; 65535]].concat(), push(&[0xab; 65535]));
+            assert_eq!(
+                [vec![0x4e, 0x00, 0x00, 0x01, 0x00], vec![0xab; 65536]].concat(),
+                push(&[0xab; 65536])
+            );
+        }
+
+        #[test]
+        fn push_int() {
+            let push = |value: i64| hex::encode(Script::new().push_int(value).as_bytes());
+
+            assert_eq!("00", push(0));
+            assert_eq!("4f", push(-1));
+            assert_eq!("51", push(1));
+            assert_eq!("60", push(16));
+            assert_eq!("0111", push(17));
+            assert_eq!("0191", push(-17));
+            assert_eq!("017f", push(127));
+            assert_eq!("028000", push(128));
+            assert_eq!("028080", push(-128));
+            assert_eq!("02e803", push(1000));
+            assert_eq!("029000", push(144));
+            assert_eq!("03ffff00", push(65535));
+            assert_eq!("04ffffff7f", push(i32::MAX as i64));
+            assert_eq!("08ffffffffffffffff", push(-i64::MAX));
+        }
+    }
+
+    mod instructions {
+        use super::*;
+
+        #[test]
+        fn instructions() {
+            let script = script(&format!("004c02abcd4d0100ef4e01000000ef5176ba{}bb", "02abcd"));
+            let instructions: Vec<Instruction> = script.instructions().map(Result::unwrap).collect();
+            assert_eq!(
+                vec![
+                    Instruction::PushBytes(&[]),
+                    Instruction::PushBytes(&[0xab, 0xcd]),
+                    Instruction::PushBytes(&[0xef]),
+                    Instruction::PushBytes(&[0xef]),
+                    Instruction::Op(Opcode::OP_1),
+                    Instruction::Op(Opcode::OP_DUP),
+                    Instruction::Op(Opcode::OP_CHECKSIGADD),
+                    Instruction::PushBytes(&[0xab, 0xcd]),
+                    Instruction::Unknown(0xbb),
+                ],
+                instructions
+            );
+        }
+
+        #[test]
+        fn unexpected_end_of_script() {
+            for (hex, len, remaining) in [("03abcd", 3, 2), ("4c", 1, 0), ("4c02ab", 2, 1), ("4d01", 2, 1), ("4e0100", 4, 2)] {
+                let script = script(hex);
+                let mut instructions = script.instructions();
+                assert_eq!(Some(Err(ScriptError::UnexpectedEndOfScript(len, remaining))), instructions.next());
+                assert_eq!(None, instructions.next());
+            }
+        }
+
+        #[test]
+        fn position() {
+            let script = script("004c02abcd76");
+            let mut instructions = script.instructions();
+            let mut positions = vec![instructions.position()];
+            while instructions.next().is_some() {
+                positions.push(instructions.position());
+            }
+            assert_eq!(vec![0, 1, 5, 6], positions);
+        }
+    }
+
+    const ASM_SCRIPTS: [(&str, &str); 6] = [
+        (
+            "76a91489abcdefabbaabbaabbaabbaabbaabbaabbaabba88ac",
+            "OP_DUP OP_HASH160 89abcdefabbaabbaabbaabbaabbaabbaabbaabba OP_EQUALVERIFY OP_CHECKSIG",
+        ),
+        (
+            "522102ff12471208c14bd580709cb2358d98975247d8765f92bc25eab3b2763ed605f82102fe6f0a5a297eb38c391581c4413e084773ea23954d93f7753db7dc0adc188b2f52ae",
+            "OP_2 02ff12471208c14bd580709cb2358d98975247d8765f92bc25eab3b2763ed605f8 02fe6f0a5a297eb38c391581c4413e084773ea23954d93f7753db7dc0adc188b2f OP_2 OP_CHECKMULTISIG",
+        ),
+        (
+            "029000b275",
+            "9000 OP_CHECKSEQUENCEVERIFY OP_DROP",
+        ),
+        (
+            "6a0b68656c6c6f20776f726c64",
+            "OP_RETURN 68656c6c6f20776f726c64",
+        ),
+        (
+            "00630101670068",
+            "OP_0 OP_IF 01 OP_ELSE OP_0 OP_ENDIF",
+        ),
+        (
+            "ba87bb",
+            "OP_CHECKSIGADD OP_EQUAL 0xbb",
+        ),
+    ];
+
+    mod asm {
+        use super::*;
+
+        #[test]
+        fn to_asm() {
+            ASM_SCRIPTS.iter().for_each(|&(hex, asm)| {
+                assert_eq!(asm, script(hex).to_asm());
+                assert_eq!(asm, script(hex).to_string());
+            });
+            assert_eq!("OP_DUP [error]", script("7603abcd").to_asm());
+        }
+
+        #[test]
+        fn from_asm() {
+            ASM_SCRIPTS.iter().for_each(|&(hex, asm)| {
+                // The push of 0x01 is OP_1 when minimal
+                let expected = match hex {
+                    "00630101670068" => "006351670068",
+                    _ => hex,
+                };
+                assert_eq!(expected, hex::encode(Script::from_asm(asm).unwrap().as_bytes()));
+            });
+            assert_eq!(script("0051b1"), Script::from_str("OP_FALSE OP_TRUE OP_NOP2").unwrap());
+            assert_eq!(script("4c02abcd"), Script::from_str("0x4c02abcd").unwrap());
+        }
+
+        #[test]
+        fn invalid() {
+            assert_eq!(Err(ScriptError::InvalidAsm("OP_FOO".into())), Script::from_asm("OP_DUP OP_FOO"));
+            assert_eq!(Err(ScriptError::InvalidAsm("abc".into())), Script::from_asm("abc"));
+            assert_eq!(Err(ScriptError::InvalidAsm("0xzz".into())), Script::from_asm("0xzz"));
+        }
+    }
+
+    mod templates {
+        use super::*;
+
+        #[test]
+        fn new() {
+            let public_key_hash: [u8; 20] = hex::decode(PUBLIC_KEY_HASH).unwrap().try_into().unwrap();
+            let script_hash: [u8; 32] = hex::decode(SCRIPT_HASH).unwrap().try_into().unwrap();
+
+            assert_eq!(format!("76a914{}88ac", PUBLIC_KEY_HASH), hex::encode(Script::new_p2pkh(&public_key_hash).as_bytes()));
+            assert_eq!(format!("a914{}87", PUBLIC_KEY_HASH), hex::encode(Script::new_p2sh(&public_key_hash).as_bytes()));
+            assert_eq!(format!("0014{}", PUBLIC_KEY_HASH), hex::encode(Script::new_p2wpkh(&public_key_hash).as_bytes()));
+            assert_eq!(format!("0020{}", SCRIPT_HASH), hex::encode(Script::new_p2wsh(&script_hash).as_bytes()));
+            assert_eq!(format!("5120{}", SCRIPT_HASH), hex::encode(Script::new_p2tr(&script_hash).as_bytes()));
+            assert_eq!("6a03abcdef", hex::encode(Script::new_op_return(&[0xab, 0xcd, 0xef]).as_bytes()));
+
+            let program = WitnessProgram::new(&hex::decode("10020102").unwrap()).unwrap();
+            assert_eq!("60020102", hex::encode(Script::new_witness_program(&program).as_bytes()));
+        }
+
+        #[test]
+        fn recognise() {
+            let p2pkh = script(&format!("76a914{}88ac", PUBLIC_KEY_HASH));
+            let p2sh = script(&format!("a914{}87", PUBLIC_KEY_HASH));
+            let p2wpkh = script(&format!("0014{}", PUBLIC_KEY_HASH));
+            let p2wsh = script(&format!("0020{}", SCRIPT_HASH));
+            let p2tr = script(&format!("5120{}", SCRIPT_HASH));
+            let multisig = Script::from_asm(ASM_SCRIPTS[1].1).unwrap();
+            let op_return = script("6a0b68656c6c6f20776f726c64");
+
+            let templates = |script: &Script| {
+                [
+                    script.is_p2pkh(),
+                    script.is_p2sh(),
+                    script.is_p2wpkh(),
+                    script.is_p2wsh(),
+                    script.is_p2tr(),
+                    script.is_multisig(),
+                    script.is_op_return(),
+                ]
+            };
+            for (index, script) in [&p2pkh, &p2sh, &p2wpkh, &p2wsh, &p2tr, &multisig, &op_return].iter().enumerate() {
+                let expected: Vec<bool> = (0..7).map(|template| template == index).collect();
+                assert_eq!(expected, templates(script));
+            }
+
+            assert!(!script(&format!("76a914{}88ad", PUBLIC_KEY_HASH)).is_p2pkh());
+            assert!(!script(&format!("a914{}8787", PUBLIC_KEY_HASH)).is_p2sh());
+            assert!(!script(&format!("5220{}", SCRIPT_HASH)).is_p2tr());
+            assert!(!Script::new().is_op_return());
+        }
+
+        #[test]
+        fn witness_program() {
+            let program = script(&format!("0014{}", PUBLIC_KEY_HASH)).witness_program().unwrap();
+            assert_eq!((0, hex::decode(PUBLIC_KEY_HASH).unwrap()), (program.version, program.program));
+
+            let program = script(&format!("5120{}", SCRIPT_HASH)).witness_program().unwrap();
+            assert_eq!((1, hex::decode(SCRIPT_HASH).unwrap()), (program.version, program.program));
+
+            let program = script("60020102").witness_program().unwrap();
+            assert_eq!((16, vec![0x01, 0x02]), (program.version, program.program));
+
+            // Version 0 programs are 20 or 32 bytes, and any program 2 to 40 bytes
+            assert_eq!(None, script("0015000000000000000000000000000000000000000000").witness_program());
+            assert_eq!(None, script("510100").witness_program());
+            assert_eq!(None, script("6102abcd").witness_program());
+            assert_eq!(None, script(&format!("76a914{}88ac", PUBLIC_KEY_HASH)).witness_program());
+        }
+    }
+}
@@ -0,0 +1,152 @@
+//! # Signature Hash Cache
+//!
+//! The parts of the SegWit v0 (BIP-143) and Taproot (BIP-341) signature messages shared by every input
+//! of a transaction, computed once so that signing or verifying all of its inputs takes linear time.
+//! They commit to the outpoints, sequences and spent outputs of the inputs and to the outputs, none of
+//! which change when inputs are signed, so the cache of an unsigned transaction remains valid for its
+//! signed versions.
+//! https://github.com/bitcoin/bips/blob/master/bip-0143.mediawiki#specification
+//! https://github.com/bitcoin/bips/blob/master/bip-0341.mediawiki#common-signature-message
+
+use crate::network::BitcoinNetwork;
+use crate::transaction::{variable_length_integer, BitcoinTransaction};
+use chainlib_core::TransactionError;
+
+use sha2::{Digest, Sha256};
+
+/// Represents the signature hash midstates of a transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SignatureHashCache {
+    /// The SHA256 of the outpoints of the inputs
+    pub(crate) sha_prevouts: [u8; 32],
+    /// The SHA256 of the sequences of the inputs
+    pub(crate) sha_sequences: [u8; 32],
+    /// The SHA256 of the serialized outputs
+    pub(crate) sha_outputs: [u8; 32],
+    /// The SHA256 of the amounts of the spent outputs, if every outpoint carries its amount
+    pub(crate) sha_amounts: Option<[u8; 32]>,
+    /// The SHA256 of the script public keys of the spent outputs, if every outpoint carries its script public key
+    pub(crate) sha_script_pub_keys: Option<[u8; 32]>,
+    /// The double SHA256 of the outpoints of the inputs (hashPrevouts)
+    pub(crate) hash_prevouts: [u8; 32],
+    /// The double SHA256 of the sequences of the inputs (hashSequence)
+    pub(crate) hash_sequence: [u8; 32],
+    /// The double SHA256 of the serialized outputs (hashOutputs)
+    pub(crate) hash_outputs: [u8; 32],
+}
+
+impl SignatureHashCache {
+    /// Returns the signature hash midstates of the given transaction.
+    pub(crate) fn new<N: BitcoinNetwork>(transaction: &BitcoinTransaction<N>) -> Result<Self, TransactionError> {
+        let mut prevouts = Sha256::new();
+        let mut sequences = Sha256::new();
+        let mut amounts = Some(Sha256::new());
+        let mut script_pub_keys = Some(Sha256::new());
+        for input in &transaction.parameters.inputs {
+            prevouts.update(&input.outpoint.reverse_transaction_id);
+            prevouts.update(input.outpoint.index.to_le_bytes());
+            sequences.update(&input.sequence);
+            amounts = match (amounts, &input.outpoint.amount) {
+                (Some(mut amounts), Some(amount)) => {
+                    amounts.update(amount.0.to_le_bytes());
+                    Some(amounts)
+                }
+                _ => None,
+            };
+            script_pub_keys = match (script_pub_keys, &input.outpoint.script_pub_key) {
+                (Some(mut script_pub_keys), Some(script_pub_key)) => {
+                    script_pub_keys.update(variable_length_integer(script_pub_key.len() as u64)?);
+                    script_pub_keys.update(script_pub_key);
+                    Some(script_pub_keys)
+                }
+                _ => None,
+            };
+        }
+
+        let mut outputs = Sha256::new();
+        for output in &transaction.parameters.outputs {
+            outputs.update(output.serialize()?);
+        }
+
+        let sha_prevouts = digest(prevouts);
+        let sha_sequences = digest(sequences);
+        let sha_outputs = digest(outputs);
+        Ok(Self {
+            sha_prevouts,
+            sha_sequences,
+            sha_outputs,
+            sha_amounts: amounts.map(digest),
+            sha_script_pub_keys: script_pub_keys.map(digest),
+            hash_prevouts: digest(Sha256::new_with_prefix(sha_prevouts)),
+            hash_sequence: digest(Sha256::new_with_prefix(sha_sequences)),
+            hash_outputs: digest(Sha256::new_with_prefix(sha_outputs)),
+        })
+    }
+}
+
+fn digest(hasher: Sha256) -> [u8; 32] {
+    hasher.finalize().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amount::BitcoinAmount;
+    use crate::format::BitcoinFormat;
+    use crate::interpreter::VerificationFlags;
+    use crate::network::Mainnet;
+    use crate::private_key::BitcoinPrivateKey;
+    use crate::transaction::{BitcoinTransactionBuilder, BitcoinTransactionInputBuilder};
+    use chainlib_core::{PrivateKey, Transaction};
+    use core::str::FromStr;
+
+    type N = Mainnet;
+
+    // The native P2WPKH example of BIP-143 and its midstates
+    // https://github.com/bitcoin/bips/blob/master/bip-0143.mediawiki#native-p2wpkh
+    const TRANSACTION: &str = "0100000002fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f0000000000eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac11000000";
+    const HASH_PREVOUTS: &str = "96b827c8483d4e9b96712b6713a7b68d6e8003a781feba36c31143470b4efd37";
+    const HASH_SEQUENCE: &str = "52b0a642eea2fb7ae638c36f6252b6750293dbe574a806984b8e4d8548339a3b";
+    const HASH_OUTPUTS: &str = "863ef3e1a92afbfdb97f31ad0fc7683ee943e9abcf2501590ff8f6551f47e5e5";
+
+    const PRIVATE_KEY: &str = "KyRv5iFPHG7iB5E4CqvMzH3WFJVhbfYK4VY7XAedd9Ys69mEsPLQ";
+
+    #[test]
+    fn new() {
+        let transaction = BitcoinTransaction::<N>::from_str(TRANSACTION).unwrap();
+        let cache = SignatureHashCache::new(&transaction).unwrap();
+        assert_eq!(HASH_PREVOUTS, hex::encode(cache.hash_prevouts));
+        assert_eq!(HASH_SEQUENCE, hex::encode(cache.hash_sequence));
+        assert_eq!(HASH_OUTPUTS, hex::encode(cache.hash_outputs));
+        assert_eq!(cache.hash_outputs, <[u8; 32]>::from(Sha256::digest(cache.sha_outputs)));
+
+        // The outpoints of a parsed transaction carry no spent outputs
+        assert_eq!(None, cache.sha_amounts);
+        assert_eq!(None, cache.sha_script_pub_keys);
+    }
+
+    #[test]
+    fn signing() {
+        let private_key = BitcoinPrivateKey::<N>::from_str(PRIVATE_KEY).unwrap();
+        let mut transaction = BitcoinTransactionBuilder::<N>::new();
+        for (index, format) in [BitcoinFormat::P2PKH, BitcoinFormat::Bech32, BitcoinFormat::P2TR].iter().enumerate() {
+            let address = private_key.to_address(format).unwrap().to_string();
+            transaction = transaction.input(
+                BitcoinTransactionInputBuilder::new(&hex::encode([index as u8; 32]), 0)
+                    .address(&address)
+                    .amount(BitcoinAmount(100_000)),
+            );
+        }
+        let mut transaction = transaction
+            .output("1Fyxts6r24DpEieygQiNnWxUdb18ANa5p7", BitcoinAmount(290_000))
+            .build()
+            .unwrap();
+
+        // Signatures are not part of the midstates
+        let cache = SignatureHashCache::new(&transaction).unwrap();
+        assert!(cache.sha_amounts.is_some() && cache.sha_script_pub_keys.is_some());
+        transaction.sign_with_private_key(&private_key).unwrap();
+        assert_eq!(cache, SignatureHashCache::new(&transaction).unwrap());
+        assert!(transaction.verify(VerificationFlags::STANDARD).iter().all(Result::is_ok));
+    }
+}
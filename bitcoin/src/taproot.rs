@@ -0,0 +1,674 @@
+//!
+//! Taproot
+//!
+//! This module contains the BIP-340 Schnorr signature scheme and the BIP-341 key tweaking used by
+//! pay-to-taproot (P2TR) outputs.
+//!
+//! - A P2TR output commits to a 32-byte x-only output key Q = P + tG, where P is the internal key
+//!   lifted to its even y coordinate and t = hashTapTweak(P || merkle root).
+//! - Without a script tree (BIP-86) the merkle root is omitted from the tweak.
+//! - A key-path spend is a single 64-byte Schnorr signature for Q, followed by the sighash byte
+//!   unless it is SIGHASH_DEFAULT.
+//! - A script-path spend reveals one leaf script of the tree, followed by a control block holding the
+//!   leaf version, the parity of Q, P and the merkle path proving that the leaf is committed to by Q.
+//!
+
+use chainlib_core::libsecp256k1::{
+    curve::{Affine, Field, Jacobian, Scalar},
+    PublicKey, SecretKey, ECMULT_CONTEXT,
+};
+use crate::transaction::variable_length_integer;
+use chainlib_core::no_std::*;
+use chainlib_core::{AddressError, TransactionError};
+
+use core::convert::TryFrom;
+use sha2::{Digest, Sha256};
+
+/// The leaf version of BIP-342 tapscript
+pub const TAPROOT_LEAF_TAPSCRIPT: u8 = 0xc0;
+/// The leaf version bits of the first byte of a control block, the last bit being the output key parity
+const TAPROOT_LEAF_MASK: u8 = 0xfe;
+/// The size of a control block without merkle path
+const TAPROOT_CONTROL_BASE_SIZE: usize = 33;
+/// The size of a node of the merkle path of a control block
+const TAPROOT_CONTROL_NODE_SIZE: usize = 32;
+/// The maximum depth of a script tree
+const TAPROOT_CONTROL_MAX_NODE_COUNT: usize = 128;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum TaprootError {
+    #[error("invalid control block length {0}")]
+    InvalidControlBlockLength(usize),
+
+    #[error("invalid leaf version {0}")]
+    InvalidLeafVersion(u8),
+
+    #[error("invalid nonce")]
+    InvalidNonce,
+
+    #[error("invalid x-only public key {0}")]
+    InvalidPublicKey(String),
+
+    #[error("invalid secret key")]
+    InvalidSecretKey,
+
+    #[error("invalid tweak {0}")]
+    InvalidTweak(String),
+
+    #[error("leaf script {0} is not in the script tree")]
+    MissingLeaf(String),
+
+    #[error("script tree depth {0} exceeds 128")]
+    TreeTooDeep(usize),
+}
+
+impl From<TaprootError> for AddressError {
+    fn from(error: TaprootError) -> Self {
+        AddressError::Crate("Taproot", format!("{:?}", error))
+    }
+}
+
+impl From<TaprootError> for TransactionError {
+    fn from(error: TaprootError) -> Self {
+        TransactionError::Crate("Taproot", format!("{:?}", error))
+    }
+}
+
+/// Returns the BIP-340 tagged hash SHA256(SHA256(tag) || SHA256(tag) || data).
+pub fn tagged_hash(tag: &str, data: &[u8]) -> [u8; 32] {
+    let tag = Sha256::digest(tag.as_bytes());
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(&Sha256::digest([&tag[..], &tag[..], data].concat()));
+    hash
+}
+
+/// Represents a leaf of a taproot script tree
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TapLeaf {
+    /// The leaf version, 0xc0 for tapscript
+    pub version: u8,
+    /// The leaf script
+    pub script: Vec<u8>,
+}
+
+impl TapLeaf {
+    /// Returns a tapscript leaf of the given script.
+    pub fn new(script: Vec<u8>) -> Self {
+        Self {
+            version: TAPROOT_LEAF_TAPSCRIPT,
+            script,
+        }
+    }
+
+    /// Returns a leaf of the given script and leaf version, which must have its last bit unset.
+    pub fn with_version(script: Vec<u8>, version: u8) -> Result<Self, TaprootError> {
+        match version & TAPROOT_LEAF_MASK == version {
+            true => Ok(Self { version, script }),
+            false => Err(TaprootError::InvalidLeafVersion(version)),
+        }
+    }
+
+    /// Returns the leaf hash, hashTapLeaf(version || compact size(script) || script).
+    pub fn hash(&self) -> [u8; 32] {
+        let mut data = vec![self.version];
+        data.extend(variable_length_integer(self.script.len() as u64).expect("compact size encoding is infallible"));
+        data.extend(&self.script);
+        tagged_hash("TapLeaf", &data)
+    }
+}
+
+/// Represents a taproot script tree, whose branches are hashed in lexicographic order
+/// https://github.com/bitcoin/bips/blob/master/bip-0341.mediawiki#constructing-and-spending-taproot-outputs
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TapTree {
+    Leaf(TapLeaf),
+    Branch(Box<TapTree>, Box<TapTree>),
+}
+
+impl TapTree {
+    /// Returns a tree of a single tapscript leaf.
+    pub fn leaf(script: Vec<u8>) -> Self {
+        TapTree::Leaf(TapLeaf::new(script))
+    }
+
+    /// Returns a tree joining the two given trees.
+    pub fn branch(left: TapTree, right: TapTree) -> Self {
+        TapTree::Branch(Box::new(left), Box::new(right))
+    }
+
+    /// Returns the merkle root of the tree.
+    pub fn merkle_root(&self) -> [u8; 32] {
+        match self {
+            TapTree::Leaf(leaf) => leaf.hash(),
+            TapTree::Branch(left, right) => branch_hash(&left.merkle_root(), &right.merkle_root()),
+        }
+    }
+
+    /// Returns the leaves of the tree from left to right, each with its merkle path from the leaf up to the root.
+    pub fn leaves(&self) -> Vec<(TapLeaf, Vec<[u8; 32]>)> {
+        match self {
+            TapTree::Leaf(leaf) => vec![(leaf.clone(), vec![])],
+            TapTree::Branch(left, right) => {
+                let (left_hash, right_hash) = (left.merkle_root(), right.merkle_root());
+                let mut leaves = left.leaves();
+                leaves.iter_mut().for_each(|(_, path)| path.push(right_hash));
+                let mut right_leaves = right.leaves();
+                right_leaves.iter_mut().for_each(|(_, path)| path.push(left_hash));
+                leaves.extend(right_leaves);
+                leaves
+            }
+        }
+    }
+
+    /// Returns the control block spending the output of the internal key and this tree through the given leaf.
+    pub fn control_block(&self, internal_key: &[u8; 32], leaf: &TapLeaf) -> Result<ControlBlock, TaprootError> {
+        let merkle_path = match self.leaves().into_iter().find(|(candidate, _)| candidate == leaf) {
+            Some((_, merkle_path)) => merkle_path,
+            None => return Err(TaprootError::MissingLeaf(hex::encode(&leaf.script))),
+        };
+        if merkle_path.len() > TAPROOT_CONTROL_MAX_NODE_COUNT {
+            return Err(TaprootError::TreeTooDeep(merkle_path.len()));
+        }
+
+        let (_, output_key_parity) = tweak_public_key(internal_key, Some(&self.merkle_root()))?;
+        Ok(ControlBlock {
+            leaf_version: leaf.version,
+            output_key_parity,
+            internal_key: *internal_key,
+            merkle_path,
+        })
+    }
+}
+
+/// Represents the control block of a script path spend
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ControlBlock {
+    /// The leaf version of the spent leaf
+    pub leaf_version: u8,
+    /// If true, the output key has an odd y coordinate
+    pub output_key_parity: bool,
+    /// The x-only internal key
+    pub internal_key: [u8; 32],
+    /// The merkle path from the spent leaf up to the root
+    pub merkle_path: Vec<[u8; 32]>,
+}
+
+impl ControlBlock {
+    /// Returns the serialized control block.
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut control_block = vec![self.leaf_version | self.output_key_parity as u8];
+        control_block.extend(&self.internal_key);
+        self.merkle_path.iter().for_each(|node| control_block.extend(node));
+        control_block
+    }
+
+    /// Returns the control block of the given bytes.
+    pub fn deserialize(control_block: &[u8]) -> Result<Self, TaprootError> {
+        let length = control_block.len();
+        let node_count = length.saturating_sub(TAPROOT_CONTROL_BASE_SIZE) / TAPROOT_CONTROL_NODE_SIZE;
+        if length != TAPROOT_CONTROL_BASE_SIZE + node_count * TAPROOT_CONTROL_NODE_SIZE
+            || node_count > TAPROOT_CONTROL_MAX_NODE_COUNT
+        {
+            return Err(TaprootError::InvalidControlBlockLength(length));
+        }
+
+        let mut internal_key = [0u8; 32];
+        internal_key.copy_from_slice(&control_block[1..TAPROOT_CONTROL_BASE_SIZE]);
+        let merkle_path = control_block[TAPROOT_CONTROL_BASE_SIZE..]
+            .chunks_exact(TAPROOT_CONTROL_NODE_SIZE)
+            .map(|chunk| {
+                let mut node = [0u8; 32];
+                node.copy_from_slice(chunk);
+                node
+            })
+            .collect();
+
+        Ok(Self {
+            leaf_version: control_block[0] & TAPROOT_LEAF_MASK,
+            output_key_parity: control_block[0] & 0x01 == 0x01,
+            internal_key,
+            merkle_path,
+        })
+    }
+
+    /// Returns `true` if the control block proves that the output key commits to the given leaf script.
+    /// https://github.com/bitcoin/bips/blob/master/bip-0341.mediawiki#script-validation-rules
+    pub fn verify(&self, output_key: &[u8; 32], script: &[u8]) -> bool {
+        let leaf = TapLeaf {
+            version: self.leaf_version,
+            script: script.to_vec(),
+        };
+        let merkle_root = self.merkle_path.iter().fold(leaf.hash(), |hash, node| branch_hash(&hash, node));
+        match tweak_public_key(&self.internal_key, Some(&merkle_root)) {
+            Ok((key, parity)) => &key == output_key && parity == self.output_key_parity,
+            Err(_) => false,
+        }
+    }
+}
+
+/// Returns the branch hash of two nodes, hashTapBranch(min(a, b) || max(a, b)).
+fn branch_hash(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    match a < b {
+        true => tagged_hash("TapBranch", &[&a[..], &b[..]].concat()),
+        false => tagged_hash("TapBranch", &[&b[..], &a[..]].concat()),
+    }
+}
+
+/// Returns the x-only encoding of the public key and whether its y coordinate is odd.
+pub fn x_only_public_key(public_key: &PublicKey) -> ([u8; 32], bool) {
+    let compressed = public_key.serialize_compressed();
+    let mut x = [0u8; 32];
+    x.copy_from_slice(&compressed[1..]);
+    (x, compressed[0] == 0x03)
+}
+
+/// Returns the point with the given x coordinate and an even y coordinate.
+pub fn lift_x(x: &[u8; 32]) -> Result<PublicKey, TaprootError> {
+    let mut compressed = [0x02; 33];
+    compressed[1..].copy_from_slice(x);
+    PublicKey::parse_compressed(&compressed).map_err(|_| TaprootError::InvalidPublicKey(hex::encode(x)))
+}
+
+/// Returns the tweak committing the internal key to the merkle root of its script tree, if any.
+fn taproot_tweak(internal_key: &[u8; 32], merkle_root: Option<&[u8; 32]>) -> Result<SecretKey, TaprootError> {
+    let mut data = internal_key.to_vec();
+    if let Some(merkle_root) = merkle_root {
+        data.extend(merkle_root);
+    }
+    let tweak = tagged_hash("TapTweak", &data);
+    SecretKey::parse(&tweak).map_err(|_| TaprootError::InvalidTweak(hex::encode(tweak)))
+}
+
+/// Returns the x-only output key of the given x-only internal key and whether its y coordinate is odd.
+/// https://github.com/bitcoin/bips/blob/master/bip-0341.mediawiki#constructing-and-spending-taproot-outputs
+pub fn tweak_public_key(internal_key: &[u8; 32], merkle_root: Option<&[u8; 32]>) -> Result<([u8; 32], bool), TaprootError> {
+    let mut output_key = lift_x(internal_key)?;
+    let tweak = taproot_tweak(internal_key, merkle_root)?;
+    output_key
+        .tweak_add_assign(&tweak)
+        .map_err(|_| TaprootError::InvalidTweak(hex::encode(tweak.serialize())))?;
+    Ok(x_only_public_key(&output_key))
+}
+
+/// Returns the secret key of the output key committing to the given merkle root.
+pub fn tweak_secret_key(secret_key: &SecretKey, merkle_root: Option<&[u8; 32]>) -> Result<SecretKey, TaprootError> {
+    let (internal_key, odd) = x_only_public_key(&PublicKey::from_secret_key(secret_key));
+    let secret_key = even_secret_key(secret_key, odd);
+    let tweak: Scalar = taproot_tweak(&internal_key, merkle_root)?.into();
+    SecretKey::try_from(secret_key + tweak).map_err(|_| TaprootError::InvalidSecretKey)
+}
+
+/// Returns the BIP-340 signature of the message, of any length, with the given auxiliary randomness.
+/// https://github.com/bitcoin/bips/blob/master/bip-0340.mediawiki#default-signing
+pub fn schnorr_sign(message: &[u8], secret_key: &SecretKey, aux_rand: &[u8; 32]) -> Result<[u8; 64], TaprootError> {
+    let (public_key, odd) = x_only_public_key(&PublicKey::from_secret_key(secret_key));
+    let d = even_secret_key(secret_key, odd);
+
+    let mut t = d.b32();
+    t.iter_mut()
+        .zip(tagged_hash("BIP0340/aux", aux_rand).iter())
+        .for_each(|(t, a)| *t ^= a);
+    let nonce = tagged_hash("BIP0340/nonce", &[&t[..], &public_key, message].concat());
+    let k = SecretKey::try_from(reduce(&nonce)).map_err(|_| TaprootError::InvalidNonce)?;
+    let (r, odd) = x_only_public_key(&PublicKey::from_secret_key(&k));
+    let k = even_secret_key(&k, odd);
+
+    let e = reduce(&tagged_hash("BIP0340/challenge", &[&r[..], &public_key, message].concat()));
+    let s = k + e * d;
+
+    let mut signature = [0u8; 64];
+    signature[..32].copy_from_slice(&r);
+    signature[32..].copy_from_slice(&s.b32());
+    match schnorr_verify(message, &public_key, &signature) {
+        true => Ok(signature),
+        false => Err(TaprootError::InvalidSecretKey),
+    }
+}
+
+/// Returns `true` if the signature is a valid BIP-340 signature of the message for the x-only public key.
+/// https://github.com/bitcoin/bips/blob/master/bip-0340.mediawiki#verification
+pub fn schnorr_verify(message: &[u8], public_key: &[u8; 32], signature: &[u8; 64]) -> bool {
+    let point: Affine = match lift_x(public_key) {
+        Ok(point) => point.into(),
+        Err(_) => return false,
+    };
+
+    let mut r_bytes = [0u8; 32];
+    r_bytes.copy_from_slice(&signature[..32]);
+    let mut r = Field::default();
+    if !r.set_b32(&r_bytes) {
+        return false;
+    }
+    let mut s_bytes = [0u8; 32];
+    s_bytes.copy_from_slice(&signature[32..]);
+    let mut s = Scalar::default();
+    if bool::from(s.set_b32(&s_bytes)) {
+        return false;
+    }
+
+    // R = sG - eP
+    let e = reduce(&tagged_hash("BIP0340/challenge", &[&r_bytes[..], public_key, message].concat()));
+    let mut point_r = Jacobian::default();
+    ECMULT_CONTEXT.ecmult(&mut point_r, &Jacobian::from_ge(&point), &-e, &s);
+    if point_r.is_infinity() {
+        return false;
+    }
+
+    let mut point_r = Affine::from_gej(&point_r);
+    point_r.x.normalize();
+    point_r.y.normalize();
+    !point_r.y.is_odd() && point_r.x == r
+}
+
+/// Returns the scalar of the secret key, negated if its public key has an odd y coordinate.
+fn even_secret_key(secret_key: &SecretKey, odd: bool) -> Scalar {
+    let scalar: Scalar = (*secret_key).into();
+    match odd {
+        true => -scalar,
+        false => scalar,
+    }
+}
+
+/// Returns the 32-byte big-endian integer reduced modulo the curve order.
+fn reduce(bytes: &[u8; 32]) -> Scalar {
+    let mut scalar = Scalar::default();
+    let _ = scalar.set_b32(bytes);
+    scalar
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode32(hex_str: &str) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        bytes.copy_from_slice(&hex::decode(hex_str).unwrap());
+        bytes
+    }
+
+    mod schnorr {
+        use super::*;
+
+        // https://github.com/bitcoin/bips/blob/master/bip-0340/test-vectors.csv
+        // (index, secret key, public key, aux rand, message, signature, verification result, comment)
+        type Vector = (usize, &'static str, &'static str, &'static str, &'static str, &'static str, bool, &'static str);
+
+        #[rustfmt::skip]
+        const VECTORS: [Vector; 19] = [
+            (0, "0000000000000000000000000000000000000000000000000000000000000003", "F9308A019258C31049344F85F89D5229B531C845836F99B08601F113BCE036F9", "0000000000000000000000000000000000000000000000000000000000000000",
+             "0000000000000000000000000000000000000000000000000000000000000000",
+             "E907831F80848D1069A5371B402410364BDF1C5F8307B0084C55F1CE2DCA821525F66A4A85EA8B71E482A74F382D2CE5EBEEE8FDB2172F477DF4900D310536C0", true, ""),
+            (1, "B7E151628AED2A6ABF7158809CF4F3C762E7160F38B4DA56A784D9045190CFEF", "DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659", "0000000000000000000000000000000000000000000000000000000000000001",
+             "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89",
+             "6896BD60EEAE296DB48A229FF71DFE071BDE413E6D43F917DC8DCF8C78DE33418906D11AC976ABCCB20B091292BFF4EA897EFCB639EA871CFA95F6DE339E4B0A", true, ""),
+            (2, "C90FDAA22168C234C4C6628B80DC1CD129024E088A67CC74020BBEA63B14E5C9", "DD308AFEC5777E13121FA72B9CC1B7CC0139715309B086C960E18FD969774EB8", "C87AA53824B4D7AE2EB035A2B5BBBCCC080E76CDC6D1692C4B0B62D798E6D906",
+             "7E2D58D8B3BCDF1ABADEC7829054F90DDA9805AAB56C77333024B9D0A508B75C",
+             "5831AAEED7B44BB74E5EAB94BA9D4294C49BCF2A60728D8B4C200F50DD313C1BAB745879A5AD954A72C45A91C3A51D3C7ADEA98D82F8481E0E1E03674A6F3FB7", true, ""),
+            (3, "0B432B2677937381AEF05BB02A66ECD012773062CF3FA2549E44F58ED2401710", "25D1DFF95105F5253C4022F628A996AD3A0D95FBF21D468A1B33F8C160D8F517", "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF",
+             "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF",
+             "7EB0509757E246F19449885651611CB965ECC1A187DD51B64FDA1EDC9637D5EC97582B9CB13DB3933705B32BA982AF5AF25FD78881EBB32771FC5922EFC66EA3", true, ""),
+            (4, "", "D69C3509BB99E412E68B0FE8544E72837DFA30746D8BE2AA65975F29D22DC7B9", "",
+             "4DF3C3F68FCC83B27E9D42C90431A72499F17875C81A599B566C9889B9696703",
+             "00000000000000000000003B78CE563F89A0ED9414F5AA28AD0D96D6795F9C6376AFB1548AF603B3EB45C9F8207DEE1060CB71C04E80F593060B07D28308D7F4", true, ""),
+            (5, "", "EEFDEA4CDB677750A420FEE807EACF21EB9898AE79B9768766E4FAA04A2D4A34", "",
+             "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89",
+             "6CFF5C3BA86C69EA4B7376F31A9BCB4F74C1976089B2D9963DA2E5543E17776969E89B4C5564D00349106B8497785DD7D1D713A8AE82B32FA79D5F7FC407D39B", false, "public key not on the curve"),
+            (6, "", "DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659", "",
+             "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89",
+             "FFF97BD5755EEEA420453A14355235D382F6472F8568A18B2F057A14602975563CC27944640AC607CD107AE10923D9EF7A73C643E166BE5EBEAFA34B1AC553E2", false, "has_even_y(R) is false"),
+            (7, "", "DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659", "",
+             "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89",
+             "1FA62E331EDBC21C394792D2AB1100A7B432B013DF3F6FF4F99FCB33E0E1515F28890B3EDB6E7189B630448B515CE4F8622A954CFE545735AAEA5134FCCDB2BD", false, "negated message"),
+            (8, "", "DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659", "",
+             "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89",
+             "6CFF5C3BA86C69EA4B7376F31A9BCB4F74C1976089B2D9963DA2E5543E177769961764B3AA9B2FFCB6EF947B6887A226E8D7C93E00C5ED0C1834FF0D0C2E6DA6", false, "negated s value"),
+            (9, "", "DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659", "",
+             "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89",
+             "0000000000000000000000000000000000000000000000000000000000000000123DDA8328AF9C23A94C1FEECFD123BA4FB73476F0D594DCB65C6425BD186051", false, "sG - eP is infinite, with x(inf) as 0"),
+            (10, "", "DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659", "",
+             "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89",
+             "00000000000000000000000000000000000000000000000000000000000000017615FBAF5AE28864013C099742DEADB4DBA87F11AC6754F93780D5A1837CF197", false, "sG - eP is infinite, with x(inf) as 1"),
+            (11, "", "DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659", "",
+             "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89",
+             "4A298DACAE57395A15D0795DDBFD1DCB564DA82B0F269BC70A74F8220429BA1D69E89B4C5564D00349106B8497785DD7D1D713A8AE82B32FA79D5F7FC407D39B", false, "sig[0:32] is not an X coordinate on the curve"),
+            (12, "", "DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659", "",
+             "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89",
+             "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEFFFFFC2F69E89B4C5564D00349106B8497785DD7D1D713A8AE82B32FA79D5F7FC407D39B", false, "sig[0:32] is equal to field size"),
+            (13, "", "DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659", "",
+             "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89",
+             "6CFF5C3BA86C69EA4B7376F31A9BCB4F74C1976089B2D9963DA2E5543E177769FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364141", false, "sig[32:64] is equal to curve order"),
+            (14, "", "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEFFFFFC30", "",
+             "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89",
+             "6CFF5C3BA86C69EA4B7376F31A9BCB4F74C1976089B2D9963DA2E5543E17776969E89B4C5564D00349106B8497785DD7D1D713A8AE82B32FA79D5F7FC407D39B", false, "public key is not a valid X coordinate because it exceeds the field size"),
+            (15, "0340034003400340034003400340034003400340034003400340034003400340", "778CAA53B4393AC467774D09497A87224BF9FAB6F6E68B23086497324D6FD117", "0000000000000000000000000000000000000000000000000000000000000000",
+             "",
+             "71535DB165ECD9FBBC046E5FFAEA61186BB6AD436732FCCC25291A55895464CF6069CE26BF03466228F19A3A62DB8A649F2D560FAC652827D1AF0574E427AB63", true, "message of size 0"),
+            (16, "0340034003400340034003400340034003400340034003400340034003400340", "778CAA53B4393AC467774D09497A87224BF9FAB6F6E68B23086497324D6FD117", "0000000000000000000000000000000000000000000000000000000000000000",
+             "11",
+             "08A20A0AFEF64124649232E0693C583AB1B9934AE63B4C3511F3AE1134C6A303EA3173BFEA6683BD101FA5AA5DBC1996FE7CACFC5A577D33EC14564CEC2BACBF", true, "message of size 1"),
+            (17, "0340034003400340034003400340034003400340034003400340034003400340", "778CAA53B4393AC467774D09497A87224BF9FAB6F6E68B23086497324D6FD117", "0000000000000000000000000000000000000000000000000000000000000000",
+             "0102030405060708090A0B0C0D0E0F1011",
+             "5130F39A4059B43BC7CAC09A19ECE52B5D8699D1A71E3C52DA9AFDB6B50AC370C4A482B77BF960F8681540E25B6771ECE1E5A37FD80E5A51897C5566A97EA5A5", true, "message of size 17"),
+            (18, "0340034003400340034003400340034003400340034003400340034003400340", "778CAA53B4393AC467774D09497A87224BF9FAB6F6E68B23086497324D6FD117", "0000000000000000000000000000000000000000000000000000000000000000",
+             "99999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999",
+             "403B12B0D8555A344175EA7EC746566303321E5DBFA8BE6F091635163ECA79A8585ED3E3170807E7C03B720FC54C7B23897FCBA0E9D0B4A06894CFD249F22367", true, "message of size 100"),
+        ];
+
+        #[test]
+        fn sign() {
+            VECTORS.iter().filter(|vector| !vector.1.is_empty()).for_each(|(index, secret_key, public_key, aux_rand, message, expected_signature, _, _)| {
+                let secret_key = SecretKey::parse(&decode32(secret_key)).unwrap();
+                let (x_only, _) = x_only_public_key(&PublicKey::from_secret_key(&secret_key));
+                assert_eq!(public_key.to_lowercase(), hex::encode(x_only), "vector {}", index);
+
+                let signature = schnorr_sign(&hex::decode(message).unwrap(), &secret_key, &decode32(aux_rand)).unwrap();
+                assert_eq!(expected_signature.to_lowercase(), hex::encode(signature), "vector {}", index);
+            });
+        }
+
+        #[test]
+        fn verify() {
+            VECTORS.iter().for_each(|(index, _, public_key, _, message, signature, result, comment)| {
+                let mut signature_bytes = [0u8; 64];
+                signature_bytes.copy_from_slice(&hex::decode(signature).unwrap());
+                let verified = schnorr_verify(&hex::decode(message).unwrap(), &decode32(public_key), &signature_bytes);
+                assert_eq!(*result, verified, "vector {}: {}", index, comment);
+            });
+        }
+
+        #[test]
+        fn invalid_public_key() {
+            // x = 5 is not on the curve
+            let public_key = decode32("0000000000000000000000000000000000000000000000000000000000000005");
+            assert!(lift_x(&public_key).is_err());
+            assert!(!schnorr_verify(&[0u8; 32], &public_key, &[0u8; 64]));
+        }
+    }
+
+    mod tweak {
+        use super::*;
+
+        // https://github.com/bitcoin/bips/blob/master/bip-0341/wallet-test-vectors.json
+        // https://github.com/bitcoin/bips/blob/master/bip-0086.mediawiki#test-vectors
+        // (internal key, merkle root, output key)
+        const VECTORS: [(&str, Option<&str>, &str); 4] = [
+            (
+                "d6889cb081036e0faefa3a35157ad71086b123b2b144b649798b494c300a961d",
+                None,
+                "53a1f6e454df1aa2776a2814a721372d6258050de330b3c6d10ee8f4e0dda343",
+            ),
+            (
+                "187791b6f712a8ea41c8ecdd0ee77fab3e85263b37e1ec18a3651926b3a6cf27",
+                Some("5b75adecf53548f3ec6ad7d78383bf84cc57b55a3127c72b9a2481752dd88b21"),
+                "147c9c57132f6e7ecddba9800bb0c4449251c92a1e60371ee77557b6620f3ea3",
+            ),
+            (
+                "93478e9488f956df2396be2ce6c5cced75f900dfa18e7dabd2428aae78451820",
+                Some("c525714a7f49c28aedbbba78c005931a81c234b2f6c99a73e4d06082adc8bf2b"),
+                "e4d810fd50586274face62b8a807eb9719cef49c04177cc6b76a9a4251d5450e",
+            ),
+            (
+                "cc8a4bc64d897bddc5fbc2f670f7a8ba0b386779106cf1223c6fc5d7cd6fc115",
+                None,
+                "a60869f0dbcf1dc659c9cecbaf8050135ea9e8cdc487053f1dc6880949dc684c",
+            ),
+        ];
+
+        #[test]
+        fn tweak_public_key() {
+            VECTORS.iter().for_each(|(internal_key, merkle_root, expected_output_key)| {
+                let merkle_root = merkle_root.map(decode32);
+                let (output_key, _) = super::super::tweak_public_key(&decode32(internal_key), merkle_root.as_ref()).unwrap();
+                assert_eq!(*expected_output_key, hex::encode(output_key));
+            });
+        }
+
+        #[test]
+        fn tweak_secret_key() {
+            // https://github.com/bitcoin/bips/blob/master/bip-0341/wallet-test-vectors.json (keyPathSpending)
+            let secret_key =
+                SecretKey::parse(&decode32("6b973d88838f27366ed61c9ad6367663045cb456e28335c109e30717ae0c6baa")).unwrap();
+            let tweaked = super::super::tweak_secret_key(&secret_key, None).unwrap();
+            assert_eq!(
+                "2405b971772ad26915c8dcdf10f238753a9b837e5f8e6a86fd7c0cce5b7296d9",
+                hex::encode(tweaked.serialize())
+            );
+
+            // The tweaked secret key signs for the tweaked output key
+            let (internal_key, _) = x_only_public_key(&PublicKey::from_secret_key(&secret_key));
+            let (output_key, _) = super::super::tweak_public_key(&internal_key, None).unwrap();
+            assert_eq!(output_key, x_only_public_key(&PublicKey::from_secret_key(&tweaked)).0);
+        }
+    }
+
+    mod script_tree {
+        use super::*;
+
+        fn leaf(script: &str) -> TapTree {
+            TapTree::leaf(hex::decode(script).unwrap())
+        }
+
+        // https://github.com/bitcoin/bips/blob/master/bip-0341/wallet-test-vectors.json
+        // (internal key, script tree, merkle root, output key, control blocks of the leaves from left to right)
+        fn vectors() -> Vec<(&'static str, TapTree, &'static str, &'static str, Vec<&'static str>)> {
+            vec![
+                (
+                    "187791b6f712a8ea41c8ecdd0ee77fab3e85263b37e1ec18a3651926b3a6cf27",
+                    leaf("20d85a959b0290bf19bb89ed43c916be835475d013da4b362117393e25a48229b8ac"),
+                    "5b75adecf53548f3ec6ad7d78383bf84cc57b55a3127c72b9a2481752dd88b21",
+                    "147c9c57132f6e7ecddba9800bb0c4449251c92a1e60371ee77557b6620f3ea3",
+                    vec!["c1187791b6f712a8ea41c8ecdd0ee77fab3e85263b37e1ec18a3651926b3a6cf27"],
+                ),
+                (
+                    "93478e9488f956df2396be2ce6c5cced75f900dfa18e7dabd2428aae78451820",
+                    leaf("20b617298552a72ade070667e86ca63b8f5789a9fe8731ef91202a91c9f3459007ac"),
+                    "c525714a7f49c28aedbbba78c005931a81c234b2f6c99a73e4d06082adc8bf2b",
+                    "e4d810fd50586274face62b8a807eb9719cef49c04177cc6b76a9a4251d5450e",
+                    vec!["c093478e9488f956df2396be2ce6c5cced75f900dfa18e7dabd2428aae78451820"],
+                ),
+                (
+                    "ee4fe085983462a184015d1f782d6a5f8b9c2b60130aff050ce221ecf3786592",
+                    TapTree::branch(
+                        leaf("20387671353e273264c495656e27e39ba899ea8fee3bb69fb2a680e22093447d48ac"),
+                        TapTree::Leaf(TapLeaf::with_version(hex::decode("06424950333431").unwrap(), 0xfa).unwrap()),
+                    ),
+                    "6c2dc106ab816b73f9d07e3cd1ef2c8c1256f519748e0813e4edd2405d277bef",
+                    "712447206d7a5238acc7ff53fbe94a3b64539ad291c7cdbc490b7577e4b17df5",
+                    vec![
+                        "c0ee4fe085983462a184015d1f782d6a5f8b9c2b60130aff050ce221ecf3786592f224a923cd0021ab202ab139cc56802ddb92dcfc172b9212261a539df79a112a",
+                        "faee4fe085983462a184015d1f782d6a5f8b9c2b60130aff050ce221ecf37865928ad69ec7cf41c2a4001fd1f738bf1e505ce2277acdcaa63fe4765192497f47a7",
+                    ],
+                ),
+                (
+                    "f9f400803e683727b14f463836e1e78e1c64417638aa066919291a225f0e8dd8",
+                    TapTree::branch(
+                        leaf("2044b178d64c32c4a05cc4f4d1407268f764c940d20ce97abfd44db5c3592b72fdac"),
+                        leaf("07546170726f6f74"),
+                    ),
+                    "ab179431c28d3b68fb798957faf5497d69c883c6fb1e1cd9f81483d87bac90cc",
+                    "77e30a5522dd9f894c3f8b8bd4c4b2cf82ca7da8a3ea6a239655c39c050ab220",
+                    vec![
+                        "c1f9f400803e683727b14f463836e1e78e1c64417638aa066919291a225f0e8dd82cb2b90daa543b544161530c925f285b06196940d6085ca9474d41dc3822c5cb",
+                        "c1f9f400803e683727b14f463836e1e78e1c64417638aa066919291a225f0e8dd864512fecdb5afa04f98839b50e6f0cb7b1e539bf6f205f67934083cdcc3c8d89",
+                    ],
+                ),
+                (
+                    "e0dfe2300b0dd746a3f8674dfd4525623639042569d829c7f0eed9602d263e6f",
+                    TapTree::branch(
+                        leaf("2072ea6adcf1d371dea8fba1035a09f3d24ed5a059799bae114084130ee5898e69ac"),
+                        TapTree::branch(
+                            leaf("202352d137f2f3ab38d1eaa976758873377fa5ebb817372c71e2c542313d4abda8ac"),
+                            leaf("207337c0dd4253cb86f2c43a2351aadd82cccb12a172cd120452b9bb8324f2186aac"),
+                        ),
+                    ),
+                    "ccbd66c6f7e8fdab47b3a486f59d28262be857f30d4773f2d5ea47f7761ce0e2",
+                    "91b64d5324723a985170e4dc5a0f84c041804f2cd12660fa5dec09fc21783605",
+                    vec![
+                        "c0e0dfe2300b0dd746a3f8674dfd4525623639042569d829c7f0eed9602d263e6fffe578e9ea769027e4f5a3de40732f75a88a6353a09d767ddeb66accef85e553",
+                        "c0e0dfe2300b0dd746a3f8674dfd4525623639042569d829c7f0eed9602d263e6f9e31407bffa15fefbf5090b149d53959ecdf3f62b1246780238c24501d5ceaf62645a02e0aac1fe69d69755733a9b7621b694bb5b5cde2bbfc94066ed62b9817",
+                        "c0e0dfe2300b0dd746a3f8674dfd4525623639042569d829c7f0eed9602d263e6fba982a91d4fc552163cb1c0da03676102d5b7a014304c01f0c77b2b8e888de1c2645a02e0aac1fe69d69755733a9b7621b694bb5b5cde2bbfc94066ed62b9817",
+                    ],
+                ),
+                (
+                    "55adf4e8967fbd2e29f20ac896e60c3b0f1d5b0efa9d34941b5958c7b0a0312d",
+                    TapTree::branch(
+                        leaf("2071981521ad9fc9036687364118fb6ccd2035b96a423c59c5430e98310a11abe2ac"),
+                        TapTree::branch(
+                            leaf("20d5094d2dbe9b76e2c245a2b89b6006888952e2faa6a149ae318d69e520617748ac"),
+                            leaf("20c440b462ad48c7a77f94cd4532d8f2119dcebbd7c9764557e62726419b08ad4cac"),
+                        ),
+                    ),
+                    "2f6b2c5397b6d68ca18e09a3f05161668ffe93a988582d55c6f07bd5b3329def",
+                    "75169f4001aa68f15bbed28b218df1d0a62cbbcf1188c6665110c293c907b831",
+                    vec![
+                        "c155adf4e8967fbd2e29f20ac896e60c3b0f1d5b0efa9d34941b5958c7b0a0312d3cd369a528b326bc9d2133cbd2ac21451acb31681a410434672c8e34fe757e91",
+                        "c155adf4e8967fbd2e29f20ac896e60c3b0f1d5b0efa9d34941b5958c7b0a0312dd7485025fceb78b9ed667db36ed8b8dc7b1f0b307ac167fa516fe4352b9f4ef7f154e8e8e17c31d3462d7132589ed29353c6fafdb884c5a6e04ea938834f0d9d",
+                        "c155adf4e8967fbd2e29f20ac896e60c3b0f1d5b0efa9d34941b5958c7b0a0312d737ed1fe30bc42b8022d717b44f0d93516617af64a64753b7a06bf16b26cd711f154e8e8e17c31d3462d7132589ed29353c6fafdb884c5a6e04ea938834f0d9d",
+                    ],
+                ),
+            ]
+        }
+
+        #[test]
+        fn merkle_root() {
+            vectors().iter().for_each(|(internal_key, tree, merkle_root, output_key, _)| {
+                assert_eq!(*merkle_root, hex::encode(tree.merkle_root()));
+                let (key, _) = tweak_public_key(&decode32(internal_key), Some(&tree.merkle_root())).unwrap();
+                assert_eq!(*output_key, hex::encode(key));
+            });
+        }
+
+        #[test]
+        fn control_block() {
+            vectors().iter().for_each(|(internal_key, tree, _, output_key, control_blocks)| {
+                let leaves = tree.leaves();
+                assert_eq!(control_blocks.len(), leaves.len());
+                leaves.iter().zip(control_blocks).for_each(|((leaf, _), expected_control_block)| {
+                    let control_block = tree.control_block(&decode32(internal_key), leaf).unwrap();
+                    assert_eq!(*expected_control_block, hex::encode(control_block.serialize()));
+                    assert_eq!(control_block, ControlBlock::deserialize(&control_block.serialize()).unwrap());
+                    assert!(control_block.verify(&decode32(output_key), &leaf.script));
+
+                    // Another script is not committed to by the same control block
+                    assert!(!control_block.verify(&decode32(output_key), &[0x51]));
+                });
+            });
+        }
+
+        #[test]
+        fn invalid_control_block() {
+            let (internal_key, tree, ..) = vectors().remove(0);
+            assert!(matches!(
+                tree.control_block(&decode32(internal_key), &TapLeaf::new(vec![0x51])),
+                Err(TaprootError::MissingLeaf(_))
+            ));
+            assert_eq!(Err(TaprootError::InvalidLeafVersion(0xc1)), TapLeaf::with_version(vec![0x51], 0xc1));
+            assert_eq!(Err(TaprootError::InvalidControlBlockLength(32)), ControlBlock::deserialize(&[0xc0; 32]));
+            assert_eq!(Err(TaprootError::InvalidControlBlockLength(34)), ControlBlock::deserialize(&[0xc0; 34]));
+            assert_eq!(
+                Err(TaprootError::InvalidControlBlockLength(33 + 129 * 32)),
+                ControlBlock::deserialize(&[0xc0; 33 + 129 * 32])
+            );
+        }
+    }
+}
@@ -0,0 +1,610 @@
+//! # Timelock
+//!
+//! Absolute lock times of transactions (nLockTime) checked by `OP_CHECKLOCKTIMEVERIFY` (BIP-65),
+//! and relative lock times of inputs (nSequence, BIP-68) checked by `OP_CHECKSEQUENCEVERIFY` (BIP-112).
+//! A timelock script pays a public key once its lock time has passed, and a vault additionally lets a
+//! recovery key spend at any time: `OP_IF <recovery key> OP_CHECKSIG OP_ELSE <timelock script> OP_ENDIF`.
+//! Both are spent through P2WSH with compressed public keys or through a Taproot leaf with x-only keys.
+
+use crate::address::BitcoinAddress;
+use crate::interpreter::script_num;
+use crate::network::BitcoinNetwork;
+use crate::script::{Instruction, Instructions, Opcode, Script};
+use crate::taproot::{TapLeaf, TapTree};
+use chainlib_core::no_std::*;
+use chainlib_core::{AddressError, TransactionError};
+
+use core::fmt;
+
+/// The lock time from which it is a Unix timestamp rather than a block height
+pub const LOCKTIME_THRESHOLD: u32 = 500_000_000;
+/// The sequence number flag disabling the relative lock time of an input (BIP-68)
+pub const SEQUENCE_LOCKTIME_DISABLE_FLAG: u32 = 1 << 31;
+/// The sequence number flag of a relative lock time in units of 512 seconds rather than blocks
+pub const SEQUENCE_LOCKTIME_TYPE_FLAG: u32 = 1 << 22;
+/// The sequence number bits of the value of a relative lock time
+pub const SEQUENCE_LOCKTIME_MASK: u32 = 0x0000_ffff;
+/// The granularity of a relative lock time in seconds
+const SEQUENCE_LOCKTIME_GRANULARITY: u32 = 512;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum TimelockError {
+    #[error("invalid block height {0}")]
+    InvalidHeight(u32),
+
+    #[error("invalid relative lock time of {0} seconds")]
+    InvalidSeconds(u32),
+
+    #[error("invalid timelock script {0}")]
+    InvalidScript(String),
+
+    #[error("invalid timestamp {0}")]
+    InvalidTime(u32),
+
+    #[error("lock times {0} and {1} of different units")]
+    MixedUnits(String, String),
+
+    #[error("lock time {0} does not satisfy {1}")]
+    UnsatisfiedLockTime(String, String),
+
+    #[error("sequence number {0} does not satisfy {1}")]
+    UnsatisfiedSequence(u32, String),
+
+    #[error("relative lock time of version {0} transaction")]
+    UnsupportedVersion(u32),
+}
+
+impl From<TimelockError> for AddressError {
+    fn from(error: TimelockError) -> Self {
+        AddressError::Crate("Timelock", format!("{:?}", error))
+    }
+}
+
+impl From<TimelockError> for TransactionError {
+    fn from(error: TimelockError) -> Self {
+        TransactionError::Crate("Timelock", format!("{:?}", error))
+    }
+}
+
+/// Represents the absolute lock time of a transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LockTime {
+    /// A block height below 500,000,000
+    Blocks(u32),
+    /// A Unix timestamp of at least 500,000,000
+    Seconds(u32),
+}
+
+impl LockTime {
+    /// The lock time of a transaction that may be mined in any block.
+    pub const ZERO: LockTime = LockTime::Blocks(0);
+
+    /// Returns the lock time of the block height.
+    pub fn from_height(height: u32) -> Result<Self, TimelockError> {
+        match height < LOCKTIME_THRESHOLD {
+            true => Ok(LockTime::Blocks(height)),
+            false => Err(TimelockError::InvalidHeight(height)),
+        }
+    }
+
+    /// Returns the lock time of the Unix timestamp.
+    pub fn from_time(time: u32) -> Result<Self, TimelockError> {
+        match time >= LOCKTIME_THRESHOLD {
+            true => Ok(LockTime::Seconds(time)),
+            false => Err(TimelockError::InvalidTime(time)),
+        }
+    }
+
+    /// Returns the lock time of the nLockTime field.
+    pub fn from_consensus(lock_time: u32) -> Self {
+        match lock_time < LOCKTIME_THRESHOLD {
+            true => LockTime::Blocks(lock_time),
+            false => LockTime::Seconds(lock_time),
+        }
+    }
+
+    /// Returns the nLockTime field of the lock time.
+    pub fn to_consensus_u32(self) -> u32 {
+        match self {
+            LockTime::Blocks(lock_time) | LockTime::Seconds(lock_time) => lock_time,
+        }
+    }
+
+    /// Returns true if both lock times are block heights or both are timestamps.
+    pub fn is_same_unit(&self, other: &LockTime) -> bool {
+        matches!((self, other), (LockTime::Blocks(_), LockTime::Blocks(_)) | (LockTime::Seconds(_), LockTime::Seconds(_)))
+    }
+
+    /// Returns true if a transaction with the given lock time satisfies this lock time,
+    /// as checked by `OP_CHECKLOCKTIMEVERIFY`.
+    pub fn is_satisfied_by(&self, lock_time: &LockTime) -> bool {
+        self.is_same_unit(lock_time) && self <= lock_time
+    }
+}
+
+impl From<u32> for LockTime {
+    fn from(lock_time: u32) -> Self {
+        Self::from_consensus(lock_time)
+    }
+}
+
+impl fmt::Display for LockTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LockTime::Blocks(height) => write!(f, "block {}", height),
+            LockTime::Seconds(time) => write!(f, "time {}", time),
+        }
+    }
+}
+
+/// Represents the relative lock time of an input since its spent output was mined (BIP-68)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RelativeLockTime {
+    /// A number of blocks
+    Blocks(u16),
+    /// A number of 512-second intervals
+    Time(u16),
+}
+
+impl RelativeLockTime {
+    /// Returns the relative lock time of the smallest number of 512-second intervals covering the seconds.
+    pub fn from_seconds_ceil(seconds: u32) -> Result<Self, TimelockError> {
+        match u16::try_from(seconds.div_ceil(SEQUENCE_LOCKTIME_GRANULARITY)) {
+            Ok(intervals) => Ok(RelativeLockTime::Time(intervals)),
+            Err(_) => Err(TimelockError::InvalidSeconds(seconds)),
+        }
+    }
+
+    /// Returns the relative lock time of the sequence number, if it is not disabled.
+    pub fn from_sequence(sequence: u32) -> Option<Self> {
+        if sequence & SEQUENCE_LOCKTIME_DISABLE_FLAG != 0 {
+            return None;
+        }
+        let value = (sequence & SEQUENCE_LOCKTIME_MASK) as u16;
+        match sequence & SEQUENCE_LOCKTIME_TYPE_FLAG != 0 {
+            true => Some(RelativeLockTime::Time(value)),
+            false => Some(RelativeLockTime::Blocks(value)),
+        }
+    }
+
+    /// Returns the sequence number of the relative lock time, which also signals replaceability (BIP-125).
+    pub fn to_sequence(self) -> u32 {
+        match self {
+            RelativeLockTime::Blocks(blocks) => blocks as u32,
+            RelativeLockTime::Time(intervals) => SEQUENCE_LOCKTIME_TYPE_FLAG | intervals as u32,
+        }
+    }
+
+    /// Returns true if an input with the given sequence number satisfies this relative lock time,
+    /// as checked by `OP_CHECKSEQUENCEVERIFY`.
+    pub fn is_satisfied_by_sequence(&self, sequence: u32) -> bool {
+        match (self, RelativeLockTime::from_sequence(sequence)) {
+            (RelativeLockTime::Blocks(lock), Some(RelativeLockTime::Blocks(value))) => *lock <= value,
+            (RelativeLockTime::Time(lock), Some(RelativeLockTime::Time(value))) => *lock <= value,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for RelativeLockTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RelativeLockTime::Blocks(blocks) => write!(f, "{} blocks", blocks),
+            RelativeLockTime::Time(intervals) => write!(f, "{} seconds", *intervals as u32 * SEQUENCE_LOCKTIME_GRANULARITY),
+        }
+    }
+}
+
+/// Represents the lock time a script spend waits for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Timelock {
+    /// An absolute lock time checked by `OP_CHECKLOCKTIMEVERIFY`
+    Absolute(LockTime),
+    /// A relative lock time checked by `OP_CHECKSEQUENCEVERIFY`
+    Relative(RelativeLockTime),
+}
+
+impl Timelock {
+    /// Returns the sequence number of an input spending the timelock without signalling replaceability,
+    /// which enables the lock time of the transaction for an absolute timelock.
+    pub fn sequence(&self) -> u32 {
+        match self {
+            Timelock::Absolute(_) => 0xffff_fffe,
+            Timelock::Relative(lock_time) => lock_time.to_sequence(),
+        }
+    }
+
+    /// Returns true if an input with the given sequence number can spend the timelock.
+    pub fn is_satisfied_by_sequence(&self, sequence: u32) -> bool {
+        match self {
+            Timelock::Absolute(_) => sequence != 0xffff_ffff,
+            Timelock::Relative(lock_time) => lock_time.is_satisfied_by_sequence(sequence),
+        }
+    }
+
+    /// Returns the number pushed before the timelock opcode.
+    fn value(&self) -> i64 {
+        match self {
+            Timelock::Absolute(lock_time) => lock_time.to_consensus_u32() as i64,
+            Timelock::Relative(lock_time) => lock_time.to_sequence() as i64,
+        }
+    }
+
+    /// Returns the opcode checking the timelock.
+    fn opcode(&self) -> Opcode {
+        match self {
+            Timelock::Absolute(_) => Opcode::OP_CHECKLOCKTIMEVERIFY,
+            Timelock::Relative(_) => Opcode::OP_CHECKSEQUENCEVERIFY,
+        }
+    }
+}
+
+impl fmt::Display for Timelock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Timelock::Absolute(lock_time) => write!(f, "{}", lock_time),
+            Timelock::Relative(lock_time) => write!(f, "{}", lock_time),
+        }
+    }
+}
+
+/// Represents a script paying a public key once the timelock has passed, or a recovery key at any time
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TimelockScript {
+    /// The lock time of the spend by the public key
+    timelock: Timelock,
+    /// The compressed (P2WSH) or x-only (Taproot) public key spending once the timelock has passed
+    public_key: Vec<u8>,
+    /// The public key of the same kind spending at any time
+    recovery_key: Option<Vec<u8>>,
+}
+
+impl TimelockScript {
+    /// Returns the script `<lock time> OP_CHECKLOCKTIMEVERIFY|OP_CHECKSEQUENCEVERIFY OP_DROP <public key> OP_CHECKSIG`
+    /// of a compressed (P2WSH) or x-only (Taproot) public key.
+    pub fn new(timelock: Timelock, public_key: &[u8]) -> Result<Self, TimelockError> {
+        if public_key.len() != 33 && public_key.len() != 32 {
+            return Err(TimelockError::InvalidScript(format!("public key of {} bytes", public_key.len())));
+        }
+        Ok(Self {
+            timelock,
+            public_key: public_key.to_vec(),
+            recovery_key: None,
+        })
+    }
+
+    /// Returns the vault `OP_IF <recovery key> OP_CHECKSIG OP_ELSE <timelock script> OP_ENDIF`,
+    /// with a recovery key of the same kind as the public key.
+    pub fn vault(timelock: Timelock, public_key: &[u8], recovery_key: &[u8]) -> Result<Self, TimelockError> {
+        if recovery_key.len() != public_key.len() {
+            return Err(TimelockError::InvalidScript(format!("recovery key of {} bytes", recovery_key.len())));
+        }
+        Ok(Self {
+            recovery_key: Some(recovery_key.to_vec()),
+            ..Self::new(timelock, public_key)?
+        })
+    }
+
+    /// Returns the timelock script of the serialized script.
+    pub fn from_script(script: &[u8]) -> Result<Self, TimelockError> {
+        let instructions = Instructions::new(script)
+            .collect::<Result<Vec<Instruction>, _>>()
+            .map_err(|_| TimelockError::InvalidScript(hex::encode(script)))?;
+
+        let (timelocked, recovery_key) = match instructions[..] {
+            [Instruction::Op(Opcode::OP_IF), Instruction::PushBytes(recovery_key), Instruction::Op(Opcode::OP_CHECKSIG), Instruction::Op(Opcode::OP_ELSE), ref timelocked @ .., Instruction::Op(Opcode::OP_ENDIF)] => {
+                (timelocked, Some(recovery_key))
+            }
+            ref timelocked => (timelocked, None),
+        };
+        let timelock_script = match *timelocked {
+            [lock, Instruction::Op(opcode), Instruction::Op(Opcode::OP_DROP), Instruction::PushBytes(public_key), Instruction::Op(Opcode::OP_CHECKSIG)] => {
+                let value = match lock {
+                    Instruction::PushBytes(data) => script_num(data, true, 5).ok(),
+                    Instruction::Op(small) if (Opcode::OP_1 as u8..=Opcode::OP_16 as u8).contains(&(small as u8)) => {
+                        Some((small as u8 - Opcode::OP_1 as u8 + 1) as i64)
+                    }
+                    _ => None,
+                };
+                let timelock = match (opcode, value.and_then(|value| u32::try_from(value).ok())) {
+                    (Opcode::OP_CHECKLOCKTIMEVERIFY, Some(value)) => Some(Timelock::Absolute(LockTime::from_consensus(value))),
+                    (Opcode::OP_CHECKSEQUENCEVERIFY, Some(value)) => RelativeLockTime::from_sequence(value)
+                        .filter(|lock_time| lock_time.to_sequence() == value)
+                        .map(Timelock::Relative),
+                    _ => None,
+                };
+                match (timelock, recovery_key) {
+                    (Some(timelock), Some(recovery_key)) => Self::vault(timelock, public_key, recovery_key).ok(),
+                    (Some(timelock), None) => Self::new(timelock, public_key).ok(),
+                    (None, _) => None,
+                }
+            }
+            _ => None,
+        };
+        timelock_script.ok_or_else(|| TimelockError::InvalidScript(hex::encode(script)))
+    }
+
+    /// Returns the serialized script.
+    pub fn to_script(&self) -> Vec<u8> {
+        let mut script = Script::new();
+        if let Some(recovery_key) = &self.recovery_key {
+            script = script
+                .push_opcode(Opcode::OP_IF)
+                .push_slice(recovery_key)
+                .push_opcode(Opcode::OP_CHECKSIG)
+                .push_opcode(Opcode::OP_ELSE);
+        }
+        script = script
+            .push_int(self.timelock.value())
+            .push_opcode(self.timelock.opcode())
+            .push_opcode(Opcode::OP_DROP)
+            .push_slice(&self.public_key)
+            .push_opcode(Opcode::OP_CHECKSIG);
+        if self.recovery_key.is_some() {
+            script = script.push_opcode(Opcode::OP_ENDIF);
+        }
+        script.into_bytes()
+    }
+
+    /// Returns the lock time of the spend by the public key.
+    pub fn timelock(&self) -> Timelock {
+        self.timelock
+    }
+
+    /// Returns the public key spending once the timelock has passed.
+    pub fn public_key(&self) -> &[u8] {
+        &self.public_key
+    }
+
+    /// Returns the recovery key of a vault.
+    pub fn recovery_key(&self) -> Option<&[u8]> {
+        self.recovery_key.as_deref()
+    }
+
+    /// Returns the script data pushed after the signature of the public key, selecting the timelocked branch of a vault.
+    pub fn timelocked_script_data(&self) -> Option<Vec<u8>> {
+        self.recovery_key.as_ref().map(|_| vec![])
+    }
+
+    /// Returns the script data pushed after the signature of the recovery key, selecting the recovery branch of a vault.
+    pub fn recovery_script_data(&self) -> Option<Vec<u8>> {
+        self.recovery_key.as_ref().map(|_| vec![0x01])
+    }
+
+    /// Returns the P2WSH address of the script of compressed public keys.
+    pub fn p2wsh_address<N: BitcoinNetwork>(&self) -> Result<BitcoinAddress<N>, AddressError> {
+        if self.public_key.len() != 33 {
+            return Err(TimelockError::InvalidScript("P2WSH of x-only public keys".into()).into());
+        }
+        BitcoinAddress::<N>::p2wsh(&self.to_script())
+    }
+
+    /// Returns the Taproot leaf of the script of x-only public keys.
+    pub fn tap_leaf(&self) -> Result<TapLeaf, TimelockError> {
+        if self.public_key.len() != 32 {
+            return Err(TimelockError::InvalidScript("Taproot leaf of compressed public keys".into()));
+        }
+        Ok(TapLeaf::new(self.to_script()))
+    }
+
+    /// Returns the P2TR address of the x-only internal key committing to the leaf of the script.
+    pub fn p2tr_address<N: BitcoinNetwork>(&self, internal_key: &[u8; 32]) -> Result<BitcoinAddress<N>, AddressError> {
+        let leaf = self.tap_leaf()?;
+        BitcoinAddress::<N>::p2tr_with_script_tree(internal_key, Some(&TapTree::leaf(leaf.script)))
+    }
+}
+
+/// Returns the lock time of a transaction satisfying both lock times, the later of the two.
+pub(crate) fn combine_lock_times(lock_time: LockTime, other: LockTime) -> Result<LockTime, TimelockError> {
+    match (lock_time, other) {
+        (LockTime::ZERO, other) | (other, LockTime::ZERO) => Ok(other),
+        (lock_time, other) if lock_time.is_same_unit(&other) => Ok(lock_time.max(other)),
+        (lock_time, other) => Err(TimelockError::MixedUnits(lock_time.to_string(), other.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amount::BitcoinAmount;
+    use crate::interpreter::{InterpreterError, VerificationFlags};
+    use crate::network::*;
+    use crate::private_key::BitcoinPrivateKey;
+    use crate::taproot::x_only_public_key;
+    use crate::transaction::{BitcoinTransaction, BitcoinTransactionBuilder, BitcoinTransactionInputBuilder};
+    use chainlib_core::{PrivateKey, Transaction};
+    use core::str::FromStr;
+
+    type N = Mainnet;
+
+    const OWNER_KEY: &str = "L1uyy5qTuGrVXrmrsvHWHgVzW9kKdrp27wBC7Vs6nZDTF2BRUVwy";
+    const RECOVERY_KEY: &str = "KwtetKxofS1Lhp7idNJzb5B5WninBRfELdwkjvTMZZGME4G72kMz";
+    const TRANSACTION_ID: &str = "9cb01977970f1322931532c9467e85eee9daf620b1886eb70d7c9b0e6c993bc4";
+
+    fn private_key(wif: &str) -> BitcoinPrivateKey<N> {
+        BitcoinPrivateKey::<N>::from_str(wif).unwrap()
+    }
+
+    fn public_key(wif: &str) -> Vec<u8> {
+        private_key(wif).to_public_key().to_secp256k1_public_key().serialize_compressed().to_vec()
+    }
+
+    fn x_only_key(wif: &str) -> [u8; 32] {
+        x_only_public_key(&private_key(wif).to_public_key().to_secp256k1_public_key()).0
+    }
+
+    /// Returns the builder of a transaction spending the P2WSH output of the script with the given script data.
+    fn spend(script: &TimelockScript, script_data: Option<Vec<u8>>) -> BitcoinTransactionBuilder<N> {
+        let address = script.p2wsh_address::<N>().unwrap();
+        let mut input = BitcoinTransactionInputBuilder::new(TRANSACTION_ID, 0)
+            .address(&address.to_string())
+            .amount(BitcoinAmount(100_000))
+            .redeem_script(script.to_script());
+        if let Some(script_data) = script_data {
+            input = input.witness_script_data(script_data);
+        }
+        BitcoinTransactionBuilder::<N>::new()
+            .input(input)
+            .output("1Fyxts6r24DpEieygQiNnWxUdb18ANa5p7", BitcoinAmount(99_000))
+    }
+
+    fn sign_and_verify(mut transaction: BitcoinTransaction<N>, wif: &str) -> Result<(), String> {
+        transaction.sign_with_private_key(&private_key(wif)).map_err(|error| error.to_string())?;
+        transaction.verify_input(0, VerificationFlags::STANDARD).map_err(|error| error.to_string())
+    }
+
+    #[test]
+    fn lock_times() {
+        assert_eq!(LockTime::Blocks(800_000), LockTime::from_consensus(800_000));
+        assert_eq!(LockTime::Seconds(1_700_000_000), LockTime::from(1_700_000_000));
+        assert_eq!(Err(TimelockError::InvalidHeight(LOCKTIME_THRESHOLD)), LockTime::from_height(LOCKTIME_THRESHOLD));
+        assert_eq!(Err(TimelockError::InvalidTime(1_000)), LockTime::from_time(1_000));
+        assert!(LockTime::Blocks(800_000).is_satisfied_by(&LockTime::Blocks(800_001)));
+        assert!(!LockTime::Blocks(800_000).is_satisfied_by(&LockTime::Blocks(799_999)));
+        assert!(!LockTime::Blocks(800_000).is_satisfied_by(&LockTime::Seconds(1_700_000_000)));
+
+        assert_eq!(Ok(LockTime::Blocks(800_000)), combine_lock_times(LockTime::ZERO, LockTime::Blocks(800_000)));
+        assert_eq!(Ok(LockTime::Blocks(800_000)), combine_lock_times(LockTime::Blocks(800_000), LockTime::Blocks(1_170)));
+        assert!(combine_lock_times(LockTime::Blocks(800_000), LockTime::Seconds(1_700_000_000)).is_err());
+    }
+
+    #[test]
+    fn relative_lock_times() {
+        assert_eq!(144, RelativeLockTime::Blocks(144).to_sequence());
+        assert_eq!(0x0040_0008, RelativeLockTime::from_seconds_ceil(3_600).unwrap().to_sequence());
+        assert_eq!(Some(RelativeLockTime::Time(8)), RelativeLockTime::from_sequence(0x0040_0008));
+        assert_eq!(None, RelativeLockTime::from_sequence(0xffff_fffd));
+        assert!(RelativeLockTime::from_seconds_ceil(512 * 0x1_0000).is_err());
+
+        assert!(RelativeLockTime::Blocks(144).is_satisfied_by_sequence(145));
+        assert!(!RelativeLockTime::Blocks(144).is_satisfied_by_sequence(143));
+        assert!(!RelativeLockTime::Blocks(144).is_satisfied_by_sequence(0x0040_0090));
+        assert!(!RelativeLockTime::Blocks(144).is_satisfied_by_sequence(0xffff_fffe));
+    }
+
+    #[test]
+    fn scripts() {
+        let owner = public_key(OWNER_KEY);
+        let recovery = public_key(RECOVERY_KEY);
+        let relative = Timelock::Relative(RelativeLockTime::Blocks(144));
+        let absolute = Timelock::Absolute(LockTime::Blocks(800_000));
+
+        let script = TimelockScript::new(relative, &owner).unwrap();
+        assert_eq!(
+            format!("9000 OP_CHECKSEQUENCEVERIFY OP_DROP {} OP_CHECKSIG", hex::encode(&owner)),
+            Script::from(&script.to_script()[..]).to_asm()
+        );
+        let vault = TimelockScript::vault(absolute, &owner, &recovery).unwrap();
+        assert_eq!(
+            format!(
+                "OP_IF {} OP_CHECKSIG OP_ELSE 00350c OP_CHECKLOCKTIMEVERIFY OP_DROP {} OP_CHECKSIG OP_ENDIF",
+                hex::encode(&recovery),
+                hex::encode(&owner)
+            ),
+            Script::from(&vault.to_script()[..]).to_asm()
+        );
+        let small = TimelockScript::new(Timelock::Relative(RelativeLockTime::Blocks(6)), &x_only_key(OWNER_KEY)).unwrap();
+        assert_eq!(0x56, small.to_script()[0]);
+
+        for script in [script, vault, small] {
+            assert_eq!(script, TimelockScript::from_script(&script.to_script()).unwrap());
+        }
+
+        // Non-minimal numbers and disabled relative lock times are not timelock scripts
+        let non_minimal = [vec![0x02, 0x06, 0x00, 0xb2, 0x75, 0x21], owner.clone(), vec![0xac]].concat();
+        assert!(TimelockScript::from_script(&non_minimal).is_err());
+        let disabled = [vec![0x05, 0x90, 0x00, 0x00, 0x80, 0x00, 0xb2, 0x75, 0x21], owner.clone(), vec![0xac]].concat();
+        assert!(TimelockScript::from_script(&disabled).is_err());
+        assert!(TimelockScript::new(relative, &owner[1..20]).is_err());
+        assert!(TimelockScript::vault(relative, &owner, &x_only_key(RECOVERY_KEY)).is_err());
+        assert!(TimelockScript::new(relative, &x_only_key(OWNER_KEY)).unwrap().p2wsh_address::<N>().is_err());
+        assert!(TimelockScript::new(relative, &owner).unwrap().tap_leaf().is_err());
+    }
+
+    #[test]
+    fn spend_relative_timelock() {
+        let script = TimelockScript::new(Timelock::Relative(RelativeLockTime::Blocks(144)), &public_key(OWNER_KEY)).unwrap();
+        let transaction = spend(&script, None).build().unwrap();
+        assert_eq!(Some(RelativeLockTime::Blocks(144)), transaction.parameters.inputs[0].relative_lock_time());
+        assert_eq!(Ok(()), sign_and_verify(transaction, OWNER_KEY));
+
+        // The sequence number must satisfy the timelock, in a transaction of version 2
+        assert!(spend(&script, None).version(1).build().is_err());
+        let input = BitcoinTransactionInputBuilder::<N>::new(TRANSACTION_ID, 0)
+            .address(&script.p2wsh_address::<N>().unwrap().to_string())
+            .amount(BitcoinAmount(100_000))
+            .redeem_script(script.to_script());
+        assert!(input.clone().sequence(143).build().is_err());
+        assert_eq!(145, input.sequence(145).build().unwrap().sequence_number());
+
+        // A transaction that does not satisfy the timelock is not signed
+        let mut transaction = spend(&script, None).build().unwrap();
+        transaction.parameters.inputs[0].sequence = 143u32.to_le_bytes().to_vec();
+        let expected = TransactionError::from(TimelockError::UnsatisfiedSequence(143, "144 blocks".into()));
+        assert_eq!(Err(expected.to_string()), sign_and_verify(transaction, OWNER_KEY));
+    }
+
+    #[test]
+    fn spend_absolute_timelock() {
+        let script = TimelockScript::new(Timelock::Absolute(LockTime::Blocks(800_000)), &public_key(OWNER_KEY)).unwrap();
+        let transaction = spend(&script, None).lock_time(1_170).build().unwrap();
+        assert_eq!(LockTime::Blocks(800_000), transaction.lock_time());
+        assert_eq!(0xffff_fffe, transaction.parameters.inputs[0].sequence_number());
+        assert_eq!(Ok(()), sign_and_verify(transaction, OWNER_KEY));
+
+        assert!(spend(&script, None).lock_time(1_700_000_000).build().is_err());
+
+        // A transaction below the timelock is not signed, and is rejected by the interpreter
+        let mut transaction = spend(&script, None).build().unwrap();
+        transaction.parameters.lock_time = 799_999;
+        assert!(sign_and_verify(transaction.clone(), OWNER_KEY).is_err());
+        transaction.parameters.lock_time = 800_000;
+        transaction.sign_with_private_key(&private_key(OWNER_KEY)).unwrap();
+        transaction.parameters.lock_time = 799_999;
+        let error = transaction.verify_input(0, VerificationFlags::STANDARD).unwrap_err();
+        assert_eq!(InterpreterError::UnsatisfiedLocktime, error.error);
+    }
+
+    #[test]
+    fn spend_vault() {
+        let timelock = Timelock::Relative(RelativeLockTime::from_seconds_ceil(86_400).unwrap());
+        let vault = TimelockScript::vault(timelock, &public_key(OWNER_KEY), &public_key(RECOVERY_KEY)).unwrap();
+
+        // The recovery key spends at any time
+        let transaction = spend(&vault, vault.recovery_script_data()).build().unwrap();
+        assert_eq!(None, transaction.parameters.inputs[0].relative_lock_time());
+        assert_eq!(Ok(()), sign_and_verify(transaction, RECOVERY_KEY));
+
+        // The owner key spends once the timelock has passed
+        let transaction = spend(&vault, vault.timelocked_script_data()).build().unwrap();
+        assert_eq!(Some(RelativeLockTime::Time(169)), transaction.parameters.inputs[0].relative_lock_time());
+        assert_eq!(Ok(()), sign_and_verify(transaction.clone(), OWNER_KEY));
+        assert!(sign_and_verify(transaction, RECOVERY_KEY).is_err());
+    }
+
+    #[test]
+    fn spend_tap_leaf() {
+        let timelock = Timelock::Relative(RelativeLockTime::Blocks(144));
+        let script = TimelockScript::new(timelock, &x_only_key(OWNER_KEY)).unwrap();
+        let internal_key = x_only_key(RECOVERY_KEY);
+        let address = script.p2tr_address::<N>(&internal_key).unwrap();
+        let leaf = script.tap_leaf().unwrap();
+        let control_block = TapTree::leaf(leaf.script.clone()).control_block(&internal_key, &leaf).unwrap();
+
+        let mut transaction = BitcoinTransactionBuilder::<N>::new()
+            .input(
+                BitcoinTransactionInputBuilder::new(TRANSACTION_ID, 0)
+                    .address(&address.to_string())
+                    .amount(BitcoinAmount(100_000))
+                    .timelock(timelock),
+            )
+            .output("1Fyxts6r24DpEieygQiNnWxUdb18ANa5p7", BitcoinAmount(99_000))
+            .build()
+            .unwrap();
+        assert_eq!(144, transaction.parameters.inputs[0].sequence_number());
+
+        let signature = transaction.taproot_script_path_signature(0, &private_key(OWNER_KEY), &leaf).unwrap();
+        transaction.add_taproot_script_path_witness(0, vec![signature], &leaf, &control_block).unwrap();
+        assert_eq!(Ok(()), transaction.verify_input(0, VerificationFlags::STANDARD));
+    }
+}
@@ -1,18 +1,33 @@
 use crate::address::BitcoinAddress;
 use crate::amount::BitcoinAmount;
 use crate::format::BitcoinFormat;
+use crate::interpreter::{self, ScriptFailure, VerificationFlags};
+use crate::miniscript::{Miniscript, Satisfier};
+use crate::multisig::MultisigScript;
 use crate::network::BitcoinNetwork;
+use crate::omni::{OmniTransaction, OMNI_PROPERTY_ID};
 use crate::private_key::BitcoinPrivateKey;
+use crate::psbt::BitcoinPsbt;
 use crate::public_key::BitcoinPublicKey;
+use crate::script::{remove_code_separators, Script};
+use crate::sighash_cache::SignatureHashCache;
+use crate::taproot::{schnorr_sign, tagged_hash, tweak_secret_key, ControlBlock, TapLeaf};
+use crate::timelock::{combine_lock_times, LockTime, RelativeLockTime, Timelock, TimelockError, TimelockScript};
 use crate::witness_program::WitnessProgram;
+
+pub use crate::script::Opcode;
+
 use chainlib_core::no_std::{io::Read, *};
-use chainlib_core::{PrivateKey, Transaction, TransactionError, TransactionId};
+use chainlib_core::crypto::hash160;
+use chainlib_core::{
+    AmountError, Clock, Context, PrivateKey, Transaction, TransactionError, TransactionEstimate, TransactionId,
+};
 
-use base58::FromBase58;
 use bech32::{self, FromBase32};
 use sha2::digest::typenum::Bit;
-use core::{fmt, str::FromStr};
+use core::{fmt, marker::PhantomData, str::FromStr};
 use chainlib_core::libsecp256k1;
+use rand::Rng;
 use serde::Serialize;
 use sha2::{Digest, Sha256};
 
@@ -85,7 +100,7 @@ impl BitcoinVector {
         F: Fn(&mut R) -> Result<E, TransactionError>,
     {
         let count = read_variable_length_integer(&mut reader)?;
-        Ok((count, Self::read(reader, func)))
+        Ok((count, (0..count).map(|_| func(&mut reader)).collect()))
     }
 }
 
@@ -93,73 +108,45 @@ impl BitcoinVector {
 pub fn create_script_pub_key<N: BitcoinNetwork>(address: &BitcoinAddress<N>) -> Result<Vec<u8>, TransactionError> {
     match address.format() {
         BitcoinFormat::P2PKH => {
-            let bytes = &address.to_string().from_base58()?;
-            // 去1字节除前缀和后4个字节校验码
-            let pub_key_hash = bytes[1..(bytes.len() - 4)].to_vec();
-
-            let mut script = vec![];
-            script.push(Opcode::OP_DUP as u8);
-            script.push(Opcode::OP_HASH160 as u8);
-            script.extend(variable_length_integer(pub_key_hash.len() as u64)?);
-            script.extend(pub_key_hash);
-            script.push(Opcode::OP_EQUALVERIFY as u8);
-            script.push(Opcode::OP_CHECKSIG as u8);
-            Ok(script)
-        }
-        BitcoinFormat::P2WSH => {
-            //let bech32 = Bech32::from_str(&address.to_string())?;
+            let mut pub_key_hash = [0u8; 20];
+            pub_key_hash.copy_from_slice(&address.to_hash160()?);
+            Ok(Script::new_p2pkh(&pub_key_hash).into_bytes())
+        }
+        BitcoinFormat::P2SH | BitcoinFormat::P2SH_P2WPKH | BitcoinFormat::P2SH_P2WSH => {
+            let mut script_hash = [0u8; 20];
+            script_hash.copy_from_slice(&address.to_hash160()?);
+            Ok(Script::new_p2sh(&script_hash).into_bytes())
+        }
+        BitcoinFormat::P2WSH | BitcoinFormat::Bech32 | BitcoinFormat::P2TR => {
             let (_hrp, data, _variant) = bech32::decode(&address.to_string())?;
-            let (v, script) = data.split_at(1);
-            let script = Vec::from_base32(script)?;
-            let mut script_bytes = vec![v[0].to_u8(), script.len() as u8];
-            script_bytes.extend(script);
-            Ok(script_bytes)
-        }
-        BitcoinFormat::P2SH_P2WPKH => {
-            let script_bytes = &address.to_string().from_base58()?;
-            let script_hash = script_bytes[1..(script_bytes.len() - 4)].to_vec();
-
-            let mut script = vec![];
-            script.push(Opcode::OP_HASH160 as u8);
-            script.extend(variable_length_integer(script_hash.len() as u64)?);
-            script.extend(script_hash);
-            script.push(Opcode::OP_EQUAL as u8);
-            Ok(script)
-        }
-        BitcoinFormat::Bech32 => {
-            // let bech32 = Bech32::from_str(&address.to_string())?;
-            let (hrp, data, variant) = bech32::decode(&address.to_string())?;
             let (v, program) = data.split_at(1);
             let program = Vec::from_base32(program)?;
             let mut program_bytes = vec![v[0].to_u8(), program.len() as u8];
             program_bytes.extend(program);
 
-            Ok(WitnessProgram::new(&program_bytes)?.to_scriptpubkey())
+            Ok(Script::new_witness_program(&WitnessProgram::new(&program_bytes)?).into_bytes())
         }
     }
 }
 
+/// Returns the P2SH redeem script of a P2SH_P2WSH witness script, i.e. its version 0 witness program.
+pub fn create_p2sh_p2wsh_redeem_script(witness_script: &[u8]) -> Vec<u8> {
+    [vec![0x00, 0x20], Sha256::digest(witness_script).to_vec()].concat()
+}
+
+/// Returns the script pushing the given data.
+pub(crate) fn push_data(data: &[u8]) -> Vec<u8> {
+    Script::new().push_slice(data).into_bytes()
+}
+
 /// Construct and return the OP_RETURN script for the data output of a tx
 /// that spends 'amount' basic units of OMNI coins.
 pub fn create_script_op_return(amount: i64) -> Result<Vec<u8>, TransactionError> {
-
-    let mut script = vec![];
-    
-    let msg_type: u16 = 0;
-    let msg_version: u16 = 0;
-    let property_id: u32 = 1; // OMNI coin id
-
-    script.push(Opcode::OP_RETURN as u8);
-    script.push('o' as u8);
-    script.push('m' as u8);
-    script.push('n' as u8);
-    script.push('i' as u8);
-    script.append(&mut msg_version.to_be_bytes().to_vec());
-    script.append(&mut msg_type.to_be_bytes().to_vec());
-    script.append(&mut property_id.to_be_bytes().to_vec());
-    script.append(&mut amount.to_be_bytes().to_vec());
-
-    Ok(script)
+    let transaction = OmniTransaction::SimpleSend {
+        property_id: OMNI_PROPERTY_ID,
+        amount,
+    };
+    Ok(transaction.to_script()?)
 }
 
 /// Represents a Bitcoin signature hash
@@ -167,6 +154,8 @@ pub fn create_script_op_return(amount: i64) -> Result<Vec<u8>, TransactionError>
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
 #[allow(non_camel_case_types)]
 pub enum SignatureHash {
+    /// Signs all inputs and outputs, omitting the sighash byte from the signature (Taproot only, BIP-341).
+    SIGHASH_DEFAULT = 0x00,
     /// Signs all inputs and outputs.
     SIGHASH_ALL = 0x01,
     /// Signs all inputs and none of the outputs.
@@ -186,60 +175,89 @@ pub enum SignatureHash {
     /// Signs only one input and one corresponding output per input.
     /// Allows anyone to add or remove other inputs.
     SIGHASH_SINGLE_SIGHASH_ANYONECANPAY = 0x83,
+    /// Signs all inputs and outputs with replay protection (Bitcoin Cash only).
+    SIGHASH_ALL_SIGHASH_FORKID = 0x41,
+    /// Signs all inputs and none of the outputs with replay protection (Bitcoin Cash only).
+    SIGHASH_NONE_SIGHASH_FORKID = 0x42,
+    /// Signs all inputs and one corresponding output per input with replay protection (Bitcoin Cash only).
+    SIGHASH_SINGLE_SIGHASH_FORKID = 0x43,
+    /// Signs only one input and all outputs with replay protection (Bitcoin Cash only).
+    SIGHASH_ALL_SIGHASH_FORKID_SIGHASH_ANYONECANPAY = 0xc1,
+    /// Signs only one input and none of the outputs with replay protection (Bitcoin Cash only).
+    SIGHASH_NONE_SIGHASH_FORKID_SIGHASH_ANYONECANPAY = 0xc2,
+    /// Signs only one input and one corresponding output per input with replay protection (Bitcoin Cash only).
+    SIGHASH_SINGLE_SIGHASH_FORKID_SIGHASH_ANYONECANPAY = 0xc3,
 }
 
 impl fmt::Display for SignatureHash {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
+            SignatureHash::SIGHASH_DEFAULT => write!(f, "SIGHASH_DEFAULT"),
             SignatureHash::SIGHASH_ALL => write!(f, "SIGHASH_ALL"),
             SignatureHash::SIGHASH_NONE => write!(f, "SIGHASH_NONE"),
             SignatureHash::SIGHASH_SINGLE => write!(f, "SIGHASH_SINGLE"),
             SignatureHash::SIGHASH_ALL_SIGHASH_ANYONECANPAY => write!(f, "SIGHASH_ALL | SIGHASH_ANYONECANPAY"),
             SignatureHash::SIGHASH_NONE_SIGHASH_ANYONECANPAY => write!(f, "SIGHASH_NONE | SIGHASH_ANYONECANPAY"),
             SignatureHash::SIGHASH_SINGLE_SIGHASH_ANYONECANPAY => write!(f, "SIGHASH_SINGLE | SIGHASH_ANYONECANPAY"),
+            SignatureHash::SIGHASH_ALL_SIGHASH_FORKID => write!(f, "SIGHASH_ALL | SIGHASH_FORKID"),
+            SignatureHash::SIGHASH_NONE_SIGHASH_FORKID => write!(f, "SIGHASH_NONE | SIGHASH_FORKID"),
+            SignatureHash::SIGHASH_SINGLE_SIGHASH_FORKID => write!(f, "SIGHASH_SINGLE | SIGHASH_FORKID"),
+            SignatureHash::SIGHASH_ALL_SIGHASH_FORKID_SIGHASH_ANYONECANPAY => {
+                write!(f, "SIGHASH_ALL | SIGHASH_FORKID | SIGHASH_ANYONECANPAY")
+            }
+            SignatureHash::SIGHASH_NONE_SIGHASH_FORKID_SIGHASH_ANYONECANPAY => {
+                write!(f, "SIGHASH_NONE | SIGHASH_FORKID | SIGHASH_ANYONECANPAY")
+            }
+            SignatureHash::SIGHASH_SINGLE_SIGHASH_FORKID_SIGHASH_ANYONECANPAY => {
+                write!(f, "SIGHASH_SINGLE | SIGHASH_FORKID | SIGHASH_ANYONECANPAY")
+            }
         }
     }
 }
 
 impl SignatureHash {
-    fn from_byte(byte: &u8) -> Self {
-        match byte {
+    /// Returns the signature hash of the given hash type byte, or an error if the hash type is undefined.
+    pub fn from_byte(byte: u8) -> Result<Self, TransactionError> {
+        Ok(match byte {
+            0x00 => SignatureHash::SIGHASH_DEFAULT,
             0x01 => SignatureHash::SIGHASH_ALL,
             0x02 => SignatureHash::SIGHASH_NONE,
             0x03 => SignatureHash::SIGHASH_SINGLE,
             0x81 => SignatureHash::SIGHASH_ALL_SIGHASH_ANYONECANPAY,
             0x82 => SignatureHash::SIGHASH_NONE_SIGHASH_ANYONECANPAY,
             0x83 => SignatureHash::SIGHASH_SINGLE_SIGHASH_ANYONECANPAY,
-            _ => SignatureHash::SIGHASH_ALL,
-        }
+            0x41 => SignatureHash::SIGHASH_ALL_SIGHASH_FORKID,
+            0x42 => SignatureHash::SIGHASH_NONE_SIGHASH_FORKID,
+            0x43 => SignatureHash::SIGHASH_SINGLE_SIGHASH_FORKID,
+            0xc1 => SignatureHash::SIGHASH_ALL_SIGHASH_FORKID_SIGHASH_ANYONECANPAY,
+            0xc2 => SignatureHash::SIGHASH_NONE_SIGHASH_FORKID_SIGHASH_ANYONECANPAY,
+            0xc3 => SignatureHash::SIGHASH_SINGLE_SIGHASH_FORKID_SIGHASH_ANYONECANPAY,
+            _ => return Err(TransactionError::InvalidSignatureHash(byte as u32)),
+        })
     }
-}
 
-/// Represents the commonly used script opcodes
-#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
-#[allow(non_camel_case_types)]
-pub enum Opcode {
-    OP_DUP = 0x76,
-    OP_HASH160 = 0xa9,
-    OP_CHECKSIG = 0xac,
-    OP_EQUAL = 0x87,
-    OP_EQUALVERIFY = 0x88,
-    OP_RETURN = 0x6a,
+    /// Returns true if the signature hash commits to the fork id of the network (SIGHASH_FORKID).
+    pub fn has_fork_id(&self) -> bool {
+        *self as u8 & SIGHASH_FORKID != 0
+    }
 }
 
-impl fmt::Display for Opcode {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            Opcode::OP_DUP => write!(f, "OP_DUP"),
-            Opcode::OP_HASH160 => write!(f, "OP_HASH160"),
-            Opcode::OP_CHECKSIG => write!(f, "OP_CHECKSIG"),
-            Opcode::OP_EQUAL => write!(f, "OP_EQUAL"),
-            Opcode::OP_EQUALVERIFY => write!(f, "OP_EQUALVERIFY"),
-            Opcode::OP_RETURN => write!(f, "OP_RETURN"),
-        }
+/// The flag of a signature hash committing to the fork id of the network, for replay protection
+/// https://github.com/bitcoincashorg/bitcoincash.org/blob/master/spec/replay-protected-sighash.md
+pub const SIGHASH_FORKID: u8 = 0x40;
+
+/// Returns SIGHASH_ALL, with SIGHASH_FORKID on networks with a fork id as they only accept replay protected signatures.
+pub fn default_sighash<N: BitcoinNetwork>() -> SignatureHash {
+    match N::fork_id() {
+        Some(_) => SignatureHash::SIGHASH_ALL_SIGHASH_FORKID,
+        None => SignatureHash::SIGHASH_ALL,
     }
 }
 
+/// The highest sequence number of an input that signals replaceability (BIP-125)
+/// https://github.com/bitcoin/bips/blob/master/bip-0125.mediawiki
+pub const SEQUENCE_ENABLE_RBF: u32 = 0xffff_fffd;
+
 /// Represents a Bitcoin transaction outpoint
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Outpoint<N: BitcoinNetwork> {
@@ -251,7 +269,7 @@ pub struct Outpoint<N: BitcoinNetwork> {
     pub amount: Option<BitcoinAmount>,
     /// The script public key associated with spending this input
     pub script_pub_key: Option<Vec<u8>>,
-    /// An optional redeem script (for SegWit transactions)
+    /// An optional redeem script (P2SH, P2SH_P2WPKH) or witness script (P2WSH, P2SH_P2WSH)
     pub redeem_script: Option<Vec<u8>>,
     /// The address of the outpoint
     pub address: Option<BitcoinAddress<N>>,
@@ -273,28 +291,38 @@ impl<N: BitcoinNetwork> Outpoint<N> {
                 let redeem_script = match address.format() {
                     BitcoinFormat::P2PKH => match redeem_script {
                         Some(_) => return Err(TransactionError::InvalidInputs("P2PKH".into())),
-                        None => match script_pub_key[0] != Opcode::OP_DUP as u8
-                            && script_pub_key[1] != Opcode::OP_HASH160 as u8
-                            && script_pub_key[script_pub_key.len() - 1] != Opcode::OP_CHECKSIG as u8
-                        {
+                        None => match !Script::from(&script_pub_key[..]).is_p2pkh() {
                             true => return Err(TransactionError::InvalidScriptPubKey("P2PKH".into())),
                             false => None,
                         },
                     },
-                    BitcoinFormat::P2WSH => match redeem_script {
-                        Some(redeem_script) => match script_pub_key[0] != 0x00 as u8
-                            && script_pub_key[1] != 0x20 as u8
-                            && script_pub_key.len() != 34 // zero [32-byte sha256(witness script)]
+                    BitcoinFormat::P2SH => match redeem_script {
+                        Some(redeem_script) => match script_pub_key.len() != 23 // OP_HASH160 [20-byte hash160(redeem script)] OP_EQUAL
+                            || script_pub_key[2..22] != hash160(&redeem_script)
+                        {
+                            true => return Err(TransactionError::InvalidScriptPubKey("P2SH".into())),
+                            false => Some(redeem_script),
+                        },
+                        None => return Err(TransactionError::InvalidInputs("P2SH".into())),
+                    },
+                    BitcoinFormat::P2SH_P2WSH => match redeem_script {
+                        Some(witness_script) => match script_pub_key.len() != 23 // OP_HASH160 [20-byte hash160(witness program)] OP_EQUAL
+                            || script_pub_key[2..22] != hash160(&create_p2sh_p2wsh_redeem_script(&witness_script))
                         {
+                            true => return Err(TransactionError::InvalidScriptPubKey("P2SH_P2WSH".into())),
+                            false => Some(witness_script),
+                        },
+                        None => return Err(TransactionError::InvalidInputs("P2SH_P2WSH".into())),
+                    },
+                    BitcoinFormat::P2WSH => match redeem_script {
+                        Some(redeem_script) => match !Script::from(&script_pub_key[..]).is_p2wsh() {
                             true => return Err(TransactionError::InvalidScriptPubKey("P2WSH".into())),
                             false => Some(redeem_script),
                         },
                         None => return Err(TransactionError::InvalidInputs("P2WSH".into())),
                     },
                     BitcoinFormat::P2SH_P2WPKH => match redeem_script {
-                        Some(redeem_script) => match script_pub_key[0] != Opcode::OP_HASH160 as u8
-                            && script_pub_key[script_pub_key.len() - 1] != Opcode::OP_EQUAL as u8
-                        {
+                        Some(redeem_script) => match !Script::from(&script_pub_key[..]).is_p2sh() {
                             true => return Err(TransactionError::InvalidScriptPubKey("P2SH_P2WPKH".into())),
                             false => Some(redeem_script),
                         },
@@ -304,6 +332,13 @@ impl<N: BitcoinNetwork> Outpoint<N> {
                         true => return Err(TransactionError::InvalidInputs("Bech32".into())),
                         false => None,
                     },
+                    BitcoinFormat::P2TR => match redeem_script {
+                        Some(_) => return Err(TransactionError::InvalidInputs("P2TR".into())),
+                        None => match !Script::from(&script_pub_key[..]).is_p2tr() {
+                            true => return Err(TransactionError::InvalidScriptPubKey("P2TR".into())),
+                            false => None,
+                        },
+                    },
                 };
 
                 (Some(script_pub_key), redeem_script)
@@ -338,14 +373,21 @@ pub struct BitcoinTransactionInput<N: BitcoinNetwork> {
     pub witnesses: Vec<Vec<u8>>,
     /// If true, the input has been signed
     pub is_signed: bool,
-    /// Provide more flexibility for multiple signatures (for P2WSH)
-    pub additional_witness: Option<(Vec<u8>, bool)>,
+    /// The signatures of a multisig script by serialized public key, until its threshold is reached
+    pub partial_signatures: BTreeMap<Vec<u8>, Vec<u8>>,
     /// Option for additional witness stack script args
     pub witness_script_data: Option<Vec<u8>>,
 }
 
 impl<N: BitcoinNetwork> BitcoinTransactionInput<N> {
-    const DEFAULT_SEQUENCE: [u8; 4] = [0xff, 0xff, 0xff, 0xff];
+    /// The sequence of a final input
+    pub(crate) const DEFAULT_SEQUENCE: [u8; 4] = [0xff, 0xff, 0xff, 0xff];
+    /// The maximum size of a low-S DER signature with its sighash byte and length prefix
+    pub(crate) const PLACEHOLDER_SIGNATURE_SIZE: usize = 73;
+    /// The size of a compressed public key with its length prefix
+    pub(crate) const PLACEHOLDER_PUBLIC_KEY_SIZE: usize = 34;
+    /// The maximum size of a Schnorr signature with its sighash byte and length prefix
+    pub(crate) const PLACEHOLDER_SCHNORR_SIGNATURE_SIZE: usize = 66;
 
     /// Returns a new Bitcoin transaction input without the script (unlocking).
     pub fn new(
@@ -383,7 +425,7 @@ impl<N: BitcoinNetwork> BitcoinTransactionInput<N> {
             sighash_code: sighash,
             witnesses: vec![],
             is_signed: false,
-            additional_witness: None,
+            partial_signatures: BTreeMap::new(),
             witness_script_data: None,
         })
     }
@@ -394,8 +436,8 @@ impl<N: BitcoinNetwork> BitcoinTransactionInput<N> {
         let mut vin = [0u8; 4];
         let mut sequence = [0u8; 4];
 
-        reader.read(&mut transaction_hash)?;
-        reader.read(&mut vin)?;
+        reader.read_exact(&mut transaction_hash)?;
+        reader.read_exact(&mut vin)?;
 
         let outpoint = Outpoint::<N>::new(
             transaction_hash.to_vec(),
@@ -408,17 +450,19 @@ impl<N: BitcoinNetwork> BitcoinTransactionInput<N> {
 
         let script_sig: Vec<u8> = BitcoinVector::read(&mut reader, |s| {
             let mut byte = [0u8; 1];
-            s.read(&mut byte)?;
+            s.read_exact(&mut byte)?;
             Ok(byte[0])
         })?;
 
-        reader.read(&mut sequence)?;
+        reader.read_exact(&mut sequence)?;
 
         let script_sig_len = read_variable_length_integer(&script_sig[..])?;
-        let sighash_code = SignatureHash::from_byte(&match script_sig_len {
-            0 => 0x01,
-            length => script_sig[length],
-        });
+        // Consensus accepts any hash type in legacy signatures, so a signed input
+        // with an undefined one is still read, reporting the default signature hash
+        let sighash_code = match script_sig.get(script_sig_len) {
+            Some(&sighash) if script_sig_len > 0 => SignatureHash::from_byte(sighash).unwrap_or(default_sighash::<N>()),
+            _ => default_sighash::<N>(),
+        };
 
         Ok(Self {
             outpoint,
@@ -427,7 +471,7 @@ impl<N: BitcoinNetwork> BitcoinTransactionInput<N> {
             sighash_code,
             witnesses: vec![],
             is_signed: script_sig.len() > 0,
-            additional_witness: None,
+            partial_signatures: BTreeMap::new(),
             witness_script_data: None,
         })
     }
@@ -445,6 +489,16 @@ impl<N: BitcoinNetwork> BitcoinTransactionInput<N> {
                     Some(address) => match address.format() {
                         BitcoinFormat::Bech32 => input.extend(vec![0x00]),
                         BitcoinFormat::P2WSH => input.extend(vec![0x00]),
+                        BitcoinFormat::P2TR => input.extend(vec![0x00]),
+                        // The script code of a P2SH input is its redeem script
+                        BitcoinFormat::P2SH => {
+                            let redeem_script = match &self.outpoint.redeem_script {
+                                Some(redeem_script) => redeem_script,
+                                None => return Err(TransactionError::InvalidInputs("P2SH".into())),
+                            };
+                            input.extend(variable_length_integer(redeem_script.len() as u64)?);
+                            input.extend(redeem_script);
+                        }
                         _ => {
                             let script_pub_key = match &self.outpoint.script_pub_key {
                                 Some(script) => script,
@@ -466,6 +520,153 @@ impl<N: BitcoinNetwork> BitcoinTransactionInput<N> {
         input.extend(&self.sequence);
         Ok(input)
     }
+
+    /// Completes the script sig and witness of a P2SH, P2SH_P2WSH or P2WSH input
+    /// with the stack satisfying its script, followed by the script itself.
+    fn finalize_script(&mut self, stack: Vec<Vec<u8>>) -> Result<(), TransactionError> {
+        let format = match &self.outpoint.address {
+            Some(address) => address.format(),
+            None => return Err(TransactionError::MissingOutpointAddress),
+        };
+        let script = match &self.outpoint.redeem_script {
+            Some(script) => script.clone(),
+            None => return Err(TransactionError::InvalidInputs(format!("{:?}", format))),
+        };
+
+        match format {
+            BitcoinFormat::P2SH => {
+                let mut script_sig: Vec<u8> = stack.iter().flat_map(|item| push_data(item)).collect();
+                script_sig.extend(push_data(&script));
+                self.script_sig = script_sig;
+            }
+            _ => {
+                if format == BitcoinFormat::P2SH_P2WSH {
+                    self.script_sig = push_data(&create_p2sh_p2wsh_redeem_script(&script));
+                }
+                let mut witnesses = vec![];
+                for item in stack.iter().chain(Some(&script)) {
+                    witnesses.push([variable_length_integer(item.len() as u64)?, item.clone()].concat());
+                }
+                self.witnesses = witnesses;
+            }
+        }
+        self.is_signed = true;
+        Ok(())
+    }
+
+    /// Returns the sequence number.
+    pub fn sequence_number(&self) -> u32 {
+        match <[u8; 4]>::try_from(&self.sequence[..]) {
+            Ok(sequence) => u32::from_le_bytes(sequence),
+            Err(_) => u32::MAX,
+        }
+    }
+
+    /// Returns true if the input signals replaceability (BIP-125) with a sequence number below 0xfffffffe.
+    pub fn signals_rbf(&self) -> bool {
+        self.sequence_number() <= SEQUENCE_ENABLE_RBF
+    }
+
+    /// Signals replaceability (BIP-125), keeping a lower sequence number such as a relative lock time.
+    pub fn enable_rbf(&mut self) {
+        if !self.signals_rbf() {
+            self.sequence = SEQUENCE_ENABLE_RBF.to_le_bytes().to_vec();
+        }
+    }
+
+    /// Returns the relative lock time of the sequence number (BIP-68), if it is not disabled.
+    pub fn relative_lock_time(&self) -> Option<RelativeLockTime> {
+        RelativeLockTime::from_sequence(self.sequence_number())
+    }
+
+    /// Returns the timelock of the timelock script spent by the input,
+    /// unless the script data selects the recovery branch of a vault.
+    pub fn spent_timelock(&self) -> Option<Timelock> {
+        let script = TimelockScript::from_script(self.outpoint.redeem_script.as_ref()?).ok()?;
+        match script.recovery_key() {
+            Some(_) if self.witness_script_data != script.timelocked_script_data() => None,
+            _ => Some(script.timelock()),
+        }
+    }
+
+    /// Returns the miniscript of the P2WSH witness script spent by the input,
+    /// unless it is a multisig script or the input carries script data.
+    pub fn spent_miniscript(&self) -> Option<Miniscript> {
+        match self.outpoint.address.as_ref()?.format() {
+            BitcoinFormat::P2SH_P2WSH | BitcoinFormat::P2WSH if self.witness_script_data.is_none() => {
+                let script = self.outpoint.redeem_script.as_ref()?;
+                match MultisigScript::from_script(script) {
+                    Ok(_) => None,
+                    Err(_) => Miniscript::from_script(script).ok(),
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the size of the script sig and of the witness (with its item count) once signed.
+    /// Unsigned inputs are sized with placeholder signatures of the maximum length and compressed public keys.
+    pub fn estimate_size(&self) -> Result<(usize, usize), TransactionError> {
+        if self.is_signed {
+            let witness = match self.witnesses.len() {
+                0 => 0,
+                count => {
+                    variable_length_integer(count as u64)?.len()
+                        + self.witnesses.iter().map(|witness| witness.len()).sum::<usize>()
+                }
+            };
+            return Ok((self.script_sig.len(), witness));
+        }
+
+        let address = match &self.outpoint.address {
+            Some(address) => address,
+            None => return Err(TransactionError::MissingOutpointAddress),
+        };
+        let signature = Self::PLACEHOLDER_SIGNATURE_SIZE;
+        let public_key = Self::PLACEHOLDER_PUBLIC_KEY_SIZE;
+
+        match address.format() {
+            BitcoinFormat::P2PKH => Ok((signature + public_key, 0)),
+            BitcoinFormat::P2SH_P2WPKH => {
+                let redeem_script = match &self.outpoint.redeem_script {
+                    Some(redeem_script) => redeem_script,
+                    None => return Err(TransactionError::InvalidInputs("P2SH_P2WPKH".into())),
+                };
+                let script_sig = variable_length_integer(redeem_script.len() as u64)?.len() + redeem_script.len();
+                Ok((script_sig, 1 + signature + public_key))
+            }
+            BitcoinFormat::Bech32 => Ok((0, 1 + signature + public_key)),
+            BitcoinFormat::P2TR => Ok((0, 1 + Self::PLACEHOLDER_SCHNORR_SIGNATURE_SIZE)),
+            format @ (BitcoinFormat::P2SH | BitcoinFormat::P2SH_P2WSH | BitcoinFormat::P2WSH) => {
+                let script = match &self.outpoint.redeem_script {
+                    Some(script) => script,
+                    None => return Err(TransactionError::InvalidInputs(format!("{:?}", format))),
+                };
+                // A multisig script takes an empty dummy element and its threshold of signatures,
+                // a miniscript its largest satisfaction, and any other script one signature and the script data
+                let items = match MultisigScript::from_script(script) {
+                    Ok(multisig) => [vec![1], vec![signature; multisig.threshold()]].concat(),
+                    Err(_) => match &self.witness_script_data {
+                        Some(witness_script_data) => vec![signature, push_data(witness_script_data).len()],
+                        None => vec![signature],
+                    },
+                };
+                let (count, stack) = match self.spent_miniscript().and_then(|miniscript| miniscript.max_satisfaction_size()) {
+                    Some(satisfaction) => satisfaction,
+                    None => (items.len(), items.iter().sum::<usize>()),
+                };
+                let witness = variable_length_integer(count as u64 + 1)?.len()
+                    + stack
+                    + variable_length_integer(script.len() as u64)?.len()
+                    + script.len();
+                match format {
+                    BitcoinFormat::P2SH => Ok((stack + push_data(script).len(), 0)),
+                    BitcoinFormat::P2SH_P2WSH => Ok((push_data(&create_p2sh_p2wsh_redeem_script(script)).len(), witness)),
+                    _ => Ok((0, witness)),
+                }
+            }
+        }
+    }
 }
 
 /// Represents a Bitcoin transaction output
@@ -504,16 +705,22 @@ impl BitcoinTransactionOutput {
     /// Read and output a Bitcoin transaction output
     pub fn read<R: Read>(mut reader: &mut R) -> Result<Self, TransactionError> {
         let mut amount = [0u8; 8];
-        reader.read(&mut amount)?;
+        reader.read_exact(&mut amount)?;
 
         let script_pub_key: Vec<u8> = BitcoinVector::read(&mut reader, |s| {
             let mut byte = [0u8; 1];
-            s.read(&mut byte)?;
+            s.read_exact(&mut byte)?;
             Ok(byte[0])
         })?;
 
+        // The amount is checked against the money supply of the network with the transaction
+        let amount = u64::from_le_bytes(amount);
+        if amount > i64::MAX as u64 {
+            return Err(AmountError::AmountOutOfBounds(amount.to_string(), i64::MAX.to_string()).into());
+        }
+
         Ok(Self {
-            amount: BitcoinAmount::from_satoshi(u64::from_le_bytes(amount) as i64)?,
+            amount: BitcoinAmount(amount as i64),
             script_pub_key,
         })
     }
@@ -532,8 +739,8 @@ impl BitcoinTransactionOutput {
 /// https://github.com/bitcoin/bips/blob/master/bip-0141.mediawiki#transaction-id
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct BitcoinTransactionId {
-    txid: Vec<u8>,
-    wtxid: Vec<u8>,
+    pub(crate) txid: Vec<u8>,
+    pub(crate) wtxid: Vec<u8>,
 }
 
 impl TransactionId for BitcoinTransactionId {}
@@ -578,14 +785,14 @@ impl<N: BitcoinNetwork> BitcoinTransactionParameters<N> {
     /// Read and output the Bitcoin transaction parameters
     pub fn read<R: Read>(mut reader: R) -> Result<Self, TransactionError> {
         let mut version = [0u8; 4];
-        reader.read(&mut version)?;
+        reader.read_exact(&mut version)?;
 
         let mut inputs = BitcoinVector::read(&mut reader, BitcoinTransactionInput::<N>::read)?;
 
         let segwit_flag = match inputs.is_empty() {
             true => {
                 let mut flag = [0u8; 1];
-                reader.read(&mut flag)?;
+                reader.read_exact(&mut flag)?;
                 match flag[0] {
                     1 => {
                         inputs = BitcoinVector::read(&mut reader, BitcoinTransactionInput::<N>::read)?;
@@ -598,13 +805,16 @@ impl<N: BitcoinNetwork> BitcoinTransactionParameters<N> {
         };
 
         let outputs = BitcoinVector::read(&mut reader, BitcoinTransactionOutput::read)?;
+        for output in &outputs {
+            BitcoinAmount::from_satoshi_for::<N>(output.amount.0)?;
+        }
 
         if segwit_flag {
             for input in &mut inputs {
                 let witnesses: Vec<Vec<u8>> = BitcoinVector::read(&mut reader, |s| {
                     let (size, witness) = BitcoinVector::read_witness(s, |sr| {
                         let mut byte = [0u8; 1];
-                        sr.read(&mut byte)?;
+                        sr.read_exact(&mut byte)?;
                         Ok(byte[0])
                     })?;
 
@@ -612,7 +822,13 @@ impl<N: BitcoinNetwork> BitcoinTransactionParameters<N> {
                 })?;
 
                 if witnesses.len() > 0 {
-                    input.sighash_code = SignatureHash::from_byte(&witnesses[0][&witnesses[0].len() - 1]);
+                    // A Taproot signature without a sighash byte is signed with SIGHASH_DEFAULT,
+                    // a multisig witness starts with the empty element popped by OP_CHECKMULTISIG
+                    let signature = witnesses.iter().find(|witness| witness.len() > 1).unwrap_or(&witnesses[0]);
+                    input.sighash_code = match signature.len() {
+                        65 => SignatureHash::SIGHASH_DEFAULT,
+                        length => SignatureHash::from_byte(signature[length - 1]).unwrap_or(default_sighash::<N>()),
+                    };
                     input.is_signed = true;
                 }
                 input.witnesses = witnesses;
@@ -620,7 +836,7 @@ impl<N: BitcoinNetwork> BitcoinTransactionParameters<N> {
         }
 
         let mut lock_time = [0u8; 4];
-        reader.read(&mut lock_time)?;
+        reader.read_exact(&mut lock_time)?;
 
         let transaction_parameters = BitcoinTransactionParameters::<N> {
             version: u32::from_le_bytes(version),
@@ -634,11 +850,329 @@ impl<N: BitcoinNetwork> BitcoinTransactionParameters<N> {
     }
 }
 
+/// Represents a builder for a Bitcoin transaction input
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BitcoinTransactionInputBuilder<N: BitcoinNetwork> {
+    transaction_id: Option<String>,
+    index: Option<u32>,
+    address: Option<String>,
+    amount: Option<BitcoinAmount>,
+    redeem_script: Option<Vec<u8>>,
+    script_pub_key: Option<Vec<u8>>,
+    sequence: Option<u32>,
+    sighash_code: SignatureHash,
+    witness_script_data: Option<Vec<u8>>,
+    timelock: Option<Timelock>,
+    _network: PhantomData<N>,
+}
+
+impl<N: BitcoinNetwork> BitcoinTransactionInputBuilder<N> {
+    /// Returns a builder for the input spending the given output (transaction id in RPC byte order).
+    pub fn new(transaction_id: &str, index: u32) -> Self {
+        Self {
+            transaction_id: Some(transaction_id.to_string()),
+            index: Some(index),
+            address: None,
+            amount: None,
+            redeem_script: None,
+            script_pub_key: None,
+            sequence: None,
+            sighash_code: default_sighash::<N>(),
+            witness_script_data: None,
+            timelock: None,
+            _network: PhantomData,
+        }
+    }
+
+    /// Sets the address of the spent output.
+    pub fn address(mut self, address: &str) -> Self {
+        self.address = Some(address.to_string());
+        self
+    }
+
+    /// Sets the amount of the spent output (required for SegWit signatures and fee checks).
+    pub fn amount(mut self, amount: BitcoinAmount) -> Self {
+        self.amount = Some(amount);
+        self
+    }
+
+    /// Sets the redeem script (P2SH, P2SH_P2WPKH) or witness script (P2WSH, P2SH_P2WSH) of the spent output.
+    pub fn redeem_script(mut self, redeem_script: Vec<u8>) -> Self {
+        self.redeem_script = Some(redeem_script);
+        self
+    }
+
+    /// Sets the script public key of the spent output, if it differs from the address script.
+    pub fn script_pub_key(mut self, script_pub_key: Vec<u8>) -> Self {
+        self.script_pub_key = Some(script_pub_key);
+        self
+    }
+
+    /// Sets the sequence number.
+    pub fn sequence(mut self, sequence: u32) -> Self {
+        self.sequence = Some(sequence);
+        self
+    }
+
+    /// Signals replaceability (BIP-125), keeping a lower sequence number such as a relative lock time.
+    pub fn replaceable(mut self) -> Self {
+        self.sequence = Some(match self.sequence {
+            Some(sequence) if sequence <= SEQUENCE_ENABLE_RBF => sequence,
+            _ => SEQUENCE_ENABLE_RBF,
+        });
+        self
+    }
+
+    /// Sets the signature hash type, by default `default_sighash`.
+    pub fn sighash(mut self, sighash_code: SignatureHash) -> Self {
+        self.sighash_code = sighash_code;
+        self
+    }
+
+    /// Sets the stack script args of a P2SH, P2SH_P2WSH or P2WSH input, pushed after the signature
+    /// of a script other than multisig.
+    pub fn witness_script_data(mut self, witness_script_data: Vec<u8>) -> Self {
+        self.witness_script_data = Some(witness_script_data);
+        self
+    }
+
+    /// Sets the timelock of the spent script, which is otherwise taken from a timelock script
+    /// in the redeem script. The sequence number defaults to one satisfying it.
+    pub fn timelock(mut self, timelock: Timelock) -> Self {
+        self.timelock = Some(timelock);
+        self
+    }
+
+    /// Returns the timelock the input waits for.
+    fn spent_timelock(&self, input: &BitcoinTransactionInput<N>) -> Option<Timelock> {
+        self.timelock.or_else(|| input.spent_timelock())
+    }
+
+    /// Returns the transaction input, validating the outpoint, address, amount and timelock.
+    pub fn build(&self) -> Result<BitcoinTransactionInput<N>, TransactionError> {
+        let transaction_id = match &self.transaction_id {
+            Some(transaction_id) => hex::decode(transaction_id)?,
+            None => return Err(TransactionError::MissingField("transaction_id")),
+        };
+        if transaction_id.len() != 32 {
+            return Err(TransactionError::InvalidTransactionId(transaction_id.len()));
+        }
+        let index = self.index.ok_or(TransactionError::MissingField("index"))?;
+        let address = match (&self.address, &self.redeem_script) {
+            (Some(address), Some(script)) => BitcoinAddress::<N>::from_str(address)?.with_script(script)?,
+            (Some(address), None) => BitcoinAddress::<N>::from_str(address)?,
+            (None, _) => return Err(TransactionError::MissingOutpointAddress),
+        };
+
+        let amount = match self.amount {
+            Some(amount) if amount.0 < 0 => {
+                return Err(AmountError::InvalidAmount(amount.0.to_string()).into())
+            }
+            Some(amount) => Some(BitcoinAmount::from_satoshi_for::<N>(amount.0)?),
+            // SegWit signatures commit to the amount of the spent output
+            None => match address.format() {
+                BitcoinFormat::P2PKH => None,
+                _ => return Err(TransactionError::MissingOutpointAmount),
+            },
+        };
+
+        let mut input = BitcoinTransactionInput::<N>::new(
+            transaction_id,
+            index,
+            Some(address),
+            amount,
+            self.redeem_script.clone(),
+            self.script_pub_key.clone(),
+            self.sequence.map(|sequence| sequence.to_le_bytes().to_vec()),
+            self.sighash_code,
+        )?;
+        input.witness_script_data = self.witness_script_data.clone();
+
+        if let Some(timelock) = self.spent_timelock(&input) {
+            match self.sequence {
+                Some(sequence) if !timelock.is_satisfied_by_sequence(sequence) => {
+                    return Err(TimelockError::UnsatisfiedSequence(sequence, timelock.to_string()).into())
+                }
+                Some(_) => {}
+                None => input.sequence = timelock.sequence().to_le_bytes().to_vec(),
+            }
+        }
+        Ok(input)
+    }
+}
+
+/// Represents an output added to a Bitcoin transaction builder
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum BitcoinOutputBuilder {
+    Address(String, BitcoinAmount),
+    Omni(OmniTransaction),
+}
+
+/// Represents a builder for a Bitcoin transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BitcoinTransactionBuilder<N: BitcoinNetwork> {
+    version: u32,
+    inputs: Vec<BitcoinTransactionInputBuilder<N>>,
+    outputs: Vec<BitcoinOutputBuilder>,
+    lock_time: LockTime,
+    max_fee: Option<BitcoinAmount>,
+}
+
+impl<N: BitcoinNetwork> Default for BitcoinTransactionBuilder<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<N: BitcoinNetwork> BitcoinTransactionBuilder<N> {
+    /// Returns a builder for a version 2 transaction without inputs, outputs or lock time.
+    pub fn new() -> Self {
+        Self {
+            version: 2,
+            inputs: vec![],
+            outputs: vec![],
+            lock_time: LockTime::ZERO,
+            max_fee: None,
+        }
+    }
+
+    /// Sets the transaction version.
+    pub fn version(mut self, version: u32) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Adds an input.
+    pub fn input(mut self, input: BitcoinTransactionInputBuilder<N>) -> Self {
+        self.inputs.push(input);
+        self
+    }
+
+    /// Adds an output paying the given amount to the given address.
+    pub fn output(mut self, address: &str, amount: BitcoinAmount) -> Self {
+        self.outputs.push(BitcoinOutputBuilder::Address(address.to_string(), amount));
+        self
+    }
+
+    /// Adds the OP_RETURN output of an Omni Layer simple send of OMNI.
+    pub fn omni_data_output(self, amount: BitcoinAmount) -> Self {
+        self.omni_output(OmniTransaction::SimpleSend {
+            property_id: OMNI_PROPERTY_ID,
+            amount: amount.0,
+        })
+    }
+
+    /// Adds the OP_RETURN output of an Omni Layer transaction, see [`crate::omni::OmniTransactionBuilder`]
+    /// for the order of the outputs.
+    pub fn omni_output(mut self, transaction: OmniTransaction) -> Self {
+        self.outputs.push(BitcoinOutputBuilder::Omni(transaction));
+        self
+    }
+
+    /// Sets the lock time, which is raised to the absolute timelocks of the inputs.
+    pub fn lock_time(mut self, lock_time: impl Into<LockTime>) -> Self {
+        self.lock_time = lock_time.into();
+        self
+    }
+
+    /// Sets the maximum fee (inputs minus outputs) the transaction may pay.
+    pub fn max_fee(mut self, max_fee: BitcoinAmount) -> Self {
+        self.max_fee = Some(max_fee);
+        self
+    }
+
+    /// Returns the unsigned transaction, validating the inputs, outputs, amounts and fee.
+    pub fn build(&self) -> Result<BitcoinTransaction<N>, TransactionError> {
+        if self.version != 1 && self.version != 2 {
+            return Err(TransactionError::Message(format!("unsupported transaction version: {}", self.version)));
+        }
+        if self.inputs.is_empty() {
+            return Err(TransactionError::MissingField("inputs"));
+        }
+        if self.outputs.is_empty() {
+            return Err(TransactionError::MissingField("outputs"));
+        }
+
+        let mut inputs: Vec<BitcoinTransactionInput<N>> = vec![];
+        let mut lock_time = self.lock_time;
+        for builder in &self.inputs {
+            let input = builder.build()?;
+            match builder.spent_timelock(&input) {
+                Some(Timelock::Absolute(input_lock_time)) => lock_time = combine_lock_times(lock_time, input_lock_time)?,
+                // Relative lock times are only enforced from version 2 (BIP-68)
+                Some(Timelock::Relative(_)) if self.version < 2 => {
+                    return Err(TimelockError::UnsupportedVersion(self.version).into())
+                }
+                _ => {}
+            }
+            if inputs.iter().any(|other| {
+                other.outpoint.reverse_transaction_id == input.outpoint.reverse_transaction_id
+                    && other.outpoint.index == input.outpoint.index
+            }) {
+                return Err(TransactionError::DuplicateOutpoint(
+                    builder.transaction_id.clone().unwrap_or_default(),
+                    input.outpoint.index,
+                ));
+            }
+            inputs.push(input);
+        }
+
+        let mut outputs = vec![];
+        for output in &self.outputs {
+            let output = match output {
+                BitcoinOutputBuilder::Address(address, amount) => {
+                    if amount.0 < 0 {
+                        return Err(AmountError::InvalidAmount(amount.0.to_string()).into());
+                    }
+                    let address = BitcoinAddress::<N>::from_str(address)?;
+                    BitcoinTransactionOutput::new(&address, BitcoinAmount::from_satoshi_for::<N>(amount.0)?)?
+                }
+                BitcoinOutputBuilder::Omni(transaction) => transaction.to_output()?,
+            };
+            outputs.push(output);
+        }
+
+        // The total of the outputs must be within the supply and covered by the inputs
+        let total_output = BitcoinAmount::from_satoshi_for::<N>(outputs.iter().map(|output| output.amount.0).sum())?;
+        let input_amounts: Option<Vec<i64>> = inputs
+            .iter()
+            .map(|input| input.outpoint.amount.map(|amount| amount.0))
+            .collect();
+        match input_amounts {
+            Some(amounts) => {
+                let total_input = amounts.iter().sum::<i64>();
+                if total_input < total_output.0 {
+                    return Err(TransactionError::InsufficientFunds(
+                        total_input.to_string(),
+                        total_output.0.to_string(),
+                    ));
+                }
+                if let Some(max_fee) = self.max_fee {
+                    let fee = total_input - total_output.0;
+                    if fee > max_fee.0 {
+                        return Err(TransactionError::InvalidFee(format!("{} exceeds the maximum of {}", fee, max_fee.0)));
+                    }
+                }
+            }
+            None if self.max_fee.is_some() => return Err(TransactionError::MissingOutpointAmount),
+            None => {}
+        }
+
+        BitcoinTransaction::<N>::new(&BitcoinTransactionParameters {
+            version: self.version,
+            inputs,
+            outputs,
+            lock_time: lock_time.to_consensus_u32(),
+            segwit_flag: false,
+        })
+    }
+}
+
 /// Represents a Bitcoin transaction
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct BitcoinTransaction<N: BitcoinNetwork> {
     /// The transaction parameters (version, inputs, outputs, lock_time, segwit_flag)
-    parameters: BitcoinTransactionParameters<N>,
+    pub(crate) parameters: BitcoinTransactionParameters<N>,
 }
 
 impl<N: BitcoinNetwork> fmt::Display for BitcoinTransaction<N> {
@@ -663,94 +1197,253 @@ impl<N: BitcoinNetwork> Transaction for BitcoinTransaction<N> {
     }
 
     /// Returns a signed transaction given the private key of the sender.
+    /// Taproot inputs are signed deterministically, with zero auxiliary randomness as BIP-340 permits,
+    /// so that signing is reproducible without an RNG; see `sign_with_context` to draw it from a context.
     fn sign_with_private_key(&mut self, private_key: &Self::PrivateKey) -> Result<Vec<u8>, TransactionError> {
-        let transaction = self.clone();
-        // let mut inputs = &self.parameters.inputs;
-        for (vin, input) in self.parameters.inputs.iter_mut().enumerate() {
-            let address = match &input.outpoint.address {
-                Some(address) => address,
-                None => continue,
-            };
+        self.sign_inputs(private_key, &mut || [0u8; 32])
+    }
 
-            let address_is_valid = match &address.format() {
-                BitcoinFormat::P2WSH => {
-                    let input_script = match &input.outpoint.redeem_script {
-                        Some(redeem_script) => redeem_script.clone(),
-                        None => return Err(TransactionError::InvalidInputs("P2WSH".into())),
-                    };
-                    let c_address = BitcoinAddress::<N>::p2wsh(&input_script)?;
-                    address == &c_address
-                }
-                _ => address == &private_key.to_address(&address.format())?,
-            };
+    /// Returns a transaction given the transaction bytes.
+    /// Note:: Raw transaction hex does not include enough
+    fn from_bytes(transaction: &Vec<u8>) -> Result<Self, TransactionError> {
+        Ok(Self {
+            parameters: Self::TransactionParameters::read(&transaction[..])?,
+        })
+    }
 
-            if address_is_valid && !input.is_signed {
-                // Transaction hash
-                let preimage = match &address.format() {
-                    BitcoinFormat::P2PKH => transaction.p2pkh_hash_preimage(vin, input.sighash_code)?,
-                    _ => transaction.segwit_hash_preimage(vin, input.sighash_code)?,
-                };
-                let transaction_hash = Sha256::digest(&Sha256::digest(&preimage));
+    /// Returns the transaction in bytes.
+    fn to_bytes(&self) -> Result<Vec<u8>, TransactionError> {
+        let mut transaction = self.parameters.version.to_le_bytes().to_vec();
 
-                // Signature
-                let (signature, _) = libsecp256k1::sign(
-                    &libsecp256k1::Message::parse_slice(&transaction_hash)?,
-                    &private_key.to_secp256k1_secret_key(),
-                );
-                let mut signature = signature.serialize_der().as_ref().to_vec();
-                signature.push((input.sighash_code as u32).to_le_bytes()[0]);
-                let signature = [variable_length_integer(signature.len() as u64)?, signature].concat();
+        if self.parameters.segwit_flag {
+            transaction.extend(vec![0x00, 0x01]);
+        }
 
-                // Public key
-                let public_key = private_key.to_public_key();
-                let public_key_bytes = match (&address.format(), public_key.is_compressed()) {
-                    (BitcoinFormat::P2PKH, false) => public_key.to_secp256k1_public_key().serialize().to_vec(),
-                    _ => public_key.to_secp256k1_public_key().serialize_compressed().to_vec(),
-                };
-                let public_key = [vec![public_key_bytes.len() as u8], public_key_bytes].concat();
+        transaction.extend(variable_length_integer(self.parameters.inputs.len() as u64)?);
+        let mut has_witness = false;
+        for input in &self.parameters.inputs {
+            if !has_witness {
+                has_witness = input.witnesses.len() > 0;
+            }
+            transaction.extend(input.serialize(!input.is_signed)?);
+        }
 
-                match &address.format() {
-                    BitcoinFormat::P2PKH => {
-                        input.script_sig = [signature.clone(), public_key].concat();
-                        input.is_signed = true;
-                    }
-                    BitcoinFormat::P2WSH => {
-                        let input_script = match &input.outpoint.redeem_script {
-                            Some(redeem_script) => redeem_script.clone(),
-                            None => return Err(TransactionError::InvalidInputs("P2WSH".into())),
-                        };
+        transaction.extend(variable_length_integer(self.parameters.outputs.len() as u64)?);
+        for output in &self.parameters.outputs {
+            transaction.extend(output.serialize()?);
+        }
 
-                        let ser_input_script =
-                            [variable_length_integer(input_script.len() as u64)?, input_script].concat();
-                        self.parameters.segwit_flag = true;
-                        input.script_sig = vec![];
-                        // TODO: (jaakinyele) Generalize to a vec of additional witnesses
-                        let (other_signature, is_other_sig_first) =
-                            match input.additional_witness.clone() {
-                                Some(n) => (n.0, n.1),
-                                None => {
-                                    return Err(TransactionError::InvalidInputs(
-                                        "P2WSH: missing additional witness input to complete multi-sig".into(),
-                                    ))
+        if has_witness {
+            for input in &self.parameters.inputs {
+                match input.witnesses.len() {
+                    0 => transaction.extend(vec![0x00]),
+                    _ => {
+                        transaction.extend(variable_length_integer(input.witnesses.len() as u64)?);
+                        for witness in &input.witnesses {
+                            transaction.extend(witness);
+                        }
+                    }
+                };
+            }
+        }
+
+        transaction.extend(&self.parameters.lock_time.to_le_bytes());
+
+        Ok(transaction)
+    }
+
+    /// Returns the transaction id.
+    fn to_transaction_id(&self) -> Result<Self::TransactionId, TransactionError> {
+        let mut txid = Sha256::digest(&Sha256::digest(&self.to_transaction_bytes_without_witness()?)).to_vec();
+        let mut wtxid = Sha256::digest(&Sha256::digest(&self.to_bytes()?)).to_vec();
+
+        txid.reverse();
+        wtxid.reverse();
+
+        Ok(Self::TransactionId { txid, wtxid })
+    }
+
+    fn sign(&mut self, signature: Vec<u8>, recid: u8) -> Result<Vec<u8>, TransactionError> {
+        todo!()
+    }
+
+    /// Returns the estimated size and virtual size of the transaction once signed.
+    fn estimate(&self) -> Result<TransactionEstimate, TransactionError> {
+        let (base, witness) = self.estimate_sizes()?;
+        Ok(TransactionEstimate {
+            size: base + witness,
+            units: self.vsize()?,
+        })
+    }
+}
+
+impl<N: BitcoinNetwork> BitcoinTransaction<N> {
+    /// Returns the signed transaction of a finalized PSBT of version 0 or 2.
+    pub fn from_psbt(psbt: &BitcoinPsbt<N>) -> Result<Self, TransactionError> {
+        psbt.extract()
+    }
+
+    /// Returns a signed transaction given the private key of the sender, drawing the auxiliary randomness
+    /// of Taproot signatures (BIP-340) from the RNG of the context, so the same context signs identically.
+    pub fn sign_with_context<C: Clock, R: Rng>(
+        &mut self,
+        private_key: &BitcoinPrivateKey<N>,
+        context: &mut Context<C, R>,
+    ) -> Result<Vec<u8>, TransactionError> {
+        self.sign_inputs(private_key, &mut || context.rng().gen())
+    }
+
+    /// Signs the inputs spendable by the private key, drawing the auxiliary randomness of each Taproot signature.
+    fn sign_inputs(
+        &mut self,
+        private_key: &BitcoinPrivateKey<N>,
+        aux_rand: &mut dyn FnMut() -> [u8; 32],
+    ) -> Result<Vec<u8>, TransactionError> {
+        // The midstates, version and lock time do not change as inputs are signed
+        let cache = SignatureHashCache::new(self)?;
+        let (version, transaction_lock_time) = (self.parameters.version, self.lock_time());
+        for vin in 0..self.parameters.inputs.len() {
+            let input = &self.parameters.inputs[vin];
+            let address = match &input.outpoint.address {
+                Some(address) => address.clone(),
+                None => continue,
+            };
+
+            let address_is_valid = match &address.format() {
+                format @ (BitcoinFormat::P2SH | BitcoinFormat::P2SH_P2WSH | BitcoinFormat::P2WSH) => {
+                    let input_script = match &input.outpoint.redeem_script {
+                        Some(redeem_script) => redeem_script.clone(),
+                        None => return Err(TransactionError::InvalidInputs(format!("{:?}", format))),
+                    };
+                    // Only the cosigners of a multisig script or a miniscript sign it
+                    let public_key = private_key.to_public_key().to_secp256k1_public_key();
+                    let is_cosigner = match (MultisigScript::from_script(&input_script), input.spent_miniscript()) {
+                        (Ok(multisig), _) => multisig.find_public_key(&public_key).is_some(),
+                        (Err(_), Some(miniscript)) => {
+                            miniscript.public_keys().contains(&&public_key.serialize_compressed()[..])
+                        }
+                        (Err(_), None) => true,
+                    };
+                    is_cosigner && address == BitcoinAddress::<N>::from_script(&input_script, format)?
+                }
+                _ => address == private_key.to_address(&address.format())?,
+            };
+
+            if address_is_valid && !input.is_signed && address.format() == BitcoinFormat::P2TR {
+                // Key path spend of the BIP-86 output key
+                let preimage = self.taproot_signature_message(&cache, vin, input.sighash_code as u8, None, None)?;
+                let sighash = tagged_hash("TapSighash", &preimage);
+                let secret_key = tweak_secret_key(&private_key.to_secp256k1_secret_key(), None)?;
+                let mut signature = schnorr_sign(&sighash, &secret_key, &aux_rand())?.to_vec();
+                if input.sighash_code != SignatureHash::SIGHASH_DEFAULT {
+                    signature.push(input.sighash_code as u8);
+                }
+
+                let input = &mut self.parameters.inputs[vin];
+                self.parameters.segwit_flag = true;
+                input.witnesses.push([variable_length_integer(signature.len() as u64)?, signature].concat());
+                input.is_signed = true;
+            } else if address_is_valid && !input.is_signed {
+                if input.sighash_code == SignatureHash::SIGHASH_DEFAULT {
+                    return Err(TransactionError::InvalidInputs(format!("SIGHASH_DEFAULT for {}", address.format())));
+                }
+                // Replay protected signatures are required on networks with a fork id and unknown to the others
+                if input.sighash_code.has_fork_id() != N::fork_id().is_some() {
+                    return Err(TransactionError::InvalidInputs(format!("{} on {}", input.sighash_code, N::NAME)));
+                }
+
+                // Transaction hash
+                let preimage = match &address.format() {
+                    BitcoinFormat::P2PKH | BitcoinFormat::P2SH if N::fork_id().is_some() => {
+                        self.fork_id_signature_message(&cache, vin, input.sighash_code)?
+                    }
+                    BitcoinFormat::P2PKH | BitcoinFormat::P2SH => self.p2pkh_hash_preimage(vin, input.sighash_code)?,
+                    _ => self.segwit_signature_message(&cache, vin, input.sighash_code)?,
+                };
+                let transaction_hash = Sha256::digest(&Sha256::digest(&preimage));
+
+                // Signature
+                let (signature, _) = libsecp256k1::sign(
+                    &libsecp256k1::Message::parse_slice(&transaction_hash)?,
+                    &private_key.to_secp256k1_secret_key(),
+                );
+                let mut der_signature = signature.serialize_der().as_ref().to_vec();
+                der_signature.push((input.sighash_code as u32).to_le_bytes()[0]);
+                let signature = [variable_length_integer(der_signature.len() as u64)?, der_signature.clone()].concat();
+
+                // Public key
+                let public_key = private_key.to_public_key();
+                let public_key_bytes = match (&address.format(), public_key.is_compressed()) {
+                    (BitcoinFormat::P2PKH, false) => public_key.to_secp256k1_public_key().serialize().to_vec(),
+                    _ => public_key.to_secp256k1_public_key().serialize_compressed().to_vec(),
+                };
+                let public_key = [vec![public_key_bytes.len() as u8], public_key_bytes].concat();
+
+                let input = &mut self.parameters.inputs[vin];
+                match &address.format() {
+                    BitcoinFormat::P2PKH => {
+                        input.script_sig = [signature.clone(), public_key].concat();
+                        input.is_signed = true;
+                    }
+                    format @ (BitcoinFormat::P2SH | BitcoinFormat::P2SH_P2WSH | BitcoinFormat::P2WSH) => {
+                        let input_script = match &input.outpoint.redeem_script {
+                            Some(redeem_script) => redeem_script.clone(),
+                            None => return Err(TransactionError::InvalidInputs(format!("{:?}", format))),
+                        };
+                        // A timelock script is only spendable once the transaction satisfies its lock time
+                        match input.spent_timelock() {
+                            Some(timelock @ Timelock::Absolute(lock_time))
+                                if !lock_time.is_satisfied_by(&transaction_lock_time)
+                                    || !timelock.is_satisfied_by_sequence(input.sequence_number()) =>
+                            {
+                                let lock_time = transaction_lock_time.to_string();
+                                return Err(TimelockError::UnsatisfiedLockTime(lock_time, timelock.to_string()).into());
+                            }
+                            Some(timelock @ Timelock::Relative(_))
+                                if !timelock.is_satisfied_by_sequence(input.sequence_number())
+                                    || version < 2 =>
+                            {
+                                let sequence = input.sequence_number();
+                                return Err(TimelockError::UnsatisfiedSequence(sequence, timelock.to_string()).into());
+                            }
+                            _ => {}
+                        }
+                        // A multisig script is complete once its threshold of cosigners has signed,
+                        // a miniscript once the signatures and lock times satisfy it,
+                        // and any other script is signed with one signature followed by the script data
+                        let miniscript = input.spent_miniscript();
+                        let stack = match MultisigScript::from_script(&input_script) {
+                            Ok(multisig) => {
+                                let public_key = private_key.to_public_key().to_secp256k1_public_key();
+                                if let Some(public_key) = multisig.find_public_key(&public_key) {
+                                    input.partial_signatures.insert(public_key.clone(), der_signature);
                                 }
-                            };
-                        // Determine whether to append or prepend other signature(s)
-                        let mut witness_field = match is_other_sig_first {
-                            true => vec![other_signature, signature.clone()],
-                            false => vec![signature.clone(), other_signature],
+                                match multisig.satisfy(&input.partial_signatures) {
+                                    Some(stack) => stack,
+                                    None => continue,
+                                }
+                            }
+                            Err(_) => match (miniscript, &input.witness_script_data) {
+                                (Some(miniscript), _) => {
+                                    let public_key = private_key.to_public_key().to_secp256k1_public_key();
+                                    input.partial_signatures.insert(public_key.serialize_compressed().to_vec(), der_signature);
+                                    let satisfier = Satisfier {
+                                        signatures: input.partial_signatures.clone(),
+                                        ..Satisfier::new(version, transaction_lock_time, input.sequence_number())
+                                    };
+                                    match miniscript.satisfy(&satisfier) {
+                                        Some(stack) => stack,
+                                        None => continue,
+                                    }
+                                }
+                                (None, Some(witness_script_data)) => vec![der_signature, witness_script_data.clone()],
+                                (None, None) => vec![der_signature],
+                            },
                         };
-                        // Append witness stack script args (before witness script)
-                        if input.witness_script_data.is_some() {
-                            let witness_script_data =
-                            input.witness_script_data.clone().unwrap();
-                            let witness_script_data =
-                                [vec![witness_script_data.len() as u8], witness_script_data].concat();
-                            witness_field.append(&mut vec![witness_script_data]);
+                        input.finalize_script(stack)?;
+                        if *format != BitcoinFormat::P2SH {
+                            self.parameters.segwit_flag = true;
                         }
-                        // Append the witness script last
-                        witness_field.append(&mut vec![ser_input_script.clone()]);
-                        input.witnesses.append(&mut witness_field);
-                        input.is_signed = true;
                     }
                     BitcoinFormat::P2SH_P2WPKH => {
                         let input_script = match &input.outpoint.redeem_script {
@@ -772,6 +1465,7 @@ impl<N: BitcoinNetwork> Transaction for BitcoinTransaction<N> {
                             .append(&mut vec![signature.clone(), public_key]);
                         input.is_signed = true;
                     }
+                    BitcoinFormat::P2TR => unreachable!(),
                 };
             }
         }
@@ -780,106 +1474,134 @@ impl<N: BitcoinNetwork> Transaction for BitcoinTransaction<N> {
         self.to_bytes()
     }
 
-    /// Returns a transaction given the transaction bytes.
-    /// Note:: Raw transaction hex does not include enough
-    fn from_bytes(transaction: &Vec<u8>) -> Result<Self, TransactionError> {
-        Ok(Self {
-            parameters: Self::TransactionParameters::read(&transaction[..])?,
-        })
-    }
-
-    /// Returns the transaction in bytes.
-    fn to_bytes(&self) -> Result<Vec<u8>, TransactionError> {
-        let mut transaction = self.parameters.version.to_le_bytes().to_vec();
-
-        if self.parameters.segwit_flag {
-            transaction.extend(vec![0x00, 0x01]);
+    /// Returns true if the transaction is a coinbase transaction, whose single input spends no outpoint.
+    pub fn is_coinbase(&self) -> bool {
+        match self.parameters.inputs.as_slice() {
+            [input] => input.outpoint.index == u32::MAX && input.outpoint.reverse_transaction_id.iter().all(|byte| *byte == 0),
+            _ => false,
         }
+    }
 
-        transaction.extend(variable_length_integer(self.parameters.inputs.len() as u64)?);
-        let mut has_witness = false;
-        for input in &self.parameters.inputs {
-            if !has_witness {
-                has_witness = input.witnesses.len() > 0;
+    /// Adds the signature of a cosigner of the multisig script of a P2SH, P2SH_P2WSH or P2WSH input,
+    /// e.g. signed on another device, and returns the transaction in bytes.
+    /// The signature is DER encoded and followed by the sighash byte of the input. The script sig
+    /// or witness of the input is completed once the threshold of signatures is reached.
+    pub fn add_multisig_signature(
+        &mut self,
+        vin: usize,
+        public_key: &BitcoinPublicKey<N>,
+        signature: Vec<u8>,
+    ) -> Result<Vec<u8>, TransactionError> {
+        let input = match self.parameters.inputs.get(vin) {
+            Some(input) if input.is_signed => {
+                return Err(TransactionError::InvalidInputs(format!("input {} is already signed", vin)))
             }
-            transaction.extend(input.serialize(!input.is_signed)?);
-        }
+            Some(input) => input,
+            None => return Err(TransactionError::InvalidInputs(format!("input {} does not exist", vin))),
+        };
+        let format = match &input.outpoint.address {
+            Some(address) => address.format(),
+            None => return Err(TransactionError::MissingOutpointAddress),
+        };
+        let multisig = match (&format, &input.outpoint.redeem_script) {
+            (BitcoinFormat::P2SH | BitcoinFormat::P2SH_P2WSH | BitcoinFormat::P2WSH, Some(script)) => {
+                MultisigScript::from_script(script)?
+            }
+            _ => return Err(TransactionError::InvalidInputs(format!("input {} is not a multisig {}", vin, format))),
+        };
+        let secp256k1_public_key = public_key.to_secp256k1_public_key();
+        let serialized_public_key = match multisig.find_public_key(&secp256k1_public_key) {
+            Some(public_key) => public_key.clone(),
+            None => return Err(TransactionError::InvalidInputs(format!("{} is not a cosigner of input {}", public_key, vin))),
+        };
 
-        transaction.extend(variable_length_integer(self.parameters.outputs.len() as u64)?);
-        for output in &self.parameters.outputs {
-            transaction.extend(output.serialize()?);
+        let sighash_code = input.sighash_code;
+        let der_signature = match signature.split_last() {
+            Some((&sighash, der_signature)) if sighash == sighash_code as u8 => der_signature,
+            _ => return Err(TransactionError::InvalidInputs(format!("signature without {} for input {}", sighash_code, vin))),
+        };
+        let preimage = match format {
+            BitcoinFormat::P2SH if N::fork_id().is_some() => self.fork_id_hash_preimage(vin, sighash_code)?,
+            BitcoinFormat::P2SH => self.p2pkh_hash_preimage(vin, sighash_code)?,
+            _ => self.segwit_hash_preimage(vin, sighash_code)?,
+        };
+        let transaction_hash = Sha256::digest(&Sha256::digest(&preimage));
+        if !libsecp256k1::verify(
+            &libsecp256k1::Message::parse_slice(&transaction_hash)?,
+            &libsecp256k1::Signature::parse_der(der_signature)?,
+            &secp256k1_public_key,
+        ) {
+            return Err(TransactionError::InvalidInputs(format!("invalid signature of {} for input {}", public_key, vin)));
         }
 
-        if has_witness {
-            for input in &self.parameters.inputs {
-                match input.witnesses.len() {
-                    0 => transaction.extend(vec![0x00]),
-                    _ => {
-                        transaction.extend(variable_length_integer(input.witnesses.len() as u64)?);
-                        for witness in &input.witnesses {
-                            transaction.extend(witness);
-                        }
-                    }
-                };
+        let input = &mut self.parameters.inputs[vin];
+        input.partial_signatures.insert(serialized_public_key, signature);
+        if let Some(stack) = multisig.satisfy(&input.partial_signatures) {
+            input.finalize_script(stack)?;
+            if format != BitcoinFormat::P2SH {
+                self.parameters.segwit_flag = true;
             }
         }
-
-        transaction.extend(&self.parameters.lock_time.to_le_bytes());
-
-        Ok(transaction)
+        self.to_bytes()
     }
 
-    /// Returns the transaction id.
-    fn to_transaction_id(&self) -> Result<Self::TransactionId, TransactionError> {
-        let mut txid = Sha256::digest(&Sha256::digest(&self.to_transaction_bytes_without_witness()?)).to_vec();
-        let mut wtxid = Sha256::digest(&Sha256::digest(&self.to_bytes()?)).to_vec();
-
-        txid.reverse();
-        wtxid.reverse();
-
-        Ok(Self::TransactionId { txid, wtxid })
+    /// Verifies the script sig and witness of the input against the script public key of its outpoint
+    /// under the given rules, returning the failing rule and opcode of an invalid input.
+    pub fn verify_input(&self, vin: usize, flags: VerificationFlags) -> Result<(), ScriptFailure> {
+        interpreter::verify_input(self, vin, flags)
     }
 
-    fn sign(&mut self, signature: Vec<u8>, recid: u8) -> Result<Vec<u8>, TransactionError> {
-        todo!()
+    /// Verifies every input under the given rules, returning the result of each input in order.
+    pub fn verify(&self, flags: VerificationFlags) -> Vec<Result<(), ScriptFailure>> {
+        let cache = match SignatureHashCache::new(self) {
+            Ok(cache) => cache,
+            // Every input then fails as with verify_input
+            Err(_) => return (0..self.parameters.inputs.len()).map(|vin| self.verify_input(vin, flags)).collect(),
+        };
+        (0..self.parameters.inputs.len())
+            .map(|vin| interpreter::verify_input_with_cache(self, &cache, vin, flags))
+            .collect()
     }
-}
 
-impl<N: BitcoinNetwork> BitcoinTransaction<N> {
-    /// Return the P2PKH hash preimage of the raw transaction.
+    /// Return the P2PKH hash preimage of the raw transaction, whose script code is the script public key
+    /// of a P2PKH input or the redeem script of a P2SH input.
     pub fn p2pkh_hash_preimage(&self, vin: usize, sighash: SignatureHash) -> Result<Vec<u8>, TransactionError> {
-        let mut preimage = self.parameters.version.to_le_bytes().to_vec();
-        preimage.extend(variable_length_integer(self.parameters.inputs.len() as u64)?);
-        for (index, input) in self.parameters.inputs.iter().enumerate() {
-            preimage.extend(input.serialize(index != vin)?);
-        }
-        preimage.extend(variable_length_integer(self.parameters.outputs.len() as u64)?);
-        for output in &self.parameters.outputs {
-            preimage.extend(output.serialize()?);
-        }
-        preimage.extend(&self.parameters.lock_time.to_le_bytes());
-        preimage.extend(&(sighash as u32).to_le_bytes());
-        Ok(preimage)
+        Self::check_ecdsa_sighash(sighash)?;
+        let input = match self.parameters.inputs.get(vin) {
+            Some(input) => input,
+            None => return Err(TransactionError::InvalidInputs(format!("input {} does not exist", vin))),
+        };
+        let script_code = match input.outpoint.address.as_ref().map(|address| address.format()) {
+            Some(BitcoinFormat::P2SH) => match &input.outpoint.redeem_script {
+                Some(redeem_script) => redeem_script,
+                None => return Err(TransactionError::InvalidInputs("P2SH".into())),
+            },
+            _ => match &input.outpoint.script_pub_key {
+                Some(script_pub_key) => script_pub_key,
+                None => return Err(TransactionError::MissingOutpointScriptPublicKey),
+            },
+        };
+        self.legacy_signature_message(vin, script_code, sighash as u32)
     }
 
     /// Return the SegWit hash preimage of the raw transaction
     /// https://github.com/bitcoin/bips/blob/master/bip-0143.mediawiki#specification
     pub fn segwit_hash_preimage(&self, vin: usize, sighash: SignatureHash) -> Result<Vec<u8>, TransactionError> {
-        let mut prev_outputs = vec![];
-        let mut prev_sequences = vec![];
-        let mut outputs = vec![];
-
-        for input in &self.parameters.inputs {
-            prev_outputs.extend(&input.outpoint.reverse_transaction_id);
-            prev_outputs.extend(&input.outpoint.index.to_le_bytes());
-            prev_sequences.extend(&input.sequence);
-        }
-
-        for output in &self.parameters.outputs {
-            outputs.extend(&output.serialize()?);
-        }
+        self.segwit_signature_message(&SignatureHashCache::new(self)?, vin, sighash)
+    }
 
-        let input = &self.parameters.inputs[vin];
+    /// Return the SegWit hash preimage of the raw transaction with its midstates.
+    fn segwit_signature_message(
+        &self,
+        cache: &SignatureHashCache,
+        vin: usize,
+        sighash: SignatureHash,
+    ) -> Result<Vec<u8>, TransactionError> {
+        Self::check_ecdsa_sighash(sighash)?;
+        let input = match self.parameters.inputs.get(vin) {
+            Some(input) => input,
+            None => return Err(TransactionError::InvalidInputs(format!("input {} does not exist", vin))),
+        };
         let format = match &input.outpoint.address {
             Some(address) => address.format(),
             None => return Err(TransactionError::MissingOutpointAddress),
@@ -890,19 +1612,21 @@ impl<N: BitcoinNetwork> BitcoinTransaction<N> {
                 Some(script) => script[1..].to_vec(),
                 None => return Err(TransactionError::MissingOutpointScriptPublicKey),
             },
-            BitcoinFormat::P2WSH => match &input.outpoint.redeem_script {
-                Some(redeem_script) => redeem_script.to_vec(),
-                None => return Err(TransactionError::InvalidInputs("P2WSH".into())),
+            BitcoinFormat::P2SH_P2WSH | BitcoinFormat::P2WSH => match &input.outpoint.redeem_script {
+                Some(witness_script) => witness_script.to_vec(),
+                None => return Err(TransactionError::InvalidInputs(format!("{:?}", format))),
             },
             BitcoinFormat::P2SH_P2WPKH => match &input.outpoint.redeem_script {
                 Some(redeem_script) => redeem_script[1..].to_vec(),
                 None => return Err(TransactionError::InvalidInputs("P2SH_P2WPKH".into())),
             },
             BitcoinFormat::P2PKH => return Err(TransactionError::UnsupportedPreimage("P2PKH".into())),
+            BitcoinFormat::P2SH => return Err(TransactionError::UnsupportedPreimage("P2SH".into())),
+            BitcoinFormat::P2TR => return Err(TransactionError::UnsupportedPreimage("P2TR".into())),
         };
 
         let mut script_code = vec![];
-        if format == BitcoinFormat::P2WSH {
+        if format == BitcoinFormat::P2WSH || format == BitcoinFormat::P2SH_P2WSH {
             script_code.extend(script);
         } else {
             script_code.push(Opcode::OP_DUP as u8);
@@ -911,90 +1635,550 @@ impl<N: BitcoinNetwork> BitcoinTransaction<N> {
             script_code.push(Opcode::OP_EQUALVERIFY as u8);
             script_code.push(Opcode::OP_CHECKSIG as u8);
         }
-        let script_code = [variable_length_integer(script_code.len() as u64)?, script_code].concat();
-        let hash_prev_outputs = Sha256::digest(&Sha256::digest(&prev_outputs));
-        let hash_sequence = Sha256::digest(&Sha256::digest(&prev_sequences));
-        let hash_outputs = Sha256::digest(&Sha256::digest(&outputs));
-        let outpoint_amount = match &input.outpoint.amount {
-            Some(amount) => amount.0.to_le_bytes(),
+        let amount = match input.outpoint.amount {
+            Some(amount) => amount,
             None => return Err(TransactionError::MissingOutpointAmount),
         };
 
-        let mut preimage = vec![];
-        preimage.extend(&self.parameters.version.to_le_bytes());
-        preimage.extend(hash_prev_outputs);
-        preimage.extend(hash_sequence);
-        preimage.extend(&input.outpoint.reverse_transaction_id);
-        preimage.extend(&input.outpoint.index.to_le_bytes());
-        preimage.extend(&script_code);
-        preimage.extend(&outpoint_amount);
-        preimage.extend(&input.sequence);
-        preimage.extend(hash_outputs);
-        preimage.extend(&self.parameters.lock_time.to_le_bytes());
-        preimage.extend(&(sighash as u32).to_le_bytes());
-
-        Ok(preimage)
+        self.witness_v0_signature_message(cache, vin, &script_code, amount, sighash as u32)
     }
 
-    /// Returns the transaction with the traditional serialization (no witness).
-    fn to_transaction_bytes_without_witness(&self) -> Result<Vec<u8>, TransactionError> {
-        let mut transaction = self.parameters.version.to_le_bytes().to_vec();
-
-        transaction.extend(variable_length_integer(self.parameters.inputs.len() as u64)?);
-        for input in &self.parameters.inputs {
-            transaction.extend(input.serialize(false)?);
-        }
+    /// Return the replay protected hash preimage of a P2PKH or P2SH input of the raw transaction on a network
+    /// with a fork id, which follows BIP-143 for every input and commits to the fork id in the sighash type.
+    /// https://github.com/bitcoincashorg/bitcoincash.org/blob/master/spec/replay-protected-sighash.md
+    pub fn fork_id_hash_preimage(&self, vin: usize, sighash: SignatureHash) -> Result<Vec<u8>, TransactionError> {
+        self.fork_id_signature_message(&SignatureHashCache::new(self)?, vin, sighash)
+    }
 
-        transaction.extend(variable_length_integer(self.parameters.outputs.len() as u64)?);
-        for output in &self.parameters.outputs {
-            transaction.extend(output.serialize()?);
+    /// Return the replay protected hash preimage of a P2PKH or P2SH input with the midstates of the transaction.
+    fn fork_id_signature_message(
+        &self,
+        cache: &SignatureHashCache,
+        vin: usize,
+        sighash: SignatureHash,
+    ) -> Result<Vec<u8>, TransactionError> {
+        let fork_id = match N::fork_id() {
+            Some(fork_id) => fork_id,
+            None => return Err(TransactionError::UnsupportedPreimage(format!("SIGHASH_FORKID on {}", N::NAME))),
+        };
+        if !sighash.has_fork_id() {
+            return Err(TransactionError::InvalidInputs(format!("{} without SIGHASH_FORKID", sighash)));
         }
 
-        transaction.extend(&self.parameters.lock_time.to_le_bytes());
+        let input = match self.parameters.inputs.get(vin) {
+            Some(input) => input,
+            None => return Err(TransactionError::InvalidInputs(format!("input {} does not exist", vin))),
+        };
+        let format = match &input.outpoint.address {
+            Some(address) => address.format(),
+            None => return Err(TransactionError::MissingOutpointAddress),
+        };
+        let script_code = match format {
+            BitcoinFormat::P2PKH => match &input.outpoint.script_pub_key {
+                Some(script_pub_key) => script_pub_key,
+                None => return Err(TransactionError::MissingOutpointScriptPublicKey),
+            },
+            BitcoinFormat::P2SH => match &input.outpoint.redeem_script {
+                Some(redeem_script) => redeem_script,
+                None => return Err(TransactionError::InvalidInputs("P2SH".into())),
+            },
+            format => return Err(TransactionError::UnsupportedPreimage(format.to_string())),
+        };
+        let amount = match input.outpoint.amount {
+            Some(amount) => amount,
+            None => return Err(TransactionError::MissingOutpointAmount),
+        };
 
-        Ok(transaction)
+        self.witness_v0_signature_message(cache, vin, script_code, amount, fork_id << 8 | sighash as u32)
     }
 
-    /// Update a transaction's input outpoint
-    #[allow(dead_code)]
-    pub fn update_outpoint(&self, outpoint: Outpoint<N>) -> Self {
-        let mut new_transaction = self.clone();
-        for (vin, input) in self.parameters.inputs.iter().enumerate() {
-            if &outpoint.reverse_transaction_id == &input.outpoint.reverse_transaction_id &&
-                &outpoint.index == &input.outpoint.index {
-                new_transaction.parameters.inputs[vin].outpoint = outpoint.clone();
-            }
-        }
-        new_transaction
+    /// Return the Taproot key path hash preimage of the raw transaction, prefixed with its epoch byte.
+    /// Unless signing with SIGHASH_ANYONECANPAY, the outpoints of all inputs must carry their amount and script public key.
+    /// https://github.com/bitcoin/bips/blob/master/bip-0341.mediawiki#common-signature-message
+    pub fn taproot_hash_preimage(&self, vin: usize, sighash: SignatureHash) -> Result<Vec<u8>, TransactionError> {
+        self.taproot_signature_message(&SignatureHashCache::new(self)?, vin, sighash as u8, None, None)
     }
-}
 
-impl<N: BitcoinNetwork> FromStr for BitcoinTransaction<N> {
-    type Err = TransactionError;
+    /// Return the Taproot script path hash preimage of the raw transaction spending the input through the given leaf.
+    /// https://github.com/bitcoin/bips/blob/master/bip-0342.mediawiki#signature-validation
+    pub fn taproot_script_path_hash_preimage(
+        &self,
+        vin: usize,
+        leaf: &TapLeaf,
+        sighash: SignatureHash,
+    ) -> Result<Vec<u8>, TransactionError> {
+        self.taproot_signature_message(&SignatureHashCache::new(self)?, vin, sighash as u8, None, Some((leaf.hash(), u32::MAX)))
+    }
 
-    fn from_str(transaction: &str) -> Result<Self, Self::Err> {
-        Self::from_bytes(&hex::decode(transaction)?)
+    /// Returns the BIP-340 signature of the private key for spending the input through the given leaf,
+    /// followed by the sighash byte of the input unless it is SIGHASH_DEFAULT.
+    /// The signature is deterministic, with zero auxiliary randomness as BIP-340 permits.
+    pub fn taproot_script_path_signature(
+        &self,
+        vin: usize,
+        private_key: &BitcoinPrivateKey<N>,
+        leaf: &TapLeaf,
+    ) -> Result<Vec<u8>, TransactionError> {
+        self.taproot_script_path_signature_with_aux(vin, private_key, leaf, &[0u8; 32])
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::Mainnet;
-    use chainlib_core::crypto::hash160;
+    /// Returns the signature of `taproot_script_path_signature` with auxiliary randomness drawn from the RNG of the context.
+    pub fn taproot_script_path_signature_with_context<C: Clock, R: Rng>(
+        &self,
+        vin: usize,
+        private_key: &BitcoinPrivateKey<N>,
+        leaf: &TapLeaf,
+        context: &mut Context<C, R>,
+    ) -> Result<Vec<u8>, TransactionError> {
+        self.taproot_script_path_signature_with_aux(vin, private_key, leaf, &context.rng().gen())
+    }
 
-    pub struct TransactionTestCase<'a> {
-        pub version: u32,
-        pub lock_time: u32,
-        pub inputs: &'a [Input],
-        pub outputs: &'a [Output],
-        pub expected_signed_transaction: &'a str,
-        pub expected_transaction_id: &'a str,
+    fn taproot_script_path_signature_with_aux(
+        &self,
+        vin: usize,
+        private_key: &BitcoinPrivateKey<N>,
+        leaf: &TapLeaf,
+        aux_rand: &[u8; 32],
+    ) -> Result<Vec<u8>, TransactionError> {
+        let sighash_code = match self.parameters.inputs.get(vin) {
+            Some(input) => input.sighash_code,
+            None => return Err(TransactionError::InvalidInputs(format!("input {} does not exist", vin))),
+        };
+        let sighash = tagged_hash("TapSighash", &self.taproot_script_path_hash_preimage(vin, leaf, sighash_code)?);
+        let mut signature = schnorr_sign(&sighash, &private_key.to_secp256k1_secret_key(), aux_rand)?.to_vec();
+        if sighash_code != SignatureHash::SIGHASH_DEFAULT {
+            signature.push(sighash_code as u8);
+        }
+        Ok(signature)
     }
 
-    #[derive(Debug, Clone)]
-    pub struct Input {
-        pub private_key: &'static str,
+    /// Completes the witness of a Taproot script path spend with the stack satisfying the leaf script
+    /// (e.g. its signatures), the leaf script and the control block, and returns the transaction in bytes.
+    /// The control block must prove that the output key of the spent output commits to the leaf script.
+    pub fn add_taproot_script_path_witness(
+        &mut self,
+        vin: usize,
+        stack: Vec<Vec<u8>>,
+        leaf: &TapLeaf,
+        control_block: &ControlBlock,
+    ) -> Result<Vec<u8>, TransactionError> {
+        let input = match self.parameters.inputs.get_mut(vin) {
+            Some(input) => input,
+            None => return Err(TransactionError::InvalidInputs(format!("input {} does not exist", vin))),
+        };
+        let output_key = match &input.outpoint.script_pub_key {
+            Some(script_pub_key) if Script::from(&script_pub_key[..]).is_p2tr() => {
+                let mut output_key = [0u8; 32];
+                output_key.copy_from_slice(&script_pub_key[2..]);
+                output_key
+            }
+            Some(_) => return Err(TransactionError::InvalidScriptPubKey("P2TR".into())),
+            None => return Err(TransactionError::MissingOutpointScriptPublicKey),
+        };
+        if control_block.leaf_version != leaf.version || !control_block.verify(&output_key, &leaf.script) {
+            return Err(TransactionError::InvalidInputs("P2TR: control block does not commit to the leaf script".into()));
+        }
+
+        let mut witnesses = stack;
+        witnesses.push(leaf.script.clone());
+        witnesses.push(control_block.serialize());
+        input.witnesses = witnesses
+            .into_iter()
+            .map(|witness| Ok([variable_length_integer(witness.len() as u64)?, witness].concat()))
+            .collect::<Result<Vec<Vec<u8>>, TransactionError>>()?;
+        input.is_signed = true;
+        self.parameters.segwit_flag = true;
+        self.to_bytes()
+    }
+
+    /// Return the BIP-341 signature message of the input given its hash type and annex (with its 0x50 tag),
+    /// extended for script path spends with the leaf hash and the position of the last executed OP_CODESEPARATOR.
+    pub(crate) fn taproot_signature_message(
+        &self,
+        cache: &SignatureHashCache,
+        vin: usize,
+        hash_type: u8,
+        annex: Option<&[u8]>,
+        leaf: Option<([u8; 32], u32)>,
+    ) -> Result<Vec<u8>, TransactionError> {
+        if vin >= self.parameters.inputs.len() {
+            return Err(TransactionError::InvalidInputs(format!("input {} does not exist", vin)));
+        }
+        let anyone_can_pay = hash_type as u32 & 0x80 != 0;
+        let base = hash_type as u32 & 0x03;
+        let single = base == SignatureHash::SIGHASH_SINGLE as u32;
+        let none = base == SignatureHash::SIGHASH_NONE as u32;
+
+        let spent_output = |input: &BitcoinTransactionInput<N>| -> Result<Vec<u8>, TransactionError> {
+            let amount = match &input.outpoint.amount {
+                Some(amount) => amount.0.to_le_bytes(),
+                None => return Err(TransactionError::MissingOutpointAmount),
+            };
+            let script_pub_key = match &input.outpoint.script_pub_key {
+                Some(script_pub_key) => script_pub_key,
+                None => return Err(TransactionError::MissingOutpointScriptPublicKey),
+            };
+            Ok([amount.to_vec(), variable_length_integer(script_pub_key.len() as u64)?, script_pub_key.clone()].concat())
+        };
+
+        let mut preimage = vec![0x00, hash_type];
+        preimage.extend(&self.parameters.version.to_le_bytes());
+        preimage.extend(&self.parameters.lock_time.to_le_bytes());
+
+        if !anyone_can_pay {
+            let (sha_amounts, sha_script_pub_keys) = match (cache.sha_amounts, cache.sha_script_pub_keys) {
+                (Some(sha_amounts), Some(sha_script_pub_keys)) => (sha_amounts, sha_script_pub_keys),
+                // Reports the spent output missing from the first incomplete outpoint
+                _ => {
+                    self.parameters.inputs.iter().try_for_each(|input| spent_output(input).map(|_| ()))?;
+                    return Err(TransactionError::MissingOutpointAmount);
+                }
+            };
+            preimage.extend(cache.sha_prevouts);
+            preimage.extend(sha_amounts);
+            preimage.extend(sha_script_pub_keys);
+            preimage.extend(cache.sha_sequences);
+        }
+
+        if !single && !none {
+            preimage.extend(cache.sha_outputs);
+        }
+
+        // The spend type is the extension flag (1 for tapscript) times two, plus one with an annex
+        preimage.push(match leaf {
+            Some(_) => 0x02,
+            None => 0x00,
+        } + annex.is_some() as u8);
+
+        let input = &self.parameters.inputs[vin];
+        match anyone_can_pay {
+            true => {
+                preimage.extend(&input.outpoint.reverse_transaction_id);
+                preimage.extend(&input.outpoint.index.to_le_bytes());
+                preimage.extend(spent_output(input)?);
+                preimage.extend(&input.sequence);
+            }
+            false => preimage.extend(&(vin as u32).to_le_bytes()),
+        }
+
+        if let Some(annex) = annex {
+            preimage.extend(Sha256::digest([variable_length_integer(annex.len() as u64)?, annex.to_vec()].concat()));
+        }
+
+        if single {
+            match self.parameters.outputs.get(vin) {
+                Some(output) => preimage.extend(Sha256::digest(&output.serialize()?)),
+                None => return Err(TransactionError::InvalidInputs(format!("input {} has no output to sign", vin))),
+            }
+        }
+
+        // The tapscript extension commits to the leaf, the key version and the last executed OP_CODESEPARATOR
+        if let Some((leaf_hash, code_separator_position)) = leaf {
+            preimage.extend(leaf_hash);
+            preimage.push(0x00);
+            preimage.extend(&code_separator_position.to_le_bytes());
+        }
+
+        Ok(preimage)
+    }
+
+    /// Return the legacy hash preimage of the raw transaction given the script code of the input,
+    /// for inputs whose outpoint does not carry its address (e.g. in a PSBT).
+    /// https://en.bitcoin.it/wiki/OP_CHECKSIG
+    pub fn legacy_hash_preimage(
+        &self,
+        vin: usize,
+        script_code: &[u8],
+        sighash: SignatureHash,
+    ) -> Result<Vec<u8>, TransactionError> {
+        Self::check_ecdsa_sighash(sighash)?;
+        self.legacy_signature_message(vin, script_code, sighash as u32)
+    }
+
+    /// Return the legacy signature hash of the input given its script code, which is the number one
+    /// when signing with SIGHASH_SINGLE an input without a matching output (the SIGHASH_SINGLE bug).
+    /// Such a signature commits to no part of the transaction, so it has no preimage to sign.
+    /// https://en.bitcoin.it/wiki/OP_CHECKSIG#Procedure_for_Hashtype_SIGHASH_SINGLE
+    pub fn legacy_signature_hash(
+        &self,
+        vin: usize,
+        script_code: &[u8],
+        sighash: SignatureHash,
+    ) -> Result<[u8; 32], TransactionError> {
+        Self::check_ecdsa_sighash(sighash)?;
+        self.legacy_signature_digest(vin, script_code, sighash as u32)
+    }
+
+    /// Return the legacy signature hash of the input given its script code and any hash type.
+    pub(crate) fn legacy_signature_digest(
+        &self,
+        vin: usize,
+        script_code: &[u8],
+        hash_type: u32,
+    ) -> Result<[u8; 32], TransactionError> {
+        if vin >= self.parameters.inputs.len() {
+            return Err(TransactionError::InvalidInputs(format!("input {} does not exist", vin)));
+        }
+        if hash_type & 0x1f == SignatureHash::SIGHASH_SINGLE as u32 && vin >= self.parameters.outputs.len() {
+            let mut one = [0u8; 32];
+            one[0] = 0x01;
+            return Ok(one);
+        }
+        let mut hash = [0u8; 32];
+        hash.copy_from_slice(&Sha256::digest(Sha256::digest(self.legacy_signature_message(vin, script_code, hash_type)?)));
+        Ok(hash)
+    }
+
+    /// Return the legacy signature message of the input given its script code and any hash type,
+    /// including the undefined ones accepted by consensus. The OP_CODESEPARATOR opcodes of the
+    /// script code are not signed.
+    pub(crate) fn legacy_signature_message(
+        &self,
+        vin: usize,
+        script_code: &[u8],
+        hash_type: u32,
+    ) -> Result<Vec<u8>, TransactionError> {
+        if vin >= self.parameters.inputs.len() {
+            return Err(TransactionError::InvalidInputs(format!("input {} does not exist", vin)));
+        }
+        let anyone_can_pay = hash_type & 0x80 != 0;
+        let base = hash_type & 0x1f;
+
+        let mut preimage = self.parameters.version.to_le_bytes().to_vec();
+        let inputs: Vec<(usize, &BitcoinTransactionInput<N>)> = match anyone_can_pay {
+            true => vec![(vin, &self.parameters.inputs[vin])],
+            false => self.parameters.inputs.iter().enumerate().collect(),
+        };
+        preimage.extend(variable_length_integer(inputs.len() as u64)?);
+        for (index, input) in inputs {
+            preimage.extend(&input.outpoint.reverse_transaction_id);
+            preimage.extend(&input.outpoint.index.to_le_bytes());
+            match index == vin {
+                true => {
+                    let script_code = remove_code_separators(script_code);
+                    preimage.extend(variable_length_integer(script_code.len() as u64)?);
+                    preimage.extend(script_code);
+                }
+                false => preimage.push(0x00),
+            }
+            // Other inputs may be updated when their sequence is not signed
+            match index != vin && (base == SignatureHash::SIGHASH_NONE as u32 || base == SignatureHash::SIGHASH_SINGLE as u32) {
+                true => preimage.extend(&[0u8; 4]),
+                false => preimage.extend(&input.sequence),
+            }
+        }
+
+        match base {
+            base if base == SignatureHash::SIGHASH_NONE as u32 => preimage.push(0x00),
+            base if base == SignatureHash::SIGHASH_SINGLE as u32 => {
+                if vin >= self.parameters.outputs.len() {
+                    return Err(TransactionError::InvalidInputs(format!("input {} has no output to sign", vin)));
+                }
+                preimage.extend(variable_length_integer(vin as u64 + 1)?);
+                for _ in 0..vin {
+                    preimage.extend(&u64::MAX.to_le_bytes());
+                    preimage.push(0x00);
+                }
+                preimage.extend(self.parameters.outputs[vin].serialize()?);
+            }
+            _ => {
+                preimage.extend(variable_length_integer(self.parameters.outputs.len() as u64)?);
+                for output in &self.parameters.outputs {
+                    preimage.extend(output.serialize()?);
+                }
+            }
+        }
+
+        preimage.extend(&self.parameters.lock_time.to_le_bytes());
+        preimage.extend(&hash_type.to_le_bytes());
+        Ok(preimage)
+    }
+
+    /// Return the SegWit v0 hash preimage of the raw transaction given the script code and amount of the input,
+    /// for inputs whose outpoint does not carry its address (e.g. in a PSBT).
+    /// https://github.com/bitcoin/bips/blob/master/bip-0143.mediawiki#specification
+    pub fn witness_v0_hash_preimage(
+        &self,
+        vin: usize,
+        script_code: &[u8],
+        amount: BitcoinAmount,
+        sighash: SignatureHash,
+    ) -> Result<Vec<u8>, TransactionError> {
+        Self::check_ecdsa_sighash(sighash)?;
+        self.witness_v0_signature_message(&SignatureHashCache::new(self)?, vin, script_code, amount, sighash as u32)
+    }
+
+    /// Return the SegWit v0 signature message of the input given its script code, amount and any hash type,
+    /// with the midstates of the transaction.
+    pub(crate) fn witness_v0_signature_message(
+        &self,
+        cache: &SignatureHashCache,
+        vin: usize,
+        script_code: &[u8],
+        amount: BitcoinAmount,
+        hash_type: u32,
+    ) -> Result<Vec<u8>, TransactionError> {
+        if vin >= self.parameters.inputs.len() {
+            return Err(TransactionError::InvalidInputs(format!("input {} does not exist", vin)));
+        }
+        let anyone_can_pay = hash_type & 0x80 != 0;
+        let base = hash_type & 0x1f;
+        let single = base == SignatureHash::SIGHASH_SINGLE as u32;
+        let none = base == SignatureHash::SIGHASH_NONE as u32;
+
+        let hash_prev_outputs = match anyone_can_pay {
+            true => [0u8; 32],
+            false => cache.hash_prevouts,
+        };
+        let hash_sequence = match anyone_can_pay || single || none {
+            true => [0u8; 32],
+            false => cache.hash_sequence,
+        };
+        let hash_outputs = match (single, none) {
+            (false, false) => cache.hash_outputs,
+            (true, _) if vin < self.parameters.outputs.len() => {
+                Sha256::digest(Sha256::digest(self.parameters.outputs[vin].serialize()?)).into()
+            }
+            _ => [0u8; 32],
+        };
+
+        let input = &self.parameters.inputs[vin];
+        let mut preimage = vec![];
+        preimage.extend(&self.parameters.version.to_le_bytes());
+        preimage.extend(hash_prev_outputs);
+        preimage.extend(hash_sequence);
+        preimage.extend(&input.outpoint.reverse_transaction_id);
+        preimage.extend(&input.outpoint.index.to_le_bytes());
+        preimage.extend(variable_length_integer(script_code.len() as u64)?);
+        preimage.extend(script_code);
+        preimage.extend(&amount.0.to_le_bytes());
+        preimage.extend(&input.sequence);
+        preimage.extend(hash_outputs);
+        preimage.extend(&self.parameters.lock_time.to_le_bytes());
+        preimage.extend(&hash_type.to_le_bytes());
+
+        Ok(preimage)
+    }
+
+    /// Returns an error unless the signature hash is defined for ECDSA signatures on the network,
+    /// as SIGHASH_DEFAULT is reserved to Taproot and SIGHASH_FORKID to networks with a fork id.
+    fn check_ecdsa_sighash(sighash: SignatureHash) -> Result<(), TransactionError> {
+        match sighash {
+            SignatureHash::SIGHASH_DEFAULT => Err(TransactionError::InvalidSignatureHash(sighash as u32)),
+            sighash if sighash.has_fork_id() != N::fork_id().is_some() => {
+                Err(TransactionError::InvalidSignatureHash(sighash as u32))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the absolute lock time of the transaction.
+    pub fn lock_time(&self) -> LockTime {
+        LockTime::from_consensus(self.parameters.lock_time)
+    }
+
+    /// Returns true if any input signals replaceability (BIP-125).
+    pub fn signals_rbf(&self) -> bool {
+        self.parameters.inputs.iter().any(|input| input.signals_rbf())
+    }
+
+    /// Returns the weight of the transaction once signed, in weight units.
+    /// https://github.com/bitcoin/bips/blob/master/bip-0141.mediawiki#transaction-size-calculations
+    pub fn weight(&self) -> Result<u64, TransactionError> {
+        let (base, witness) = self.estimate_sizes()?;
+        Ok(base * 4 + witness)
+    }
+
+    /// Returns the virtual size of the transaction once signed, in virtual bytes.
+    pub fn vsize(&self) -> Result<u64, TransactionError> {
+        Ok(self.weight()?.div_ceil(4))
+    }
+
+    /// Returns the sizes of the serialization without witness and of the witness data once signed.
+    fn estimate_sizes(&self) -> Result<(u64, u64), TransactionError> {
+        let mut base = self.parameters.version.to_le_bytes().len() + self.parameters.lock_time.to_le_bytes().len();
+        base += variable_length_integer(self.parameters.inputs.len() as u64)?.len();
+        base += variable_length_integer(self.parameters.outputs.len() as u64)?.len();
+
+        let mut witnesses = vec![];
+        for input in &self.parameters.inputs {
+            let (script_sig, witness) = input.estimate_size()?;
+            base += input.outpoint.reverse_transaction_id.len() + 4 + input.sequence.len();
+            base += variable_length_integer(script_sig as u64)?.len() + script_sig;
+            witnesses.push(witness);
+        }
+        for output in &self.parameters.outputs {
+            base += output.serialize()?.len();
+        }
+
+        // The segwit marker and flag, then an empty witness for each input without one
+        let witness = match witnesses.iter().any(|witness| *witness > 0) {
+            true => 2 + witnesses.iter().map(|witness| (*witness).max(1)).sum::<usize>(),
+            false => 0,
+        };
+
+        Ok((base as u64, witness as u64))
+    }
+
+    /// Returns the transaction with the traditional serialization (no witness).
+    fn to_transaction_bytes_without_witness(&self) -> Result<Vec<u8>, TransactionError> {
+        let mut transaction = self.parameters.version.to_le_bytes().to_vec();
+
+        transaction.extend(variable_length_integer(self.parameters.inputs.len() as u64)?);
+        for input in &self.parameters.inputs {
+            transaction.extend(input.serialize(false)?);
+        }
+
+        transaction.extend(variable_length_integer(self.parameters.outputs.len() as u64)?);
+        for output in &self.parameters.outputs {
+            transaction.extend(output.serialize()?);
+        }
+
+        transaction.extend(&self.parameters.lock_time.to_le_bytes());
+
+        Ok(transaction)
+    }
+
+    /// Update a transaction's input outpoint
+    #[allow(dead_code)]
+    pub fn update_outpoint(&self, outpoint: Outpoint<N>) -> Self {
+        let mut new_transaction = self.clone();
+        for (vin, input) in self.parameters.inputs.iter().enumerate() {
+            if &outpoint.reverse_transaction_id == &input.outpoint.reverse_transaction_id &&
+                &outpoint.index == &input.outpoint.index {
+                new_transaction.parameters.inputs[vin].outpoint = outpoint.clone();
+            }
+        }
+        new_transaction
+    }
+}
+
+impl<N: BitcoinNetwork> FromStr for BitcoinTransaction<N> {
+    type Err = TransactionError;
+
+    fn from_str(transaction: &str) -> Result<Self, Self::Err> {
+        Self::from_bytes(&hex::decode(transaction)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BitcoinCash, Dash, Dogecoin, Litecoin, Mainnet};
+    use chainlib_core::crypto::hash160;
+
+    pub struct TransactionTestCase<'a> {
+        pub version: u32,
+        pub lock_time: u32,
+        pub inputs: &'a [Input],
+        pub outputs: &'a [Output],
+        pub expected_signed_transaction: &'a str,
+        pub expected_transaction_id: &'a str,
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct Input {
+        pub private_key: &'static str,
         pub address_format: BitcoinFormat,
         pub transaction_id: &'static str,
         pub index: u32,
@@ -1056,10 +2240,6 @@ mod tests {
             )
             .unwrap();
 
-            // check if P2WSH input (include any additional witness)
-            transaction_input.additional_witness = Some((vec![0x00], false));
-            transaction_input.witness_script_data = None;
-
             input_vec.push(transaction_input);
         }
 
@@ -1370,205 +2550,638 @@ mod tests {
                         sighash_code: SignatureHash::SIGHASH_ALL
                     },
                     Input {
-                        private_key: "KwcN2pT3wnRAurhy7qMczzbkpY5nXMW2ubh696UBc1bcwctTx26z",
+                        private_key: "KwcN2pT3wnRAurhy7qMczzbkpY5nXMW2ubh696UBc1bcwctTx26z",
+                        address_format: BitcoinFormat::P2PKH,
+                        transaction_id: "7d865e959b2466918c9863afca942d0fb89d7c9ac0c99bafc3749504ded97730",
+                        index: 0,
+                        redeem_script: None,
+                        script_pub_key: None,
+                        utxo_amount: BitcoinAmount(0),
+                        sequence: Some([0xff, 0xff, 0xff, 0xff]),
+                        sighash_code: SignatureHash::SIGHASH_ALL
+                    }
+                ],
+                outputs: &[
+                    Output {
+                        address: "1CUNEBjYrCn2y1SdiUMohaKUi4wpP326Lb",
+                        amount: BitcoinAmount(180000)
+                    },
+                    Output {
+                        address: "1JtK9CQw1syfWj1WtFMWomrYdV3W2tWBF9",
+                        amount: BitcoinAmount(170000)
+                    },
+                ],
+                expected_signed_transaction: "01000000024c94e48a870b85f41228d33cf25213dfcc8dd796e7211ed6b1f9a014809dbbb5060000006a473044022041450c258ce7cac7da97316bf2ea1ce66d88967c4df94f3e91f4c2a30f5d08cb02203674d516e6bb2b0afd084c3551614bd9cec3c2945231245e891b145f2d6951f0012103e05ce435e462ec503143305feb6c00e06a3ad52fbf939e85c65f3a765bb7baacffffffff3077d9de049574c3af9bc9c09a7c9db80f2d94caaf63988c9166249b955e867d000000006b483045022100aeb5f1332c79c446d3f906e4499b2e678500580a3f90329edf1ba502eec9402e022072c8b863f8c8d6c26f4c691ac9a6610aa4200edc697306648ee844cfbc089d7a012103df7940ee7cddd2f97763f67e1fb13488da3fbdd7f9c68ec5ef0864074745a289ffffffff0220bf0200000000001976a9147dd65592d0ab2fe0d0257d571abf032cd9db93dc88ac10980200000000001976a914c42e7ef92fdb603af844d064faad95db9bcdfd3d88ac00000000",
+                expected_transaction_id: "af0ae7ab766f49c33312d33541868c2185ad559cc0457e7af398311bda4f18f7",
+            },
+            TransactionTestCase { // p2sh_p2wsh to multiple address types
+                version: 2,
+                lock_time: 0,
+                inputs: &[
+                    Input {
+                        private_key: "Kxxkik2L9KgrGgvdkEvYSkgAxaY4qPGfvxe1M1KBVBB7Ls3xDD8o",
+                        address_format: BitcoinFormat::P2SH_P2WPKH,
+                        transaction_id: "7c95424e4c86467eaea85b878985fa77d191bad2b9c5cac5a0cb98f760616afa",
+                        index: 55,
+                        redeem_script: None,
+                        script_pub_key: None,
+                        utxo_amount: BitcoinAmount(2000000),
+                        sequence: None,
+                        sighash_code: SignatureHash::SIGHASH_ALL
+                    },
+                ],
+                outputs: &[
+                    Output {
+                        address: "3DTGFEmobt8BaJpfPe62HvCQKp2iGsnYqD",
+                        amount: BitcoinAmount(30000)
+                    },
+                    Output {
+                        address: "1NxCpkhj6n8orGdhPpxCD3B52WvoR4CS7S",
+                        amount: BitcoinAmount(2000000)
+                    },
+                ],
+                expected_signed_transaction: "02000000000101fa6a6160f798cba0c5cac5b9d2ba91d177fa8589875ba8ae7e46864c4e42957c37000000171600143d295b6276ff8e4579f3350873db3e839e230f41ffffffff02307500000000000017a9148107a4409368488413295580eb88cbf7609cce658780841e00000000001976a914f0cb63944bcbbeb75c26492196939ae419c515a988ac024730440220243435ca67a713f6715d14d761b5ab073e88b30559a02f8b1add1aee8082f1c902207dfea838a2e815132999035245d9ebf51b4c740cbe4d95c609c7012ba9beb86301210324804353b8e10ce351d073da432fb046a4d13edf22052577a6e09cf9a5090cda00000000",
+                expected_transaction_id: "ba4dfdff505035b1d70842bbcb3be160528b3b5261292124a9c1b58a0d34f7f8",
+            },
+            TransactionTestCase { // p2sh_p2wsh and p2pkh to multiple address types
+                version: 2,
+                lock_time: 0,
+                inputs: &[
+                    Input {
+                        private_key: "L3EEWFaodvuDcH7yeTtugQDvNxnBs8Fkzerqf8tgmHYKQ4QkQJDE",
+                        address_format: BitcoinFormat::P2PKH,
+                        transaction_id: "6b88c087247aa2f07ee1c5956b8e1a9f4c7f892a70e324f1bb3d161e05ca107b",
+                        index: 0,
+                        redeem_script: None,
+                        script_pub_key: None,
+                        utxo_amount: BitcoinAmount(0),
+                        sequence: None,
+                        sighash_code: SignatureHash::SIGHASH_ALL
+                    },
+                    Input {
+                        private_key: "KzZtscUzkZS38CYqYfRZ8pUKfUr1JnAnwJLK25S8a6Pj6QgPYJkq",
+                        address_format: BitcoinFormat::P2SH_P2WPKH,
+                        transaction_id: "93ca92c0653260994680a4caa40cfc7b0aac02a077c4f022b007813d6416c70d",
+                        index: 1,
+                        redeem_script: None,
+                        script_pub_key: None,
+                        utxo_amount: BitcoinAmount(100000),
+                        sequence: None,
+                        sighash_code: SignatureHash::SIGHASH_ALL
+                    },
+                ],
+                outputs: &[
+                    Output {
+                        address: "36NCSwdvrL7XpiRSsfYWY99azC4xWgtL3X",
+                        amount: BitcoinAmount(50000)
+                    },
+                    Output {
+                        address: "17rB37JzbUVmFuKMx8fexrHjdWBtDurpuL",
+                        amount: BitcoinAmount(123456)
+                    },
+                ],
+                expected_signed_transaction: "020000000001027b10ca051e163dbbf124e3702a897f4c9f1a8e6b95c5e17ef0a27a2487c0886b000000006b483045022100b15c1d8e7de7c1d77612f80ab49c48c3d0c23467a0becaa86fcd98009d2dff6002200f3b2341a591889f38e629dc4b938faf9165aecedc4e3be768b13ef491cbb37001210264174f4ff6006a98be258fe1c371b635b097b000ce714c6a2842d5c269dbf2e9ffffffff0dc716643d8107b022f0c477a002ac0a7bfc0ca4caa4804699603265c092ca9301000000171600142b654d833c287e239f73ba8165bbadf4dee3c00effffffff0250c300000000000017a914334982bfd308f92f8ea5d22e9f7ee52f2265543b8740e20100000000001976a9144b1d83c75928642a41f2945c8a3be48550822a9a88ac0002483045022100a37e7aeb82332d5dc65d1582bb917acbf2d56a90f5a792a932cfec3c09f7a534022033baa11aa0f3ad4ba9723c703e65dce724ccb79c00838e09b96892087e43f1c8012102d9c6aaa344ee7cc41466e4705780020deb70720bef8ddb9b4e83e75df02e1d8600000000",
+                expected_transaction_id: "0c76c2ad441b7853966ba2dae6fc3f0b890b81761ea3e1421f8df02e80a08050",
+            },
+            TransactionTestCase { // p2pkh to bech32
+                version: 2,
+                lock_time: 0,
+                inputs: &[
+                    Input {
+                        private_key: "5JsX1A2JNjqVmLFUUhUJuDsVjFH2yfoVdV5qtFQpWhLkYzamKKy",
+                        address_format: BitcoinFormat::P2PKH,
+                        transaction_id: "bda2ebcbf0bd6bc4ee1c330a64a9ff95e839cc2d25c593a01e704996bc1e869c",
+                        index: 0,
+                        redeem_script: None,
+                        script_pub_key: None,
+                        utxo_amount: BitcoinAmount(0),
+                        sequence: None,
+                        sighash_code: SignatureHash::SIGHASH_ALL
+                    },
+                ],
+                outputs: &[
+                    Output {
+                        address: "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq",
+                        amount: BitcoinAmount(1234)
+                    },
+                    Output {
+                        address: "bc1qc7slrfxkknqcq2jevvvkdgvrt8080852dfjewde450xdlk4ugp7szw5tk9",
+                        amount: BitcoinAmount(111)
+                    },
+                ],
+                expected_signed_transaction: "02000000019c861ebc9649701ea093c5252dcc39e895ffa9640a331ceec46bbdf0cbeba2bd000000008a473044022077be9faa83fc2289bb59eb7538c3801f513d3640466a48cea9845f3b26f14cd802207b80fda8836610c487e8b59105e682d1c438f98f6324d1ea74cdec5d2d74ef04014104cc58298806e4e233ad1acb81feeb90368e05ad79a1d4b3698156dc4766ca077f39fbb47f52cbd5282c15a8a9fb08401e678acb9ef2fd28e043164723e9f29bb2ffffffff02d204000000000000160014e8df018c7e326cc253faac7e46cdc51e68542c426f00000000000000220020c7a1f1a4d6b4c1802a59631966a18359de779e8a6a65973735a3ccdfdabc407d00000000",
+                expected_transaction_id: "954c460ad8d9ea42e3679a4a70910a3be2e19c2cc8d6018d68038d9416db495e",
+            },
+            TransactionTestCase { // p2sh-p2wpkh to bech32
+                version: 2,
+                lock_time: 0,
+                inputs: &[
+                    Input {
+                        private_key: "KzZtscUzkZS38CYqYfRZ8pUKfUr1JnAnwJLK25S8a6Pj6QgPYJkq",
+                        address_format: BitcoinFormat::P2SH_P2WPKH,
+                        transaction_id: "1a2290470e0aa7549ab1e04b2453274374149ffee517a57715e5206e4142c233",
+                        index: 1,
+                        redeem_script: None,
+                        script_pub_key: None,
+                        utxo_amount: BitcoinAmount(1500000),
+                        sequence: None,
+                        sighash_code: SignatureHash::SIGHASH_ALL
+                    },
+                ],
+                outputs: &[
+                    Output {
+                        address: "bc1pw508d6qejxtdg4y5r3zarvary0c5xw7kw508d6qejxtdg4y5r3zarvary0c5xw7kt5nd6y", // witness version 1 (Bech32m, BIP-350)
+                        amount: BitcoinAmount(100000000)
+                    },
+                    Output {
+                        address: "bc1qquxqz4f7wzen367stgwt25tf640gp4vud5vez0",
+                        amount: BitcoinAmount(42500001)
+                    },
+                ],
+                expected_signed_transaction: "0200000000010133c242416e20e51577a517e5fe9f1474432753244be0b19a54a70a0e4790221a01000000171600142b654d833c287e239f73ba8165bbadf4dee3c00effffffff0200e1f505000000002a5128751e76e8199196d454941c45d1b3a323f1433bd6751e76e8199196d454941c45d1b3a323f1433bd6a17f880200000000160014070c01553e70b338ebd05a1cb55169d55e80d59c02483045022100b5cf710307329d8634842c1894057ef243e172284e0908b215479e3b1889f62302205dfdd0287899e3034c95526bcfb1f437a0ca66de42a63c3c36aabb5b893459fb012102d9c6aaa344ee7cc41466e4705780020deb70720bef8ddb9b4e83e75df02e1d8600000000",
+                expected_transaction_id: "7f9c4bc972b1b7749b9883b34bb26be3eb9fd8c4877818ae4c7c27e4cb5eda67",
+            },
+            TransactionTestCase { // p2pkh and bech32(p2wpkh) to multiple address types
+                version: 1,
+                lock_time: 0,
+                inputs: &[
+                    Input {
+                        private_key: "L1X6apYnZ39CLFJFX6Ny7oriHX3nmeBcjkobeYgmk6arbyZfouJu",
+                        address_format: BitcoinFormat::P2PKH,
+                        transaction_id: "9f96ade4b41d5433f4eda31e1738ec2b36f6e7d1420d94a6af99801a88f7f7ff",
+                        index: 0,
+                        redeem_script: None,
+                        script_pub_key: Some("76a9148631bf621f7c6671f8d2d646327b636cbbe79f8c88ac"), // Manually specify script_pub_key
+                        utxo_amount: BitcoinAmount(0),
+                        sequence: Some([0xee, 0xff, 0xff, 0xff]),
+                        sighash_code: SignatureHash::SIGHASH_ALL
+                    },
+                    Input {
+                        private_key: "5JZGuGYM4vfKvpxaJg5g5D3uvVYVQ74UUdueCVvWCNacrAkkvGi",
+                        address_format: BitcoinFormat::Bech32,
+                        transaction_id: "8ac60eb9575db5b2d987e29f301b5b819ea83a5c6579d282d189cc04b8e151ef",
+                        index: 1,
+                        redeem_script: None,
+                        script_pub_key: None,
+                        utxo_amount: BitcoinAmount(600000000),
+                        sequence: Some([0xff, 0xff, 0xff, 0xff]),
+                        sighash_code: SignatureHash::SIGHASH_ALL
+                    },
+                ],
+                outputs: &[
+                    Output {
+                        address: "bc1qgwu40h9vf3q9ua7llnsr29fws920enj8gflr4d",
+                        amount: BitcoinAmount(10)
+                    },
+                    Output {
+                        address: "1AD97NRftXXd2rkHDU17x8uq21LWYJFNa1",
+                        amount: BitcoinAmount(5555555)
+                    },
+                    Output {
+                        address: "3AnU6mchUQHZwgcRUD6JJaHe91fJ7UhajZ",
+                        amount: BitcoinAmount(9182631)
+                    },
+                ],
+                expected_signed_transaction: "01000000000102fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f000000006b4830450221009eed10e4b7cc9eb23efc36dc9b0907d0b4dd224ae5d0ee9c92d7912c9a9cde7e02203ede96d667901abfb9f3997aba8e08c6b9de218db920916203f2632c713cd99c012103f4edae249cb015280d48cae959d1823440eeab74f9fc9752a8a18cba76c892b6eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff030a0000000000000016001443b957dcac4c405e77dffce035152e8154fcce4763c55400000000001976a9146504e4b146b24898cf7881b0bdcd059dc35dd5a888aca71d8c000000000017a91463c110106d813c69514b3d97e1a1e6c94ad1b56a870002483045022100cfff608b18a97cc46cf8d22e97e78b22343cfcc19028918a5cd06fc9031f532302201b877de8872619a832387d7d0e15482521e449ce0d4daeb2d080995317883cd60121025476c2e83188368da1ff3e292e7acafcdb3566bb0ad253f62fc70f07aeee635700000000",
+                expected_transaction_id: "62ee2045fa2e3ee0353fed70b39adac13cb4114dbafa3a60a12084104d14f1b0",
+            },
+        ];
+
+        #[test]
+        fn test_mainnet_transactions() {
+            TRANSACTIONS.iter().for_each(|transaction| {
+                test_transaction::<N>(
+                    transaction.version,
+                    transaction.lock_time,
+                    transaction.inputs.to_vec(),
+                    transaction.outputs.to_vec(),
+                    transaction.expected_signed_transaction,
+                    transaction.expected_transaction_id,
+                );
+            });
+        }
+
+        #[test]
+        fn test_reconstructed_mainnet_transactions() {
+            TRANSACTIONS.iter().for_each(|transaction| {
+                test_reconstructed_transaction::<N>(
+                    transaction.version,
+                    transaction.lock_time,
+                    transaction.inputs.to_vec(),
+                    transaction.outputs.to_vec(),
+                    transaction.expected_signed_transaction,
+                    transaction.expected_transaction_id,
+                );
+            });
+        }
+    }
+
+    mod test_real_mainnet_transactions {
+        use super::*;
+        type N = Mainnet;
+
+        const REAL_TRANSACTIONS: [TransactionTestCase; 5] = [
+            TransactionTestCase { // Transaction 1 -> Segwit P2SH_P2WPKH to P2PKH and Bech32(P2WPKH)
+                version: 1,
+                lock_time: 0,
+                inputs: &[
+                    Input {
+                        private_key: "L1fUQgwdWcqGUAr3kFznuAP36Vw3oFeGHH29XRYMwxN1HpSw5yBm",
+                        address_format: BitcoinFormat::P2SH_P2WPKH,
+                        transaction_id: "a5766fafb27aba97e7aeb3e71be79806dd23f03bbd1b61135bf5792159f42ab6",
+                        index: 0,
+                        redeem_script: None,
+                        script_pub_key: None,
+                        utxo_amount: BitcoinAmount(80000),
+                        sequence: None,
+                        sighash_code: SignatureHash::SIGHASH_ALL
+                    },
+                ],
+                outputs: &[
+                    Output {
+                        address: "176DPNootfp2bSiE7KQUZp1VZj5EyGQeCt",
+                        amount: BitcoinAmount(35000)
+                    },
+                    Output {
+                        address: "bc1qcsjz44ce84j3650qfu9k87tyd3z8h4qyxz470n",
+                        amount: BitcoinAmount(35000)
+                    },
+                ],
+                expected_signed_transaction: "01000000000101b62af4592179f55b13611bbd3bf023dd0698e71be7b3aee797ba7ab2af6f76a50000000017160014b5ccbe3c5a285af4afada113a8619827fb30b2eeffffffff02b8880000000000001976a91442cd2c7460acc561c96b11c4aa96d0346b84db7f88acb888000000000000160014c4242ad7193d651d51e04f0b63f9646c447bd404024730440220449ca32ff3f8da3c17c1813dac91010cb1fea7a77b2f63065184b8318e1b9ed70220315da34cfeae62c26557c40f5ac5cde46b2801349e6677fc96597b4bfee04b0b012102973e9145ca85357b06de3009a12db171d70bae8a648dc8188e49723a2a46459100000000",
+                expected_transaction_id: "60805eb82c53d9c53900ad6d1c423ffc2235caa0c266625afd9cf03e856bf92c",
+            },
+            TransactionTestCase { // Transaction 2 -> P2PKH to P2SH_P2WPKH and P2PKH uncompressed
+                version: 1,
+                lock_time: 0,
+                inputs: &[
+                    Input {
+                        private_key: "KzZQ4ZzAecDmeDqxEJqSKpCfpPCa1x74ouyBhXUgMV2UdqNcaJiJ",
+                        address_format: BitcoinFormat::P2PKH,
+                        transaction_id: "60805eb82c53d9c53900ad6d1c423ffc2235caa0c266625afd9cf03e856bf92c",
+                        index: 0,
+                        redeem_script: None,
+                        script_pub_key: None,
+                        utxo_amount: BitcoinAmount(0),
+                        sequence: None,
+                        sighash_code: SignatureHash::SIGHASH_ALL
+                    },
+                ],
+                outputs: &[
+                    Output {
+                        address: "3QDTHVyuJrHixUhhsdZXQ7M8P9MQngmw1P",
+                        amount: BitcoinAmount(12000)
+                    },
+                    Output {
+                        address: "1C5RdoaGMVyQy8qjk96NsL4dW79aVPYrCK",
+                        amount: BitcoinAmount(12000)
+                    },
+                ],
+                expected_signed_transaction: "01000000012cf96b853ef09cfd5a6266c2a0ca3522fc3f421c6dad0039c5d9532cb85e8060000000006a473044022079471aadca4be014260a4788e7dc7d7168712c8f21c536f326caccb843569ab802206c7b464e3fbe0518f147ee7c5fa39c05e04e7ed17fbe464a2773b179fe0ef35401210384faa5d9710f727523906f6d2fe781b40cf58a3139d02eeaad293dd03be7b69cffffffff02e02e00000000000017a914f7146aaa6f24a1012528c1d27cfe49d256d5a70187e02e0000000000001976a914797f9c80ef57ba7f30b31598383683923a5a7a7c88ac00000000",
+                expected_transaction_id: "76ef90fa70e4c10adc358432a979683a2cf1855ff545f88c5022dea8863ed5ab",
+            },
+            TransactionTestCase { // Transaction 3 -> Bech32 (P2WPKH) to P2SH_P2WPKH
+                version: 1,
+                lock_time: 0,
+                inputs: &[
+                    Input {
+                        private_key: "L5HiUByNV6D4anzT5aMhheZpG9oKdcvoPXjWJopEPiEzFisNTM7X",
+                        address_format: BitcoinFormat::Bech32,
+                        transaction_id: "60805eb82c53d9c53900ad6d1c423ffc2235caa0c266625afd9cf03e856bf92c",
+                        index: 1,
+                        redeem_script: None,
+                        script_pub_key: None,
+                        utxo_amount: BitcoinAmount(35000),
+                        sequence: None,
+                        sighash_code: SignatureHash::SIGHASH_ALL
+                    },
+                ],
+                outputs: &[
+                    Output {
+                        address: "3QDTHVyuJrHixUhhsdZXQ7M8P9MQngmw1P",
+                        amount: BitcoinAmount(25000)
+                    },
+                ],
+                expected_signed_transaction: "010000000001012cf96b853ef09cfd5a6266c2a0ca3522fc3f421c6dad0039c5d9532cb85e80600100000000ffffffff01a86100000000000017a914f7146aaa6f24a1012528c1d27cfe49d256d5a701870247304402206af8b1cad8d8138631f2b2b08535643afb0c9597e1dd9b8daa4a565be274c96902203844c6af50658fb244370afaaffdb6f6e85ca681b80cd094bfd4f3eeae4febf0012103dcf5a50ac66bde7fe9f01c4710fb5d438d51f1da1ce138863d34fee6499f328900000000",
+                expected_transaction_id: "32464234781c37831398b5d2f1e1766f8dbb55ac3b41ed047e365c07e9b03429",
+            },
+            TransactionTestCase { // Transaction 4 -> Segwit P2SH_P2WPKH to Bech32(P2WPKH) and itself
+                version: 1,
+                lock_time: 0,
+                inputs: &[
+                    Input {
+                        private_key: "L5TmwLMEyEqMAYj1qd7Fx9YRhNJTCvNn4ofr98ErbgHA99GjLBXC",
+                        address_format: BitcoinFormat::P2SH_P2WPKH,
+                        transaction_id: "32464234781c37831398b5d2f1e1766f8dbb55ac3b41ed047e365c07e9b03429",
+                        index: 0,
+                        redeem_script: None,
+                        script_pub_key: None,
+                        utxo_amount: BitcoinAmount(25000),
+                        sequence: None,
+                        sighash_code: SignatureHash::SIGHASH_ALL
+                    },
+                    Input {
+                        private_key: "L5TmwLMEyEqMAYj1qd7Fx9YRhNJTCvNn4ofr98ErbgHA99GjLBXC",
+                        address_format: BitcoinFormat::P2SH_P2WPKH,
+                        transaction_id: "76ef90fa70e4c10adc358432a979683a2cf1855ff545f88c5022dea8863ed5ab",
+                        index: 0,
+                        redeem_script: None,
+                        script_pub_key: None,
+                        utxo_amount: BitcoinAmount(12000),
+                        sequence: None,
+                        sighash_code: SignatureHash::SIGHASH_ALL
+                    },
+                ],
+                outputs: &[
+                    Output {
+                        address: "bc1qzkuhp5jxuvwx90eg65wkxuw6y2pfe740yw6h5s",
+                        amount: BitcoinAmount(12000)
+                    },
+                    Output {
+                        address: "3QDTHVyuJrHixUhhsdZXQ7M8P9MQngmw1P",
+                        amount: BitcoinAmount(15000)
+                    },
+                ],
+                expected_signed_transaction: "010000000001022934b0e9075c367e04ed413bac55bb8d6f76e1f1d2b5981383371c78344246320000000017160014354816a98500d7df9201d46e008c203dd5143b92ffffffffabd53e86a8de22508cf845f55f85f12c3a6879a9328435dc0ac1e470fa90ef760000000017160014354816a98500d7df9201d46e008c203dd5143b92ffffffff02e02e00000000000016001415b970d246e31c62bf28d51d6371da22829cfaaf983a00000000000017a914f7146aaa6f24a1012528c1d27cfe49d256d5a7018702483045022100988bc569371f74d6e49f20ae05ab06abfbe7ba92bbc177b61e38c0c9f430646702207a874da47387b6cfc066c26c24c99ccb75dac6772a0f94b7327703bdb156c4c8012103f850b5fa8fe53be8675dd3045ed89c8a4235155b484d88eb62d0afed7cb9ef050247304402204296465f1f95480f058ccebd70a0f80b9f092021a15793c954f39373e1e6500102206ca2d3f6cb68d1a9fde36ed6ded6509e2284c6afe860abf7f49c3ae18944ffdf012103f850b5fa8fe53be8675dd3045ed89c8a4235155b484d88eb62d0afed7cb9ef0500000000",
+                expected_transaction_id: "6a06bd83718f24dd1883332939e59fdd26b95d8a328eac37a45b7c489618eac8",
+            },
+            TransactionTestCase { // Transaction 5 -> P2SH_P2WPKH, P2PKH uncompressed, and Bech32(P2WPKH) to Bech32(P2WPKH)
+                version: 1,
+                lock_time: 0,
+                inputs: &[
+                    Input {
+                        private_key: "L5TmwLMEyEqMAYj1qd7Fx9YRhNJTCvNn4ofr98ErbgHA99GjLBXC",
+                        address_format: BitcoinFormat::P2SH_P2WPKH,
+                        transaction_id: "6a06bd83718f24dd1883332939e59fdd26b95d8a328eac37a45b7c489618eac8",
+                        index: 1,
+                        redeem_script: None,
+                        script_pub_key: None,
+                        utxo_amount: BitcoinAmount(15000),
+                        sequence: None,
+                        sighash_code: SignatureHash::SIGHASH_ALL
+                    },
+                    Input {
+                        private_key: "5KRoKpnWWav74XDgz28opnJUsBozUg8STwEQPq354yUa3MiXySn",
+                        address_format: BitcoinFormat::P2PKH,
+                        transaction_id: "76ef90fa70e4c10adc358432a979683a2cf1855ff545f88c5022dea8863ed5ab",
+                        index: 1,
+                        redeem_script: None,
+                        script_pub_key: None,
+                        utxo_amount: BitcoinAmount(0),
+                        sequence: None,
+                        sighash_code: SignatureHash::SIGHASH_ALL
+                    },
+                    Input {
+                        private_key: "Kzs2rY8y9brmULJ3VK9gZHiZAhNJ2ttjn7ZuyJbG52pToZfCpQDr",
+                        address_format: BitcoinFormat::Bech32,
+                        transaction_id: "6a06bd83718f24dd1883332939e59fdd26b95d8a328eac37a45b7c489618eac8",
+                        index: 0,
+                        redeem_script: None,
+                        script_pub_key: None,
+                        utxo_amount: BitcoinAmount(12000),
+                        sequence: None,
+                        sighash_code: SignatureHash::SIGHASH_ALL
+                    },
+                ],
+                outputs: &[
+                    Output {
+                        address: "bc1qmj865gnmg3hv7eh74qmvu5fcde43ecd7haa5hy",
+                        amount: BitcoinAmount(30000)
+                    },
+                ],
+                expected_signed_transaction: "01000000000103c8ea1896487c5ba437ac8e328a5db926dd9fe53929338318dd248f7183bd066a0100000017160014354816a98500d7df9201d46e008c203dd5143b92ffffffffabd53e86a8de22508cf845f55f85f12c3a6879a9328435dc0ac1e470fa90ef76010000008b4830450221008bf28b9f9e2c6d7d0ef9705b7fd914e7693b2f4f3584deff6dfa9dc83fc9f73402201cdbf5cd78bf04ccedfa11f17cff3728965dd328d30fad4f91ba2be57fb2ccab014104db232c08ac5f0332d317e6cd805f3e29e98b93fc9ca74831a6c5d27a0368cdb0862d536a445250a8de9d92cf1d450c7dc9b8efd6ca2ff0865d553f85f1bd346fffffffffc8ea1896487c5ba437ac8e328a5db926dd9fe53929338318dd248f7183bd066a0000000000ffffffff013075000000000000160014dc8faa227b446ecf66fea836ce51386e6b1ce1be02483045022100c77d6548c8f068d7088d1a5eab91be1f4bd394fdd7e7334699ccb1533af2c6300220621399e24b9f84bb580fab62ced44b979f0b5a06a1c429ffe4f8c2ae27f740fb012103f850b5fa8fe53be8675dd3045ed89c8a4235155b484d88eb62d0afed7cb9ef05000247304402205b3676bb82313d8ed25dec2efc30aa24076b4a5c0dc0e2b2953507a8135a470102207cad2e535a5cac8b947c9d37aeb9162ec745c61b7133eafba790442faa2a19000121030f36fbc8825fcdc2b79e5764b6bb70c2038bf4dba63dbf71483320e4d7f63a0500000000",
+                expected_transaction_id: "b2eb46fe6f8075caf013cd8e947f3b9dc06a416896d28aef450c9ec8d310361f",
+            }
+        ];
+
+        #[test]
+        fn test_real_mainnet_transactions() {
+            REAL_TRANSACTIONS.iter().for_each(|transaction| {
+                test_transaction::<N>(
+                    transaction.version,
+                    transaction.lock_time,
+                    transaction.inputs.to_vec(),
+                    transaction.outputs.to_vec(),
+                    transaction.expected_signed_transaction,
+                    transaction.expected_transaction_id,
+                );
+            });
+        }
+
+        #[test]
+        fn test_real_reconstructed_mainnet_transactions() {
+            REAL_TRANSACTIONS.iter().for_each(|transaction| {
+                test_reconstructed_transaction::<N>(
+                    transaction.version,
+                    transaction.lock_time,
+                    transaction.inputs.to_vec(),
+                    transaction.outputs.to_vec(),
+                    transaction.expected_signed_transaction,
+                    transaction.expected_transaction_id,
+                );
+            });
+        }
+    }
+
+    mod test_litecoin_transactions {
+        use super::*;
+        type N = Litecoin;
+
+        const TRANSACTIONS: [TransactionTestCase; 3] = [
+            TransactionTestCase { // p2pkh to p2pkh and p2sh (M prefix)
+                version: 1,
+                lock_time: 0,
+                inputs: &[
+                    Input {
+                        private_key: "T45TTV58LamkyYq34BzWjvgUi2zQxg4MNCB2WuBVFbSg5a7Dqx4L",
                         address_format: BitcoinFormat::P2PKH,
-                        transaction_id: "7d865e959b2466918c9863afca942d0fb89d7c9ac0c99bafc3749504ded97730",
+                        transaction_id: "9cb01977970f1322931532c9467e85eee9daf620b1886eb70d7c9b0e6c993bc4",
                         index: 0,
                         redeem_script: None,
                         script_pub_key: None,
-                        utxo_amount: BitcoinAmount(0),
-                        sequence: Some([0xff, 0xff, 0xff, 0xff]),
+                        utxo_amount: BitcoinAmount(150000000),
+                        sequence: None,
                         sighash_code: SignatureHash::SIGHASH_ALL
-                    }
+                    },
                 ],
                 outputs: &[
                     Output {
-                        address: "1CUNEBjYrCn2y1SdiUMohaKUi4wpP326Lb",
-                        amount: BitcoinAmount(180000)
+                        address: "Lf2SXRzFwowWvG4TZ3Wcir3hpp1D6zsqGn",
+                        amount: BitcoinAmount(100000000)
                     },
                     Output {
-                        address: "1JtK9CQw1syfWj1WtFMWomrYdV3W2tWBF9",
-                        amount: BitcoinAmount(170000)
+                        address: "MMSUCM28bRwqhpmHVAookHkokTbCHe9uCT",
+                        amount: BitcoinAmount(49990000)
                     },
                 ],
-                expected_signed_transaction: "01000000024c94e48a870b85f41228d33cf25213dfcc8dd796e7211ed6b1f9a014809dbbb5060000006a473044022041450c258ce7cac7da97316bf2ea1ce66d88967c4df94f3e91f4c2a30f5d08cb02203674d516e6bb2b0afd084c3551614bd9cec3c2945231245e891b145f2d6951f0012103e05ce435e462ec503143305feb6c00e06a3ad52fbf939e85c65f3a765bb7baacffffffff3077d9de049574c3af9bc9c09a7c9db80f2d94caaf63988c9166249b955e867d000000006b483045022100aeb5f1332c79c446d3f906e4499b2e678500580a3f90329edf1ba502eec9402e022072c8b863f8c8d6c26f4c691ac9a6610aa4200edc697306648ee844cfbc089d7a012103df7940ee7cddd2f97763f67e1fb13488da3fbdd7f9c68ec5ef0864074745a289ffffffff0220bf0200000000001976a9147dd65592d0ab2fe0d0257d571abf032cd9db93dc88ac10980200000000001976a914c42e7ef92fdb603af844d064faad95db9bcdfd3d88ac00000000",
-                expected_transaction_id: "af0ae7ab766f49c33312d33541868c2185ad559cc0457e7af398311bda4f18f7",
+                expected_signed_transaction: "0100000001c43b996c0e9b7c0db76e88b120f6dae9ee857e46c932159322130f977719b09c000000006a4730440220608680251c3c833be936d1490daa43104f04b7aeef71eb30d306b098075343b4022001b1acf8fe31d3045b63e8e1892a02f8f257a181926cabec5b17209d48ca5fc3012103f028892bad7ed57d2fb57bf33081d5cfcf6f9ed3d3d7f159c2e2fff579dc341affffffff0200e1f505000000001976a914d9351dcbad5b8f3b8bfa2f2cdc85c28118ca932688ac70c9fa020000000017a91494858691137c2eb11bf6a8a900f1d711670e23838700000000",
+                expected_transaction_id: "4f12248a5e233b0c90da668e5c054df22f08144c1641430f8f59eb9d04fe48ad",
             },
-            TransactionTestCase { // p2sh_p2wsh to multiple address types
+            TransactionTestCase { // p2sh_p2wpkh to bech32
                 version: 2,
                 lock_time: 0,
                 inputs: &[
                     Input {
-                        private_key: "Kxxkik2L9KgrGgvdkEvYSkgAxaY4qPGfvxe1M1KBVBB7Ls3xDD8o",
+                        private_key: "T3TccUZx4EXBZaHnFiP9eTr8igDEZoqSjNvbA56Z8vV74oyAcjTK",
                         address_format: BitcoinFormat::P2SH_P2WPKH,
-                        transaction_id: "7c95424e4c86467eaea85b878985fa77d191bad2b9c5cac5a0cb98f760616afa",
-                        index: 55,
+                        transaction_id: "77541aeb3c4dac9260b68f74f44c973081a9d4cb2ebe8038b2d70faa201b6bdb",
+                        index: 1,
                         redeem_script: None,
                         script_pub_key: None,
-                        utxo_amount: BitcoinAmount(2000000),
-                        sequence: None,
+                        utxo_amount: BitcoinAmount(250000000),
+                        sequence: Some([0xfe, 0xff, 0xff, 0xff]),
                         sighash_code: SignatureHash::SIGHASH_ALL
                     },
                 ],
                 outputs: &[
                     Output {
-                        address: "3DTGFEmobt8BaJpfPe62HvCQKp2iGsnYqD",
-                        amount: BitcoinAmount(30000)
-                    },
-                    Output {
-                        address: "1NxCpkhj6n8orGdhPpxCD3B52WvoR4CS7S",
-                        amount: BitcoinAmount(2000000)
+                        address: "ltc1qh0q7g23e6pdye3sh2ttfvwmld8gfhvnml4ucqx",
+                        amount: BitcoinAmount(249980000)
                     },
                 ],
-                expected_signed_transaction: "02000000000101fa6a6160f798cba0c5cac5b9d2ba91d177fa8589875ba8ae7e46864c4e42957c37000000171600143d295b6276ff8e4579f3350873db3e839e230f41ffffffff02307500000000000017a9148107a4409368488413295580eb88cbf7609cce658780841e00000000001976a914f0cb63944bcbbeb75c26492196939ae419c515a988ac024730440220243435ca67a713f6715d14d761b5ab073e88b30559a02f8b1add1aee8082f1c902207dfea838a2e815132999035245d9ebf51b4c740cbe4d95c609c7012ba9beb86301210324804353b8e10ce351d073da432fb046a4d13edf22052577a6e09cf9a5090cda00000000",
-                expected_transaction_id: "ba4dfdff505035b1d70842bbcb3be160528b3b5261292124a9c1b58a0d34f7f8",
+                expected_signed_transaction: "02000000000101db6b1b20aa0fd7b23880be2ecbd4a98130974cf4748fb66092ac4d3ceb1a54770100000017160014d9351dcbad5b8f3b8bfa2f2cdc85c28118ca9326feffffff016064e60e00000000160014bbc1e42a39d05a4cc61752d6963b7f69d09bb27b0248304502210087faae3b213aa4a2772af932f303e1910a1d1bf8c009b5ba93e52187ade4e1a902205ead5ec94c3f434437a30220cd49e14144290ac1b4eee25676dde9074e256694012102d0de0aaeaefad02b8bdc8a01a1b8b11c696bd3d66a2c5f10780d95b7df42645c00000000",
+                expected_transaction_id: "aba21b0cec4330619eb704723a373f909980e5b45ac8b1427eb47bfe8de2d114",
             },
-            TransactionTestCase { // p2sh_p2wsh and p2pkh to multiple address types
+            TransactionTestCase { // bech32 and p2pkh to p2pkh
                 version: 2,
-                lock_time: 0,
+                lock_time: 2500000,
                 inputs: &[
                     Input {
-                        private_key: "L3EEWFaodvuDcH7yeTtugQDvNxnBs8Fkzerqf8tgmHYKQ4QkQJDE",
-                        address_format: BitcoinFormat::P2PKH,
-                        transaction_id: "6b88c087247aa2f07ee1c5956b8e1a9f4c7f892a70e324f1bb3d161e05ca107b",
+                        private_key: "T4UgJBpcJnhyPniVbrGaaHvuinnferrowEHo7GvNATWqCuaibJK6",
+                        address_format: BitcoinFormat::Bech32,
+                        transaction_id: "61d520ccb74288c96bc1a2b20ea1c0d5a704776dd0164a396efec3ea7040349d",
                         index: 0,
                         redeem_script: None,
                         script_pub_key: None,
-                        utxo_amount: BitcoinAmount(0),
+                        utxo_amount: BitcoinAmount(84000000),
                         sequence: None,
                         sighash_code: SignatureHash::SIGHASH_ALL
                     },
                     Input {
-                        private_key: "KzZtscUzkZS38CYqYfRZ8pUKfUr1JnAnwJLK25S8a6Pj6QgPYJkq",
-                        address_format: BitcoinFormat::P2SH_P2WPKH,
-                        transaction_id: "93ca92c0653260994680a4caa40cfc7b0aac02a077c4f022b007813d6416c70d",
-                        index: 1,
+                        private_key: "T45TTV58LamkyYq34BzWjvgUi2zQxg4MNCB2WuBVFbSg5a7Dqx4L",
+                        address_format: BitcoinFormat::P2PKH,
+                        transaction_id: "9cb01977970f1322931532c9467e85eee9daf620b1886eb70d7c9b0e6c993bc4",
+                        index: 2,
                         redeem_script: None,
                         script_pub_key: None,
-                        utxo_amount: BitcoinAmount(100000),
-                        sequence: None,
+                        utxo_amount: BitcoinAmount(16000000),
+                        sequence: Some([0xfe, 0xff, 0xff, 0xff]),
                         sighash_code: SignatureHash::SIGHASH_ALL
                     },
                 ],
                 outputs: &[
                     Output {
-                        address: "36NCSwdvrL7XpiRSsfYWY99azC4xWgtL3X",
-                        amount: BitcoinAmount(50000)
-                    },
-                    Output {
-                        address: "17rB37JzbUVmFuKMx8fexrHjdWBtDurpuL",
-                        amount: BitcoinAmount(123456)
+                        address: "LcLittPgg5DXY34hp62SnWa5KtEcvWHUsr",
+                        amount: BitcoinAmount(99950000)
                     },
                 ],
-                expected_signed_transaction: "020000000001027b10ca051e163dbbf124e3702a897f4c9f1a8e6b95c5e17ef0a27a2487c0886b000000006b483045022100b15c1d8e7de7c1d77612f80ab49c48c3d0c23467a0becaa86fcd98009d2dff6002200f3b2341a591889f38e629dc4b938faf9165aecedc4e3be768b13ef491cbb37001210264174f4ff6006a98be258fe1c371b635b097b000ce714c6a2842d5c269dbf2e9ffffffff0dc716643d8107b022f0c477a002ac0a7bfc0ca4caa4804699603265c092ca9301000000171600142b654d833c287e239f73ba8165bbadf4dee3c00effffffff0250c300000000000017a914334982bfd308f92f8ea5d22e9f7ee52f2265543b8740e20100000000001976a9144b1d83c75928642a41f2945c8a3be48550822a9a88ac0002483045022100a37e7aeb82332d5dc65d1582bb917acbf2d56a90f5a792a932cfec3c09f7a534022033baa11aa0f3ad4ba9723c703e65dce724ccb79c00838e09b96892087e43f1c8012102d9c6aaa344ee7cc41466e4705780020deb70720bef8ddb9b4e83e75df02e1d8600000000",
-                expected_transaction_id: "0c76c2ad441b7853966ba2dae6fc3f0b890b81761ea3e1421f8df02e80a08050",
+                expected_signed_transaction: "020000000001029d344070eac3fe6e394a16d06d7704a7d5c0a10eb2a2c16bc98842b7cc20d5610000000000ffffffffc43b996c0e9b7c0db76e88b120f6dae9ee857e46c932159322130f977719b09c020000006a473044022062c708fa038b6a3d63fbafde1c5edb387b4ee50d1aeec2c73d0f9f21da06ff1c022001e6e6e957c9baf6db3b4a9b375b253d1fd1d46a92dd9997b80d781020e05120012103f028892bad7ed57d2fb57bf33081d5cfcf6f9ed3d3d7f159c2e2fff579dc341afeffffff01b01df505000000001976a914bbc1e42a39d05a4cc61752d6963b7f69d09bb27b88ac02483045022100a825adf0e7b03b1db71a00e65675024bd47b21041be74ee2088742e41cef0a4e0220780ccb3c0c67c302a095cd266656f32a0a792fe1253d03e77b62a853ef7e8409012102f4bf1f9c53ffed4324017507e2b57397251c68c76bf8f583d7320b69a57ff03200a0252600",
+                expected_transaction_id: "1316301afdd6f3c269d1b896c935ac0157ce3d5a9978567b8efa80341eb22899",
             },
-            TransactionTestCase { // p2pkh to bech32
-                version: 2,
+        ];
+
+        #[test]
+        fn test_litecoin_transactions() {
+            TRANSACTIONS.iter().for_each(|transaction| {
+                test_transaction::<N>(
+                    transaction.version,
+                    transaction.lock_time,
+                    transaction.inputs.to_vec(),
+                    transaction.outputs.to_vec(),
+                    transaction.expected_signed_transaction,
+                    transaction.expected_transaction_id,
+                );
+            });
+        }
+
+        #[test]
+        fn test_reconstructed_litecoin_transactions() {
+            TRANSACTIONS.iter().for_each(|transaction| {
+                test_reconstructed_transaction::<N>(
+                    transaction.version,
+                    transaction.lock_time,
+                    transaction.inputs.to_vec(),
+                    transaction.outputs.to_vec(),
+                    transaction.expected_signed_transaction,
+                    transaction.expected_transaction_id,
+                );
+            });
+        }
+    }
+
+    mod test_dogecoin_transactions {
+        use super::*;
+        type N = Dogecoin;
+
+        const TRANSACTIONS: [TransactionTestCase; 2] = [
+            TransactionTestCase { // p2pkh to p2pkh, above the 21 million coins of Bitcoin
+                version: 1,
                 lock_time: 0,
                 inputs: &[
                     Input {
-                        private_key: "5JsX1A2JNjqVmLFUUhUJuDsVjFH2yfoVdV5qtFQpWhLkYzamKKy",
+                        private_key: "QPe7AaawBpHHSEbD6ptSQnyiEDNfw9BGSExgSiwnQyss1cbfeDc9",
                         address_format: BitcoinFormat::P2PKH,
-                        transaction_id: "bda2ebcbf0bd6bc4ee1c330a64a9ff95e839cc2d25c593a01e704996bc1e869c",
+                        transaction_id: "9cb01977970f1322931532c9467e85eee9daf620b1886eb70d7c9b0e6c993bc4",
                         index: 0,
                         redeem_script: None,
                         script_pub_key: None,
-                        utxo_amount: BitcoinAmount(0),
+                        utxo_amount: BitcoinAmount(5000000000000000),
                         sequence: None,
                         sighash_code: SignatureHash::SIGHASH_ALL
                     },
                 ],
                 outputs: &[
                     Output {
-                        address: "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq",
-                        amount: BitcoinAmount(1234)
+                        address: "DQwaoUd5AZbkCTYu7VWszb9YVjNEFtT2DQ",
+                        amount: BitcoinAmount(4000000000000000)
                     },
                     Output {
-                        address: "bc1qc7slrfxkknqcq2jevvvkdgvrt8080852dfjewde450xdlk4ugp7szw5tk9",
-                        amount: BitcoinAmount(111)
+                        address: "DNFsAw2VtpskpEZ9NY2i4FfuzobeBaxqMm",
+                        amount: BitcoinAmount(999999000000000)
                     },
                 ],
-                expected_signed_transaction: "02000000019c861ebc9649701ea093c5252dcc39e895ffa9640a331ceec46bbdf0cbeba2bd000000008a473044022077be9faa83fc2289bb59eb7538c3801f513d3640466a48cea9845f3b26f14cd802207b80fda8836610c487e8b59105e682d1c438f98f6324d1ea74cdec5d2d74ef04014104cc58298806e4e233ad1acb81feeb90368e05ad79a1d4b3698156dc4766ca077f39fbb47f52cbd5282c15a8a9fb08401e678acb9ef2fd28e043164723e9f29bb2ffffffff02d204000000000000160014e8df018c7e326cc253faac7e46cdc51e68542c426f00000000000000220020c7a1f1a4d6b4c1802a59631966a18359de779e8a6a65973735a3ccdfdabc407d00000000",
-                expected_transaction_id: "954c460ad8d9ea42e3679a4a70910a3be2e19c2cc8d6018d68038d9416db495e",
+                expected_signed_transaction: "0100000001c43b996c0e9b7c0db76e88b120f6dae9ee857e46c932159322130f977719b09c000000006b483045022100fde645830f98ecf6d5d44fb8cf6f8ed81d4c2a7d9ee924edb497743899f3a36502202501f7d549dae3533cd705b5c148f0be3d030c7ca78f13e60375b3617bde65ac012103f028892bad7ed57d2fb57bf33081d5cfcf6f9ed3d3d7f159c2e2fff579dc341affffffff0200001a93fa350e001976a914d9351dcbad5b8f3b8bfa2f2cdc85c28118ca932688ac00b62b697e8d03001976a914bbc1e42a39d05a4cc61752d6963b7f69d09bb27b88ac00000000",
+                expected_transaction_id: "1d0728999f682fecb19a72b21df4cc6e20231305ee06f1cce832155082e68cc3",
             },
-            TransactionTestCase { // p2sh-p2wpkh to bech32
-                version: 2,
+            TransactionTestCase { // two p2pkh to p2pkh
+                version: 1,
                 lock_time: 0,
                 inputs: &[
                     Input {
-                        private_key: "KzZtscUzkZS38CYqYfRZ8pUKfUr1JnAnwJLK25S8a6Pj6QgPYJkq",
-                        address_format: BitcoinFormat::P2SH_P2WPKH,
-                        transaction_id: "1a2290470e0aa7549ab1e04b2453274374149ffee517a57715e5206e4142c233",
+                        private_key: "QPe7AaawBpHHSEbD6ptSQnyiEDNfw9BGSExgSiwnQyss1cbfeDc9",
+                        address_format: BitcoinFormat::P2PKH,
+                        transaction_id: "77541aeb3c4dac9260b68f74f44c973081a9d4cb2ebe8038b2d70faa201b6bdb",
                         index: 1,
                         redeem_script: None,
                         script_pub_key: None,
-                        utxo_amount: BitcoinAmount(1500000),
+                        utxo_amount: BitcoinAmount(100000000),
                         sequence: None,
                         sighash_code: SignatureHash::SIGHASH_ALL
                     },
-                ],
-                outputs: &[
-                    Output {
-                        address: "bc1pw508d6qejxtdg4y5r3zarvary0c5xw7kw508d6qejxtdg4y5r3zarvary0c5xw7k7grplx", // witness version 1
-                        amount: BitcoinAmount(100000000)
-                    },
-                    Output {
-                        address: "bc1qquxqz4f7wzen367stgwt25tf640gp4vud5vez0",
-                        amount: BitcoinAmount(42500001)
-                    },
-                ],
-                expected_signed_transaction: "0200000000010133c242416e20e51577a517e5fe9f1474432753244be0b19a54a70a0e4790221a01000000171600142b654d833c287e239f73ba8165bbadf4dee3c00effffffff0200e1f505000000002a5128751e76e8199196d454941c45d1b3a323f1433bd6751e76e8199196d454941c45d1b3a323f1433bd6a17f880200000000160014070c01553e70b338ebd05a1cb55169d55e80d59c02483045022100b5cf710307329d8634842c1894057ef243e172284e0908b215479e3b1889f62302205dfdd0287899e3034c95526bcfb1f437a0ca66de42a63c3c36aabb5b893459fb012102d9c6aaa344ee7cc41466e4705780020deb70720bef8ddb9b4e83e75df02e1d8600000000",
-                expected_transaction_id: "7f9c4bc972b1b7749b9883b34bb26be3eb9fd8c4877818ae4c7c27e4cb5eda67",
-            },
-            TransactionTestCase { // p2pkh and bech32(p2wpkh) to multiple address types
-                version: 1,
-                lock_time: 0,
-                inputs: &[
                     Input {
-                        private_key: "L1X6apYnZ39CLFJFX6Ny7oriHX3nmeBcjkobeYgmk6arbyZfouJu",
+                        private_key: "QP2GKa5kuU2i2G3xJMH5KL9NErbVYGxMoRiF5trrJJvHzrJ2Ebp7",
                         address_format: BitcoinFormat::P2PKH,
-                        transaction_id: "9f96ade4b41d5433f4eda31e1738ec2b36f6e7d1420d94a6af99801a88f7f7ff",
+                        transaction_id: "61d520ccb74288c96bc1a2b20ea1c0d5a704776dd0164a396efec3ea7040349d",
                         index: 0,
                         redeem_script: None,
-                        script_pub_key: Some("76a9148631bf621f7c6671f8d2d646327b636cbbe79f8c88ac"), // Manually specify script_pub_key
-                        utxo_amount: BitcoinAmount(0),
-                        sequence: Some([0xee, 0xff, 0xff, 0xff]),
-                        sighash_code: SignatureHash::SIGHASH_ALL
-                    },
-                    Input {
-                        private_key: "5JZGuGYM4vfKvpxaJg5g5D3uvVYVQ74UUdueCVvWCNacrAkkvGi",
-                        address_format: BitcoinFormat::Bech32,
-                        transaction_id: "8ac60eb9575db5b2d987e29f301b5b819ea83a5c6579d282d189cc04b8e151ef",
-                        index: 1,
-                        redeem_script: None,
                         script_pub_key: None,
-                        utxo_amount: BitcoinAmount(600000000),
-                        sequence: Some([0xff, 0xff, 0xff, 0xff]),
+                        utxo_amount: BitcoinAmount(250000000),
+                        sequence: None,
                         sighash_code: SignatureHash::SIGHASH_ALL
                     },
                 ],
                 outputs: &[
                     Output {
-                        address: "bc1qgwu40h9vf3q9ua7llnsr29fws920enj8gflr4d",
-                        amount: BitcoinAmount(10)
-                    },
-                    Output {
-                        address: "1AD97NRftXXd2rkHDU17x8uq21LWYJFNa1",
-                        amount: BitcoinAmount(5555555)
-                    },
-                    Output {
-                        address: "3AnU6mchUQHZwgcRUD6JJaHe91fJ7UhajZ",
-                        amount: BitcoinAmount(9182631)
+                        address: "DErn28E7PVjwmm36vjwubwMJV2STv44F3x",
+                        amount: BitcoinAmount(349000000)
                     },
                 ],
-                expected_signed_transaction: "01000000000102fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f000000006b4830450221009eed10e4b7cc9eb23efc36dc9b0907d0b4dd224ae5d0ee9c92d7912c9a9cde7e02203ede96d667901abfb9f3997aba8e08c6b9de218db920916203f2632c713cd99c012103f4edae249cb015280d48cae959d1823440eeab74f9fc9752a8a18cba76c892b6eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff030a0000000000000016001443b957dcac4c405e77dffce035152e8154fcce4763c55400000000001976a9146504e4b146b24898cf7881b0bdcd059dc35dd5a888aca71d8c000000000017a91463c110106d813c69514b3d97e1a1e6c94ad1b56a870002483045022100cfff608b18a97cc46cf8d22e97e78b22343cfcc19028918a5cd06fc9031f532302201b877de8872619a832387d7d0e15482521e449ce0d4daeb2d080995317883cd60121025476c2e83188368da1ff3e292e7acafcdb3566bb0ad253f62fc70f07aeee635700000000",
-                expected_transaction_id: "62ee2045fa2e3ee0353fed70b39adac13cb4114dbafa3a60a12084104d14f1b0",
+                expected_signed_transaction: "0100000002db6b1b20aa0fd7b23880be2ecbd4a98130974cf4748fb66092ac4d3ceb1a5477010000006b4830450221008116e3d15ba22eefba0a337aea845c95695b9b58c148e45c94797a96e22c0c79022038f0551b741bc14c7fdfd6580c73e7b6d16d088f7c640d09a60072ce73ba6da8012103f028892bad7ed57d2fb57bf33081d5cfcf6f9ed3d3d7f159c2e2fff579dc341affffffff9d344070eac3fe6e394a16d06d7704a7d5c0a10eb2a2c16bc98842b7cc20d561000000006b483045022100fbbb48cd1a360d35ca2525652b9c1403f5b18cccff03f217995c7b183cea2b6202204fb1a49c26ca606b44075b90cb75cd9c7a2d037b05ab0fa32aff4257f8591663012102d0de0aaeaefad02b8bdc8a01a1b8b11c696bd3d66a2c5f10780d95b7df42645cffffffff014051cd14000000001976a9146a9b24a37f4d830ab59efa3bfecb23a7e722228488ac00000000",
+                expected_transaction_id: "437546c87266ccfbf350e41a367a08e39892669c288f4ae36044dd85838f0a92",
             },
         ];
 
         #[test]
-        fn test_mainnet_transactions() {
+        fn test_dogecoin_transactions() {
             TRANSACTIONS.iter().for_each(|transaction| {
                 test_transaction::<N>(
                     transaction.version,
@@ -1582,7 +3195,7 @@ mod tests {
         }
 
         #[test]
-        fn test_reconstructed_mainnet_transactions() {
+        fn test_reconstructed_dogecoin_transactions() {
             TRANSACTIONS.iter().for_each(|transaction| {
                 test_reconstructed_transaction::<N>(
                     transaction.version,
@@ -1596,186 +3209,187 @@ mod tests {
         }
     }
 
-    mod test_real_mainnet_transactions {
+    mod test_dash_transactions {
         use super::*;
-        type N = Mainnet;
+        type N = Dash;
 
-        const REAL_TRANSACTIONS: [TransactionTestCase; 5] = [
-            TransactionTestCase { // Transaction 1 -> Segwit P2SH_P2WPKH to P2PKH and Bech32(P2WPKH)
+        const TRANSACTIONS: [TransactionTestCase; 2] = [
+            TransactionTestCase { // p2pkh to p2pkh
                 version: 1,
                 lock_time: 0,
                 inputs: &[
                     Input {
-                        private_key: "L1fUQgwdWcqGUAr3kFznuAP36Vw3oFeGHH29XRYMwxN1HpSw5yBm",
-                        address_format: BitcoinFormat::P2SH_P2WPKH,
-                        transaction_id: "a5766fafb27aba97e7aeb3e71be79806dd23f03bbd1b61135bf5792159f42ab6",
+                        private_key: "XCK7U1AKEtRcG3CYYK3X2oL7gCcgLPehvZcgBct8zzYbxqgRdBrB",
+                        address_format: BitcoinFormat::P2PKH,
+                        transaction_id: "9cb01977970f1322931532c9467e85eee9daf620b1886eb70d7c9b0e6c993bc4",
                         index: 0,
                         redeem_script: None,
                         script_pub_key: None,
-                        utxo_amount: BitcoinAmount(80000),
+                        utxo_amount: BitcoinAmount(120000000),
                         sequence: None,
                         sighash_code: SignatureHash::SIGHASH_ALL
                     },
                 ],
                 outputs: &[
                     Output {
-                        address: "176DPNootfp2bSiE7KQUZp1VZj5EyGQeCt",
-                        amount: BitcoinAmount(35000)
+                        address: "XvVL6ULKprv3pPxtEnqYJMfjSwDd3uJmFy",
+                        amount: BitcoinAmount(100000000)
                     },
                     Output {
-                        address: "bc1qcsjz44ce84j3650qfu9k87tyd3z8h4qyxz470n",
-                        amount: BitcoinAmount(35000)
+                        address: "XsocTvjkZ8C4SAy8VqMNN2C6x1T2qqbjCG",
+                        amount: BitcoinAmount(19990000)
                     },
                 ],
-                expected_signed_transaction: "01000000000101b62af4592179f55b13611bbd3bf023dd0698e71be7b3aee797ba7ab2af6f76a50000000017160014b5ccbe3c5a285af4afada113a8619827fb30b2eeffffffff02b8880000000000001976a91442cd2c7460acc561c96b11c4aa96d0346b84db7f88acb888000000000000160014c4242ad7193d651d51e04f0b63f9646c447bd404024730440220449ca32ff3f8da3c17c1813dac91010cb1fea7a77b2f63065184b8318e1b9ed70220315da34cfeae62c26557c40f5ac5cde46b2801349e6677fc96597b4bfee04b0b012102973e9145ca85357b06de3009a12db171d70bae8a648dc8188e49723a2a46459100000000",
-                expected_transaction_id: "60805eb82c53d9c53900ad6d1c423ffc2235caa0c266625afd9cf03e856bf92c",
+                expected_signed_transaction: "0100000001c43b996c0e9b7c0db76e88b120f6dae9ee857e46c932159322130f977719b09c000000006b483045022100c89b50d0f10b0e240715b7c4bb5ff0950e10a505478892bf9bba6fcc6eddd3f2022023266dca4713381cbdeddc1792c09fad391a0e662ee74caf55cbdc59559f4630012103f028892bad7ed57d2fb57bf33081d5cfcf6f9ed3d3d7f159c2e2fff579dc341affffffff0200e1f505000000001976a914d9351dcbad5b8f3b8bfa2f2cdc85c28118ca932688acf0053101000000001976a914bbc1e42a39d05a4cc61752d6963b7f69d09bb27b88ac00000000",
+                expected_transaction_id: "3c8c1ddf3ded799e8206d5e8f370300097e04ebf4c3110f9cfbf91a3241f3667",
             },
-            TransactionTestCase { // Transaction 2 -> P2PKH to P2SH_P2WPKH and P2PKH uncompressed
-                version: 1,
+            TransactionTestCase { // two p2pkh to p2pkh
+                version: 2,
                 lock_time: 0,
                 inputs: &[
                     Input {
-                        private_key: "KzZQ4ZzAecDmeDqxEJqSKpCfpPCa1x74ouyBhXUgMV2UdqNcaJiJ",
+                        private_key: "XCK7U1AKEtRcG3CYYK3X2oL7gCcgLPehvZcgBct8zzYbxqgRdBrB",
                         address_format: BitcoinFormat::P2PKH,
-                        transaction_id: "60805eb82c53d9c53900ad6d1c423ffc2235caa0c266625afd9cf03e856bf92c",
-                        index: 0,
+                        transaction_id: "77541aeb3c4dac9260b68f74f44c973081a9d4cb2ebe8038b2d70faa201b6bdb",
+                        index: 3,
                         redeem_script: None,
                         script_pub_key: None,
-                        utxo_amount: BitcoinAmount(0),
+                        utxo_amount: BitcoinAmount(50000000),
                         sequence: None,
                         sighash_code: SignatureHash::SIGHASH_ALL
                     },
-                ],
-                outputs: &[
-                    Output {
-                        address: "3QDTHVyuJrHixUhhsdZXQ7M8P9MQngmw1P",
-                        amount: BitcoinAmount(12000)
-                    },
-                    Output {
-                        address: "1C5RdoaGMVyQy8qjk96NsL4dW79aVPYrCK",
-                        amount: BitcoinAmount(12000)
-                    },
-                ],
-                expected_signed_transaction: "01000000012cf96b853ef09cfd5a6266c2a0ca3522fc3f421c6dad0039c5d9532cb85e8060000000006a473044022079471aadca4be014260a4788e7dc7d7168712c8f21c536f326caccb843569ab802206c7b464e3fbe0518f147ee7c5fa39c05e04e7ed17fbe464a2773b179fe0ef35401210384faa5d9710f727523906f6d2fe781b40cf58a3139d02eeaad293dd03be7b69cffffffff02e02e00000000000017a914f7146aaa6f24a1012528c1d27cfe49d256d5a70187e02e0000000000001976a914797f9c80ef57ba7f30b31598383683923a5a7a7c88ac00000000",
-                expected_transaction_id: "76ef90fa70e4c10adc358432a979683a2cf1855ff545f88c5022dea8863ed5ab",
-            },
-            TransactionTestCase { // Transaction 3 -> Bech32 (P2WPKH) to P2SH_P2WPKH
-                version: 1,
-                lock_time: 0,
-                inputs: &[
                     Input {
-                        private_key: "L5HiUByNV6D4anzT5aMhheZpG9oKdcvoPXjWJopEPiEzFisNTM7X",
-                        address_format: BitcoinFormat::Bech32,
-                        transaction_id: "60805eb82c53d9c53900ad6d1c423ffc2235caa0c266625afd9cf03e856bf92c",
+                        private_key: "XBhGczf8xYB2r4fHjqS9wLVmgqqVwXRoHkNEpnoCtKb2x5RsXrCP",
+                        address_format: BitcoinFormat::P2PKH,
+                        transaction_id: "61d520ccb74288c96bc1a2b20ea1c0d5a704776dd0164a396efec3ea7040349d",
                         index: 1,
                         redeem_script: None,
                         script_pub_key: None,
-                        utxo_amount: BitcoinAmount(35000),
+                        utxo_amount: BitcoinAmount(70000000),
                         sequence: None,
                         sighash_code: SignatureHash::SIGHASH_ALL
                     },
                 ],
                 outputs: &[
                     Output {
-                        address: "3QDTHVyuJrHixUhhsdZXQ7M8P9MQngmw1P",
-                        amount: BitcoinAmount(25000)
+                        address: "XkQXK7wN3o4FPhT643GZuhsVSEHreBrkeg",
+                        amount: BitcoinAmount(119990000)
                     },
                 ],
-                expected_signed_transaction: "010000000001012cf96b853ef09cfd5a6266c2a0ca3522fc3f421c6dad0039c5d9532cb85e80600100000000ffffffff01a86100000000000017a914f7146aaa6f24a1012528c1d27cfe49d256d5a701870247304402206af8b1cad8d8138631f2b2b08535643afb0c9597e1dd9b8daa4a565be274c96902203844c6af50658fb244370afaaffdb6f6e85ca681b80cd094bfd4f3eeae4febf0012103dcf5a50ac66bde7fe9f01c4710fb5d438d51f1da1ce138863d34fee6499f328900000000",
-                expected_transaction_id: "32464234781c37831398b5d2f1e1766f8dbb55ac3b41ed047e365c07e9b03429",
+                expected_signed_transaction: "0200000002db6b1b20aa0fd7b23880be2ecbd4a98130974cf4748fb66092ac4d3ceb1a5477030000006b483045022100b4c7970750daebb99511d3aacc5c110764c63d839608b62b44a3d1d0d087ec9e022050b388037e33df7ce787ac68d984f87724fb699695c632214f1d8a3311e6712e012103f028892bad7ed57d2fb57bf33081d5cfcf6f9ed3d3d7f159c2e2fff579dc341affffffff9d344070eac3fe6e394a16d06d7704a7d5c0a10eb2a2c16bc98842b7cc20d561010000006b48304502210084af6b48c1d731cab3eb44f523dc7b2ccf0d1f44939b1572afb9de57bf7fc0f0022031a606437677e2b902d3049bd2f7ef0bf5ac1876dd9fb0d9b5ca4e0f64e21a28012102d0de0aaeaefad02b8bdc8a01a1b8b11c696bd3d66a2c5f10780d95b7df42645cffffffff01f0e62607000000001976a9146a9b24a37f4d830ab59efa3bfecb23a7e722228488ac00000000",
+                expected_transaction_id: "0634f010ebf62706017ee385b5b602590a7a7627f64d35152a4c41f73e8a585c",
             },
-            TransactionTestCase { // Transaction 4 -> Segwit P2SH_P2WPKH to Bech32(P2WPKH) and itself
-                version: 1,
-                lock_time: 0,
-                inputs: &[
-                    Input {
-                        private_key: "L5TmwLMEyEqMAYj1qd7Fx9YRhNJTCvNn4ofr98ErbgHA99GjLBXC",
-                        address_format: BitcoinFormat::P2SH_P2WPKH,
-                        transaction_id: "32464234781c37831398b5d2f1e1766f8dbb55ac3b41ed047e365c07e9b03429",
-                        index: 0,
-                        redeem_script: None,
-                        script_pub_key: None,
-                        utxo_amount: BitcoinAmount(25000),
-                        sequence: None,
-                        sighash_code: SignatureHash::SIGHASH_ALL
-                    },
+        ];
+
+        #[test]
+        fn test_dash_transactions() {
+            TRANSACTIONS.iter().for_each(|transaction| {
+                test_transaction::<N>(
+                    transaction.version,
+                    transaction.lock_time,
+                    transaction.inputs.to_vec(),
+                    transaction.outputs.to_vec(),
+                    transaction.expected_signed_transaction,
+                    transaction.expected_transaction_id,
+                );
+            });
+        }
+
+        #[test]
+        fn test_reconstructed_dash_transactions() {
+            TRANSACTIONS.iter().for_each(|transaction| {
+                test_reconstructed_transaction::<N>(
+                    transaction.version,
+                    transaction.lock_time,
+                    transaction.inputs.to_vec(),
+                    transaction.outputs.to_vec(),
+                    transaction.expected_signed_transaction,
+                    transaction.expected_transaction_id,
+                );
+            });
+        }
+    }
+
+    mod test_bitcoin_cash_transactions {
+        use super::*;
+        use crate::interpreter::{InterpreterError, VerificationFlags};
+        type N = BitcoinCash;
+
+        const TRANSACTIONS: [TransactionTestCase; 2] = [
+            TransactionTestCase { // p2pkh to p2pkh
+                version: 1,
+                lock_time: 0,
+                inputs: &[
                     Input {
-                        private_key: "L5TmwLMEyEqMAYj1qd7Fx9YRhNJTCvNn4ofr98ErbgHA99GjLBXC",
-                        address_format: BitcoinFormat::P2SH_P2WPKH,
-                        transaction_id: "76ef90fa70e4c10adc358432a979683a2cf1855ff545f88c5022dea8863ed5ab",
+                        private_key: "KyRv5iFPHG7iB5E4CqvMzH3WFJVhbfYK4VY7XAedd9Ys69mEsPLQ",
+                        address_format: BitcoinFormat::P2PKH,
+                        transaction_id: "9cb01977970f1322931532c9467e85eee9daf620b1886eb70d7c9b0e6c993bc4",
                         index: 0,
                         redeem_script: None,
                         script_pub_key: None,
-                        utxo_amount: BitcoinAmount(12000),
+                        utxo_amount: BitcoinAmount(120000000),
                         sequence: None,
-                        sighash_code: SignatureHash::SIGHASH_ALL
+                        sighash_code: SignatureHash::SIGHASH_ALL_SIGHASH_FORKID
                     },
                 ],
                 outputs: &[
                     Output {
-                        address: "bc1qzkuhp5jxuvwx90eg65wkxuw6y2pfe740yw6h5s",
-                        amount: BitcoinAmount(12000)
+                        address: "bitcoincash:qprcvtlpvhnpyxhcp4wau8ktg78dzuzktvetlc7g9s",
+                        amount: BitcoinAmount(100000000)
                     },
                     Output {
-                        address: "3QDTHVyuJrHixUhhsdZXQ7M8P9MQngmw1P",
-                        amount: BitcoinAmount(15000)
+                        address: "bitcoincash:qrhamld5e4fprnx4g4lxcgmu409dzn208yc07hur5p",
+                        amount: BitcoinAmount(19990000)
                     },
                 ],
-                expected_signed_transaction: "010000000001022934b0e9075c367e04ed413bac55bb8d6f76e1f1d2b5981383371c78344246320000000017160014354816a98500d7df9201d46e008c203dd5143b92ffffffffabd53e86a8de22508cf845f55f85f12c3a6879a9328435dc0ac1e470fa90ef760000000017160014354816a98500d7df9201d46e008c203dd5143b92ffffffff02e02e00000000000016001415b970d246e31c62bf28d51d6371da22829cfaaf983a00000000000017a914f7146aaa6f24a1012528c1d27cfe49d256d5a7018702483045022100988bc569371f74d6e49f20ae05ab06abfbe7ba92bbc177b61e38c0c9f430646702207a874da47387b6cfc066c26c24c99ccb75dac6772a0f94b7327703bdb156c4c8012103f850b5fa8fe53be8675dd3045ed89c8a4235155b484d88eb62d0afed7cb9ef050247304402204296465f1f95480f058ccebd70a0f80b9f092021a15793c954f39373e1e6500102206ca2d3f6cb68d1a9fde36ed6ded6509e2284c6afe860abf7f49c3ae18944ffdf012103f850b5fa8fe53be8675dd3045ed89c8a4235155b484d88eb62d0afed7cb9ef0500000000",
-                expected_transaction_id: "6a06bd83718f24dd1883332939e59fdd26b95d8a328eac37a45b7c489618eac8",
+                expected_signed_transaction: "0100000001c43b996c0e9b7c0db76e88b120f6dae9ee857e46c932159322130f977719b09c000000006b483045022100996e9dbfa6a60d2fac4134689554781953abad2e8921eea81fec042c82088cb302201eab765331ff693b3eee10e5e8279c5324cf96ce8c151ba10846b7710ba22dc7412103cc8a4bc64d897bddc5fbc2f670f7a8ba0b386779106cf1223c6fc5d7cd6fc115ffffffff0200e1f505000000001976a91447862fe165e6121af80d5dde1ecb478ed170565b88acf0053101000000001976a914efddfdb4cd5211ccd5457e6c237cabcad14d4f3988ac00000000",
+                expected_transaction_id: "b4919e3db79294a022503383c4f6171702c826e424c3a67be67fa9f49cba767b",
             },
-            TransactionTestCase { // Transaction 5 -> P2SH_P2WPKH, P2PKH uncompressed, and Bech32(P2WPKH) to Bech32(P2WPKH)
-                version: 1,
+            TransactionTestCase { // two p2pkh to legacy p2pkh and p2sh
+                version: 2,
                 lock_time: 0,
                 inputs: &[
                     Input {
-                        private_key: "L5TmwLMEyEqMAYj1qd7Fx9YRhNJTCvNn4ofr98ErbgHA99GjLBXC",
-                        address_format: BitcoinFormat::P2SH_P2WPKH,
-                        transaction_id: "6a06bd83718f24dd1883332939e59fdd26b95d8a328eac37a45b7c489618eac8",
-                        index: 1,
+                        private_key: "KyRv5iFPHG7iB5E4CqvMzH3WFJVhbfYK4VY7XAedd9Ys69mEsPLQ",
+                        address_format: BitcoinFormat::P2PKH,
+                        transaction_id: "77541aeb3c4dac9260b68f74f44c973081a9d4cb2ebe8038b2d70faa201b6bdb",
+                        index: 3,
                         redeem_script: None,
                         script_pub_key: None,
-                        utxo_amount: BitcoinAmount(15000),
+                        utxo_amount: BitcoinAmount(50000000),
                         sequence: None,
-                        sighash_code: SignatureHash::SIGHASH_ALL
+                        sighash_code: SignatureHash::SIGHASH_SINGLE_SIGHASH_FORKID_SIGHASH_ANYONECANPAY
                     },
                     Input {
-                        private_key: "5KRoKpnWWav74XDgz28opnJUsBozUg8STwEQPq354yUa3MiXySn",
+                        private_key: "L1uyy5qTuGrVXrmrsvHWHgVzW9kKdrp27wBC7Vs6nZDTF2BRUVwy",
                         address_format: BitcoinFormat::P2PKH,
-                        transaction_id: "76ef90fa70e4c10adc358432a979683a2cf1855ff545f88c5022dea8863ed5ab",
+                        transaction_id: "61d520ccb74288c96bc1a2b20ea1c0d5a704776dd0164a396efec3ea7040349d",
                         index: 1,
                         redeem_script: None,
                         script_pub_key: None,
-                        utxo_amount: BitcoinAmount(0),
-                        sequence: None,
-                        sighash_code: SignatureHash::SIGHASH_ALL
-                    },
-                    Input {
-                        private_key: "Kzs2rY8y9brmULJ3VK9gZHiZAhNJ2ttjn7ZuyJbG52pToZfCpQDr",
-                        address_format: BitcoinFormat::Bech32,
-                        transaction_id: "6a06bd83718f24dd1883332939e59fdd26b95d8a328eac37a45b7c489618eac8",
-                        index: 0,
-                        redeem_script: None,
-                        script_pub_key: None,
-                        utxo_amount: BitcoinAmount(12000),
-                        sequence: None,
-                        sighash_code: SignatureHash::SIGHASH_ALL
+                        utxo_amount: BitcoinAmount(70000000),
+                        sequence: Some([0xfe, 0xff, 0xff, 0xff]),
+                        sighash_code: SignatureHash::SIGHASH_ALL_SIGHASH_FORKID
                     },
                 ],
                 outputs: &[
                     Output {
-                        address: "bc1qmj865gnmg3hv7eh74qmvu5fcde43ecd7haa5hy",
-                        amount: BitcoinAmount(30000)
+                        address: "1Lqo8DmoGQqMga77j3AUZBUMwSR2HD4zeW",
+                        amount: BitcoinAmount(49990000)
+                    },
+                    Output {
+                        address: "bitcoincash:prdpw30fk4ym6zl6rftfjuw806arpn26fv8cp7wyl3",
+                        amount: BitcoinAmount(69990000)
                     },
                 ],
-                expected_signed_transaction: "01000000000103c8ea1896487c5ba437ac8e328a5db926dd9fe53929338318dd248f7183bd066a0100000017160014354816a98500d7df9201d46e008c203dd5143b92ffffffffabd53e86a8de22508cf845f55f85f12c3a6879a9328435dc0ac1e470fa90ef76010000008b4830450221008bf28b9f9e2c6d7d0ef9705b7fd914e7693b2f4f3584deff6dfa9dc83fc9f73402201cdbf5cd78bf04ccedfa11f17cff3728965dd328d30fad4f91ba2be57fb2ccab014104db232c08ac5f0332d317e6cd805f3e29e98b93fc9ca74831a6c5d27a0368cdb0862d536a445250a8de9d92cf1d450c7dc9b8efd6ca2ff0865d553f85f1bd346fffffffffc8ea1896487c5ba437ac8e328a5db926dd9fe53929338318dd248f7183bd066a0000000000ffffffff013075000000000000160014dc8faa227b446ecf66fea836ce51386e6b1ce1be02483045022100c77d6548c8f068d7088d1a5eab91be1f4bd394fdd7e7334699ccb1533af2c6300220621399e24b9f84bb580fab62ced44b979f0b5a06a1c429ffe4f8c2ae27f740fb012103f850b5fa8fe53be8675dd3045ed89c8a4235155b484d88eb62d0afed7cb9ef05000247304402205b3676bb82313d8ed25dec2efc30aa24076b4a5c0dc0e2b2953507a8135a470102207cad2e535a5cac8b947c9d37aeb9162ec745c61b7133eafba790442faa2a19000121030f36fbc8825fcdc2b79e5764b6bb70c2038bf4dba63dbf71483320e4d7f63a0500000000",
-                expected_transaction_id: "b2eb46fe6f8075caf013cd8e947f3b9dc06a416896d28aef450c9ec8d310361f",
-            }
+                expected_signed_transaction: "0200000002db6b1b20aa0fd7b23880be2ecbd4a98130974cf4748fb66092ac4d3ceb1a5477030000006b483045022100dca4bc86c3ef1c9b864ef94494d252982db185e02447c96e7f9b7f51be4eecd90220613f445cfc9e707381a7453d9e31681c33e5d2f01fcf9efba129e24612f0f790c32103cc8a4bc64d897bddc5fbc2f670f7a8ba0b386779106cf1223c6fc5d7cd6fc115ffffffff9d344070eac3fe6e394a16d06d7704a7d5c0a10eb2a2c16bc98842b7cc20d561010000006b483045022100bf872e5d6e2d7b0180bca2eaf68b3efeda556b3f6b2e697ca6dcf92d918df2650220354884b9fd3e34a0322afa26949ef0f807031de3f4500f58ebcbabaf55fcd08c4121029f50f51d63b345039a290c94bffd3180c99ed659ff6ea6b1242bca47eb93b59ffeffffff0270c9fa02000000001976a914d9a4dbdcef5ce3bb280c54a8201d2c93f403115c88ac70f62b040000000017a914da1745e9b549bd0bfa1a569971c77eba30cd5a4b8700000000",
+                expected_transaction_id: "3195e15e3093d19929d634a8cfd3b083c1ab259f53b3c70f89cd5259b992745b",
+            },
         ];
 
         #[test]
-        fn test_real_mainnet_transactions() {
-            REAL_TRANSACTIONS.iter().for_each(|transaction| {
+        fn test_bitcoin_cash_transactions() {
+            TRANSACTIONS.iter().for_each(|transaction| {
                 test_transaction::<N>(
                     transaction.version,
                     transaction.lock_time,
@@ -1788,8 +3402,10 @@ mod tests {
         }
 
         #[test]
-        fn test_real_reconstructed_mainnet_transactions() {
-            REAL_TRANSACTIONS.iter().for_each(|transaction| {
+        fn test_reconstructed_bitcoin_cash_transactions() {
+            // Inputs read from an unsigned transaction are signed with the default sighash
+            let is_default = |input: &Input| input.sighash_code == default_sighash::<N>();
+            for transaction in TRANSACTIONS.iter().filter(|transaction| transaction.inputs.iter().all(is_default)) {
                 test_reconstructed_transaction::<N>(
                     transaction.version,
                     transaction.lock_time,
@@ -1798,8 +3414,138 @@ mod tests {
                     transaction.expected_signed_transaction,
                     transaction.expected_transaction_id,
                 );
+            }
+        }
+
+        fn p2pkh_transaction<M: BitcoinNetwork>(sighash: SignatureHash) -> (BitcoinTransaction<M>, BitcoinPrivateKey<M>) {
+            let private_key = BitcoinPrivateKey::<M>::from_str(TRANSACTIONS[0].inputs[0].private_key).unwrap();
+            let address = private_key.to_address(&BitcoinFormat::P2PKH).unwrap();
+            let input = BitcoinTransactionInputBuilder::<M>::new(TRANSACTIONS[0].inputs[0].transaction_id, 0)
+                .address(&address.to_string())
+                .amount(BitcoinAmount(120000000))
+                .sighash(sighash)
+                .build()
+                .unwrap();
+            let output = BitcoinTransactionOutput::new(&address, BitcoinAmount(119990000)).unwrap();
+            let parameters = BitcoinTransactionParameters::<M>::new(vec![input], vec![output]).unwrap();
+            (BitcoinTransaction::<M>::new(&parameters).unwrap(), private_key)
+        }
+
+        fn sign(transaction: &TransactionTestCase) -> BitcoinTransaction<N> {
+            let inputs = transaction
+                .inputs
+                .iter()
+                .map(|input| {
+                    let private_key = BitcoinPrivateKey::<N>::from_str(input.private_key).unwrap();
+                    BitcoinTransactionInput::<N>::new(
+                        hex::decode(input.transaction_id).unwrap(),
+                        input.index,
+                        Some(private_key.to_address(&input.address_format).unwrap()),
+                        Some(input.utxo_amount),
+                        None,
+                        None,
+                        input.sequence.map(|sequence| sequence.to_vec()),
+                        input.sighash_code,
+                    )
+                    .unwrap()
+                })
+                .collect();
+            let outputs = transaction
+                .outputs
+                .iter()
+                .map(|output| {
+                    let address = BitcoinAddress::<N>::from_str(output.address).unwrap();
+                    BitcoinTransactionOutput::new(&address, output.amount).unwrap()
+                })
+                .collect();
+            let mut signed = BitcoinTransaction::<N>::new(&BitcoinTransactionParameters::<N> {
+                version: transaction.version,
+                inputs,
+                outputs,
+                lock_time: transaction.lock_time,
+                segwit_flag: false,
+            })
+            .unwrap();
+            for input in transaction.inputs {
+                signed.sign_with_private_key(&BitcoinPrivateKey::from_str(input.private_key).unwrap()).unwrap();
+            }
+            signed
+        }
+
+        #[test]
+        fn test_verify_fork_id_signatures() {
+            TRANSACTIONS.iter().for_each(|transaction| {
+                let mut signed = sign(transaction);
+                assert!(signed.verify(VerificationFlags::STANDARD).iter().all(Result::is_ok));
+
+                // The signatures commit to the amounts of the inputs
+                let amount = signed.parameters.inputs[0].outpoint.amount.unwrap();
+                signed.parameters.inputs[0].outpoint.amount = Some(BitcoinAmount(amount.0 + 1));
+                assert!(signed.verify_input(0, VerificationFlags::STANDARD).is_err());
             });
         }
+
+        #[test]
+        fn test_verify_signatures_without_fork_id() {
+            // A valid Bitcoin signature is rejected on Bitcoin Cash
+            let (mut transaction, private_key) = p2pkh_transaction::<Mainnet>(SignatureHash::SIGHASH_ALL);
+            let signed = transaction.sign_with_private_key(&private_key).unwrap();
+            assert!(transaction.verify_input(0, VerificationFlags::STANDARD).is_ok());
+
+            let (unsigned, _) = p2pkh_transaction::<N>(SignatureHash::SIGHASH_ALL_SIGHASH_FORKID);
+            let mut transaction = BitcoinTransaction::<N>::from_str(&hex::encode(signed)).unwrap();
+            transaction.parameters.inputs[0].outpoint = unsigned.parameters.inputs[0].outpoint.clone();
+            let failure = transaction.verify_input(0, VerificationFlags::STANDARD).unwrap_err();
+            assert_eq!(InterpreterError::MustUseForkId, failure.error);
+        }
+
+        #[test]
+        fn test_verify_fork_id_multisig() {
+            let private_keys: Vec<BitcoinPrivateKey<N>> = TRANSACTIONS[1]
+                .inputs
+                .iter()
+                .map(|input| BitcoinPrivateKey::from_str(input.private_key).unwrap())
+                .collect();
+            let public_keys: Vec<_> = private_keys.iter().map(|private_key| private_key.to_public_key()).collect();
+            let script = MultisigScript::new(2, &public_keys).unwrap().to_script();
+            let address = BitcoinAddress::<N>::p2sh(&script).unwrap();
+
+            let mut transaction = BitcoinTransactionBuilder::<N>::new()
+                .input(
+                    BitcoinTransactionInputBuilder::new(TRANSACTIONS[0].inputs[0].transaction_id, 0)
+                        .address(&address.to_string())
+                        .amount(BitcoinAmount(100_000))
+                        .redeem_script(script),
+                )
+                .output(TRANSACTIONS[0].outputs[0].address, BitcoinAmount(99_000))
+                .build()
+                .unwrap();
+            for private_key in &private_keys {
+                transaction.sign_with_private_key(private_key).unwrap();
+            }
+            assert!(transaction.parameters.inputs[0].is_signed);
+            assert_eq!(Ok(()), transaction.verify_input(0, VerificationFlags::STANDARD));
+        }
+
+        #[test]
+        fn test_default_sighash() {
+            let input = BitcoinTransactionInputBuilder::<N>::new(TRANSACTIONS[0].inputs[0].transaction_id, 0);
+            assert_eq!(SignatureHash::SIGHASH_ALL_SIGHASH_FORKID, input.sighash_code);
+            let input = BitcoinTransactionInputBuilder::<Mainnet>::new(TRANSACTIONS[0].inputs[0].transaction_id, 0);
+            assert_eq!(SignatureHash::SIGHASH_ALL, input.sighash_code);
+        }
+
+        #[test]
+        fn test_invalid_sighash() {
+            // Signatures without replay protection are rejected on Bitcoin Cash, and with it elsewhere
+            let (mut transaction, private_key) = p2pkh_transaction::<N>(SignatureHash::SIGHASH_ALL);
+            assert!(transaction.sign_with_private_key(&private_key).is_err());
+            assert!(transaction.fork_id_hash_preimage(0, SignatureHash::SIGHASH_ALL).is_err());
+
+            let (mut transaction, private_key) = p2pkh_transaction::<Mainnet>(SignatureHash::SIGHASH_ALL_SIGHASH_FORKID);
+            assert!(transaction.sign_with_private_key(&private_key).is_err());
+            assert!(transaction.fork_id_hash_preimage(0, SignatureHash::SIGHASH_ALL_SIGHASH_FORKID).is_err());
+        }
     }
 
     mod test_invalid_transactions {
@@ -2005,6 +3751,148 @@ mod tests {
         }
     }
 
+    mod test_multisig_signing {
+        use super::*;
+        use crate::multisig::MultisigScript;
+        type N = Mainnet;
+
+        const PRIVATE_KEYS: [&str; 3] = [
+            "L1uyy5qTuGrVXrmrsvHWHgVzW9kKdrp27wBC7Vs6nZDTF2BRUVwy",
+            "KwtetKxofS1Lhp7idNJzb5B5WninBRfELdwkjvTMZZGME4G72kMz",
+            "L1Knwj9W3qK3qMKdTvmg3VfzUs3ij2LETTFhxza9LfD5dngnoLG1",
+        ];
+        const OTHER_PRIVATE_KEY: &str = "KwcN2pT3wnRAurhy7qMczzbkpY5nXMW2ubh696UBc1bcwctTx26z";
+        const TRANSACTION_ID: &str = "9cb01977970f1322931532c9467e85eee9daf620b1886eb70d7c9b0e6c993bc4";
+        const FORMATS: [BitcoinFormat; 3] = [BitcoinFormat::P2SH, BitcoinFormat::P2SH_P2WSH, BitcoinFormat::P2WSH];
+
+        fn private_key(private_key: &str) -> BitcoinPrivateKey<N> {
+            BitcoinPrivateKey::<N>::from_str(private_key).unwrap()
+        }
+
+        /// A 2-of-3 multisig with the public keys sorted as specified in BIP-67
+        fn multisig() -> MultisigScript {
+            let public_keys: Vec<BitcoinPublicKey<N>> =
+                PRIVATE_KEYS.iter().map(|key| private_key(key).to_public_key()).collect();
+            MultisigScript::sorted(2, &public_keys).unwrap()
+        }
+
+        fn unsigned_transaction(format: &BitcoinFormat) -> BitcoinTransaction<N> {
+            let multisig = multisig();
+            let address = multisig.to_address::<N>(format).unwrap();
+            BitcoinTransactionBuilder::<N>::new()
+                .input(
+                    BitcoinTransactionInputBuilder::new(TRANSACTION_ID, 1)
+                        .address(&address.to_string())
+                        .redeem_script(multisig.to_script())
+                        .amount(BitcoinAmount(100000)),
+                )
+                .output("1Fyxts6r24DpEieygQiNnWxUdb18ANa5p7", BitcoinAmount(90000))
+                .build()
+                .unwrap()
+        }
+
+        fn transaction_hash(transaction: &BitcoinTransaction<N>, format: &BitcoinFormat) -> libsecp256k1::Message {
+            let preimage = match format {
+                BitcoinFormat::P2SH => transaction.p2pkh_hash_preimage(0, SignatureHash::SIGHASH_ALL).unwrap(),
+                _ => transaction.segwit_hash_preimage(0, SignatureHash::SIGHASH_ALL).unwrap(),
+            };
+            libsecp256k1::Message::parse_slice(&Sha256::digest(&Sha256::digest(&preimage))).unwrap()
+        }
+
+        #[test]
+        fn sign() {
+            let multisig = multisig();
+            FORMATS.iter().for_each(|format| {
+                let mut transaction = unsigned_transaction(format);
+                let unsigned = transaction.to_bytes().unwrap();
+                let message = transaction_hash(&transaction, format);
+                let (estimated_script_sig, estimated_witness) = transaction.parameters.inputs[0].estimate_size().unwrap();
+                assert_eq!(*format, transaction.parameters.inputs[0].outpoint.address.as_ref().unwrap().format());
+
+                // The input is not signed until the threshold of cosigners has signed
+                assert_eq!(unsigned, transaction.sign_with_private_key(&private_key(PRIVATE_KEYS[2])).unwrap());
+                assert_eq!(unsigned, transaction.sign_with_private_key(&private_key(OTHER_PRIVATE_KEY)).unwrap());
+                assert_eq!(1, transaction.parameters.inputs[0].partial_signatures.len());
+                assert!(!transaction.parameters.inputs[0].is_signed);
+
+                let signed_transaction = transaction.sign_with_private_key(&private_key(PRIVATE_KEYS[0])).unwrap();
+                let input = &transaction.parameters.inputs[0];
+                assert!(input.is_signed);
+                let (script_sig, witness) = input.estimate_size().unwrap();
+                assert!(script_sig <= estimated_script_sig && estimated_script_sig - script_sig <= 4);
+                assert!(witness <= estimated_witness && estimated_witness - witness <= 4);
+
+                // The stack is the empty element and the signatures in the order of the public keys
+                let stack = multisig.satisfy(&input.partial_signatures).unwrap();
+                let signatures: Vec<&Vec<u8>> = multisig.public_keys().iter().filter_map(|key| input.partial_signatures.get(key)).collect();
+                assert!(stack[0].is_empty());
+                assert_eq!(signatures, stack[1..].iter().collect::<Vec<&Vec<u8>>>());
+                for (public_key, signature) in &input.partial_signatures {
+                    let public_key = libsecp256k1::PublicKey::parse_slice(public_key, None).unwrap();
+                    let signature = libsecp256k1::Signature::parse_der(&signature[..signature.len() - 1]).unwrap();
+                    assert!(libsecp256k1::verify(&message, &signature, &public_key));
+                }
+
+                let script = multisig.to_script();
+                let mut expected_witness = vec![];
+                for item in stack.iter().chain(Some(&script)) {
+                    expected_witness.push([variable_length_integer(item.len() as u64).unwrap(), item.clone()].concat());
+                }
+                match format {
+                    BitcoinFormat::P2SH => {
+                        let mut expected_script_sig: Vec<u8> = stack.iter().flat_map(|item| push_data(item)).collect();
+                        expected_script_sig.extend(push_data(&script));
+                        assert_eq!(expected_script_sig, input.script_sig);
+                        assert!(input.witnesses.is_empty());
+                    }
+                    BitcoinFormat::P2SH_P2WSH => {
+                        assert_eq!(push_data(&create_p2sh_p2wsh_redeem_script(&script)), input.script_sig);
+                        assert_eq!(expected_witness, input.witnesses);
+                    }
+                    _ => {
+                        assert!(input.script_sig.is_empty());
+                        assert_eq!(expected_witness, input.witnesses);
+                    }
+                }
+
+                let signed = BitcoinTransaction::<N>::from_bytes(&signed_transaction).unwrap();
+                assert_eq!(signed_transaction, signed.to_bytes().unwrap());
+                assert_eq!(SignatureHash::SIGHASH_ALL, signed.parameters.inputs[0].sighash_code);
+            });
+        }
+
+        #[test]
+        fn add_multisig_signature() {
+            FORMATS.iter().for_each(|format| {
+                let mut expected_transaction = unsigned_transaction(format);
+                expected_transaction.sign_with_private_key(&private_key(PRIVATE_KEYS[0])).unwrap();
+                let expected = expected_transaction.sign_with_private_key(&private_key(PRIVATE_KEYS[1])).unwrap();
+
+                // Each cosigner signs its own copy of the transaction
+                let mut transaction = unsigned_transaction(format);
+                let mut cosigner_transaction = unsigned_transaction(format);
+                transaction.sign_with_private_key(&private_key(PRIVATE_KEYS[0])).unwrap();
+                cosigner_transaction.sign_with_private_key(&private_key(PRIVATE_KEYS[1])).unwrap();
+                let cosigner = private_key(PRIVATE_KEYS[1]).to_public_key();
+                let signature = cosigner_transaction.parameters.inputs[0].partial_signatures.values().next().unwrap().clone();
+
+                // The signature must be of a cosigner, for the input and with its sighash
+                let other = private_key(OTHER_PRIVATE_KEY).to_public_key();
+                assert!(transaction.add_multisig_signature(0, &other, signature.clone()).is_err());
+                let third = private_key(PRIVATE_KEYS[2]).to_public_key();
+                assert!(transaction.add_multisig_signature(0, &third, signature.clone()).is_err());
+                let mut invalid_sighash = signature.clone();
+                *invalid_sighash.last_mut().unwrap() = SignatureHash::SIGHASH_NONE as u8;
+                assert!(transaction.add_multisig_signature(0, &cosigner, invalid_sighash).is_err());
+                assert!(transaction.add_multisig_signature(1, &cosigner, signature.clone()).is_err());
+                assert!(!transaction.parameters.inputs[0].is_signed);
+
+                assert_eq!(expected, transaction.add_multisig_signature(0, &cosigner, signature.clone()).unwrap());
+                assert!(transaction.add_multisig_signature(0, &cosigner, signature).is_err());
+            });
+        }
+    }
+
     mod test_helper_functions {
         use super::*;
 
@@ -2040,4 +3928,609 @@ mod tests {
             });
         }
     }
+
+    mod test_transaction_builder {
+        use super::*;
+        type N = Mainnet;
+
+        // p2sh_p2wpkh to p2pkh - based on https://github.com/bitcoin/bips/blob/master/bip-0143.mediawiki#p2sh-p2wpkh
+        const PRIVATE_KEY: &str = "5Kbxro1cmUF9mTJ8fDrTfNB6URTBsFMUG52jzzumP2p9C94uKCh";
+        const TRANSACTION_ID: &str = "77541aeb3c4dac9260b68f74f44c973081a9d4cb2ebe8038b2d70faa201b6bdb";
+        const EXPECTED_SIGNED_TRANSACTION: &str = "01000000000101db6b1b20aa0fd7b23880be2ecbd4a98130974cf4748fb66092ac4d3ceb1a5477010000001716001479091972186c449eb1ded22b78e40d009bdf0089feffffff02b8b4eb0b000000001976a914a457b684d7f0d539a46a45bbc043f35b59d0d96388ac0008af2f000000001976a914fd270b1ee6abcaea97fea7ad0402e8bd8ad6d77c88ac02473044022047ac8e878352d3ebbde1c94ce3a10d057c24175747116f8288e5d794d12d482f0220217f36a485cae903c713331d877c1f64677e3622ad4010726870540656fe9dcb012103ad1d8e89212f0b92c74d23bb710c00662ad1470198ac48c43f7d6f93a2a2687392040000";
+
+        fn input() -> BitcoinTransactionInputBuilder<N> {
+            let private_key = BitcoinPrivateKey::<N>::from_str(PRIVATE_KEY).unwrap();
+            let public_key = private_key.to_public_key().to_secp256k1_public_key().serialize_compressed();
+            let redeem_script = [vec![0x00, 0x14], hash160(&public_key)].concat();
+            let address = private_key.to_address(&BitcoinFormat::P2SH_P2WPKH).unwrap();
+
+            BitcoinTransactionInputBuilder::new(TRANSACTION_ID, 1)
+                .address(&address.to_string())
+                .amount(BitcoinAmount(1000000000))
+                .redeem_script(redeem_script)
+                .sequence(0xfffffffe)
+        }
+
+        fn builder() -> BitcoinTransactionBuilder<N> {
+            BitcoinTransactionBuilder::<N>::new()
+                .version(1)
+                .lock_time(1170)
+                .input(input())
+                .output("1Fyxts6r24DpEieygQiNnWxUdb18ANa5p7", BitcoinAmount(199996600))
+                .output("1Q5YjKVj5yQWHBBsyEBamkfph3cA6G9KK8", BitcoinAmount(800000000))
+        }
+
+        #[test]
+        fn build() {
+            let mut transaction = builder().max_fee(BitcoinAmount(3400)).build().unwrap();
+            let private_key = BitcoinPrivateKey::<N>::from_str(PRIVATE_KEY).unwrap();
+            let signed_transaction = transaction.sign_with_private_key(&private_key).unwrap();
+            assert_eq!(EXPECTED_SIGNED_TRANSACTION, hex::encode(signed_transaction));
+        }
+
+        #[test]
+        fn replaceable() {
+            assert!(!builder().build().unwrap().signals_rbf());
+
+            let replaceable = input().replaceable().build().unwrap();
+            assert_eq!(SEQUENCE_ENABLE_RBF, replaceable.sequence_number());
+            // A relative lock time signals replaceability on its own
+            let relative_lock_time = input().sequence(144).replaceable().build().unwrap();
+            assert_eq!(144, relative_lock_time.sequence_number());
+
+            let mut final_input = input().build().unwrap();
+            assert!(!final_input.signals_rbf());
+            final_input.enable_rbf();
+            assert!(final_input.signals_rbf());
+        }
+
+        #[test]
+        fn invalid_build() {
+            assert!(matches!(
+                BitcoinTransactionBuilder::<N>::new().build(),
+                Err(TransactionError::MissingField("inputs"))
+            ));
+            assert!(matches!(
+                BitcoinTransactionBuilder::<N>::new().input(input()).build(),
+                Err(TransactionError::MissingField("outputs"))
+            ));
+            assert!(matches!(
+                builder().max_fee(BitcoinAmount(3399)).build(),
+                Err(TransactionError::InvalidFee(_))
+            ));
+            assert!(matches!(
+                builder().output("1Fyxts6r24DpEieygQiNnWxUdb18ANa5p7", BitcoinAmount(3401)).build(),
+                Err(TransactionError::InsufficientFunds(_, _))
+            ));
+            assert!(matches!(
+                builder().output("1Fyxts6r24DpEieygQiNnWxUdb18ANa5p7", BitcoinAmount(-1)).build(),
+                Err(TransactionError::AmountError(_))
+            ));
+            assert!(matches!(
+                builder().input(input()).build(),
+                Err(TransactionError::DuplicateOutpoint(id, 1)) if id == TRANSACTION_ID
+            ));
+
+            // Testnet address on mainnet
+            assert!(matches!(
+                builder().output("mkHS9ne12qx9pS9VojpwU5xtRd4T7X7ZUt", BitcoinAmount(1)).build(),
+                Err(TransactionError::AddressError(_))
+            ));
+
+            // SegWit inputs require the amount of the spent output
+            let private_key = BitcoinPrivateKey::<N>::from_str(PRIVATE_KEY).unwrap();
+            let address = private_key.to_address(&BitcoinFormat::Bech32).unwrap();
+            let input = BitcoinTransactionInputBuilder::<N>::new(TRANSACTION_ID, 0).address(&address.to_string());
+            assert!(matches!(input.build(), Err(TransactionError::MissingOutpointAmount)));
+
+            let input = BitcoinTransactionInputBuilder::<N>::new("77541aeb", 0).address(&address.to_string());
+            assert!(matches!(input.build(), Err(TransactionError::InvalidTransactionId(4))));
+        }
+
+        #[test]
+        fn estimate() {
+            let mut transaction = builder().build().unwrap();
+            let estimate = transaction.estimate().unwrap();
+            let weight = transaction.weight().unwrap();
+
+            let private_key = BitcoinPrivateKey::<N>::from_str(PRIVATE_KEY).unwrap();
+            let signed_transaction = transaction.sign_with_private_key(&private_key).unwrap();
+            assert_eq!(signed_transaction.len() as u64, transaction.estimate().unwrap().size);
+
+            // The placeholder signature is 1 byte longer than the signature of the vector
+            assert_eq!(signed_transaction.len() as u64 + 1, estimate.size);
+            assert_eq!(transaction.weight().unwrap() + 1, weight);
+            assert_eq!(estimate.units, weight.div_ceil(4));
+            assert_eq!(estimate.units * 5, builder().build().unwrap().estimate_fee(5).unwrap());
+        }
+
+        #[test]
+        fn estimate_p2wpkh() {
+            // One P2WPKH input and two P2WPKH outputs weigh 562 units (141 virtual bytes)
+            let private_key = BitcoinPrivateKey::<N>::from_str(PRIVATE_KEY).unwrap();
+            let address = private_key.to_address(&BitcoinFormat::Bech32).unwrap().to_string();
+            let transaction = BitcoinTransactionBuilder::<N>::new()
+                .input(BitcoinTransactionInputBuilder::new(TRANSACTION_ID, 0).address(&address).amount(BitcoinAmount(10000)))
+                .output(&address, BitcoinAmount(5000))
+                .output(&address, BitcoinAmount(4000))
+                .build()
+                .unwrap();
+            assert_eq!(562, transaction.weight().unwrap());
+            assert_eq!(TransactionEstimate { size: 223, units: 141 }, transaction.estimate().unwrap());
+            assert_eq!(1410, transaction.estimate_fee(10).unwrap());
+
+            let transaction_id = hex::decode(TRANSACTION_ID).unwrap();
+            let input =
+                BitcoinTransactionInput::<N>::new(transaction_id, 0, None, None, None, None, None, SignatureHash::SIGHASH_ALL)
+                    .unwrap();
+            assert!(matches!(input.estimate_size(), Err(TransactionError::MissingOutpointAddress)));
+        }
+    }
+
+    mod test_p2tr_transactions {
+        use super::*;
+        use crate::taproot::{schnorr_verify, tweak_public_key, x_only_public_key};
+        type N = Mainnet;
+
+        // The BIP-86 key of m/86'/0'/0'/0/0, https://github.com/bitcoin/bips/blob/master/bip-0086.mediawiki#test-vectors
+        const PRIVATE_KEY: &str = "KyRv5iFPHG7iB5E4CqvMzH3WFJVhbfYK4VY7XAedd9Ys69mEsPLQ";
+        const TRANSACTION_ID: &str = "9cb01977970f1322931532c9467e85eee9daf620b1886eb70d7c9b0e6c993bc4";
+        const OTHER_TRANSACTION_ID: &str = "77541aeb3c4dac9260b68f74f44c973081a9d4cb2ebe8038b2d70faa201b6bdb";
+        // The BIP-341 signature hash of input 0 for each sighash type, cross-checked against the reference implementation
+        const EXPECTED_SIGHASHES: [(SignatureHash, &str); 3] = [
+            (SignatureHash::SIGHASH_DEFAULT, "649c47a8035f69db7b11e96d3161aa0c7b6c772d08f601a8e15cd3bfd3800da4"),
+            (SignatureHash::SIGHASH_NONE, "37a9b0482f8a3543db3d9ac13153a8c06be5928c135723e73790337cfbaaa7f4"),
+            (SignatureHash::SIGHASH_SINGLE_SIGHASH_ANYONECANPAY, "d0d2d1fcefb1ba8c7bd30b3305150bcdcaa38b12789587a1e3848613d7a78603"),
+        ];
+
+        fn unsigned_transaction(sighash: SignatureHash) -> BitcoinTransaction<N> {
+            let private_key = BitcoinPrivateKey::<N>::from_str(PRIVATE_KEY).unwrap();
+            let address = private_key.to_address(&BitcoinFormat::P2TR).unwrap().to_string();
+            let other_address = private_key.to_address(&BitcoinFormat::Bech32).unwrap().to_string();
+            BitcoinTransactionBuilder::<N>::new()
+                .version(2)
+                .lock_time(500)
+                .input(
+                    BitcoinTransactionInputBuilder::new(TRANSACTION_ID, 1)
+                        .address(&address)
+                        .amount(BitcoinAmount(100000))
+                        .sequence(0xfffffffd)
+                        .sighash(sighash),
+                )
+                .input(BitcoinTransactionInputBuilder::new(OTHER_TRANSACTION_ID, 0).address(&other_address).amount(BitcoinAmount(50000)))
+                .output(&address, BitcoinAmount(90000))
+                .output("1Fyxts6r24DpEieygQiNnWxUdb18ANa5p7", BitcoinAmount(50000))
+                .build()
+                .unwrap()
+        }
+
+        #[test]
+        fn sign() {
+            let private_key = BitcoinPrivateKey::<N>::from_str(PRIVATE_KEY).unwrap();
+            let (internal_key, _) = x_only_public_key(&private_key.to_public_key().to_secp256k1_public_key());
+            let (output_key, _) = tweak_public_key(&internal_key, None).unwrap();
+
+            EXPECTED_SIGHASHES.iter().for_each(|(sighash_code, expected_sighash)| {
+                let mut transaction = unsigned_transaction(*sighash_code);
+                let (_, estimate) = transaction.parameters.inputs[0].estimate_size().unwrap();
+                let sighash = tagged_hash("TapSighash", &transaction.taproot_hash_preimage(0, *sighash_code).unwrap());
+                assert_eq!(*expected_sighash, hex::encode(sighash));
+
+                let signed_transaction = transaction.sign_with_private_key(&private_key).unwrap();
+                let signed = BitcoinTransaction::<N>::from_bytes(&signed_transaction).unwrap();
+                assert_eq!(signed_transaction, signed.to_bytes().unwrap());
+
+                // The key path witness is a single Schnorr signature for the output key
+                let witnesses = &transaction.parameters.inputs[0].witnesses;
+                assert_eq!(1, witnesses.len());
+                let mut signature = [0u8; 64];
+                signature.copy_from_slice(&witnesses[0][1..65]);
+                assert!(schnorr_verify(&sighash, &output_key, &signature));
+                match sighash_code {
+                    SignatureHash::SIGHASH_DEFAULT => {
+                        assert_eq!(65, witnesses[0].len());
+                        assert_eq!(1 + 65 + 1, estimate);
+                    }
+                    _ => {
+                        assert_eq!(vec![66, *sighash_code as u8], vec![witnesses[0].len() as u8, witnesses[0][65]]);
+                        assert_eq!(1 + 66, estimate);
+                    }
+                }
+            });
+        }
+
+        #[test]
+        fn sign_with_context() {
+            use chainlib_core::FixedClock;
+            use rand::{rngs::StdRng, SeedableRng};
+
+            let private_key = BitcoinPrivateKey::<N>::from_str(PRIVATE_KEY).unwrap();
+            let (internal_key, _) = x_only_public_key(&private_key.to_public_key().to_secp256k1_public_key());
+            let (output_key, _) = tweak_public_key(&internal_key, None).unwrap();
+            let sighash = tagged_hash(
+                "TapSighash",
+                &unsigned_transaction(SignatureHash::SIGHASH_DEFAULT).taproot_hash_preimage(0, SignatureHash::SIGHASH_DEFAULT).unwrap(),
+            );
+
+            let sign = |seed: u64| {
+                let mut context = Context::new(FixedClock(0), StdRng::seed_from_u64(seed));
+                let mut transaction = unsigned_transaction(SignatureHash::SIGHASH_DEFAULT);
+                let signed_transaction = transaction.sign_with_context(&private_key, &mut context).unwrap();
+                let mut signature = [0u8; 64];
+                signature.copy_from_slice(&transaction.parameters.inputs[0].witnesses[0][1..65]);
+                assert!(schnorr_verify(&sighash, &output_key, &signature));
+                signed_transaction
+            };
+
+            // The auxiliary randomness is drawn from the context, so the same seed signs byte for byte identically
+            assert_eq!(sign(2022), sign(2022));
+            assert_ne!(sign(2022), sign(2023));
+            assert_ne!(
+                sign(2022),
+                unsigned_transaction(SignatureHash::SIGHASH_DEFAULT).sign_with_private_key(&private_key).unwrap()
+            );
+        }
+
+        #[test]
+        fn invalid_sighash() {
+            // Every spent output is committed to unless signing with SIGHASH_ANYONECANPAY
+            let mut transaction = unsigned_transaction(SignatureHash::SIGHASH_ALL);
+            transaction.parameters.inputs[1].outpoint.amount = None;
+            assert!(matches!(
+                transaction.taproot_hash_preimage(0, SignatureHash::SIGHASH_ALL),
+                Err(TransactionError::MissingOutpointAmount)
+            ));
+            assert!(transaction.taproot_hash_preimage(0, SignatureHash::SIGHASH_ALL_SIGHASH_ANYONECANPAY).is_ok());
+            assert!(transaction.taproot_hash_preimage(2, SignatureHash::SIGHASH_ALL).is_err());
+
+            // SIGHASH_DEFAULT is only defined for Taproot
+            let private_key = BitcoinPrivateKey::<N>::from_str(PRIVATE_KEY).unwrap();
+            let mut transaction = unsigned_transaction(SignatureHash::SIGHASH_ALL);
+            transaction.parameters.inputs[0].sighash_code = SignatureHash::SIGHASH_ALL;
+            transaction.parameters.inputs[1].sighash_code = SignatureHash::SIGHASH_DEFAULT;
+            assert!(matches!(transaction.sign_with_private_key(&private_key), Err(TransactionError::InvalidInputs(_))));
+            assert!(transaction.segwit_hash_preimage(0, SignatureHash::SIGHASH_ALL).is_err());
+        }
+    }
+
+    mod test_p2tr_script_path {
+        use super::*;
+        use crate::taproot::{schnorr_verify, x_only_public_key, ControlBlock, TapTree};
+        type N = Mainnet;
+
+        const INTERNAL_KEY: &str = "KyRv5iFPHG7iB5E4CqvMzH3WFJVhbfYK4VY7XAedd9Ys69mEsPLQ";
+        const OWNER_KEY: &str = "L1uyy5qTuGrVXrmrsvHWHgVzW9kKdrp27wBC7Vs6nZDTF2BRUVwy";
+        const RECOVERY_KEY: &str = "KwtetKxofS1Lhp7idNJzb5B5WninBRfELdwkjvTMZZGME4G72kMz";
+        const RECOVERY_DELAY: u8 = 144;
+        const TRANSACTION_ID: &str = "9cb01977970f1322931532c9467e85eee9daf620b1886eb70d7c9b0e6c993bc4";
+        // The BIP-342 signature hash of the recovery leaf for each sighash type, cross-checked against the reference implementation
+        const EXPECTED_SIGHASHES: [(SignatureHash, &str); 2] = [
+            (SignatureHash::SIGHASH_DEFAULT, "261c38cd970b6d0b1cb89db468331fe4e0f009b944d2d5b36aa5bae6851536ed"),
+            (SignatureHash::SIGHASH_ALL_SIGHASH_ANYONECANPAY, "1c2adb845ffefab0ad7b787e8679fd492f423e1e25eb055071d53cb577c32242"),
+        ];
+
+        fn x_only_key(wif: &str) -> [u8; 32] {
+            let private_key = BitcoinPrivateKey::<N>::from_str(wif).unwrap();
+            x_only_public_key(&private_key.to_public_key().to_secp256k1_public_key()).0
+        }
+
+        /// A vault spendable by the owner at any time, or by the recovery key after a relative timelock
+        fn vault() -> (TapTree, TapLeaf) {
+            let owner = [vec![0x20], x_only_key(OWNER_KEY).to_vec(), vec![0xac]].concat();
+            let recovery = [
+                vec![0x02, RECOVERY_DELAY, 0x00, 0xb2, 0x75, 0x20],
+                x_only_key(RECOVERY_KEY).to_vec(),
+                vec![0xac],
+            ]
+            .concat();
+            let tree = TapTree::branch(TapTree::leaf(owner), TapTree::leaf(recovery.clone()));
+            (tree, TapLeaf::new(recovery))
+        }
+
+        fn unsigned_transaction(sighash: SignatureHash) -> BitcoinTransaction<N> {
+            let (tree, _) = vault();
+            let address = BitcoinAddress::<N>::p2tr_with_script_tree(&x_only_key(INTERNAL_KEY), Some(&tree)).unwrap();
+            BitcoinTransactionBuilder::<N>::new()
+                .version(2)
+                .input(
+                    BitcoinTransactionInputBuilder::new(TRANSACTION_ID, 0)
+                        .address(&address.to_string())
+                        .amount(BitcoinAmount(100000))
+                        .sequence(RECOVERY_DELAY as u32)
+                        .sighash(sighash),
+                )
+                .output("1Fyxts6r24DpEieygQiNnWxUdb18ANa5p7", BitcoinAmount(99000))
+                .build()
+                .unwrap()
+        }
+
+        #[test]
+        fn sign() {
+            let recovery_key = BitcoinPrivateKey::<N>::from_str(RECOVERY_KEY).unwrap();
+            let (tree, leaf) = vault();
+            let control_block = tree.control_block(&x_only_key(INTERNAL_KEY), &leaf).unwrap();
+
+            EXPECTED_SIGHASHES.iter().for_each(|(sighash_code, expected_sighash)| {
+                let mut transaction = unsigned_transaction(*sighash_code);
+                let sighash = tagged_hash(
+                    "TapSighash",
+                    &transaction.taproot_script_path_hash_preimage(0, &leaf, *sighash_code).unwrap(),
+                );
+                assert_eq!(*expected_sighash, hex::encode(sighash));
+
+                let signature = transaction.taproot_script_path_signature(0, &recovery_key, &leaf).unwrap();
+                let mut schnorr_signature = [0u8; 64];
+                schnorr_signature.copy_from_slice(&signature[..64]);
+                assert!(schnorr_verify(&sighash, &x_only_key(RECOVERY_KEY), &schnorr_signature));
+
+                let with_context = |seed: u64| {
+                    let mut context = chainlib_core::Context::new(chainlib_core::FixedClock(0), rand::rngs::mock::StepRng::new(seed, 1));
+                    transaction.taproot_script_path_signature_with_context(0, &recovery_key, &leaf, &mut context).unwrap()
+                };
+                assert_eq!(with_context(1), with_context(1));
+                assert_ne!(with_context(1), with_context(2));
+                schnorr_signature.copy_from_slice(&with_context(1)[..64]);
+                assert!(schnorr_verify(&sighash, &x_only_key(RECOVERY_KEY), &schnorr_signature));
+
+                let signed_transaction = transaction
+                    .add_taproot_script_path_witness(0, vec![signature.clone()], &leaf, &control_block)
+                    .unwrap();
+                let signed = BitcoinTransaction::<N>::from_bytes(&signed_transaction).unwrap();
+                assert_eq!(signed_transaction, signed.to_bytes().unwrap());
+                assert_eq!(*sighash_code, signed.parameters.inputs[0].sighash_code);
+
+                // The script path witness is the leaf stack, the leaf script and the control block
+                let witnesses = &signed.parameters.inputs[0].witnesses;
+                assert_eq!(3, witnesses.len());
+                assert_eq!(signature, witnesses[0][1..]);
+                assert_eq!(leaf.script, witnesses[1][1..]);
+                assert_eq!(control_block, ControlBlock::deserialize(&witnesses[2][1..]).unwrap());
+            });
+        }
+
+        #[test]
+        fn invalid_control_block() {
+            let (tree, leaf) = vault();
+            let internal_key = x_only_key(INTERNAL_KEY);
+            let mut transaction = unsigned_transaction(SignatureHash::SIGHASH_DEFAULT);
+
+            // The control block must commit to the spent leaf under the output key of the spent output
+            let (owner, _) = tree.leaves().remove(0);
+            let control_block = tree.control_block(&internal_key, &owner).unwrap();
+            assert!(matches!(
+                transaction.add_taproot_script_path_witness(0, vec![], &leaf, &control_block),
+                Err(TransactionError::InvalidInputs(_))
+            ));
+
+            let mut control_block = tree.control_block(&internal_key, &leaf).unwrap();
+            control_block.internal_key = x_only_key(RECOVERY_KEY);
+            assert!(matches!(
+                transaction.add_taproot_script_path_witness(0, vec![], &leaf, &control_block),
+                Err(TransactionError::InvalidInputs(_))
+            ));
+            assert!(transaction.add_taproot_script_path_witness(1, vec![], &leaf, &control_block).is_err());
+        }
+    }
+
+    mod test_signature_hash {
+        use super::*;
+        use crate::interpreter::VerificationFlags;
+        use serde_json::Value;
+        type N = Mainnet;
+
+        // Legacy signature hashes of random transactions, scripts and hash types from the sighash.json tests
+        // of Bitcoin Core: [raw transaction, script, input index, hash type, reversed hash]
+        const SIGHASH_TESTS: &str = include_str!("../tests/data/sighash.json");
+
+        // The native P2WPKH example of BIP-143, with input 1 spending 6 BTC
+        // https://github.com/bitcoin/bips/blob/master/bip-0143.mediawiki#native-p2wpkh
+        const P2WPKH_TRANSACTION: &str = "0100000002fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f0000000000eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac11000000";
+        const P2WPKH_SCRIPT_CODE: &str = "76a9141d0f172a0ecb48aee1be1f2687d2963ae33f71a188ac";
+
+        // The P2SH-P2WSH 6-of-6 multisig example of BIP-143, signed with every sighash type
+        // https://github.com/bitcoin/bips/blob/master/bip-0143.mediawiki#p2sh-p2wsh
+        const P2SH_P2WSH_TRANSACTION: &str = "010000000136641869ca081e70f394c6948e8af409e18b619df2ed74aa106c1ca29787b96e0100000000ffffffff0200e9a435000000001976a914389ffce9cd9ae88dcc0631e88a821ffdbe9bfe2688acc0832f05000000001976a9147480a33f950689af511e6e84c138dbbd3c3ee41588ac00000000";
+        const P2SH_P2WSH_WITNESS_SCRIPT: &str = "56210307b8ae49ac90a048e9b53357a2354b3334e9c8bee813ecb98e99a7e07e8c3ba32103b28f0c28bfab54554ae8c658ac5c3e0ce6e79ad336331f78c428dd43eea8449b21034b8113d703413d57761b8b9781957b8c0ac1dfe69f492580ca4195f50376ba4a21033400f6afecb833092a9a21cfdf1ed1376e58c5d1f47de74683123987e967a8f42103a6d48b1131e94ba04d9737d61acdaa1322008af9602b3b14862c07a1789aac162102d8b661b0b3302ee2f162b09e07a55ad5dfbe673a9f01d9f0c19617681024306b56ae";
+        const P2SH_P2WSH_SIGHASHES: [(SignatureHash, &str); 6] = [
+            (SignatureHash::SIGHASH_ALL, "185c0be5263dce5b4bb50a047973c1b6272bfbd0103a89444597dc40b248ee7c"),
+            (SignatureHash::SIGHASH_NONE, "e9733bc60ea13c95c6527066bb975a2ff29a925e80aa14c213f686cbae5d2f36"),
+            (SignatureHash::SIGHASH_SINGLE, "1e1f1c303dc025bd664acb72e583e933fae4cff9148bf78c157d1e8f78530aea"),
+            (SignatureHash::SIGHASH_ALL_SIGHASH_ANYONECANPAY, "2a67f03e63a6a422125878b40b82da593be8d4efaafe88ee528af6e5a9955c6e"),
+            (SignatureHash::SIGHASH_NONE_SIGHASH_ANYONECANPAY, "781ba15f3779d5542ce8ecb5c18716733a5ee42a6f51488ec96154934e2c890a"),
+            (SignatureHash::SIGHASH_SINGLE_SIGHASH_ANYONECANPAY, "511e8e52ed574121fc1b654970395502128263f62662e076dc6baf05c2e6a99b"),
+        ];
+
+        // The P2WPKH example without its second output, so that input 1 has no matching output; the BIP-143
+        // signature hashes are cross-checked against an independent implementation of the specification
+        const SINGLE_BUG_TRANSACTION: &str = "0100000002fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f0000000000eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff01202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac11000000";
+        const SINGLE_BUG_SIGHASHES: [(SignatureHash, &str); 2] = [
+            (SignatureHash::SIGHASH_SINGLE, "471a6e7963aa0c328ee12392fb1a345148edf326b4223660e1a770fdc2826435"),
+            (SignatureHash::SIGHASH_SINGLE_SIGHASH_ANYONECANPAY, "09b28b7a6e37ac255408f208f5666470ad2bb342f6c12acb44fee4a846b4511c"),
+        ];
+
+        const PRIVATE_KEY: &str = "KyRv5iFPHG7iB5E4CqvMzH3WFJVhbfYK4VY7XAedd9Ys69mEsPLQ";
+        const TRANSACTION_ID: &str = "9cb01977970f1322931532c9467e85eee9daf620b1886eb70d7c9b0e6c993bc4";
+        const OTHER_TRANSACTION_ID: &str = "77541aeb3c4dac9260b68f74f44c973081a9d4cb2ebe8038b2d70faa201b6bdb";
+
+        fn double_sha256(preimage: &[u8]) -> String {
+            hex::encode(Sha256::digest(Sha256::digest(preimage)))
+        }
+
+        fn p2wpkh_input(transaction_id: &str, sighash: SignatureHash) -> BitcoinTransactionInputBuilder<N> {
+            let private_key = BitcoinPrivateKey::<N>::from_str(PRIVATE_KEY).unwrap();
+            let address = private_key.to_address(&BitcoinFormat::Bech32).unwrap().to_string();
+            BitcoinTransactionInputBuilder::new(transaction_id, 0)
+                .address(&address)
+                .amount(BitcoinAmount(100_000))
+                .sighash(sighash)
+        }
+
+        fn run_sighash_tests(tests: &str) -> usize {
+            let tests: Vec<Vec<Value>> = serde_json::from_str(tests).unwrap();
+            // Single strings are comments
+            let tests: Vec<&Vec<Value>> = tests.iter().filter(|test| test.len() > 1).collect();
+            tests.iter().for_each(|test| {
+                let transaction = BitcoinTransaction::<N>::from_str(test[0].as_str().unwrap()).unwrap();
+                let script_code = hex::decode(test[1].as_str().unwrap()).unwrap();
+                let vin = test[2].as_u64().unwrap() as usize;
+                let hash_type = test[3].as_i64().unwrap() as i32 as u32;
+                let mut hash = transaction.legacy_signature_digest(vin, &script_code, hash_type).unwrap();
+                hash.reverse();
+                assert_eq!(test[4].as_str().unwrap(), hex::encode(hash), "{:?}", test);
+            });
+            tests.len()
+        }
+
+        #[test]
+        fn sighash_tests() {
+            assert_eq!(6, run_sighash_tests(SIGHASH_TESTS));
+        }
+
+        #[test]
+        fn bip143() {
+            let transaction = BitcoinTransaction::<N>::from_str(P2WPKH_TRANSACTION).unwrap();
+            let script_code = hex::decode(P2WPKH_SCRIPT_CODE).unwrap();
+            let preimage = transaction
+                .witness_v0_hash_preimage(1, &script_code, BitcoinAmount(600_000_000), SignatureHash::SIGHASH_ALL)
+                .unwrap();
+            assert_eq!("c37af31116d1b27caf68aae9e3ac82f1477929014d5b917657d0eb49478cb670", double_sha256(&preimage));
+
+            let transaction = BitcoinTransaction::<N>::from_str(P2SH_P2WSH_TRANSACTION).unwrap();
+            let witness_script = hex::decode(P2SH_P2WSH_WITNESS_SCRIPT).unwrap();
+            P2SH_P2WSH_SIGHASHES.iter().for_each(|(sighash, expected)| {
+                let preimage = transaction
+                    .witness_v0_hash_preimage(0, &witness_script, BitcoinAmount(987_654_321), *sighash)
+                    .unwrap();
+                assert_eq!(*expected, double_sha256(&preimage), "{}", sighash);
+            });
+        }
+
+        #[test]
+        fn sighash_single_bug() {
+            let transaction = BitcoinTransaction::<N>::from_str(SINGLE_BUG_TRANSACTION).unwrap();
+            let script_code = hex::decode(P2WPKH_SCRIPT_CODE).unwrap();
+            let mut one = [0u8; 32];
+            one[0] = 0x01;
+            for sighash in [SignatureHash::SIGHASH_SINGLE, SignatureHash::SIGHASH_SINGLE_SIGHASH_ANYONECANPAY] {
+                assert_eq!(one, transaction.legacy_signature_hash(1, &script_code, sighash).unwrap());
+                assert!(transaction.legacy_hash_preimage(1, &script_code, sighash).is_err());
+                // Input 0 has a matching output
+                assert_ne!(one, transaction.legacy_signature_hash(0, &script_code, sighash).unwrap());
+            }
+
+            // BIP-143 signs an empty hash of the outputs instead
+            SINGLE_BUG_SIGHASHES.iter().for_each(|(sighash, expected)| {
+                let preimage = transaction
+                    .witness_v0_hash_preimage(1, &script_code, BitcoinAmount(600_000_000), *sighash)
+                    .unwrap();
+                assert_eq!(*expected, double_sha256(&preimage), "{}", sighash);
+            });
+
+            // An input is never signed with SIGHASH_SINGLE without a matching output
+            let private_key = BitcoinPrivateKey::<N>::from_str(PRIVATE_KEY).unwrap();
+            let address = private_key.to_address(&BitcoinFormat::P2PKH).unwrap().to_string();
+            let mut transaction = BitcoinTransactionBuilder::<N>::new()
+                .input(BitcoinTransactionInputBuilder::new(TRANSACTION_ID, 0).address(&address).amount(BitcoinAmount(100_000)))
+                .input(
+                    BitcoinTransactionInputBuilder::new(OTHER_TRANSACTION_ID, 0)
+                        .address(&address)
+                        .amount(BitcoinAmount(100_000))
+                        .sighash(SignatureHash::SIGHASH_SINGLE),
+                )
+                .output(&address, BitcoinAmount(190_000))
+                .build()
+                .unwrap();
+            assert!(transaction.sign_with_private_key(&private_key).is_err());
+        }
+
+        #[test]
+        fn code_separators() {
+            let transaction = BitcoinTransaction::<N>::from_str(SINGLE_BUG_TRANSACTION).unwrap();
+            let script_code = hex::decode(P2WPKH_SCRIPT_CODE).unwrap();
+            let separated = [&script_code[..], &[Opcode::OP_CODESEPARATOR as u8], &script_code[..]].concat();
+            let sighash = SignatureHash::SIGHASH_ALL_SIGHASH_ANYONECANPAY;
+            let mut hash = transaction.legacy_signature_hash(0, &separated, sighash).unwrap();
+            hash.reverse();
+            assert_eq!("2874d741d1bf5b3e96202cfcb90a69b3eb341f3f5b960e9bc04eb75f5057d36b", hex::encode(hash));
+            assert_eq!(
+                transaction.legacy_hash_preimage(0, &[script_code.clone(), script_code.clone()].concat(), sighash).unwrap(),
+                transaction.legacy_hash_preimage(0, &separated, sighash).unwrap()
+            );
+            // The byte of OP_CODESEPARATOR pushed as data is signed
+            let push = [&[0x01, Opcode::OP_CODESEPARATOR as u8][..], &script_code[..]].concat();
+            let preimage = transaction.legacy_hash_preimage(0, &push, sighash).unwrap();
+            assert!(preimage.windows(push.len()).any(|window| window == &push[..]));
+        }
+
+        #[test]
+        fn from_byte() {
+            for byte in 0..=255u8 {
+                match SignatureHash::from_byte(byte) {
+                    Ok(sighash) => assert_eq!(byte, sighash as u8),
+                    Err(error) => {
+                        assert!(![0x00, 0x01, 0x02, 0x03, 0x81, 0x82, 0x83].contains(&byte));
+                        assert_eq!(TransactionError::InvalidSignatureHash(byte as u32).to_string(), error.to_string());
+                    }
+                }
+            }
+            assert_eq!(13, (0..=255u8).filter(|byte| SignatureHash::from_byte(*byte).is_ok()).count());
+        }
+
+        #[test]
+        fn invalid_sighash() {
+            let transaction = BitcoinTransaction::<N>::from_str(P2WPKH_TRANSACTION).unwrap();
+            let script_code = hex::decode(P2WPKH_SCRIPT_CODE).unwrap();
+            let amount = BitcoinAmount(600_000_000);
+            // SIGHASH_DEFAULT is reserved to Taproot and SIGHASH_FORKID to networks with a fork id
+            for sighash in [SignatureHash::SIGHASH_DEFAULT, SignatureHash::SIGHASH_ALL_SIGHASH_FORKID] {
+                assert!(transaction.legacy_hash_preimage(0, &script_code, sighash).is_err());
+                assert!(transaction.legacy_signature_hash(0, &script_code, sighash).is_err());
+                assert!(transaction.witness_v0_hash_preimage(1, &script_code, amount, sighash).is_err());
+            }
+
+            let transaction = BitcoinTransactionBuilder::<N>::new()
+                .input(p2wpkh_input(TRANSACTION_ID, SignatureHash::SIGHASH_ALL))
+                .output("1Fyxts6r24DpEieygQiNnWxUdb18ANa5p7", BitcoinAmount(90_000))
+                .build()
+                .unwrap();
+            assert!(transaction.segwit_hash_preimage(0, SignatureHash::SIGHASH_DEFAULT).is_err());
+            assert!(transaction.segwit_hash_preimage(0, SignatureHash::SIGHASH_NONE_SIGHASH_FORKID).is_err());
+            assert!(transaction.segwit_hash_preimage(1, SignatureHash::SIGHASH_ALL).is_err());
+        }
+
+        #[test]
+        fn anyone_can_pay() {
+            let private_key = BitcoinPrivateKey::<N>::from_str(PRIVATE_KEY).unwrap();
+            let output = BitcoinAddress::<N>::from_str("1Fyxts6r24DpEieygQiNnWxUdb18ANa5p7").unwrap();
+            let other_input = p2wpkh_input(OTHER_TRANSACTION_ID, SignatureHash::SIGHASH_ALL).build().unwrap();
+            let other_output = BitcoinTransactionOutput::new(&output, BitcoinAmount(50_000)).unwrap();
+
+            // (sighash, remains valid with another input, remains valid with another output)
+            let cases = [
+                (SignatureHash::SIGHASH_ALL, false, false),
+                (SignatureHash::SIGHASH_NONE, false, true),
+                (SignatureHash::SIGHASH_SINGLE, false, true),
+                (SignatureHash::SIGHASH_ALL_SIGHASH_ANYONECANPAY, true, false),
+                (SignatureHash::SIGHASH_NONE_SIGHASH_ANYONECANPAY, true, true),
+                (SignatureHash::SIGHASH_SINGLE_SIGHASH_ANYONECANPAY, true, true),
+            ];
+            for (sighash, other_inputs, other_outputs) in cases {
+                let mut transaction = BitcoinTransactionBuilder::<N>::new()
+                    .input(p2wpkh_input(TRANSACTION_ID, sighash))
+                    .output(&output.to_string(), BitcoinAmount(90_000))
+                    .build()
+                    .unwrap();
+                transaction.sign_with_private_key(&private_key).unwrap();
+                assert!(transaction.verify_input(0, VerificationFlags::STANDARD).is_ok(), "{}", sighash);
+
+                let mut with_input = transaction.clone();
+                with_input.parameters.inputs.push(other_input.clone());
+                assert_eq!(other_inputs, with_input.verify_input(0, VerificationFlags::STANDARD).is_ok(), "{}", sighash);
+
+                let mut with_output = transaction.clone();
+                with_output.parameters.outputs.push(other_output.clone());
+                assert_eq!(other_outputs, with_output.verify_input(0, VerificationFlags::STANDARD).is_ok(), "{}", sighash);
+            }
+        }
+    }
 }
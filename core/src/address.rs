@@ -1,4 +1,5 @@
 use crate::format::Format;
+use crate::network::NetworkError;
 use crate::private_key::{PrivateKey, PrivateKeyError};
 use crate::public_key::{PublicKey, PublicKeyError};
 use crate::no_std::*;
@@ -67,6 +68,9 @@ pub enum AddressError {
 
     #[error("{0}")]
     PublicKeyError(PublicKeyError),
+
+    #[error("{0} addresses commit to a script and cannot be derived from a single public key")]
+    ScriptRequired(String),
 }
 
 impl From<crate::no_std::io::Error> for AddressError {
@@ -87,6 +91,12 @@ impl From<&'static str> for AddressError {
     }
 }
 
+impl From<NetworkError> for AddressError {
+    fn from(error: NetworkError) -> Self {
+        AddressError::Crate("network", format!("{:?}", error))
+    }
+}
+
 impl From<PrivateKeyError> for AddressError {
     fn from(error: PrivateKeyError) -> Self {
         AddressError::PrivateKeyError(error)
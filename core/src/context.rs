@@ -0,0 +1,112 @@
+//! Injectable sources of time and randomness.
+//!
+//! Key generation and transaction construction never read the system clock or
+//! a thread-local RNG on their own when given a [`Context`], so the same clock
+//! and seeded RNG always produce byte-identical keys and transactions.
+
+use crate::private_key::{PrivateKey, PrivateKeyError};
+
+use rand::Rng;
+
+/// A source of the current time.
+pub trait Clock {
+    /// Returns the milliseconds elapsed since the Unix epoch.
+    fn now_millis(&self) -> i64;
+}
+
+impl<C: Clock + ?Sized> Clock for &C {
+    fn now_millis(&self) -> i64 {
+        (**self).now_millis()
+    }
+}
+
+/// The clock of the operating system.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SystemClock;
+
+#[cfg(feature = "std")]
+impl Clock for SystemClock {
+    fn now_millis(&self) -> i64 {
+        use std::time::{SystemTime, UNIX_EPOCH};
+
+        match SystemTime::now().duration_since(UNIX_EPOCH) {
+            Ok(elapsed) => elapsed.as_millis() as i64,
+            Err(error) => -(error.duration().as_millis() as i64),
+        }
+    }
+}
+
+/// A clock frozen at the given milliseconds since the Unix epoch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedClock(pub i64);
+
+impl Clock for FixedClock {
+    fn now_millis(&self) -> i64 {
+        self.0
+    }
+}
+
+/// Represents the clock and RNG used to generate keys and construct transactions.
+#[derive(Debug, Clone)]
+pub struct Context<C: Clock, R: Rng> {
+    clock: C,
+    rng: R,
+}
+
+impl<C: Clock, R: Rng> Context<C, R> {
+    /// Returns a context given a clock and an RNG, e.g. `FixedClock` and a seeded `StdRng`.
+    pub fn new(clock: C, rng: R) -> Self {
+        Self { clock, rng }
+    }
+
+    /// Returns the clock of the context.
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
+
+    /// Returns the RNG of the context.
+    pub fn rng(&mut self) -> &mut R {
+        &mut self.rng
+    }
+
+    /// Returns the current time of the context clock in milliseconds.
+    pub fn now_millis(&self) -> i64 {
+        self.clock.now_millis()
+    }
+
+    /// Returns a private key generated from the context RNG.
+    pub fn generate_private_key<P: PrivateKey>(&mut self) -> Result<P, PrivateKeyError> {
+        P::new(&mut self.rng)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::mock::StepRng;
+
+    #[test]
+    fn fixed_clock() {
+        let clock = &FixedClock(1_656_000_000_000);
+        assert_eq!(1_656_000_000_000, clock.now_millis());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn system_clock() {
+        // 2022-01-01T00:00:00Z
+        assert!(SystemClock.now_millis() > 1_640_995_200_000);
+    }
+
+    #[test]
+    fn context_is_reproducible() {
+        let mut first = Context::new(FixedClock(42), StepRng::new(7, 3));
+        let mut second = Context::new(FixedClock(42), StepRng::new(7, 3));
+        assert_eq!(first.now_millis(), second.now_millis());
+
+        let first_bytes: [u8; 32] = first.rng().gen();
+        let second_bytes: [u8; 32] = second.rng().gen();
+        assert_eq!(first_bytes, second_bytes);
+    }
+}
@@ -1,5 +1,6 @@
 use crate::AddressError;
 use crate::AmountError;
+use crate::ExtendedKeyError;
 use crate::FormatError;
 use crate::PrivateKeyError;
 use crate::PublicKeyError;
@@ -25,6 +26,9 @@ pub enum Error {
     #[error("Invalid PublickKey: {0:}")]
     InvalidPublickKey(#[from] PublicKeyError),
 
+    #[error("Invalid Extended Key: {0:}")]
+    InvalidExtendedKey(#[from] ExtendedKeyError),
+
     #[error("Invalid Format: {0:}")]
     InvalidFormat(#[from] FormatError),
 
@@ -0,0 +1,506 @@
+//!
+//! Extended Public Key
+//!
+//! This module contains the BIP32 extended public key and the non-hardened (public) child key
+//! derivation used to derive watch-only addresses without access to any private key.
+//! https://github.com/bitcoin/bips/blob/master/bip-0032.mediawiki
+//!
+
+use crate::address::{Address, AddressError};
+use crate::utilities::crypto::{checksum, hash160, hmac_sha512};
+use crate::no_std::*;
+use core::{
+    fmt::{self, Display},
+    ops::Range,
+    str::FromStr,
+};
+
+use base58::{FromBase58, ToBase58};
+
+/// The first index of the hardened child keys.
+pub const HARDENED_OFFSET: u32 = 0x8000_0000;
+
+/// The version bytes of the extended private keys of BIP32, BIP49 and BIP84 (xprv, yprv, Yprv, zprv, Zprv
+/// and their testnet counterparts tprv, uprv, Uprv, vprv, Vprv), rejected where a public key is expected.
+const EXTENDED_PRIVATE_KEY_VERSIONS: [[u8; 4]; 10] = [
+    [0x04, 0x88, 0xad, 0xe4],
+    [0x04, 0x9d, 0x78, 0x78],
+    [0x02, 0x95, 0xb0, 0x05],
+    [0x04, 0xb2, 0x43, 0x0c],
+    [0x02, 0xaa, 0x7a, 0x99],
+    [0x04, 0x35, 0x83, 0x94],
+    [0x04, 0x4a, 0x4e, 0x28],
+    [0x02, 0x42, 0x85, 0xb5],
+    [0x04, 0x5f, 0x18, 0xbc],
+    [0x02, 0x57, 0x50, 0x48],
+];
+
+/// Represents a child index in a BIP32 derivation path
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ChildIndex {
+    /// A non-hardened index, derivable from the extended public key
+    Normal(u32),
+    /// A hardened index, derivable only from the extended private key
+    Hardened(u32),
+}
+
+impl ChildIndex {
+    /// Returns the child index of the given 32-bit child number.
+    pub fn from_child_number(child_number: u32) -> Self {
+        match child_number & HARDENED_OFFSET {
+            0 => ChildIndex::Normal(child_number),
+            _ => ChildIndex::Hardened(child_number ^ HARDENED_OFFSET),
+        }
+    }
+
+    /// Returns the 32-bit child number of the child index.
+    pub fn to_child_number(&self) -> u32 {
+        match self {
+            ChildIndex::Normal(index) => *index,
+            ChildIndex::Hardened(index) => index | HARDENED_OFFSET,
+        }
+    }
+
+    /// Returns `true` if the child index is hardened.
+    pub fn is_hardened(&self) -> bool {
+        match self {
+            ChildIndex::Normal(_) => false,
+            ChildIndex::Hardened(_) => true,
+        }
+    }
+}
+
+impl FromStr for ChildIndex {
+    type Err = ExtendedKeyError;
+
+    fn from_str(index: &str) -> Result<Self, Self::Err> {
+        let (number, hardened) = match index.strip_suffix(|c| c == '\'' || c == 'h' || c == 'H') {
+            Some(number) => (number, true),
+            None => (index, false),
+        };
+
+        let number = number
+            .parse::<u32>()
+            .map_err(|_| ExtendedKeyError::InvalidChildIndex(index.into()))?;
+        if number >= HARDENED_OFFSET {
+            return Err(ExtendedKeyError::InvalidChildIndex(index.into()));
+        }
+
+        match hardened {
+            true => Ok(ChildIndex::Hardened(number)),
+            false => Ok(ChildIndex::Normal(number)),
+        }
+    }
+}
+
+impl Display for ChildIndex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ChildIndex::Normal(index) => write!(f, "{}", index),
+            ChildIndex::Hardened(index) => write!(f, "{}'", index),
+        }
+    }
+}
+
+/// Represents a BIP32 derivation path, e.g. m/0/1
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct DerivationPath(Vec<ChildIndex>);
+
+impl DerivationPath {
+    /// Returns a derivation path given its child indexes.
+    pub fn new(path: Vec<ChildIndex>) -> Self {
+        Self(path)
+    }
+
+    /// Returns the child indexes of the derivation path.
+    pub fn indexes(&self) -> &[ChildIndex] {
+        &self.0
+    }
+
+    /// Returns `true` if the path contains a hardened child index.
+    pub fn is_hardened(&self) -> bool {
+        self.0.iter().any(|index| index.is_hardened())
+    }
+}
+
+impl FromStr for DerivationPath {
+    type Err = ExtendedKeyError;
+
+    /// Returns a derivation path given its string form, with or without the leading "m".
+    fn from_str(path: &str) -> Result<Self, Self::Err> {
+        let mut parts = path.split('/').peekable();
+        if parts.peek() == Some(&"m") {
+            parts.next();
+        }
+
+        let path = parts
+            .map(|index| match index.is_empty() {
+                true => Err(ExtendedKeyError::InvalidDerivationPath(path.into())),
+                false => ChildIndex::from_str(index),
+            })
+            .collect::<Result<Vec<ChildIndex>, ExtendedKeyError>>()?;
+
+        Ok(Self(path))
+    }
+}
+
+impl Display for DerivationPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "m")?;
+        for index in &self.0 {
+            write!(f, "/{}", index)?;
+        }
+        Ok(())
+    }
+}
+
+/// Represents a BIP32 extended public key, e.g. xpub, ypub or zpub
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtendedPublicKey {
+    /// The version bytes (4 bytes), e.g. 0x0488B21E for xpub
+    version: [u8; 4],
+    /// The depth in the derivation tree (1 byte)
+    depth: u8,
+    /// The fingerprint of the parent key (4 bytes)
+    parent_fingerprint: [u8; 4],
+    /// The child number of this key (4 bytes)
+    child_number: u32,
+    /// The chain code (32 bytes)
+    chain_code: [u8; 32],
+    /// The secp256k1 public key (33 bytes compressed)
+    public_key: libsecp256k1::PublicKey,
+}
+
+impl ExtendedPublicKey {
+    /// Returns the version bytes of the extended public key.
+    pub fn version(&self) -> [u8; 4] {
+        self.version
+    }
+
+    /// Returns the depth of the extended public key.
+    pub fn depth(&self) -> u8 {
+        self.depth
+    }
+
+    /// Returns the fingerprint of the parent key.
+    pub fn parent_fingerprint(&self) -> [u8; 4] {
+        self.parent_fingerprint
+    }
+
+    /// Returns the child number of the extended public key.
+    pub fn child_number(&self) -> u32 {
+        self.child_number
+    }
+
+    /// Returns the chain code of the extended public key.
+    pub fn chain_code(&self) -> [u8; 32] {
+        self.chain_code
+    }
+
+    /// Returns the secp256k1 public key of the extended public key.
+    pub fn to_secp256k1_public_key(&self) -> libsecp256k1::PublicKey {
+        self.public_key
+    }
+
+    /// Returns the fingerprint (the first 4 bytes of the hash160 of the public key).
+    pub fn fingerprint(&self) -> [u8; 4] {
+        let mut fingerprint = [0u8; 4];
+        fingerprint.copy_from_slice(&hash160(&self.public_key.serialize_compressed())[0..4]);
+        fingerprint
+    }
+
+    /// Returns the non-hardened child of the extended public key at the given index.
+    pub fn derive_child(&self, index: ChildIndex) -> Result<Self, ExtendedKeyError> {
+        if index.is_hardened() {
+            return Err(ExtendedKeyError::HardenedDerivation(index.to_string()));
+        }
+        if self.depth == u8::MAX {
+            return Err(ExtendedKeyError::MaximumDepthReached);
+        }
+
+        let mut data = self.public_key.serialize_compressed().to_vec();
+        data.extend(&index.to_child_number().to_be_bytes());
+        let hmac = hmac_sha512(&self.chain_code, &data);
+
+        // The derived key is invalid if I_L >= n or the resulting point is at infinity,
+        // in which case the caller should proceed with the next index.
+        let tweak = libsecp256k1::SecretKey::parse_slice(&hmac[0..32])
+            .map_err(|_| ExtendedKeyError::InvalidChildKey(index.to_child_number()))?;
+        let mut public_key = self.public_key;
+        public_key
+            .tweak_add_assign(&tweak)
+            .map_err(|_| ExtendedKeyError::InvalidChildKey(index.to_child_number()))?;
+
+        let mut chain_code = [0u8; 32];
+        chain_code.copy_from_slice(&hmac[32..]);
+
+        Ok(Self {
+            version: self.version,
+            depth: self.depth + 1,
+            parent_fingerprint: self.fingerprint(),
+            child_number: index.to_child_number(),
+            chain_code,
+            public_key,
+        })
+    }
+
+    /// Returns the descendant of the extended public key at the given non-hardened path.
+    pub fn derive_path(&self, path: &DerivationPath) -> Result<Self, ExtendedKeyError> {
+        let mut extended_public_key = self.clone();
+        for index in path.indexes() {
+            extended_public_key = extended_public_key.derive_child(*index)?;
+        }
+        Ok(extended_public_key)
+    }
+
+    /// Returns the children at the given range of indexes below the given path.
+    /// The path is derived once and each child costs a single derivation step,
+    /// which makes scanning a gap limit linear in the size of the range.
+    /// As in BIP32, an index whose child key is invalid is skipped, so the children
+    /// may be fewer than the indexes; their child numbers identify them.
+    pub fn derive_range(&self, path: &DerivationPath, range: Range<u32>) -> Result<Vec<Self>, ExtendedKeyError> {
+        let parent = self.derive_path(path)?;
+        range
+            .filter_map(|index| match parent.derive_child(ChildIndex::from_child_number(index)) {
+                Err(ExtendedKeyError::InvalidChildKey(_)) => None,
+                child => Some(child),
+            })
+            .collect()
+    }
+}
+
+impl FromStr for ExtendedPublicKey {
+    type Err = ExtendedKeyError;
+
+    /// Returns an extended public key given its base58check encoding.
+    fn from_str(extended_public_key: &str) -> Result<Self, Self::Err> {
+        let data = extended_public_key.from_base58()?;
+        if data.len() != 82 {
+            return Err(ExtendedKeyError::InvalidByteLength(data.len()));
+        }
+
+        let expected = &data[78..82];
+        let checksum = &checksum(&data[0..78])[0..4];
+        if *expected != *checksum {
+            return Err(ExtendedKeyError::InvalidChecksum(expected.to_base58(), checksum.to_base58()));
+        }
+
+        let mut version = [0u8; 4];
+        version.copy_from_slice(&data[0..4]);
+        if EXTENDED_PRIVATE_KEY_VERSIONS.contains(&version) {
+            return Err(ExtendedKeyError::InvalidVersionBytes(version.to_vec()));
+        }
+        let mut parent_fingerprint = [0u8; 4];
+        parent_fingerprint.copy_from_slice(&data[5..9]);
+        let mut child_number = [0u8; 4];
+        child_number.copy_from_slice(&data[9..13]);
+        let mut chain_code = [0u8; 32];
+        chain_code.copy_from_slice(&data[13..45]);
+
+        // Only compressed public keys are valid; extended private keys start with 0x00 here.
+        let public_key = libsecp256k1::PublicKey::parse_slice(
+            &data[45..78],
+            Some(libsecp256k1::PublicKeyFormat::Compressed),
+        )?;
+
+        Ok(Self {
+            version,
+            depth: data[4],
+            parent_fingerprint,
+            child_number: u32::from_be_bytes(child_number),
+            chain_code,
+            public_key,
+        })
+    }
+}
+
+impl Display for ExtendedPublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut data = Vec::with_capacity(82);
+        data.extend(&self.version);
+        data.push(self.depth);
+        data.extend(&self.parent_fingerprint);
+        data.extend(&self.child_number.to_be_bytes());
+        data.extend(&self.chain_code);
+        data.extend(&self.public_key.serialize_compressed()[..]);
+
+        let checksum = checksum(&data);
+        data.extend(&checksum[0..4]);
+        write!(f, "{}", data.to_base58())
+    }
+}
+
+/// The interface for an address derivable from a secp256k1 extended public key (watch-only).
+pub trait WatchOnlyAddress: Address {
+    /// Returns the address corresponding to the given secp256k1 public key.
+    fn from_secp256k1_public_key(
+        public_key: &libsecp256k1::PublicKey,
+        format: &Self::Format,
+    ) -> Result<Self, AddressError>;
+
+    /// Returns an error if addresses cannot be derived from the extended public key,
+    /// e.g. when its version belongs to another network. Accepts every key by default.
+    fn validate_extended_public_key(_extended_public_key: &ExtendedPublicKey) -> Result<(), AddressError> {
+        Ok(())
+    }
+
+    /// Returns the address at the given non-hardened path below the extended public key.
+    fn from_extended_public_key(
+        extended_public_key: &ExtendedPublicKey,
+        path: &DerivationPath,
+        format: &Self::Format,
+    ) -> Result<Self, AddressError> {
+        Self::validate_extended_public_key(extended_public_key)?;
+        let child = extended_public_key.derive_path(path)?;
+        Self::from_secp256k1_public_key(&child.to_secp256k1_public_key(), format)
+    }
+
+    /// Returns the addresses at the given range of indexes below the path of the extended public key,
+    /// skipping the indexes whose child key is invalid.
+    fn derive_addresses(
+        extended_public_key: &ExtendedPublicKey,
+        path: &DerivationPath,
+        range: Range<u32>,
+        format: &Self::Format,
+    ) -> Result<Vec<Self>, AddressError> {
+        Self::validate_extended_public_key(extended_public_key)?;
+        extended_public_key
+            .derive_range(path, range)?
+            .iter()
+            .map(|child| Self::from_secp256k1_public_key(&child.to_secp256k1_public_key(), format))
+            .collect()
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ExtendedKeyError {
+    #[error("{0}: {1}")]
+    Crate(&'static str, String),
+
+    #[error("cannot derive the hardened child {0} from an extended public key")]
+    HardenedDerivation(String),
+
+    #[error("invalid byte length: {0}")]
+    InvalidByteLength(usize),
+
+    #[error("invalid extended key checksum: {{ expected: {0}, found: {1} }}")]
+    InvalidChecksum(String, String),
+
+    #[error("invalid child index: {0}")]
+    InvalidChildIndex(String),
+
+    #[error("the child key at index {0} is invalid, proceed with the next index")]
+    InvalidChildKey(u32),
+
+    #[error("invalid derivation path: {0}")]
+    InvalidDerivationPath(String),
+
+    #[error("invalid version bytes: {0:?}")]
+    InvalidVersionBytes(Vec<u8>),
+
+    #[error("maximum derivation depth reached")]
+    MaximumDepthReached,
+}
+
+impl From<base58::FromBase58Error> for ExtendedKeyError {
+    fn from(error: base58::FromBase58Error) -> Self {
+        ExtendedKeyError::Crate("base58", format!("{:?}", error))
+    }
+}
+
+impl From<libsecp256k1::Error> for ExtendedKeyError {
+    fn from(error: libsecp256k1::Error) -> Self {
+        ExtendedKeyError::Crate("libsecp256k1", format!("{:?}", error))
+    }
+}
+
+impl From<ExtendedKeyError> for AddressError {
+    fn from(error: ExtendedKeyError) -> Self {
+        AddressError::Crate("ExtendedPublicKey", format!("{:?}", error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // https://github.com/bitcoin/bips/blob/master/bip-0032.mediawiki#test-vector-1
+    const PUBLIC_DERIVATIONS: [(&str, &str, &str); 2] = [
+        (
+            "xpub68Gmy5EdvgibQVfPdqkBBCHxA5htiqg55crXYuXoQRKfDBFA1WEjWgP6LHhwBZeNK1VTsfTFUHCdrfp1bgwQ9xv5ski8PX9rL2dZXvgGDnw",
+            "m/1",
+            "xpub6ASuArnXKPbfEwhqN6e3mwBcDTgzisQN1wXN9BJcM47sSikHjJf3UFHKkNAWbWMiGj7Wf5uMash7SyYq527Hqck2AxYysAA7xmALppuCkwQ",
+        ),
+        (
+            "xpub6D4BDPcP2GT577Vvch3R8wDkScZWzQzMMUm3PWbmWvVJrZwQY4VUNgqFJPMM3No2dFDFGTsxxpG5uJh7n7epu4trkrX7x7DogT5Uv6fcLW5",
+            "m/2/1000000000",
+            "xpub6H1LXWLaKsWFhvm6RVpEL9P4KfRZSW7abD2ttkWP3SSQvnyA8FSVqNTEcYFgJS2UaFcxupHiYkro49S8yGasTvXEYBVPamhGW6cFJodrTHy",
+        ),
+    ];
+
+    #[test]
+    fn from_str() {
+        PUBLIC_DERIVATIONS.iter().for_each(|(parent, _, child)| {
+            assert_eq!(*parent, ExtendedPublicKey::from_str(parent).unwrap().to_string());
+            assert_eq!(*child, ExtendedPublicKey::from_str(child).unwrap().to_string());
+        });
+    }
+
+    #[test]
+    fn derive_path() {
+        PUBLIC_DERIVATIONS.iter().for_each(|(parent, path, child)| {
+            let parent = ExtendedPublicKey::from_str(parent).unwrap();
+            let path = DerivationPath::from_str(path).unwrap();
+            assert_eq!(*child, parent.derive_path(&path).unwrap().to_string());
+        });
+    }
+
+    #[test]
+    fn derive_range() {
+        let (parent, _, _) = PUBLIC_DERIVATIONS[0];
+        let parent = ExtendedPublicKey::from_str(parent).unwrap();
+        let children = parent.derive_range(&DerivationPath::default(), 0..5).unwrap();
+        assert_eq!(5, children.len());
+        for (index, child) in children.iter().enumerate() {
+            let expected = parent.derive_child(ChildIndex::Normal(index as u32)).unwrap();
+            assert_eq!(expected, *child);
+        }
+        assert_eq!(PUBLIC_DERIVATIONS[0].2, children[1].to_string());
+        assert_eq!(vec![3, 4], children[3..].iter().map(|child| child.child_number()).collect::<Vec<_>>());
+
+        // Hardened indexes are not skipped like invalid child keys but fail the range
+        let range = HARDENED_OFFSET - 1..HARDENED_OFFSET + 1;
+        assert!(matches!(
+            parent.derive_range(&DerivationPath::default(), range),
+            Err(ExtendedKeyError::HardenedDerivation(_))
+        ));
+    }
+
+    #[test]
+    fn invalid_version_bytes() {
+        // The master extended private key of BIP32 test vector 1
+        let xprv = "xprv9s21ZrQH143K3QTDL4LXw2F7HEK3wJUD2nW2nRk4stbPy6cq3jPPqjiChkVvvNKmPGJxWUtg6LnF5kejMRNNU3TGtRBeJgk33yuGBxrMPHi";
+        assert!(matches!(
+            ExtendedPublicKey::from_str(xprv),
+            Err(ExtendedKeyError::InvalidVersionBytes(version)) if version == vec![0x04, 0x88, 0xad, 0xe4]
+        ));
+    }
+
+    #[test]
+    fn derivation_path() {
+        let path = DerivationPath::from_str("m/44'/0h/0H/1/25").unwrap();
+        assert_eq!("m/44'/0'/0'/1/25", path.to_string());
+        assert!(path.is_hardened());
+        assert_eq!(DerivationPath::from_str("0/1").unwrap(), DerivationPath::from_str("m/0/1").unwrap());
+        assert!(DerivationPath::from_str("m//1").is_err());
+        assert!(DerivationPath::from_str("m/2147483648").is_err());
+    }
+
+    #[test]
+    fn hardened_derivation() {
+        let (parent, _, _) = PUBLIC_DERIVATIONS[0];
+        let parent = ExtendedPublicKey::from_str(parent).unwrap();
+        let path = DerivationPath::from_str("m/0'").unwrap();
+        assert!(parent.derive_path(&path).is_err());
+    }
+}
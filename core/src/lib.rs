@@ -23,6 +23,12 @@ pub use self::address::*;
 pub mod amount;
 pub use self::amount::*;
 
+pub mod context;
+pub use self::context::*;
+
+pub mod extended_public_key;
+pub use self::extended_public_key::*;
+
 
 pub mod format;
 pub use self::format::*;
@@ -34,6 +40,9 @@ pub use self::network::*;
 pub mod private_key;
 pub use self::private_key::*;
 
+pub mod private_key_codec;
+pub use self::private_key_codec::*;
+
 pub mod public_key;
 pub use self::public_key::*;
 
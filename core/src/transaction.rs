@@ -49,6 +49,36 @@ pub trait Transaction: Clone + Send + Sync + 'static {
 
     /// Returns the transaction id.
     fn to_transaction_id(&self) -> Result<Self::TransactionId, TransactionError>;
+
+    /// Returns the estimated size and fee units of the transaction once signed.
+    /// Unsigned parts are sized with placeholder signatures, so this can be used before signing.
+    fn estimate(&self) -> Result<TransactionEstimate, TransactionError>;
+
+    /// Returns the estimated fee given a fee rate per fee unit,
+    /// e.g. satoshi per virtual byte or wei per gas.
+    fn estimate_fee(&self, fee_rate: u64) -> Result<u64, TransactionError> {
+        let estimate = self.estimate()?;
+        estimate.fee(fee_rate).ok_or_else(|| {
+            TransactionError::InvalidFee(format!("{} units at a fee rate of {} overflows", estimate.units, fee_rate))
+        })
+    }
+}
+
+/// Represents the estimated resources a transaction consumes once signed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TransactionEstimate {
+    /// The size of the signed transaction in bytes
+    pub size: u64,
+    /// The units the fee rate is charged for, i.e. virtual bytes (Bitcoin),
+    /// gas (Ethereum, Filecoin) or bandwidth bytes (Tron)
+    pub units: u64,
+}
+
+impl TransactionEstimate {
+    /// Returns the fee given a fee rate per unit, or `None` on overflow.
+    pub fn fee(&self, fee_rate: u64) -> Option<u64> {
+        self.units.checked_mul(fee_rate)
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -65,9 +95,15 @@ pub enum TransactionError {
     #[error("{0}: {1}")]
     Crate(&'static str, String),
 
+    #[error("duplicate outpoint {0}:{1}")]
+    DuplicateOutpoint(String, u32),
+
     #[error("Failed note decryption for enc_cyphertext: {0}")]
     FailedNoteDecryption(String),
 
+    #[error("insufficient funds: {{ inputs: {0}, outputs: {1} }}")]
+    InsufficientFunds(String, String),
+
     #[error("invalid binding signature for the transaction")]
     InvalidBindingSig(),
 
@@ -77,15 +113,33 @@ pub enum TransactionError {
     #[error("invalid ephemeral key {0}")]
     InvalidEphemeralKey(String),
 
+    #[error("invalid expiration: {0}")]
+    InvalidExpiration(String),
+
+    #[error("invalid fee: {0}")]
+    InvalidFee(String),
+
+    #[error("invalid gas limit: {0}")]
+    InvalidGasLimit(String),
+
     #[error("insufficient information to craft transaction. missing: {0}")]
     InvalidInputs(String),
 
+    #[error("invalid network: {{ expected: {0}, found: {1} }}")]
+    InvalidNetwork(String, String),
+
     #[error("invalid output address: {0}")]
     InvalidOutputAddress(String),
 
     #[error("invalid ouptut description for address: {0}")]
     InvalidOutputDescription(String),
 
+    #[error("invalid PSBT: {0}")]
+    InvalidPsbt(String),
+
+    #[error("invalid reference block: {0}")]
+    InvalidRefBlock(String),
+
     #[error("invalid transaction RLP length: expected - 9, found - {0}")]
     InvalidRlpLength(usize),
 
@@ -95,6 +149,9 @@ pub enum TransactionError {
     #[error("invalid segwit flag: {0}")]
     InvalidSegwitFlag(usize),
 
+    #[error("invalid signature hash type: {0:#x}")]
+    InvalidSignatureHash(u32),
+
     #[error("invalid spend description for address")]
     InvalidSpendDescription,
 
@@ -113,6 +170,9 @@ pub enum TransactionError {
     #[error("missing diversifier, check that the address is a Sapling address")]
     MissingDiversifier,
 
+    #[error("missing transaction field: {0}")]
+    MissingField(&'static str),
+
     #[error("missing outpoint address")]
     MissingOutpointAddress,
 
@@ -168,6 +228,12 @@ impl From<PrivateKeyError> for TransactionError {
     }
 }
 
+impl From<base64::DecodeError> for TransactionError {
+    fn from(error: base64::DecodeError) -> Self {
+        TransactionError::Crate("base64", format!("{:?}", error))
+    }
+}
+
 impl From<base58::FromBase58Error> for TransactionError {
     fn from(error: base58::FromBase58Error) -> Self {
         TransactionError::Crate("base58", format!("{:?}", error))
@@ -218,11 +284,18 @@ impl From<serde_json::error::Error> for TransactionError {
 
 #[cfg(test)]
 mod tests{
-    use crate::func_selector;
+    use crate::{func_selector, TransactionEstimate};
 
     #[test]
     fn test_func_selector(){
         let selector = func_selector("transfer(address,uint256)");
         assert_eq!("a9059cbb",hex::encode(selector));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_estimate_fee(){
+        let estimate = TransactionEstimate { size: 225, units: 141 };
+        assert_eq!(Some(1410), estimate.fee(10));
+        assert_eq!(None, estimate.fee(u64::MAX));
+    }
+}
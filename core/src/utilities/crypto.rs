@@ -1,4 +1,4 @@
-use sha2::{Digest, Sha256};
+use sha2::{Digest, Sha256, Sha512};
 use sha3::Keccak256;
 use ripemd::Ripemd160;
 
@@ -55,6 +55,35 @@ pub fn hash160(bytes: &[u8]) -> Vec<u8> {
     Ripemd160::digest(&Sha256::digest(&bytes)).to_vec()
 }
 
+/// Returns the HMAC-SHA512 of the given message under the given key.
+/// https://tools.ietf.org/html/rfc2104
+pub fn hmac_sha512(key: &[u8], message: &[u8]) -> [u8; 64] {
+    const BLOCK_SIZE: usize = 128;
+
+    let mut block = [0u8; BLOCK_SIZE];
+    if key.len() > BLOCK_SIZE {
+        block[..64].copy_from_slice(&Sha512::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let mut inner_pad = [0x36u8; BLOCK_SIZE];
+    let mut outer_pad = [0x5cu8; BLOCK_SIZE];
+    for i in 0..BLOCK_SIZE {
+        inner_pad[i] ^= block[i];
+        outer_pad[i] ^= block[i];
+    }
+
+    let mut inner = Sha512::new();
+    inner.update(inner_pad);
+    inner.update(message);
+
+    let mut outer = Sha512::new();
+    outer.update(outer_pad);
+    outer.update(inner.finalize());
+    outer.finalize().into()
+}
+
 /// Length of the checksum hash for string encodings.
 pub const CHECKSUM_HASH_LEN: usize = 4;
 
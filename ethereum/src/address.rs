@@ -1,7 +1,7 @@
 use crate::format::EthereumFormat;
 use crate::private_key::EthereumPrivateKey;
 use crate::public_key::EthereumPublicKey;
-use chainlib_core::{to_hex_string, Address, PrivateKey, Error, AddressError};
+use chainlib_core::{to_hex_string, Address, PrivateKey, Error, AddressError, WatchOnlyAddress, libsecp256k1};
 
 use core::{convert::TryFrom, fmt, str::FromStr};
 use regex::Regex;
@@ -30,6 +30,13 @@ impl Address for EthereumAddress {
     }
 }
 
+impl WatchOnlyAddress for EthereumAddress {
+    /// Returns the address corresponding to the given secp256k1 public key.
+    fn from_secp256k1_public_key(public_key: &libsecp256k1::PublicKey, _: &Self::Format) -> Result<Self, AddressError> {
+        Ok(Self::checksum_address(&EthereumPublicKey::from_secp256k1_public_key(*public_key)))
+    }
+}
+
 impl EthereumAddress {
     /// Returns the checksum address given a public key.
     /// Adheres to EIP-55 (https://eips.ethereum.org/EIPS/eip-55).
@@ -183,6 +190,33 @@ mod tests {
         }
     }
 
+    mod watch_only {
+        use super::*;
+        use chainlib_core::{DerivationPath, ExtendedPublicKey};
+
+        // BIP-44 account m/44'/60'/0' of the "abandon ... about" mnemonic
+        const XPUB: &str = "xpub6DCoCpSuQZB2jawqnGMEPS63ePKWkwWPH4TU45Q7LPXWuNd8TMtVxRrgjtEshuqpK3mdhaWHPFsBngh5GFZaM6si3yZdUsT8ddYM3PwnATt";
+
+        #[test]
+        fn from_extended_public_key() {
+            let extended_public_key = ExtendedPublicKey::from_str(XPUB).unwrap();
+            let path = DerivationPath::from_str("m/0/0").unwrap();
+            let address =
+                EthereumAddress::from_extended_public_key(&extended_public_key, &path, &EthereumFormat::Standard).unwrap();
+            test_to_str("0x9858EfFD232B4033E47d90003D41EC34EcaEda94", &address);
+        }
+
+        #[test]
+        fn derive_addresses() {
+            let extended_public_key = ExtendedPublicKey::from_str(XPUB).unwrap();
+            let path = DerivationPath::from_str("m/0").unwrap();
+            let addresses =
+                EthereumAddress::derive_addresses(&extended_public_key, &path, 0..10, &EthereumFormat::Standard).unwrap();
+            assert_eq!(10, addresses.len());
+            test_to_str("0x9858EfFD232B4033E47d90003D41EC34EcaEda94", &addresses[0]);
+        }
+    }
+
     #[test]
     fn test_checksum_address_invalid() {
         // Mismatched keypair
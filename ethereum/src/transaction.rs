@@ -4,7 +4,7 @@ use crate::format::EthereumFormat;
 use crate::network::EthereumNetwork;
 use crate::private_key::EthereumPrivateKey;
 use crate::public_key::EthereumPublicKey;
-use chainlib_core::{PublicKey, Transaction, TransactionId,libsecp256k1,hex, Error, TransactionError};
+use chainlib_core::{PublicKey, Transaction, TransactionEstimate, TransactionId,libsecp256k1,hex, AmountError, Error, TransactionError};
 use core::{fmt, marker::PhantomData, str::FromStr};
 use chainlib_core::ethereum_types::U256;
 use rlp::{decode_list, RlpStream};
@@ -24,6 +24,32 @@ pub fn to_bytes(value: u32) -> Result<Vec<u8>, TransactionError> {
     }
 }
 
+/// The additional gas of a contract creation (EIP-2)
+const CONTRACT_CREATION_GAS: u64 = 32_000;
+/// The gas per zero byte of calldata
+const ZERO_BYTE_GAS: u64 = 4;
+/// The gas per non-zero byte of calldata (EIP-2028)
+const NON_ZERO_BYTE_GAS: u64 = 16;
+/// The gas per 32-byte word of contract creation code (EIP-3860)
+const INITCODE_WORD_GAS: u64 = 2;
+/// The gas per address of an access list (EIP-2930)
+const ACCESS_LIST_ADDRESS_GAS: u64 = 2_400;
+/// The gas per storage key of an access list (EIP-2930)
+const ACCESS_LIST_STORAGE_KEY_GAS: u64 = 1_900;
+
+/// Returns the intrinsic gas of a transaction, i.e. the gas charged before any execution
+/// for its calldata, contract creation and access list entries.
+pub fn intrinsic_gas(data: &[u8], contract_creation: bool, access_list_addresses: u64, access_list_storage_keys: u64) -> u64 {
+    let zero_bytes = data.iter().filter(|byte| **byte == 0).count() as u64;
+    let non_zero_bytes = data.len() as u64 - zero_bytes;
+
+    let mut gas = MIN_GAS_LIMIT + zero_bytes * ZERO_BYTE_GAS + non_zero_bytes * NON_ZERO_BYTE_GAS;
+    if contract_creation {
+        gas += CONTRACT_CREATION_GAS + (data.len() as u64).div_ceil(32) * INITCODE_WORD_GAS;
+    }
+    gas + access_list_addresses * ACCESS_LIST_ADDRESS_GAS + access_list_storage_keys * ACCESS_LIST_STORAGE_KEY_GAS
+}
+
 pub fn u256_to_bytes(value: &U256) -> Result<Vec<u8>, Error> {
     let mut bytes : Vec<u8> = vec![];
     value.to_big_endian(&mut bytes);
@@ -111,6 +137,8 @@ pub struct EthereumTransaction<N: EthereumNetwork> {
     parameters: EthereumTransactionParameters,
     /// The transaction signature
     signature: Option<EthereumTransactionSignature>,
+    /// Whether the transaction creates a contract, encoding no receiver
+    contract_creation: bool,
     _network: PhantomData<N>,
 }
 
@@ -128,6 +156,7 @@ impl<N: EthereumNetwork> Transaction for EthereumTransaction<N> {
             sender: None,
             parameters: parameters.clone(),
             signature: None,
+            contract_creation: false,
             _network: PhantomData,
         })
     }
@@ -180,7 +209,10 @@ impl<N: EthereumNetwork> Transaction for EthereumTransaction<N> {
         }
 
         let parameters = EthereumTransactionParameters {
-            receiver: EthereumAddress::from_str(&hex::encode(&list[3]))?,
+            receiver: match list[3].is_empty() {
+                true => EthereumAddress::from_str(CONTRACT_CREATION_RECEIVER)?,
+                false => EthereumAddress::from_str(&hex::encode(&list[3]))?,
+            },
             amount: match list[4].is_empty() {
                 true => EthereumAmount::from_u256(U256::zero()),
                 false => EthereumAmount::from_u256(U256::from(list[4].as_slice())),
@@ -199,6 +231,7 @@ impl<N: EthereumNetwork> Transaction for EthereumTransaction<N> {
             },
             data: list[5].clone(),
         };
+        let contract_creation = list[3].is_empty();
 
         match list[7].is_empty() && list[8].is_empty() {
             true => {
@@ -207,6 +240,7 @@ impl<N: EthereumNetwork> Transaction for EthereumTransaction<N> {
                     sender: None,
                     parameters,
                     signature: None,
+                    contract_creation,
                     _network: PhantomData,
                 })
             }
@@ -221,6 +255,7 @@ impl<N: EthereumNetwork> Transaction for EthereumTransaction<N> {
                     sender: None,
                     parameters: parameters.clone(),
                     signature: None,
+                    contract_creation,
                     _network: PhantomData,
                 };
                 let message = libsecp256k1::Message::parse_slice(&raw_transaction.to_transaction_id()?.txid)?;
@@ -238,6 +273,7 @@ impl<N: EthereumNetwork> Transaction for EthereumTransaction<N> {
                         r: list[7].clone(),
                         s: list[8].clone(),
                     }),
+                    contract_creation,
                     _network: PhantomData,
                 })
             }
@@ -252,11 +288,15 @@ impl<N: EthereumNetwork> Transaction for EthereumTransaction<N> {
         fn encode_transaction(
             transaction_rlp: &mut RlpStream,
             parameters: &EthereumTransactionParameters,
+            contract_creation: bool,
         ) -> Result<(), TransactionError> {
             transaction_rlp.append(&parameters.nonce);
             transaction_rlp.append(&parameters.gas_price.0);
             transaction_rlp.append(&parameters.gas);
-            transaction_rlp.append(&hex::decode(&parameters.receiver.to_string()[2..])?);
+            match contract_creation {
+                true => transaction_rlp.append_empty_data(),
+                false => transaction_rlp.append(&hex::decode(&parameters.receiver.to_string()[2..])?),
+            };
             transaction_rlp.append(&parameters.amount.0);
             transaction_rlp.append(&parameters.data);
             Ok(())
@@ -265,10 +305,11 @@ impl<N: EthereumNetwork> Transaction for EthereumTransaction<N> {
         // Returns the raw transaction (in RLP).
         fn raw_transaction<N: EthereumNetwork>(
             parameters: &EthereumTransactionParameters,
+            contract_creation: bool,
         ) -> Result<RlpStream, TransactionError> {
             let mut transaction_rlp = RlpStream::new();
             transaction_rlp.begin_list(9);
-            encode_transaction(&mut transaction_rlp, parameters)?;
+            encode_transaction(&mut transaction_rlp, parameters, contract_creation)?;
             transaction_rlp.append(&to_bytes(N::CHAIN_ID)?);
             transaction_rlp.append(&0u8);
             transaction_rlp.append(&0u8);
@@ -278,11 +319,12 @@ impl<N: EthereumNetwork> Transaction for EthereumTransaction<N> {
         // Returns the signed transaction (in RLP).
         fn signed_transaction(
             parameters: &EthereumTransactionParameters,
+            contract_creation: bool,
             signature: &EthereumTransactionSignature,
         ) -> Result<RlpStream, TransactionError> {
             let mut transaction_rlp = RlpStream::new();
             transaction_rlp.begin_list(9);
-            encode_transaction(&mut transaction_rlp, parameters)?;
+            encode_transaction(&mut transaction_rlp, parameters, contract_creation)?;
             transaction_rlp.append(&signature.v);
             transaction_rlp.append(&signature.r);
             transaction_rlp.append(&signature.s);
@@ -290,8 +332,8 @@ impl<N: EthereumNetwork> Transaction for EthereumTransaction<N> {
         }
 
         match &self.signature {
-            Some(signature) => Ok(signed_transaction(&self.parameters, signature)?.out().to_vec()),
-            None => Ok(raw_transaction::<N>(&self.parameters)?.out().to_vec()),
+            Some(signature) => Ok(signed_transaction(&self.parameters, self.contract_creation, signature)?.out().to_vec()),
+            None => Ok(raw_transaction::<N>(&self.parameters, self.contract_creation)?.out().to_vec()),
         }
     }
 
@@ -302,6 +344,195 @@ impl<N: EthereumNetwork> Transaction for EthereumTransaction<N> {
             txid: keccak256(&self.to_bytes()?).iter().cloned().collect(),
         })
     }
+
+    /// Returns the estimated size and intrinsic gas of the transaction once signed.
+    /// Gas consumed by contract execution is not included.
+    fn estimate(&self) -> Result<TransactionEstimate, TransactionError> {
+        let mut transaction = self.clone();
+        if transaction.signature.is_none() {
+            transaction.signature = Some(EthereumTransactionSignature {
+                v: to_bytes(N::CHAIN_ID * 2 + 36)?,
+                r: vec![0xff; 32],
+                s: vec![0xff; 32],
+            });
+        }
+
+        Ok(TransactionEstimate {
+            size: transaction.to_bytes()?.len() as u64,
+            // Legacy (EIP-155) transactions carry no access list
+            units: intrinsic_gas(&self.parameters.data, self.contract_creation, 0, 0),
+        })
+    }
+}
+
+impl<N: EthereumNetwork> EthereumTransaction<N> {
+    /// Returns an unsigned contract creation given the transaction parameters, with the data
+    /// as the init code. The receiver is not encoded and is the zero address once decoded.
+    pub fn new_contract_creation(parameters: &EthereumTransactionParameters) -> Result<Self, TransactionError> {
+        let mut transaction = Self::new(parameters)?;
+        transaction.contract_creation = true;
+        Ok(transaction)
+    }
+
+    /// Returns true if the transaction creates a contract.
+    pub fn is_contract_creation(&self) -> bool {
+        self.contract_creation
+    }
+}
+
+/// The receiver a decoded contract creation holds in its parameters
+const CONTRACT_CREATION_RECEIVER: &str = "0x0000000000000000000000000000000000000000";
+
+/// The minimum gas limit of a transaction (the intrinsic gas of a transfer)
+const MIN_GAS_LIMIT: u64 = 21_000;
+
+/// The maximum gas limit of a transaction (the block gas limit)
+const MAX_GAS_LIMIT: u64 = 30_000_000;
+
+/// Represents a builder for an Ethereum transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EthereumTransactionBuilder<N: EthereumNetwork> {
+    receiver: Option<String>,
+    amount: EthereumAmount,
+    gas: Option<U256>,
+    gas_price: Option<EthereumAmount>,
+    nonce: Option<U256>,
+    data: Vec<u8>,
+    contract_creation: bool,
+    chain_id: Option<u32>,
+    max_fee: Option<EthereumAmount>,
+    _network: PhantomData<N>,
+}
+
+impl<N: EthereumNetwork> Default for EthereumTransactionBuilder<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<N: EthereumNetwork> EthereumTransactionBuilder<N> {
+    /// Returns a builder for a transaction without value or data.
+    pub fn new() -> Self {
+        Self {
+            receiver: None,
+            amount: EthereumAmount::from_u256(U256::zero()),
+            gas: None,
+            gas_price: None,
+            nonce: None,
+            data: vec![],
+            contract_creation: false,
+            chain_id: None,
+            max_fee: None,
+            _network: PhantomData,
+        }
+    }
+
+    /// Sets the address of the receiver.
+    pub fn receiver(mut self, receiver: &str) -> Self {
+        self.receiver = Some(receiver.to_string());
+        self
+    }
+
+    /// Sets the amount (in wei).
+    pub fn amount(mut self, amount: EthereumAmount) -> Self {
+        self.amount = amount;
+        self
+    }
+
+    /// Sets the gas limit.
+    pub fn gas(mut self, gas: U256) -> Self {
+        self.gas = Some(gas);
+        self
+    }
+
+    /// Sets the gas price (in wei).
+    pub fn gas_price(mut self, gas_price: EthereumAmount) -> Self {
+        self.gas_price = Some(gas_price);
+        self
+    }
+
+    /// Sets the nonce of the sender account.
+    pub fn nonce(mut self, nonce: U256) -> Self {
+        self.nonce = Some(nonce);
+        self
+    }
+
+    /// Sets the transaction data.
+    pub fn data(mut self, data: Vec<u8>) -> Self {
+        self.data = data;
+        self
+    }
+
+    /// Sets the init code of a contract creation, which takes no receiver.
+    pub fn contract_creation(mut self, init_code: Vec<u8>) -> Self {
+        self.data = init_code;
+        self.contract_creation = true;
+        self
+    }
+
+    /// Sets the chain id the transaction is intended for, checked against the network.
+    pub fn chain_id(mut self, chain_id: u32) -> Self {
+        self.chain_id = Some(chain_id);
+        self
+    }
+
+    /// Sets the maximum fee (gas limit times gas price) the transaction may pay.
+    pub fn max_fee(mut self, max_fee: EthereumAmount) -> Self {
+        self.max_fee = Some(max_fee);
+        self
+    }
+
+    /// Returns the unsigned transaction, validating the receiver, gas, fee and network.
+    pub fn build(&self) -> Result<EthereumTransaction<N>, TransactionError> {
+        if let Some(chain_id) = self.chain_id {
+            if chain_id != N::CHAIN_ID {
+                return Err(TransactionError::InvalidNetwork(N::CHAIN_ID.to_string(), chain_id.to_string()));
+            }
+        }
+
+        let receiver = match (&self.receiver, self.contract_creation) {
+            (Some(receiver), false) => EthereumAddress::from_str(receiver)?,
+            (None, false) => return Err(TransactionError::MissingField("receiver")),
+            (None, true) => EthereumAddress::from_str(CONTRACT_CREATION_RECEIVER)?,
+            (Some(_), true) => return Err(TransactionError::Message("a contract creation takes no receiver".into())),
+        };
+        let nonce = self.nonce.ok_or(TransactionError::MissingField("nonce"))?;
+        let gas = self.gas.ok_or(TransactionError::MissingField("gas"))?;
+        let gas_price = self.gas_price.ok_or(TransactionError::MissingField("gas_price"))?;
+
+        if gas < U256::from(MIN_GAS_LIMIT) || gas > U256::from(MAX_GAS_LIMIT) {
+            return Err(TransactionError::InvalidGasLimit(format!(
+                "{} is not within {}..={}",
+                gas, MIN_GAS_LIMIT, MAX_GAS_LIMIT
+            )));
+        }
+
+        let fee = match gas.checked_mul(gas_price.0) {
+            Some(fee) => fee,
+            None => return Err(TransactionError::InvalidFee(format!("{} * {} overflows", gas, gas_price.0))),
+        };
+        if fee.checked_add(self.amount.0).is_none() {
+            return Err(AmountError::AmountOutOfBounds(self.amount.0.to_string(), U256::max_value().to_string()).into());
+        }
+        if let Some(max_fee) = self.max_fee {
+            if fee > max_fee.0 {
+                return Err(TransactionError::InvalidFee(format!("{} exceeds the maximum of {}", fee, max_fee.0)));
+            }
+        }
+
+        let parameters = EthereumTransactionParameters {
+            receiver,
+            amount: self.amount,
+            gas,
+            gas_price,
+            nonce,
+            data: self.data.clone(),
+        };
+        match self.contract_creation {
+            true => EthereumTransaction::<N>::new_contract_creation(&parameters),
+            false => EthereumTransaction::<N>::new(&parameters),
+        }
+    }
 }
 
 impl<N: EthereumNetwork> FromStr for EthereumTransaction<N> {
@@ -870,4 +1101,120 @@ mod tests {
         let bytes = encode_transfer("transfer", &address, amount);
         println!("{}",hex::encode(bytes));
     }
+
+    mod builder {
+        use super::*;
+
+        type N = Mainnet;
+
+        fn builder() -> EthereumTransactionBuilder<N> {
+            EthereumTransactionBuilder::<N>::new()
+                .receiver("0xB5D590A6aBf5E349C1b6C511Bc87CEAbFB3D7e65")
+                .amount(EthereumAmount::from_wei("1000000000000000000").unwrap())
+                .gas(U256::from(21000))
+                .gas_price(EthereumAmount::from_wei("1000000000").unwrap())
+                .nonce(U256::zero())
+        }
+
+        #[test]
+        fn build() {
+            let parameters = EthereumTransactionParameters {
+                receiver: EthereumAddress::from_str("0xB5D590A6aBf5E349C1b6C511Bc87CEAbFB3D7e65").unwrap(),
+                amount: EthereumAmount::from_wei("1000000000000000000").unwrap(),
+                gas: U256::from(21000),
+                gas_price: EthereumAmount::from_wei("1000000000").unwrap(),
+                nonce: U256::zero(),
+                data: vec![],
+            };
+            let expected = EthereumTransaction::<N>::new(&parameters).unwrap();
+
+            let transaction = builder()
+                .chain_id(Mainnet::CHAIN_ID)
+                .max_fee(EthereumAmount::from_wei("21000000000000").unwrap())
+                .build()
+                .unwrap();
+            assert_eq!(expected, transaction);
+        }
+
+        #[test]
+        fn sign_with_context_key() {
+            use chainlib_core::{Context, FixedClock};
+            use rand::rngs::mock::StepRng;
+
+            let sign = || {
+                let mut context = Context::new(FixedClock(0), StepRng::new(1, 1));
+                let private_key: EthereumPrivateKey = context.generate_private_key().unwrap();
+                let mut transaction = builder().build().unwrap();
+                transaction.sign_with_private_key(&private_key).unwrap()
+            };
+            assert_eq!(sign(), sign());
+        }
+
+        #[test]
+        fn invalid_build() {
+            assert!(matches!(
+                EthereumTransactionBuilder::<N>::new().build(),
+                Err(TransactionError::MissingField("receiver"))
+            ));
+            assert!(matches!(
+                builder().receiver("0xB5D590A6aBf5E349C1b6C511Bc87CEAbFB3D7e").build(),
+                Err(TransactionError::AddressError(_))
+            ));
+            assert!(matches!(
+                builder().gas(U256::from(20999)).build(),
+                Err(TransactionError::InvalidGasLimit(_))
+            ));
+            assert!(matches!(
+                builder().max_fee(EthereumAmount::from_wei("20999999999999").unwrap()).build(),
+                Err(TransactionError::InvalidFee(_))
+            ));
+            assert!(matches!(
+                builder().chain_id(Ropsten::CHAIN_ID).build(),
+                Err(TransactionError::InvalidNetwork(_, _))
+            ));
+            assert!(matches!(
+                builder().contract_creation(vec![0x00]).build(),
+                Err(TransactionError::Message(_))
+            ));
+        }
+
+        #[test]
+        fn estimate() {
+            let mut transaction = builder().build().unwrap();
+            let estimate = transaction.estimate().unwrap();
+            assert_eq!(21_000, estimate.units);
+            assert_eq!(21_000_000_000_000, transaction.estimate_fee(1_000_000_000).unwrap());
+
+            let private_key =
+                EthereumPrivateKey::from_str("f89f23eaeac18252fedf81bb8318d3c111d48c19b0680dcf6e0a8d5136caf287").unwrap();
+            let signed_transaction = transaction.sign_with_private_key(&private_key).unwrap();
+            assert_eq!(signed_transaction.len() as u64, estimate.size);
+            assert_eq!(estimate, transaction.estimate().unwrap());
+
+            let transaction = builder().gas(U256::from(100_000)).data(vec![0xa9, 0x05, 0x00, 0x00]).build().unwrap();
+            assert_eq!(21_000 + 2 * 16 + 2 * 4, transaction.estimate().unwrap().units);
+
+            // A contract creation has no receiver and pays for its init code
+            let mut transaction = EthereumTransactionBuilder::<N>::new()
+                .contract_creation(vec![0xa9, 0x05, 0x00, 0x00])
+                .gas(U256::from(100_000))
+                .gas_price(EthereumAmount::from_wei("1000000000").unwrap())
+                .nonce(U256::zero())
+                .build()
+                .unwrap();
+            assert!(transaction.is_contract_creation());
+            assert_eq!(21_000 + 32_000 + 2 * 16 + 2 * 4 + 2, transaction.estimate().unwrap().units);
+            let signed_transaction = transaction.sign_with_private_key(&private_key).unwrap();
+            assert_eq!(transaction, EthereumTransaction::<N>::from_bytes(&signed_transaction).unwrap());
+        }
+
+        #[test]
+        fn intrinsic_gas() {
+            assert_eq!(21_000, super::intrinsic_gas(&[], false, 0, 0));
+            assert_eq!(21_000 + 16 + 4, super::intrinsic_gas(&[1, 0], false, 0, 0));
+            // 33 bytes of init code span 2 words
+            assert_eq!(21_000 + 32_000 + 33 * 16 + 2 * 2, super::intrinsic_gas(&[1; 33], true, 0, 0));
+            assert_eq!(21_000 + 2 * 2_400 + 3 * 1_900, super::intrinsic_gas(&[], false, 2, 3));
+        }
+    }
 }
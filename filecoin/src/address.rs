@@ -13,6 +13,8 @@ use chainlib_core::{
     Address,
     AddressError,
     PrivateKey,
+    WatchOnlyAddress,
+    libsecp256k1,
     utilities::crypto::{
         blake2b_160,
         blake2b_checksum
@@ -64,6 +66,14 @@ impl Address for FilecoinAddress {
     }
 }
 
+impl WatchOnlyAddress for FilecoinAddress {
+    /// Returns the secp256k1 address corresponding to the given public key.
+    fn from_secp256k1_public_key(public_key: &libsecp256k1::PublicKey, _: &Self::Format) -> Result<Self, AddressError> {
+        FilecoinAddress::new_secp256k1(&public_key.serialize())
+            .map_err(|error| AddressError::Message(error.to_string()))
+    }
+}
+
 impl Cbor for FilecoinAddress {}
 
 impl FilecoinAddress {
@@ -548,4 +558,71 @@ mod tests {
         println!("bls private key = {}", privkey);
         println!("bls address = {}", addr);
     }
+
+    #[test]
+    fn address_from_extended_public_key() {
+        use chainlib_core::{DerivationPath, ExtendedPublicKey};
+
+        // The account key m/44'/461'/0' of the BIP39 mnemonic "abandon abandon ... about"
+        let xpub = ExtendedPublicKey::from_str("xpub6D2RAta9bdnVzbRSCqZHMeiEiVd19V8g3sP92jHFLrfNDjGgJ5Rfej7j4Bc3sYNUbgfgY1kjTJkKhycEitDjUAjXyvUqx5TBFyQTzsQHhZE").unwrap();
+        let path = DerivationPath::from_str("m/0/0").unwrap();
+        let addr = FilecoinAddress::from_extended_public_key(&xpub, &path, &FilecoinFormat::Base32).unwrap();
+        assert_eq!("f1qode47ievxlxzk6z2viuovedabmn3tq6t57uqhq", addr.to_string());
+
+        let path = DerivationPath::from_str("m/1").unwrap();
+        let addrs = FilecoinAddress::derive_addresses(&xpub, &path, 0..3, &FilecoinFormat::Base32).unwrap();
+        assert_eq!(
+            vec![
+                "f1ad3yechgj4ihgdmowbmqdrov422ivd2somiqh3a",
+                "f1aanlbpncxqldrjyfbnc7moftxs3kwkp6xbwhwvy",
+                "f1qw26vmup3sfembey33sln26nnpr3ibl4ptqik2i",
+            ],
+            addrs.iter().map(|addr| addr.to_string()).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn private_key_from_context() {
+        use chainlib_core::{Context, FixedClock};
+        use rand::rngs::mock::StepRng;
+
+        let mut first = Context::new(FixedClock(0), StepRng::new(1, 1));
+        let mut second = Context::new(FixedClock(0), StepRng::new(1, 1));
+
+        let secp: FilecoinPrivateKey = first.generate_private_key().unwrap();
+        assert_eq!(secp, FilecoinPrivateKey::new_secp256k1_with_rng(second.rng()).unwrap());
+        assert_eq!(
+            secp.to_address(&FilecoinFormat::Base32).unwrap(),
+            FilecoinPrivateKey::new_secp256k1_with_rng(&mut StepRng::new(1, 1)).unwrap().to_address(&FilecoinFormat::Base32).unwrap()
+        );
+
+        let bls = FilecoinPrivateKey::new_bls_with_context(&mut first).unwrap();
+        assert_eq!(bls, FilecoinPrivateKey::new_bls_with_context(&mut second).unwrap());
+    }
+
+    #[test]
+    fn transaction_from_context() {
+        use crate::amount::FilecoinAmount;
+        use crate::transaction::FilecoinTransactionBuilder;
+        use chainlib_core::{Context, FixedClock, Transaction};
+        use rand::rngs::mock::StepRng;
+
+        let sign = || {
+            let mut context = Context::new(FixedClock(0), StepRng::new(1, 1));
+            let private_key = FilecoinPrivateKey::new_secp256k1_with_context(&mut context).unwrap();
+            let from = private_key.to_address(&FilecoinFormat::Base32).unwrap().to_string();
+            let mut transaction = FilecoinTransactionBuilder::new()
+                .from(&from)
+                .to("f1qode47ievxlxzk6z2viuovedabmn3tq6t57uqhq")
+                .sequence(0)
+                .value(FilecoinAmount::from(1_000_000))
+                .gas_limit(1_000_000)
+                .gas_fee_cap(FilecoinAmount::from(100_000))
+                .gas_premium(FilecoinAmount::from(100_000))
+                .build()
+                .unwrap();
+            transaction.sign_with_private_key(&private_key).unwrap()
+        };
+        assert_eq!(sign(), sign());
+    }
 }
@@ -2,7 +2,12 @@ use crate::address::FilecoinAddress;
 use crate::format::FilecoinFormat;
 use crate::public_key::FilecoinPublicKey;
 use chainlib_core::{
+    Clock,
+    Context,
+    KeyMaterial,
+    KeyType,
     PrivateKey,
+    PrivateKeyCodec,
     PrivateKeyError,
     PublicKey,
     Address,
@@ -46,21 +51,67 @@ impl PrivateKey for FilecoinPrivateKey {
     }
 }
 
+impl PrivateKeyCodec for FilecoinPrivateKey {
+    /// Returns a filecoin private key given key material, e.g. a Lotus KeyInfo.
+    fn from_key_material(key: &KeyMaterial) -> Result<Self, PrivateKeyError> {
+        match key.key_type() {
+            KeyType::Secp256k1 => Ok(Self::Secp256k1(key.to_secp256k1_secret_key()?)),
+            KeyType::Bls => {
+                let private_key = bls_signatures::PrivateKey::from_bytes(&key.to_bytes())
+                    .map_err(|error| PrivateKeyError::Crate("bls_signatures", format!("{:?}", error)))?;
+                Ok(Self::Bls(private_key))
+            }
+        }
+    }
+
+    /// Returns the key material of the filecoin private key.
+    fn to_key_material(&self) -> KeyMaterial {
+        match self {
+            Self::Secp256k1(key) => KeyMaterial::from_secp256k1_secret_key(key),
+            Self::Bls(key) => {
+                let mut bytes = [0u8; 32];
+                bytes.copy_from_slice(&key.as_bytes());
+                KeyMaterial::from_bls_bytes(bytes)
+            }
+        }
+    }
+}
+
 impl FilecoinPrivateKey {
 
     /// Returns a randomly generated filecoin private key for secp256k1 curve
     pub fn new_secp256k1() -> Result<Self, PrivateKeyError> {
-        let mut rng = rand::thread_rng();
-        Self::new(&mut rng)
+        Self::new_secp256k1_with_rng(&mut rand::thread_rng())
+    }
+
+    /// Returns a filecoin private key for secp256k1 curve generated from the given RNG,
+    /// e.g. `Context::rng` for reproducible keys
+    pub fn new_secp256k1_with_rng<R: Rng>(rng: &mut R) -> Result<Self, PrivateKeyError> {
+        Self::new(rng)
+    }
+
+    /// Returns a filecoin private key for secp256k1 curve generated from the RNG of the context
+    pub fn new_secp256k1_with_context<C: Clock, R: Rng>(context: &mut Context<C, R>) -> Result<Self, PrivateKeyError> {
+        Self::new_secp256k1_with_rng(context.rng())
     }
 
     /// Returns a randomly generated filecoin private key for bls curve
     pub fn new_bls() -> Result<Self, PrivateKeyError> {
-        let mut rng = rand::thread_rng();
+        Self::new_bls_with_rng(&mut rand::thread_rng())
+    }
+
+    /// Returns a filecoin private key for bls curve generated from the given RNG,
+    /// e.g. `Context::rng` for reproducible keys
+    pub fn new_bls_with_rng<R: Rng>(rng: &mut R) -> Result<Self, PrivateKeyError> {
         let random: [u8; 32] = rng.gen();
         Ok(Self::Bls(bls_signatures::PrivateKey::new(&random)))
     }
 
+    /// Returns a filecoin private key for bls curve generated from the RNG of the context
+    pub fn new_bls_with_context<C: Clock, R: Rng>(context: &mut Context<C, R>) -> Result<Self, PrivateKeyError> {
+        Self::new_bls_with_rng(context.rng())
+    }
+
     /// Returns a filecoin private key given an secp256k1 secret key.
     pub fn from_secp256k1_secret_key(secret_key: &libsecp256k1::SecretKey) -> Self {
         Self::Secp256k1(secret_key.clone())
@@ -153,4 +204,23 @@ fn test() {
     let key = FilecoinPublicKey::from_str(s).unwrap();
 
     println!("pub key = {}", key);
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_lotus_key_info() {
+    use chainlib_core::PrivateKeyEncoding;
+
+    // {"Type":"secp256k1","PrivateKey":"+J8j6urBglL+34G7gxjTwRHUjBmwaA3PbgqNUTbK8oc="}
+    let key_info = "7b2254797065223a22736563703235366b31222c22507269766174654b6579223a222b4a386a36757242676c4c2b3334473767786a54775248556a426d77614133506267714e5554624b386f633d227d";
+    let private_key = FilecoinPrivateKey::import(key_info.as_bytes()).unwrap();
+    assert_eq!(
+        "secp256k1_priv_f89f23eaeac18252fedf81bb8318d3c111d48c19b0680dcf6e0a8d5136caf287",
+        private_key.to_string()
+    );
+    assert_eq!(key_info.as_bytes(), &private_key.export(&PrivateKeyEncoding::LotusKeyInfo).unwrap()[..]);
+
+    let bls_priv_key = FilecoinPrivateKey::new_bls().unwrap();
+    let key_info = bls_priv_key.export(&PrivateKeyEncoding::LotusKeyInfo).unwrap();
+    assert_eq!(bls_priv_key, FilecoinPrivateKey::import(&key_info).unwrap());
+    assert!(bls_priv_key.export(&PrivateKeyEncoding::Pkcs8Pem).is_err());
+}
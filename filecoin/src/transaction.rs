@@ -1,10 +1,11 @@
-use crate::address::{FilecoinAddress, Protocol};
+use crate::address::{FilecoinAddress, Network, Protocol};
 use crate::amount::FilecoinAmount;
 use crate::format::FilecoinFormat;
 use crate::private_key::FilecoinPrivateKey;
 use crate::public_key::FilecoinPublicKey;
 use chainlib_core::{
     Transaction,
+    TransactionEstimate,
     TransactionId,
     libsecp256k1,
     bls_signatures,
@@ -26,6 +27,7 @@ use forest_encoding::tuple::*;
 use fvm_ipld_encoding::repr::*;
 
 use core::panic;
+use core::str::FromStr;
 use std::fmt;
 use std::borrow::Cow;
 
@@ -66,6 +68,185 @@ impl FilecoinTransactionParameters {
     }
 }
 
+/// The block gas limit, which bounds the gas limit of a single message
+const BLOCK_GAS_LIMIT: i64 = 10_000_000_000;
+
+/// The total supply of filecoin in attoFIL (2 billion FIL)
+const TOTAL_FILECOIN: u128 = 2_000_000_000 * 1_000_000_000_000_000_000;
+
+/// The compute gas of including a message on chain
+const ON_CHAIN_MESSAGE_COMPUTE_GAS: u64 = 38_863;
+
+/// The storage gas of a message on chain, before the storage multiplier
+const ON_CHAIN_MESSAGE_STORAGE_BASE: u64 = 36;
+
+/// The storage gas per byte of a message on chain, before the storage multiplier
+const ON_CHAIN_MESSAGE_STORAGE_PER_BYTE: u64 = 1;
+
+/// The multiplier of storage gas
+const STORAGE_GAS_MULTIPLIER: u64 = 1_300;
+
+/// The length of a secp256k1 signature with its recovery id
+const SECP256K1_SIGNATURE_LEN: usize = 65;
+
+/// Represents a builder for a filecoin transaction
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FilecoinTransactionBuilder {
+    from: Option<String>,
+    to: Option<String>,
+    sequence: Option<u64>,
+    value: Option<FilecoinAmount>,
+    method_num: MethodNum,
+    params: RawBytes,
+    gas_limit: Option<i64>,
+    gas_fee_cap: Option<FilecoinAmount>,
+    gas_premium: Option<FilecoinAmount>,
+    network: Option<Network>,
+    max_fee: Option<FilecoinAmount>,
+}
+
+impl FilecoinTransactionBuilder {
+    /// Returns a builder for a plain value transfer (method 0).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the sender address.
+    pub fn from(mut self, from: &str) -> Self {
+        self.from = Some(from.to_string());
+        self
+    }
+
+    /// Sets the receiver address.
+    pub fn to(mut self, to: &str) -> Self {
+        self.to = Some(to.to_string());
+        self
+    }
+
+    /// Sets the nonce of the sender.
+    pub fn sequence(mut self, sequence: u64) -> Self {
+        self.sequence = Some(sequence);
+        self
+    }
+
+    /// Sets the value in attoFIL.
+    pub fn value(mut self, value: FilecoinAmount) -> Self {
+        self.value = Some(value);
+        self
+    }
+
+    /// Sets the actor method to invoke and its encoded parameters.
+    pub fn method(mut self, method_num: MethodNum, params: RawBytes) -> Self {
+        self.method_num = method_num;
+        self.params = params;
+        self
+    }
+
+    /// Sets the gas limit.
+    pub fn gas_limit(mut self, gas_limit: i64) -> Self {
+        self.gas_limit = Some(gas_limit);
+        self
+    }
+
+    /// Sets the gas fee cap in attoFIL per unit of gas.
+    pub fn gas_fee_cap(mut self, gas_fee_cap: FilecoinAmount) -> Self {
+        self.gas_fee_cap = Some(gas_fee_cap);
+        self
+    }
+
+    /// Sets the gas premium in attoFIL per unit of gas.
+    pub fn gas_premium(mut self, gas_premium: FilecoinAmount) -> Self {
+        self.gas_premium = Some(gas_premium);
+        self
+    }
+
+    /// Sets the network both addresses must belong to.
+    pub fn network(mut self, network: Network) -> Self {
+        self.network = Some(network);
+        self
+    }
+
+    /// Sets the maximum fee (gas fee cap * gas limit) in attoFIL the transaction may pay.
+    pub fn max_fee(mut self, max_fee: FilecoinAmount) -> Self {
+        self.max_fee = Some(max_fee);
+        self
+    }
+
+    /// Returns the unsigned transaction, validating the addresses, value and gas parameters.
+    pub fn build(&self) -> Result<FilecoinTransaction, TransactionError> {
+        let from = Self::parse_address(self.from.as_deref(), "from")?;
+        let to = Self::parse_address(self.to.as_deref(), "to")?;
+        let sequence = self.sequence.ok_or(TransactionError::MissingField("sequence"))?;
+        let value = self.value.clone().ok_or(TransactionError::MissingField("value"))?;
+        let gas_limit = self.gas_limit.ok_or(TransactionError::MissingField("gas_limit"))?;
+        let gas_fee_cap = self.gas_fee_cap.clone().ok_or(TransactionError::MissingField("gas_fee_cap"))?;
+        let gas_premium = self.gas_premium.clone().ok_or(TransactionError::MissingField("gas_premium"))?;
+
+        let network = self.network.unwrap_or_else(|| from.network());
+        for address in [&from, &to] {
+            if address.network() != network {
+                return Err(TransactionError::InvalidNetwork(
+                    format!("{:?}", network),
+                    format!("{:?}", address.network()),
+                ));
+            }
+        }
+        match from.protocol() {
+            Protocol::Secp256k1 | Protocol::BLS => {}
+            protocol => {
+                return Err(TransactionError::Message(format!(
+                    "sender {} cannot sign with protocol {}",
+                    from, protocol
+                )))
+            }
+        }
+
+        if value < FilecoinAmount::from(0) || value > FilecoinAmount::from(TOTAL_FILECOIN) {
+            return Err(TransactionError::Message(format!("invalid value: {} attoFIL", value)));
+        }
+        if gas_limit <= 0 || gas_limit > BLOCK_GAS_LIMIT {
+            return Err(TransactionError::InvalidGasLimit(format!(
+                "{} is not within 1..={}",
+                gas_limit, BLOCK_GAS_LIMIT
+            )));
+        }
+        if gas_premium < FilecoinAmount::from(0) || gas_premium > gas_fee_cap {
+            return Err(TransactionError::InvalidFee(format!(
+                "gas premium {} is not within 0..={}",
+                gas_premium, gas_fee_cap
+            )));
+        }
+        if let Some(max_fee) = &self.max_fee {
+            let fee = &gas_fee_cap * FilecoinAmount::from(gas_limit);
+            if &fee > max_fee {
+                return Err(TransactionError::InvalidFee(format!(
+                    "fee {} exceeds the maximum fee {}",
+                    fee, max_fee
+                )));
+            }
+        }
+
+        FilecoinTransaction::new(&FilecoinTransactionParameters {
+            version: 0,
+            from,
+            to,
+            sequence,
+            value,
+            method_num: self.method_num,
+            params: self.params.clone(),
+            gas_limit,
+            gas_fee_cap,
+            gas_premium,
+        })
+    }
+
+    fn parse_address(address: Option<&str>, field: &'static str) -> Result<FilecoinAddress, TransactionError> {
+        let address = address.ok_or(TransactionError::MissingField(field))?;
+        FilecoinAddress::from_str(address)
+            .map_err(|error| TransactionError::Message(format!("invalid {} address {}: {}", field, address, error)))
+    }
+}
+
 impl Serialize for FilecoinTransactionParameters {
     fn serialize<S>(&self, s: S) -> std::result::Result<S::Ok, S::Error>
     where
@@ -276,6 +457,34 @@ impl Transaction for FilecoinTransaction {
             hash: blake2b_256(&stream).to_vec(),
         })
     }
+
+    /// Returns the estimated chain length of this filecoin transaction and the gas charged
+    /// for including it on chain. Gas consumed by the method execution is not included.
+    fn estimate(&self) -> Result<TransactionEstimate, TransactionError> {
+        let message = match self.params.from.protocol() {
+            // BLS signatures are aggregated per block, so the message is included unsigned
+            Protocol::BLS => self.params.marshal_cbor(),
+            _ => {
+                let mut transaction = self.clone();
+                if transaction.signature.bytes.is_empty() {
+                    transaction.signature = FilecoinSignature {
+                        sig_type: FilecoinSignatureType::Secp256k1,
+                        bytes: vec![0; SECP256K1_SIGNATURE_LEN],
+                    };
+                }
+                transaction.marshal_cbor()
+            }
+        };
+        let size = message
+            .map_err(|error| TransactionError::Crate("fvm_ipld_encoding", format!("{:?}", error)))?
+            .len() as u64;
+
+        Ok(TransactionEstimate {
+            size,
+            units: ON_CHAIN_MESSAGE_COMPUTE_GAS
+                + (ON_CHAIN_MESSAGE_STORAGE_BASE + size * ON_CHAIN_MESSAGE_STORAGE_PER_BYTE) * STORAGE_GAS_MULTIPLIER,
+        })
+    }
 }
 
 pub mod json {
@@ -657,3 +866,81 @@ pub mod amount_json {
         FilecoinAmount::from_str(&s).map_err(serde::de::Error::custom)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod builder {
+        use super::*;
+
+        fn builder() -> FilecoinTransactionBuilder {
+            FilecoinTransactionBuilder::new()
+                .from("f1qode47ievxlxzk6z2viuovedabmn3tq6t57uqhq")
+                .to("f1ad3yechgj4ihgdmowbmqdrov422ivd2somiqh3a")
+                .sequence(0)
+                .value(FilecoinAmount::from(1_000_000))
+                .gas_limit(1_000_000)
+                .gas_fee_cap(FilecoinAmount::from(100_000))
+                .gas_premium(FilecoinAmount::from(100_000))
+        }
+
+        #[test]
+        fn build() {
+            let transaction = builder()
+                .network(Network::Mainnet)
+                .max_fee(FilecoinAmount::from(100_000_000_000i64))
+                .build()
+                .unwrap();
+            assert_eq!(builder().build().unwrap(), transaction);
+        }
+
+        #[test]
+        fn invalid_network() {
+            assert!(matches!(
+                builder().network(Network::Testnet).build(),
+                Err(TransactionError::InvalidNetwork(_, _))
+            ));
+            // Testnet receiver of a mainnet sender
+            assert!(matches!(
+                builder().to("t1ad3yechgj4ihgdmowbmqdrov422ivd2somiqh3a").build(),
+                Err(TransactionError::InvalidNetwork(_, _))
+            ));
+        }
+
+        #[test]
+        fn invalid_gas_limit() {
+            assert!(builder().gas_limit(BLOCK_GAS_LIMIT).build().is_ok());
+            assert!(matches!(
+                builder().gas_limit(0).build(),
+                Err(TransactionError::InvalidGasLimit(_))
+            ));
+            assert!(matches!(
+                builder().gas_limit(BLOCK_GAS_LIMIT + 1).build(),
+                Err(TransactionError::InvalidGasLimit(_))
+            ));
+        }
+
+        #[test]
+        fn invalid_gas_premium() {
+            assert!(matches!(
+                builder().gas_premium(FilecoinAmount::from(100_001)).build(),
+                Err(TransactionError::InvalidFee(_))
+            ));
+            assert!(matches!(
+                builder().gas_premium(FilecoinAmount::from(-1)).build(),
+                Err(TransactionError::InvalidFee(_))
+            ));
+        }
+
+        #[test]
+        fn invalid_max_fee() {
+            // The fee is the gas fee cap times the gas limit
+            assert!(builder().max_fee(FilecoinAmount::from(100_000_000_000i64)).build().is_ok());
+            assert!(matches!(
+                builder().max_fee(FilecoinAmount::from(99_999_999_999i64)).build(),
+                Err(TransactionError::InvalidFee(_))
+            ));
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use chainlib_core::{Address,AddressError, PrivateKey};
+use chainlib_core::{libsecp256k1, Address, AddressError, PrivateKey, WatchOnlyAddress};
 use crate::{TronFormat,TronPrivateKey,TronPublicKey};
 use serde::Serialize;
 use sha3::Keccak256;
@@ -37,6 +37,12 @@ impl Address for TronAddress{
     }
 }
 
+impl WatchOnlyAddress for TronAddress {
+    fn from_secp256k1_public_key(public_key: &libsecp256k1::PublicKey, format: &Self::Format) -> Result<Self, AddressError> {
+        Self::from_public_key(&TronPublicKey::from_secp256k1_public_key(*public_key), format)
+    }
+}
+
 impl TronAddress{
     pub fn to_hex(&self) -> String {
         hex::encode_upper(self.0)
@@ -247,4 +253,22 @@ mod tests{
         assert_eq!(addr.to_hex(),"412A2B9F7641D0750C1E822D0E49EF765C8106524B");
     }
 
+    #[test]
+    fn test_address_from_extended_public_key() {
+        use chainlib_core::{DerivationPath, ExtendedPublicKey};
+
+        // The account key m/44'/195'/0' of the BIP39 mnemonic "abandon abandon ... about"
+        let xpub = ExtendedPublicKey::from_str("xpub6D1AabNHCupeiLM65ZR9UStMhJ1vCpyV4XbZdyhMZBiJXALQtmn9p42VTQckoHVn8WNqS7dqnJokZHAHcHGoaQgmv8D45oNUKx6DZMNZBCd").unwrap();
+        let path = DerivationPath::from_str("m/0/0").unwrap();
+        let addr = TronAddress::from_extended_public_key(&xpub, &path, &TronFormat::Standard).unwrap();
+        assert_eq!(addr.to_string(), "TUEZSdKsoDHQMeZwihtdoBiN46zxhGWYdH");
+
+        let path = DerivationPath::from_str("m/1").unwrap();
+        let addrs = TronAddress::derive_addresses(&xpub, &path, 0..3, &TronFormat::Standard).unwrap();
+        assert_eq!(
+            addrs.iter().map(|addr| addr.to_string()).collect::<Vec<_>>(),
+            vec!["THLNguYKN3S8RUzVyi9ErynW4YmXLU5c8n", "TN6bXdkzGdtkSzn5obMqi67ZseqyHLnd3q", "TNWq2Qr7CSouCeZyfNjHsg4ZZXyw5LJxZW"]
+        );
+    }
+
 }
\ No newline at end of file
@@ -2,7 +2,9 @@ use chainlib_core::Address;
 use chainlib_core::AddressError;
 use chainlib_core::PublicKey;
 use chainlib_core::libsecp256k1;
+use chainlib_core::KeyMaterial;
 use chainlib_core::PrivateKey;
+use chainlib_core::PrivateKeyCodec;
 use chainlib_core::PrivateKeyError;
 use core::fmt::{self,Display};
 
@@ -34,6 +36,18 @@ impl PrivateKey for TronPrivateKey {
     }
 }
 
+impl PrivateKeyCodec for TronPrivateKey {
+    /// Returns a Tron private key given key material.
+    fn from_key_material(key: &KeyMaterial) -> Result<Self, PrivateKeyError> {
+        Ok(Self(key.to_secp256k1_secret_key()?))
+    }
+
+    /// Returns the key material of the Tron private key.
+    fn to_key_material(&self) -> KeyMaterial {
+        KeyMaterial::from_secp256k1_secret_key(&self.0)
+    }
+}
+
 impl TronPrivateKey {
     /// Returns a private key given a secp256k1 secret key.
     pub fn from_secp256k1_secret_key(secret_key: &libsecp256k1::SecretKey) -> Self {
@@ -81,4 +95,17 @@ mod tests{
         let addr = TronAddress::from_public_key(&pubkey,&TronFormat::Standard).unwrap();
         assert_eq!(addr.to_base58(),"TG7jQ7eGsns6nmQNfcKNgZKyKBFkx7CvXr".to_string())
     }
+
+    #[test]
+    pub fn test_import_export(){
+        use chainlib_core::PrivateKeyEncoding;
+
+        let privkey = TronPrivateKey::from_str("0838b9c472def15e82fed31208944a683b37dfb09f5a04febc45416bd8a00161").unwrap();
+        let pem = privkey.export(&PrivateKeyEncoding::Sec1Pem).unwrap();
+        let imported = TronPrivateKey::import(&pem).unwrap();
+        assert_eq!(privkey, imported);
+
+        let addr = imported.to_address(&TronFormat::Standard).unwrap();
+        assert_eq!(addr.to_base58(),"TG7jQ7eGsns6nmQNfcKNgZKyKBFkx7CvXr".to_string())
+    }
 }
\ No newline at end of file
@@ -1,6 +1,8 @@
 use std::fmt;
+use chainlib_core::{Clock, Context, SystemClock};
 use chainlib_core::Transaction;
 use chainlib_core::TransactionError;
+use chainlib_core::TransactionEstimate;
 use crate::{TronAddress, TronFormat, TronPrivateKey, TronPublicKey};
 use crate::protocol::Tron::transaction::{
     Raw as TransactionRaw,
@@ -8,6 +10,7 @@ use crate::protocol::Tron::transaction::{
 };
 use crate::protocol::Tron::Transaction as TransactionProto;
 use chainlib_core::utilities::crypto;
+use rand::Rng;
 use chainlib_core::TransactionId;
 use protobuf::{Message};
 use crate::trx;
@@ -27,13 +30,18 @@ pub struct TronTransactionParameters {
 }
 
 impl TronTransactionParameters {
-    pub fn set_ref_block(&mut self, number: i64, hash: &str){
+    pub fn set_ref_block(&mut self, number: i64, hash: &str) -> Result<(), TransactionError> {
+        let hash = hex::decode(hash)?;
+        if hash.len() != 32 {
+            return Err(TransactionError::InvalidRefBlock(format!("invalid block hash length: {}", hash.len())));
+        }
 
         self.ref_block_bytes = vec![
                 ((number & 0xff00) >> 8) as u8,
                 (number & 0xff) as u8,
             ];
-        self.ref_block_hash = hex::decode(hash).unwrap()[8..16].to_owned();
+        self.ref_block_hash = hash[8..16].to_owned();
+        Ok(())
     }
 
     pub fn set_contract(&mut self, ct: Contract){
@@ -53,11 +61,20 @@ impl TronTransactionParameters {
     }
     
     pub fn to_transaction_raw(&self) -> Result<TransactionRaw, TransactionError>{
+        self.to_transaction_raw_with_clock(&SystemClock)
+    }
+
+    /// Returns the raw transaction, reading the timestamp of a new transaction from the clock of the context.
+    pub fn to_transaction_raw_with_context<C: Clock, R: Rng>(&self, context: &Context<C, R>) -> Result<TransactionRaw, TransactionError>{
+        self.to_transaction_raw_with_clock(context.clock())
+    }
+
+    fn to_transaction_raw_with_clock<C: Clock>(&self, clock: &C) -> Result<TransactionRaw, TransactionError>{
         let mut raw = TransactionRaw::new();
         let mut timestamp = self.timestamp;
         // if timestamp equals 0, means the tx is new
         if self.timestamp == 0 {
-            timestamp = trx::timestamp_millis();
+            timestamp = clock.now_millis();
         }
         raw.contract = vec![self.contract.clone()].into();
         if self.memo.len() > 0 {
@@ -92,6 +109,144 @@ impl Default for TronTransactionParameters{
 }
 
 
+/// The maximum fee limit of a transaction in sun (15,000 TRX)
+const MAX_FEE_LIMIT: i64 = 15_000_000_000;
+
+/// The maximum expiration window of a transaction in milliseconds (24 hours)
+const MAX_EXPIRATION: i64 = 24 * 60 * 60 * 1000;
+
+/// The bandwidth charged on top of the signed transaction bytes for the transaction result
+const MAX_RESULT_SIZE_IN_TX: u64 = 64;
+
+/// Represents a builder for a Tron transaction
+#[derive(Debug, Clone, PartialEq)]
+pub struct TronTransactionBuilder {
+    ref_block: Option<(i64, String)>,
+    contract: Option<Contract>,
+    timestamp: Option<i64>,
+    expiration: i64,
+    fee_limit: i64,
+    memo: String,
+}
+
+impl Default for TronTransactionBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TronTransactionBuilder {
+    /// Returns a builder for a transaction expiring 5 minutes after its timestamp.
+    pub fn new() -> Self {
+        let parameters = TronTransactionParameters::default();
+        Self {
+            ref_block: None,
+            contract: None,
+            timestamp: None,
+            expiration: parameters.expiration,
+            fee_limit: parameters.fee_limit,
+            memo: parameters.memo,
+        }
+    }
+
+    /// Sets the reference block given its number and block id (hex).
+    pub fn ref_block(mut self, number: i64, hash: &str) -> Self {
+        self.ref_block = Some((number, hash.to_string()));
+        self
+    }
+
+    /// Sets the contract of the transaction, e.g. from `trx::build_transfer_contract`.
+    pub fn contract(mut self, contract: Contract) -> Self {
+        self.contract = Some(contract);
+        self
+    }
+
+    /// Sets the timestamp in milliseconds. If unset, the time of the context clock passed to `build_with_context` is used.
+    pub fn timestamp(mut self, timestamp: i64) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    /// Sets the expiration window after the timestamp in milliseconds.
+    pub fn expiration(mut self, expiration: i64) -> Self {
+        self.expiration = expiration;
+        self
+    }
+
+    /// Sets the fee limit in sun (for smart contract calls).
+    pub fn fee_limit(mut self, fee_limit: i64) -> Self {
+        self.fee_limit = fee_limit;
+        self
+    }
+
+    /// Sets the memo.
+    pub fn memo(mut self, memo: &str) -> Self {
+        self.memo = memo.to_string();
+        self
+    }
+
+    /// Returns the unsigned transaction timestamped with the system clock unless a timestamp is set.
+    pub fn build(&self) -> Result<TronTransaction, TransactionError> {
+        self.build_with_clock(&SystemClock)
+    }
+
+    /// Returns the unsigned transaction, validating the reference block, contract, fee limit and expiration.
+    /// The transaction is timestamped with the clock of the context unless a timestamp is set.
+    pub fn build_with_context<C: Clock, R: Rng>(&self, context: &Context<C, R>) -> Result<TronTransaction, TransactionError> {
+        self.build_with_clock(context.clock())
+    }
+
+    fn build_with_clock<C: Clock>(&self, clock: &C) -> Result<TronTransaction, TransactionError> {
+        let (number, hash) = match &self.ref_block {
+            Some(ref_block) => ref_block,
+            None => return Err(TransactionError::MissingField("ref_block")),
+        };
+        let contract = match &self.contract {
+            Some(contract) if contract.parameter.is_some() => contract.clone(),
+            _ => return Err(TransactionError::MissingField("contract")),
+        };
+
+        if self.fee_limit < 0 || self.fee_limit > MAX_FEE_LIMIT {
+            return Err(TransactionError::InvalidFee(format!(
+                "fee limit {} is not within 0..={}",
+                self.fee_limit, MAX_FEE_LIMIT
+            )));
+        }
+        if self.expiration <= 0 || self.expiration > MAX_EXPIRATION {
+            return Err(TransactionError::InvalidExpiration(format!(
+                "{} ms is not within 1..={}",
+                self.expiration, MAX_EXPIRATION
+            )));
+        }
+        if let Some(timestamp) = self.timestamp {
+            if timestamp <= 0 {
+                return Err(TransactionError::InvalidExpiration(format!("invalid timestamp: {}", timestamp)));
+            }
+        }
+
+        let mut parameters = TronTransactionParameters::default();
+        parameters.set_ref_block(*number, hash)?;
+        // The block id starts with the big-endian block number
+        let block_id = hex::decode(hash)?;
+        let mut block_number = [0u8; 8];
+        block_number.copy_from_slice(&block_id[..8]);
+        if i64::from_be_bytes(block_number) != *number {
+            return Err(TransactionError::InvalidRefBlock(format!(
+                "block {} does not match block id {}",
+                number, hash
+            )));
+        }
+
+        parameters.set_contract(contract);
+        parameters.set_timestamp(self.timestamp.unwrap_or_else(|| clock.now_millis()));
+        parameters.set_expiration(self.expiration);
+        parameters.set_fee_limit(self.fee_limit);
+        parameters.memo = self.memo.clone();
+
+        TronTransaction::new(&parameters)
+    }
+}
+
 /// Represents an Ethereum transaction signature
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct TronTransactionSignature(Vec<u8>);
@@ -129,7 +284,25 @@ pub struct TronTransaction {
 }
 
 impl TronTransaction{
+    /// Returns a new transaction, timestamped with the context clock if the parameters have no timestamp.
+    /// The timestamp is fixed here so the transaction id and the signed bytes agree.
+    pub fn new_with_context<C: Clock, R: Rng>(
+        parameters: &TronTransactionParameters,
+        context: &Context<C, R>,
+    ) -> Result<Self, TransactionError> {
+        Self::new_with_clock(parameters, context.clock())
+    }
 
+    fn new_with_clock<C: Clock>(parameters: &TronTransactionParameters, clock: &C) -> Result<Self, TransactionError> {
+        let mut data = parameters.clone();
+        if data.timestamp == 0 {
+            data.timestamp = clock.now_millis();
+        }
+        Ok(Self{
+            data,
+            signature: None
+        })
+    }
 }
 
 impl Transaction for TronTransaction {
@@ -146,10 +319,7 @@ impl Transaction for TronTransaction {
     type TransactionParameters = TronTransactionParameters;
 
     fn new(parameters: &Self::TransactionParameters) -> Result<Self, TransactionError> {
-        Ok(Self{
-            data: parameters.clone(),
-            signature: None
-        })
+        Self::new_with_clock(parameters, &SystemClock)
     }
 
     fn sign(&mut self, signature: Vec<u8>, recid: u8) -> Result<Vec<u8>, TransactionError> {
@@ -204,6 +374,19 @@ impl Transaction for TronTransaction {
             txid: crypto::sha256(&bytes).iter().cloned().collect()
         })
     }
+
+    /// Returns the estimated size of the signed protobuf and the bandwidth it consumes.
+    fn estimate(&self) -> Result<TransactionEstimate, TransactionError> {
+        let mut transaction = self.clone();
+        if transaction.signature.is_none() {
+            transaction.signature = Some(TronTransactionSignature::new(&vec![0xff; 64], 1));
+        }
+        let size = transaction.to_bytes()?.len() as u64;
+        Ok(TransactionEstimate {
+            size,
+            units: size + MAX_RESULT_SIZE_IN_TX
+        })
+    }
 }
 
 #[cfg(test)]
@@ -211,7 +394,8 @@ mod tests {
     use std::str::FromStr;
     use crate::TronPrivateKey;
     use super::*;
-    use chainlib_core::libsecp256k1;
+    use chainlib_core::{libsecp256k1, FixedClock};
+    use rand::rngs::mock::StepRng;
 
     pub fn build_trx_transaction() -> TronTransaction{
         let addr_from = "TG7jQ7eGsns6nmQNfcKNgZKyKBFkx7CvXr";
@@ -220,7 +404,7 @@ mod tests {
         let ct =  trx::build_transfer_contract(addr_from, addr_to, amount).unwrap();
         let mut param = TronTransactionParameters::default();
         param.set_timestamp(trx::timestamp_millis());
-        param.set_ref_block(26661399, "000000000196d21784deb05dee04c69ed112b8e078e74019f9a0b1df6adc414e");
+        param.set_ref_block(26661399, "000000000196d21784deb05dee04c69ed112b8e078e74019f9a0b1df6adc414e").unwrap();
         param.set_contract(ct);
         let transaction = TronTransaction::new(&param).unwrap();
        
@@ -235,7 +419,7 @@ mod tests {
         let ct = trx::build_trc20_transfer_contract(owner_addr, contract_addr, to_addr, amount).unwrap();
         let mut param = TronTransactionParameters::default();
         param.set_timestamp(trx::timestamp_millis());
-        param.set_ref_block(26661399, "000000000196d21784deb05dee04c69ed112b8e078e74019f9a0b1df6adc414e");
+        param.set_ref_block(26661399, "000000000196d21784deb05dee04c69ed112b8e078e74019f9a0b1df6adc414e").unwrap();
         param.set_contract(ct);
         let transaction = TronTransaction::new(&param).unwrap();
         return transaction;
@@ -293,7 +477,7 @@ mod tests {
         let ct =  trx::build_transfer_contract(from_addr, to_addr, amount).unwrap();
         let mut param = TronTransactionParameters::default();
         param.set_timestamp(trx::timestamp_millis());
-        param.set_ref_block(block_height, block_hash);
+        param.set_ref_block(block_height, block_hash).unwrap();
         param.set_contract(ct);
         let transaction = TronTransaction::new(&param).unwrap();
 
@@ -317,6 +501,76 @@ mod tests {
 
     }
 
+    #[test]
+    pub fn test_builder(){
+        let addr_from = "TG7jQ7eGsns6nmQNfcKNgZKyKBFkx7CvXr";
+        let addr_to = "TFk5LfscQv8hYM11mZYmi3ZcnRfFc4LLap";
+        let block_hash = "000000000196d21784deb05dee04c69ed112b8e078e74019f9a0b1df6adc414e";
+        let ct =  trx::build_transfer_contract(addr_from, addr_to, 10*1000000).unwrap();
+
+        let timestamp = trx::timestamp_millis();
+        let mut param = TronTransactionParameters::default();
+        param.set_timestamp(timestamp);
+        param.set_ref_block(26661399, block_hash).unwrap();
+        param.set_contract(ct.clone());
+        let expected = TronTransaction::new(&param).unwrap();
+
+        let builder = TronTransactionBuilder::new()
+            .ref_block(26661399, block_hash)
+            .contract(ct)
+            .timestamp(timestamp);
+        assert_eq!(expected, builder.build().unwrap());
+
+        assert!(matches!(TronTransactionBuilder::new().build(), Err(TransactionError::MissingField("ref_block"))));
+        assert!(matches!(builder.clone().ref_block(26661400, block_hash).build(), Err(TransactionError::InvalidRefBlock(_))));
+        assert!(matches!(builder.clone().ref_block(26661399, "0196d217").build(), Err(TransactionError::InvalidRefBlock(_))));
+        assert!(matches!(builder.clone().ref_block(26661399, "not hex").build(), Err(TransactionError::Crate("hex", _))));
+        assert!(matches!(builder.clone().fee_limit(MAX_FEE_LIMIT + 1).build(), Err(TransactionError::InvalidFee(_))));
+        assert!(matches!(builder.clone().expiration(MAX_EXPIRATION + 1).build(), Err(TransactionError::InvalidExpiration(_))));
+
+        let mut param = TronTransactionParameters::default();
+        assert!(param.set_ref_block(26661399, "zz").is_err());
+    }
+
+    #[test]
+    pub fn test_deterministic_with_context(){
+        let addr_from = "TG7jQ7eGsns6nmQNfcKNgZKyKBFkx7CvXr";
+        let addr_to = "TFk5LfscQv8hYM11mZYmi3ZcnRfFc4LLap";
+        let block_hash = "000000000196d21784deb05dee04c69ed112b8e078e74019f9a0b1df6adc414e";
+        let private_key = TronPrivateKey::from_str("0838b9c472def15e82fed31208944a683b37dfb09f5a04febc45416bd8a00161").unwrap();
+        let ct = trx::build_transfer_contract(addr_from, addr_to, 10*1000000).unwrap();
+        let context = Context::new(FixedClock(1_656_000_000_000), StepRng::new(1, 1));
+
+        let builder = TronTransactionBuilder::new().ref_block(26661399, block_hash).contract(ct.clone());
+        let mut first = builder.build_with_context(&context).unwrap();
+        let mut second = builder.build_with_context(&context).unwrap();
+        assert_eq!(1_656_000_000_000, first.data.timestamp);
+        assert_eq!(first.sign_with_private_key(&private_key).unwrap(), second.sign_with_private_key(&private_key).unwrap());
+
+        let mut param = TronTransactionParameters::default();
+        param.set_ref_block(26661399, block_hash).unwrap();
+        param.set_contract(ct);
+        let transaction = TronTransaction::new_with_context(&param, &context).unwrap();
+        assert_eq!(first.to_transaction_id().unwrap(), transaction.to_transaction_id().unwrap());
+
+        // The timestamp of a new transaction is fixed at construction
+        let transaction = TronTransaction::new(&param).unwrap();
+        assert_ne!(0, transaction.data.timestamp);
+        assert_eq!(transaction.data.to_transaction_raw().unwrap(), transaction.data.to_transaction_raw_with_context(&context).unwrap());
+    }
+
+    #[test]
+    pub fn test_estimate(){
+        let mut transaction = build_trx_transaction();
+        let estimate = transaction.estimate().unwrap();
+        let privkey = TronPrivateKey::from_str("0838b9c472def15e82fed31208944a683b37dfb09f5a04febc45416bd8a00161").unwrap();
+        let signed = transaction.sign_with_private_key(&privkey).unwrap();
+        assert_eq!(signed.len() as u64, estimate.size);
+        assert_eq!(signed.len() as u64 + 64, estimate.units);
+        // Burned bandwidth costs 1000 sun per byte
+        assert_eq!(estimate.units * 1000, transaction.estimate_fee(1000).unwrap());
+    }
+
     #[test]
     pub fn test_raw(){
         let raw = "0a025aa722088cb23bfcb18ea03c40facee394ad305a67080112630a2d747970652e676f6f676c65617069732e636f6d2f70726f746f636f6c2e5472616e73666572436f6e747261637412320a1541fa3146ab779ce02392d11209f524ee75d4088a45121541436d74fc1577266b7290b85801145d9c5287e19418c0843d709afadf94ad30900180ade204";